@@ -1,7 +1,7 @@
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
-use syn::{DeriveInput, Error};
+use syn::{Data, DeriveInput, Error, Fields, GenericArgument, LitInt, Path, PathArguments, Type};
 
 #[proc_macro_derive(BuildEvent)]
 pub fn build_event_derive(item: TokenStream) -> TokenStream {
@@ -63,3 +63,207 @@ fn machine_derive2(item: TokenStream2) -> Result<TokenStream2, Error> {
 
     Ok(expanded)
 }
+
+/// Implements `CacheableEvents` and `NamespaceEventsSchema` for an enum of namespace events
+///
+/// Every variant wraps one `Event<T>`, with `T` implementing `JsonSchema`, and names how it
+/// is cached with `#[cache(..)]`: `one`, `n = 10`, `first_and_last` or `duration_secs = 3600`
+/// with an optional `bucket_secs` (default 1). With
+/// `#[namespace_events(namespace = MyNamespace)]` the emit logic of `MyNamespace`, holding the
+/// machine namespace in its `namespace` field, is generated too.
+///
+/// ```ignore
+/// #[derive(NamespaceEvents)]
+/// #[namespace_events(namespace = LaserMachineNamespace)]
+/// pub enum LaserEvents {
+///     #[cache(duration_secs = 3600)]
+///     LiveValues(Event<LiveValuesEvent>),
+///     #[cache(first_and_last)]
+///     State(Event<StateEvent>),
+/// }
+/// ```
+#[proc_macro_derive(NamespaceEvents, attributes(cache, namespace_events))]
+pub fn namespace_events_derive(item: TokenStream) -> TokenStream {
+    namespace_events_derive2(item.into())
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn namespace_events_derive2(item: TokenStream2) -> Result<TokenStream2, Error> {
+    let ast: DeriveInput = syn::parse2(item)?;
+
+    let ident = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let Data::Enum(data) = &ast.data else {
+        return Err(Error::new_spanned(
+            ident,
+            "NamespaceEvents can only be derived for enums",
+        ));
+    };
+
+    let mut value_arms = Vec::new();
+    let mut cache_arms = Vec::new();
+    let mut schema_entries = Vec::new();
+    for variant in &data.variants {
+        let variant_ident = &variant.ident;
+        let field = match &variant.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => &fields.unnamed[0],
+            _ => {
+                return Err(Error::new_spanned(
+                    variant,
+                    "NamespaceEvents variants must wrap exactly one event",
+                ));
+            }
+        };
+        let (event_data, event_name) = event_data_type(&field.ty)?;
+
+        let cache_attr = variant
+            .attrs
+            .iter()
+            .find(|attr| attr.path().is_ident("cache"))
+            .ok_or_else(|| {
+                Error::new_spanned(variant, "missing #[cache(..)] attribute on event variant")
+            })?;
+        let cache_fn = parse_cache_attr(cache_attr)?;
+
+        value_arms.push(quote! { Self::#variant_ident(event) => event.into(), });
+        cache_arms.push(quote! { Self::#variant_ident(_) => #cache_fn, });
+        schema_entries.push(quote! {
+            (
+                stringify!(#event_name).to_string(),
+                control_core::machines::schema::event_schema::<#event_data>(),
+            ),
+        });
+    }
+
+    let mut namespace: Option<Path> = None;
+    for attr in &ast.attrs {
+        if !attr.path().is_ident("namespace_events") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("namespace") {
+                namespace = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `namespace = ..`"))
+            }
+        })?;
+    }
+
+    let namespace_impl = namespace.map(|namespace| {
+        quote! {
+            impl #impl_generics control_core::socketio::namespace::NamespaceCacheingLogic<#ident #ty_generics> for #namespace #where_clause {
+                #[doc = "Implemented by the NamespaceEvents derive macro"]
+                #[tracing::instrument(skip_all)]
+                fn emit(&mut self, events: #ident #ty_generics) {
+                    use control_core::socketio::namespace::CacheableEvents;
+
+                    let event = std::sync::Arc::new(events.event_value());
+                    let buffer_fn = events.event_cache_fn();
+
+                    let mut namespace = self.namespace.lock_blocking();
+                    namespace.emit(event, &buffer_fn);
+                }
+            }
+        }
+    });
+
+    let expanded = quote! {
+        impl #impl_generics control_core::socketio::namespace::CacheableEvents<Self> for #ident #ty_generics #where_clause {
+            #[doc = "Implemented by the NamespaceEvents derive macro"]
+            fn event_value(&self) -> control_core::socketio::event::GenericEvent {
+                match self {
+                    #(#value_arms)*
+                }
+            }
+
+            #[doc = "Implemented by the NamespaceEvents derive macro"]
+            fn event_cache_fn(&self) -> control_core::socketio::namespace::CacheFn {
+                match self {
+                    #(#cache_arms)*
+                }
+            }
+        }
+
+        impl #impl_generics control_core::machines::schema::NamespaceEventsSchema for #ident #ty_generics #where_clause {
+            #[doc = "Implemented by the NamespaceEvents derive macro"]
+            fn event_schemas() -> std::collections::BTreeMap<String, control_core::machines::schema::RootSchema> {
+                std::collections::BTreeMap::from([
+                    #(#schema_entries)*
+                ])
+            }
+        }
+
+        #namespace_impl
+    };
+
+    Ok(expanded)
+}
+
+/// `T` of a variant wrapping `Event<T>`, the event is named after `T` like by `BuildEvent`
+fn event_data_type(ty: &Type) -> Result<(&Type, &syn::Ident), Error> {
+    let error = || Error::new_spanned(ty, "NamespaceEvents variants must wrap an `Event<T>`");
+    let Type::Path(path) = ty else {
+        return Err(error());
+    };
+    let segment = path.path.segments.last().ok_or_else(error)?;
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return Err(error());
+    };
+    match arguments.args.first() {
+        Some(GenericArgument::Type(data @ Type::Path(data_path))) if segment.ident == "Event" => {
+            let name = &data_path.path.segments.last().ok_or_else(error)?.ident;
+            Ok((data, name))
+        }
+        _ => Err(error()),
+    }
+}
+
+/// Cache function expression of a `#[cache(..)]` attribute
+fn parse_cache_attr(attr: &syn::Attribute) -> Result<TokenStream2, Error> {
+    let mut cache_fn = None;
+    let mut duration_secs: Option<LitInt> = None;
+    let mut bucket_secs: Option<LitInt> = None;
+
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("one") {
+            cache_fn = Some(quote! { control_core::socketio::namespace::cache_one_event() });
+        } else if meta.path.is_ident("first_and_last") {
+            cache_fn =
+                Some(quote! { control_core::socketio::namespace::cache_first_and_last_event() });
+        } else if meta.path.is_ident("n") {
+            let n: LitInt = meta.value()?.parse()?;
+            cache_fn = Some(quote! { control_core::socketio::namespace::cache_n_events(#n) });
+        } else if meta.path.is_ident("duration_secs") {
+            duration_secs = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("bucket_secs") {
+            bucket_secs = Some(meta.value()?.parse()?);
+        } else {
+            return Err(
+                meta.error("expected `one`, `n = ..`, `first_and_last` or `duration_secs = ..`")
+            );
+        }
+        Ok(())
+    })?;
+
+    match (cache_fn, duration_secs, bucket_secs) {
+        (Some(cache_fn), None, None) => Ok(cache_fn),
+        (None, Some(duration_secs), bucket_secs) => {
+            let bucket_secs = bucket_secs
+                .map(|bucket_secs| quote! { #bucket_secs })
+                .unwrap_or_else(|| quote! { 1 });
+            Ok(quote! {
+                control_core::socketio::namespace::cache_duration(
+                    std::time::Duration::from_secs(#duration_secs),
+                    std::time::Duration::from_secs(#bucket_secs),
+                )
+            })
+        }
+        _ => Err(Error::new_spanned(
+            attr,
+            "#[cache(..)] takes exactly one of `one`, `n = ..`, `first_and_last` or `duration_secs = ..`",
+        )),
+    }
+}
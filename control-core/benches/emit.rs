@@ -0,0 +1,133 @@
+use control_core::socketio::{
+    delivery::{Recipient, coalesce},
+    event::{Event, GenericEvent},
+    namespace::{Namespace, cache_duration, cache_one_event},
+};
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+use serde::Serialize;
+use std::{hint::black_box, sync::Arc, time::Duration};
+
+const SUBSCRIBERS: [usize; 3] = [1, 8, 64];
+
+#[derive(Serialize, Clone)]
+struct LiveValues {
+    screw_rpm: f64,
+    pressure: f64,
+    temperatures: [f64; 4],
+    strand_diameters: Vec<f64>,
+}
+
+fn live_values(ts: u64) -> Arc<GenericEvent> {
+    let mut event: GenericEvent = Event::new(
+        "LiveValuesEvent",
+        LiveValues {
+            screw_rpm: 42.0,
+            pressure: 120.5,
+            temperatures: [180.0, 190.0, 200.0, 210.0],
+            strand_diameters: vec![1.75; 8],
+        },
+    )
+    .into();
+    event.ts = ts;
+    Arc::new(event)
+}
+
+struct BenchRecipient(usize);
+
+impl Recipient for BenchRecipient {
+    type Id = usize;
+
+    fn id(&self) -> usize {
+        self.0
+    }
+
+    fn namespace(&self) -> &str {
+        "/machine"
+    }
+}
+
+fn namespace_emit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("namespace_emit");
+    let (queue_tx, _queue_rx) = smol::channel::unbounded();
+    let mut namespace = Namespace::new(queue_tx);
+    let cache_fn = cache_one_event();
+    let mut ts = 0;
+    group.bench_function("cache_one", |b| {
+        b.iter(|| {
+            ts += 1;
+            namespace.emit(live_values(ts), &cache_fn);
+        })
+    });
+
+    let (queue_tx, _queue_rx) = smol::channel::unbounded();
+    let mut namespace = Namespace::new(queue_tx);
+    let cache_fn = cache_duration(Duration::from_secs(3600), Duration::from_secs(1));
+    let mut ts = 0;
+    group.bench_function("cache_duration", |b| {
+        b.iter(|| {
+            ts += 100;
+            namespace.emit(live_values(ts), &cache_fn);
+        })
+    });
+    group.finish();
+}
+
+fn queue_coalesce(c: &mut Criterion) {
+    let mut group = c.benchmark_group("queue_coalesce");
+    for subscribers in SUBSCRIBERS {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(subscribers),
+            &subscribers,
+            |b, &subscribers| {
+                b.iter_batched(
+                    || {
+                        (0..16)
+                            .flat_map(|ts| {
+                                let event = live_values(ts);
+                                (0..subscribers).map(move |id| (BenchRecipient(id), event.clone()))
+                            })
+                            .collect::<Vec<_>>()
+                    },
+                    |items| black_box(coalesce(items)),
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Encoding an event for every subscriber compared to encoding it once and sharing the buffer
+fn event_encoding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("event_encoding");
+    let event = live_values(0);
+    for subscribers in SUBSCRIBERS {
+        group.bench_with_input(
+            BenchmarkId::new("per_subscriber", subscribers),
+            &subscribers,
+            |b, &subscribers| {
+                b.iter(|| {
+                    for _ in 0..subscribers {
+                        black_box(serde_json::to_string(event.as_ref()).unwrap());
+                    }
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("shared", subscribers),
+            &subscribers,
+            |b, &subscribers| {
+                b.iter(|| {
+                    let encoded: Arc<str> = serde_json::to_string(event.as_ref()).unwrap().into();
+                    for _ in 0..subscribers {
+                        black_box(encoded.clone());
+                    }
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, namespace_emit, queue_coalesce, event_encoding);
+criterion_main!(benches);
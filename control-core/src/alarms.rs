@@ -0,0 +1,292 @@
+use crate::machines::identification::MachineIdentificationUnique;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Cleared alarms kept for the alarm history, older ones are dropped first
+const MAX_CLEARED_ALARMS: usize = 200;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AlarmSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlarmState {
+    /// Condition is present and nobody acknowledged it yet
+    Active,
+    /// Condition is present and an operator acknowledged it
+    Acknowledged,
+    /// Condition is gone
+    Cleared,
+}
+
+/// Condition a machine currently reports, see [`crate::machines::api::MachineApi::api_alarms`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AlarmCondition {
+    /// Stable identifier of the condition within the machine, e.g. `out_of_tolerance`
+    pub code: String,
+    pub message: String,
+    pub severity: AlarmSeverity,
+}
+
+impl AlarmCondition {
+    pub fn new(code: &str, message: impl Into<String>, severity: AlarmSeverity) -> Self {
+        Self {
+            code: code.to_string(),
+            message: message.into(),
+            severity,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Alarm {
+    pub id: u64,
+    /// `None` for alarms not raised by a single machine
+    pub machine: Option<MachineIdentificationUnique>,
+    pub code: String,
+    pub message: String,
+    pub severity: AlarmSeverity,
+    pub state: AlarmState,
+    /// unix timestamps in milliseconds
+    pub raised_at: u64,
+    pub acknowledged_at: Option<u64>,
+    pub acknowledged_by: Option<String>,
+    pub cleared_at: Option<u64>,
+}
+
+/// Central alarm list of the line
+///
+/// Sources report the set of conditions they currently have, the manager raises
+/// alarms for new conditions and clears alarms whose condition is gone.
+#[derive(Debug, Default)]
+pub struct AlarmManager {
+    /// Active and acknowledged alarms
+    alarms: Vec<Alarm>,
+    cleared: VecDeque<Alarm>,
+    next_id: u64,
+    /// Alarms raised, acknowledged or cleared since the last [`Self::take_changes`]
+    changes: Vec<Alarm>,
+}
+
+impl AlarmManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Continues the ids after `id`, so ids stay unique across restarts of a persisted history
+    pub fn continue_ids_after(&mut self, id: u64) {
+        self.next_id = self.next_id.max(id);
+    }
+
+    /// Latest state of the alarms that changed since the last call, for persisting them
+    pub fn take_changes(&mut self) -> Vec<Alarm> {
+        std::mem::take(&mut self.changes)
+    }
+
+    /// Replaces the conditions of a source, returns if any alarm changed
+    pub fn update(
+        &mut self,
+        machine: Option<&MachineIdentificationUnique>,
+        conditions: &[AlarmCondition],
+        now: u64,
+    ) -> bool {
+        let mut changed = false;
+
+        // clear alarms whose condition is gone
+        let mut i = 0;
+        while i < self.alarms.len() {
+            let alarm = &self.alarms[i];
+            let gone = alarm.machine.as_ref() == machine
+                && !conditions
+                    .iter()
+                    .any(|condition| condition.code == alarm.code);
+            if gone {
+                let mut alarm = self.alarms.remove(i);
+                alarm.state = AlarmState::Cleared;
+                alarm.cleared_at = Some(now);
+                self.changes.push(alarm.clone());
+                self.push_cleared(alarm);
+                changed = true;
+            } else {
+                i += 1;
+            }
+        }
+
+        // raise alarms for new conditions
+        for condition in conditions {
+            let exists = self
+                .alarms
+                .iter()
+                .any(|alarm| alarm.machine.as_ref() == machine && alarm.code == condition.code);
+            if exists {
+                continue;
+            }
+            self.next_id += 1;
+            let alarm = Alarm {
+                id: self.next_id,
+                machine: machine.cloned(),
+                code: condition.code.clone(),
+                message: condition.message.clone(),
+                severity: condition.severity,
+                state: AlarmState::Active,
+                raised_at: now,
+                acknowledged_at: None,
+                acknowledged_by: None,
+                cleared_at: None,
+            };
+            self.changes.push(alarm.clone());
+            self.alarms.push(alarm);
+            changed = true;
+        }
+
+        changed
+    }
+
+    /// Acknowledges an active alarm
+    pub fn acknowledge(&mut self, id: u64, user: &str, now: u64) -> Result<(), anyhow::Error> {
+        let alarm = self
+            .alarms
+            .iter_mut()
+            .find(|alarm| alarm.id == id)
+            .ok_or_else(|| anyhow::anyhow!("Alarm {} is not active", id))?;
+        if alarm.state == AlarmState::Active {
+            alarm.state = AlarmState::Acknowledged;
+            alarm.acknowledged_at = Some(now);
+            alarm.acknowledged_by = Some(user.to_string());
+            self.changes.push(alarm.clone());
+        }
+        Ok(())
+    }
+
+    /// Acknowledges all active alarms, returns how many were acknowledged
+    pub fn acknowledge_all(&mut self, user: &str, now: u64) -> usize {
+        let mut count = 0;
+        for alarm in &mut self.alarms {
+            if alarm.state == AlarmState::Active {
+                alarm.state = AlarmState::Acknowledged;
+                alarm.acknowledged_at = Some(now);
+                alarm.acknowledged_by = Some(user.to_string());
+                self.changes.push(alarm.clone());
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Active and acknowledged alarms, most severe first
+    pub fn active(&self) -> Vec<Alarm> {
+        let mut alarms = self.alarms.clone();
+        alarms.sort_by(|a, b| {
+            b.severity
+                .cmp(&a.severity)
+                .then(a.raised_at.cmp(&b.raised_at))
+        });
+        alarms
+    }
+
+    /// Cleared alarms, newest first
+    pub fn cleared(&self) -> Vec<Alarm> {
+        self.cleared.iter().rev().cloned().collect()
+    }
+
+    fn push_cleared(&mut self, alarm: Alarm) {
+        if self.cleared.len() >= MAX_CLEARED_ALARMS {
+            self.cleared.pop_front();
+        }
+        self.cleared.push_back(alarm);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machines::identification::MachineIdentification;
+
+    fn machine(serial: u16) -> MachineIdentificationUnique {
+        MachineIdentificationUnique {
+            machine_identification: MachineIdentification {
+                vendor: 1,
+                machine: 6,
+            },
+            serial,
+        }
+    }
+
+    fn condition(code: &str, severity: AlarmSeverity) -> AlarmCondition {
+        AlarmCondition::new(code, code, severity)
+    }
+
+    #[test]
+    fn test_raise_and_clear() {
+        let mut manager = AlarmManager::new();
+        let laser = machine(1);
+
+        let conditions = [condition("out_of_tolerance", AlarmSeverity::Warning)];
+        assert!(manager.update(Some(&laser), &conditions, 100));
+        // same conditions don't raise again
+        assert!(!manager.update(Some(&laser), &conditions, 200));
+        assert_eq!(manager.active().len(), 1);
+        assert_eq!(manager.active()[0].raised_at, 100);
+
+        assert!(manager.update(Some(&laser), &[], 300));
+        assert!(manager.active().is_empty());
+        let cleared = manager.cleared();
+        assert_eq!(cleared.len(), 1);
+        assert_eq!(cleared[0].state, AlarmState::Cleared);
+        assert_eq!(cleared[0].cleared_at, Some(300));
+
+        let changes = manager.take_changes();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].state, AlarmState::Active);
+        assert_eq!(changes[1].state, AlarmState::Cleared);
+        assert!(manager.take_changes().is_empty());
+    }
+
+    #[test]
+    fn test_sources_are_independent() {
+        let mut manager = AlarmManager::new();
+        let conditions = [condition("wiring_error", AlarmSeverity::Critical)];
+        manager.update(Some(&machine(1)), &conditions, 0);
+        manager.update(Some(&machine(2)), &conditions, 0);
+        assert_eq!(manager.active().len(), 2);
+
+        manager.update(Some(&machine(1)), &[], 10);
+        let active = manager.active();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].machine, Some(machine(2)));
+    }
+
+    #[test]
+    fn test_acknowledge() {
+        let mut manager = AlarmManager::new();
+        manager.update(
+            None,
+            &[
+                condition("a", AlarmSeverity::Info),
+                condition("b", AlarmSeverity::Critical),
+            ],
+            0,
+        );
+
+        // most severe first
+        let active = manager.active();
+        assert_eq!(active[0].code, "b");
+
+        manager.acknowledge(active[0].id, "op", 5).unwrap();
+        assert!(manager.acknowledge(999, "op", 5).is_err());
+        let active = manager.active();
+        assert_eq!(active[0].state, AlarmState::Acknowledged);
+        assert_eq!(active[0].acknowledged_by.as_deref(), Some("op"));
+        assert_eq!(active[1].state, AlarmState::Active);
+
+        assert_eq!(manager.acknowledge_all("op", 6), 1);
+        assert_eq!(manager.acknowledge_all("op", 7), 0);
+
+        manager.continue_ids_after(100);
+        manager.update(None, &[condition("c", AlarmSeverity::Info)], 8);
+        assert_eq!(manager.active().last().unwrap().id, 101);
+    }
+}
@@ -0,0 +1,202 @@
+use std::f64::consts::{FRAC_1_SQRT_2, PI};
+
+use uom::si::{f64::Frequency, frequency::hertz};
+
+/// Second order IIR filter (biquad)
+///
+/// Coefficients follow the RBJ audio EQ cookbook, the filter runs in transposed direct form II.
+/// The filter assumes a constant sample rate, usually one sample per control cycle.
+///
+/// # Example
+/// ```ignore
+/// // suppress a 2 Hz disturbance of a signal sampled every 10 ms
+/// let notch = Biquad::notch(
+///     Frequency::new::<hertz>(2.0),
+///     Frequency::new::<hertz>(100.0),
+///     Biquad::BUTTERWORTH_Q,
+/// )?;
+///
+/// let filtered = notch.filter(diameter);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Biquad {
+    // Params, normalized to a0 = 1
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    // State
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    /// Q of a maximally flat pass band
+    pub const BUTTERWORTH_Q: f64 = FRAC_1_SQRT_2;
+
+    /// Passes frequencies below `cutoff`
+    pub fn low_pass(
+        cutoff: Frequency,
+        sample_rate: Frequency,
+        q: f64,
+    ) -> Result<Self, anyhow::Error> {
+        let (cos, alpha) = Self::prepare("low_pass", cutoff, sample_rate, q)?;
+        Ok(Self::from_coefficients(
+            [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        ))
+    }
+
+    /// Removes a narrow band around `center`, a lower `q` widens the band
+    pub fn notch(center: Frequency, sample_rate: Frequency, q: f64) -> Result<Self, anyhow::Error> {
+        let (cos, alpha) = Self::prepare("notch", center, sample_rate, q)?;
+        Ok(Self::from_coefficients(
+            [1.0, -2.0 * cos, 1.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        ))
+    }
+
+    /// Validates the parameters and returns `cos(w0)` and `alpha` of the cookbook
+    fn prepare(
+        filter: &str,
+        frequency: Frequency,
+        sample_rate: Frequency,
+        q: f64,
+    ) -> Result<(f64, f64), anyhow::Error> {
+        let frequency = frequency.get::<hertz>();
+        let sample_rate = sample_rate.get::<hertz>();
+        if !sample_rate.is_finite() || sample_rate <= 0.0 {
+            return Err(anyhow::anyhow!(
+                "[{}::Biquad::{}] Sample rate must be positive, got {} Hz",
+                module_path!(),
+                filter,
+                sample_rate
+            ));
+        }
+        if !frequency.is_finite() || frequency <= 0.0 || frequency >= sample_rate / 2.0 {
+            return Err(anyhow::anyhow!(
+                "[{}::Biquad::{}] Frequency must be between 0 Hz and half the sample rate ({} Hz), got {} Hz",
+                module_path!(),
+                filter,
+                sample_rate / 2.0,
+                frequency
+            ));
+        }
+        if !q.is_finite() || q <= 0.0 {
+            return Err(anyhow::anyhow!(
+                "[{}::Biquad::{}] Q must be positive, got {}",
+                module_path!(),
+                filter,
+                q
+            ));
+        }
+        let w0 = 2.0 * PI * frequency / sample_rate;
+        Ok((w0.cos(), w0.sin() / (2.0 * q)))
+    }
+
+    fn from_coefficients(b: [f64; 3], a: [f64; 3]) -> Self {
+        Self {
+            b0: b[0] / a[0],
+            b1: b[1] / a[0],
+            b2: b[2] / a[0],
+            a1: a[1] / a[0],
+            a2: a[2] / a[0],
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    /// Filters one sample
+    pub fn filter(&mut self, input: f64) -> f64 {
+        let output = self.b0.mul_add(input, self.z1);
+        self.z1 = self.a1.mul_add(-output, self.b1.mul_add(input, self.z2));
+        self.z2 = self.a2.mul_add(-output, self.b2 * input);
+        output
+    }
+
+    /// Settles the filter at a constant `input`
+    ///
+    /// Avoids the step response from zero when the filter starts on a running signal.
+    pub fn reset_to(&mut self, input: f64) {
+        let gain = (self.b0 + self.b1 + self.b2) / (1.0 + self.a1 + self.a2);
+        let output = gain * input;
+        self.z1 = self.b0.mul_add(-input, output);
+        self.z2 = self.a2.mul_add(-output, self.b2 * input);
+    }
+
+    pub const fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    const SAMPLE_RATE: f64 = 100.0;
+
+    fn hz(value: f64) -> Frequency {
+        Frequency::new::<hertz>(value)
+    }
+
+    /// Output amplitude of a unit sine at `frequency` after the filter settled
+    ///
+    /// Taken from the RMS over whole periods, the samples rarely hit the peak of the sine.
+    fn amplitude(filter: &Biquad, frequency: f64) -> f64 {
+        let mut filter = filter.clone();
+        let power = (0..2000)
+            .map(|i| filter.filter((2.0 * PI * frequency * f64::from(i) / SAMPLE_RATE).sin()))
+            .skip(1000)
+            .map(|output| output * output)
+            .sum::<f64>()
+            / 1000.0;
+        (2.0 * power).sqrt()
+    }
+
+    #[test]
+    fn test_low_pass() {
+        let mut low_pass =
+            Biquad::low_pass(hz(5.0), hz(SAMPLE_RATE), Biquad::BUTTERWORTH_Q).unwrap();
+
+        // unit gain for constant signals
+        let mut output = 0.0;
+        for _ in 0..1000 {
+            output = low_pass.filter(3.0);
+        }
+        assert_relative_eq!(output, 3.0, epsilon = 1e-9);
+
+        assert!(amplitude(&low_pass, 0.5) > 0.99);
+        // -3 dB at the cutoff
+        assert_relative_eq!(amplitude(&low_pass, 5.0), FRAC_1_SQRT_2, epsilon = 0.02);
+        assert!(amplitude(&low_pass, 40.0) < 0.02);
+    }
+
+    #[test]
+    fn test_notch() {
+        let notch = Biquad::notch(hz(2.0), hz(SAMPLE_RATE), 2.0).unwrap();
+
+        assert!(amplitude(&notch, 2.0) < 0.01);
+        assert!(amplitude(&notch, 0.1) > 0.99);
+        assert!(amplitude(&notch, 20.0) > 0.99);
+    }
+
+    #[test]
+    fn test_reset_to() {
+        let mut filter = Biquad::low_pass(hz(1.0), hz(SAMPLE_RATE), Biquad::BUTTERWORTH_Q).unwrap();
+        filter.reset_to(1.75);
+        for _ in 0..10 {
+            assert_relative_eq!(filter.filter(1.75), 1.75, epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_invalid_parameters() {
+        assert!(Biquad::low_pass(hz(50.0), hz(SAMPLE_RATE), 0.7).is_err());
+        assert!(Biquad::low_pass(hz(0.0), hz(SAMPLE_RATE), 0.7).is_err());
+        assert!(Biquad::notch(hz(2.0), hz(0.0), 0.7).is_err());
+        assert!(Biquad::notch(hz(2.0), hz(SAMPLE_RATE), 0.0).is_err());
+    }
+}
@@ -104,6 +104,23 @@ impl ClampingTimeagnosticPidController {
         self.kd = kd;
     }
 
+    /// Changes the gains without a jump of the output
+    ///
+    /// Unlike [`ClampingTimeagnosticPidController::configure`] the state is kept, the integral
+    /// absorbs the change of the proportional and derivative part. Without an integral gain the
+    /// output jumps.
+    pub fn set_gains(&mut self, kp: f64, ki: f64, kd: f64) {
+        if self.last.is_some() && ki != 0.0 {
+            let signal = self
+                .kd
+                .mul_add(self.ed, self.kp.mul_add(self.ep, self.ki * self.ei));
+            self.ei = (signal - kd.mul_add(self.ed, kp * self.ep)) / ki;
+        }
+        self.kp = kp;
+        self.ki = ki;
+        self.kd = kd;
+    }
+
     pub const fn optional_clamp(value: f64, min: Option<f64>, max: Option<f64>) -> f64 {
         match (min, max) {
             (Some(min), Some(max)) => value.clamp(min, max),
@@ -175,6 +192,8 @@ impl ClampingTimeagnosticPidController {
 #[cfg(test)]
 mod tests {
     use crate::controllers::clamping_timeagnostic_pid::ClampingTimeagnosticPidController;
+    use approx::assert_relative_eq;
+    use std::time::{Duration, Instant};
 
     #[test]
     fn test_optional_clamp_with_both_bounds() {
@@ -215,4 +234,21 @@ mod tests {
         let clamped = ClampingTimeagnosticPidController::optional_clamp(val, None, None);
         assert_eq!(clamped, 42.0);
     }
+
+    #[test]
+    fn test_set_gains_is_bumpless() {
+        let dt = Duration::from_millis(100);
+        let mut t = Instant::now();
+        let mut pid = ClampingTimeagnosticPidController::simple_new(2.0, 0.5, 0.0);
+        let mut unchanged = ClampingTimeagnosticPidController::simple_new(2.0, 0.5, 0.0);
+        for _ in 0..10 {
+            pid.update(1.0, t);
+            unchanged.update(1.0, t);
+            t += dt;
+        }
+
+        pid.set_gains(4.0, 0.5, 0.0);
+        assert_eq!(pid.get_kp(), 4.0);
+        assert_relative_eq!(pid.update(1.0, t), unchanged.update(1.0, t), epsilon = 1e-9);
+    }
 }
@@ -1,3 +1,4 @@
+pub mod biquad;
 pub mod clamping_timeagnostic_pid;
 pub mod first_degree_motion;
 pub mod pid;
@@ -1,4 +1,4 @@
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 #[derive(Debug)]
 pub struct PidController {
@@ -9,13 +9,21 @@ pub struct PidController {
     ki: f64,
     /// Derivative gain
     kd: f64,
+    /// Setpoint weight of the proportional part, see [`PidController::update_setpoint`]
+    setpoint_weight_p: f64,
+    /// Setpoint weight of the derivative part, see [`PidController::update_setpoint`]
+    setpoint_weight_d: f64,
+    /// Time constant of the derivative low-pass filter, zero disables the filter
+    derivative_filter: Duration,
     // State
     /// Proportional error
     ep: f64,
     /// Integral error
     ei: f64,
-    /// Derivative error
+    /// Derivative error, low-pass filtered
     ed: f64,
+    /// Input of the derivative part of the last update
+    d_input: f64,
 
     last: Option<Instant>,
 }
@@ -26,9 +34,13 @@ impl PidController {
             kp,
             ki,
             kd,
+            setpoint_weight_p: 1.0,
+            setpoint_weight_d: 1.0,
+            derivative_filter: Duration::ZERO,
             ep: 0.0,
             ei: 0.0,
             ed: 0.0,
+            d_input: 0.0,
             last: None,
         }
     }
@@ -40,6 +52,20 @@ impl PidController {
         self.kd = kd;
     }
 
+    /// Changes the gains without a jump of the output
+    ///
+    /// Unlike [`PidController::configure`] the state is kept, the integral absorbs the change of
+    /// the proportional and derivative part. Without an integral gain the output jumps.
+    pub fn set_gains(&mut self, kp: f64, ki: f64, kd: f64) {
+        if self.last.is_some() && ki != 0.0 {
+            let signal = self.signal();
+            self.ei = (signal - kd.mul_add(self.ed, kp * self.ep)) / ki;
+        }
+        self.kp = kp;
+        self.ki = ki;
+        self.kd = kd;
+    }
+
     pub const fn get_kp(&self) -> f64 {
         self.kp
     }
@@ -52,24 +78,76 @@ impl PidController {
         self.kd
     }
 
+    /// Sets the setpoint weights of the proportional and derivative part
+    ///
+    /// A weight below 1 softens the reaction to setpoint steps while disturbances are rejected
+    /// as before. A derivative weight of 0 is the common "derivative on measurement".
+    pub const fn set_setpoint_weights(&mut self, proportional: f64, derivative: f64) {
+        self.setpoint_weight_p = proportional;
+        self.setpoint_weight_d = derivative;
+    }
+
+    pub const fn get_setpoint_weights(&self) -> (f64, f64) {
+        (self.setpoint_weight_p, self.setpoint_weight_d)
+    }
+
+    /// Sets the time constant of the first order low-pass on the derivative part
+    ///
+    /// Makes the derivative usable on noisy signals, [`Duration::ZERO`] disables the filter.
+    pub const fn set_derivative_filter(&mut self, time_constant: Duration) {
+        self.derivative_filter = time_constant;
+    }
+
+    pub const fn get_derivative_filter(&self) -> Duration {
+        self.derivative_filter
+    }
+
+    /// Updates with the control error, setpoint weights don't apply
     pub fn update(&mut self, error: f64, t: Instant) -> f64 {
+        self.step(error, error, error, t)
+    }
+
+    /// Updates with setpoint and measurement so the setpoint weights apply
+    pub fn update_setpoint(&mut self, setpoint: f64, measurement: f64, t: Instant) -> f64 {
+        self.step(
+            setpoint - measurement,
+            self.setpoint_weight_p.mul_add(setpoint, -measurement),
+            self.setpoint_weight_d.mul_add(setpoint, -measurement),
+            t,
+        )
+    }
+
+    /// Moves the time base to `t` without updating, e.g. while the measurement is missing
+    ///
+    /// The output stays the same and the next update doesn't integrate over the gap.
+    pub const fn hold(&mut self, t: Instant) {
+        if self.last.is_some() {
+            self.last = Some(t);
+        }
+    }
+
+    /// Starts the controller at `t` so its output for `error` is `output`
+    ///
+    /// Bumpless transfer from manual control, the integral takes the difference between the
+    /// output and the proportional part. Without an integral gain the output jumps.
+    pub fn initialize(&mut self, output: f64, error: f64, t: Instant) {
+        self.ep = error;
+        self.ed = 0.0;
+        self.d_input = error;
+        self.ei = if self.ki == 0.0 {
+            0.0
+        } else {
+            self.kp.mul_add(-error, output) / self.ki
+        };
+        self.last = Some(t);
+    }
+
+    fn step(&mut self, error: f64, p_input: f64, d_input: f64, t: Instant) -> f64 {
         match self.last {
             // First update
             None => {
-                // Calculate error
-                let ep = error;
-
-                // Calculate signal
-                let signal = self.kp * ep;
-
-                // Set values
-                self.ep = ep;
                 self.ei = 0.0;
-
                 self.ed = 0.0;
-                self.last = Some(t);
-
-                signal
             }
             // Subsequent updates
             Some(last) => {
@@ -77,28 +155,139 @@ impl PidController {
                 let dt = t.duration_since(last).as_secs_f64();
 
                 // Calculate errors
-                let ep = error;
-                let ei = ep.mul_add(dt, self.ei);
-                let ed = (ep - self.ep) / dt;
+                self.ei = error.mul_add(dt, self.ei);
+                let ed = (d_input - self.d_input) / dt;
+                self.ed = if self.derivative_filter.is_zero() {
+                    ed
+                } else {
+                    // first order low-pass
+                    let alpha = dt / (self.derivative_filter.as_secs_f64() + dt);
+                    alpha.mul_add(ed - self.ed, self.ed)
+                };
+            }
+        }
 
-                // Calculate signal
-                let signal = self.kd.mul_add(ed, self.kp.mul_add(ep, self.ki * ei));
+        // Set values
+        self.ep = p_input;
+        self.d_input = d_input;
+        self.last = Some(t);
 
-                // Set values
-                self.ep = ep;
-                self.ei = ei;
-                self.ed = ed;
-                self.last = Some(t);
+        self.signal()
+    }
 
-                signal
-            }
-        }
+    /// Proportional, integral and derivative part of the last output
+    pub fn terms(&self) -> (f64, f64, f64) {
+        (self.kp * self.ep, self.ki * self.ei, self.kd * self.ed)
+    }
+
+    fn signal(&self) -> f64 {
+        self.kd
+            .mul_add(self.ed, self.kp.mul_add(self.ep, self.ki * self.ei))
     }
 
     pub const fn reset(&mut self) {
         self.ep = 0.0;
         self.ei = 0.0;
         self.ed = 0.0;
+        self.d_input = 0.0;
         self.last = None;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    const DT: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn test_update() {
+        let mut pid = PidController::new(2.0, 1.0, 0.1);
+        let t = Instant::now();
+
+        assert_relative_eq!(pid.update(1.0, t), 2.0);
+        // 2 * 2 + 1 * 0.02 + 0.1 * 100
+        assert_relative_eq!(pid.update(2.0, t + DT), 14.02, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_derivative_filter() {
+        let mut unfiltered = PidController::new(0.0, 0.0, 1.0);
+        let mut filtered = PidController::new(0.0, 0.0, 1.0);
+        filtered.set_derivative_filter(Duration::from_millis(90));
+        let t = Instant::now();
+
+        unfiltered.update(0.0, t);
+        filtered.update(0.0, t);
+        // a step of 1 in 10 ms
+        let raw = unfiltered.update(1.0, t + DT);
+        let first = filtered.update(1.0, t + DT);
+        assert_relative_eq!(raw, 100.0, epsilon = 1e-9);
+        assert_relative_eq!(first, 10.0, epsilon = 1e-9);
+
+        // the filtered derivative decays instead of dropping to zero
+        let second = filtered.update(1.0, t + 2 * DT);
+        assert_relative_eq!(second, 9.0, epsilon = 1e-9);
+        assert_relative_eq!(unfiltered.update(1.0, t + 2 * DT), 0.0);
+    }
+
+    #[test]
+    fn test_setpoint_weights() {
+        let mut pid = PidController::new(1.0, 0.0, 1.0);
+        pid.set_setpoint_weights(0.5, 0.0);
+        let t = Instant::now();
+
+        assert_relative_eq!(pid.update_setpoint(0.0, 0.0, t), 0.0);
+        // a setpoint step only kicks the weighted proportional part
+        assert_relative_eq!(pid.update_setpoint(2.0, 0.0, t + DT), 1.0);
+        // a measurement step acts fully on both
+        assert_relative_eq!(
+            pid.update_setpoint(2.0, 0.1, t + 2 * DT),
+            0.9 - 10.0,
+            epsilon = 1e-9
+        );
+    }
+
+    #[test]
+    fn test_hold() {
+        let mut pid = PidController::new(1.0, 1.0, 0.0);
+        let t = Instant::now();
+
+        pid.update(1.0, t);
+        pid.hold(t + Duration::from_secs(10));
+        // only the 10 ms after the hold are integrated
+        assert_relative_eq!(
+            pid.update(1.0, t + Duration::from_secs(10) + DT),
+            1.01,
+            epsilon = 1e-9
+        );
+    }
+
+    #[test]
+    fn test_initialize() {
+        let mut pid = PidController::new(2.0, 0.5, 1.0);
+        let t = Instant::now();
+
+        pid.initialize(3.0, 0.5, t);
+        // continues from the manual output instead of starting at kp * error
+        assert_relative_eq!(pid.update(0.5, t + DT), 3.0025, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_bumpless_gain_change() {
+        let mut pid = PidController::new(1.0, 0.5, 0.0);
+        let mut t = Instant::now();
+        let mut signal = 0.0;
+        for _ in 0..100 {
+            signal = pid.update(1.0, t);
+            t += DT;
+        }
+
+        pid.set_gains(3.0, 1.0, 0.0);
+        assert_relative_eq!(pid.get_kp(), 3.0);
+        // same error a cycle later only adds the new integral step
+        let after = pid.update(1.0, t);
+        assert_relative_eq!(after, signal + 0.01, epsilon = 1e-9);
+    }
+}
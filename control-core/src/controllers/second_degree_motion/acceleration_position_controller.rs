@@ -8,6 +8,7 @@ pub enum MotionControllerError {
     InvalidSpeedLimits,
     InvalidAccelerationLimits,
     InvalidPositionLimits,
+    InvalidJerkLimits,
     ZeroDeceleration,
 }
 
@@ -32,6 +33,9 @@ impl fmt::Display for MotionControllerError {
                     "Invalid position limits: min_position must be ≤ max_position"
                 )
             }
+            Self::InvalidJerkLimits => {
+                write!(f, "Invalid jerk limits: max_jerk must be > 0")
+            }
             Self::ZeroDeceleration => {
                 write!(
                     f,
@@ -703,6 +707,16 @@ impl AccelerationPositionController {
         // Update position based on current speed
         let new_position = self.current_speed.mul_add(dt, self.current_position);
 
+        // Deceleration starts on the first update past the deceleration point, so the last
+        // step can carry the position past the target. Stop at the target instead.
+        if (new_position - self.target_position) * f64::from(self.direction) > 0.0 {
+            self.current_position = self.target_position;
+            self.current_speed = 0.0;
+            self.current_acceleration = 0.0;
+            self.motion_phase = MotionPhase::Idle;
+            return Ok(());
+        }
+
         // Check position limits and handle violations
         let mut position_limited = false;
 
@@ -77,10 +77,10 @@ impl JerkSpeedController {
     ) -> Self {
         // Create the base controller with renamed parameters
         let base_controller = AccelerationPositionController::new(
-            min_jerk,         // min_speed in the base controller
-            max_jerk,         // max_speed in the base controller
-            min_acceleration, // min_acceleration in the base controller
-            max_acceleration, // max_acceleration in the base controller
+            min_acceleration, // min_speed in the base controller
+            max_acceleration, // max_speed in the base controller
+            min_jerk,         // min_acceleration in the base controller
+            max_jerk,         // max_acceleration in the base controller
             min_speed,        // min_position in the base controller
             max_speed,        // max_position in the base controller
             1e-6,             // position_tolerance
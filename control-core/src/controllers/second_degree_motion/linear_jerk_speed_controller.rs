@@ -279,3 +279,45 @@ impl LinearJerkSpeedController {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{check_ramp, ramp_limits, run_ramp, time_step, velocity};
+    use proptest::prelude::*;
+    use uom::ConstZero;
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn ramp_from_rest_stays_within_limits(
+            limits in ramp_limits(),
+            target in velocity(7.5),
+            dt in time_step(),
+        ) {
+            let mut controller = limits.controller();
+            let samples = run_ramp(
+                &mut controller,
+                target,
+                dt,
+                limits.settle_time(Velocity::ZERO, target),
+            );
+            check_ramp(&samples, &limits, Velocity::ZERO, target, dt)?;
+        }
+
+        #[test]
+        fn ramp_between_speeds_stays_within_limits(
+            limits in ramp_limits(),
+            start in velocity(1.0),
+            target in velocity(7.5),
+            dt in time_step(),
+        ) {
+            let start = limits.reachable(start);
+            let mut controller = limits.controller();
+            controller.reset(start).unwrap();
+            let samples = run_ramp(&mut controller, target, dt, limits.settle_time(start, target));
+            check_ramp(&samples, &limits, start, target, dt)?;
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use std::time::{Duration, Instant};
+
+use uom::si::{
+    acceleration::meter_per_second_squared,
+    f64::{Acceleration, Jerk, Length, Velocity},
+    jerk::meter_per_second_cubed,
+    length::meter,
+    velocity::meter_per_second,
+};
+
+use super::acceleration_position_controller::MotionControllerError;
+use super::s_curve_profile::{MotionState, SCurveLimits, SCurveProfile};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SCurveTarget {
+    Position(f64),
+    Speed(f64),
+}
+
+/// Linear S-Curve Controller with proper physical units
+///
+/// Moves a linear axis along jerk limited [`SCurveProfile`]s, either to a target position where
+/// it stops or to a target speed it keeps. Unlike the `LinearJerkSpeedController` the
+/// acceleration and deceleration limits can differ.
+///
+/// A new target is picked up at the next point of the running profile without acceleration,
+/// so the acceleration never jumps. That is right away at rest or at constant speed and at the
+/// end of the current speed change otherwise.
+///
+/// # Example
+/// ```ignore
+/// let mut controller = LinearSCurveController::new(
+///     Velocity::new::<meter_per_second>(0.1),
+///     Acceleration::new::<meter_per_second_squared>(1.0),
+///     Acceleration::new::<meter_per_second_squared>(0.5),
+///     Jerk::new::<meter_per_second_cubed>(20.0),
+/// )?;
+///
+/// controller.set_target_position(Length::new::<meter>(0.05));
+/// let position = controller.update(Instant::now());
+/// ```
+#[derive(Debug)]
+pub struct LinearSCurveController {
+    limits: SCurveLimits,
+    profile: SCurveProfile,
+    /// Start of the running profile, set on the first update
+    profile_start: Option<Instant>,
+    /// Time since the start of the running profile at the last update in seconds
+    elapsed: f64,
+    state: MotionState,
+    pending: Option<SCurveTarget>,
+}
+
+impl LinearSCurveController {
+    /// Create a new controller at rest at position zero
+    ///
+    /// # Errors
+    /// Returns MotionControllerError if a limit is not positive
+    pub fn new(
+        max_speed: Velocity,
+        max_acceleration: Acceleration,
+        max_deceleration: Acceleration,
+        max_jerk: Jerk,
+    ) -> Result<Self, MotionControllerError> {
+        let limits = SCurveLimits::new(
+            max_speed.get::<meter_per_second>(),
+            max_acceleration.get::<meter_per_second_squared>(),
+            max_deceleration.get::<meter_per_second_squared>(),
+            max_jerk.get::<meter_per_second_cubed>(),
+        )?;
+        let state = MotionState::at_rest(0.0);
+        Ok(Self {
+            limits,
+            profile: SCurveProfile::hold(state),
+            profile_start: None,
+            elapsed: 0.0,
+            state,
+            pending: None,
+        })
+    }
+
+    /// Move to `position` and stop there
+    pub fn set_target_position(&mut self, position: Length) {
+        self.pending = Some(SCurveTarget::Position(position.get::<meter>()));
+    }
+
+    /// Move at `speed`, limited to the maximum speed
+    pub fn set_target_speed(&mut self, speed: Velocity) {
+        self.pending = Some(SCurveTarget::Speed(speed.get::<meter_per_second>()));
+    }
+
+    /// Advance the motion to `t` and return the position
+    pub fn update(&mut self, t: Instant) -> Length {
+        let mut start = *self.profile_start.get_or_insert(t);
+        let mut elapsed = t.saturating_duration_since(start).as_secs_f64();
+
+        if let Some(target) = self.pending {
+            let switch = self.profile.next_steady_time(self.elapsed);
+            if switch <= elapsed {
+                let from = MotionState {
+                    acceleration: 0.0,
+                    ..self.profile.sample(switch)
+                };
+                self.profile = match target {
+                    SCurveTarget::Position(position) => {
+                        SCurveProfile::plan_position(from, position, &self.limits)
+                    }
+                    SCurveTarget::Speed(speed) => {
+                        SCurveProfile::plan_speed(from, speed, &self.limits)
+                    }
+                };
+                self.pending = None;
+                start += Duration::from_secs_f64(switch);
+                self.profile_start = Some(start);
+                elapsed = t.saturating_duration_since(start).as_secs_f64();
+            }
+        }
+
+        self.elapsed = elapsed;
+        self.state = self.profile.sample(elapsed);
+        Length::new::<meter>(self.state.position)
+    }
+
+    /// Get the current position
+    pub fn get_position(&self) -> Length {
+        Length::new::<meter>(self.state.position)
+    }
+
+    /// Get the current velocity
+    pub fn get_speed(&self) -> Velocity {
+        Velocity::new::<meter_per_second>(self.state.speed)
+    }
+
+    /// Get the current acceleration
+    pub fn get_acceleration(&self) -> Acceleration {
+        Acceleration::new::<meter_per_second_squared>(self.state.acceleration)
+    }
+
+    /// The last target is reached and there is no new one
+    pub const fn is_settled(&self) -> bool {
+        self.pending.is_none() && self.elapsed >= self.profile.get_duration()
+    }
+
+    /// Reset the controller to rest at `position`
+    ///
+    /// Drops the running profile and any pending target.
+    pub fn reset(&mut self, position: Length) {
+        self.state = MotionState::at_rest(position.get::<meter>());
+        self.profile = SCurveProfile::hold(self.state);
+        self.profile_start = None;
+        self.elapsed = 0.0;
+        self.pending = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::clock::{Clock, ManualClock};
+    use approx::assert_relative_eq;
+    use uom::si::length::millimeter;
+
+    const DT: Duration = Duration::from_millis(1);
+
+    fn controller() -> LinearSCurveController {
+        LinearSCurveController::new(
+            Velocity::new::<meter_per_second>(0.1),
+            Acceleration::new::<meter_per_second_squared>(1.0),
+            Acceleration::new::<meter_per_second_squared>(0.5),
+            Jerk::new::<meter_per_second_cubed>(20.0),
+        )
+        .unwrap()
+    }
+
+    /// Updates until settled and returns the accelerations in m/s² after every step
+    fn run(controller: &mut LinearSCurveController, clock: &ManualClock) -> Vec<f64> {
+        let mut accelerations = Vec::new();
+        while !controller.is_settled() {
+            clock.advance(DT);
+            controller.update(clock.now());
+            accelerations.push(
+                controller
+                    .get_acceleration()
+                    .get::<meter_per_second_squared>(),
+            );
+            assert!(accelerations.len() < 100_000);
+        }
+        accelerations
+    }
+
+    #[test]
+    fn test_move_to_position() {
+        let clock = ManualClock::new();
+        let mut controller = controller();
+        controller.reset(Length::new::<millimeter>(20.0));
+        controller.update(clock.now());
+
+        controller.set_target_position(Length::new::<millimeter>(100.0));
+        run(&mut controller, &clock);
+        assert_relative_eq!(
+            controller.get_position().get::<millimeter>(),
+            100.0,
+            epsilon = 1e-6
+        );
+        assert_relative_eq!(
+            controller.get_speed().get::<meter_per_second>(),
+            0.0,
+            epsilon = 1e-9
+        );
+    }
+
+    #[test]
+    fn test_new_target_while_accelerating() {
+        let clock = ManualClock::new();
+        let mut controller = controller();
+        controller.update(clock.now());
+
+        controller.set_target_position(Length::new::<millimeter>(100.0));
+        for _ in 0..20 {
+            clock.advance(DT);
+            controller.update(clock.now());
+        }
+        assert!(
+            controller
+                .get_acceleration()
+                .get::<meter_per_second_squared>()
+                > 0.0
+        );
+
+        let last = controller
+            .get_acceleration()
+            .get::<meter_per_second_squared>();
+        controller.set_target_position(Length::new::<millimeter>(-50.0));
+        let accelerations = run(&mut controller, &clock);
+        assert_relative_eq!(
+            controller.get_position().get::<millimeter>(),
+            -50.0,
+            epsilon = 1e-6
+        );
+
+        // the acceleration stays continuous through the switch
+        let mut last = last;
+        for acceleration in accelerations {
+            assert!((acceleration - last).abs() <= 20.0 * 0.001 + 1e-9);
+            last = acceleration;
+        }
+    }
+
+    #[test]
+    fn test_target_speed() {
+        let clock = ManualClock::new();
+        let mut controller = controller();
+        controller.update(clock.now());
+
+        controller.set_target_speed(Velocity::new::<meter_per_second>(1.0));
+        run(&mut controller, &clock);
+        // limited to the maximum speed
+        assert_relative_eq!(
+            controller.get_speed().get::<meter_per_second>(),
+            0.1,
+            epsilon = 1e-9
+        );
+    }
+}
@@ -4,3 +4,5 @@ pub mod angular_jerk_speed_controller;
 pub mod jerk_speed_controller;
 pub mod linear_acceleration_position_controller;
 pub mod linear_jerk_speed_controller;
+pub mod linear_s_curve_controller;
+pub mod s_curve_profile;
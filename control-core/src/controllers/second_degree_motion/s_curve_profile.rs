@@ -0,0 +1,457 @@
+use super::acceleration_position_controller::MotionControllerError;
+
+/// Limits of an [`SCurveProfile`]
+///
+/// Acceleration applies while the speed magnitude grows and deceleration while it shrinks,
+/// which allows asymmetric profiles, e.g. an axis that brakes softer than it starts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SCurveLimits {
+    max_speed: f64,
+    max_acceleration: f64,
+    max_deceleration: f64,
+    max_jerk: f64,
+}
+
+impl SCurveLimits {
+    /// All limits are magnitudes and have to be positive
+    pub fn new(
+        max_speed: f64,
+        max_acceleration: f64,
+        max_deceleration: f64,
+        max_jerk: f64,
+    ) -> Result<Self, MotionControllerError> {
+        let valid = |value: f64| value.is_finite() && value > 0.0;
+        if !valid(max_speed) {
+            return Err(MotionControllerError::InvalidSpeedLimits);
+        }
+        if !valid(max_acceleration) || !valid(max_deceleration) {
+            return Err(MotionControllerError::InvalidAccelerationLimits);
+        }
+        if !valid(max_jerk) {
+            return Err(MotionControllerError::InvalidJerkLimits);
+        }
+        Ok(Self {
+            max_speed,
+            max_acceleration,
+            max_deceleration,
+            max_jerk,
+        })
+    }
+
+    pub const fn get_max_speed(&self) -> f64 {
+        self.max_speed
+    }
+
+    pub const fn get_max_acceleration(&self) -> f64 {
+        self.max_acceleration
+    }
+
+    pub const fn get_max_deceleration(&self) -> f64 {
+        self.max_deceleration
+    }
+
+    pub const fn get_max_jerk(&self) -> f64 {
+        self.max_jerk
+    }
+
+    /// Duration of a speed change by `speed_change` (magnitude) at `acceleration`
+    ///
+    /// Returns the duration of the jerk phases and of the constant acceleration phase.
+    fn ramp_times(&self, speed_change: f64, acceleration: f64) -> (f64, f64) {
+        let jerk_time = acceleration / self.max_jerk;
+        if speed_change * self.max_jerk <= acceleration * acceleration {
+            // the acceleration limit isn't reached
+            ((speed_change / self.max_jerk).sqrt(), 0.0)
+        } else {
+            (jerk_time, speed_change / acceleration - jerk_time)
+        }
+    }
+
+    fn ramp_duration(&self, speed_change: f64, acceleration: f64) -> f64 {
+        let (jerk_time, constant_time) = self.ramp_times(speed_change, acceleration);
+        2.0f64.mul_add(jerk_time, constant_time)
+    }
+}
+
+/// Position, speed and acceleration at one point of a profile
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MotionState {
+    pub position: f64,
+    pub speed: f64,
+    pub acceleration: f64,
+}
+
+impl MotionState {
+    pub const fn at_rest(position: f64) -> Self {
+        Self {
+            position,
+            speed: 0.0,
+            acceleration: 0.0,
+        }
+    }
+
+    /// State after `dt` seconds at a constant `jerk`
+    fn advance(&self, dt: f64, jerk: f64) -> Self {
+        let dt2 = dt * dt;
+        Self {
+            position: (jerk * dt / 6.0).mul_add(
+                dt2,
+                self.acceleration
+                    .mul_add(dt2 / 2.0, self.speed.mul_add(dt, self.position)),
+            ),
+            speed: (jerk / 2.0).mul_add(dt2, self.acceleration.mul_add(dt, self.speed)),
+            acceleration: jerk.mul_add(dt, self.acceleration),
+        }
+    }
+}
+
+/// Segment of constant jerk
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Segment {
+    duration: f64,
+    jerk: f64,
+}
+
+/// Jerk limited (S-curve) motion profile
+///
+/// A move consists of up to seven segments of constant jerk:
+/// 1. increase the acceleration
+/// 2. constant acceleration
+/// 3. decrease the acceleration
+/// 4. constant speed
+/// 5. increase the deceleration
+/// 6. constant deceleration
+/// 7. decrease the deceleration
+///
+/// The acceleration is continuous over the whole profile. Short moves skip the constant
+/// phases. A move against the current direction first stops and then adds the seven segments.
+///
+/// Profiles start with zero acceleration, see [`SCurveProfile::next_steady_time`] for
+/// switching to a new profile during a move. Times are in seconds since the start of the
+/// profile, units of the other values are up to the caller.
+///
+/// # Example
+/// ```ignore
+/// let limits = SCurveLimits::new(100.0, 1000.0, 500.0, 10000.0)?;
+/// let profile = SCurveProfile::plan_position(MotionState::at_rest(0.0), 50.0, &limits);
+///
+/// let state = profile.sample(0.1);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SCurveProfile {
+    start: MotionState,
+    segments: Vec<Segment>,
+    /// Time intervals without acceleration, a new profile can start there without a jump
+    steady: Vec<(f64, f64)>,
+    duration: f64,
+}
+
+impl SCurveProfile {
+    fn new(start: MotionState) -> Self {
+        Self {
+            start,
+            segments: Vec::new(),
+            steady: vec![(0.0, 0.0)],
+            duration: 0.0,
+        }
+    }
+
+    /// Keeps `start` forever
+    pub fn hold(start: MotionState) -> Self {
+        Self::new(start)
+    }
+
+    /// Moves to `target_speed`, limited to the maximum speed
+    ///
+    /// `start` must not accelerate. A change of direction brakes to a stop with the
+    /// deceleration limit first.
+    pub fn plan_speed(start: MotionState, target_speed: f64, limits: &SCurveLimits) -> Self {
+        let target_speed = target_speed.clamp(-limits.max_speed, limits.max_speed);
+        let mut profile = Self::new(start);
+
+        if start.speed * target_speed < 0.0 {
+            profile.push_ramp(-start.speed, limits.max_deceleration, limits);
+            profile.push_ramp(target_speed, limits.max_acceleration, limits);
+        } else if target_speed.abs() >= start.speed.abs() {
+            profile.push_ramp(target_speed - start.speed, limits.max_acceleration, limits);
+        } else {
+            profile.push_ramp(target_speed - start.speed, limits.max_deceleration, limits);
+        }
+        profile
+    }
+
+    /// Moves to `target_position` and stops there
+    ///
+    /// `start` must not accelerate. If the target can't be reached without overshooting, the
+    /// profile stops first and moves back.
+    pub fn plan_position(start: MotionState, target_position: f64, limits: &SCurveLimits) -> Self {
+        let mut profile = Self::new(start);
+
+        let direction = (target_position - start.position).signum();
+        let speed = start.speed * direction;
+        let distance = (target_position - start.position).abs();
+        let stopping_distance =
+            speed * limits.ramp_duration(speed.abs(), limits.max_deceleration) / 2.0;
+        if speed < 0.0 || stopping_distance > distance {
+            profile.push_ramp(-start.speed, limits.max_deceleration, limits);
+        }
+
+        let from = profile.end();
+        let direction = (target_position - from.position).signum();
+        let speed = from.speed * direction;
+        let distance = (target_position - from.position).abs();
+
+        // distance to get from the current speed to `peak` and stop from there
+        // every ramp is point symmetric, its mean speed is the mean of its start and end speed
+        let travel = |peak: f64| {
+            let acceleration = match peak >= speed {
+                true => limits.max_acceleration,
+                false => limits.max_deceleration,
+            };
+            ((speed + peak) / 2.0).mul_add(
+                limits.ramp_duration((peak - speed).abs(), acceleration),
+                peak / 2.0 * limits.ramp_duration(peak, limits.max_deceleration),
+            )
+        };
+
+        // the travel grows with the peak speed, the limit is the start or the maximum speed
+        let (mut low, mut high) = (speed, limits.max_speed.max(speed));
+        for _ in 0..100 {
+            let mid = (low + high) / 2.0;
+            match travel(mid) > distance {
+                true => high = mid,
+                false => low = mid,
+            }
+        }
+        let peak = low;
+        if peak <= 0.0 {
+            return profile;
+        }
+
+        let acceleration = match peak >= speed {
+            true => limits.max_acceleration,
+            false => limits.max_deceleration,
+        };
+        profile.push_ramp((peak - speed) * direction, acceleration, limits);
+        profile.push_cruise((distance - travel(peak)).max(0.0) / peak);
+        profile.push_ramp(-peak * direction, limits.max_deceleration, limits);
+        profile
+    }
+
+    /// Adds a speed change by `speed_change` at up to `acceleration`
+    fn push_ramp(&mut self, speed_change: f64, acceleration: f64, limits: &SCurveLimits) {
+        let (jerk_time, constant_time) = limits.ramp_times(speed_change.abs(), acceleration);
+        let jerk = limits.max_jerk * speed_change.signum();
+        self.push_segment(jerk_time, jerk);
+        self.push_segment(constant_time, 0.0);
+        self.push_segment(jerk_time, -jerk);
+        self.steady.push((self.duration, self.duration));
+    }
+
+    fn push_cruise(&mut self, duration: f64) {
+        let start = self.duration;
+        self.push_segment(duration, 0.0);
+        self.steady.push((start, self.duration));
+    }
+
+    fn push_segment(&mut self, duration: f64, jerk: f64) {
+        self.segments.push(Segment { duration, jerk });
+        self.duration += duration;
+    }
+
+    pub const fn get_start(&self) -> MotionState {
+        self.start
+    }
+
+    /// Time in seconds until the profile ends at a constant speed
+    pub const fn get_duration(&self) -> f64 {
+        self.duration
+    }
+
+    /// State at the end of the profile
+    pub fn end(&self) -> MotionState {
+        let state = self.segments.iter().fold(self.start, |state, segment| {
+            state.advance(segment.duration, segment.jerk)
+        });
+        // the ramps end at zero acceleration, drop the rounding error
+        MotionState {
+            acceleration: 0.0,
+            ..state
+        }
+    }
+
+    /// State `t` seconds after the start of the profile
+    ///
+    /// After the end the profile continues at the end speed.
+    pub fn sample(&self, t: f64) -> MotionState {
+        if t >= self.duration {
+            return self.end().advance(t - self.duration, 0.0);
+        }
+        let mut state = self.start;
+        let mut remaining = t.max(0.0);
+        for segment in &self.segments {
+            if remaining <= segment.duration {
+                return state.advance(remaining, segment.jerk);
+            }
+            state = state.advance(segment.duration, segment.jerk);
+            remaining -= segment.duration;
+        }
+        state
+    }
+
+    /// First time at or after `t` without acceleration
+    ///
+    /// A new profile starting from the state at this time continues the motion without a jump
+    /// of the acceleration.
+    pub fn next_steady_time(&self, t: f64) -> f64 {
+        self.steady
+            .iter()
+            .filter(|(_, end)| *end >= t)
+            .map(|(start, _)| start.max(t))
+            .fold(self.duration.max(t), f64::min)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    const DT: f64 = 0.001;
+
+    fn limits() -> SCurveLimits {
+        SCurveLimits::new(100.0, 1000.0, 500.0, 20000.0).unwrap()
+    }
+
+    /// Samples the profile and checks the limits, returns the samples
+    fn check(profile: &SCurveProfile, limits: &SCurveLimits) -> Vec<MotionState> {
+        let steps = (profile.get_duration() / DT).ceil() as usize + 1;
+        let samples: Vec<_> = (0..=steps).map(|i| profile.sample(i as f64 * DT)).collect();
+        for pair in samples.windows(2) {
+            let [last, state] = pair else { unreachable!() };
+            assert!(state.speed.abs() <= limits.max_speed + 1e-9);
+            // speeding up is limited by the acceleration, slowing down by the deceleration
+            let limit = match state.speed * state.acceleration >= 0.0 {
+                true => limits.max_acceleration,
+                false => limits.max_deceleration,
+            };
+            assert!(state.acceleration.abs() <= limit + 1e-9, "{:?}", state);
+            let jerk = (state.acceleration - last.acceleration) / DT;
+            assert!(jerk.abs() <= limits.max_jerk * (1.0 + 1e-9), "{}", jerk);
+        }
+        samples
+    }
+
+    #[test]
+    fn test_limits() {
+        assert!(SCurveLimits::new(0.0, 1.0, 1.0, 1.0).is_err());
+        assert!(SCurveLimits::new(1.0, 1.0, -1.0, 1.0).is_err());
+        assert!(SCurveLimits::new(1.0, 1.0, 1.0, f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn test_long_move_cruises() {
+        let limits = limits();
+        let profile = SCurveProfile::plan_position(MotionState::at_rest(10.0), 110.0, &limits);
+        let samples = check(&profile, &limits);
+
+        let end = profile.end();
+        assert_relative_eq!(end.position, 110.0, epsilon = 1e-9);
+        assert_relative_eq!(end.speed, 0.0, epsilon = 1e-9);
+        let peak = samples.iter().map(|state| state.speed).fold(0.0, f64::max);
+        assert_relative_eq!(peak, 100.0, epsilon = 1e-9);
+        assert!(samples.iter().all(|state| state.position <= 110.0 + 1e-9));
+
+        // the softer deceleration makes braking take longer than accelerating
+        let accelerating = samples
+            .iter()
+            .filter(|state| state.acceleration > 1e-9)
+            .count();
+        let decelerating = samples
+            .iter()
+            .filter(|state| state.acceleration < -1e-9)
+            .count();
+        assert!(decelerating > accelerating);
+    }
+
+    #[test]
+    fn test_short_move() {
+        let limits = limits();
+        let profile = SCurveProfile::plan_position(MotionState::at_rest(0.0), -0.5, &limits);
+        let samples = check(&profile, &limits);
+
+        assert_relative_eq!(profile.end().position, -0.5, epsilon = 1e-9);
+        let peak = samples
+            .iter()
+            .map(|state| state.speed.abs())
+            .fold(0.0, f64::max);
+        assert!(peak < 100.0);
+        assert!(samples.iter().all(|state| state.speed <= 1e-9));
+    }
+
+    #[test]
+    fn test_move_back_stops_first() {
+        let limits = limits();
+        let start = MotionState {
+            position: 0.0,
+            speed: 50.0,
+            acceleration: 0.0,
+        };
+        // behind and too close in front both need a stop and a move back
+        for target in [-10.0, 0.5] {
+            let profile = SCurveProfile::plan_position(start, target, &limits);
+            let samples = check(&profile, &limits);
+            assert_relative_eq!(profile.end().position, target, epsilon = 1e-9);
+            assert_relative_eq!(profile.end().speed, 0.0, epsilon = 1e-9);
+            assert!(samples.iter().any(|state| state.speed < 0.0));
+        }
+    }
+
+    #[test]
+    fn test_speed_change() {
+        let limits = limits();
+        let start = MotionState {
+            position: 0.0,
+            speed: 40.0,
+            acceleration: 0.0,
+        };
+        let profile = SCurveProfile::plan_speed(start, -200.0, &limits);
+        check(&profile, &limits);
+
+        assert_relative_eq!(profile.end().speed, -100.0, epsilon = 1e-9);
+        // continues at the end speed
+        let later = profile.sample(profile.get_duration() + 1.0);
+        assert_relative_eq!(
+            later.position,
+            profile.end().position - 100.0,
+            epsilon = 1e-9
+        );
+        assert_eq!(later.acceleration, 0.0);
+    }
+
+    #[test]
+    fn test_next_steady_time() {
+        let limits = limits();
+        let profile = SCurveProfile::plan_position(MotionState::at_rest(0.0), 100.0, &limits);
+        let (jerk_time, constant_time) = limits.ramp_times(100.0, 1000.0);
+        let accelerated = 2.0f64.mul_add(jerk_time, constant_time);
+
+        assert_eq!(profile.next_steady_time(0.0), 0.0);
+        // during the acceleration the next steady point is the cruise
+        assert_relative_eq!(profile.next_steady_time(0.01), accelerated, epsilon = 1e-12);
+        assert_relative_eq!(
+            profile.sample(accelerated).acceleration,
+            0.0,
+            epsilon = 1e-9
+        );
+        // anywhere in the cruise
+        assert_eq!(
+            profile.next_steady_time(accelerated + 0.1),
+            accelerated + 0.1
+        );
+        assert_eq!(
+            profile.next_steady_time(profile.get_duration() + 1.0),
+            profile.get_duration() + 1.0
+        );
+    }
+}
@@ -570,4 +570,71 @@ mod tests {
             epsilon = EPSILON
         );
     }
+
+    mod properties {
+        use super::*;
+        use crate::test_support::{
+            check_round_trip, steps_per_revolution, velocity, wheel_diameter,
+        };
+        use proptest::prelude::*;
+        use uom::si::angular_velocity::radian_per_second;
+
+        proptest! {
+            #[test]
+            fn velocity_round_trips_through_angular_velocity(
+                steps in steps_per_revolution(),
+                diameter in wheel_diameter(),
+                velocity in velocity(10.0),
+            ) {
+                let converter = LinearStepConverter::from_diameter(steps, diameter);
+                let angular_velocity = converter.velocity_to_angular_velocity(velocity);
+                let round_trip = converter.angular_velocity_to_velocity(angular_velocity);
+                check_round_trip(
+                    round_trip.get::<meter_per_second>(),
+                    velocity.get::<meter_per_second>(),
+                )?;
+            }
+
+            #[test]
+            fn velocity_round_trips_through_steps(
+                steps in steps_per_revolution(),
+                diameter in wheel_diameter(),
+                velocity in velocity(10.0),
+            ) {
+                let converter = LinearStepConverter::from_diameter(steps, diameter);
+                let round_trip = converter.steps_to_velocity(converter.velocity_to_steps(velocity));
+                check_round_trip(
+                    round_trip.get::<meter_per_second>(),
+                    velocity.get::<meter_per_second>(),
+                )?;
+            }
+
+            #[test]
+            fn distance_round_trips_through_steps(
+                steps in steps_per_revolution(),
+                diameter in wheel_diameter(),
+                distance in -100.0..100.0f64,
+            ) {
+                let converter = LinearStepConverter::from_diameter(steps, diameter);
+                let distance = Length::new::<meter>(distance);
+                let round_trip = converter.steps_to_distance(converter.distance_to_steps(distance));
+                check_round_trip(round_trip.get::<meter>(), distance.get::<meter>())?;
+            }
+
+            #[test]
+            fn angular_velocity_scales_with_velocity(
+                steps in steps_per_revolution(),
+                diameter in wheel_diameter(),
+                velocity in velocity(10.0),
+            ) {
+                // one revolution covers the circumference, independent of the step resolution
+                let converter = LinearStepConverter::from_diameter(steps, diameter);
+                let angular_velocity = converter.velocity_to_angular_velocity(velocity);
+                check_round_trip(
+                    angular_velocity.get::<radian_per_second>() * converter.radius().get::<meter>(),
+                    velocity.get::<meter_per_second>(),
+                )?;
+            }
+        }
+    }
 }
@@ -0,0 +1,124 @@
+use std::{
+    fmt::Debug,
+    sync::{Arc, LazyLock, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Source of the current time for controllers and trackers.
+///
+/// Production code uses [`SystemClock`], tests inject a [`ManualClock`] to step time
+/// deterministically instead of sleeping.
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// Monotonic system time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Clock that only moves when advanced, clones share the same time.
+///
+/// # Examples
+///
+/// ```rust
+/// use control_core::helpers::clock::{Clock, ManualClock};
+/// use std::time::Duration;
+///
+/// let clock = ManualClock::new();
+/// let start = clock.now();
+/// clock.advance(Duration::from_millis(250));
+/// assert_eq!(clock.now() - start, Duration::from_millis(250));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(|e| e.into_inner());
+        *now += duration;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Start of the monotonic event time and the wall clock at that moment, in milliseconds
+static EPOCH: LazyLock<(Instant, u64)> = LazyLock::new(|| (Instant::now(), unix_millis()));
+
+/// Starts the monotonic event time, called once when the server starts
+pub fn init_monotonic_clock() {
+    LazyLock::force(&EPOCH);
+}
+
+/// Wall clock time in milliseconds since the unix epoch, jumps when the clock is set
+pub fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Milliseconds since the server started, never jumps
+///
+/// Unlike [`unix_millis`] this keeps counting evenly when NTP or an operator sets the clock,
+/// so intervals between events stay correct.
+pub fn monotonic_millis() -> u64 {
+    EPOCH.0.elapsed().as_millis() as u64
+}
+
+/// Microseconds of `t` on the time base of [`monotonic_millis`], zero for earlier instants
+pub fn monotonic_micros_at(t: Instant) -> u64 {
+    t.saturating_duration_since(EPOCH.0).as_micros() as u64
+}
+
+/// Wall clock time in milliseconds at which [`monotonic_millis`] was zero
+pub fn monotonic_epoch_millis() -> u64 {
+    EPOCH.1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_clock_clones_share_time() {
+        let clock = ManualClock::new();
+        let shared = clock.clone();
+        let start = clock.now();
+
+        assert_eq!(shared.now(), start);
+        shared.advance(Duration::from_secs(3));
+        assert_eq!(clock.now() - start, Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_monotonic_millis() {
+        let first = monotonic_millis();
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(monotonic_millis() >= first + 5);
+        assert!(monotonic_epoch_millis() <= unix_millis());
+    }
+}
@@ -1,3 +1,4 @@
+pub mod clock;
 pub mod compare_lists;
 pub mod hasher_serializer;
 pub mod hashing;
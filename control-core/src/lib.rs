@@ -1,3 +1,4 @@
+pub mod alarms;
 pub mod controllers;
 pub mod converters;
 pub mod downcast;
@@ -8,11 +9,15 @@ pub mod machines;
 pub mod modbus;
 pub mod realtime;
 pub mod rest;
+pub mod scope;
 pub mod serial;
 pub mod socketio;
 pub mod transmission;
 pub mod uom_extensions;
 
+#[cfg(any(test, feature = "proptest"))]
+pub mod test_support;
+
 #[cfg(feature = "video-streaming")]
 pub mod video_streaming;
 
@@ -2,12 +2,81 @@ use serde_json::Value;
 use smol::lock::Mutex;
 use std::sync::Arc;
 
+use crate::alarms::AlarmCondition;
+use crate::machines::identification::MachineCapabilities;
+use crate::machines::self_test::SelfTestReport;
 use crate::socketio::namespace::Namespace;
 
 pub trait MachineApi {
     fn api_mutate(&mut self, value: Value) -> Result<(), anyhow::Error>;
     fn api_event_namespace(&mut self) -> Arc<Mutex<Namespace>>;
 
+    /// Applies the machine specific section of a recipe
+    ///
+    /// Machines without recipe support reject every section.
+    fn api_apply_recipe(&mut self, section: Value) -> Result<(), anyhow::Error> {
+        let _ = section;
+        Err(anyhow::anyhow!(
+            "[{}::MachineApi::api_apply_recipe] Machine does not support recipes",
+            module_path!()
+        ))
+    }
+
+    /// Full configuration of the machine, e.g. targets, tolerances, gains and limits
+    ///
+    /// Imported by [`MachineApi::api_import_config`] on a machine of the same type.
+    fn api_export_config(&self) -> Result<Value, anyhow::Error> {
+        Err(anyhow::anyhow!(
+            "[{}::MachineApi::api_export_config] Machine does not support configuration export",
+            module_path!()
+        ))
+    }
+
+    /// Applies a configuration exported by [`MachineApi::api_export_config`]
+    fn api_import_config(&mut self, config: Value) -> Result<(), anyhow::Error> {
+        let _ = config;
+        Err(anyhow::anyhow!(
+            "[{}::MachineApi::api_import_config] Machine does not support configuration import",
+            module_path!()
+        ))
+    }
+
+    /// The mutation `name` as it would set the machine to its current state
+    ///
+    /// E.g. `{ "SetPullerTargetSpeed": 10.0 }` for a puller at 10 m/min. Bounds the
+    /// decrease-only mutations of a remote session, `None` if the machine doesn't report it.
+    fn api_current_mutation(&self, name: &str) -> Option<Value> {
+        let _ = name;
+        None
+    }
+
+    /// Hardware features of the machine, read once when it is created or bound again
+    fn api_capabilities(&self) -> MachineCapabilities {
+        MachineCapabilities::default()
+    }
+
+    /// Alarm conditions the machine currently has
+    ///
+    /// Polled by the alarm manager, which raises and clears the alarms.
+    fn api_alarms(&self) -> Vec<AlarmCondition> {
+        Vec::new()
+    }
+
+    /// Starts the self test, non-destructive checks of the drives, devices and wiring
+    ///
+    /// Checks needing feedback, e.g. a micro-move of a motor, run over the following cycles.
+    fn api_run_self_test(&mut self) -> Result<(), anyhow::Error> {
+        Err(anyhow::anyhow!(
+            "[{}::MachineApi::api_run_self_test] Machine does not support self tests",
+            module_path!()
+        ))
+    }
+
+    /// Report of the last self test started by [`MachineApi::api_run_self_test`]
+    fn api_self_test_report(&self) -> Option<SelfTestReport> {
+        None
+    }
+
     /// Returns a list of available video stream identifiers for this machine
     #[cfg(feature = "video-streaming")]
     fn api_video_streams(&self) -> Vec<String> {
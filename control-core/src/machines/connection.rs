@@ -1,7 +1,10 @@
 use crate::downcast::Downcast;
-use crate::machines::identification::MachineIdentificationUnique;
+use crate::machines::identification::{MachineCapabilities, MachineIdentificationUnique};
 use crate::machines::manager::MachineManager;
-use crate::socketio::{event::GenericEvent, namespace::Namespace};
+use crate::socketio::{
+    event::GenericEvent, namespace::Namespace, namespace_id::NamespaceId, snapshot::SnapshotCell,
+};
+use schemars::JsonSchema;
 use serde::Serialize;
 use smol::block_on;
 use smol::lock::RwLock;
@@ -21,6 +24,9 @@ where
     Error(anyhow::Error),
     Disconnected,
     Connected(Arc<Mutex<M>>),
+    /// Hardware of the machine is gone, the machine keeps its configuration
+    /// and is bound again once the hardware comes back
+    Degraded(Arc<Mutex<M>>),
 }
 
 #[derive(Debug)]
@@ -30,6 +36,10 @@ where
 {
     pub machine_connection: MachineConnection<M>,
     pub namespace: Arc<Mutex<Namespace>>,
+    /// Latest events of the namespace, read without waiting for the act loop
+    pub snapshot: Arc<SnapshotCell>,
+    /// Capabilities of the machine last connected, kept while it is disconnected
+    pub capabilities: MachineCapabilities,
 }
 
 pub type MachineConnectionGeneric = MachineConnection<dyn Machine>;
@@ -40,6 +50,7 @@ impl<M: Machine> Clone for MachineConnection<M> {
     fn clone(&self) -> Self {
         match self {
             Self::Connected(m) => Self::Connected(m.clone()),
+            Self::Degraded(m) => Self::Degraded(m.clone()),
             Self::Error(e) => Self::Error(anyhow!(e.to_string())),
             Self::Disconnected => Self::Disconnected,
         }
@@ -51,6 +62,9 @@ impl<M: Machine + ?Sized> MachineConnection<M> {
         match self {
             MachineConnection::Error(err) => Some(anyhow!(err.to_string())),
             Self::Disconnected => Some(anyhow!("Machine is disconnected")),
+            Self::Degraded(_) => Some(anyhow!(
+                "Machine is degraded, waiting for its hardware to reconnect"
+            )),
             _ => None,
         }
     }
@@ -64,17 +78,34 @@ impl<M: Machine + ?Sized> MachineConnection<M> {
 }
 
 impl<M: Machine + ?Sized> MachineSlot<M> {
-    pub fn new(socket_queue_tx: Sender<(SocketRef, Arc<GenericEvent>)>) -> Self {
-        let namespace = Namespace::new(socket_queue_tx);
+    pub fn new(
+        socket_queue_tx: Sender<(SocketRef, Arc<GenericEvent>)>,
+        machine_identification: MachineIdentificationUnique,
+    ) -> Self {
+        // machines emit in every act cycle, the loop flushes once per cycle
+        let namespace = Namespace::new_batching(socket_queue_tx)
+            .with_id(NamespaceId::Machine(machine_identification));
         Self {
             machine_connection: MachineConnection::Disconnected,
+            snapshot: namespace.snapshot.clone(),
             namespace: Arc::new(Mutex::new(namespace)),
+            capabilities: MachineCapabilities::default(),
         }
     }
 
+    /// Connects a created or rebound machine and reads its capabilities
+    pub fn connect(&mut self, machine: Arc<Mutex<M>>) {
+        self.capabilities = machine.lock_blocking().api_capabilities();
+        self.machine_connection = MachineConnection::Connected(machine);
+    }
+
     pub const fn is_connected(&self) -> bool {
         matches!(self.machine_connection, MachineConnection::Connected(_))
     }
+
+    pub const fn is_degraded(&self) -> bool {
+        matches!(self.machine_connection, MachineConnection::Degraded(_))
+    }
 }
 
 pub trait CrossConnectableMachine<
@@ -102,7 +133,7 @@ pub struct MachineCrossConnection<
                                                        // to an unknown type. See also https://github.com/qitechgmbh/control/pull/625#discussion_r2379566315
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, JsonSchema)]
 pub struct MachineCrossConnectionState {
     /// Connected Machine
     pub machine_identification_unique: Option<MachineIdentificationUnique>,
@@ -179,6 +210,17 @@ impl<F: CrossConnectableMachine<F, T>, T: CrossConnectableMachine<T, F>>
         }
     }
 
+    /// Runs `f` on the connected machine, `None` if no machine is connected or available
+    pub fn with_connected_machine<R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        let slot = self.connected_machine.upgrade()?;
+        let slot = slot.lock_blocking();
+
+        match &slot.machine_connection {
+            MachineConnection::Connected(machine) => Some(f(&mut machine.lock_blocking())),
+            _ => None,
+        }
+    }
+
     pub fn is_connected(&self) -> bool {
         let arc = self.connected_machine.upgrade();
         arc.is_some()
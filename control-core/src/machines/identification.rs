@@ -1,10 +1,11 @@
 use std::fmt::Display;
 
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 
 /// Identifies a spacifi machine
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 pub struct MachineIdentificationUnique {
     pub machine_identification: MachineIdentification,
     pub serial: u16,
@@ -28,7 +29,7 @@ impl Display for MachineIdentificationUnique {
 }
 
 /// Identifies a machine
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 pub struct MachineIdentification {
     pub vendor: u16,
     pub machine: u16,
@@ -41,6 +42,44 @@ impl MachineIdentification {
     }
 }
 
+/// Hardware features of a machine, so clients adapt to variants of a machine type
+///
+/// Serialized as the bitset, bits 0 to 2 are the flags, bits 8 and 9 the number of axes of
+/// the laser.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(transparent)]
+pub struct MachineCapabilities(pub u32);
+
+impl MachineCapabilities {
+    pub const HAS_TRAVERSE: u32 = 1 << 0;
+    pub const HAS_CUTTER: u32 = 1 << 1;
+    pub const HAS_TENSION_SENSOR: u32 = 1 << 2;
+    const LASER_AXES_SHIFT: u32 = 8;
+    const LASER_AXES_MASK: u32 = 0b11 << Self::LASER_AXES_SHIFT;
+
+    pub const fn with(self, flag: u32, set: bool) -> Self {
+        match set {
+            true => Self(self.0 | flag),
+            false => Self(self.0 & !flag),
+        }
+    }
+
+    pub const fn has(self, flag: u32) -> bool {
+        self.0 & flag == flag
+    }
+
+    /// Up to 3 axes, more are stored as 3
+    pub const fn with_laser_axes(self, axes: u8) -> Self {
+        let axes = if axes > 3 { 3 } else { axes as u32 };
+        Self((self.0 & !Self::LASER_AXES_MASK) | (axes << Self::LASER_AXES_SHIFT))
+    }
+
+    /// 0 for machines without a laser
+    pub const fn laser_axes(self) -> u8 {
+        ((self.0 & Self::LASER_AXES_MASK) >> Self::LASER_AXES_SHIFT) as u8
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct DeviceMachineIdentification {
     pub machine_identification_unique: MachineIdentificationUnique,
@@ -110,4 +149,29 @@ pub struct DeviceHardwareIdentificationEthercat {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct DeviceHardwareIdentificationSerial {
     pub path: String,
+    /// Serial number of the USB adapter, identifies the device independent of its path
+    #[serde(default)]
+    pub usb_serial_number: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_machine_capabilities() {
+        let winder = MachineCapabilities::default()
+            .with(MachineCapabilities::HAS_TRAVERSE, true)
+            .with(MachineCapabilities::HAS_TENSION_SENSOR, true)
+            .with(MachineCapabilities::HAS_CUTTER, false);
+        assert!(winder.has(MachineCapabilities::HAS_TRAVERSE));
+        assert!(!winder.has(MachineCapabilities::HAS_CUTTER));
+        assert_eq!(winder.laser_axes(), 0);
+        assert_eq!(serde_json::to_string(&winder).unwrap(), "5");
+
+        let laser = MachineCapabilities::default().with_laser_axes(2);
+        assert_eq!(laser.laser_axes(), 2);
+        assert_eq!(laser.0, 0x200);
+        assert_eq!(laser.with_laser_axes(7).laser_axes(), 3);
+    }
 }
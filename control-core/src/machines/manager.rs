@@ -17,6 +17,7 @@ use crate::{
         },
         identification::MachineIdentificationUnique,
         new::{MachineNewHardware, MachineNewHardwareEthercat, MachineNewParams},
+        values::MachineValueBus,
     },
     serial::SerialDevice,
     socketio::event::GenericEvent,
@@ -30,6 +31,8 @@ use std::{
 pub struct MachineManager {
     pub ethercat_machines: HashMap<MachineIdentificationUnique, Arc<Mutex<MachineSlotGeneric>>>,
     pub serial_machines: HashMap<MachineIdentificationUnique, Arc<Mutex<MachineSlotGeneric>>>,
+    /// Values the machines publish for each other
+    pub values: Arc<MachineValueBus>,
 }
 
 impl Default for MachineManager {
@@ -43,6 +46,7 @@ impl MachineManager {
         Self {
             ethercat_machines: HashMap::new(),
             serial_machines: HashMap::new(),
+            values: Arc::new(MachineValueBus::new()),
         }
     }
 
@@ -87,12 +91,13 @@ impl MachineManager {
                 socket_queue_tx: socket_queue_tx.clone(),
                 machine_manager: machine_manager.clone(),
                 namespace: slot.namespace.clone(),
+                values: self.values.clone(),
             });
 
-            slot.machine_connection = match new_machine {
-                Err(err) => MachineConnectionGeneric::Error(err),
-                Ok(machine) => MachineConnectionGeneric::Connected(machine),
-            };
+            match new_machine {
+                Err(err) => slot.machine_connection = MachineConnectionGeneric::Error(err),
+                Ok(machine) => slot.connect(machine),
+            }
         }
     }
 
@@ -105,7 +110,10 @@ impl MachineManager {
             return slot;
         }
 
-        let slot = Arc::new(Mutex::new(MachineSlot::new(socket_queue_tx)));
+        let slot = Arc::new(Mutex::new(MachineSlot::new(
+            socket_queue_tx,
+            machine_identification.clone(),
+        )));
         self.ethercat_machines
             .insert(machine_identification, slot.clone());
 
@@ -146,18 +154,41 @@ impl MachineManager {
         let slot = self.get_or_create_slot(socket_queue_tx.clone(), machine_identification);
         let mut slot = slot.lock_blocking();
 
-        let new_machine = machine_registry.new_machine(&MachineNewParams {
-            device_group: &vec![device_identification_identified.clone()],
+        let device_group = vec![device_identification_identified.clone()];
+        let params = MachineNewParams {
+            device_group: &device_group,
             hardware: &MachineNewHardware::Serial(&hardware),
             socket_queue_tx,
             machine_manager: machine_manager.clone(),
             namespace: slot.namespace.clone(),
-        });
+            values: self.values.clone(),
+        };
 
-        slot.machine_connection = match new_machine {
-            Err(err) => MachineConnectionGeneric::Error(err),
-            Ok(machine) => MachineConnectionGeneric::Connected(machine),
+        // a degraded machine keeps its configuration if it can be bound to the new device
+        let degraded = match &slot.machine_connection {
+            MachineConnectionGeneric::Degraded(machine) => Some(machine.clone()),
+            _ => None,
         };
+        if let Some(machine) = degraded {
+            let rebound = machine.lock_blocking().rebind(&params);
+            match rebound {
+                Ok(()) => {
+                    slot.connect(machine);
+                    tracing::info!("Rebound serial machine {:?}", slot);
+                    return;
+                }
+                Err(err) => tracing::warn!(
+                    "Failed to rebind serial machine {:?}, creating it again: {:?}",
+                    device_identification_identified,
+                    err
+                ),
+            }
+        }
+
+        match machine_registry.new_machine(&params) {
+            Err(err) => slot.machine_connection = MachineConnectionGeneric::Error(err),
+            Ok(machine) => slot.connect(machine),
+        }
 
         tracing::info!("Adding serial machine {:?}", slot);
     }
@@ -178,9 +209,13 @@ impl MachineManager {
             .device_machine_identification
             .machine_identification_unique;
 
-        if let Some(slot) = self.serial_machines.get(machine_identification_unique) {
+        if let Some(slot) = self.get(machine_identification_unique) {
             let mut slot = slot.lock_blocking();
-            slot.machine_connection = MachineConnection::Disconnected;
+            // keep the machine around so it can be bound again once the device comes back
+            slot.machine_connection = match slot.machine_connection.to_machine() {
+                Some(machine) => MachineConnection::Degraded(machine),
+                None => MachineConnection::Disconnected,
+            };
         }
     }
 
@@ -210,6 +245,7 @@ impl MachineManager {
             match connection {
                 MachineConnectionGeneric::Error(_) => None,
                 MachineConnectionGeneric::Disconnected => None,
+                MachineConnectionGeneric::Degraded(_) => None,
                 MachineConnectionGeneric::Connected(machine) => Some(Arc::downgrade(&machine)),
             }
         });
@@ -12,8 +12,13 @@ pub mod manager;
 pub mod manager_iter;
 pub mod new;
 pub mod registry;
+pub mod schema;
+pub mod self_test;
+pub mod values;
 
-pub trait Machine: MachineAct + MachineNewTrait + MachineApi + Any + Debug + Send + Sync {
+pub trait Machine:
+    MachineAct + MachineNewTrait + MachineApi + AnyGetters + Any + Debug + Send + Sync
+{
     fn get_machine_identification_unique(&self) -> MachineIdentificationUnique;
 }
 
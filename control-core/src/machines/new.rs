@@ -1,5 +1,8 @@
 use crate::{
-    machines::{identification::MachineIdentificationUnique, manager::MachineManager},
+    machines::{
+        identification::MachineIdentificationUnique, manager::MachineManager,
+        values::MachineValueBus,
+    },
     serial::SerialDevice,
     socketio::namespace::Namespace,
 };
@@ -26,10 +29,42 @@ pub trait MachineNewTrait {
     fn new(params: &MachineNewParams<'_, '_, '_, '_, '_, '_, '_>) -> Result<Self, Error>
     where
         Self: Sized;
+
+    /// Binds a degraded machine to its hardware again after it re-enumerated
+    ///
+    /// The machine keeps its configuration. Machines that don't support this are created
+    /// again with [`MachineNewTrait::new`].
+    fn rebind(
+        &mut self,
+        _params: &MachineNewParams<'_, '_, '_, '_, '_, '_, '_>,
+    ) -> Result<(), Error> {
+        Err(anyhow::anyhow!(
+            "Machine does not support rebinding its hardware"
+        ))
+    }
 }
 
 pub trait MachineAct {
     fn act(&mut self, now: Instant);
+
+    /// Brings the actuators into a safe state before the server exits
+    ///
+    /// Called repeatedly during shutdown while the control loop keeps calling [`MachineAct::act`],
+    /// so motors ramp down along their usual acceleration and jerk limits.
+    /// Returns `true` once the machine is safe.
+    fn act_safe_stop(&mut self) -> bool {
+        true
+    }
+
+    /// Motion control of machines driven by a dedicated motion thread
+    ///
+    /// Called by that thread at its own period, [`MachineAct::act`] leaves the motion control
+    /// to it if [`MachineAct::has_dedicated_motion`] is set.
+    fn act_motion(&mut self, _now: Instant) {}
+
+    fn has_dedicated_motion(&self) -> bool {
+        false
+    }
 }
 
 pub struct MachineNewParams<
@@ -58,6 +93,8 @@ pub struct MachineNewParams<
     pub machine_manager: Weak<RwLock<MachineManager>>,
 
     pub namespace: Arc<Mutex<Namespace>>,
+    /// Values the machines publish for each other
+    pub values: Arc<MachineValueBus>,
 }
 
 impl MachineNewParams<'_, '_, '_, '_, '_, '_, '_> {
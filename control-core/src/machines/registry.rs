@@ -1,13 +1,57 @@
-use super::{Machine, identification::MachineIdentification, new::MachineNewParams};
+use super::{
+    Machine,
+    identification::MachineIdentification,
+    new::MachineNewParams,
+    schema::{MachineApiSchema, MachineApiTypes},
+};
 use anyhow::Error;
 use smol::lock::Mutex;
-use std::{any::TypeId, collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc};
+
+pub use inventory;
 
 pub type MachineNewClosure =
     Box<dyn Fn(&MachineNewParams) -> Result<Arc<Mutex<dyn Machine>>, Error> + Send + Sync>;
 
+struct MachineRegistration {
+    new: MachineNewClosure,
+    /// `None` for machine types registered without [`MachineApiTypes`]
+    api_schema: Option<fn() -> MachineApiSchema>,
+}
+
+/// Machine constructors by the vendor and machine id of the machine type
 pub struct MachineRegistry {
-    type_map: HashMap<TypeId, (MachineIdentification, MachineNewClosure)>,
+    machines: HashMap<MachineIdentification, MachineRegistration>,
+}
+
+/// Set of machine types registered together, e.g. the machines of one vendor
+///
+/// Crates outside the server add their machine types with [`submit_machine_plugin!`], the
+/// server registers all submitted plugins on startup.
+///
+/// [`submit_machine_plugin!`]: crate::submit_machine_plugin
+pub trait MachinePlugin: Sync {
+    fn name(&self) -> &'static str;
+    fn register(&self, registry: &mut MachineRegistry);
+}
+
+/// A plugin submitted with [`submit_machine_plugin!`](crate::submit_machine_plugin)
+pub struct SubmittedMachinePlugin(pub &'static dyn MachinePlugin);
+
+inventory::collect!(SubmittedMachinePlugin);
+
+/// Submits a [`MachinePlugin`] from any crate linked into the server
+///
+/// ```ignore
+/// control_core::submit_machine_plugin!(AcmeMachines);
+/// ```
+#[macro_export]
+macro_rules! submit_machine_plugin {
+    ($plugin:expr) => {
+        $crate::machines::registry::inventory::submit! {
+            $crate::machines::registry::SubmittedMachinePlugin(&$plugin)
+        }
+    };
 }
 
 impl Default for MachineRegistry {
@@ -19,26 +63,70 @@ impl Default for MachineRegistry {
 impl MachineRegistry {
     pub fn new() -> Self {
         Self {
-            type_map: HashMap::new(),
+            machines: HashMap::new(),
         }
     }
 
-    pub fn register<T: Machine + 'static>(
+    pub fn register<T: Machine + MachineApiTypes + 'static>(
         &mut self,
         machine_identficiation: MachineIdentification,
     ) {
-        self.type_map.insert(
-            TypeId::of::<T>(),
-            (
-                machine_identficiation.clone(),
+        self.register_with(machine_identficiation.clone(), T::new);
+        if let Some(registration) = self.machines.get_mut(&machine_identficiation) {
+            registration.api_schema = Some(T::api_schema);
+        }
+    }
+
+    /// Registers a machine type with a custom constructor instead of [`super::new::MachineNewTrait`]
+    pub fn register_with<T, F>(&mut self, machine_identficiation: MachineIdentification, new_fn: F)
+    where
+        T: Machine + 'static,
+        F: Fn(&MachineNewParams) -> Result<T, Error> + Send + Sync + 'static,
+    {
+        self.machines.insert(
+            machine_identficiation,
+            MachineRegistration {
                 // create a machine construction closure
-                Box::new(|machine_new_params| {
-                    Ok(Arc::new(Mutex::new(T::new(machine_new_params)?)))
+                new: Box::new(move |machine_new_params| {
+                    Ok(Arc::new(Mutex::new(new_fn(machine_new_params)?)))
                 }),
-            ),
+                api_schema: None,
+            },
         );
     }
 
+    /// Registers the machine types of a plugin
+    ///
+    /// Fails without registering anything if the plugin registers a machine type that is
+    /// already registered, plugins can't replace machine types.
+    pub fn register_plugin(&mut self, plugin: &dyn MachinePlugin) -> Result<(), Error> {
+        let mut plugin_registry = Self::new();
+        plugin.register(&mut plugin_registry);
+
+        if let Some(machine_identification) = plugin_registry
+            .machines
+            .keys()
+            .find(|machine_identification| self.machines.contains_key(machine_identification))
+        {
+            return Err(anyhow::anyhow!(
+                "[{}::MachineRegistry::register_plugin] Plugin {} registers {:?} which is already registered",
+                module_path!(),
+                plugin.name(),
+                machine_identification
+            ));
+        }
+
+        self.machines.extend(plugin_registry.machines);
+        Ok(())
+    }
+
+    /// Plugins submitted with [`submit_machine_plugin!`](crate::submit_machine_plugin)
+    pub fn submitted_plugins() -> impl Iterator<Item = &'static dyn MachinePlugin> {
+        inventory::iter::<SubmittedMachinePlugin>
+            .into_iter()
+            .map(|submitted| submitted.0)
+    }
+
     pub fn new_machine(
         &self,
         machine_new_params: &MachineNewParams,
@@ -53,22 +141,33 @@ impl MachineRegistry {
                     module_path!()
                 ))?;
 
-        // find machine new function by comparing MachineIdentification
-        let (_, machine_new_closure) = self
-            .type_map
-            .values()
-            .find(|(mi, _)| {
-                mi == &device_identification
+        // find machine new function by MachineIdentification
+        let registration = self
+            .machines
+            .get(
+                &device_identification
                     .device_machine_identification
                     .machine_identification_unique
-                    .machine_identification
-            })
+                    .machine_identification,
+            )
             .ok_or(anyhow::anyhow!(
                 "[{}::MachineConstructor::new_machine] Machine not found",
                 module_path!()
             ))?;
 
         // call machine new function by reference
-        (machine_new_closure)(machine_new_params)
+        (registration.new)(machine_new_params)
+    }
+
+    /// Mutation and event schemas of all registered machine types that declare them
+    pub fn api_schemas(&self) -> Vec<(MachineIdentification, MachineApiSchema)> {
+        self.machines
+            .iter()
+            .filter_map(|(mi, registration)| {
+                registration
+                    .api_schema
+                    .map(|api_schema| (mi.clone(), api_schema()))
+            })
+            .collect()
     }
 }
@@ -0,0 +1,51 @@
+use schemars::{JsonSchema, schema_for};
+
+pub use schemars::schema::RootSchema;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Schemas of the mutations a machine type accepts and the events it emits
+#[derive(Serialize, Debug, Clone)]
+pub struct MachineApiSchema {
+    pub mutations: RootSchema,
+    /// Event data by event name
+    pub events: BTreeMap<String, RootSchema>,
+}
+
+/// Schemas of the events in a namespace event enum, implemented by the `NamespaceEvents` derive
+pub trait NamespaceEventsSchema {
+    fn event_schemas() -> BTreeMap<String, RootSchema>;
+}
+
+/// Typed API of a machine type
+///
+/// Mutations are deserialized into [`MachineApiTypes::Mutation`], so the exported schema is the
+/// one the server actually accepts.
+pub trait MachineApiTypes {
+    type Mutation: DeserializeOwned + JsonSchema;
+    type Events: NamespaceEventsSchema;
+
+    /// Deserializes a mutation request body
+    fn parse_mutation(value: Value) -> Result<Self::Mutation, anyhow::Error> {
+        serde_json::from_value(value).map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::MachineApiTypes::parse_mutation] Invalid mutation\n{:?}",
+                module_path!(),
+                e
+            )
+        })
+    }
+
+    fn api_schema() -> MachineApiSchema {
+        MachineApiSchema {
+            mutations: schema_for!(Self::Mutation),
+            events: Self::Events::event_schemas(),
+        }
+    }
+}
+
+/// Schema of the data of one event, used by the `NamespaceEvents` derive
+pub fn event_schema<T: JsonSchema>() -> RootSchema {
+    schema_for!(T)
+}
@@ -0,0 +1,70 @@
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfTestResult {
+    Passed,
+    Failed,
+    /// Not wired or not configured, e.g. a loopback without an assigned input
+    Skipped,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SelfTestCheck {
+    pub name: String,
+    pub result: SelfTestResult,
+    /// What was measured, e.g. `moved 48 steps`
+    pub detail: String,
+}
+
+/// Non-destructive checks of a machine during commissioning
+///
+/// Checks needing feedback from the hardware run over several cycles, the report is only
+/// complete once `finished` is set.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct SelfTestReport {
+    pub checks: Vec<SelfTestCheck>,
+    pub finished: bool,
+    /// No check failed, only set once finished
+    pub passed: bool,
+}
+
+impl SelfTestReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, name: &str, result: SelfTestResult, detail: impl Into<String>) {
+        self.checks.push(SelfTestCheck {
+            name: name.to_string(),
+            result,
+            detail: detail.into(),
+        });
+    }
+
+    pub fn finish(&mut self) {
+        self.finished = true;
+        self.passed = self
+            .checks
+            .iter()
+            .all(|check| check.result != SelfTestResult::Failed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test_report() {
+        let mut report = SelfTestReport::new();
+        report.add("inverter", SelfTestResult::Passed, "responds");
+        report.add("loopback", SelfTestResult::Skipped, "not wired");
+        assert!(!report.passed);
+        report.finish();
+        assert!(report.finished && report.passed);
+
+        report.add("puller", SelfTestResult::Failed, "moved 0 steps");
+        report.finish();
+        assert!(!report.passed);
+    }
+}
@@ -0,0 +1,298 @@
+use super::identification::MachineIdentificationUnique;
+use std::{
+    any::Any,
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+use uom::si::f64::{AngularVelocity, Length, ThermodynamicTemperature, Velocity};
+
+/// Typed identifier of a value machines publish on the [`MachineValueBus`]
+#[derive(Debug)]
+pub struct MachineValueKey<T> {
+    pub id: &'static str,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T> MachineValueKey<T> {
+    pub const fn new(id: &'static str) -> Self {
+        Self {
+            id,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for MachineValueKey<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for MachineValueKey<T> {}
+
+/// Measured filament diameter, e.g. of a laser
+pub const DIAMETER: MachineValueKey<Length> = MachineValueKey::new("diameter");
+/// Speed the filament is pulled with, e.g. by the puller of a winder
+pub const LINE_SPEED: MachineValueKey<Velocity> = MachineValueKey::new("line_speed");
+/// Whether the diameter is in tolerance, only published while a laser monitors the tolerance
+pub const IN_TOLERANCE: MachineValueKey<bool> = MachineValueKey::new("in_tolerance");
+/// Speed of an extruder screw
+pub const SCREW_SPEED: MachineValueKey<AngularVelocity> = MachineValueKey::new("screw_speed");
+/// Hottest water temperature of a water bath, e.g. of an aquapath
+pub const WATER_TEMPERATURE: MachineValueKey<ThermodynamicTemperature> =
+    MachineValueKey::new("water_temperature");
+/// Whether the key switch of remote sessions is turned, published by the machine wired to it
+pub const REMOTE_KEY_SWITCH: MachineValueKey<bool> = MachineValueKey::new("remote_key_switch");
+/// Target diameter of the line, published by a winder when a recipe is applied
+pub const DIAMETER_TARGET: MachineValueKey<DiameterTarget> =
+    MachineValueKey::new("diameter_target");
+
+/// Target diameter a machine sets for the line, lasers adopt it as their target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiameterTarget {
+    pub diameter: Length,
+    /// lower and higher tolerance, `None` keeps the tolerances of the laser
+    pub tolerances: Option<(Length, Length)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MachineValueSample<T> {
+    pub value: T,
+    pub published: Instant,
+}
+
+impl<T> MachineValueSample<T> {
+    pub fn age(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.published)
+    }
+
+    pub fn is_stale(&self, now: Instant, max_age: Duration) -> bool {
+        self.age(now) > max_age
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    value: Box<dyn Any + Send + Sync>,
+    published: Instant,
+}
+
+/// Latest values machines publish for other machines, keyed by machine and value id
+///
+/// Couples machines like laser and winder without sharing the machines themselves. A value
+/// keeps its publish time so readers can tell a disconnected source from a live one.
+#[derive(Debug, Default)]
+pub struct MachineValueBus {
+    values: RwLock<HashMap<(MachineIdentificationUnique, &'static str), Entry>>,
+}
+
+impl MachineValueBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn publish<T: Any + Send + Sync>(
+        &self,
+        machine: &MachineIdentificationUnique,
+        key: MachineValueKey<T>,
+        value: T,
+        now: Instant,
+    ) {
+        let Ok(mut values) = self.values.write() else {
+            return;
+        };
+        values.insert(
+            (machine.clone(), key.id),
+            Entry {
+                value: Box::new(value),
+                published: now,
+            },
+        );
+    }
+
+    /// Latest value of a machine, `None` if it never published the value
+    pub fn get<T: Any + Clone>(
+        &self,
+        machine: &MachineIdentificationUnique,
+        key: MachineValueKey<T>,
+    ) -> Option<MachineValueSample<T>> {
+        let values = self.values.read().ok()?;
+        let entry = values.get(&(machine.clone(), key.id))?;
+        Some(MachineValueSample {
+            value: entry.value.downcast_ref::<T>()?.clone(),
+            published: entry.published,
+        })
+    }
+
+    /// Most recent value any machine published under `key`
+    ///
+    /// For values a line has once, e.g. the screw speed of its extruder. With several
+    /// publishers the latest one wins, use [`MachineValueBus::get`] to pick a machine.
+    pub fn latest<T: Any + Clone>(&self, key: MachineValueKey<T>) -> Option<MachineValueSample<T>> {
+        let values = self.values.read().ok()?;
+        let entry = values
+            .iter()
+            .filter(|((_, id), _)| *id == key.id)
+            .map(|(_, entry)| entry)
+            .max_by_key(|entry| entry.published)?;
+        Some(MachineValueSample {
+            value: entry.value.downcast_ref::<T>()?.clone(),
+            published: entry.published,
+        })
+    }
+
+    /// Drops all values of a machine, e.g. when it was removed
+    pub fn remove_machine(&self, machine: &MachineIdentificationUnique) {
+        if let Ok(mut values) = self.values.write() {
+            values.retain(|(publisher, _), _| publisher != machine);
+        }
+    }
+
+    /// Subscribes to a value of another machine, older values than `max_age` count as stale
+    pub fn subscribe<T: Any + Clone>(
+        self: &Arc<Self>,
+        source: MachineIdentificationUnique,
+        key: MachineValueKey<T>,
+        max_age: Duration,
+    ) -> MachineValueSubscription<T> {
+        MachineValueSubscription {
+            bus: self.clone(),
+            source,
+            key,
+            max_age,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MachineValueReading<T> {
+    Fresh(T),
+    Stale {
+        value: T,
+        age: Duration,
+    },
+    /// The source never published the value
+    Missing,
+}
+
+impl<T> MachineValueReading<T> {
+    pub fn fresh(self) -> Option<T> {
+        match self {
+            Self::Fresh(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Value of another machine read in the act cycle of the subscriber
+#[derive(Debug, Clone)]
+pub struct MachineValueSubscription<T> {
+    bus: Arc<MachineValueBus>,
+    pub source: MachineIdentificationUnique,
+    pub key: MachineValueKey<T>,
+    pub max_age: Duration,
+}
+
+impl<T: Any + Clone> MachineValueSubscription<T> {
+    pub fn read(&self, now: Instant) -> MachineValueReading<T> {
+        match self.bus.get(&self.source, self.key) {
+            None => MachineValueReading::Missing,
+            Some(sample) if sample.is_stale(now, self.max_age) => MachineValueReading::Stale {
+                age: sample.age(now),
+                value: sample.value,
+            },
+            Some(sample) => MachineValueReading::Fresh(sample.value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machines::identification::MachineIdentification;
+    use uom::si::length::millimeter;
+
+    fn machine(serial: u16) -> MachineIdentificationUnique {
+        MachineIdentificationUnique {
+            machine_identification: MachineIdentification {
+                vendor: 1,
+                machine: 6,
+            },
+            serial,
+        }
+    }
+
+    #[test]
+    fn test_subscription_staleness() {
+        let bus = Arc::new(MachineValueBus::new());
+        let subscription = bus.subscribe(machine(1), DIAMETER, Duration::from_millis(100));
+        let start = Instant::now();
+        assert_eq!(subscription.read(start), MachineValueReading::Missing);
+
+        let diameter = Length::new::<millimeter>(1.75);
+        bus.publish(&machine(1), DIAMETER, diameter, start);
+        // values of other machines don't interfere
+        bus.publish(
+            &machine(2),
+            DIAMETER,
+            Length::new::<millimeter>(2.85),
+            start,
+        );
+        assert_eq!(
+            subscription.read(start + Duration::from_millis(50)),
+            MachineValueReading::Fresh(diameter)
+        );
+        assert_eq!(
+            subscription.read(start + Duration::from_millis(150)),
+            MachineValueReading::Stale {
+                value: diameter,
+                age: Duration::from_millis(150)
+            }
+        );
+
+        bus.remove_machine(&machine(1));
+        assert_eq!(subscription.read(start), MachineValueReading::Missing);
+        assert!(bus.get(&machine(2), DIAMETER).is_some());
+    }
+
+    #[test]
+    fn test_keys_are_typed() {
+        let bus = MachineValueBus::new();
+        bus.publish(
+            &machine(1),
+            LINE_SPEED,
+            Velocity::new::<uom::si::velocity::meter_per_second>(1.0),
+            Instant::now(),
+        );
+        // same id with another type doesn't downcast
+        let wrong: MachineValueKey<Length> = MachineValueKey::new(LINE_SPEED.id);
+        assert!(bus.get(&machine(1), wrong).is_none());
+        assert!(bus.get(&machine(1), LINE_SPEED).is_some());
+    }
+
+    #[test]
+    fn test_latest() {
+        let bus = MachineValueBus::new();
+        let start = Instant::now();
+        assert!(bus.latest(DIAMETER).is_none());
+
+        bus.publish(
+            &machine(1),
+            DIAMETER,
+            Length::new::<millimeter>(1.75),
+            start,
+        );
+        bus.publish(
+            &machine(2),
+            DIAMETER,
+            Length::new::<millimeter>(2.85),
+            start + Duration::from_millis(10),
+        );
+        let latest = bus.latest(DIAMETER).unwrap();
+        assert_eq!(latest.value, Length::new::<millimeter>(2.85));
+        assert_eq!(latest.published, start + Duration::from_millis(10));
+        assert!(bus.latest(LINE_SPEED).is_none());
+    }
+}
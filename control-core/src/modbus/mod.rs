@@ -165,7 +165,12 @@ fn check_crc(raw_data: &Vec<u8>) -> Result<bool, Error> {
     Ok(expected_crc == actual_crc)
 }
 
-fn validate_modbus_response(raw_data: Vec<u8>) -> Result<Vec<u8>, Error> {
+/// Checks the CRC of a raw frame, `false` for frames too short to carry one
+pub fn is_crc_valid(raw_data: &[u8]) -> bool {
+    raw_data.len() >= 5 && check_crc(&raw_data.to_vec()).unwrap_or(false)
+}
+
+pub fn validate_modbus_response(raw_data: Vec<u8>) -> Result<Vec<u8>, Error> {
     if raw_data.is_empty() {
         return Err(anyhow::anyhow!("Error: Response is Empty!"));
     }
@@ -283,6 +288,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_is_crc_valid() {
+        let mut response_raw = vec![
+            0x11, 0x03, 0x06, 0x17, 0x70, 0x0b, 0xb8, 0x03, 0xe8, 0x2c, 0xe6,
+        ];
+        assert!(is_crc_valid(&response_raw));
+
+        response_raw[3] = 0x18;
+        assert!(!is_crc_valid(&response_raw));
+        assert!(!is_crc_valid(&[0x11, 0x03]));
+    }
+
     #[test]
     fn test_modbus_request_to_vec() {
         // Create a ModbusRequest for reading holding registers
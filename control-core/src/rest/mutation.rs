@@ -8,6 +8,8 @@ use crate::machines::identification::MachineIdentificationUnique;
 pub struct MutationResponse {
     pub success: bool,
     pub error: Option<String>,
+    /// What kind of error, so a client can tell the operator what to do about it
+    pub kind: Option<MutationErrorKind>,
 }
 
 impl MutationResponse {
@@ -15,16 +17,67 @@ impl MutationResponse {
         Self {
             success: true,
             error: None,
+            kind: None,
         }
     }
-    pub const fn error(error: String) -> Self {
+    pub const fn error(kind: MutationErrorKind, error: String) -> Self {
         Self {
             success: false,
             error: Some(error),
+            kind: Some(kind),
         }
     }
 }
 
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MutationErrorKind {
+    /// The mutation is unknown or a value is invalid, sending it again won't help
+    Validation,
+    /// A safety condition blocks the mutation, e.g. an open guard
+    Interlock,
+    /// The machine can't take the mutation right now, e.g. while winding or disconnected
+    NotReady,
+    NotFound,
+    Unauthorized,
+    Forbidden,
+    /// Sent too often, can be sent again later
+    RateLimited,
+    /// The server failed, not the request
+    Internal,
+}
+
+/// Error of a mutation with its kind
+///
+/// Machines return it as `anyhow::Error` for errors that aren't
+/// [`MutationErrorKind::Validation`], which is assumed for all other errors.
+#[derive(Debug)]
+pub struct MutationError {
+    pub kind: MutationErrorKind,
+    pub message: String,
+}
+
+impl MutationError {
+    pub const fn new(kind: MutationErrorKind, message: String) -> Self {
+        Self { kind, message }
+    }
+
+    /// Kind of a mutation error returned by a machine
+    pub fn kind_of(error: &anyhow::Error) -> MutationErrorKind {
+        error
+            .downcast_ref::<Self>()
+            .map_or(MutationErrorKind::Validation, |error| error.kind)
+    }
+}
+
+impl std::fmt::Display for MutationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for MutationError {}
+
 #[derive(Debug, serde::Deserialize)]
 pub struct MachineMutationBody<T>
 where
@@ -49,3 +102,29 @@ pub struct MachineVideoStreamBody {
 pub struct VideoStreamListResponse {
     pub streams: Vec<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_kind() {
+        let interlock: anyhow::Error =
+            MutationError::new(MutationErrorKind::Interlock, "Guard is open".to_string()).into();
+        assert_eq!(
+            MutationError::kind_of(&interlock),
+            MutationErrorKind::Interlock
+        );
+        assert_eq!(interlock.to_string(), "Guard is open");
+        assert_eq!(
+            MutationError::kind_of(&anyhow::anyhow!("Unknown mutation")),
+            MutationErrorKind::Validation
+        );
+
+        let response = MutationResponse::error(MutationErrorKind::NotReady, "Winding".to_string());
+        assert_eq!(
+            serde_json::to_value(response).unwrap(),
+            serde_json::json!({"success": false, "error": "Winding", "kind": "not_ready"})
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use anyhow::anyhow;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, de::Error as _};
+use serde_json::Value;
+use uom::si::{
+    angular_velocity::{radian_per_second, revolution_per_minute},
+    f64::{AngularVelocity, Length, Time, Velocity},
+    length::{centimeter, foot, inch, meter, micrometer, mil, millimeter},
+    time::{hour, millisecond, minute, second},
+    velocity::{foot_per_minute, inch_per_second, meter_per_second, millimeter_per_second},
+};
+
+use crate::uom_extensions::velocity::meter_per_minute;
+
+/// Numeric mutation payload with an optional unit
+///
+/// Accepts a bare number, which is read in the default unit of the mutation, or
+/// `{ "value": 0.07, "unit": "in" }`. Tagged values are converted on receipt and rejected
+/// if the unit doesn't match the quantity the mutation expects.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+#[serde(untagged)]
+pub enum UnitValue {
+    Bare(f64),
+    Tagged { value: f64, unit: String },
+}
+
+#[derive(Deserialize)]
+struct TaggedValue {
+    value: f64,
+    unit: String,
+}
+
+/// Read through [`Value`] instead of `#[serde(untagged)]`
+///
+/// opcua enables `arbitrary_precision` on serde_json for the whole workspace, which turns
+/// floats into maps once serde buffers them for an untagged enum.
+impl<'de> Deserialize<'de> for UnitValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Value::deserialize(deserializer)? {
+            Value::Number(number) => number
+                .as_f64()
+                .map(Self::Bare)
+                .ok_or_else(|| D::Error::custom(format!("{number} is not a valid f64"))),
+            value @ Value::Object(_) => serde_json::from_value::<TaggedValue>(value)
+                .map(|TaggedValue { value, unit }| Self::Tagged { value, unit })
+                .map_err(D::Error::custom),
+            value => Err(D::Error::custom(format!(
+                "expected a number or {{ \"value\", \"unit\" }}, got {value}"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Quantity {
+    Length(Length),
+    Velocity(Velocity),
+    Time(Time),
+    AngularVelocity(AngularVelocity),
+}
+
+impl Quantity {
+    fn parse(value: f64, unit: &str) -> Option<Self> {
+        let quantity = match unit {
+            "µm" | "um" => Self::Length(Length::new::<micrometer>(value)),
+            "mm" => Self::Length(Length::new::<millimeter>(value)),
+            "cm" => Self::Length(Length::new::<centimeter>(value)),
+            "m" => Self::Length(Length::new::<meter>(value)),
+            "in" => Self::Length(Length::new::<inch>(value)),
+            "mil" | "thou" => Self::Length(Length::new::<mil>(value)),
+            "ft" => Self::Length(Length::new::<foot>(value)),
+            "mm/s" => Self::Velocity(Velocity::new::<millimeter_per_second>(value)),
+            "m/s" => Self::Velocity(Velocity::new::<meter_per_second>(value)),
+            "m/min" => Self::Velocity(Velocity::new::<meter_per_minute>(value)),
+            "in/s" => Self::Velocity(Velocity::new::<inch_per_second>(value)),
+            "ft/min" => Self::Velocity(Velocity::new::<foot_per_minute>(value)),
+            "ms" => Self::Time(Time::new::<millisecond>(value)),
+            "s" => Self::Time(Time::new::<second>(value)),
+            "min" => Self::Time(Time::new::<minute>(value)),
+            "h" => Self::Time(Time::new::<hour>(value)),
+            "rpm" => Self::AngularVelocity(AngularVelocity::new::<revolution_per_minute>(value)),
+            "rad/s" => Self::AngularVelocity(AngularVelocity::new::<radian_per_second>(value)),
+            _ => return None,
+        };
+        Some(quantity)
+    }
+
+    /// Value in the SI base unit of the quantity
+    const fn si(&self) -> f64 {
+        match self {
+            Self::Length(length) => length.value,
+            Self::Velocity(velocity) => velocity.value,
+            Self::Time(time) => time.value,
+            Self::AngularVelocity(angular_velocity) => angular_velocity.value,
+        }
+    }
+
+    const fn name(&self) -> &'static str {
+        match self {
+            Self::Length(_) => "length",
+            Self::Velocity(_) => "velocity",
+            Self::Time(_) => "time",
+            Self::AngularVelocity(_) => "angular velocity",
+        }
+    }
+}
+
+impl UnitValue {
+    fn quantity(&self, default_unit: &str, expected: &str) -> Result<Quantity, anyhow::Error> {
+        let (value, unit) = match self {
+            Self::Bare(value) => (*value, default_unit),
+            Self::Tagged { value, unit } => (*value, unit.trim()),
+        };
+        if !value.is_finite() {
+            return Err(anyhow!(
+                "[{}::UnitValue::quantity] {} {} is not a finite value",
+                module_path!(),
+                value,
+                unit
+            ));
+        }
+        Quantity::parse(value, unit).ok_or_else(|| {
+            anyhow!(
+                "[{}::UnitValue::quantity] Unknown unit '{}', expected a {}",
+                module_path!(),
+                unit,
+                expected
+            )
+        })
+    }
+
+    fn mismatch(&self, quantity: Quantity, expected: &str) -> anyhow::Error {
+        let unit = match self {
+            Self::Bare(_) => "",
+            Self::Tagged { unit, .. } => unit.trim(),
+        };
+        anyhow!(
+            "[{}::UnitValue] '{}' is a {}, expected a {}",
+            module_path!(),
+            unit,
+            quantity.name(),
+            expected
+        )
+    }
+
+    /// Value converted to `unit`, bare numbers are read in `unit`
+    pub fn value_in(&self, unit: &str) -> Result<f64, anyhow::Error> {
+        let target = Quantity::parse(1.0, unit).ok_or_else(|| {
+            anyhow!(
+                "[{}::UnitValue::value_in] Unknown unit '{}'",
+                module_path!(),
+                unit
+            )
+        })?;
+        let quantity = self.quantity(unit, target.name())?;
+        if std::mem::discriminant(&quantity) != std::mem::discriminant(&target) {
+            return Err(self.mismatch(quantity, target.name()));
+        }
+        Ok(quantity.si() / target.si())
+    }
+
+    /// Length, bare numbers are read in `default_unit`
+    pub fn length(&self, default_unit: &str) -> Result<Length, anyhow::Error> {
+        match self.quantity(default_unit, "length")? {
+            Quantity::Length(length) => Ok(length),
+            quantity => Err(self.mismatch(quantity, "length")),
+        }
+    }
+
+    /// Velocity, bare numbers are read in `default_unit`
+    pub fn velocity(&self, default_unit: &str) -> Result<Velocity, anyhow::Error> {
+        match self.quantity(default_unit, "velocity")? {
+            Quantity::Velocity(velocity) => Ok(velocity),
+            quantity => Err(self.mismatch(quantity, "velocity")),
+        }
+    }
+
+    /// Time, bare numbers are read in `default_unit`
+    pub fn time(&self, default_unit: &str) -> Result<Time, anyhow::Error> {
+        match self.quantity(default_unit, "time")? {
+            Quantity::Time(time) => Ok(time),
+            quantity => Err(self.mismatch(quantity, "time")),
+        }
+    }
+
+    /// Angular velocity, bare numbers are read in `default_unit`
+    pub fn angular_velocity(&self, default_unit: &str) -> Result<AngularVelocity, anyhow::Error> {
+        match self.quantity(default_unit, "angular velocity")? {
+            Quantity::AngularVelocity(angular_velocity) => Ok(angular_velocity),
+            quantity => Err(self.mismatch(quantity, "angular velocity")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_bare_and_tagged_values() {
+        let bare: UnitValue = serde_json::from_str("1.75").unwrap();
+        assert_eq!(bare, UnitValue::Bare(1.75));
+        assert_relative_eq!(bare.length("mm").unwrap().get::<millimeter>(), 1.75);
+
+        let inches: UnitValue = serde_json::from_str(r#"{"value": 0.07, "unit": "in"}"#).unwrap();
+        assert_relative_eq!(
+            inches.length("mm").unwrap().get::<millimeter>(),
+            1.778,
+            epsilon = 1e-9
+        );
+
+        let mils = UnitValue::Tagged {
+            value: 2.0,
+            unit: "mil".to_string(),
+        };
+        assert_relative_eq!(
+            mils.length("mm").unwrap().get::<millimeter>(),
+            0.0508,
+            epsilon = 1e-9
+        );
+
+        let speed = UnitValue::Tagged {
+            value: 1.0,
+            unit: "m/s".to_string(),
+        };
+        assert_relative_eq!(
+            speed.velocity("m/min").unwrap().get::<meter_per_minute>(),
+            60.0,
+            epsilon = 1e-9
+        );
+
+        let pulse = UnitValue::Bare(250.0);
+        assert_relative_eq!(pulse.time("ms").unwrap().get::<second>(), 0.25);
+        assert_relative_eq!(pulse.value_in("ms").unwrap(), 250.0);
+        assert_relative_eq!(inches.value_in("mm").unwrap(), 1.778, epsilon = 1e-9);
+        assert!(speed.value_in("mm").is_err());
+        assert!(serde_json::from_str::<UnitValue>(r#""1.75""#).is_err());
+    }
+
+    #[test]
+    fn test_rejects_mismatched_units() {
+        let speed = UnitValue::Tagged {
+            value: 10.0,
+            unit: "m/min".to_string(),
+        };
+        let error = speed.length("mm").unwrap_err().to_string();
+        assert!(error.contains("is a velocity, expected a length"));
+
+        let unknown = UnitValue::Tagged {
+            value: 1.0,
+            unit: "furlong".to_string(),
+        };
+        assert!(unknown.length("mm").is_err());
+        assert!(UnitValue::Bare(f64::NAN).length("mm").is_err());
+    }
+}
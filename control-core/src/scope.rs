@@ -0,0 +1,231 @@
+//! Virtual oscilloscope for the internals of controllers
+//!
+//! Controllers register a [`ScopeChannel`] per signal when they are created, e.g. the terms of
+//! a PID, and probe it on every update. Probing an unarmed channel is a single atomic load, so
+//! the probes stay in release builds. Arming up to [`MAX_ARMED`] channels sends their samples
+//! to the sink of the server, which streams them for commissioning.
+
+use crate::helpers::clock::monotonic_micros_at;
+use serde::Serialize;
+use smol::channel::Sender;
+use std::{
+    sync::{
+        Arc, LazyLock, Mutex, RwLock, Weak,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::Instant,
+};
+
+/// Channels armed at the same time
+pub const MAX_ARMED: usize = 8;
+
+static REGISTRY: LazyLock<Mutex<Registry>> = LazyLock::new(|| Mutex::new(Registry::default()));
+
+static SINK: LazyLock<RwLock<Option<Sender<ScopeSample>>>> = LazyLock::new(|| RwLock::new(None));
+
+/// Samples dropped because the sink fell behind, since the channels were armed
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Default)]
+struct Registry {
+    channels: Vec<Weak<ChannelInner>>,
+    /// Names of the armed channels, channels registered later under one of them start armed
+    armed: Vec<String>,
+}
+
+impl Registry {
+    /// Forgets the dropped channels
+    fn prune(&mut self) {
+        self.channels.retain(|channel| channel.strong_count() > 0);
+    }
+
+    fn live(&mut self) -> Vec<Arc<ChannelInner>> {
+        self.prune();
+        self.channels.iter().filter_map(Weak::upgrade).collect()
+    }
+}
+
+#[derive(Debug)]
+struct ChannelInner {
+    name: Arc<str>,
+    armed: AtomicBool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScopeSample {
+    pub channel: Arc<str>,
+    /// microseconds on the time base of [`crate::helpers::clock::monotonic_millis`]
+    pub t: u64,
+    pub value: f64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ScopeChannelInfo {
+    pub name: String,
+    pub armed: bool,
+}
+
+/// Signal of a controller, unregistered when the last clone is dropped
+///
+/// Names are paths like `winder/1/diameter_loop/p`, several channels of the same name are
+/// armed together.
+#[derive(Debug, Clone)]
+pub struct ScopeChannel(Arc<ChannelInner>);
+
+impl ScopeChannel {
+    pub fn new(name: impl Into<String>) -> Self {
+        let name: String = name.into();
+        let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+        let channel = Arc::new(ChannelInner {
+            armed: AtomicBool::new(registry.armed.contains(&name)),
+            name: name.into(),
+        });
+        registry.prune();
+        registry.channels.push(Arc::downgrade(&channel));
+        Self(channel)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    /// Never blocks, samples are dropped while the sink falls behind
+    pub fn probe(&self, t: Instant, value: f64) {
+        if !self.0.armed.load(Ordering::Relaxed) {
+            return;
+        }
+        let sink = SINK.read().unwrap_or_else(|e| e.into_inner());
+        let Some(sink) = sink.as_ref() else {
+            return;
+        };
+        let sample = ScopeSample {
+            channel: self.0.name.clone(),
+            t: monotonic_micros_at(t),
+            value,
+        };
+        if sink.try_send(sample).is_err() {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Registered channels sorted by name, one entry per name
+pub fn channels() -> Vec<ScopeChannelInfo> {
+    let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let mut channels: Vec<_> = registry
+        .live()
+        .iter()
+        .map(|channel| ScopeChannelInfo {
+            name: channel.name.to_string(),
+            armed: registry.armed.iter().any(|armed| **armed == *channel.name),
+        })
+        .collect();
+    channels.sort_by(|a, b| a.name.cmp(&b.name));
+    channels.dedup();
+    channels
+}
+
+/// Sends the samples of the channels called `names` to `sink` from now on
+///
+/// Replaces the armed channels and the sink, dropping the previous sink closes its channel.
+pub fn arm(names: Vec<String>, sink: Sender<ScopeSample>) -> Result<(), anyhow::Error> {
+    if names.len() > MAX_ARMED {
+        return Err(anyhow::anyhow!(
+            "[{}::arm] At most {} channels can be armed, got {}",
+            module_path!(),
+            MAX_ARMED,
+            names.len()
+        ));
+    }
+    let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let channels = registry.live();
+    if let Some(unknown) = names
+        .iter()
+        .find(|name| !channels.iter().any(|channel| *channel.name == ***name))
+    {
+        return Err(anyhow::anyhow!(
+            "[{}::arm] No scope channel named {}",
+            module_path!(),
+            unknown
+        ));
+    }
+
+    *SINK.write().unwrap_or_else(|e| e.into_inner()) = Some(sink);
+    DROPPED.store(0, Ordering::Relaxed);
+    for channel in &channels {
+        let armed = names.iter().any(|name| **name == *channel.name);
+        channel.armed.store(armed, Ordering::Relaxed);
+    }
+    registry.armed = names;
+    Ok(())
+}
+
+/// Stops sampling, returns if channels were armed
+pub fn disarm() -> bool {
+    let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    for channel in registry.live() {
+        channel.armed.store(false, Ordering::Relaxed);
+    }
+    SINK.write().unwrap_or_else(|e| e.into_inner()).take();
+    !std::mem::take(&mut registry.armed).is_empty()
+}
+
+pub fn armed() -> Vec<String> {
+    REGISTRY
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .armed
+        .clone()
+}
+
+pub fn dropped_samples() -> u64 {
+    DROPPED.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_channels() {
+        let p = ScopeChannel::new("test/scope/p");
+        let i = ScopeChannel::new("test/scope/i");
+        let (tx, rx) = smol::channel::unbounded();
+
+        assert!(arm(vec!["test/scope/unknown".to_string()], tx.clone()).is_err());
+        assert!(arm(vec!["test/scope/p".to_string(); MAX_ARMED + 1], tx.clone()).is_err());
+        arm(vec!["test/scope/p".to_string()], tx).unwrap();
+
+        let now = Instant::now();
+        p.probe(now, 1.5);
+        i.probe(now, 2.5);
+        // registered while armed, e.g. a machine created again
+        ScopeChannel::new("test/scope/p").probe(now, 3.5);
+
+        let values: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|sample| (sample.channel.to_string(), sample.value))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ("test/scope/p".to_string(), 1.5),
+                ("test/scope/p".to_string(), 3.5)
+            ]
+        );
+        assert!(
+            channels()
+                .iter()
+                .any(|channel| channel.name == "test/scope/p" && channel.armed)
+        );
+
+        assert!(disarm());
+        p.probe(now, 4.5);
+        assert!(rx.try_recv().is_err());
+        drop(i);
+        assert!(
+            !channels()
+                .iter()
+                .any(|channel| channel.name == "test/scope/i")
+        );
+    }
+}
@@ -0,0 +1,332 @@
+use std::{
+    fmt::Debug,
+    io::{Read, Write},
+};
+
+use anyhow::{Error, anyhow};
+use crc::{CRC_32_ISO_HDLC, Crc};
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::retry::retry_n_times;
+
+/// Byte stream to a device in its bootloader, usually the serial port of the device
+pub trait FirmwarePort: Read + Write {}
+
+impl<T: Read + Write + ?Sized> FirmwarePort for T {}
+
+/// Attempts per chunk before the transfer is given up and rolled back
+const CHUNK_RETRIES: usize = 3;
+
+/// CRC-32 (IEEE 802.3) as used by the bootloaders to check a transferred image
+pub const fn crc32(data: &[u8]) -> u32 {
+    let crc32: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+    crc32.checksum(data)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FirmwareImage {
+    /// Version of the image as shown to the user, e.g. `1.4.0`
+    pub version: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FirmwareUpdateStage {
+    /// Waiting for the device to pick the update up
+    Pending,
+    Handshake,
+    Transfer,
+    Verify,
+    Commit,
+    RollingBack,
+    /// New firmware is running
+    Done,
+    /// Transfer failed, the previous firmware is running again
+    RolledBack,
+    /// Update failed and the previous firmware could not be restored
+    Failed,
+}
+
+impl FirmwareUpdateStage {
+    pub const fn is_finished(&self) -> bool {
+        matches!(self, Self::Done | Self::RolledBack | Self::Failed)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FirmwareProgress {
+    pub stage: FirmwareUpdateStage,
+    pub bytes_written: usize,
+    pub bytes_total: usize,
+    /// Version of the image being installed
+    pub version: String,
+    /// Firmware version the device reported in the handshake
+    pub previous_version: Option<String>,
+    /// Reason of a rollback or failure
+    pub error: Option<String>,
+}
+
+impl FirmwareProgress {
+    pub fn new(image: &FirmwareImage) -> Self {
+        Self {
+            stage: FirmwareUpdateStage::Pending,
+            bytes_written: 0,
+            bytes_total: image.data.len(),
+            version: image.version.clone(),
+            previous_version: None,
+            error: None,
+        }
+    }
+}
+
+/// Bootloader protocol of a serial device family
+///
+/// [`update_firmware`] drives the update, the protocol does the framing of its bootloader.
+pub trait FirmwareProtocol: Debug + Send {
+    /// Name of the bootloader for logs
+    fn name(&self) -> &'static str;
+
+    /// Largest payload of one [`FirmwareProtocol::write_chunk`]
+    fn chunk_size(&self) -> usize;
+
+    /// Switches the device from its application into the bootloader
+    ///
+    /// Returns the version of the firmware that was running.
+    fn enter_bootloader(&mut self, port: &mut dyn FirmwarePort) -> Result<String, Error>;
+
+    /// Writes a chunk of the image to the update slot of the device
+    fn write_chunk(
+        &mut self,
+        port: &mut dyn FirmwarePort,
+        offset: u32,
+        chunk: &[u8],
+    ) -> Result<(), Error>;
+
+    /// CRC-32 over the first `len` bytes of the update slot as computed by the device
+    fn image_crc(&mut self, port: &mut dyn FirmwarePort, len: u32) -> Result<u32, Error>;
+
+    /// Marks the update slot as valid and boots it
+    fn commit(&mut self, port: &mut dyn FirmwarePort) -> Result<(), Error>;
+
+    /// Discards the update slot and boots the previous firmware
+    fn rollback(&mut self, port: &mut dyn FirmwarePort) -> Result<(), Error>;
+}
+
+/// Installs `image` on the device behind `port`
+///
+/// The previous firmware is restored if the transfer fails or the device computes another
+/// CRC than the image has. `progress` is called on every stage change and written chunk.
+pub fn update_firmware(
+    protocol: &mut dyn FirmwareProtocol,
+    port: &mut dyn FirmwarePort,
+    image: &FirmwareImage,
+    progress: &mut dyn FnMut(&FirmwareProgress),
+) -> Result<(), Error> {
+    let mut state = FirmwareProgress::new(image);
+
+    state.stage = FirmwareUpdateStage::Handshake;
+    progress(&state);
+    // nothing was written yet, the device keeps its firmware
+    let previous_version = protocol.enter_bootloader(port).inspect_err(|e| {
+        state.stage = FirmwareUpdateStage::Failed;
+        state.error = Some(e.to_string());
+        progress(&state);
+    })?;
+    state.previous_version = Some(previous_version);
+
+    match transfer(protocol, port, image, &mut state, progress) {
+        Ok(_) => {
+            state.stage = FirmwareUpdateStage::Done;
+            progress(&state);
+            Ok(())
+        }
+        Err(e) => {
+            state.error = Some(e.to_string());
+            state.stage = FirmwareUpdateStage::RollingBack;
+            progress(&state);
+            match protocol.rollback(port) {
+                Ok(_) => {
+                    state.stage = FirmwareUpdateStage::RolledBack;
+                    progress(&state);
+                    Err(e)
+                }
+                Err(rollback_error) => {
+                    state.stage = FirmwareUpdateStage::Failed;
+                    state.error = Some(format!("{}, rollback failed: {}", e, rollback_error));
+                    progress(&state);
+                    Err(anyhow!(
+                        "[{}::update_firmware] {}, rollback failed: {}",
+                        module_path!(),
+                        e,
+                        rollback_error
+                    ))
+                }
+            }
+        }
+    }
+}
+
+fn transfer(
+    protocol: &mut dyn FirmwareProtocol,
+    port: &mut dyn FirmwarePort,
+    image: &FirmwareImage,
+    state: &mut FirmwareProgress,
+    progress: &mut dyn FnMut(&FirmwareProgress),
+) -> Result<(), Error> {
+    state.stage = FirmwareUpdateStage::Transfer;
+    progress(state);
+    let chunk_size = protocol.chunk_size().max(1);
+    for (index, chunk) in image.data.chunks(chunk_size).enumerate() {
+        let offset = (index * chunk_size) as u32;
+        retry_n_times(CHUNK_RETRIES, || protocol.write_chunk(port, offset, chunk))?;
+        state.bytes_written += chunk.len();
+        progress(state);
+    }
+
+    state.stage = FirmwareUpdateStage::Verify;
+    progress(state);
+    let expected = crc32(&image.data);
+    let actual = protocol.image_crc(port, image.data.len() as u32)?;
+    if actual != expected {
+        return Err(anyhow!(
+            "[{}::transfer] CRC mismatch, image has {:08x} but the device computed {:08x}",
+            module_path!(),
+            expected,
+            actual
+        ));
+    }
+
+    state.stage = FirmwareUpdateStage::Commit;
+    progress(state);
+    protocol.commit(port)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Bootloader keeping the update slot in memory
+    #[derive(Debug, Default)]
+    struct MemoryBootloader {
+        slot: Vec<u8>,
+        /// Flips a bit of the slot before the CRC is computed
+        corrupt: bool,
+        /// Fails the first write of every chunk
+        flaky: bool,
+        failed_offsets: Vec<u32>,
+        committed: bool,
+        rolled_back: bool,
+    }
+
+    impl FirmwareProtocol for MemoryBootloader {
+        fn name(&self) -> &'static str {
+            "memory"
+        }
+
+        fn chunk_size(&self) -> usize {
+            4
+        }
+
+        fn enter_bootloader(&mut self, _port: &mut dyn FirmwarePort) -> Result<String, Error> {
+            Ok("1.0.0".to_string())
+        }
+
+        fn write_chunk(
+            &mut self,
+            _port: &mut dyn FirmwarePort,
+            offset: u32,
+            chunk: &[u8],
+        ) -> Result<(), Error> {
+            if self.flaky && !self.failed_offsets.contains(&offset) {
+                self.failed_offsets.push(offset);
+                return Err(anyhow!("NAK"));
+            }
+            let offset = offset as usize;
+            self.slot.resize(offset.max(self.slot.len()), 0);
+            self.slot.truncate(offset);
+            self.slot.extend_from_slice(chunk);
+            Ok(())
+        }
+
+        fn image_crc(&mut self, _port: &mut dyn FirmwarePort, len: u32) -> Result<u32, Error> {
+            if self.corrupt {
+                self.slot[0] ^= 1;
+            }
+            Ok(crc32(&self.slot[..len as usize]))
+        }
+
+        fn commit(&mut self, _port: &mut dyn FirmwarePort) -> Result<(), Error> {
+            self.committed = true;
+            Ok(())
+        }
+
+        fn rollback(&mut self, _port: &mut dyn FirmwarePort) -> Result<(), Error> {
+            self.rolled_back = true;
+            Ok(())
+        }
+    }
+
+    fn image() -> FirmwareImage {
+        FirmwareImage {
+            version: "1.1.0".to_string(),
+            data: (0..10).collect(),
+        }
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(&[]), 0);
+    }
+
+    #[test]
+    fn test_update_firmware() {
+        let mut bootloader = MemoryBootloader {
+            flaky: true,
+            ..Default::default()
+        };
+        let mut stages = vec![];
+        update_firmware(
+            &mut bootloader,
+            &mut Cursor::new(vec![]),
+            &image(),
+            &mut |progress| stages.push((progress.stage, progress.bytes_written)),
+        )
+        .unwrap();
+
+        assert_eq!(bootloader.slot, image().data);
+        assert!(bootloader.committed);
+        assert!(!bootloader.rolled_back);
+        assert_eq!(
+            stages.last(),
+            Some(&(FirmwareUpdateStage::Done, image().data.len()))
+        );
+        // progress after every chunk
+        assert!(stages.contains(&(FirmwareUpdateStage::Transfer, 4)));
+        assert!(stages.contains(&(FirmwareUpdateStage::Transfer, 8)));
+    }
+
+    #[test]
+    fn test_rollback_on_crc_mismatch() {
+        let mut bootloader = MemoryBootloader {
+            corrupt: true,
+            ..Default::default()
+        };
+        let mut last = None;
+        let result = update_firmware(
+            &mut bootloader,
+            &mut Cursor::new(vec![]),
+            &image(),
+            &mut |progress| last = Some(progress.clone()),
+        );
+
+        assert!(result.is_err());
+        assert!(bootloader.rolled_back);
+        assert!(!bootloader.committed);
+        let last = last.unwrap();
+        assert_eq!(last.stage, FirmwareUpdateStage::RolledBack);
+        assert_eq!(last.previous_version.as_deref(), Some("1.0.0"));
+        assert!(last.error.unwrap().contains("CRC mismatch"));
+    }
+}
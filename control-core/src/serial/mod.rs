@@ -1,17 +1,36 @@
 use std::{any::Any, sync::Arc};
 
+use serde::Serialize;
 use smol::{channel::Sender, lock::RwLock};
 use std::fmt::Debug;
 
 use crate::{
-    machines::identification::DeviceIdentification, serial::serial_detection::SerialDeviceRemoval,
+    helpers::hashing::{byte_folding_u16, hash_djb2},
+    machines::identification::DeviceIdentification,
+    serial::serial_detection::SerialDeviceRemoval,
 };
 
+pub mod firmware;
 pub mod panic;
 pub mod registry;
 pub mod serial_detection;
 
-pub trait SerialDevice: Any + Send + Sync + SerialDeviceNew + Debug {}
+pub trait SerialDevice: Any + Send + Sync + SerialDeviceNew + Debug {
+    /// Identity the device reported, queried once it is connected
+    ///
+    /// Empty until the device answered and for protocols without an identity request.
+    fn device_info(&self) -> SerialDeviceInfo {
+        SerialDeviceInfo::default()
+    }
+}
+
+/// Identity of a serial device as it reports itself, not of its USB adapter
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct SerialDeviceInfo {
+    pub model: Option<String>,
+    pub firmware_version: Option<String>,
+    pub serial_number: Option<String>,
+}
 
 pub trait SerialDeviceNew {
     fn new_serial(
@@ -28,10 +47,68 @@ pub trait SerialDeviceThread {
 pub struct SerialDeviceNewParams {
     pub path: String,
     pub device_thread_panic_tx: Sender<SerialDeviceRemoval<String>>,
+    /// USB identification of the adapter, `None` for devices that are not on USB
+    pub serial_device_identification: Option<SerialDeviceIdentification>,
+    /// Serial number the USB adapter reports, not all adapters have one
+    pub usb_serial_number: Option<String>,
+}
+
+impl SerialDeviceNewParams {
+    /// Serial of the machine the device belongs to
+    ///
+    /// Derived from the USB identification and serial number if the adapter has one, so the
+    /// machine stays the same when the OS assigns another path, e.g. after a reboot.
+    /// Falls back to the path for adapters without a serial number.
+    pub fn machine_serial(&self) -> u16 {
+        let key = match (&self.serial_device_identification, &self.usb_serial_number) {
+            (Some(identification), Some(serial_number)) => format!(
+                "{:04x}:{:04x}:{}",
+                identification.vendor_id, identification.product_id, serial_number
+            ),
+            _ => self.path.clone(),
+        };
+        byte_folding_u16(&hash_djb2(key.as_bytes()).to_le_bytes())
+    }
 }
 
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct SerialDeviceIdentification {
     pub vendor_id: u16,
     pub product_id: u16,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(path: &str, usb_serial_number: Option<&str>) -> SerialDeviceNewParams {
+        SerialDeviceNewParams {
+            path: path.to_string(),
+            device_thread_panic_tx: smol::channel::unbounded().0,
+            serial_device_identification: Some(SerialDeviceIdentification {
+                vendor_id: 0x0403,
+                product_id: 0x6001,
+            }),
+            usb_serial_number: usb_serial_number.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_machine_serial_follows_usb_serial_number() {
+        // same adapter on another path
+        assert_eq!(
+            params("/dev/ttyUSB0", Some("A10K3XYZ")).machine_serial(),
+            params("/dev/ttyUSB1", Some("A10K3XYZ")).machine_serial()
+        );
+        // another adapter on the same path
+        assert_ne!(
+            params("/dev/ttyUSB0", Some("A10K3XYZ")).machine_serial(),
+            params("/dev/ttyUSB0", Some("B20L4ABC")).machine_serial()
+        );
+        // adapters without serial number are told apart by path
+        assert_ne!(
+            params("/dev/ttyUSB0", None).machine_serial(),
+            params("/dev/ttyUSB1", None).machine_serial()
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use super::SerialDeviceNewParams;
 use crate::{
     machines::identification::DeviceIdentification,
-    serial::{SerialDevice, SerialDeviceIdentification},
+    serial::{SerialDevice, SerialDeviceIdentification, firmware::FirmwareProtocol},
 };
 use anyhow::{Error, Result};
 use smol::lock::RwLock;
@@ -15,9 +15,19 @@ pub type SerialDeviceNewClosure = Arc<
         + Sync,
 >;
 
+pub type FirmwareProtocolNewClosure = Arc<dyn Fn() -> Box<dyn FirmwareProtocol> + Send + Sync>;
+
+/// Serial device constructors by the USB identification of the port they are bound to
+///
+/// One device type can be registered for several identifications, e.g. with a different
+/// protocol driver per identification.
 #[derive(Clone)]
 pub struct SerialDeviceRegistry {
-    pub type_map: HashMap<TypeId, (SerialDeviceIdentification, SerialDeviceNewClosure)>,
+    pub devices: HashMap<SerialDeviceIdentification, (TypeId, SerialDeviceNewClosure)>,
+    /// Constructor for devices without a registered identification, e.g. probing the port
+    pub fallback: Option<SerialDeviceNewClosure>,
+    /// Bootloader protocols of devices that can be updated in place
+    pub firmware: HashMap<SerialDeviceIdentification, FirmwareProtocolNewClosure>,
 }
 
 impl Default for SerialDeviceRegistry {
@@ -29,7 +39,9 @@ impl Default for SerialDeviceRegistry {
 impl SerialDeviceRegistry {
     pub fn new() -> Self {
         Self {
-            type_map: HashMap::new(),
+            devices: HashMap::new(),
+            fallback: None,
+            firmware: HashMap::new(),
         }
     }
 
@@ -37,28 +49,81 @@ impl SerialDeviceRegistry {
         &mut self,
         serial_device_identification: SerialDeviceIdentification,
     ) {
-        self.type_map.insert(
-            TypeId::of::<T>(),
+        self.register_with(serial_device_identification, T::new_serial);
+    }
+
+    /// Registers a device type with a custom constructor instead of [`super::SerialDeviceNew`]
+    pub fn register_with<T, F>(
+        &mut self,
+        serial_device_identification: SerialDeviceIdentification,
+        new_fn: F,
+    ) where
+        T: SerialDevice + 'static,
+        F: Fn(&SerialDeviceNewParams) -> Result<(DeviceIdentification, Arc<RwLock<T>>), Error>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.devices.insert(
+            serial_device_identification,
             (
-                serial_device_identification,
+                TypeId::of::<T>(),
                 Arc::new(move |params| {
-                    let (identification, device) = T::new_serial(params)?;
+                    let (identification, device) = new_fn(params)?;
                     Ok((identification, device))
                 }),
             ),
         );
     }
 
+    /// Registers the constructor used for devices with an unknown identification
+    pub fn register_fallback<T, F>(&mut self, new_fn: F)
+    where
+        T: SerialDevice + 'static,
+        F: Fn(&SerialDeviceNewParams) -> Result<(DeviceIdentification, Arc<RwLock<T>>), Error>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.fallback = Some(Arc::new(move |params| {
+            let (identification, device) = new_fn(params)?;
+            Ok((identification, device))
+        }));
+    }
+
+    /// Registers the bootloader protocol of the devices with the identification
+    pub fn register_firmware<F>(
+        &mut self,
+        serial_device_identification: SerialDeviceIdentification,
+        new_fn: F,
+    ) where
+        F: Fn() -> Box<dyn FirmwareProtocol> + Send + Sync + 'static,
+    {
+        self.firmware
+            .insert(serial_device_identification, Arc::new(new_fn));
+    }
+
+    /// Bootloader protocol for a device, `None` if it does not support firmware updates
+    pub fn firmware_protocol(
+        &self,
+        serial_device_identification: &SerialDeviceIdentification,
+    ) -> Option<Box<dyn FirmwareProtocol>> {
+        self.firmware
+            .get(serial_device_identification)
+            .map(|new_fn| new_fn())
+    }
+
     pub fn new_serial_device(
         &self,
         serial_device_new_params: &SerialDeviceNewParams,
         serial_device_identification: &SerialDeviceIdentification,
     ) -> Result<(DeviceIdentification, Arc<RwLock<dyn SerialDevice>>), anyhow::Error> {
         // find serial new function by comparing ProdutConfig
-        let (_, serial_new_fn) = self
-            .type_map
-            .values()
-            .find(|(sdi, _)| sdi == serial_device_identification)
+        let serial_new_fn = self
+            .devices
+            .get(serial_device_identification)
+            .map(|(_, serial_new_fn)| serial_new_fn)
+            .or(self.fallback.as_ref())
             .ok_or(anyhow::anyhow!(
                 "[{}::MachineConstructor::new_machine] Machine not found",
                 module_path!()
@@ -6,6 +6,7 @@
 *@description: This module is responsible for usb detection and validation, specially made with serialport to avoid complexity and size of tokio_serial
 */
 
+use serde::Serialize;
 use serialport::{SerialPortInfo, SerialPortType, UsbPortInfo};
 use smol::{
     channel::{Receiver, Sender, unbounded},
@@ -14,11 +15,13 @@ use smol::{
 use std::{collections::HashMap, sync::Arc};
 
 use crate::{
-    helpers::compare_lists::compare_lists, machines::identification::DeviceIdentification,
+    helpers::compare_lists::compare_lists,
+    machines::identification::{DeviceIdentification, MachineIdentificationUnique},
 };
 
 use super::{
-    SerialDevice, SerialDeviceIdentification, SerialDeviceNewParams, registry::SerialDeviceRegistry,
+    SerialDevice, SerialDeviceIdentification, SerialDeviceInfo, SerialDeviceNewParams,
+    registry::SerialDeviceRegistry,
 };
 
 pub enum SerialDeviceRemoval<T> {
@@ -26,8 +29,59 @@ pub enum SerialDeviceRemoval<T> {
     Error(T, anyhow::Error),
 }
 
+/// Ports serial devices are looked for on, by port name patterns where `*` matches any text
+///
+/// An empty allow list allows all ports, denied ports are never opened.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SerialPortFilter {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl SerialPortFilter {
+    pub fn permits(&self, port_name: &str) -> bool {
+        let allowed = self.allow.is_empty()
+            || self
+                .allow
+                .iter()
+                .any(|pattern| pattern_matches(pattern, port_name));
+        allowed
+            && !self
+                .deny
+                .iter()
+                .any(|pattern| pattern_matches(pattern, port_name))
+    }
+}
+
+fn pattern_matches(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((prefix, rest)) => text.strip_prefix(prefix).is_some_and(|text| {
+            (0..=text.len())
+                .filter(|i| text.is_char_boundary(*i))
+                .any(|i| pattern_matches(rest, &text[i..]))
+        }),
+    }
+}
+
+/// Connected serial device with its USB adapter and the identity it reported
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SerialInventoryEntry {
+    pub path: String,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    /// Manufacturer and product of the USB adapter
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    pub usb_serial_number: Option<String>,
+    /// Machine the device belongs to
+    pub machine: Option<MachineIdentificationUnique>,
+    pub device: SerialDeviceInfo,
+}
+
 pub struct SerialDetection<'serialdeviceregistry> {
     pub serial_device_registry: &'serialdeviceregistry SerialDeviceRegistry,
+    pub port_filter: SerialPortFilter,
     pub ports: HashMap<
         String,
         (
@@ -38,6 +92,8 @@ pub struct SerialDetection<'serialdeviceregistry> {
     >,
     pub device_removal_signal_rx: Receiver<SerialDeviceRemoval<String>>,
     pub device_removal_signal_tx: Sender<SerialDeviceRemoval<String>>,
+    /// Ports no device could be created for, retried once they are plugged in again
+    pub unsupported: HashMap<String, UsbPortInfo>,
 }
 
 impl<'serialdeviceregistry> SerialDetection<'serialdeviceregistry> {
@@ -45,9 +101,11 @@ impl<'serialdeviceregistry> SerialDetection<'serialdeviceregistry> {
         let (device_removal_signal_tx, device_removal_signal_rx) = unbounded();
         SerialDetection {
             serial_device_registry,
+            port_filter: SerialPortFilter::default(),
             ports: HashMap::new(),
             device_removal_signal_rx,
             device_removal_signal_tx,
+            unsupported: HashMap::new(),
         }
     }
 
@@ -92,7 +150,10 @@ impl<'serialdeviceregistry> SerialDetection<'serialdeviceregistry> {
 
     pub async fn check_ports(&mut self) -> CheckPortsResult {
         // get available ports
-        let ports = Self::get_ports();
+        let ports = Self::get_ports()
+            .into_iter()
+            .filter(|port| self.port_filter.permits(&port.port_name))
+            .collect();
 
         // extract all ports that are usb ports
         let usb_ports = self.extract_usb_serial_devices(ports);
@@ -105,6 +166,13 @@ impl<'serialdeviceregistry> SerialDetection<'serialdeviceregistry> {
 
         let usb_ports_diff = compare_lists(&last_usb_ports, &usb_ports);
 
+        // forget unsupported ports that were unplugged
+        self.unsupported.retain(|path, info| {
+            usb_ports
+                .iter()
+                .any(|port| &port.0 == path && &port.1 == info)
+        });
+
         let mut result = CheckPortsResult {
             added: Vec::new(),
             removed: Vec::new(),
@@ -121,6 +189,10 @@ impl<'serialdeviceregistry> SerialDetection<'serialdeviceregistry> {
 
         // add new ports
         for added in usb_ports_diff.added {
+            if self.unsupported.contains_key(&added.0) {
+                continue;
+            }
+
             // add the port to the list
             let serial_device_identification = SerialDeviceIdentification {
                 vendor_id: added.1.vid,
@@ -131,32 +203,63 @@ impl<'serialdeviceregistry> SerialDetection<'serialdeviceregistry> {
                 &SerialDeviceNewParams {
                     path: added.0.clone(),
                     device_thread_panic_tx: self.device_removal_signal_tx.clone(),
+                    serial_device_identification: Some(serial_device_identification.clone()),
+                    usb_serial_number: added.1.serial_number.clone(),
                 },
                 &serial_device_identification,
             );
 
             // only if created device driver sucessfully
-            if let Ok((device_identification, device)) = device_result {
-                // add the device to the ports list
-                self.ports.insert(
-                    added.0.clone(),
-                    (
-                        added.1.clone(),
-                        device_identification.clone(),
-                        device.clone(),
-                    ),
-                );
-
-                // add to result list
-                result.added.push((device_identification, device.clone()));
-
-                tracing::trace!("Added port: {}", added.0);
-            }
+            let (device_identification, device) = match device_result {
+                Ok(device) => device,
+                Err(e) => {
+                    tracing::debug!("No serial device for port {}: {:?}", added.0, e);
+                    self.unsupported.insert(added.0.clone(), added.1.clone());
+                    continue;
+                }
+            };
+
+            // add the device to the ports list
+            self.ports.insert(
+                added.0.clone(),
+                (
+                    added.1.clone(),
+                    device_identification.clone(),
+                    device.clone(),
+                ),
+            );
+
+            // add to result list
+            result.added.push((device_identification, device.clone()));
+
+            tracing::trace!("Added port: {}", added.0);
         }
 
         result
     }
 
+    /// Connected devices sorted by path, e.g. to verify firmware levels remotely
+    pub async fn inventory(&self) -> Vec<SerialInventoryEntry> {
+        let mut inventory = Vec::with_capacity(self.ports.len());
+        for (path, (usb_port_info, device_identification, device)) in &self.ports {
+            inventory.push(SerialInventoryEntry {
+                path: path.clone(),
+                vendor_id: usb_port_info.vid,
+                product_id: usb_port_info.pid,
+                manufacturer: usb_port_info.manufacturer.clone(),
+                product: usb_port_info.product.clone(),
+                usb_serial_number: usb_port_info.serial_number.clone(),
+                machine: device_identification
+                    .device_machine_identification
+                    .as_ref()
+                    .map(|machine| machine.machine_identification_unique.clone()),
+                device: device.read().await.device_info(),
+            });
+        }
+        inventory.sort_by(|a, b| a.path.cmp(&b.path));
+        inventory
+    }
+
     pub async fn check_remove_signals(&mut self) -> Vec<DeviceIdentification> {
         let mut removed_signals: Vec<DeviceIdentification> = Vec::new();
 
@@ -198,3 +301,25 @@ pub struct CheckPortsResult {
     pub added: Vec<(DeviceIdentification, Arc<RwLock<dyn SerialDevice>>)>,
     pub removed: Vec<DeviceIdentification>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serial_port_filter() {
+        assert!(SerialPortFilter::default().permits("/dev/ttyUSB0"));
+
+        let filter = SerialPortFilter {
+            allow: vec![
+                "/dev/ttyUSB*".to_string(),
+                "/dev/serial/by-id/*FTDI*".to_string(),
+            ],
+            deny: vec!["/dev/ttyUSB3".to_string()],
+        };
+        assert!(filter.permits("/dev/ttyUSB0"));
+        assert!(filter.permits("/dev/serial/by-id/usb-FTDI_FT232R-if00"));
+        assert!(!filter.permits("/dev/ttyUSB3"));
+        assert!(!filter.permits("/dev/ttyACM0"));
+    }
+}
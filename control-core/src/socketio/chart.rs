@@ -0,0 +1,307 @@
+use super::{
+    event::{Event, GenericEvent},
+    units::{DisplayQuantity, FieldUnit, UNITS_FIELD, UnitSystem},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::BTreeMap, sync::Arc};
+
+/// Socket event subscribing a chart, the data is a [`ChartSubscription`]
+pub const SUBSCRIBE_CHART: &str = "SubscribeChart";
+
+/// Socket event ending a chart subscription, the data is the id of the chart
+pub const UNSUBSCRIBE_CHART: &str = "UnsubscribeChart";
+
+/// Name of the [`ChartPointsEvent`]
+pub const CHART_POINTS_EVENT: &str = "ChartPointsEvent";
+
+/// Maximum points of a chart over its window
+pub const MAX_CHART_POINTS: usize = 10_000;
+
+/// Chart of a single value a client wants to receive already decimated
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChartSubscription {
+    /// Chosen by the client, identifies the chart in the [`ChartPointsEvent`]
+    pub id: String,
+    /// Event holding the value, e.g. `LiveValuesEvent`
+    pub event: String,
+    /// Dot separated path of the value in the event data, e.g. `diameter_loop.error`,
+    /// numbers index arrays, e.g. `strand_diameters.1`
+    pub field: String,
+    /// Time window of the chart in ms
+    pub window_ms: u64,
+    /// Points over the window, each point covers `window_ms / points`
+    pub points: usize,
+    /// Don't send the full rate `event` to the socket while the chart is subscribed
+    #[serde(default)]
+    pub mute_event: bool,
+}
+
+impl ChartSubscription {
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.points == 0 || self.points > MAX_CHART_POINTS {
+            return Err(anyhow::anyhow!(
+                "[{}::ChartSubscription::validate] Points must be between 1 and {}",
+                module_path!(),
+                MAX_CHART_POINTS
+            ));
+        }
+        if self.window_ms < self.points as u64 {
+            return Err(anyhow::anyhow!(
+                "[{}::ChartSubscription::validate] Window must be at least 1ms per point",
+                module_path!()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Time covered by one point in ms
+    pub const fn resolution_ms(&self) -> u64 {
+        self.window_ms / self.points as u64
+    }
+}
+
+/// Values of one time bucket of a chart
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct ChartPoint {
+    /// Start of the bucket, unix timestamp in ms
+    pub ts: u64,
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ChartPointsEvent {
+    pub id: String,
+    /// Set for the points of the whole window sent after subscribing, they replace all
+    /// points the client has for the chart
+    pub reset: bool,
+    /// Oldest first
+    pub points: Vec<ChartPoint>,
+    pub units: BTreeMap<String, FieldUnit>,
+}
+
+impl ChartPointsEvent {
+    pub fn build(self) -> GenericEvent {
+        Event::new(CHART_POINTS_EVENT, self).into()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    start: u64,
+    min: f64,
+    max: f64,
+    sum: f64,
+    count: u32,
+}
+
+impl Bucket {
+    const fn new(start: u64, value: f64) -> Self {
+        Self {
+            start,
+            min: value,
+            max: value,
+            sum: value,
+            count: 1,
+        }
+    }
+
+    const fn add(&mut self, value: f64) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn point(&self) -> ChartPoint {
+        ChartPoint {
+            ts: self.start,
+            min: self.min,
+            max: self.max,
+            avg: self.sum / f64::from(self.count),
+        }
+    }
+}
+
+/// Decimates the value of a [`ChartSubscription`] into buckets as events come in
+///
+/// Buckets are aligned to multiples of the resolution, a point is sent once the first
+/// value of the next bucket arrives.
+#[derive(Debug)]
+pub struct ChartStream {
+    pub subscription: ChartSubscription,
+    bucket: Option<Bucket>,
+    quantity: Option<DisplayQuantity>,
+}
+
+impl ChartStream {
+    pub const fn new(subscription: ChartSubscription) -> Self {
+        Self {
+            subscription,
+            bucket: None,
+            quantity: None,
+        }
+    }
+
+    /// Points of the cached `events` inside the window before `now`
+    ///
+    /// The bucket of the newest event stays open and is continued by [`Self::push`].
+    pub fn backfill(&mut self, events: &[Arc<GenericEvent>], now: u64) -> ChartPointsEvent {
+        let from = now.saturating_sub(self.subscription.window_ms);
+        let mut points = vec![];
+        for event in events.iter().filter(|event| event.ts >= from) {
+            if let Ok(data) = serde_json::to_value(&event.data) {
+                points.extend(self.push(event.ts, &data));
+            }
+        }
+        self.points_event(true, points)
+    }
+
+    /// Adds the value of an event, returns the point of the bucket it finished
+    pub fn push(&mut self, ts: u64, data: &Value) -> Option<ChartPoint> {
+        let value = field_value(data, &self.subscription.field)?;
+        if self.quantity.is_none() {
+            self.quantity = field_quantity(data, &self.subscription.field);
+        }
+        let resolution = self.subscription.resolution_ms().max(1);
+        let start = ts - ts % resolution;
+        match &mut self.bucket {
+            Some(bucket) if bucket.start == start => {
+                bucket.add(value);
+                None
+            }
+            // events older than the open bucket are out of order and dropped
+            Some(bucket) if bucket.start > start => None,
+            bucket => bucket
+                .replace(Bucket::new(start, value))
+                .map(|finished| finished.point()),
+        }
+    }
+
+    pub fn points_event(&self, reset: bool, points: Vec<ChartPoint>) -> ChartPointsEvent {
+        let units = self
+            .quantity
+            .map(|quantity| {
+                let unit = FieldUnit {
+                    quantity,
+                    unit: quantity.unit(UnitSystem::Metric),
+                };
+                ["points.min", "points.max", "points.avg"]
+                    .into_iter()
+                    .map(|path| (path.to_string(), unit))
+                    .collect()
+            })
+            .unwrap_or_default();
+        ChartPointsEvent {
+            id: self.subscription.id.clone(),
+            reset,
+            points,
+            units,
+        }
+    }
+}
+
+/// Number at a dot separated path, see [`ChartSubscription::field`]
+fn field_value(data: &Value, path: &str) -> Option<f64> {
+    path.split('.')
+        .try_fold(data, |value, key| match value {
+            Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+            value => value.get(key),
+        })?
+        .as_f64()
+}
+
+/// Quantity of the value at `path` declared in the units of the event data
+fn field_quantity(data: &Value, path: &str) -> Option<DisplayQuantity> {
+    let units_path = path
+        .split('.')
+        .filter(|key| key.parse::<usize>().is_err())
+        .collect::<Vec<_>>()
+        .join(".");
+    let quantity = data.get(UNITS_FIELD)?.get(units_path)?.get("quantity")?;
+    DisplayQuantity::deserialize(quantity).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn subscription() -> ChartSubscription {
+        ChartSubscription {
+            id: "diameter".to_string(),
+            event: "LiveValuesEvent".to_string(),
+            field: "strand_diameters.1".to_string(),
+            window_ms: 1000,
+            points: 10,
+            mute_event: false,
+        }
+    }
+
+    fn data(diameter: f64) -> Value {
+        json!({
+            "strand_diameters": [0.0, diameter],
+            "units": { "strand_diameters": { "quantity": "diameter", "unit": "mm" } },
+        })
+    }
+
+    #[test]
+    fn test_push_decimates_into_buckets() {
+        let mut stream = ChartStream::new(subscription());
+        assert_eq!(stream.push(1000, &data(1.0)), None);
+        assert_eq!(stream.push(1050, &data(3.0)), None);
+        // out of order events are dropped
+        assert_eq!(stream.push(990, &data(9.0)), None);
+        assert_eq!(
+            stream.push(1100, &data(2.0)),
+            Some(ChartPoint {
+                ts: 1000,
+                min: 1.0,
+                max: 3.0,
+                avg: 2.0
+            })
+        );
+        assert_eq!(stream.push(1150, &json!({})), None);
+
+        let event = stream.points_event(false, vec![]);
+        assert_eq!(event.units["points.avg"].unit, "mm");
+    }
+
+    #[test]
+    fn test_backfill_window() {
+        let events: Vec<_> = (0..30)
+            .map(|i| {
+                let mut event: GenericEvent = Event::new("LiveValuesEvent", data(i as f64)).into();
+                event.ts = i * 100;
+                Arc::new(event)
+            })
+            .collect();
+        let mut stream = ChartStream::new(subscription());
+        let backfill = stream.backfill(&events, 2950);
+        assert!(backfill.reset);
+        // 2000 to 2800 are finished, 2900 stays open
+        assert_eq!(backfill.points.len(), 9);
+        assert_eq!(backfill.points[0].ts, 2000);
+        assert_eq!(
+            stream.push(3000, &data(30.0)).map(|point| point.avg),
+            Some(29.0)
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(subscription().validate().is_ok());
+        let no_points = ChartSubscription {
+            points: 0,
+            ..subscription()
+        };
+        assert!(no_points.validate().is_err());
+        let short_window = ChartSubscription {
+            window_ms: 5,
+            ..subscription()
+        };
+        assert!(short_window.validate().is_err());
+    }
+}
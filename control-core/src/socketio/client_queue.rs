@@ -0,0 +1,146 @@
+use super::event::GenericEvent;
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Events waiting for a single client, oldest first
+///
+/// Once `capacity` events wait, the oldest droppable event makes room for a new one. Events
+/// that aren't droppable, like state changes and alarms, are always kept, so the queue can
+/// grow past its capacity until the client is considered stalled.
+#[derive(Debug)]
+pub struct ClientQueue {
+    events: VecDeque<Arc<GenericEvent>>,
+    capacity: usize,
+    /// Since when the queue is at its capacity
+    full_since: Option<Instant>,
+    /// Events dropped since the client connected
+    pub dropped: u64,
+}
+
+impl ClientQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::new(),
+            capacity: capacity.max(1),
+            full_since: None,
+            dropped: 0,
+        }
+    }
+
+    pub fn push(&mut self, event: Arc<GenericEvent>, now: Instant) {
+        if self.events.len() >= self.capacity {
+            match self.events.iter().position(|queued| queued.droppable) {
+                Some(oldest) => {
+                    self.events.remove(oldest);
+                    self.dropped += 1;
+                }
+                // only events that must arrive wait, a newer value replaces this one
+                None if event.droppable => {
+                    self.dropped += 1;
+                    return;
+                }
+                None => {}
+            }
+        }
+        self.events.push_back(event);
+        if self.events.len() >= self.capacity && self.full_since.is_none() {
+            self.full_since = Some(now);
+        }
+    }
+
+    pub fn front(&self) -> Option<&Arc<GenericEvent>> {
+        self.events.front()
+    }
+
+    pub fn pop_front(&mut self) -> Option<Arc<GenericEvent>> {
+        let event = self.events.pop_front();
+        if self.events.len() < self.capacity {
+            self.full_since = None;
+        }
+        event
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+        self.full_since = None;
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Whether the client can't keep up: twice the capacity is waiting, or the queue didn't
+    /// drop below its capacity for `timeout`
+    pub fn is_stalled(&self, now: Instant, timeout: Duration) -> bool {
+        self.events.len() >= self.capacity.saturating_mul(2)
+            || self
+                .full_since
+                .is_some_and(|since| now.saturating_duration_since(since) >= timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(name: &str, ts: u64, droppable: bool) -> Arc<GenericEvent> {
+        Arc::new(GenericEvent {
+            name: name.to_string(),
+            data: Box::new(()),
+            ts,
+            mono: 0,
+            droppable,
+        })
+    }
+
+    #[test]
+    fn test_drops_oldest_live_values_only() {
+        let now = Instant::now();
+        let mut queue = ClientQueue::new(3);
+        queue.push(event("LiveValuesEvent", 1, true), now);
+        queue.push(event("StateEvent", 2, false), now);
+        queue.push(event("LiveValuesEvent", 3, true), now);
+
+        // full, the oldest live values make room
+        queue.push(event("LiveValuesEvent", 4, true), now);
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.dropped, 1);
+        assert_eq!(queue.front().unwrap().ts, 2);
+
+        // state events are never dropped
+        queue.push(event("StateEvent", 5, false), now);
+        queue.push(event("StateEvent", 6, false), now);
+        let order: Vec<u64> = std::iter::from_fn(|| queue.pop_front().map(|e| e.ts)).collect();
+        assert_eq!(order, vec![2, 5, 6]);
+        assert_eq!(queue.dropped, 3);
+    }
+
+    #[test]
+    fn test_stalled() {
+        let now = Instant::now();
+        let timeout = Duration::from_secs(10);
+        let mut queue = ClientQueue::new(2);
+        queue.push(event("LiveValuesEvent", 1, true), now);
+        queue.push(event("LiveValuesEvent", 2, true), now);
+        assert!(!queue.is_stalled(now + Duration::from_secs(5), timeout));
+        assert!(queue.is_stalled(now + timeout, timeout));
+
+        // the client caught up in time
+        queue.pop_front();
+        assert!(!queue.is_stalled(now + timeout, timeout));
+
+        // events that can't be dropped pile up
+        let mut queue = ClientQueue::new(2);
+        for ts in 0..4 {
+            queue.push(event("StateEvent", ts, false), now);
+        }
+        assert!(queue.is_stalled(now, timeout));
+    }
+}
@@ -0,0 +1,143 @@
+use super::event::GenericEvent;
+use socketioxide::{extract::SocketRef, socket::Sid};
+use std::sync::Arc;
+
+/// Receiver of queued events
+pub trait Recipient {
+    type Id: PartialEq;
+
+    fn id(&self) -> Self::Id;
+
+    /// Namespace of the recipient, only recipients of the same namespace share a frame
+    fn namespace(&self) -> &str;
+}
+
+impl Recipient for SocketRef {
+    type Id = Sid;
+
+    fn id(&self) -> Sid {
+        self.id
+    }
+
+    fn namespace(&self) -> &str {
+        self.ns()
+    }
+}
+
+/// An event and all recipients it is sent to, so it is encoded once for all of them
+#[derive(Debug)]
+pub struct Delivery<R> {
+    pub event: Arc<GenericEvent>,
+    pub recipients: Vec<R>,
+}
+
+/// Groups queued events that go to several recipients of a namespace into one [`Delivery`]
+///
+/// Namespaces queue an event for every subscribed socket right after each other, only these
+/// consecutive entries are merged. Every recipient still gets its events in queue order and
+/// never twice in the same delivery.
+pub fn coalesce<R: Recipient>(
+    items: impl IntoIterator<Item = (R, Arc<GenericEvent>)>,
+) -> Vec<Delivery<R>> {
+    let mut deliveries: Vec<Delivery<R>> = vec![];
+    for (recipient, event) in items {
+        if let Some(last) = deliveries.last_mut() {
+            if Arc::ptr_eq(&last.event, &event)
+                && last.recipients[0].namespace() == recipient.namespace()
+                && !last.recipients.iter().any(|r| r.id() == recipient.id())
+            {
+                last.recipients.push(recipient);
+                continue;
+            }
+        }
+        deliveries.push(Delivery {
+            event,
+            recipients: vec![recipient],
+        });
+    }
+    deliveries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestRecipient(u32, &'static str);
+
+    impl Recipient for TestRecipient {
+        type Id = u32;
+
+        fn id(&self) -> u32 {
+            self.0
+        }
+
+        fn namespace(&self) -> &str {
+            self.1
+        }
+    }
+
+    fn event(name: &str) -> Arc<GenericEvent> {
+        Arc::new(GenericEvent {
+            name: name.to_string(),
+            data: Box::new(()),
+            ts: 0,
+            mono: 0,
+            droppable: false,
+        })
+    }
+
+    fn ids(deliveries: &[Delivery<TestRecipient>]) -> Vec<(String, Vec<u32>)> {
+        deliveries
+            .iter()
+            .map(|d| {
+                let ids = d.recipients.iter().map(|r| r.0).collect();
+                (d.event.name.clone(), ids)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_coalesce_consecutive() {
+        let (a, b) = (event("a"), event("b"));
+        let deliveries = coalesce([
+            (TestRecipient(1, "/machine"), a.clone()),
+            (TestRecipient(2, "/machine"), a.clone()),
+            (TestRecipient(3, "/machine"), a.clone()),
+            (TestRecipient(1, "/machine"), b.clone()),
+            (TestRecipient(2, "/machine"), b),
+            // queued again, e.g. a reemit for a new socket
+            (TestRecipient(4, "/machine"), a),
+        ]);
+        assert_eq!(
+            ids(&deliveries),
+            vec![
+                ("a".to_string(), vec![1, 2, 3]),
+                ("b".to_string(), vec![1, 2]),
+                ("a".to_string(), vec![4]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_coalesce_keeps_apart() {
+        let a = event("a");
+        let deliveries = coalesce([
+            (TestRecipient(1, "/machine"), a.clone()),
+            // other namespace
+            (TestRecipient(2, "/main"), a.clone()),
+            // same recipient twice
+            (TestRecipient(2, "/main"), a),
+            // equal but not the same event
+            (TestRecipient(3, "/main"), event("a")),
+        ]);
+        assert_eq!(
+            ids(&deliveries),
+            vec![
+                ("a".to_string(), vec![1]),
+                ("a".to_string(), vec![2]),
+                ("a".to_string(), vec![2]),
+                ("a".to_string(), vec![3]),
+            ]
+        );
+    }
+}
@@ -1,5 +1,6 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::Arc;
 
+use crate::helpers::clock::{monotonic_millis, unix_millis};
 use erased_serde::Serialize as ErasedSerialize;
 use serde::Serialize;
 
@@ -9,6 +10,18 @@ pub struct GenericEvent {
     pub data: Box<dyn ErasedSerialize + Send + Sync>,
     /// Timestamp in milliseconds
     pub ts: u64,
+    /// Milliseconds since the server started, see [`monotonic_millis`]
+    pub mono: u64,
+    /// Superseded by the next event of its name, a client falling behind may miss it
+    #[serde(skip)]
+    pub droppable: bool,
+}
+
+/// Events that only carry the latest values, see [`GenericEvent::droppable`]
+pub const DROPPABLE_EVENTS: &[&str] = &["LiveValuesEvent"];
+
+pub fn is_droppable(event: &str) -> bool {
+    DROPPABLE_EVENTS.contains(&event)
 }
 
 impl std::fmt::Debug for GenericEvent {
@@ -17,10 +30,38 @@ impl std::fmt::Debug for GenericEvent {
             .field("name", &self.name)
             .field("data", &"[erased]")
             .field("ts", &self.ts)
+            .field("mono", &self.mono)
+            .field("droppable", &self.droppable)
             .finish()
     }
 }
 
+/// Name of the event carrying an [`EventBatch`]
+pub const EVENT_BATCH: &str = "EventBatch";
+
+/// Events coalesced into a single socketio frame, oldest first
+#[derive(Debug, Clone)]
+pub struct EventBatch(pub Vec<Arc<GenericEvent>>);
+
+impl Serialize for EventBatch {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(|event| event.as_ref()))
+    }
+}
+
+impl EventBatch {
+    /// The batch can only be dropped if every event in it can
+    pub fn build(self) -> GenericEvent {
+        GenericEvent {
+            name: EVENT_BATCH.to_string(),
+            ts: self.0.last().map(|event| event.ts).unwrap_or_default(),
+            mono: self.0.last().map(|event| event.mono).unwrap_or_default(),
+            droppable: !self.0.is_empty() && self.0.iter().all(|event| event.droppable),
+            data: Box::new(self),
+        }
+    }
+}
+
 pub trait BuildEvent: Serialize + Send + Sync + Clone {
     fn build(&self) -> Event<Self>;
 }
@@ -34,6 +75,8 @@ where
     pub data: T,
     /// Timestamp in milliseconds
     pub ts: u64,
+    /// Milliseconds since the server started, see [`monotonic_millis`]
+    pub mono: u64,
 }
 
 impl<T> From<Event<T>> for GenericEvent
@@ -42,9 +85,11 @@ where
 {
     fn from(event: Event<T>) -> Self {
         Self {
+            droppable: is_droppable(&event.name),
             name: event.name,
             data: Box::new(event.data),
             ts: event.ts,
+            mono: event.mono,
         }
     }
 }
@@ -58,6 +103,8 @@ where
             name: event.name.clone(),
             data: Box::new(event.data.clone()),
             ts: event.ts,
+            mono: event.mono,
+            droppable: is_droppable(&event.name),
         }
     }
 }
@@ -70,10 +117,8 @@ where
         Self {
             name: event.to_string(),
             data,
-            ts: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_millis() as u64,
+            ts: unix_millis(),
+            mono: monotonic_millis(),
         }
     }
 }
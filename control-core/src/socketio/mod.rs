@@ -1,3 +1,10 @@
+pub mod chart;
+pub mod client_queue;
+pub mod delivery;
 pub mod event;
 pub mod namespace;
 pub mod namespace_id;
+pub mod rate_limit;
+pub mod recorder;
+pub mod snapshot;
+pub mod units;
@@ -1,7 +1,18 @@
-use crate::socketio::event::GenericEvent;
+use crate::socketio::{
+    chart::{ChartStream, ChartSubscription},
+    event::{EventBatch, GenericEvent},
+    namespace_id::NamespaceId,
+    rate_limit::{AdaptiveThrottle, EmitRateLimits, emit_rate_limits, rate_to_interval},
+    recorder::record_event,
+    snapshot::SnapshotCell,
+};
 use smol::channel::Sender;
 use socketioxide::extract::SocketRef;
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 use tracing::instrument;
 
 #[derive(Debug)]
@@ -9,19 +20,151 @@ pub struct Namespace {
     pub sockets: Vec<SocketRef>,
     pub events: HashMap<String, Vec<Arc<GenericEvent>>>,
     pub socket_queue_tx: Sender<(SocketRef, Arc<GenericEvent>)>,
+    /// Number of events emitted since the namespace was created
+    pub emitted_events: u64,
+    /// Latest emitted event per event name, kept regardless of the cache function
+    /// so reconnecting clients always get the current value
+    pub latest_events: HashMap<String, Arc<GenericEvent>>,
+    /// Hold back emitted events until [`Namespace::flush`] sends them as one frame
+    pub batching: bool,
+    /// Events waiting for the next [`Namespace::flush`]
+    pub pending: Vec<Arc<GenericEvent>>,
+    /// Minimum time between two emitted events by event name
+    pub rate_limits: HashMap<String, Duration>,
+    /// Adaptive emit rates by event name, see [`crate::socketio::rate_limit::AdaptiveEmitRate`]
+    adaptive_rates: HashMap<String, AdaptiveThrottle>,
+    /// Time of the last emitted event of every rate limited event name
+    last_emits: HashMap<String, Instant>,
+    /// Decimated charts subscribed by sockets
+    charts: Vec<(SocketRef, ChartStream)>,
+    /// Latest events for readers that must not wait for the namespace lock
+    pub snapshot: Arc<SnapshotCell>,
+    /// Emitted events are recorded under this id, see [`super::recorder`]
+    pub id: Option<NamespaceId>,
 }
 
 impl Namespace {
     pub fn new(socket_queue_tx: Sender<(SocketRef, Arc<GenericEvent>)>) -> Self {
+        let limits = emit_rate_limits();
         Self {
             sockets: vec![],
             events: HashMap::new(),
             socket_queue_tx,
+            emitted_events: 0,
+            latest_events: HashMap::new(),
+            batching: false,
+            pending: vec![],
+            rate_limits: rate_intervals(&limits),
+            adaptive_rates: adaptive_throttles(&limits),
+            last_emits: HashMap::new(),
+            charts: vec![],
+            snapshot: Arc::new(SnapshotCell::new()),
+            id: None,
         }
     }
+
+    /// Records the emitted events under `id` while a recording runs
+    pub fn with_id(mut self, id: NamespaceId) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// Namespace that batches its events, used by namespaces emitting in every act cycle
+    pub fn new_batching(socket_queue_tx: Sender<(SocketRef, Arc<GenericEvent>)>) -> Self {
+        let mut namespace = Self::new(socket_queue_tx);
+        namespace.batching = true;
+        namespace
+    }
+}
+
+fn rate_intervals(limits: &EmitRateLimits) -> HashMap<String, Duration> {
+    limits
+        .max_rate_hz
+        .iter()
+        .filter_map(|(event, rate)| Some((event.clone(), rate_to_interval(*rate)?)))
+        .collect()
+}
+
+fn adaptive_throttles(limits: &EmitRateLimits) -> HashMap<String, AdaptiveThrottle> {
+    limits
+        .adaptive
+        .iter()
+        .filter_map(|(event, rate)| Some((event.clone(), AdaptiveThrottle::new(rate)?)))
+        .collect()
 }
 
 impl Namespace {
+    /// Replaces the emit rates of this namespace, e.g. after the configured limits changed
+    pub fn apply_rate_limits(&mut self, limits: &EmitRateLimits) {
+        self.rate_limits = rate_intervals(limits);
+        self.adaptive_rates = adaptive_throttles(limits);
+        self.last_emits.retain(|event, _| {
+            self.rate_limits.contains_key(event) || self.adaptive_rates.contains_key(event)
+        });
+    }
+
+    /// Tracks the value an event with an adaptive emit rate follows, call it before each emit
+    ///
+    /// Does nothing for events without an adaptive rate.
+    pub fn track_value(&mut self, event: &str, value: f64) {
+        if let Some(throttle) = self.adaptive_rates.get_mut(event) {
+            throttle.track(value);
+        }
+    }
+
+    /// Overrides the configured emit rate of an event in this namespace, `None` removes the limit
+    pub fn set_rate_limit(&mut self, event: &str, max_rate_hz: Option<f64>) {
+        match max_rate_hz.and_then(rate_to_interval) {
+            Some(interval) => {
+                self.rate_limits.insert(event.to_string(), interval);
+            }
+            None => {
+                self.rate_limits.remove(event);
+            }
+        }
+    }
+
+    /// Whether an event of this name would be emitted now, to skip building rate limited events
+    ///
+    /// Ignores adaptive rates, whether their event is emitted depends on its value.
+    pub fn is_due(&self, event: &str) -> bool {
+        match (self.rate_limits.get(event), self.last_emits.get(event)) {
+            (Some(min_interval), Some(last_emit)) => last_emit.elapsed() >= *min_interval,
+            _ => true,
+        }
+    }
+
+    fn record(&self, event: &GenericEvent) {
+        if let Some(id) = &self.id {
+            record_event(id, event);
+        }
+    }
+
+    /// Whether an event has to be dropped because its name was emitted too recently
+    fn is_rate_limited(&mut self, event: &GenericEvent, now: Instant) -> bool {
+        let min_interval = self.rate_limits.get(&event.name);
+        let throttle = self.adaptive_rates.get_mut(&event.name);
+        if min_interval.is_none() && throttle.is_none() {
+            return false;
+        }
+        let since_last_emit = self
+            .last_emits
+            .get(&event.name)
+            .map(|last_emit| now.saturating_duration_since(*last_emit));
+
+        let too_fast = min_interval
+            .zip(since_last_emit)
+            .is_some_and(|(min_interval, elapsed)| elapsed < *min_interval);
+        if too_fast {
+            return true;
+        }
+        if throttle.is_some_and(|throttle| !throttle.should_emit(since_last_emit)) {
+            return true;
+        }
+        self.last_emits.insert(event.name.clone(), now);
+        false
+    }
+
     /// Adds a socket to the namespace.
     ///
     /// # Arguments
@@ -42,6 +185,75 @@ impl Namespace {
     pub fn unsubscribe(&mut self, socket: SocketRef) {
         // remove the socket from the list
         self.sockets.retain(|s| s.id != socket.id);
+        self.charts.retain(|(s, _)| s.id != socket.id);
+    }
+
+    /// Streams a decimated value to a socket, replacing a chart of the socket with the same id
+    ///
+    /// The points of the cached events in the window are sent right away.
+    #[instrument(skip_all)]
+    pub fn subscribe_chart(
+        &mut self,
+        socket: SocketRef,
+        subscription: ChartSubscription,
+    ) -> Result<(), anyhow::Error> {
+        subscription.validate()?;
+        self.unsubscribe_chart(&socket, &subscription.id);
+
+        let mut stream = ChartStream::new(subscription);
+        let cached = self
+            .events
+            .get(&stream.subscription.event)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let backfill = Arc::new(stream.backfill(cached, now).build());
+        self.send_to_queue(&socket, &backfill, "subscribe_chart");
+        self.charts.push((socket, stream));
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    pub fn unsubscribe_chart(&mut self, socket: &SocketRef, id: &str) {
+        self.charts
+            .retain(|(s, chart)| s.id != socket.id || chart.subscription.id != id);
+    }
+
+    /// Whether the socket subscribed a chart replacing the full rate event
+    fn is_muted(&self, socket: &SocketRef, event: &str) -> bool {
+        self.charts.iter().any(|(s, chart)| {
+            s.id == socket.id && chart.subscription.mute_event && chart.subscription.event == event
+        })
+    }
+
+    /// Sends the points finished by an event to the sockets charting it
+    fn update_charts(&mut self, event: &GenericEvent) {
+        if !self
+            .charts
+            .iter()
+            .any(|(_, chart)| chart.subscription.event == event.name)
+        {
+            return;
+        }
+        let Ok(data) = serde_json::to_value(&event.data) else {
+            return;
+        };
+        let mut updates = vec![];
+        for (socket, chart) in &mut self.charts {
+            if chart.subscription.event != event.name {
+                continue;
+            }
+            if let Some(point) = chart.push(event.ts, &data) {
+                let points = chart.points_event(false, vec![point]).build();
+                updates.push((socket.clone(), Arc::new(points)));
+            }
+        }
+        for (socket, points) in updates {
+            self.send_to_queue(&socket, &points, "chart");
+        }
     }
 
     /// Disconnects all clients in the namespace.
@@ -63,27 +275,60 @@ impl Namespace {
     /// Re-emits cached events to a specific socket.
     ///
     /// This is typically used when a socket reconnects or joins an existing namespace
-    /// to bring it up to date with the current state.
+    /// to bring it up to date with the current state. Batching namespaces replay the events
+    /// as a single frame.
     ///
     /// # Arguments
     ///
     /// * `socket` - A reference to the socket that will receive the cached events
     #[instrument(skip_all)]
     pub fn reemit(&mut self, socket: SocketRef) {
+        let replay = self.replay_events();
+        if self.batching && !replay.is_empty() {
+            // the client needs the history for its charts, so unlike live values it must not
+            // be dropped when the client is slow
+            let mut batch = EventBatch(replay).build();
+            batch.droppable = false;
+            self.send_to_queue(&socket, &Arc::new(batch), "reemit");
+            return;
+        }
+        for event in replay {
+            // Send to global queue instead of per-socket queue
+            self.send_to_queue(&socket, &event, "reemit");
+        }
+    }
+
+    /// Events sent to a newly connected socket.
+    ///
+    /// Cached events are grouped by name, groups with the lowest count first. Each group is
+    /// followed by the latest event of its name if the cache function did not keep it.
+    pub fn replay_events(&self) -> Vec<Arc<GenericEvent>> {
         // Collect events grouped by name/kind with their counts for sorting
-        let mut event_groups: Vec<(&String, &Vec<Arc<GenericEvent>>)> =
-            self.events.iter().collect();
+        let mut event_groups: Vec<(&String, &[Arc<GenericEvent>])> = self
+            .events
+            .iter()
+            .map(|(name, events)| (name, events.as_slice()))
+            .chain(
+                self.latest_events
+                    .keys()
+                    .filter(|name| !self.events.contains_key(*name))
+                    .map(|name| (name, &[][..])),
+            )
+            .collect();
 
         // Sort by event count (ascending - lowest count first)
         event_groups.sort_by(|a, b| a.1.len().cmp(&b.1.len()));
 
-        // Emit events in order of lowest count first
-        for (_event_name, events) in event_groups {
-            for event in events {
-                // Send to global queue instead of per-socket queue
-                self.send_to_queue(&socket, event, "reemit");
+        let mut replay = vec![];
+        for (event_name, events) in event_groups {
+            replay.extend(events.iter().cloned());
+            if let Some(latest) = self.latest_events.get(event_name) {
+                if !events.last().is_some_and(|last| Arc::ptr_eq(last, latest)) {
+                    replay.push(latest.clone());
+                }
             }
         }
+        replay
     }
 
     /// Caches an event with a specific key for later retrieval.
@@ -105,7 +350,7 @@ impl Namespace {
 
     /// Emits an event to all sockets in the namespace and caches it.
     ///
-    ///
+    /// Events exceeding the rate limit of their name are dropped.
     /// # Arguments
     ///
     /// * `event` - The event to be emitted and cached
@@ -116,13 +361,84 @@ impl Namespace {
         event: Arc<GenericEvent>,
         buffer_fn: &Box<dyn Fn(&mut Vec<Arc<GenericEvent>>, &Arc<GenericEvent>)>,
     ) {
+        if self.is_rate_limited(&event, Instant::now()) {
+            return;
+        }
+        self.emitted_events += 1;
+        self.record(&event);
+
         // cache the event
         self.cache(event.clone(), buffer_fn);
+        self.latest_events.insert(event.name.clone(), event.clone());
+        self.update_charts(&event);
+
+        // a newer value of the same event makes a pending one stale
+        if self.batching {
+            self.pending.retain(|pending| pending.name != event.name);
+            self.pending.push(event);
+            return;
+        }
+        self.snapshot.publish(&self.latest_events);
 
         // emit the event - inlined from emit function
-        // Send to global queue for each socket in the namespace
-        for socket in self.sockets.clone() {
-            self.send_to_queue(&socket, &event, "emit");
+        // Send to global queue for each socket in the namespace, the queue encodes
+        // the event once for all of them
+        for socket in &self.sockets {
+            if !self.is_muted(socket, &event.name) {
+                self.send_to_queue(socket, &event, "emit");
+            }
+        }
+    }
+
+    /// Emits an event to all sockets in the namespace without caching it.
+    ///
+    /// Used for notifications that only matter at the moment they happen and must not be
+    /// replayed to clients connecting later.
+    #[instrument(skip_all)]
+    pub fn emit_transient(&mut self, event: Arc<GenericEvent>) {
+        self.emitted_events += 1;
+        self.record(&event);
+        // every notification counts, they are never deduplicated
+        if self.batching {
+            self.pending.push(event);
+            return;
+        }
+        for socket in &self.sockets {
+            self.send_to_queue(socket, &event, "emit_transient");
+        }
+    }
+
+    /// Sends the pending events of a batching namespace as a single frame to all sockets.
+    ///
+    /// A single pending event is sent as is, several are wrapped in an [`EventBatch`].
+    #[instrument(skip_all)]
+    pub fn flush(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let pending = std::mem::take(&mut self.pending);
+        // readers see the events of a batch together
+        self.snapshot.publish(&self.latest_events);
+        if self.sockets.is_empty() {
+            return;
+        }
+        let frame = batch_frame(pending.clone());
+        for socket in &self.sockets {
+            if !self.charts.iter().any(|(s, _)| s.id == socket.id) {
+                self.send_to_queue(socket, &frame, "flush");
+                continue;
+            }
+            // sockets with charts may have muted some of the events
+            let unmuted = pending
+                .iter()
+                .filter(|event| !self.is_muted(socket, &event.name))
+                .cloned()
+                .collect::<Vec<_>>();
+            match unmuted.len() {
+                0 => {}
+                n if n == pending.len() => self.send_to_queue(socket, &frame, "flush"),
+                _ => self.send_to_queue(socket, &batch_frame(unmuted), "flush"),
+            }
         }
     }
 
@@ -166,6 +482,14 @@ impl Namespace {
     }
 }
 
+/// A single event as is, several wrapped in an [`EventBatch`]
+fn batch_frame(mut events: Vec<Arc<GenericEvent>>) -> Arc<GenericEvent> {
+    match events.len() {
+        1 => events.remove(0),
+        _ => Arc::new(EventBatch(events).build()),
+    }
+}
+
 impl Drop for Namespace {
     fn drop(&mut self) {
         self.disconnect_all();
@@ -262,11 +586,12 @@ pub fn cache_duration(duration: Duration, bucket_size: Duration) -> CacheFn {
 
 #[cfg(test)]
 mod tests {
-    use std::cmp::min;
+    use std::{cmp::min, collections::BTreeMap};
 
     use serde::Serialize;
 
     use super::*;
+    use crate::socketio::rate_limit::AdaptiveEmitRate;
 
     #[derive(Debug, Clone, Serialize)]
     struct TestEventData {
@@ -288,6 +613,8 @@ mod tests {
             name: "test_event".to_string(),
             data: Box::new(TestEventData { value: 1 }),
             ts: 0,
+            mono: 0,
+            droppable: false,
         });
         namespace.cache(event1, &cache_fn);
 
@@ -302,6 +629,8 @@ mod tests {
             name: "test_event".to_string(),
             data: Box::new(TestEventData { value: 2 }),
             ts: 1,
+            mono: 0,
+            droppable: false,
         });
         namespace.cache(event2, &cache_fn);
 
@@ -317,6 +646,8 @@ mod tests {
             name: "test_event".to_string(),
             data: Box::new(TestEventData { value: 3 }),
             ts: 2,
+            mono: 0,
+            droppable: false,
         });
         namespace.cache(event3, &cache_fn);
 
@@ -329,6 +660,223 @@ mod tests {
         assert_eq!(namespace.events.get("test_event").unwrap()[1].ts, 2);
     }
 
+    #[test]
+    fn test_replay_events_include_latest() {
+        // Caches at most one event per second
+        let cache_fn = cache_duration(Duration::from_secs(10), Duration::from_secs(1));
+        let (queue_tx, _queue_rx) = smol::channel::unbounded();
+        let mut namespace = Namespace::new(queue_tx);
+
+        for ts in [1000, 1500] {
+            let event = Arc::new(GenericEvent {
+                name: "test_event".to_string(),
+                data: Box::new(TestEventData { value: 0 }),
+                ts,
+                mono: 0,
+                droppable: false,
+            });
+            namespace.emit(event, &cache_fn);
+        }
+
+        // the second event is not cached but still replayed
+        assert_eq!(namespace.events.get("test_event").unwrap().len(), 1);
+        let replay: Vec<_> = namespace.replay_events().iter().map(|e| e.ts).collect();
+        assert_eq!(replay, vec![1000, 1500]);
+
+        // the latest event is not replayed twice when it is cached
+        let event = Arc::new(GenericEvent {
+            name: "test_event".to_string(),
+            data: Box::new(TestEventData { value: 0 }),
+            ts: 2000,
+            mono: 0,
+            droppable: false,
+        });
+        namespace.emit(event, &cache_fn);
+        let replay: Vec<_> = namespace.replay_events().iter().map(|e| e.ts).collect();
+        assert_eq!(replay, vec![1000, 2000]);
+    }
+
+    #[test]
+    fn test_transient_events_are_not_replayed() {
+        let (queue_tx, _queue_rx) = smol::channel::unbounded();
+        let mut namespace = Namespace::new(queue_tx);
+        namespace.emit_transient(Arc::new(GenericEvent {
+            name: "test_event".to_string(),
+            data: Box::new(TestEventData { value: 0 }),
+            ts: 1000,
+            mono: 0,
+            droppable: false,
+        }));
+        assert_eq!(namespace.emitted_events, 1);
+        assert!(namespace.replay_events().is_empty());
+    }
+
+    #[test]
+    fn test_batching_deduplicates_stale_events() {
+        let (queue_tx, _queue_rx) = smol::channel::unbounded();
+        let mut namespace = Namespace::new_batching(queue_tx);
+        let cache_fn = cache_one_event();
+        let event = |name: &str, ts| {
+            Arc::new(GenericEvent {
+                name: name.to_string(),
+                data: Box::new(TestEventData { value: 0 }),
+                ts,
+                mono: 0,
+                droppable: false,
+            })
+        };
+
+        namespace.emit(event("live_values", 1), &cache_fn);
+        namespace.emit(event("state", 2), &cache_fn);
+        namespace.emit(event("live_values", 3), &cache_fn);
+        namespace.emit_transient(event("notification", 4));
+        namespace.emit_transient(event("notification", 5));
+
+        let pending: Vec<_> = namespace
+            .pending
+            .iter()
+            .map(|e| (e.name.as_str(), e.ts))
+            .collect();
+        assert_eq!(
+            pending,
+            vec![
+                ("state", 2),
+                ("live_values", 3),
+                ("notification", 4),
+                ("notification", 5)
+            ]
+        );
+        // events are cached when emitted, not when flushed
+        assert_eq!(namespace.events.get("live_values").unwrap()[0].ts, 3);
+        assert!(namespace.snapshot.load().events.is_empty());
+
+        namespace.flush();
+        assert!(namespace.pending.is_empty());
+        assert_eq!(namespace.snapshot.load().get("live_values").unwrap().ts, 3);
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let (queue_tx, _queue_rx) = smol::channel::unbounded();
+        let mut namespace = Namespace::new(queue_tx);
+        namespace.set_rate_limit("live_values", Some(10.0));
+        let event = |name: &str| GenericEvent {
+            name: name.to_string(),
+            data: Box::new(TestEventData { value: 0 }),
+            ts: 0,
+            mono: 0,
+            droppable: false,
+        };
+
+        let start = Instant::now();
+        assert!(!namespace.is_rate_limited(&event("live_values"), start));
+        assert!(!namespace.is_due("live_values"));
+        assert!(namespace.is_due("state"));
+        assert!(
+            namespace.is_rate_limited(&event("live_values"), start + Duration::from_millis(50))
+        );
+        assert!(
+            !namespace.is_rate_limited(&event("live_values"), start + Duration::from_millis(100))
+        );
+        // other events are not limited
+        assert!(!namespace.is_rate_limited(&event("state"), start));
+        assert!(!namespace.is_rate_limited(&event("state"), start));
+
+        namespace.set_rate_limit("live_values", None);
+        assert!(
+            !namespace.is_rate_limited(&event("live_values"), start + Duration::from_millis(101))
+        );
+
+        // reloaded limits replace the previous ones
+        namespace.apply_rate_limits(&EmitRateLimits {
+            max_rate_hz: [("state".to_string(), 1.0)].into(),
+            adaptive: BTreeMap::new(),
+        });
+        assert!(namespace.is_due("live_values"));
+        assert!(!namespace.is_rate_limited(&event("state"), start));
+        assert!(namespace.is_rate_limited(&event("state"), start + Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn test_adaptive_rate() {
+        let (queue_tx, _queue_rx) = smol::channel::unbounded();
+        let mut namespace = Namespace::new(queue_tx);
+        namespace.apply_rate_limits(&EmitRateLimits {
+            max_rate_hz: [("live_values".to_string(), 10.0)].into(),
+            adaptive: [(
+                "live_values".to_string(),
+                AdaptiveEmitRate {
+                    heartbeat_hz: 1.0,
+                    min_change: 0.01,
+                },
+            )]
+            .into(),
+        });
+        let event = GenericEvent {
+            name: "live_values".to_string(),
+            data: Box::new(TestEventData { value: 0 }),
+            ts: 0,
+            mono: 0,
+            droppable: false,
+        };
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        // the emitter doesn't track a value yet, only the maximum rate applies
+        assert!(!namespace.is_rate_limited(&event, at(0)));
+        assert!(!namespace.is_rate_limited(&event, at(100)));
+
+        // a stable value waits for the heartbeat
+        namespace.track_value("live_values", 1.75);
+        assert!(!namespace.is_rate_limited(&event, at(200)));
+        namespace.track_value("live_values", 1.755);
+        assert!(namespace.is_rate_limited(&event, at(300)));
+        assert!(!namespace.is_rate_limited(&event, at(1200)));
+
+        // a changing value is emitted at the maximum rate
+        namespace.track_value("live_values", 1.80);
+        assert!(namespace.is_rate_limited(&event, at(1250)));
+        assert!(!namespace.is_rate_limited(&event, at(1300)));
+        namespace.track_value("live_values", 1.70);
+        assert!(!namespace.is_rate_limited(&event, at(1400)));
+    }
+
+    #[test]
+    fn test_event_batch_serialization() {
+        let batch = EventBatch(vec![
+            Arc::new(GenericEvent {
+                name: "a".to_string(),
+                data: Box::new(TestEventData { value: 1 }),
+                ts: 1,
+                mono: 0,
+                droppable: true,
+            }),
+            Arc::new(GenericEvent {
+                name: "b".to_string(),
+                data: Box::new(TestEventData { value: 2 }),
+                ts: 2,
+                mono: 0,
+                droppable: false,
+            }),
+        ])
+        .build();
+        assert_eq!(batch.ts, 2);
+        // "b" has to arrive, so the batch does too
+        assert!(!batch.droppable);
+        assert_eq!(
+            serde_json::to_value(&batch).unwrap(),
+            serde_json::json!({
+                "name": "EventBatch",
+                "data": [
+                    { "name": "a", "data": { "value": 1 }, "ts": 1, "mono": 0 },
+                    { "name": "b", "data": { "value": 2 }, "ts": 2, "mono": 0 }
+                ],
+                "ts": 2,
+                "mono": 0
+            })
+        );
+    }
+
     #[test]
     /// duration: 10 seconds, bucket_size: 1 second
     /// use a for loop that tries to add an event every 100ms
@@ -349,6 +897,8 @@ mod tests {
                 name: "test_event".to_string(),
                 data: Box::new(TestEventData { value: i }),
                 ts: (i * 100) as u64,
+                mono: 0,
+                droppable: false,
             });
             namespace.cache(event, &cache_fn);
 
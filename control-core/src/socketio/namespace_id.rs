@@ -8,7 +8,18 @@ use crate::machines::identification::{MachineIdentification, MachineIdentificati
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum NamespaceId {
     Main,
+    Recipes,
+    Batches,
+    Alarms,
+    Registry,
+    Diagnostics,
+    Sequences,
     Machine(MachineIdentificationUnique),
+    /// Machine of another control server, proxied in federation mode
+    Remote {
+        server: String,
+        machine: MachineIdentificationUnique,
+    },
 }
 
 impl Serialize for NamespaceId {
@@ -18,6 +29,12 @@ impl Serialize for NamespaceId {
     {
         match self {
             Self::Main => serializer.serialize_str("/main"),
+            Self::Recipes => serializer.serialize_str("/recipes"),
+            Self::Batches => serializer.serialize_str("/batches"),
+            Self::Alarms => serializer.serialize_str("/alarms"),
+            Self::Registry => serializer.serialize_str("/registry"),
+            Self::Diagnostics => serializer.serialize_str("/diagnostics"),
+            Self::Sequences => serializer.serialize_str("/sequences"),
             Self::Machine(id) => {
                 let path = format!(
                     "/machine/{}/{}/{}",
@@ -25,6 +42,7 @@ impl Serialize for NamespaceId {
                 );
                 serializer.serialize_str(&path)
             }
+            Self::Remote { .. } => serializer.serialize_str(&self.to_string()),
         }
     }
 }
@@ -51,6 +69,34 @@ impl<'de> Deserialize<'de> for NamespaceId {
                     return Ok(NamespaceId::Main);
                 }
 
+                if value == "/recipes" {
+                    return Ok(NamespaceId::Recipes);
+                }
+
+                if value == "/batches" {
+                    return Ok(NamespaceId::Batches);
+                }
+
+                if value == "/alarms" {
+                    return Ok(NamespaceId::Alarms);
+                }
+
+                if value == "/registry" {
+                    return Ok(NamespaceId::Registry);
+                }
+
+                if value == "/diagnostics" {
+                    return Ok(NamespaceId::Diagnostics);
+                }
+
+                if value == "/sequences" {
+                    return Ok(NamespaceId::Sequences);
+                }
+
+                if value.starts_with("/remote/") {
+                    return NamespaceId::from_str(value).map_err(E::custom);
+                }
+
                 if let Some(machine_path) = value.strip_prefix("/machine/") {
                     let parts: Vec<&str> = machine_path.split('/').collect();
                     if parts.len() == 3 {
@@ -88,6 +134,44 @@ impl FromStr for NamespaceId {
             return Ok(Self::Main);
         }
 
+        if s == "/recipes" {
+            return Ok(Self::Recipes);
+        }
+
+        if s == "/batches" {
+            return Ok(Self::Batches);
+        }
+
+        if s == "/alarms" {
+            return Ok(Self::Alarms);
+        }
+
+        if s == "/registry" {
+            return Ok(Self::Registry);
+        }
+
+        if s == "/diagnostics" {
+            return Ok(Self::Diagnostics);
+        }
+
+        if s == "/sequences" {
+            return Ok(Self::Sequences);
+        }
+
+        if let Some(remote_path) = s.strip_prefix("/remote/") {
+            let (server, machine_path) = remote_path
+                .split_once('/')
+                .filter(|(server, _)| !server.is_empty())
+                .ok_or_else(|| format!("Invalid remote namespace path: {}", s))?;
+            return match Self::from_str(&format!("/{}", machine_path))? {
+                Self::Machine(machine) => Ok(Self::Remote {
+                    server: server.to_string(),
+                    machine,
+                }),
+                _ => Err(format!("Invalid remote namespace path: {}", s)),
+            };
+        }
+
         if let Some(machine_path) = s.strip_prefix("/machine/") {
             let parts: Vec<&str> = machine_path.split('/').collect();
             if parts.len() == 3 {
@@ -117,6 +201,12 @@ impl fmt::Display for NamespaceId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Main => write!(f, "/main"),
+            Self::Recipes => write!(f, "/recipes"),
+            Self::Batches => write!(f, "/batches"),
+            Self::Alarms => write!(f, "/alarms"),
+            Self::Registry => write!(f, "/registry"),
+            Self::Diagnostics => write!(f, "/diagnostics"),
+            Self::Sequences => write!(f, "/sequences"),
             Self::Machine(id) => {
                 write!(
                     f,
@@ -124,6 +214,9 @@ impl fmt::Display for NamespaceId {
                     id.machine_identification.vendor, id.machine_identification.machine, id.serial
                 )
             }
+            Self::Remote { server, machine } => {
+                write!(f, "/remote/{}{}", server, Self::Machine(machine.clone()))
+            }
         }
     }
 }
@@ -177,6 +270,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_remote_machine() {
+        let namespace_id = NamespaceId::Remote {
+            server: "line2".to_string(),
+            machine: MachineIdentificationUnique {
+                machine_identification: MachineIdentification {
+                    vendor: 1,
+                    machine: 2,
+                },
+                serial: 3,
+            },
+        };
+        let serialized = to_string(&namespace_id).unwrap();
+        assert_eq!(serialized, "\"/remote/line2/machine/1/2/3\"");
+        assert_eq!(from_str::<NamespaceId>(&serialized).unwrap(), namespace_id);
+
+        assert!(NamespaceId::from_str("/remote//machine/1/2/3").is_err());
+        assert!(NamespaceId::from_str("/remote/line2/main").is_err());
+    }
+
     #[test]
     fn test_deserialize_invalid_path() {
         let json = "\"/invalid/path\"";
@@ -204,6 +317,78 @@ mod tests {
         assert!(matches!(namespace_id, NamespaceId::Main));
     }
 
+    #[test]
+    fn test_roundtrip_recipes() {
+        let serialized = to_string(&NamespaceId::Recipes).unwrap();
+        assert_eq!(serialized, "\"/recipes\"");
+        let deserialized: NamespaceId = from_str(&serialized).unwrap();
+        assert_eq!(deserialized, NamespaceId::Recipes);
+        assert_eq!(
+            NamespaceId::from_str("/recipes").unwrap(),
+            NamespaceId::Recipes
+        );
+    }
+
+    #[test]
+    fn test_roundtrip_batches() {
+        let serialized = to_string(&NamespaceId::Batches).unwrap();
+        assert_eq!(serialized, "\"/batches\"");
+        let deserialized: NamespaceId = from_str(&serialized).unwrap();
+        assert_eq!(deserialized, NamespaceId::Batches);
+        assert_eq!(
+            NamespaceId::from_str("/batches").unwrap(),
+            NamespaceId::Batches
+        );
+    }
+
+    #[test]
+    fn test_roundtrip_alarms() {
+        let serialized = to_string(&NamespaceId::Alarms).unwrap();
+        assert_eq!(serialized, "\"/alarms\"");
+        let deserialized: NamespaceId = from_str(&serialized).unwrap();
+        assert_eq!(deserialized, NamespaceId::Alarms);
+        assert_eq!(
+            NamespaceId::from_str("/alarms").unwrap(),
+            NamespaceId::Alarms
+        );
+    }
+
+    #[test]
+    fn test_roundtrip_registry() {
+        let serialized = to_string(&NamespaceId::Registry).unwrap();
+        assert_eq!(serialized, "\"/registry\"");
+        let deserialized: NamespaceId = from_str(&serialized).unwrap();
+        assert_eq!(deserialized, NamespaceId::Registry);
+        assert_eq!(
+            NamespaceId::from_str("/registry").unwrap(),
+            NamespaceId::Registry
+        );
+    }
+
+    #[test]
+    fn test_roundtrip_diagnostics() {
+        let serialized = to_string(&NamespaceId::Diagnostics).unwrap();
+        assert_eq!(serialized, "\"/diagnostics\"");
+        let deserialized: NamespaceId = from_str(&serialized).unwrap();
+        assert_eq!(deserialized, NamespaceId::Diagnostics);
+        assert_eq!(
+            NamespaceId::from_str("/diagnostics").unwrap(),
+            NamespaceId::Diagnostics
+        );
+    }
+
+    #[test]
+    fn test_roundtrip_sequences() {
+        let serialized = to_string(&NamespaceId::Sequences).unwrap();
+        assert_eq!(serialized, "\"/sequences\"");
+        let deserialized: NamespaceId = from_str(&serialized).unwrap();
+        assert_eq!(deserialized, NamespaceId::Sequences);
+        assert_eq!(
+            NamespaceId::from_str("/sequences").unwrap(),
+            NamespaceId::Sequences
+        );
+    }
+
     #[test]
     fn test_from_str_machine() {
         let namespace_id = NamespaceId::from_str("/machine/123/456/789").unwrap();
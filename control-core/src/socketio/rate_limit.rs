@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    sync::{LazyLock, RwLock},
+    time::Duration,
+};
+
+static EMIT_RATE_LIMITS: LazyLock<RwLock<EmitRateLimits>> =
+    LazyLock::new(|| RwLock::new(EmitRateLimits::default()));
+
+/// Maximum emit rates by event name, applied by every [`super::namespace::Namespace`]
+///
+/// Events above the rate are dropped, so limits suit values that are emitted continuously.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EmitRateLimits {
+    /// Maximum rate in Hz by event name, e.g. `{"LiveValuesEvent": 30.0}`
+    pub max_rate_hz: BTreeMap<String, f64>,
+    /// Events that slow down to a heartbeat while their value is stable
+    #[serde(default)]
+    pub adaptive: BTreeMap<String, AdaptiveEmitRate>,
+}
+
+/// Emit rate following how fast the value of an event changes
+///
+/// While the value moves by at least `min_change` from one emitted event to the next, e.g. on a
+/// startup or a disturbance, the event is emitted at its maximum rate. A stable value is only
+/// emitted at `heartbeat_hz`. Only applies to events whose emitter tracks the value, see
+/// [`super::namespace::Namespace::track_value`].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveEmitRate {
+    pub heartbeat_hz: f64,
+    /// change since the last emitted value that counts as volatile, in the unit of the value
+    pub min_change: f64,
+}
+
+/// State of an [`AdaptiveEmitRate`] for one event
+#[derive(Debug, Clone)]
+pub struct AdaptiveThrottle {
+    heartbeat: Duration,
+    min_change: f64,
+    /// latest tracked value, `None` while the emitter doesn't track one
+    value: Option<f64>,
+    emitted_value: Option<f64>,
+}
+
+impl AdaptiveThrottle {
+    /// `None` for a heartbeat that doesn't slow anything down
+    pub fn new(rate: &AdaptiveEmitRate) -> Option<Self> {
+        Some(Self {
+            heartbeat: rate_to_interval(rate.heartbeat_hz)?,
+            min_change: rate.min_change.max(0.0),
+            value: None,
+            emitted_value: None,
+        })
+    }
+
+    pub const fn track(&mut self, value: f64) {
+        self.value = Some(value);
+    }
+
+    /// Whether the event is emitted `since_last_emit` after the previous one, `None` for the
+    /// first event
+    pub fn should_emit(&mut self, since_last_emit: Option<Duration>) -> bool {
+        let Some(value) = self.value else {
+            return true;
+        };
+        let volatile = self
+            .emitted_value
+            .is_none_or(|emitted| (value - emitted).abs() >= self.min_change);
+        let heartbeat = since_last_emit.is_none_or(|elapsed| elapsed >= self.heartbeat);
+        if volatile || heartbeat {
+            self.emitted_value = Some(value);
+        }
+        volatile || heartbeat
+    }
+}
+
+impl Default for EmitRateLimits {
+    fn default() -> Self {
+        Self {
+            max_rate_hz: BTreeMap::from([
+                ("LiveValuesEvent".to_string(), 30.0),
+                ("MinMaxDiameterEvent".to_string(), 1.0),
+                ("ProductionEvent".to_string(), 1.0),
+                ("MaintenanceEvent".to_string(), 0.1),
+                ("PullerAxesEvent".to_string(), 2.0),
+            ]),
+            adaptive: BTreeMap::new(),
+        }
+    }
+}
+
+impl EmitRateLimits {
+    /// Minimum time between two events of this name, `None` if the rate is not limited
+    pub fn min_interval(&self, event: &str) -> Option<Duration> {
+        self.max_rate_hz
+            .get(event)
+            .and_then(|rate| rate_to_interval(*rate))
+    }
+}
+
+/// `None` for rates that don't limit anything, e.g. 0 or infinite
+pub fn rate_to_interval(max_rate_hz: f64) -> Option<Duration> {
+    if max_rate_hz.is_finite() && max_rate_hz > 0.0 {
+        Some(Duration::from_secs_f64(1.0 / max_rate_hz))
+    } else {
+        None
+    }
+}
+
+/// Sets the limits of all namespaces created afterwards
+///
+/// Existing namespaces keep their limits until [`super::namespace::Namespace::apply_rate_limits`].
+pub fn set_emit_rate_limits(limits: EmitRateLimits) {
+    *EMIT_RATE_LIMITS.write().unwrap_or_else(|e| e.into_inner()) = limits;
+}
+
+/// Configured limits, the defaults if [`set_emit_rate_limits`] wasn't called
+pub fn emit_rate_limits() -> EmitRateLimits {
+    EMIT_RATE_LIMITS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
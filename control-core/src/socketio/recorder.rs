@@ -0,0 +1,141 @@
+//! Recording of the socketio traffic to reproduce bugs of the frontend
+//!
+//! Namespaces with an [`Namespace::id`](super::namespace::Namespace::id) pass every event they
+//! emit to the recorder, the server adds the machine mutations it receives. Cheap while nothing
+//! records, so namespaces call it for every event.
+
+use super::{event::GenericEvent, namespace_id::NamespaceId};
+use crate::helpers::clock::{monotonic_millis, unix_millis};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use smol::channel::Sender;
+use std::sync::{
+    LazyLock, RwLock,
+    atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+static RECORDER: LazyLock<RwLock<Option<Sender<RecordedEntry>>>> =
+    LazyLock::new(|| RwLock::new(None));
+
+static RECORDING: AtomicBool = AtomicBool::new(false);
+
+/// Entries dropped because the writer fell behind, since the recording started
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind")]
+pub enum RecordedKind {
+    /// Event as emitted by the namespace, before unit conversion and batching
+    Event { name: String, data: Value },
+    /// Machine mutation as received, whether the machine accepted it or not
+    Mutation { data: Value },
+}
+
+/// One line of a recording
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RecordedEntry {
+    /// unix timestamp in milliseconds
+    pub ts: u64,
+    /// milliseconds since the server started, see [`monotonic_millis`]
+    pub mono: u64,
+    pub namespace: NamespaceId,
+    #[serde(flatten)]
+    pub kind: RecordedKind,
+}
+
+/// Sends the recorded entries to `sink` from now on, `None` stops recording
+///
+/// Dropping the previous sink closes its channel, so its writer knows the recording ended.
+pub fn set_recorder(sink: Option<Sender<RecordedEntry>>) {
+    let mut recorder = RECORDER.write().unwrap_or_else(|e| e.into_inner());
+    RECORDING.store(sink.is_some(), Ordering::Relaxed);
+    DROPPED.store(0, Ordering::Relaxed);
+    *recorder = sink;
+}
+
+pub fn is_recording() -> bool {
+    RECORDING.load(Ordering::Relaxed)
+}
+
+pub fn dropped_entries() -> u64 {
+    DROPPED.load(Ordering::Relaxed)
+}
+
+/// Never blocks, entries are dropped while the writer falls behind
+pub fn record(entry: RecordedEntry) {
+    let recorder = RECORDER.read().unwrap_or_else(|e| e.into_inner());
+    if let Some(sink) = recorder.as_ref() {
+        if sink.try_send(entry).is_err() {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+pub fn record_event(namespace: &NamespaceId, event: &GenericEvent) {
+    if !is_recording() {
+        return;
+    }
+    let Ok(data) = serde_json::to_value(&event.data) else {
+        return;
+    };
+    record(RecordedEntry {
+        ts: event.ts,
+        mono: event.mono,
+        namespace: namespace.clone(),
+        kind: RecordedKind::Event {
+            name: event.name.clone(),
+            data,
+        },
+    });
+}
+
+pub fn record_mutation(namespace: &NamespaceId, data: &Value) {
+    if !is_recording() {
+        return;
+    }
+    record(RecordedEntry {
+        ts: unix_millis(),
+        mono: monotonic_millis(),
+        namespace: namespace.clone(),
+        kind: RecordedKind::Mutation { data: data.clone() },
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machines::identification::{MachineIdentification, MachineIdentificationUnique};
+    use serde_json::json;
+
+    #[test]
+    fn test_recorded_entry_roundtrip() {
+        let entry = RecordedEntry {
+            ts: 1_700_000_000_000,
+            mono: 1500,
+            namespace: NamespaceId::Machine(MachineIdentificationUnique {
+                machine_identification: MachineIdentification {
+                    vendor: 1,
+                    machine: 6,
+                },
+                serial: 3,
+            }),
+            kind: RecordedKind::Event {
+                name: "LiveValuesEvent".to_string(),
+                data: json!({ "diameter": 1.75 }),
+            },
+        };
+        let line = serde_json::to_string(&entry).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&line).unwrap(),
+            json!({
+                "ts": 1_700_000_000_000u64,
+                "mono": 1500,
+                "namespace": "/machine/1/6/3",
+                "kind": "Event",
+                "name": "LiveValuesEvent",
+                "data": { "diameter": 1.75 }
+            })
+        );
+        assert_eq!(serde_json::from_str::<RecordedEntry>(&line).unwrap(), entry);
+    }
+}
@@ -0,0 +1,65 @@
+use super::event::GenericEvent;
+use arc_swap::ArcSwap;
+use std::{collections::HashMap, sync::Arc};
+
+/// Latest event of every name a namespace emitted
+///
+/// Replaced as a whole, so a reader always sees the events of one point in time.
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    pub events: HashMap<String, Arc<GenericEvent>>,
+}
+
+impl Snapshot {
+    pub fn get(&self, event: &str) -> Option<&Arc<GenericEvent>> {
+        self.events.get(event)
+    }
+}
+
+/// [`Snapshot`] of a namespace that API queries and exporters read without locking the
+/// namespace, the namespace publishes a new one when its events go out
+#[derive(Debug, Default)]
+pub struct SnapshotCell(ArcSwap<Snapshot>);
+
+impl SnapshotCell {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(&self) -> Arc<Snapshot> {
+        self.0.load_full()
+    }
+
+    pub fn publish(&self, events: &HashMap<String, Arc<GenericEvent>>) {
+        self.0.store(Arc::new(Snapshot {
+            events: events.clone(),
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_readers_keep_their_snapshot() {
+        let cell = SnapshotCell::new();
+        let event = |ts| {
+            Arc::new(GenericEvent {
+                name: "LiveValuesEvent".to_string(),
+                data: Box::new(()),
+                ts,
+                mono: 0,
+                droppable: false,
+            })
+        };
+        let mut events = HashMap::from([("LiveValuesEvent".to_string(), event(1))]);
+        cell.publish(&events);
+        let before = cell.load();
+
+        events.insert("LiveValuesEvent".to_string(), event(2));
+        cell.publish(&events);
+        assert_eq!(before.get("LiveValuesEvent").unwrap().ts, 1);
+        assert_eq!(cell.load().get("LiveValuesEvent").unwrap().ts, 2);
+    }
+}
@@ -0,0 +1,448 @@
+use super::event::{BuildEvent, EVENT_BATCH, Event};
+use schemars::{JsonSchema, r#gen::SchemaGenerator, schema::Schema};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::BTreeMap, str::FromStr};
+use uom::si::{
+    Unit,
+    f64::{Length, Velocity},
+    length::{foot, inch, meter, mil, millimeter},
+    velocity::foot_per_minute,
+};
+
+use crate::uom_extensions::velocity::meter_per_minute;
+
+/// Field of event data holding the [`EventUnits`]
+pub const UNITS_FIELD: &str = "units";
+
+/// Units a client wants values in, selected with the `units` query parameter of the socket
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UnitSystem {
+    #[default]
+    Metric,
+    Imperial,
+}
+
+impl FromStr for UnitSystem {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "metric" => Ok(Self::Metric),
+            "imperial" => Ok(Self::Imperial),
+            _ => Err(anyhow::anyhow!(
+                "[{}::UnitSystem::from_str] Unknown unit system '{}'",
+                module_path!(),
+                s
+            )),
+        }
+    }
+}
+
+/// Kind of a value, decides the unit it is displayed in
+///
+/// Diameters and positions are both lengths but read best in mils and inches.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DisplayQuantity {
+    /// mm or mil
+    Diameter,
+    /// mm or in
+    Position,
+    /// m/min or ft/min
+    LineSpeed,
+    /// m or ft
+    FilamentLength,
+}
+
+impl DisplayQuantity {
+    pub const fn unit(self, system: UnitSystem) -> &'static str {
+        match (self, system) {
+            (Self::Diameter, UnitSystem::Metric) => "mm",
+            (Self::Diameter, UnitSystem::Imperial) => "mil",
+            (Self::Position, UnitSystem::Metric) => "mm",
+            (Self::Position, UnitSystem::Imperial) => "in",
+            (Self::LineSpeed, UnitSystem::Metric) => "m/min",
+            (Self::LineSpeed, UnitSystem::Imperial) => "ft/min",
+            (Self::FilamentLength, UnitSystem::Metric) => "m",
+            (Self::FilamentLength, UnitSystem::Imperial) => "ft",
+        }
+    }
+
+    /// Converts a value in the metric unit of the quantity
+    pub fn convert(self, metric_value: f64, system: UnitSystem) -> f64 {
+        match (self, system) {
+            (_, UnitSystem::Metric) => metric_value,
+            (Self::Diameter, UnitSystem::Imperial) => {
+                Length::new::<millimeter>(metric_value).get::<mil>()
+            }
+            (Self::Position, UnitSystem::Imperial) => {
+                Length::new::<millimeter>(metric_value).get::<inch>()
+            }
+            (Self::LineSpeed, UnitSystem::Imperial) => {
+                Velocity::new::<meter_per_minute>(metric_value).get::<foot_per_minute>()
+            }
+            (Self::FilamentLength, UnitSystem::Imperial) => {
+                Length::new::<meter>(metric_value).get::<foot>()
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub struct FieldUnit {
+    pub quantity: DisplayQuantity,
+    pub unit: &'static str,
+}
+
+/// Units of the values in event data by dot separated field path, e.g. `laser_state.target_diameter`
+///
+/// Events are built in metric units. Sockets of clients preferring another [`UnitSystem`] get
+/// their events passed through [`convert_event`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventUnits(pub &'static [(&'static str, DisplayQuantity)]);
+
+impl Serialize for EventUnits {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(path, quantity)| {
+            (
+                path,
+                FieldUnit {
+                    quantity: *quantity,
+                    unit: quantity.unit(UnitSystem::Metric),
+                },
+            )
+        }))
+    }
+}
+
+impl JsonSchema for EventUnits {
+    fn schema_name() -> String {
+        "EventUnits".to_string()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        generator.subschema_for::<std::collections::BTreeMap<String, FieldUnit>>()
+    }
+}
+
+/// Name of the [`SchemaEvent`]
+pub const SCHEMA_EVENT: &str = "SchemaEvent";
+
+/// Unit and valid range of one value of an event
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct FieldSchema {
+    pub unit: String,
+    /// set for values converted to the [`UnitSystem`] of the client
+    pub quantity: Option<DisplayQuantity>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl FieldSchema {
+    /// Value in the uom unit `U`, e.g. `FieldSchema::of::<degree_celsius>()`
+    pub fn of<U: Unit>() -> Self {
+        Self {
+            unit: U::abbreviation().to_string(),
+            quantity: None,
+            min: None,
+            max: None,
+        }
+    }
+
+    /// Value of a [`DisplayQuantity`], the unit is the metric one like in the event itself
+    pub fn display(quantity: DisplayQuantity) -> Self {
+        Self {
+            unit: quantity.unit(UnitSystem::Metric).to_string(),
+            quantity: Some(quantity),
+            min: None,
+            max: None,
+        }
+    }
+
+    pub const fn min(mut self, min: f64) -> Self {
+        self.min = Some(min);
+        self
+    }
+
+    pub const fn max(mut self, max: f64) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    pub const fn range(self, min: f64, max: f64) -> Self {
+        self.min(min).max(max)
+    }
+}
+
+/// Units and ranges of the live values of a namespace
+///
+/// Emitted once when the namespace is created, so clients can label axes and validate
+/// inputs without knowing the machine.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+pub struct SchemaEvent {
+    /// Fields of the live values by dot separated field path, like [`EventUnits`]
+    pub fields: BTreeMap<String, FieldSchema>,
+}
+
+impl SchemaEvent {
+    pub fn new<'a>(fields: impl IntoIterator<Item = (&'a str, FieldSchema)>) -> Self {
+        Self {
+            fields: fields
+                .into_iter()
+                .map(|(path, field)| (path.to_string(), field))
+                .collect(),
+        }
+    }
+}
+
+impl BuildEvent for SchemaEvent {
+    fn build(&self) -> Event<Self> {
+        Event::new(SCHEMA_EVENT, self.clone())
+    }
+}
+
+/// Converts a serialized event, including the events of an event batch, to `system`
+pub fn convert_event(event: &mut Value, system: UnitSystem) {
+    if system == UnitSystem::Metric {
+        return;
+    }
+    let name = event.get("name").and_then(Value::as_str);
+    let is_batch = name == Some(EVENT_BATCH);
+    let is_schema = name == Some(SCHEMA_EVENT);
+    match event.get_mut("data") {
+        Some(Value::Array(events)) if is_batch => {
+            for event in events {
+                convert_event(event, system);
+            }
+        }
+        Some(data) if is_schema => convert_schema(data, system),
+        Some(data) => convert_event_data(data, system),
+        None => {}
+    }
+}
+
+/// Converts the ranges and units of the fields of a [`SchemaEvent`] with a [`DisplayQuantity`]
+fn convert_schema(data: &mut Value, system: UnitSystem) {
+    let Some(Value::Object(fields)) = data.get_mut("fields") else {
+        return;
+    };
+    for field in fields.values_mut() {
+        let Ok(mut schema) = FieldSchema::deserialize(&*field) else {
+            continue;
+        };
+        let Some(quantity) = schema.quantity else {
+            continue;
+        };
+        if schema.unit != quantity.unit(UnitSystem::Metric) {
+            continue;
+        }
+        schema.unit = quantity.unit(system).to_string();
+        schema.min = schema.min.map(|min| quantity.convert(min, system));
+        schema.max = schema.max.map(|max| quantity.convert(max, system));
+        if let Ok(converted) = serde_json::to_value(schema) {
+            *field = converted;
+        }
+    }
+}
+
+/// Converts the fields listed in the [`UNITS_FIELD`] of event data from metric to `system`
+pub fn convert_event_data(data: &mut Value, system: UnitSystem) {
+    if system == UnitSystem::Metric {
+        return;
+    }
+    let Some(Value::Object(units)) = data.get(UNITS_FIELD).cloned() else {
+        return;
+    };
+    let mut converted = serde_json::Map::new();
+    for (path, field_unit) in units {
+        let Some(quantity) = field_unit
+            .get("quantity")
+            .and_then(|quantity| DisplayQuantity::deserialize(quantity).ok())
+        else {
+            converted.insert(path, field_unit);
+            continue;
+        };
+        // only metric values are converted, so converting twice does nothing
+        if field_unit.get("unit").and_then(Value::as_str) != Some(quantity.unit(UnitSystem::Metric))
+        {
+            converted.insert(path, field_unit);
+            continue;
+        }
+        let keys = path.split('.').collect::<Vec<_>>();
+        convert_path(data, &keys, quantity, system);
+        converted.insert(
+            path,
+            serde_json::json!({ "quantity": quantity, "unit": quantity.unit(system) }),
+        );
+    }
+    data[UNITS_FIELD] = Value::Object(converted);
+}
+
+/// Converts the number at `path`, arrays on the way have every element converted
+///
+/// Per strand values are arrays, e.g. `strand_diameters` or `strands.target_diameter`.
+fn convert_path(value: &mut Value, path: &[&str], quantity: DisplayQuantity, system: UnitSystem) {
+    match (value, path.split_first()) {
+        (Value::Array(items), _) => {
+            for item in items {
+                convert_path(item, path, quantity, system);
+            }
+        }
+        (value, Some((key, rest))) => {
+            if let Some(value) = value.get_mut(*key) {
+                convert_path(value, rest, quantity, system);
+            }
+        }
+        (value, None) => {
+            if let Some(number) = value.as_f64() {
+                *value = Value::from(quantity.convert(number, system));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::socketio::event::{Event, EventBatch, GenericEvent};
+    use approx::assert_relative_eq;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[derive(Serialize, Clone)]
+    struct Sample {
+        diameter: f64,
+        state: SampleState,
+        units: EventUnits,
+    }
+
+    #[derive(Serialize, Clone)]
+    struct SampleState {
+        speed: Option<f64>,
+    }
+
+    fn sample() -> Sample {
+        Sample {
+            diameter: 1.75,
+            state: SampleState { speed: Some(10.0) },
+            units: EventUnits(&[
+                ("diameter", DisplayQuantity::Diameter),
+                ("state.speed", DisplayQuantity::LineSpeed),
+            ]),
+        }
+    }
+
+    #[test]
+    fn test_convert_event_data() {
+        let mut data = serde_json::to_value(sample()).unwrap();
+        convert_event_data(&mut data, UnitSystem::Imperial);
+        assert_relative_eq!(
+            data["diameter"].as_f64().unwrap(),
+            68.897_637_795,
+            epsilon = 1e-6
+        );
+        assert_relative_eq!(
+            data["state"]["speed"].as_f64().unwrap(),
+            32.808_398_95,
+            epsilon = 1e-6
+        );
+        assert_eq!(
+            data["units"]["diameter"],
+            json!({"quantity": "diameter", "unit": "mil"})
+        );
+
+        // already converted values stay as they are
+        let converted = data.clone();
+        convert_event_data(&mut data, UnitSystem::Imperial);
+        assert_eq!(data, converted);
+
+        let mut missing = serde_json::to_value(Sample {
+            state: SampleState { speed: None },
+            ..sample()
+        })
+        .unwrap();
+        convert_event_data(&mut missing, UnitSystem::Imperial);
+        assert!(missing["state"]["speed"].is_null());
+    }
+
+    #[test]
+    fn test_convert_arrays() {
+        #[derive(Serialize)]
+        struct Strand {
+            target_diameter: f64,
+        }
+        #[derive(Serialize)]
+        struct Strands {
+            strand_diameters: Vec<f64>,
+            strands: Vec<Strand>,
+            units: EventUnits,
+        }
+
+        let mut data = serde_json::to_value(Strands {
+            strand_diameters: vec![1.0, 2.0],
+            strands: vec![Strand {
+                target_diameter: 1.0,
+            }],
+            units: EventUnits(&[
+                ("strand_diameters", DisplayQuantity::Diameter),
+                ("strands.target_diameter", DisplayQuantity::Diameter),
+            ]),
+        })
+        .unwrap();
+        convert_event_data(&mut data, UnitSystem::Imperial);
+        assert_relative_eq!(
+            data["strand_diameters"][1].as_f64().unwrap(),
+            78.740_157_48,
+            epsilon = 1e-6
+        );
+        assert_relative_eq!(
+            data["strands"][0]["target_diameter"].as_f64().unwrap(),
+            39.370_078_74,
+            epsilon = 1e-6
+        );
+    }
+
+    #[test]
+    fn test_convert_schema_event() {
+        let schema = SchemaEvent::new([
+            (
+                "diameter",
+                FieldSchema::display(DisplayQuantity::Diameter).range(0.0, 5.0),
+            ),
+            (
+                "temperature",
+                FieldSchema::of::<uom::si::thermodynamic_temperature::degree_celsius>().max(300.0),
+            ),
+        ]);
+        let event: GenericEvent = schema.build().into();
+        let mut value = serde_json::to_value(&event).unwrap();
+        convert_event(&mut value, UnitSystem::Imperial);
+
+        let diameter = &value["data"]["fields"]["diameter"];
+        assert_eq!(diameter["unit"], "mil");
+        assert_relative_eq!(
+            diameter["max"].as_f64().unwrap(),
+            196.850_393_7,
+            epsilon = 1e-6
+        );
+        assert_eq!(
+            value["data"]["fields"]["temperature"],
+            json!({"unit": "°C", "quantity": null, "min": null, "max": 300.0})
+        );
+    }
+
+    #[test]
+    fn test_convert_event_batch() {
+        let event: GenericEvent = Event::new("LiveValuesEvent", sample()).into();
+        let batch = EventBatch(vec![Arc::new(event)]).build();
+        let mut value = serde_json::to_value(&batch).unwrap();
+        convert_event(&mut value, UnitSystem::Imperial);
+        assert_eq!(value["data"][0]["data"]["units"]["diameter"]["unit"], "mil");
+
+        let mut metric = serde_json::to_value(&batch).unwrap();
+        convert_event(&mut metric, UnitSystem::Metric);
+        assert_eq!(metric["data"][0]["data"]["diameter"], 1.75);
+    }
+}
@@ -0,0 +1,206 @@
+//! Property test helpers for converters and motion controllers
+//!
+//! Used by the tests of this crate and available to other crates through the `proptest`
+//! feature. Strategies generate physically sensible parameters, the `check_*` functions return
+//! a [`TestCaseError`] so they can be used inside `proptest!` blocks.
+
+use crate::{
+    controllers::second_degree_motion::linear_jerk_speed_controller::LinearJerkSpeedController,
+    helpers::clock::{Clock, ManualClock},
+};
+use proptest::{prelude::*, test_runner::TestCaseError};
+use std::time::Duration;
+use uom::si::{
+    acceleration::meter_per_second_squared,
+    f64::{Acceleration, Jerk, Length, Velocity},
+    jerk::meter_per_second_cubed,
+    length::millimeter,
+    velocity::meter_per_second,
+};
+
+/// Relative tolerance for values that went through a conversion and back
+pub const ROUND_TRIP_TOLERANCE: f64 = 1e-9;
+
+/// Every valid stepper resolution
+pub fn steps_per_revolution() -> impl Strategy<Value = i16> {
+    1..=i16::MAX
+}
+
+/// Pulleys and wheels from 1 mm to 1 m
+pub fn wheel_diameter() -> impl Strategy<Value = Length> {
+    (1.0..1000.0).prop_map(Length::new::<millimeter>)
+}
+
+/// Velocity between `-max` and `max` m/s
+pub fn velocity(max: f64) -> impl Strategy<Value = Velocity> {
+    (-max..max).prop_map(Velocity::new::<meter_per_second>)
+}
+
+/// Control loop periods from 1 to 20 ms
+pub fn time_step() -> impl Strategy<Value = Duration> {
+    (1u64..=20).prop_map(Duration::from_millis)
+}
+
+/// Symmetric limits of a [`LinearJerkSpeedController`]
+#[derive(Debug, Clone, Copy)]
+pub struct RampLimits {
+    pub speed: Velocity,
+    pub acceleration: Acceleration,
+    pub jerk: Jerk,
+}
+
+impl RampLimits {
+    pub fn controller(&self) -> LinearJerkSpeedController {
+        LinearJerkSpeedController::new_simple(Some(self.speed), self.acceleration, self.jerk)
+    }
+
+    /// Target the controller settles at, clamped to the speed limit
+    pub fn reachable(&self, target: Velocity) -> Velocity {
+        target.max(-self.speed).min(self.speed)
+    }
+
+    /// Upper bound for ramping from `start` to `target`, with a second of margin
+    pub fn settle_time(&self, start: Velocity, target: Velocity) -> Duration {
+        let acceleration = self.acceleration.get::<meter_per_second_squared>();
+        let jerk = self.jerk.get::<meter_per_second_cubed>();
+        let speed_change = (self.reachable(target) - start)
+            .get::<meter_per_second>()
+            .abs();
+        Duration::from_secs_f64(speed_change / acceleration + 2.0 * acceleration / jerk + 1.0)
+    }
+}
+
+pub fn ramp_limits() -> impl Strategy<Value = RampLimits> {
+    (0.5..5.0, 0.5..10.0, 1.0..50.0).prop_map(|(speed, acceleration, jerk)| RampLimits {
+        speed: Velocity::new::<meter_per_second>(speed),
+        acceleration: Acceleration::new::<meter_per_second_squared>(acceleration),
+        jerk: Jerk::new::<meter_per_second_cubed>(jerk),
+    })
+}
+
+/// State of the controller after one update
+#[derive(Debug, Clone, Copy)]
+pub struct RampSample {
+    pub speed: Velocity,
+    pub acceleration: Acceleration,
+    pub jerk: Jerk,
+}
+
+/// Updates the controller every `dt` for `duration` on a [`ManualClock`]
+pub fn run_ramp(
+    controller: &mut LinearJerkSpeedController,
+    target: Velocity,
+    dt: Duration,
+    duration: Duration,
+) -> Vec<RampSample> {
+    let clock = ManualClock::new();
+    // the first update only sets the time reference
+    controller.update(target, clock.now());
+
+    let steps = duration.as_nanos().div_ceil(dt.as_nanos());
+    (0..steps)
+        .map(|_| {
+            clock.advance(dt);
+            let speed = controller.update(target, clock.now());
+            RampSample {
+                speed,
+                acceleration: controller.get_acceleration(),
+                jerk: controller.get_jerk(),
+            }
+        })
+        .collect()
+}
+
+/// Checks a ramp from `start` to the reachable `target`:
+/// - acceleration and jerk stay within their limits, the speed within its limit
+/// - the speed moves monotonically towards the target without leaving the span between
+///   start and target, allowing one control period of discretisation error
+/// - the speed settles at the target
+pub fn check_ramp(
+    samples: &[RampSample],
+    limits: &RampLimits,
+    start: Velocity,
+    target: Velocity,
+    dt: Duration,
+) -> Result<(), TestCaseError> {
+    let max_acceleration = limits.acceleration.get::<meter_per_second_squared>();
+    let max_jerk = limits.jerk.get::<meter_per_second_cubed>();
+    let max_speed = limits.speed.get::<meter_per_second>();
+    let start = start.get::<meter_per_second>();
+    let target = limits.reachable(target).get::<meter_per_second>();
+    let direction = (target - start).signum();
+    // one control period at full acceleration
+    let slack = max_acceleration * dt.as_secs_f64() + 1e-9;
+
+    let mut last = start;
+    for (step, sample) in samples.iter().enumerate() {
+        let speed = sample.speed.get::<meter_per_second>();
+        let acceleration = sample.acceleration.get::<meter_per_second_squared>();
+        let jerk = sample.jerk.get::<meter_per_second_cubed>();
+
+        prop_assert!(
+            acceleration.abs() <= max_acceleration * (1.0 + ROUND_TRIP_TOLERANCE),
+            "step {}: acceleration {} exceeds {}",
+            step,
+            acceleration,
+            max_acceleration
+        );
+        prop_assert!(
+            jerk.abs() <= max_jerk * (1.0 + ROUND_TRIP_TOLERANCE),
+            "step {}: jerk {} exceeds {}",
+            step,
+            jerk,
+            max_jerk
+        );
+        prop_assert!(
+            speed.abs() <= max_speed + 1e-9,
+            "step {}: speed {} exceeds {}",
+            step,
+            speed,
+            max_speed
+        );
+        prop_assert!(
+            (speed - last).abs() <= max_acceleration * dt.as_secs_f64() + slack,
+            "step {}: speed jumped from {} to {}",
+            step,
+            last,
+            speed
+        );
+        prop_assert!(
+            (speed - last) * direction >= -slack,
+            "step {}: speed moved away from {}: {} -> {}",
+            step,
+            target,
+            last,
+            speed
+        );
+        prop_assert!(
+            speed >= start.min(target) - slack && speed <= start.max(target) + slack,
+            "step {}: speed {} left the span {}..{}",
+            step,
+            speed,
+            start,
+            target
+        );
+        last = speed;
+    }
+
+    prop_assert!(
+        (last - target).abs() <= 1e-3,
+        "speed {} did not settle at {}",
+        last,
+        target
+    );
+    Ok(())
+}
+
+/// Checks that `actual` matches `expected` within [`ROUND_TRIP_TOLERANCE`]
+pub fn check_round_trip(actual: f64, expected: f64) -> Result<(), TestCaseError> {
+    prop_assert!(
+        (actual - expected).abs() <= ROUND_TRIP_TOLERANCE * expected.abs().max(1.0),
+        "round trip gave {}, expected {}",
+        actual,
+        expected
+    );
+    Ok(())
+}
@@ -179,6 +179,11 @@ impl StepperVelocityEL70x1Device<EL7031StepperPort> for EL7031 {
                     moving_positive: stm_status.moving_positive,
                     moving_negative: stm_status.moving_negative,
                     torque_reduced: stm_status.torque_reduced,
+                    info_data: self
+                        .txpdo
+                        .stm_synchron_info_data
+                        .as_ref()
+                        .map(|info_data| [info_data.info_data_1, info_data.info_data_2]),
                 })
             }
         }
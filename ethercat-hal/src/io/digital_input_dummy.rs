@@ -0,0 +1,39 @@
+use super::digital_input::{DigitalInput, DigitalInputDevice, DigitalInputInput};
+use smol::lock::RwLock;
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+#[derive(Clone)]
+pub struct DigitalInputDummyPort;
+
+/// Digital input without hardware, clones share their value
+#[derive(Clone, Default)]
+pub struct DigitalInputDummy {
+    value: Arc<AtomicBool>,
+}
+
+impl DigitalInputDevice<DigitalInputDummyPort> for DigitalInputDummy {
+    fn get_input(&self, _port: DigitalInputDummyPort) -> Result<DigitalInputInput, anyhow::Error> {
+        Ok(DigitalInputInput {
+            value: self.value.load(Ordering::Relaxed),
+        })
+    }
+}
+
+impl DigitalInputDummy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn digital_input(&self) -> DigitalInput {
+        let device: Arc<RwLock<dyn DigitalInputDevice<DigitalInputDummyPort>>> =
+            Arc::new(RwLock::new(self.clone()));
+        DigitalInput::new(device, DigitalInputDummyPort)
+    }
+
+    pub fn set_value(&self, value: bool) {
+        self.value.store(value, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,41 @@
+use super::digital_output::{DigitalOutput, DigitalOutputDevice, DigitalOutputOutput};
+use smol::lock::RwLock;
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+#[derive(Clone)]
+pub struct DigitalOutputDummyPort;
+
+/// Digital output without hardware, clones share their value
+#[derive(Clone, Default)]
+pub struct DigitalOutputDummy {
+    value: Arc<AtomicBool>,
+}
+
+impl DigitalOutputDevice<DigitalOutputDummyPort> for DigitalOutputDummy {
+    fn set_output(&mut self, _port: DigitalOutputDummyPort, value: DigitalOutputOutput) {
+        self.value.store(value.into(), Ordering::Relaxed);
+    }
+
+    fn get_output(&self, _port: DigitalOutputDummyPort) -> DigitalOutputOutput {
+        self.value.load(Ordering::Relaxed).into()
+    }
+}
+
+impl DigitalOutputDummy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn digital_output(&self) -> DigitalOutput {
+        let device: Arc<RwLock<dyn DigitalOutputDevice<DigitalOutputDummyPort>>> =
+            Arc::new(RwLock::new(self.clone()));
+        DigitalOutput::new(device, DigitalOutputDummyPort)
+    }
+
+    pub fn get_value(&self) -> bool {
+        self.value.load(Ordering::Relaxed)
+    }
+}
@@ -128,6 +128,13 @@ impl<'device> StepperVelocityEL70x1 {
         input.counter_value
     }
 
+    /// Info data 1 and 2 as selected in the configuration, by default the coil currents in mA
+    ///
+    /// `None` if the PDO assignment doesn't include the info data.
+    pub fn get_info_data(&self) -> Option<[u16; 2]> {
+        (self.get_input)().ok()?.info_data
+    }
+
     /// Set the position of the stepper
     pub fn set_position(&mut self, position: i128) {
         // Get current state to preserve other output values
@@ -165,6 +172,10 @@ pub struct StepperVelocityEL70x1Input {
 
     /// `torque_reduced` from [`crate::pdo::el70x1::StmStatus`]
     pub torque_reduced: bool,
+
+    /// `info_data_1` and `info_data_2` from [`crate::pdo::el70x1::StmSynchronInfoData`],
+    /// `None` without an info data PDO
+    pub info_data: Option<[u16; 2]>,
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,152 @@
+use super::stepper_velocity_el70x1::{
+    StepperVelocityEL70x1, StepperVelocityEL70x1Device, StepperVelocityEL70x1Input,
+    StepperVelocityEL70x1Output,
+};
+use crate::helpers::el70xx_velocity_converter::EL70x1VelocityConverter;
+use crate::shared_config::el70x1::EL70x1SpeedRange;
+use smol::lock::RwLock;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Clone, Copy)]
+pub struct StepperVelocityEL70x1DummyPort;
+
+struct StepperDummyState {
+    output: StepperVelocityEL70x1Output,
+    counter: f64,
+}
+
+/// Stepper without hardware, the counter follows the commanded velocity when [`Self::advance`] is called
+///
+/// Clones share their state, so one clone can be handed to a machine while another one moves it.
+#[derive(Clone)]
+pub struct StepperVelocityEL70x1Dummy {
+    state: Arc<Mutex<StepperDummyState>>,
+    speed_range: EL70x1SpeedRange,
+    /// Counter increments per full step, the microsteps configured on the real terminal
+    counts_per_step: u16,
+}
+
+impl StepperVelocityEL70x1Device<StepperVelocityEL70x1DummyPort> for StepperVelocityEL70x1Dummy {
+    fn set_output(
+        &mut self,
+        _port: StepperVelocityEL70x1DummyPort,
+        mut value: StepperVelocityEL70x1Output,
+    ) -> Result<(), anyhow::Error> {
+        let mut state = self.state.lock().unwrap();
+        // like the terminal, the counter is set once instead of being held
+        if let Some(counter) = value.set_counter.take() {
+            state.counter = counter as f64;
+        }
+        state.output = value;
+        Ok(())
+    }
+
+    fn get_input(
+        &self,
+        _port: StepperVelocityEL70x1DummyPort,
+    ) -> Result<StepperVelocityEL70x1Input, anyhow::Error> {
+        let state = self.state.lock().unwrap();
+        let velocity = if state.output.enable {
+            state.output.velocity
+        } else {
+            0
+        };
+        Ok(StepperVelocityEL70x1Input {
+            counter_value: state.counter.round() as i128,
+            ready_to_enable: true,
+            ready: state.output.enable,
+            warning: false,
+            error: false,
+            moving_positive: velocity > 0,
+            moving_negative: velocity < 0,
+            torque_reduced: state.output.reduce_torque,
+            info_data: None,
+        })
+    }
+
+    fn get_output(
+        &self,
+        _port: StepperVelocityEL70x1DummyPort,
+    ) -> Result<StepperVelocityEL70x1Output, anyhow::Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state.output.clone())
+    }
+
+    fn get_speed_range(&self, _port: StepperVelocityEL70x1DummyPort) -> EL70x1SpeedRange {
+        self.speed_range
+    }
+}
+
+impl StepperVelocityEL70x1Dummy {
+    pub fn new(speed_range: EL70x1SpeedRange, counts_per_step: u16) -> Self {
+        let state = Arc::new(Mutex::new(StepperDummyState {
+            output: StepperVelocityEL70x1Output {
+                velocity: 0,
+                enable: false,
+                reduce_torque: false,
+                reset: false,
+                set_counter: None,
+            },
+            counter: 0.0,
+        }));
+        Self {
+            state,
+            speed_range,
+            counts_per_step,
+        }
+    }
+
+    pub fn stepper_velocity(&self) -> StepperVelocityEL70x1 {
+        StepperVelocityEL70x1::new(
+            Arc::new(RwLock::new(self.clone())),
+            StepperVelocityEL70x1DummyPort,
+        )
+    }
+
+    /// Full steps per second the stepper currently turns with, 0 while it is disabled
+    pub fn speed(&self) -> f64 {
+        let state = self.state.lock().unwrap();
+        if !state.output.enable {
+            return 0.0;
+        }
+        // the terminal turns with the exact fraction of its speed range
+        let max_steps = EL70x1VelocityConverter::new(&self.speed_range)
+            .velocity_to_steps(i16::MAX, false) as f64;
+        state.output.velocity as f64 / i16::MAX as f64 * max_steps
+    }
+
+    /// Moves the stepper for `dt` with its current speed
+    pub fn advance(&self, dt: Duration) -> f64 {
+        let steps = self.speed() * dt.as_secs_f64();
+        let mut state = self.state.lock().unwrap();
+        state.counter += steps * self.counts_per_step as f64;
+        steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stepper_velocity_el70x1_dummy() {
+        let dummy = StepperVelocityEL70x1Dummy::new(EL70x1SpeedRange::Steps1000, 64);
+        let mut stepper = dummy.stepper_velocity();
+
+        stepper.set_speed(500.0).unwrap();
+        // a disabled stepper doesn't move
+        assert_eq!(dummy.advance(Duration::from_secs(1)), 0.0);
+
+        stepper.set_enabled(true);
+        let steps = dummy.advance(Duration::from_secs(1));
+        assert!((steps - 500.0).abs() <= 1.0, "{}", steps);
+        assert_eq!(stepper.get_position(), (steps * 64.0).round() as i128);
+
+        stepper.set_position(0);
+        assert_eq!(stepper.get_position(), 0);
+        // the counter is only set once
+        dummy.advance(Duration::from_secs(1));
+        assert!(stepper.get_position() > 0);
+    }
+}
@@ -0,0 +1,65 @@
+use clap::Parser;
+use hil::{
+    client::ServerClient,
+    replay::{read_recording, replay},
+};
+use std::{path::PathBuf, process::ExitCode};
+
+/// Feeds a session recording into a server running `--simulate`
+#[derive(Parser, Debug)]
+#[command(name = "replay")]
+struct Cli {
+    /// Recording from the `recordings` directory of the server, e.g. `session-1700000000000.jsonl.gz`
+    recording: PathBuf,
+
+    /// Base URL of the simulated server
+    #[arg(long, default_value = "http://localhost:3001")]
+    server: String,
+
+    /// User to log in with, an engineer to inject sensor values
+    #[arg(long, env = "HIL_USER")]
+    user: Option<String>,
+
+    #[arg(long, env = "HIL_PASSWORD", default_value = "")]
+    password: String,
+
+    /// Pace relative to the recording, e.g. 10 to replay an hour in six minutes
+    #[arg(long, default_value_t = 1.0)]
+    speed: f64,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    if !(cli.speed.is_finite() && cli.speed > 0.0) {
+        eprintln!("--speed has to be positive");
+        return ExitCode::FAILURE;
+    }
+
+    let mut client = ServerClient::new(&cli.server);
+    if let Some(user) = &cli.user {
+        if let Err(e) = client.login(user, &cli.password) {
+            eprintln!("{:?}", e);
+            return ExitCode::FAILURE;
+        }
+    }
+    // sensor values can only be injected into the simulation
+    if let Err(e) = client.simulation() {
+        eprintln!("{:?}", e);
+        return ExitCode::FAILURE;
+    }
+
+    let entries = match read_recording(&cli.recording) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("{:?}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("Replaying {} entries", entries.len());
+    let report = replay(&client, &entries, cli.speed);
+    println!(
+        "Sent {} mutations and {} sensor injections, {} failed",
+        report.mutations, report.injections, report.failed
+    );
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,181 @@
+use crate::scenario::MachineRef;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use std::time::Duration;
+use ureq::Agent;
+
+/// Timeout of a single request, the scenario timing is handled by the runner
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MachineIdentification {
+    pub vendor: u16,
+    pub machine: u16,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MachineIdentificationUnique {
+    pub machine_identification: MachineIdentification,
+    pub serial: u16,
+}
+
+#[derive(Deserialize, Debug)]
+struct MachineObj {
+    machine_identification_unique: MachineIdentificationUnique,
+}
+
+/// Latest value of a machine event
+#[derive(Deserialize, Debug, Clone)]
+pub struct LatestEvent {
+    /// unix timestamp in milliseconds
+    pub ts: u64,
+    pub data: Value,
+}
+
+#[derive(Deserialize, Debug)]
+struct Session {
+    token: String,
+}
+
+/// REST client of a running server, the simulator or a lab rig
+#[derive(Debug)]
+pub struct ServerClient {
+    agent: Agent,
+    url: String,
+    token: Option<String>,
+}
+
+impl ServerClient {
+    /// `url` like `http://localhost:3001`
+    pub fn new(url: &str) -> Self {
+        let agent = Agent::config_builder()
+            .timeout_global(Some(REQUEST_TIMEOUT))
+            .build()
+            .into();
+        Self {
+            agent,
+            url: url.trim_end_matches('/').to_string(),
+            token: None,
+        }
+    }
+
+    /// Starts a session, without one only reading works on servers with users
+    pub fn login(&mut self, name: &str, password: &str) -> Result<(), anyhow::Error> {
+        let session: Session = self.post(
+            "/api/v1/auth/login",
+            &json!({"name": name, "password": password}),
+        )?;
+        self.token = Some(session.token);
+        Ok(())
+    }
+
+    /// Resolves the serial of machines declared without one
+    pub fn find_machine(
+        &self,
+        machine: &MachineRef,
+    ) -> Result<MachineIdentificationUnique, anyhow::Error> {
+        let machines: Vec<MachineObj> = self.get("/api/v1/machines")?;
+        machines
+            .into_iter()
+            .map(|obj| obj.machine_identification_unique)
+            .find(|found| {
+                found.machine_identification.vendor == machine.vendor
+                    && found.machine_identification.machine == machine.machine
+                    && machine.serial.is_none_or(|serial| serial == found.serial)
+            })
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "[{}::ServerClient::find_machine] No machine {:?} is connected",
+                    module_path!(),
+                    machine
+                )
+            })
+    }
+
+    pub fn mutate_machine(
+        &self,
+        machine: &MachineIdentificationUnique,
+        data: &Value,
+    ) -> Result<(), anyhow::Error> {
+        self.post::<Value>(&Self::machine_path(machine, "mutate"), data)?;
+        Ok(())
+    }
+
+    /// `None` if the machine has not emitted the event yet
+    pub fn machine_event(
+        &self,
+        machine: &MachineIdentificationUnique,
+        event: &str,
+    ) -> Result<Option<LatestEvent>, anyhow::Error> {
+        match self.get(&Self::machine_path(machine, &format!("events/{}", event))) {
+            Ok(event) => Ok(Some(event)),
+            Err(e) if is_not_found(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Drives and sensors of the simulated line, fails if the server is not simulating
+    pub fn simulation(&self) -> Result<Value, anyhow::Error> {
+        self.get("/api/v1/simulation")
+    }
+
+    /// Replaces the injected sensor values of the simulation
+    pub fn inject(&self, overrides: &Value) -> Result<(), anyhow::Error> {
+        self.post::<Value>(
+            "/api/v1/simulation/mutate",
+            &json!({ "SetOverrides": overrides }),
+        )?;
+        Ok(())
+    }
+
+    fn machine_path(machine: &MachineIdentificationUnique, rest: &str) -> String {
+        format!(
+            "/api/v1/machines/{}/{}/{}/{}",
+            machine.machine_identification.vendor,
+            machine.machine_identification.machine,
+            machine.serial,
+            rest
+        )
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, anyhow::Error> {
+        let mut request = self.agent.get(format!("{}{}", self.url, path));
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        let mut response = request.call().map_err(|e| request_error(path, e))?;
+        response
+            .body_mut()
+            .read_json()
+            .map_err(|e| request_error(path, e))
+    }
+
+    fn post<T: DeserializeOwned>(&self, path: &str, body: &Value) -> Result<T, anyhow::Error> {
+        let mut request = self.agent.post(format!("{}{}", self.url, path));
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        let mut response = request
+            .send_json(body)
+            .map_err(|e| request_error(path, e))?;
+        response
+            .body_mut()
+            .read_json()
+            .map_err(|e| request_error(path, e))
+    }
+}
+
+fn request_error(path: &str, error: ureq::Error) -> anyhow::Error {
+    anyhow::anyhow!(error).context(format!(
+        "[{}::ServerClient] Request to {} failed",
+        module_path!(),
+        path
+    ))
+}
+
+fn is_not_found(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<ureq::Error>(),
+        Some(ureq::Error::StatusCode(404))
+    )
+}
@@ -0,0 +1,11 @@
+//! Scripted scenarios against a running server
+//!
+//! A [`scenario::Scenario`] sets targets through machine mutations, injects sensor values and
+//! asserts events and actuator commands with timing tolerances. Against `server --simulate`
+//! all steps are available, against a lab rig only mutations, waits and machine events.
+//! [`replay::replay`] feeds a session recorded in the field into the simulator.
+
+pub mod client;
+pub mod replay;
+pub mod runner;
+pub mod scenario;
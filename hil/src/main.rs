@@ -0,0 +1,73 @@
+use clap::Parser;
+use hil::{client::ServerClient, runner::run_scenario, scenario::Scenario};
+use std::{path::PathBuf, process::ExitCode};
+
+/// Runs scenario files against a server, exits with 1 if any scenario fails
+#[derive(Parser, Debug)]
+#[command(name = "hil")]
+struct Cli {
+    /// Scenario files
+    #[arg(required = true)]
+    scenarios: Vec<PathBuf>,
+
+    /// Base URL of the server
+    #[arg(long, default_value = "http://localhost:3001")]
+    server: String,
+
+    /// User to log in with, an operator for mutations, an engineer to inject sensor values
+    #[arg(long, env = "HIL_USER")]
+    user: Option<String>,
+
+    #[arg(long, env = "HIL_PASSWORD", default_value = "")]
+    password: String,
+
+    /// Skip scenarios that inject sensor values or check actuators, for lab rigs
+    #[arg(long)]
+    hardware: bool,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let mut client = ServerClient::new(&cli.server);
+    if let Some(user) = &cli.user {
+        if let Err(e) = client.login(user, &cli.password) {
+            eprintln!("{:?}", e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let mut failed = 0;
+    for path in &cli.scenarios {
+        let scenario = match Scenario::read(path) {
+            Ok(scenario) => scenario,
+            Err(e) => {
+                eprintln!("{:?}", e);
+                failed += 1;
+                continue;
+            }
+        };
+        if cli.hardware && scenario.needs_simulation() {
+            println!("SKIP {} (needs --simulate)", scenario.name);
+            continue;
+        }
+
+        let report = run_scenario(&client, &scenario);
+        for step in &report.steps {
+            println!("  {:>6.2} s  {:?}", step.elapsed.as_secs_f64(), step.step);
+        }
+        match &report.failure {
+            None => println!("PASS {}", report.name),
+            Some((step, reason)) => {
+                failed += 1;
+                println!("FAIL {} at step {}\n{}", report.name, step, reason);
+            }
+        }
+    }
+
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,238 @@
+use crate::{
+    client::{MachineIdentificationUnique, ServerClient},
+    scenario::MachineRef,
+};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+const VENDOR_QITECH: u16 = 0x0001;
+const MACHINE_WINDER_V1: u16 = 0x0002;
+const MACHINE_LASER_V1: u16 = 0x0006;
+
+/// Minimum time between two injections, the laser emits its live values at up to 30 Hz
+const INJECT_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind")]
+pub enum RecordedKind {
+    Event { name: String, data: Value },
+    Mutation { data: Value },
+}
+
+/// One line of a session recording of the server, see `GET /api/v1/recording`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RecordedEntry {
+    /// milliseconds since the recorded server started
+    pub mono: u64,
+    /// e.g. `/machine/1/6/3`
+    pub namespace: String,
+    #[serde(flatten)]
+    pub kind: RecordedKind,
+}
+
+/// Reads a gzip compressed recording, e.g. `session-1700000000000.jsonl.gz`
+pub fn read_recording(path: &Path) -> Result<Vec<RecordedEntry>, anyhow::Error> {
+    let file = File::open(path).map_err(|e| {
+        anyhow::anyhow!(
+            "[{}::read_recording] Failed to open {:?}: {}",
+            module_path!(),
+            path,
+            e
+        )
+    })?;
+    let reader = BufReader::new(flate2::read::GzDecoder::new(file));
+    let mut entries = vec![];
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line).map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::read_recording] Invalid entry in line {} of {:?}: {}",
+                module_path!(),
+                i + 1,
+                path,
+                e
+            )
+        })?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// What the replay does with a recorded entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayAction {
+    Mutate {
+        machine: MachineRef,
+        data: Value,
+    },
+    /// Sensor values of the simulation, all seen so far
+    Inject(Value),
+}
+
+/// Machine type of a machine namespace, the serials of the simulation differ from the field
+fn machine_of(namespace: &str) -> Option<MachineRef> {
+    let mut parts = namespace.strip_prefix("/machine/")?.split('/');
+    let vendor = parts.next()?.parse().ok()?;
+    let machine = parts.next()?.parse().ok()?;
+    Some(MachineRef {
+        vendor,
+        machine,
+        serial: None,
+    })
+}
+
+/// Mutations are sent again, the live values of the laser and the winder become sensor values
+/// of the simulation. Everything else follows from them.
+pub fn replay_action(
+    entry: &RecordedEntry,
+    overrides: &mut Map<String, Value>,
+) -> Option<ReplayAction> {
+    let machine = machine_of(&entry.namespace)?;
+    match &entry.kind {
+        RecordedKind::Mutation { data } => Some(ReplayAction::Mutate {
+            machine,
+            data: data.clone(),
+        }),
+        RecordedKind::Event { name, data } if name == "LiveValuesEvent" => {
+            let (field, sensor) = match (machine.vendor, machine.machine) {
+                (VENDOR_QITECH, MACHINE_LASER_V1) => ("diameter", "laser_diameter"),
+                (VENDOR_QITECH, MACHINE_WINDER_V1) => ("tension_arm_angle", "tension_arm_angle"),
+                _ => return None,
+            };
+            let value = data.get(field).filter(|value| value.is_number())?;
+            if overrides.get(sensor) == Some(value) {
+                return None;
+            }
+            overrides.insert(sensor.to_string(), value.clone());
+            Some(ReplayAction::Inject(Value::Object(overrides.clone())))
+        }
+        RecordedKind::Event { .. } => None,
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ReplayReport {
+    pub mutations: usize,
+    pub injections: usize,
+    /// Requests the server rejected, a recorded mutation may have been rejected as well
+    pub failed: usize,
+}
+
+/// Feeds the recording into a server running `--simulate` at `speed` times the recorded pace
+pub fn replay(client: &ServerClient, entries: &[RecordedEntry], speed: f64) -> ReplayReport {
+    let mut report = ReplayReport::default();
+    let Some(first) = entries.first() else {
+        return report;
+    };
+    let start = Instant::now();
+    let mut machines: BTreeMap<(u16, u16), MachineIdentificationUnique> = BTreeMap::new();
+    let mut overrides = Map::new();
+    let mut last_injection: Option<Instant> = None;
+
+    for entry in entries {
+        let Some(action) = replay_action(entry, &mut overrides) else {
+            continue;
+        };
+        let due = Duration::from_secs_f64(entry.mono.saturating_sub(first.mono) as f64 / 1000.0)
+            .div_f64(speed);
+        thread::sleep(due.saturating_sub(start.elapsed()));
+
+        let result = match action {
+            ReplayAction::Mutate { machine, data } => {
+                report.mutations += 1;
+                let key = (machine.vendor, machine.machine);
+                let found = match machines.get(&key) {
+                    Some(found) => Ok(*found),
+                    None => client.find_machine(&machine),
+                };
+                found.and_then(|found| {
+                    machines.insert(key, found);
+                    client.mutate_machine(&found, &data)
+                })
+            }
+            ReplayAction::Inject(sensors) => {
+                let now = Instant::now();
+                if last_injection.is_some_and(|last| now.duration_since(last) < INJECT_INTERVAL) {
+                    continue;
+                }
+                last_injection = Some(now);
+                report.injections += 1;
+                client.inject(&sensors)
+            }
+        };
+        if let Err(e) = result {
+            report.failed += 1;
+            eprintln!(
+                "{:>8.2} s  {}: {:?}",
+                entry.mono.saturating_sub(first.mono) as f64 / 1000.0,
+                entry.namespace,
+                e
+            );
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_replay_action() {
+        let entry = |line: Value| serde_json::from_value::<RecordedEntry>(line).unwrap();
+        let mut overrides = Map::new();
+
+        let mutation = entry(json!({
+            "mono": 10,
+            "namespace": "/machine/1/2/7",
+            "kind": "Mutation",
+            "data": { "SetMode": "Pull" }
+        }));
+        assert_eq!(
+            replay_action(&mutation, &mut overrides),
+            Some(ReplayAction::Mutate {
+                machine: MachineRef {
+                    vendor: 1,
+                    machine: 2,
+                    serial: None
+                },
+                data: json!({ "SetMode": "Pull" })
+            })
+        );
+
+        let diameter = entry(json!({
+            "mono": 20,
+            "namespace": "/machine/1/6/3",
+            "kind": "Event",
+            "name": "LiveValuesEvent",
+            "data": { "diameter": 1.76, "x_diameter": null }
+        }));
+        assert_eq!(
+            replay_action(&diameter, &mut overrides),
+            Some(ReplayAction::Inject(json!({ "laser_diameter": 1.76 })))
+        );
+        // unchanged values are not injected again
+        assert_eq!(replay_action(&diameter, &mut overrides), None);
+
+        let alarms = entry(json!({
+            "mono": 30,
+            "namespace": "/alarms",
+            "kind": "Event",
+            "name": "AlarmsEvent",
+            "data": {}
+        }));
+        assert_eq!(replay_action(&alarms, &mut overrides), None);
+    }
+}
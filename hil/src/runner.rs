@@ -0,0 +1,129 @@
+use crate::{
+    client::{MachineIdentificationUnique, ServerClient},
+    scenario::{Expectation, Scenario, Step},
+};
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often expectations are polled until they match
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Outcome of a scenario, it stops at the first failing step
+#[derive(Debug)]
+pub struct ScenarioReport {
+    pub name: String,
+    pub steps: Vec<StepReport>,
+    /// Index of the failed step and why it failed
+    pub failure: Option<(usize, String)>,
+}
+
+#[derive(Debug)]
+pub struct StepReport {
+    pub step: Step,
+    /// Time the step took, for expectations how long the value took to match
+    pub elapsed: Duration,
+}
+
+impl ScenarioReport {
+    pub const fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// Runs the steps of a scenario in order against the server of the client
+pub fn run_scenario(client: &ServerClient, scenario: &Scenario) -> ScenarioReport {
+    let mut report = ScenarioReport {
+        name: scenario.name.clone(),
+        steps: Vec::new(),
+        failure: None,
+    };
+
+    let machines = match resolve_machines(client, scenario) {
+        Ok(machines) => machines,
+        Err(e) => {
+            report.failure = Some((0, format!("{:?}", e)));
+            return report;
+        }
+    };
+
+    for (index, step) in scenario.steps.iter().enumerate() {
+        let start = Instant::now();
+        if let Err(e) = run_step(client, &machines, step) {
+            report.failure = Some((index + 1, format!("{:?}", e)));
+            return report;
+        }
+        report.steps.push(StepReport {
+            step: step.clone(),
+            elapsed: start.elapsed(),
+        });
+    }
+    report
+}
+
+fn resolve_machines(
+    client: &ServerClient,
+    scenario: &Scenario,
+) -> Result<BTreeMap<String, MachineIdentificationUnique>, anyhow::Error> {
+    scenario
+        .machines
+        .iter()
+        .map(|(name, machine)| Ok((name.clone(), client.find_machine(machine)?)))
+        .collect()
+}
+
+fn run_step(
+    client: &ServerClient,
+    machines: &BTreeMap<String, MachineIdentificationUnique>,
+    step: &Step,
+) -> Result<(), anyhow::Error> {
+    match step {
+        Step::Mutate { machine, data } => client.mutate_machine(&machines[machine], data),
+        Step::Inject(overrides) => client.inject(overrides),
+        Step::Wait(seconds) => {
+            thread::sleep(Duration::from_secs_f64(seconds.max(0.0)));
+            Ok(())
+        }
+        Step::ExpectEvent {
+            machine,
+            event,
+            expectation,
+        } => {
+            let machine = &machines[machine];
+            poll(expectation, || {
+                Ok(client
+                    .machine_event(machine, event)?
+                    .map_or(Value::Null, |latest| latest.data))
+            })
+        }
+        Step::ExpectActuator(expectation) => poll(expectation, || client.simulation()),
+    }
+}
+
+/// Reads the value until the expectation matches, fails once its `within` ran out
+fn poll(
+    expectation: &Expectation,
+    mut read: impl FnMut() -> Result<Value, anyhow::Error>,
+) -> Result<(), anyhow::Error> {
+    let deadline = Instant::now() + expectation.within();
+    loop {
+        let mismatch = match expectation.check(&read()?) {
+            Ok(()) => return Ok(()),
+            Err(mismatch) => mismatch,
+        };
+        if Instant::now() >= deadline {
+            return Err(anyhow::anyhow!(
+                "[{}::poll] Expected {} at {} within {:.1} s, {}",
+                module_path!(),
+                expectation.value,
+                expectation.pointer,
+                expectation.within,
+                mismatch
+            ));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,221 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::{collections::BTreeMap, path::Path, time::Duration};
+
+/// Steps run in order against one server, read from a TOML file
+///
+/// ```toml
+/// name = "Winder pulls at the target speed"
+///
+/// [machines]
+/// winder = { vendor = 1, machine = 2 }
+///
+/// [[steps]]
+/// mutate = { machine = "winder", data = { SetMode = "Pull" } }
+///
+/// [[steps]]
+/// expect_actuator = { pointer = "/puller_speed", value = 1.0, tolerance = 0.05, within = 5.0 }
+/// ```
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub name: String,
+    /// Machines referenced by the steps, by a name chosen in the scenario
+    #[serde(default)]
+    pub machines: BTreeMap<String, MachineRef>,
+    pub steps: Vec<Step>,
+}
+
+/// Machine type and optionally the serial, the first connected machine of the type if unset
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct MachineRef {
+    pub vendor: u16,
+    pub machine: u16,
+    pub serial: Option<u16>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    /// Sends a machine mutation, e.g. a new target
+    Mutate { machine: String, data: Value },
+    /// Replaces the injected sensor values of the simulation, see `SensorOverrides` of the server
+    Inject(Value),
+    /// Seconds to let the line run
+    Wait(f64),
+    /// Waits for the latest event of a machine to match
+    ExpectEvent {
+        machine: String,
+        event: String,
+        #[serde(flatten)]
+        expectation: Expectation,
+    },
+    /// Waits for the drives of the simulation to match, e.g. the commanded puller speed
+    ExpectActuator(Expectation),
+}
+
+impl Step {
+    /// Whether the step needs a server running with `--simulate`
+    pub const fn needs_simulation(&self) -> bool {
+        matches!(self, Self::Inject(_) | Self::ExpectActuator(_))
+    }
+}
+
+/// Value at a JSON pointer, polled until it matches or the timing tolerance runs out
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Expectation {
+    /// e.g. `/mode_state/mode`, the whole value if empty
+    #[serde(default)]
+    pub pointer: String,
+    pub value: Value,
+    /// Allowed deviation of numbers, other values have to be equal
+    #[serde(default)]
+    pub tolerance: f64,
+    /// Seconds the value may take to match
+    #[serde(default)]
+    pub within: f64,
+}
+
+impl Expectation {
+    pub fn within(&self) -> Duration {
+        Duration::from_secs_f64(self.within.max(0.0))
+    }
+
+    /// Whether the value at the pointer matches, `Err` with what was found otherwise
+    pub fn check(&self, data: &Value) -> Result<(), String> {
+        let Some(actual) = data.pointer(&self.pointer) else {
+            return Err(format!("nothing at {}", self.pointer));
+        };
+        let matches = match (actual.as_f64(), self.value.as_f64()) {
+            (Some(actual), Some(expected)) => (actual - expected).abs() <= self.tolerance,
+            _ => *actual == self.value,
+        };
+        if matches {
+            Ok(())
+        } else {
+            Err(format!("{} is {}", self.pointer, actual))
+        }
+    }
+}
+
+impl Scenario {
+    pub fn read(path: &Path) -> Result<Self, anyhow::Error> {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::Scenario::read] Failed to read {:?}\n{:?}",
+                module_path!(),
+                path,
+                e
+            )
+        })?;
+        let scenario: Self = toml::from_str(&text).map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::Scenario::read] Invalid scenario {:?}\n{}",
+                module_path!(),
+                path,
+                e
+            )
+        })?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// Rejects steps referencing machines the scenario does not declare
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        for (index, step) in self.steps.iter().enumerate() {
+            let machine = match step {
+                Step::Mutate { machine, .. } | Step::ExpectEvent { machine, .. } => machine,
+                _ => continue,
+            };
+            if !self.machines.contains_key(machine) {
+                return Err(anyhow::anyhow!(
+                    "[{}::Scenario::validate] Step {} uses undeclared machine {}",
+                    module_path!(),
+                    index + 1,
+                    machine
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the scenario can only run against a server running with `--simulate`
+    pub fn needs_simulation(&self) -> bool {
+        self.steps.iter().any(Step::needs_simulation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_scenario() {
+        let scenario: Scenario = toml::from_str(
+            r#"
+            name = "pull"
+
+            [machines]
+            winder = { vendor = 1, machine = 2 }
+
+            [[steps]]
+            mutate = { machine = "winder", data = { SetMode = "Pull" } }
+
+            [[steps]]
+            wait = 0.5
+
+            [[steps]]
+            expect_event = { machine = "winder", event = "StateEvent", pointer = "/mode_state/mode", value = "Pull", within = 2.0 }
+            "#,
+        )
+        .unwrap();
+        assert!(scenario.validate().is_ok());
+        assert!(!scenario.needs_simulation());
+        assert!(
+            matches!(&scenario.steps[0], Step::Mutate { data, .. } if data["SetMode"] == "Pull")
+        );
+        assert!(
+            matches!(scenario.steps[1], Step::Wait(seconds) if (seconds - 0.5).abs() < f64::EPSILON)
+        );
+
+        let scenario: Scenario = toml::from_str(
+            r#"
+            name = "typo"
+
+            [[steps]]
+            mutate = { machine = "puller", data = "Stop" }
+            "#,
+        )
+        .unwrap();
+        assert!(scenario.validate().is_err());
+    }
+
+    #[test]
+    fn test_expectation() {
+        let data = json!({"puller_speed": 1.02, "mode_state": {"mode": "Pull"}});
+
+        let speed = Expectation {
+            pointer: "/puller_speed".to_string(),
+            value: json!(1.0),
+            tolerance: 0.05,
+            within: 1.0,
+        };
+        assert!(speed.check(&data).is_ok());
+        let exact = Expectation {
+            tolerance: 0.0,
+            ..speed
+        };
+        assert_eq!(exact.check(&data), Err("/puller_speed is 1.02".to_string()));
+
+        let mode = Expectation {
+            pointer: "/mode_state/mode".to_string(),
+            value: json!("Pull"),
+            tolerance: 0.0,
+            within: 0.0,
+        };
+        assert!(mode.check(&data).is_ok());
+        assert!(mode.check(&json!({})).is_err());
+    }
+}
@@ -75,6 +75,8 @@ serviceConfig = {
   User = cfg.user;
   Group = cfg.group;
   ExecStart = "${cfg.package}/bin/server";
+  # reloads the config files without interrupting a run
+  ExecReload = "${pkgs.coreutils}/bin/kill -HUP $MAINPID";
   Restart = "always";
   RestartSec = "10s";
 
@@ -87,6 +89,8 @@ serviceConfig = {
   ProtectSystem = "strict";
   # Open only /proc/irq explicitly
   ReadWritePaths = [ "/proc/irq" ];
+  # Persistent data like recipes, exported as STATE_DIRECTORY
+  StateDirectory = "qitech-control-server";
   ProtectHome = true;
   PrivateTmp = true;
   PrivateDevices = false;
@@ -0,0 +1,12 @@
+//! Generates the gRPC messages and service of `proto/control.proto`, see
+//! `src/exporters/grpc.rs`
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // the line computers have no protoc installed
+    let mut config = tonic_build::Config::new();
+    config.protoc_executable(protoc_bin_vendored::protoc_bin_path()?);
+    tonic_build::configure()
+        .build_client(false)
+        .compile_protos_with_config(config, &["proto/control.proto"], &["proto"])?;
+    Ok(())
+}
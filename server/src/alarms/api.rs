@@ -0,0 +1,89 @@
+use crate::app_state::AppState;
+use control_core::{
+    alarms::Alarm,
+    socketio::{
+        event::{BuildEvent, Event, GenericEvent},
+        namespace::{CacheFn, CacheableEvents, Namespace, NamespaceCacheingLogic, cache_one_event},
+        namespace_id::NamespaceId,
+    },
+};
+use control_core_derive::BuildEvent;
+use serde::{Deserialize, Serialize};
+use smol::channel::Sender;
+use socketioxide::extract::SocketRef;
+use std::sync::Arc;
+use tracing::instrument;
+
+#[derive(Serialize, Debug, Clone, BuildEvent)]
+pub struct AlarmsEvent {
+    /// Active and acknowledged alarms of all machines, most severe first
+    pub active: Vec<Alarm>,
+    /// Recently cleared alarms, newest first
+    pub cleared: Vec<Alarm>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub enum Mutation {
+    /// Acknowledge the alarm with the given id
+    Acknowledge(u64),
+    AcknowledgeAll,
+}
+
+pub enum AlarmsNamespaceEvents {
+    Alarms(Event<AlarmsEvent>),
+}
+
+impl CacheableEvents<Self> for AlarmsNamespaceEvents {
+    fn event_value(&self) -> GenericEvent {
+        match self {
+            Self::Alarms(event) => event.into(),
+        }
+    }
+
+    fn event_cache_fn(&self) -> CacheFn {
+        match self {
+            Self::Alarms(_) => cache_one_event(),
+        }
+    }
+}
+
+pub struct AlarmsRoom {
+    pub namespace: Namespace,
+}
+
+impl AlarmsRoom {
+    pub fn new(socket_queue_tx: Sender<(SocketRef, Arc<GenericEvent>)>) -> Self {
+        Self {
+            namespace: Namespace::new(socket_queue_tx).with_id(NamespaceId::Alarms),
+        }
+    }
+}
+
+impl NamespaceCacheingLogic<AlarmsNamespaceEvents> for AlarmsRoom {
+    #[instrument(skip_all)]
+    fn emit(&mut self, event: AlarmsNamespaceEvents) {
+        let buffer_fn = event.event_cache_fn();
+        let generic_event = Arc::new(event.event_value());
+        self.namespace.emit(generic_event, &buffer_fn);
+    }
+}
+
+/// Emits the alarm list to the alarms namespace
+pub async fn emit_alarms(app_state: &Arc<AppState>) {
+    let event = {
+        let alarms = app_state.alarms.read().await;
+        AlarmsEvent {
+            active: alarms.active(),
+            cleared: alarms.cleared(),
+        }
+        .build()
+    };
+
+    let alarms_namespace = &mut app_state
+        .socketio_setup
+        .namespaces
+        .write()
+        .await
+        .alarms_namespace;
+    alarms_namespace.emit(AlarmsNamespaceEvents::Alarms(event));
+}
@@ -0,0 +1,26 @@
+use crate::app_state::AppState;
+use control_core::helpers::clock::unix_millis;
+use std::sync::Arc;
+
+/// Continues the alarm ids of the history and clears the alarms left open by the last run
+pub async fn restore_alarm_history(app_state: &Arc<AppState>) {
+    let mut history = app_state.history.lock().await;
+    if let Err(e) = history.clear_open_alarms(unix_millis()) {
+        tracing::error!("Failed to clear open alarms of the last run: {:?}", e);
+    }
+    match history.last_alarm_id() {
+        Ok(id) => app_state.alarms.write().await.continue_ids_after(id),
+        Err(e) => tracing::error!("Failed to read the last alarm id: {:?}", e),
+    }
+}
+
+/// Writes the alarms that changed since the last call to the history
+pub async fn persist_alarm_changes(app_state: &Arc<AppState>) {
+    let changes = app_state.alarms.write().await.take_changes();
+    if changes.is_empty() {
+        return;
+    }
+    if let Err(e) = app_state.history.lock().await.upsert_alarms(&changes) {
+        tracing::error!("Failed to persist {} alarms: {:?}", changes.len(), e);
+    }
+}
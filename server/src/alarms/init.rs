@@ -0,0 +1,145 @@
+use super::{
+    api::emit_alarms,
+    history::{persist_alarm_changes, restore_alarm_history},
+};
+use crate::{
+    app_state::AppState,
+    panic::{PanicDetails, send_panic},
+};
+use control_core::{
+    alarms::{AlarmCondition, AlarmSeverity},
+    helpers::clock::unix_millis,
+    machines::{connection::MachineConnection, identification::MachineIdentificationUnique},
+};
+use smol::channel::Sender;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+
+/// Interval the machines are polled for alarm conditions
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+pub fn init_alarms(
+    thread_panic_tx: Sender<PanicDetails>,
+    app_state: Arc<AppState>,
+) -> Result<(), anyhow::Error> {
+    smol::block_on(async {
+        restore_alarm_history(&app_state).await;
+        emit_alarms(&app_state).await;
+    });
+
+    std::thread::Builder::new()
+        .name("alarms".to_owned())
+        .spawn(move || {
+            send_panic(thread_panic_tx);
+            smol::block_on(async {
+                let mut known = HashSet::new();
+                let mut machine_conditions = HashMap::new();
+                loop {
+                    smol::Timer::after(POLL_INTERVAL).await;
+
+                    let conditions = collect_conditions(&app_state, &mut machine_conditions).await;
+                    let now = unix_millis();
+                    let mut changed = false;
+                    {
+                        let mut alarms = app_state.alarms.write().await;
+                        // machines that were removed have no conditions anymore
+                        for machine in known.iter() {
+                            if !conditions.iter().any(|(m, _)| m == machine) {
+                                changed |= alarms.update(Some(machine), &[], now);
+                            }
+                        }
+                        for (machine, conditions) in &conditions {
+                            changed |= alarms.update(Some(machine), conditions, now);
+                        }
+                    }
+                    known = conditions.into_iter().map(|(machine, _)| machine).collect();
+
+                    if changed {
+                        persist_alarm_changes(&app_state).await;
+                        emit_alarms(&app_state).await;
+                    }
+                }
+            });
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_alarms] Failed to spawn alarms thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Current conditions of every machine
+///
+/// Besides the conditions a machine reports itself, machines with an error, a lost
+/// connection or a stalled act cycle raise an alarm.
+///
+/// `machine_conditions` keeps the last conditions reported by each machine, they are
+/// reused while a machine is locked so a hanging machine does not block the alarms.
+async fn collect_conditions(
+    app_state: &Arc<AppState>,
+    machine_conditions: &mut HashMap<MachineIdentificationUnique, Vec<AlarmCondition>>,
+) -> Vec<(MachineIdentificationUnique, Vec<AlarmCondition>)> {
+    // conditions of connected machines are read after releasing the machines lock
+    let mut conditions = Vec::new();
+    let mut connected = Vec::new();
+    for (machine, slot) in app_state.machines.read().await.iter() {
+        match &slot.lock_blocking().machine_connection {
+            MachineConnection::Connected(m) => connected.push((machine.clone(), m.clone())),
+            MachineConnection::Error(e) => conditions.push((
+                machine.clone(),
+                vec![AlarmCondition::new(
+                    "machine_error",
+                    format!("Machine has error: {}", e),
+                    AlarmSeverity::Critical,
+                )],
+            )),
+            MachineConnection::Degraded(_) => conditions.push((
+                machine.clone(),
+                vec![AlarmCondition::new(
+                    "machine_degraded",
+                    "Hardware of the machine is gone, waiting for it to reconnect",
+                    AlarmSeverity::Warning,
+                )],
+            )),
+            MachineConnection::Disconnected => conditions.push((
+                machine.clone(),
+                vec![AlarmCondition::new(
+                    "machine_disconnected",
+                    "Machine is disconnected",
+                    AlarmSeverity::Warning,
+                )],
+            )),
+        }
+    }
+
+    machine_conditions.retain(|machine, _| connected.iter().any(|(m, _)| m == machine));
+    let watchdog = app_state.watchdog.read().await;
+    for (machine, m) in connected {
+        if let Some(m) = m.try_lock() {
+            machine_conditions.insert(machine.clone(), m.api_alarms());
+        }
+        let mut current = machine_conditions
+            .get(&machine)
+            .cloned()
+            .unwrap_or_default();
+        if watchdog.is_stalled(&machine) {
+            current.push(AlarmCondition::new(
+                "act_loop_stalled",
+                format!(
+                    "Machine did not act for more than {}ms",
+                    watchdog.config.deadline_ms
+                ),
+                AlarmSeverity::Critical,
+            ));
+        }
+        conditions.push((machine, current));
+    }
+    conditions
+}
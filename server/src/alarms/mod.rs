@@ -0,0 +1,4 @@
+pub mod api;
+pub mod history;
+pub mod init;
+pub mod notifier;
@@ -0,0 +1,521 @@
+use crate::{
+    app_state::AppState,
+    machines::machine_slug,
+    panic::{PanicDetails, send_panic},
+    storage,
+};
+use control_core::{
+    alarms::{Alarm, AlarmSeverity, AlarmState},
+    helpers::clock::unix_millis,
+};
+use lettre::{
+    Message, SmtpTransport, Transport, message::header::ContentType,
+    transport::smtp::authentication::Credentials,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use smol::channel::Sender;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+/// File inside [`storage::data_dir`] configuring the notifier
+///
+/// The notifier only sends notifications while the file exists.
+pub const NOTIFIER_FILE: &str = "notifier.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NotifierConfig {
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    /// Required if any rule sends emails
+    #[serde(default)]
+    pub smtp: Option<SmtpConfig>,
+    pub rules: Vec<NotificationRule>,
+    /// Minimum time between two notifications of a rule for the same alarm code and machine,
+    /// so a flapping condition does not flood the targets
+    #[serde(default = "default_rate_limit_s")]
+    pub rate_limit_s: u64,
+    #[serde(default = "default_interval_ms")]
+    pub interval_ms: u64,
+}
+
+const fn default_rate_limit_s() -> u64 {
+    15 * 60
+}
+
+const fn default_interval_ms() -> u64 {
+    1000
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WebhookConfig {
+    /// Referenced by [`NotificationTarget::Webhook`]
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub format: WebhookFormat,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WebhookFormat {
+    /// `{"text": "..."}` as accepted by Slack and Teams incoming webhooks
+    #[default]
+    Text,
+    /// The rule name and the alarm as JSON
+    Json,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SmtpSecurity {
+    None,
+    #[default]
+    StartTls,
+    Tls,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SmtpConfig {
+    pub host: String,
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    pub from: String,
+}
+
+const fn default_smtp_port() -> u16 {
+    587
+}
+
+/// Routes matching alarms to targets
+///
+/// E.g. codes `["out_of_tolerance"]` with `min_active_s` 300 notifies about diameters
+/// that are out of tolerance for more than 5 minutes.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NotificationRule {
+    pub name: String,
+    /// Alarm codes the rule matches, e.g. `filament_break` or `machine_disconnected`,
+    /// empty matches all codes
+    #[serde(default)]
+    pub codes: Vec<String>,
+    #[serde(default = "default_min_severity")]
+    pub min_severity: AlarmSeverity,
+    /// Time the alarm has to be active and unacknowledged before it is sent
+    #[serde(default)]
+    pub min_active_s: u64,
+    pub targets: Vec<NotificationTarget>,
+}
+
+const fn default_min_severity() -> AlarmSeverity {
+    AlarmSeverity::Critical
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum NotificationTarget {
+    /// Name of a configured webhook
+    Webhook(String),
+    /// Email address, sent over the configured SMTP server
+    Email(String),
+}
+
+impl NotificationRule {
+    fn matches(&self, alarm: &Alarm, now: u64) -> bool {
+        alarm.state == AlarmState::Active
+            && alarm.severity >= self.min_severity
+            && (self.codes.is_empty() || self.codes.contains(&alarm.code))
+            && now.saturating_sub(alarm.raised_at) >= self.min_active_s * 1000
+    }
+}
+
+/// Alarm that is due to be sent to the targets of a rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub rule: String,
+    pub targets: Vec<NotificationTarget>,
+    pub alarm: Alarm,
+}
+
+/// Decides which alarms are sent, every alarm is sent at most once per rule
+#[derive(Debug)]
+pub struct Notifier {
+    rules: Vec<NotificationRule>,
+    rate_limit: u64,
+    /// Rule index and alarm id of alarms that were handled
+    handled: HashSet<(usize, u64)>,
+    /// Last notification per rule index, machine and code
+    last_sent: HashMap<(usize, String), u64>,
+}
+
+impl Notifier {
+    pub fn new(rules: Vec<NotificationRule>, rate_limit_s: u64) -> Self {
+        Self {
+            rules,
+            rate_limit: rate_limit_s * 1000,
+            handled: HashSet::new(),
+            last_sent: HashMap::new(),
+        }
+    }
+
+    /// Notifications for the active alarms that became due
+    pub fn due(&mut self, active: &[Alarm], now: u64) -> Vec<Notification> {
+        // forget alarms that are no longer active
+        self.handled
+            .retain(|(_, id)| active.iter().any(|alarm| alarm.id == *id));
+
+        let mut notifications = vec![];
+        for (index, rule) in self.rules.iter().enumerate() {
+            for alarm in active {
+                if self.handled.contains(&(index, alarm.id)) || !rule.matches(alarm, now) {
+                    continue;
+                }
+                self.handled.insert((index, alarm.id));
+
+                let key = (index, source_key(alarm));
+                if let Some(last) = self.last_sent.get(&key) {
+                    if now.saturating_sub(*last) < self.rate_limit {
+                        tracing::debug!(
+                            "Rate limited notification of alarm {} for rule {}",
+                            alarm.id,
+                            rule.name
+                        );
+                        continue;
+                    }
+                }
+                self.last_sent.insert(key, now);
+
+                notifications.push(Notification {
+                    rule: rule.name.clone(),
+                    targets: rule.targets.clone(),
+                    alarm: alarm.clone(),
+                });
+            }
+        }
+        notifications
+    }
+}
+
+fn source_key(alarm: &Alarm) -> String {
+    match &alarm.machine {
+        Some(machine) => format!("{}:{}", machine, alarm.code),
+        None => alarm.code.clone(),
+    }
+}
+
+/// Config the notifier thread runs with, `None` if notifications are off
+static NOTIFIER_CONFIG: RwLock<Option<Arc<NotifierConfig>>> = RwLock::new(None);
+
+/// Reads and validates [`NOTIFIER_FILE`], `None` if it does not exist
+pub fn read_notifier_config() -> Result<Option<NotifierConfig>, anyhow::Error> {
+    let Some(config) =
+        storage::read_json::<NotifierConfig>(&storage::data_dir().join(NOTIFIER_FILE))?
+    else {
+        return Ok(None);
+    };
+    validate(&config)?;
+    Ok(Some(config))
+}
+
+/// Replaces the config of the running notifier, returns whether it changed
+pub fn set_notifier_config(config: Option<NotifierConfig>) -> bool {
+    let mut current = NOTIFIER_CONFIG.write().unwrap_or_else(|e| e.into_inner());
+    if current.as_deref() == config.as_ref() {
+        return false;
+    }
+    match &config {
+        Some(config) => tracing::info!(
+            "Sending alarm notifications for {} rules",
+            config.rules.len()
+        ),
+        None => tracing::info!("Alarm notifications are off"),
+    }
+    *current = config.map(Arc::new);
+    true
+}
+
+fn notifier_config() -> Option<Arc<NotifierConfig>> {
+    NOTIFIER_CONFIG
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// Starts the notifier, it stays idle until [`NOTIFIER_FILE`] exists
+pub fn init_notifier(
+    thread_panic_tx: Sender<PanicDetails>,
+    app_state: Arc<AppState>,
+) -> Result<(), anyhow::Error> {
+    set_notifier_config(read_notifier_config()?);
+
+    std::thread::Builder::new()
+        .name("notifier".to_owned())
+        .spawn(move || {
+            send_panic(thread_panic_tx);
+            smol::block_on(async {
+                // rebuilt whenever the config is replaced
+                let mut notifier: Option<(Arc<NotifierConfig>, Notifier)> = None;
+                loop {
+                    let config = notifier_config();
+                    let interval_ms = config
+                        .as_ref()
+                        .map_or(default_interval_ms(), |config| config.interval_ms);
+                    smol::Timer::after(Duration::from_millis(interval_ms)).await;
+
+                    let Some(config) = config else {
+                        notifier = None;
+                        continue;
+                    };
+                    let active = app_state.alarms.read().await.active();
+                    let outdated = notifier
+                        .as_ref()
+                        .is_none_or(|(current, _)| !Arc::ptr_eq(current, &config));
+                    if outdated {
+                        let mut rebuilt = Notifier::new(config.rules.clone(), config.rate_limit_s);
+                        // alarms that were already active don't notify again after a reload
+                        if notifier.is_some() {
+                            rebuilt.due(&active, unix_millis());
+                        }
+                        notifier = Some((config.clone(), rebuilt));
+                    }
+
+                    if let Some((_, notifier)) = &mut notifier {
+                        for notification in notifier.due(&active, unix_millis()) {
+                            send(&config, &notification);
+                        }
+                    }
+                }
+            });
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_notifier] Failed to spawn notifier thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Checks that every target of the rules is configured
+fn validate(config: &NotifierConfig) -> Result<(), anyhow::Error> {
+    for rule in &config.rules {
+        for target in &rule.targets {
+            match target {
+                NotificationTarget::Webhook(name) => {
+                    if !config.webhooks.iter().any(|webhook| &webhook.name == name) {
+                        return Err(anyhow::anyhow!(
+                            "[{}::validate] Rule {} uses unknown webhook {}",
+                            module_path!(),
+                            rule.name,
+                            name
+                        ));
+                    }
+                }
+                NotificationTarget::Email(_) => {
+                    if config.smtp.is_none() {
+                        return Err(anyhow::anyhow!(
+                            "[{}::validate] Rule {} sends emails but no SMTP server is configured",
+                            module_path!(),
+                            rule.name
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn send(config: &NotifierConfig, notification: &Notification) {
+    tracing::info!(
+        "Sending alarm {} for rule {}",
+        notification.alarm.id,
+        notification.rule
+    );
+    for target in &notification.targets {
+        let result = match target {
+            NotificationTarget::Webhook(name) => config
+                .webhooks
+                .iter()
+                .find(|webhook| &webhook.name == name)
+                .map_or(Ok(()), |webhook| send_webhook(webhook, notification)),
+            NotificationTarget::Email(to) => config
+                .smtp
+                .as_ref()
+                .map_or(Ok(()), |smtp| send_email(smtp, to, notification)),
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to send alarm notification to {:?}: {:?}", target, e);
+        }
+    }
+}
+
+fn send_webhook(webhook: &WebhookConfig, notification: &Notification) -> Result<(), anyhow::Error> {
+    let body = match webhook.format {
+        WebhookFormat::Text => json!({ "text": message(&notification.alarm) }),
+        WebhookFormat::Json => json!({
+            "rule": notification.rule,
+            "alarm": notification.alarm,
+        }),
+    };
+    ureq::post(&webhook.url)
+        .header("Content-Type", "application/json")
+        .send(body.to_string())?;
+    Ok(())
+}
+
+fn send_email(
+    smtp: &SmtpConfig,
+    to: &str,
+    notification: &Notification,
+) -> Result<(), anyhow::Error> {
+    let email = Message::builder()
+        .from(smtp.from.parse()?)
+        .to(to.parse()?)
+        .subject(subject(&notification.alarm))
+        .header(ContentType::TEXT_PLAIN)
+        .body(message(&notification.alarm))?;
+
+    let mut transport = match smtp.security {
+        SmtpSecurity::None => SmtpTransport::builder_dangerous(&smtp.host),
+        SmtpSecurity::StartTls => SmtpTransport::starttls_relay(&smtp.host)?,
+        SmtpSecurity::Tls => SmtpTransport::relay(&smtp.host)?,
+    }
+    .port(smtp.port);
+    if let (Some(username), Some(password)) = (&smtp.username, &smtp.password) {
+        transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+    transport.build().send(&email)?;
+    Ok(())
+}
+
+/// Machine the alarm belongs to, e.g. `winder2 #3`
+fn source(alarm: &Alarm) -> String {
+    match &alarm.machine {
+        Some(machine) => {
+            let machine_identification = &machine.machine_identification;
+            match machine_slug(machine_identification.machine) {
+                Some(slug) => format!("{} #{}", slug, machine.serial),
+                None => machine.to_string(),
+            }
+        }
+        None => "line".to_string(),
+    }
+}
+
+fn subject(alarm: &Alarm) -> String {
+    format!("[{:?}] {}: {}", alarm.severity, source(alarm), alarm.code)
+}
+
+fn message(alarm: &Alarm) -> String {
+    format!("{}\n{}", subject(alarm), alarm.message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use control_core::machines::identification::{
+        MachineIdentification, MachineIdentificationUnique,
+    };
+
+    fn alarm(id: u64, code: &str, severity: AlarmSeverity, raised_at: u64) -> Alarm {
+        Alarm {
+            id,
+            machine: Some(MachineIdentificationUnique {
+                machine_identification: MachineIdentification {
+                    vendor: 1,
+                    machine: 6,
+                },
+                serial: 1,
+            }),
+            code: code.to_string(),
+            message: code.to_string(),
+            severity,
+            state: AlarmState::Active,
+            raised_at,
+            acknowledged_at: None,
+            acknowledged_by: None,
+            cleared_at: None,
+        }
+    }
+
+    fn rule(codes: &[&str], min_active_s: u64) -> NotificationRule {
+        NotificationRule {
+            name: "rule".to_string(),
+            codes: codes.iter().map(|code| code.to_string()).collect(),
+            min_severity: AlarmSeverity::Warning,
+            min_active_s,
+            targets: vec![NotificationTarget::Webhook("slack".to_string())],
+        }
+    }
+
+    #[test]
+    fn test_min_active_and_once_per_alarm() {
+        let mut notifier = Notifier::new(vec![rule(&["out_of_tolerance"], 60)], 0);
+        let active = [
+            alarm(1, "out_of_tolerance", AlarmSeverity::Warning, 0),
+            alarm(2, "filament_break", AlarmSeverity::Critical, 0),
+        ];
+
+        assert!(notifier.due(&active, 59_000).is_empty());
+        let due = notifier.due(&active, 60_000);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].alarm.id, 1);
+        assert!(notifier.due(&active, 61_000).is_empty());
+    }
+
+    #[test]
+    fn test_filters() {
+        let mut notifier = Notifier::new(vec![rule(&[], 0)], 0);
+        let mut acknowledged = alarm(2, "b", AlarmSeverity::Critical, 0);
+        acknowledged.state = AlarmState::Acknowledged;
+        let active = [alarm(1, "a", AlarmSeverity::Info, 0), acknowledged];
+        assert!(notifier.due(&active, 0).is_empty());
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let mut notifier = Notifier::new(vec![rule(&["filament_break"], 0)], 60);
+
+        let first = [alarm(1, "filament_break", AlarmSeverity::Critical, 0)];
+        assert_eq!(notifier.due(&first, 0).len(), 1);
+
+        // raised again after clearing, within the rate limit
+        let second = [alarm(2, "filament_break", AlarmSeverity::Critical, 10_000)];
+        assert!(notifier.due(&second, 10_000).is_empty());
+
+        let third = [alarm(3, "filament_break", AlarmSeverity::Critical, 60_000)];
+        assert_eq!(notifier.due(&third, 60_000).len(), 1);
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut config = NotifierConfig {
+            webhooks: vec![],
+            smtp: None,
+            rules: vec![rule(&[], 0)],
+            rate_limit_s: default_rate_limit_s(),
+            interval_ms: default_interval_ms(),
+        };
+        assert!(validate(&config).is_err());
+
+        config.webhooks.push(WebhookConfig {
+            name: "slack".to_string(),
+            url: "https://hooks.slack.com/services/x".to_string(),
+            format: WebhookFormat::Text,
+        });
+        assert!(validate(&config).is_ok());
+
+        config.rules[0]
+            .targets
+            .push(NotificationTarget::Email("ops@example.com".to_string()));
+        assert!(validate(&config).is_err());
+    }
+}
@@ -1,8 +1,24 @@
+use crate::auth::{AUTH_FILE, AuthStore};
+use crate::batches::{BatchTracker, RUNS_DIR};
+use crate::config::config;
 use crate::ethercat::config::{MAX_SUBDEVICES, PDI_LEN};
+use crate::federation::FederationStatus;
+use crate::history::{HISTORY_FILE, HistoryStore};
+use crate::instrumentation::{ActInstrumentation, InstrumentationConfig};
+use crate::machines::mutation_limits::MutationRateLimiter;
+use crate::mes::{MES_ORDERS_FILE, OrderStore};
+use crate::motion::MotionSetup;
 use crate::performance_metrics::EthercatPerformanceMetrics;
+use crate::recipes::{RECIPES_FILE, RecipeStore};
+use crate::remote_session::RemoteSession;
+use crate::scheduler::{ActScheduler, SchedulerConfig};
+use crate::sequences::{SEQUENCES_FILE, SequenceStore};
 use crate::serial::registry::SERIAL_DEVICE_REGISTRY;
 use crate::socketio::main_namespace::machines_event::MachineObj;
 use crate::socketio::namespaces::Namespaces;
+use crate::storage;
+use crate::watchdog::{Watchdog, WatchdogConfig};
+use control_core::alarms::AlarmManager;
 use control_core::machines::Machine;
 use control_core::machines::identification::{DeviceIdentification, MachineIdentificationUnique};
 use control_core::machines::manager::MachineManager;
@@ -11,7 +27,7 @@ use control_core::socketio::event::GenericEvent;
 use ethercat_hal::devices::EthercatDevice;
 use ethercrab::{MainDevice, SubDeviceGroup, subdevice_group::Op};
 use smol::channel::{Receiver, Sender};
-use smol::lock::RwLock;
+use smol::lock::{Mutex, RwLock};
 use socketioxide::SocketIo;
 use socketioxide::extract::SocketRef;
 use std::collections::HashMap;
@@ -34,6 +50,20 @@ pub struct AppState {
     pub serial_setup: Arc<RwLock<SerialSetup>>,
     pub machines: Arc<RwLock<MachineManager>>,
     pub performance_metrics: Arc<RwLock<EthercatPerformanceMetrics>>,
+    pub recipes: Arc<RwLock<RecipeStore>>,
+    pub sequences: Arc<RwLock<SequenceStore>>,
+    pub batches: Arc<RwLock<BatchTracker>>,
+    pub history: Arc<Mutex<HistoryStore>>,
+    pub auth: Arc<RwLock<AuthStore>>,
+    pub alarms: Arc<RwLock<AlarmManager>>,
+    pub watchdog: Arc<RwLock<Watchdog>>,
+    pub instrumentation: Arc<RwLock<ActInstrumentation>>,
+    pub scheduler: Arc<RwLock<ActScheduler>>,
+    pub federation: Arc<RwLock<FederationStatus>>,
+    pub mes_orders: Arc<RwLock<OrderStore>>,
+    pub motion: MotionSetup,
+    pub mutation_rate_limiter: Arc<RwLock<MutationRateLimiter>>,
+    pub remote_session: Arc<RwLock<RemoteSession>>,
 }
 
 pub type Machines =
@@ -85,10 +115,39 @@ impl AppState {
             },
             ethercat_setup: Arc::new(RwLock::new(None)),
             serial_setup: Arc::new(RwLock::new(SerialSetup {
-                serial_detection: SerialDetection::new(&SERIAL_DEVICE_REGISTRY),
+                serial_detection: SerialDetection {
+                    port_filter: config().serial.port_filter(),
+                    ..SerialDetection::new(&SERIAL_DEVICE_REGISTRY)
+                },
             })),
             machines: Arc::new(RwLock::new(MachineManager::new())),
             performance_metrics: Arc::new(RwLock::new(EthercatPerformanceMetrics::new())),
+            recipes: Arc::new(RwLock::new(RecipeStore::load(
+                storage::data_dir().join(RECIPES_FILE),
+            ))),
+            sequences: Arc::new(RwLock::new(SequenceStore::load(
+                storage::data_dir().join(SEQUENCES_FILE),
+            ))),
+            batches: Arc::new(RwLock::new(BatchTracker::new(
+                storage::data_dir().join(RUNS_DIR),
+            ))),
+            history: Arc::new(Mutex::new(open_history())),
+            auth: Arc::new(RwLock::new(AuthStore::load(
+                &storage::data_dir().join(AUTH_FILE),
+            ))),
+            alarms: Arc::new(RwLock::new(AlarmManager::new())),
+            watchdog: Arc::new(RwLock::new(Watchdog::new(WatchdogConfig::load()))),
+            instrumentation: Arc::new(RwLock::new(ActInstrumentation::new(
+                InstrumentationConfig::load(),
+            ))),
+            scheduler: Arc::new(RwLock::new(ActScheduler::new(SchedulerConfig::load()))),
+            federation: Arc::new(RwLock::new(FederationStatus::default())),
+            mes_orders: Arc::new(RwLock::new(OrderStore::load(
+                storage::data_dir().join(MES_ORDERS_FILE),
+            ))),
+            motion: MotionSetup::new(config().motion.queue_capacity),
+            mutation_rate_limiter: Arc::new(RwLock::new(MutationRateLimiter::new())),
+            remote_session: Arc::new(RwLock::new(RemoteSession::new())),
         }
     }
 
@@ -97,16 +156,26 @@ impl AppState {
         machines
             .iter()
             .map(|machine| {
-                let error = {
-                    let slot = machine.1.lock_blocking();
-                    slot.machine_connection.to_error().map(|e| e.to_string())
-                };
-
+                let slot = machine.1.lock_blocking();
                 MachineObj {
                     machine_identification_unique: machine.0.clone(),
-                    error,
+                    error: slot.machine_connection.to_error().map(|e| e.to_string()),
+                    capabilities: slot.capabilities,
                 }
             })
             .collect()
     }
 }
+
+/// Opens the history database, falls back to memory so the server still starts
+fn open_history() -> HistoryStore {
+    let path = storage::data_dir().join(HISTORY_FILE);
+    HistoryStore::open(&path).unwrap_or_else(|e| {
+        tracing::error!(
+            "Failed to open history at {:?}, history is not kept: {:?}",
+            path,
+            e
+        );
+        HistoryStore::open_in_memory().expect("Failed to open in memory history")
+    })
+}
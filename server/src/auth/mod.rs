@@ -0,0 +1,475 @@
+use crate::storage;
+use axum::http::HeaderMap;
+use control_core::helpers::clock::unix_millis;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, path::Path};
+
+/// File inside [`storage::data_dir`] configuring users and permissions
+///
+/// Authorization is only enforced if the file exists.
+/// The MQTT, OPC-UA and Modbus integrations are configured by the operator of the server
+/// and are not subject to it.
+pub const AUTH_FILE: &str = "auth.json";
+
+/// Mutations only engineers may apply unless configured otherwise
+const ENGINEER_MUTATIONS: &[&str] = &[
+    "SetPressurePidSettings",
+    "SetPullerDiameterLoopGains",
+    "SetPayoffPidSettings",
+    "RevertPressurePidSettings",
+    "RevertPullerDiameterLoopGains",
+    "RevertPayoffPidSettings",
+    "SetSpoolAdaptiveTensionTarget",
+    "SetSpoolAdaptiveRadiusLearningRate",
+    "SetSpoolAdaptiveMaxSpeedMultiplier",
+    "SetSpoolAdaptiveAccelerationFactor",
+    "SetSpoolAdaptiveDeaccelerationUrgencyMultiplier",
+    "SetConnectedMachine",
+    "DisconnectMachine",
+    "ResetInverter",
+    "ResetMaintenanceCounter",
+];
+
+/// Roles in ascending order of permissions, every role may do what the roles below it may
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// May read machine data
+    Viewer,
+    /// May run the line: mutate machines, apply recipes and track runs
+    Operator,
+    /// May additionally tune controllers, edit recipes and assign machine identifications
+    Engineer,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuthConfig {
+    pub users: Vec<UserConfig>,
+    #[serde(default = "default_session_minutes")]
+    pub session_minutes: u64,
+    /// Required role per mutation name, overrides the defaults
+    #[serde(default)]
+    pub mutation_roles: HashMap<String, Role>,
+    /// Append every authorized or denied mutation to `audit.jsonl` in [`storage::data_dir`]
+    #[serde(default)]
+    pub audit_file: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UserConfig {
+    pub name: String,
+    /// Hex encoded SHA-256 of the password, e.g. from `echo -n <password> | sha256sum`
+    pub password_sha256: String,
+    pub role: Role,
+}
+
+const fn default_session_minutes() -> u64 {
+    12 * 60
+}
+
+/// Authenticated user of a request
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    pub user: String,
+    pub role: Role,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Session {
+    pub token: String,
+    pub user: String,
+    pub role: Role,
+    /// unix timestamp in milliseconds
+    pub expires_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthError {
+    /// No or an unknown/expired session token
+    Unauthenticated,
+    Forbidden {
+        role: Role,
+        required: Role,
+    },
+}
+
+impl std::fmt::Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unauthenticated => write!(f, "Not logged in or session expired"),
+            Self::Forbidden { role, required } => {
+                write!(
+                    f,
+                    "Role {:?} is not allowed, {:?} is required",
+                    role, required
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+/// Authorized or denied mutation, passed to every audit hook
+#[derive(Serialize, Debug, Clone)]
+pub struct AuditEntry {
+    /// unix timestamp in milliseconds
+    pub ts: u64,
+    /// `None` for requests without a valid session
+    pub user: Option<String>,
+    /// e.g. `machine/mutate`
+    pub action: String,
+    pub detail: Value,
+    pub allowed: bool,
+}
+
+pub type AuditHook = Box<dyn Fn(&AuditEntry) + Send + Sync>;
+
+/// Users, sessions and audit hooks
+///
+/// Without a config every request is authorized as anonymous engineer.
+pub struct AuthStore {
+    config: Option<AuthConfig>,
+    sessions: HashMap<String, Session>,
+    audit_hooks: Vec<AuditHook>,
+}
+
+impl AuthStore {
+    pub fn new(config: Option<AuthConfig>) -> Self {
+        let mut store = Self {
+            config,
+            sessions: HashMap::new(),
+            audit_hooks: vec![],
+        };
+        store.add_audit_hook(Box::new(|entry| {
+            tracing::info!(
+                target: "audit",
+                user = ?entry.user,
+                action = %entry.action,
+                allowed = entry.allowed,
+                "{}",
+                entry.detail
+            );
+        }));
+        store
+    }
+
+    /// Loads the config from `path`
+    ///
+    /// A broken file locks all mutations instead of disabling authorization.
+    pub fn load(path: &Path) -> Self {
+        let config = match storage::read_json::<AuthConfig>(path) {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::error!("Failed to load auth config, locking mutations: {:?}", e);
+                Some(AuthConfig {
+                    users: vec![],
+                    session_minutes: default_session_minutes(),
+                    mutation_roles: HashMap::new(),
+                    audit_file: false,
+                })
+            }
+        };
+        let mut store = Self::new(config);
+        if store
+            .config
+            .as_ref()
+            .is_some_and(|config| config.audit_file)
+        {
+            let audit_path = storage::data_dir().join("audit.jsonl");
+            store.add_audit_hook(Box::new(move |entry| {
+                if let Err(e) = append_jsonl(&audit_path, entry) {
+                    tracing::error!("Failed to write audit log: {:?}", e);
+                }
+            }));
+        }
+        store
+    }
+
+    pub const fn is_enabled(&self) -> bool {
+        self.config.is_some()
+    }
+
+    pub fn add_audit_hook(&mut self, hook: AuditHook) {
+        self.audit_hooks.push(hook);
+    }
+
+    /// Starts a session for a user
+    pub fn login(&mut self, name: &str, password: &str) -> Result<Session, AuthError> {
+        let config = self.config.as_ref().ok_or(AuthError::Unauthenticated)?;
+        let user = config
+            .users
+            .iter()
+            .find(|user| user.name == name)
+            .filter(|user| constant_time_eq(&user.password_sha256, &sha256_hex(password)))
+            .ok_or(AuthError::Unauthenticated)?;
+
+        let now = unix_millis();
+        self.sessions.retain(|_, session| session.expires_at > now);
+        let session = Session {
+            token: new_token(),
+            user: user.name.clone(),
+            role: user.role,
+            expires_at: now + config.session_minutes * 60 * 1000,
+        };
+        self.sessions.insert(session.token.clone(), session.clone());
+        Ok(session)
+    }
+
+    pub fn logout(&mut self, token: &str) {
+        self.sessions.remove(token);
+    }
+
+    /// Checks that the session of `token` has at least the `required` role
+    pub fn authorize(&self, token: Option<&str>, required: Role) -> Result<Principal, AuthError> {
+        if !self.is_enabled() {
+            return Ok(Principal {
+                user: "anonymous".to_string(),
+                role: Role::Engineer,
+            });
+        }
+        let session = token
+            .and_then(|token| self.sessions.get(token))
+            .filter(|session| session.expires_at > unix_millis())
+            .ok_or(AuthError::Unauthenticated)?;
+        if session.role < required {
+            return Err(AuthError::Forbidden {
+                role: session.role,
+                required,
+            });
+        }
+        Ok(Principal {
+            user: session.user.clone(),
+            role: session.role,
+        })
+    }
+
+    /// [`Self::authorize`] for a mutation, the outcome is passed to the audit hooks
+    pub fn authorize_mutation(
+        &self,
+        token: Option<&str>,
+        required: Role,
+        action: &str,
+        detail: &Value,
+    ) -> Result<Principal, AuthError> {
+        let result = self.authorize(token, required);
+        if self.is_enabled() {
+            let user = token
+                .and_then(|token| self.sessions.get(token))
+                .map(|session| session.user.clone());
+            let entry = AuditEntry {
+                ts: unix_millis(),
+                user,
+                action: action.to_string(),
+                detail: detail.clone(),
+                allowed: result.is_ok(),
+            };
+            for hook in &self.audit_hooks {
+                hook(&entry);
+            }
+        }
+        result
+    }
+
+    /// Role required to apply a machine mutation
+    pub fn mutation_role(&self, mutation: &Value) -> Role {
+        let Some(name) = mutation_name(mutation) else {
+            return Role::Engineer;
+        };
+        if let Some(role) = self
+            .config
+            .as_ref()
+            .and_then(|config| config.mutation_roles.get(name))
+        {
+            return *role;
+        }
+        if ENGINEER_MUTATIONS.contains(&name) {
+            Role::Engineer
+        } else {
+            Role::Operator
+        }
+    }
+}
+
+/// Name of an externally tagged mutation, `{"SetMode": "Wind"}` or `"ResetSpoolProgress"`
+pub fn mutation_name(mutation: &Value) -> Option<&str> {
+    match mutation {
+        Value::String(name) => Some(name),
+        Value::Object(map) if map.len() == 1 => map.keys().next().map(String::as_str),
+        _ => None,
+    }
+}
+
+/// Session token of a request from `Authorization: Bearer <token>`
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(axum::http::header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+fn sha256_hex(value: &str) -> String {
+    Sha256::digest(value.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn new_token() -> String {
+    rand::random::<[u8; 32]>()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.to_ascii_lowercase(), b.to_ascii_lowercase());
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+fn append_jsonl<T: Serialize>(path: &Path, value: &T) -> Result<(), anyhow::Error> {
+    use std::io::Write;
+    let mut line = serde_json::to_vec(value)?;
+    line.push(b'\n');
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(&line)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    fn store() -> AuthStore {
+        AuthStore::new(Some(AuthConfig {
+            users: vec![
+                UserConfig {
+                    name: "op".to_string(),
+                    password_sha256: sha256_hex("secret"),
+                    role: Role::Operator,
+                },
+                UserConfig {
+                    name: "eng".to_string(),
+                    password_sha256: sha256_hex("tuning"),
+                    role: Role::Engineer,
+                },
+            ],
+            session_minutes: 60,
+            mutation_roles: HashMap::from([("SetMode".to_string(), Role::Engineer)]),
+            audit_file: false,
+        }))
+    }
+
+    #[test]
+    fn test_disabled_allows_everything() {
+        let store = AuthStore::new(None);
+        assert_eq!(
+            store.authorize(None, Role::Engineer).map(|p| p.role),
+            Ok(Role::Engineer)
+        );
+    }
+
+    #[test]
+    fn test_login_and_roles() {
+        let mut store = store();
+        assert_eq!(
+            store.login("op", "wrong").map(|_| ()),
+            Err(AuthError::Unauthenticated)
+        );
+        assert_eq!(
+            store.authorize(None, Role::Viewer),
+            Err(AuthError::Unauthenticated)
+        );
+
+        let session = store.login("op", "secret").unwrap();
+        let token = Some(session.token.as_str());
+        assert!(store.authorize(token, Role::Operator).is_ok());
+        assert_eq!(
+            store.authorize(token, Role::Engineer),
+            Err(AuthError::Forbidden {
+                role: Role::Operator,
+                required: Role::Engineer
+            })
+        );
+
+        store.logout(&session.token);
+        assert_eq!(
+            store.authorize(token, Role::Viewer),
+            Err(AuthError::Unauthenticated)
+        );
+    }
+
+    #[test]
+    fn test_mutation_role() {
+        let store = store();
+        assert_eq!(
+            store.mutation_role(&json!({ "SetTargetDiameter": 1.75 })),
+            Role::Operator
+        );
+        assert_eq!(
+            store.mutation_role(&json!({ "SetPressurePidSettings": {} })),
+            Role::Engineer
+        );
+        assert_eq!(
+            store.mutation_role(&json!({ "SetPullerDiameterLoopGains": {} })),
+            Role::Engineer
+        );
+        assert_eq!(
+            store.mutation_role(&json!({ "SetPayoffPidSettings": {} })),
+            Role::Engineer
+        );
+        for revert in [
+            "RevertPressurePidSettings",
+            "RevertPullerDiameterLoopGains",
+            "RevertPayoffPidSettings",
+        ] {
+            assert_eq!(store.mutation_role(&json!(revert)), Role::Engineer);
+        }
+        assert_eq!(
+            store.mutation_role(&json!({ "SetMode": "Wind" })),
+            Role::Engineer
+        );
+        assert_eq!(
+            store.mutation_role(&json!("ResetSpoolProgress")),
+            Role::Operator
+        );
+        assert_eq!(store.mutation_role(&json!([1, 2])), Role::Engineer);
+    }
+
+    #[test]
+    fn test_audit_hook() {
+        let mut store = store();
+        let entries = Arc::new(Mutex::new(vec![]));
+        store.add_audit_hook(Box::new({
+            let entries = entries.clone();
+            move |entry| entries.lock().unwrap().push(entry.clone())
+        }));
+
+        let session = store.login("op", "secret").unwrap();
+        let mutation = json!({ "SetPressurePidSettings": {} });
+        let result = store.authorize_mutation(
+            Some(&session.token),
+            store.mutation_role(&mutation),
+            "machine/mutate",
+            &mutation,
+        );
+        assert!(result.is_err());
+
+        let entries = entries.lock().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].user.as_deref(), Some("op"));
+        assert!(!entries[0].allowed);
+    }
+}
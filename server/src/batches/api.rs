@@ -0,0 +1,105 @@
+use super::RunReport;
+use crate::{app_state::AppState, mes::api::OrdersEvent};
+use control_core::socketio::{
+    event::{BuildEvent, Event, GenericEvent},
+    namespace::{CacheFn, CacheableEvents, Namespace, NamespaceCacheingLogic, cache_one_event},
+    namespace_id::NamespaceId,
+};
+use control_core_derive::BuildEvent;
+use serde::{Deserialize, Serialize};
+use smol::channel::Sender;
+use socketioxide::extract::SocketRef;
+use std::sync::Arc;
+use tracing::instrument;
+
+use super::RunMetadata;
+
+#[derive(Serialize, Debug, Clone, BuildEvent)]
+pub struct RunStateEvent {
+    /// report of the open run so far, `None` if no run is open
+    pub run: Option<RunReport>,
+}
+
+#[derive(Serialize, Debug, Clone, BuildEvent)]
+pub struct RunEndedEvent {
+    pub report: RunReport,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub enum Mutation {
+    StartRun(RunMetadata),
+    /// Close the open run and persist its report
+    EndRun,
+    /// Count a defect the operator spotted by hand
+    RecordDefect,
+    /// Take a note, e.g. "added regrind", attached to the open run if there is one
+    Annotate(String),
+    /// Apply the recipe of the next queued MES order and open its run
+    ApplyNextOrder,
+}
+
+pub enum BatchesNamespaceEvents {
+    RunState(Event<RunStateEvent>),
+    RunEnded(Event<RunEndedEvent>),
+    Orders(Event<OrdersEvent>),
+}
+
+impl CacheableEvents<Self> for BatchesNamespaceEvents {
+    fn event_value(&self) -> GenericEvent {
+        match self {
+            Self::RunState(event) => event.into(),
+            Self::RunEnded(event) => event.into(),
+            Self::Orders(event) => event.into(),
+        }
+    }
+
+    fn event_cache_fn(&self) -> CacheFn {
+        match self {
+            Self::RunState(_) => cache_one_event(),
+            Self::RunEnded(_) => cache_one_event(),
+            Self::Orders(_) => cache_one_event(),
+        }
+    }
+}
+
+pub struct BatchesRoom {
+    pub namespace: Namespace,
+}
+
+impl BatchesRoom {
+    pub fn new(socket_queue_tx: Sender<(SocketRef, Arc<GenericEvent>)>) -> Self {
+        Self {
+            namespace: Namespace::new(socket_queue_tx).with_id(NamespaceId::Batches),
+        }
+    }
+}
+
+impl NamespaceCacheingLogic<BatchesNamespaceEvents> for BatchesRoom {
+    #[instrument(skip_all)]
+    fn emit(&mut self, event: BatchesNamespaceEvents) {
+        let buffer_fn = event.event_cache_fn();
+        let generic_event = Arc::new(event.event_value());
+        self.namespace.emit(generic_event, &buffer_fn);
+    }
+}
+
+/// Emits the state of the open run to the batches namespace
+pub async fn emit_run_state(app_state: &Arc<AppState>) {
+    let event = RunStateEvent {
+        run: app_state
+            .batches
+            .read()
+            .await
+            .current()
+            .map(|run| run.report(None)),
+    }
+    .build();
+
+    let batches_namespace = &mut app_state
+        .socketio_setup
+        .namespaces
+        .write()
+        .await
+        .batches_namespace;
+    batches_namespace.emit(BatchesNamespaceEvents::RunState(event));
+}
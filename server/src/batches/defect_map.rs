@@ -0,0 +1,112 @@
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use std::{fmt::Write, path::Path};
+
+/// Directory inside [`storage::data_dir`] holding the defect maps of the closed spools
+pub const DEFECT_MAPS_DIR: &str = "defect_maps";
+
+/// Stretch of out of tolerance filament on a spool
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DefectSegment {
+    /// wound length in m where the segment starts
+    pub start: f64,
+    /// wound length in m where the segment ends
+    pub end: f64,
+    /// smallest diameter in mm measured in the segment
+    pub min_diameter: f64,
+    /// largest diameter in mm measured in the segment
+    pub max_diameter: f64,
+}
+
+/// Out of tolerance segments of one spool, exported when the spool is closed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SpoolDefectMap {
+    /// number of the spool on the winder
+    pub spool: u32,
+    /// unix timestamp in milliseconds
+    pub closed_at: u64,
+    /// wound length in m
+    pub length: f64,
+    /// diameters in mm measured over the whole spool, `None` without a measurement
+    pub min_diameter: Option<f64>,
+    pub max_diameter: Option<f64>,
+    pub average_diameter: Option<f64>,
+    pub segments: Vec<DefectSegment>,
+}
+
+impl SpoolDefectMap {
+    /// One line per segment below a header
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("start_m,end_m,min_diameter_mm,max_diameter_mm\n");
+        for segment in &self.segments {
+            // writing to a String can't fail
+            let _ = writeln!(
+                csv,
+                "{:.2},{:.2},{:.3},{:.3}",
+                segment.start, segment.end, segment.min_diameter, segment.max_diameter
+            );
+        }
+        csv
+    }
+
+    /// Writes the map as JSON and CSV into `dir`, named after the close time and spool
+    pub fn export(&self, dir: &Path) -> Result<(), anyhow::Error> {
+        let name = format!("{}-spool-{}", self.closed_at, self.spool);
+        storage::write_json(&dir.join(format!("{}.json", name)), self)?;
+
+        let csv_path = dir.join(format!("{}.csv", name));
+        std::fs::write(&csv_path, self.to_csv()).map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::SpoolDefectMap::export] Failed to write {:?}: {}",
+                module_path!(),
+                csv_path,
+                e
+            )
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map() -> SpoolDefectMap {
+        SpoolDefectMap {
+            spool: 3,
+            closed_at: 1_700_000_000_000,
+            length: 330.0,
+            min_diameter: Some(1.68),
+            max_diameter: Some(1.7512),
+            average_diameter: Some(1.749),
+            segments: vec![DefectSegment {
+                start: 12.5,
+                end: 14.25,
+                min_diameter: 1.68,
+                max_diameter: 1.7512,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_csv() {
+        assert_eq!(
+            map().to_csv(),
+            "start_m,end_m,min_diameter_mm,max_diameter_mm\n12.50,14.25,1.680,1.751\n"
+        );
+    }
+
+    #[test]
+    fn test_export() {
+        let dir = std::env::temp_dir().join(format!("qitech-defect-maps-{}", std::process::id()));
+        let map = map();
+        map.export(&dir).unwrap();
+
+        let json = dir.join("1700000000000-spool-3.json");
+        assert_eq!(storage::read_json(&json).unwrap(), Some(map.clone()));
+        let csv = std::fs::read_to_string(dir.join("1700000000000-spool-3.csv")).unwrap();
+        assert_eq!(csv, map.to_csv());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
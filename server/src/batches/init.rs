@@ -0,0 +1,176 @@
+use super::{
+    DefectRecord, RunSample,
+    api::emit_run_state,
+    defect_map::{DEFECT_MAPS_DIR, SpoolDefectMap},
+};
+use crate::{
+    app_state::AppState,
+    config::config,
+    machines::{laser::LaserMachine, winder2::Winder2},
+    mes::api::emit_orders,
+    panic::{PanicDetails, send_panic},
+    storage,
+};
+use smol::channel::Sender;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use uom::si::length::meter;
+
+/// Interval the line is sampled in while a run is open
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Interval the open run is emitted to the batches namespace
+const EMIT_INTERVAL: Duration = Duration::from_secs(1);
+
+pub fn init_batches(
+    thread_panic_tx: Sender<PanicDetails>,
+    app_state: Arc<AppState>,
+) -> Result<(), anyhow::Error> {
+    smol::block_on(emit_run_state(&app_state));
+    smol::block_on(emit_orders(&app_state));
+
+    std::thread::Builder::new()
+        .name("batches".to_owned())
+        .spawn(move || {
+            send_panic(thread_panic_tx);
+            smol::block_on(async {
+                let mut last_sample = Instant::now();
+                let mut last_emit = Instant::now();
+                let mut run_open = false;
+                loop {
+                    smol::Timer::after(SAMPLE_INTERVAL).await;
+
+                    let now = Instant::now();
+                    let dt = now - last_sample;
+                    last_sample = now;
+                    let emit_due = now - last_emit >= EMIT_INTERVAL;
+                    if emit_due {
+                        last_emit = now;
+                        // spools are closed with and without an open run
+                        export_defect_maps(&app_state).await;
+                    }
+
+                    if app_state.batches.read().await.current().is_none() {
+                        run_open = false;
+                        continue;
+                    }
+
+                    let mut sample = sample_line(&app_state).await;
+                    // defects the winder collected before the run belong to no run
+                    if !std::mem::replace(&mut run_open, true) {
+                        sample.defects.clear();
+                    }
+                    app_state.batches.write().await.add_sample(&sample, dt);
+
+                    if emit_due {
+                        emit_run_state(&app_state).await;
+                    }
+                }
+            });
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_batches] Failed to spawn batches thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Reads the pull speed and spool defects of the first winder and the diameter of the first laser
+async fn sample_line(app_state: &Arc<AppState>) -> RunSample {
+    let machines: Vec<_> = app_state
+        .machines
+        .read()
+        .await
+        .iter()
+        .filter_map(|(_, slot)| slot.lock_blocking().machine_connection.to_machine())
+        .collect();
+
+    let mut sample = RunSample::default();
+    for machine in machines {
+        let mut machine = machine.lock().await;
+        if let Some(laser) = machine.as_any().downcast_ref::<LaserMachine>() {
+            if sample.diameter.is_none() {
+                sample.diameter = Some(laser.get_diameter());
+                // the startup transient isn't part of the statistics
+                sample.in_tolerance = laser
+                    .is_monitoring_tolerance()
+                    .then(|| laser.is_in_tolerance())
+                    .flatten();
+            }
+        } else if let Some(winder) = machine.as_any_mut().downcast_mut::<Winder2>() {
+            if sample.pull_speed.is_none() {
+                sample.pull_speed = Some(winder.puller_speed_controller.last_speed);
+                sample.defects = winder
+                    .take_spool_defects()
+                    .into_iter()
+                    .map(|defect| DefectRecord {
+                        spool: defect.spool,
+                        position: defect.position.get::<meter>(),
+                    })
+                    .collect();
+            }
+        }
+        drop(machine);
+    }
+
+    // a laser without reading or still starting up doesn't tell anything about the filament
+    if sample.in_tolerance.is_none() {
+        sample.diameter = None;
+    }
+    sample
+}
+
+/// Writes the defect maps of the spools the winders closed, attaches them to the open run and
+/// prints their labels
+async fn export_defect_maps(app_state: &Arc<AppState>) {
+    let machines: Vec<_> = app_state
+        .machines
+        .read()
+        .await
+        .iter()
+        .filter_map(|(_, slot)| slot.lock_blocking().machine_connection.to_machine())
+        .collect();
+
+    let mut defect_maps: Vec<SpoolDefectMap> = Vec::new();
+    for machine in machines {
+        let mut machine = machine.lock().await;
+        if let Some(winder) = machine.as_any_mut().downcast_mut::<Winder2>() {
+            defect_maps.extend(winder.take_closed_defect_maps());
+        }
+        drop(machine);
+    }
+    if defect_maps.is_empty() {
+        return;
+    }
+
+    let dir = storage::data_dir().join(DEFECT_MAPS_DIR);
+    let mut labels = Vec::new();
+    let mut batches = app_state.batches.write().await;
+    for defect_map in defect_maps {
+        if let Err(e) = defect_map.export(&dir) {
+            tracing::error!(
+                "Failed to export the defect map of spool {}: {:?}",
+                defect_map.spool,
+                e
+            );
+        }
+        labels.push(batches.add_spool(defect_map));
+    }
+    drop(batches);
+
+    let Some(printer) = config().labels.printer else {
+        return;
+    };
+    for label in labels {
+        let spool = label.spool;
+        if let Err(e) = smol::unblock(move || label.print(printer)).await {
+            tracing::error!("Failed to print the label of spool {}: {:?}", spool, e);
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use super::{ProductionRun, defect_map::SpoolDefectMap};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::Write,
+    net::{SocketAddr, TcpStream},
+    time::Duration,
+};
+
+/// Traceability label of a finished spool
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SpoolLabel {
+    /// id of the run the spool was produced in, `None` without an open run
+    pub run_id: Option<u64>,
+    pub material: Option<String>,
+    /// number of the spool on the winder
+    pub spool: u32,
+    /// net weight in kg, `None` without an open run or a diameter measurement
+    pub net_weight: Option<f64>,
+    /// wound length in m
+    pub length: f64,
+    /// mm
+    pub min_diameter: Option<f64>,
+    /// mm
+    pub max_diameter: Option<f64>,
+    /// mm
+    pub average_diameter: Option<f64>,
+    /// unix timestamp in milliseconds the spool was finished
+    pub finished_at: u64,
+}
+
+impl SpoolLabel {
+    /// Connecting to an unreachable printer gives up after this
+    const PRINT_TIMEOUT: Duration = Duration::from_secs(3);
+
+    pub fn new(defect_map: &SpoolDefectMap, run: Option<&ProductionRun>) -> Self {
+        // weight of a cylinder with the average diameter
+        let net_weight = run.zip(defect_map.average_diameter).map(|(run, diameter)| {
+            let radius_m = diameter / 2000.0;
+            let volume_m3 = std::f64::consts::PI * radius_m * radius_m * defect_map.length;
+            // g/cm³ equals 1000 kg/m³
+            volume_m3 * run.metadata.material_density * 1000.0
+        });
+        Self {
+            run_id: run.map(|run| run.id),
+            material: run.map(|run| run.metadata.material.clone()),
+            spool: defect_map.spool,
+            net_weight,
+            length: defect_map.length,
+            min_diameter: defect_map.min_diameter,
+            max_diameter: defect_map.max_diameter,
+            average_diameter: defect_map.average_diameter,
+            finished_at: defect_map.closed_at,
+        }
+    }
+
+    /// Label in the Zebra Programming Language with the label as JSON in a QR code
+    pub fn to_zpl(&self) -> String {
+        let optional = |value: Option<f64>, precision: usize| {
+            value.map_or_else(
+                || "-".to_string(),
+                |value| format!("{:.*}", precision, value),
+            )
+        };
+        let lines = [
+            format!(
+                "Run {}",
+                self.run_id
+                    .map_or_else(|| "-".to_string(), |id| id.to_string())
+            ),
+            self.material.clone().unwrap_or_else(|| "-".to_string()),
+            format!("Spool {}", self.spool),
+            format!("{} kg / {:.1} m", optional(self.net_weight, 3), self.length),
+            format!(
+                "Avg {} mm ({} - {})",
+                optional(self.average_diameter, 3),
+                optional(self.min_diameter, 3),
+                optional(self.max_diameter, 3)
+            ),
+            utc_date(self.finished_at),
+        ];
+
+        let mut zpl = String::from("^XA^CI28\n");
+        for (i, line) in lines.iter().enumerate() {
+            zpl += &format!(
+                "^FO30,{}^A0N,30,30^FD{}^FS\n",
+                30 + i * 40,
+                zpl_escape(line)
+            );
+        }
+        let payload = serde_json::to_string(self).unwrap_or_default();
+        zpl += &format!("^FO480,30^BQN,2,4^FDQA,{}^FS\n", zpl_escape(&payload));
+        zpl += "^XZ\n";
+        zpl
+    }
+
+    /// Sends the label to a network printer accepting raw ZPL, usually on port 9100
+    pub fn print(&self, printer: SocketAddr) -> Result<(), anyhow::Error> {
+        let mut stream =
+            TcpStream::connect_timeout(&printer, Self::PRINT_TIMEOUT).map_err(|e| {
+                anyhow::anyhow!(
+                    "[{}::SpoolLabel::print] Failed to connect to {}: {}",
+                    module_path!(),
+                    printer,
+                    e
+                )
+            })?;
+        stream.set_write_timeout(Some(Self::PRINT_TIMEOUT))?;
+        stream.write_all(self.to_zpl().as_bytes()).map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::SpoolLabel::print] Failed to send the label to {}: {}",
+                module_path!(),
+                printer,
+                e
+            )
+        })?;
+        Ok(())
+    }
+}
+
+/// `^` and `~` start ZPL commands, they can't be part of field data
+fn zpl_escape(text: &str) -> String {
+    text.replace(['^', '~'], " ")
+}
+
+/// `YYYY-MM-DD` of a unix timestamp in milliseconds
+fn utc_date(unix_millis: u64) -> String {
+    // days to the civil calendar, shifted to years starting in March
+    let days = (unix_millis / 86_400_000) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batches::RunMetadata;
+    use approx::assert_relative_eq;
+
+    fn defect_map() -> SpoolDefectMap {
+        SpoolDefectMap {
+            spool: 2,
+            // 2024-02-29 12:00 UTC
+            closed_at: 1_709_208_000_000,
+            length: 330.0,
+            min_diameter: Some(1.70),
+            max_diameter: Some(1.80),
+            average_diameter: Some(1.75),
+            segments: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_label() {
+        let run = ProductionRun::new(RunMetadata {
+            operator: "operator".to_string(),
+            material: "PLA^red".to_string(),
+            material_density: 1.24,
+            recipe: None,
+            order: None,
+        });
+        let label = SpoolLabel::new(&defect_map(), Some(&run));
+        assert_eq!(label.run_id, Some(run.id));
+        // 330m of 1.75mm PLA weigh about 984g
+        assert_relative_eq!(label.net_weight.unwrap(), 0.9842, epsilon = 1e-4);
+
+        let zpl = label.to_zpl();
+        assert!(zpl.starts_with("^XA"));
+        assert!(zpl.contains("^FDPLA red^FS"));
+        assert!(zpl.contains("^FD2024-02-29^FS"));
+        assert!(zpl.contains("^FDQA,{\"run_id\""));
+
+        let without_run = SpoolLabel::new(&defect_map(), None);
+        assert!(without_run.net_weight.is_none());
+        assert!(without_run.to_zpl().contains("^FDRun -^FS"));
+    }
+
+    #[test]
+    fn test_utc_date() {
+        assert_eq!(utc_date(0), "1970-01-01");
+        assert_eq!(utc_date(951_782_400_000), "2000-02-29");
+        assert_eq!(utc_date(1_735_689_599_999), "2024-12-31");
+    }
+}
@@ -0,0 +1,437 @@
+use crate::{history::Annotation, storage};
+use control_core::helpers::clock::unix_millis;
+use defect_map::SpoolDefectMap;
+use label::SpoolLabel;
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+use uom::{
+    ConstZero,
+    si::{
+        f64::{Length, Mass, Velocity},
+        length::{meter, millimeter},
+        mass::kilogram,
+    },
+};
+
+pub mod api;
+pub mod defect_map;
+pub mod init;
+pub mod label;
+
+/// Directory inside [`storage::data_dir`] holding one report file per run
+pub const RUNS_DIR: &str = "runs";
+
+/// Metadata given by the operator when opening a run
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RunMetadata {
+    pub operator: String,
+    pub material: String,
+    /// material density in g/cm³, used to calculate the weight
+    pub material_density: f64,
+    /// name of the recipe the run is produced with
+    pub recipe: Option<String>,
+    /// id of the MES order the run produces
+    #[serde(default)]
+    pub order: Option<String>,
+}
+
+/// Start of out of tolerance filament on a spool
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DefectRecord {
+    /// number of the spool on the winder
+    pub spool: u32,
+    /// wound length in m before the defect starts
+    pub position: f64,
+}
+
+/// One observation of the line taken by the batch sampler
+#[derive(Debug, Clone, Default)]
+pub struct RunSample {
+    /// speed the filament is pulled with, `None` without a winder
+    pub pull_speed: Option<Velocity>,
+    /// measured diameter, `None` without a measuring laser
+    pub diameter: Option<Length>,
+    /// `None` without a measuring laser
+    pub in_tolerance: Option<bool>,
+    /// defects that reached the spool since the last sample
+    pub defects: Vec<DefectRecord>,
+}
+
+/// Statistics accumulated over a run
+#[derive(Debug, Clone)]
+pub struct RunStatistics {
+    pub length: Length,
+    pub weight: Mass,
+    pub measured_time: Duration,
+    pub time_in_tolerance: Duration,
+    /// Every excursion out of tolerance and every manually recorded defect
+    pub defect_count: u32,
+    last_in_tolerance: Option<bool>,
+}
+
+impl Default for RunStatistics {
+    fn default() -> Self {
+        Self {
+            length: Length::ZERO,
+            weight: Mass::ZERO,
+            measured_time: Duration::ZERO,
+            time_in_tolerance: Duration::ZERO,
+            defect_count: 0,
+            last_in_tolerance: None,
+        }
+    }
+}
+
+impl RunStatistics {
+    /// Accumulates a sample that was valid for `dt`
+    pub fn add_sample(&mut self, sample: &RunSample, dt: Duration, material_density: f64) {
+        if let Some(pull_speed) = sample.pull_speed {
+            let length = pull_speed.abs()
+                * uom::si::f64::Time::new::<uom::si::time::second>(dt.as_secs_f64());
+            self.length += length;
+
+            // weight of a cylinder with the measured diameter
+            if let Some(diameter) = sample.diameter {
+                let radius_m = diameter.get::<meter>() / 2.0;
+                let volume_m3 = std::f64::consts::PI * radius_m * radius_m * length.get::<meter>();
+                // g/cm³ equals 1000 kg/m³
+                self.weight += Mass::new::<kilogram>(volume_m3 * material_density * 1000.0);
+            }
+        }
+
+        if let Some(in_tolerance) = sample.in_tolerance {
+            self.measured_time += dt;
+            if in_tolerance {
+                self.time_in_tolerance += dt;
+            } else if self.last_in_tolerance != Some(false) {
+                self.defect_count += 1;
+            }
+        }
+        self.last_in_tolerance = sample.in_tolerance;
+    }
+}
+
+/// Report of a run, persisted when the run is closed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RunReport {
+    pub id: u64,
+    pub metadata: RunMetadata,
+    /// unix timestamp in milliseconds
+    pub started_at: u64,
+    /// unix timestamp in milliseconds, `None` while the run is open
+    pub ended_at: Option<u64>,
+    /// produced length in m
+    pub length: f64,
+    /// produced weight in kg
+    pub weight: f64,
+    /// time the laser measured in s
+    pub measured_time: f64,
+    /// time the diameter was in tolerance in s
+    pub time_in_tolerance: f64,
+    /// share of the measured time in tolerance (0.0-1.0)
+    pub in_tolerance_ratio: Option<f64>,
+    pub defect_count: u32,
+    /// average diameter in mm over all samples with a measurement
+    pub average_diameter: Option<f64>,
+    /// where the out of tolerance filament ended up, missing in reports of older versions
+    #[serde(default)]
+    pub defects: Vec<DefectRecord>,
+    /// spools closed during the run, missing in reports of older versions
+    #[serde(default)]
+    pub defect_maps: Vec<SpoolDefectMap>,
+    /// notes of the operators, missing in reports of older versions
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+}
+
+/// The currently open run
+#[derive(Debug, Clone)]
+pub struct ProductionRun {
+    pub id: u64,
+    pub metadata: RunMetadata,
+    pub started_at: u64,
+    pub statistics: RunStatistics,
+    defects: Vec<DefectRecord>,
+    defect_maps: Vec<SpoolDefectMap>,
+    annotations: Vec<Annotation>,
+    diameter_sum: f64,
+    diameter_samples: u64,
+}
+
+impl ProductionRun {
+    pub fn new(metadata: RunMetadata) -> Self {
+        let started_at = unix_millis();
+        Self {
+            id: started_at,
+            metadata,
+            started_at,
+            statistics: RunStatistics::default(),
+            defects: Vec::new(),
+            defect_maps: Vec::new(),
+            annotations: Vec::new(),
+            diameter_sum: 0.0,
+            diameter_samples: 0,
+        }
+    }
+
+    pub fn add_sample(&mut self, sample: &RunSample, dt: Duration) {
+        self.statistics
+            .add_sample(sample, dt, self.metadata.material_density);
+        self.defects.extend_from_slice(&sample.defects);
+        if let Some(diameter) = sample.diameter {
+            self.diameter_sum += diameter.get::<millimeter>();
+            self.diameter_samples += 1;
+        }
+    }
+
+    pub fn report(&self, ended_at: Option<u64>) -> RunReport {
+        let statistics = &self.statistics;
+        let measured_time = statistics.measured_time.as_secs_f64();
+        let time_in_tolerance = statistics.time_in_tolerance.as_secs_f64();
+        RunReport {
+            id: self.id,
+            metadata: self.metadata.clone(),
+            started_at: self.started_at,
+            ended_at,
+            length: statistics.length.get::<meter>(),
+            weight: statistics.weight.get::<kilogram>(),
+            measured_time,
+            time_in_tolerance,
+            in_tolerance_ratio: (measured_time > 0.0).then(|| time_in_tolerance / measured_time),
+            defect_count: statistics.defect_count,
+            average_diameter: (self.diameter_samples > 0)
+                .then(|| self.diameter_sum / self.diameter_samples as f64),
+            defects: self.defects.clone(),
+            defect_maps: self.defect_maps.clone(),
+            annotations: self.annotations.clone(),
+        }
+    }
+}
+
+/// Opens, accumulates and closes production runs
+#[derive(Debug)]
+pub struct BatchTracker {
+    dir: PathBuf,
+    current: Option<ProductionRun>,
+    last_label: Option<SpoolLabel>,
+}
+
+impl BatchTracker {
+    const MAX_ANNOTATION_LENGTH: usize = 1000;
+
+    pub const fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            current: None,
+            last_label: None,
+        }
+    }
+
+    pub const fn current(&self) -> Option<&ProductionRun> {
+        self.current.as_ref()
+    }
+
+    pub fn start_run(&mut self, metadata: RunMetadata) -> Result<(), anyhow::Error> {
+        if let Some(run) = &self.current {
+            return Err(anyhow::anyhow!(
+                "[{}::BatchTracker::start_run] Run {} is still open",
+                module_path!(),
+                run.id
+            ));
+        }
+        if metadata.material_density <= 0.0 {
+            return Err(anyhow::anyhow!("Material density must be positive"));
+        }
+        let run = ProductionRun::new(metadata);
+        tracing::info!("Started production run {}", run.id);
+        self.current = Some(run);
+        Ok(())
+    }
+
+    /// Closes the open run and persists its report
+    pub fn end_run(&mut self) -> Result<RunReport, anyhow::Error> {
+        let run = self.current.take().ok_or_else(|| {
+            anyhow::anyhow!("[{}::BatchTracker::end_run] No run is open", module_path!())
+        })?;
+        let report = run.report(Some(unix_millis()));
+        storage::write_json(&self.report_path(report.id), &report)?;
+        tracing::info!("Closed production run {}", report.id);
+        Ok(report)
+    }
+
+    pub fn record_defect(&mut self) -> Result<(), anyhow::Error> {
+        let run = self.current.as_mut().ok_or_else(|| {
+            anyhow::anyhow!(
+                "[{}::BatchTracker::record_defect] No run is open",
+                module_path!()
+            )
+        })?;
+        run.statistics.defect_count += 1;
+        Ok(())
+    }
+
+    /// Takes a note of `user`, attached to the open run if there is one
+    pub fn annotate(&mut self, user: &str, text: &str) -> Result<Annotation, anyhow::Error> {
+        let text = text.trim();
+        if text.is_empty() || text.len() > Self::MAX_ANNOTATION_LENGTH {
+            return Err(anyhow::anyhow!(
+                "[{}::BatchTracker::annotate] Annotations must have 1 to {} bytes",
+                module_path!(),
+                Self::MAX_ANNOTATION_LENGTH
+            ));
+        }
+        let annotation = Annotation {
+            ts: unix_millis(),
+            user: user.to_string(),
+            text: text.to_string(),
+            run_id: self.current.as_ref().map(|run| run.id),
+        };
+        if let Some(run) = self.current.as_mut() {
+            run.annotations.push(annotation.clone());
+        }
+        Ok(annotation)
+    }
+
+    pub fn add_sample(&mut self, sample: &RunSample, dt: Duration) {
+        if let Some(run) = self.current.as_mut() {
+            run.add_sample(sample, dt);
+        }
+    }
+
+    /// Labels a closed spool and attaches its map to the open run
+    pub fn add_spool(&mut self, defect_map: SpoolDefectMap) -> SpoolLabel {
+        let label = SpoolLabel::new(&defect_map, self.current.as_ref());
+        if let Some(run) = self.current.as_mut() {
+            run.defect_maps.push(defect_map);
+        }
+        self.last_label = Some(label.clone());
+        label
+    }
+
+    /// Label of the spool closed last since the server started
+    pub const fn last_label(&self) -> Option<&SpoolLabel> {
+        self.last_label.as_ref()
+    }
+
+    /// All persisted reports, newest first
+    pub fn list_reports(&self) -> Result<Vec<RunReport>, anyhow::Error> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut reports = vec![];
+        for entry in entries {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                if let Some(report) = storage::read_json::<RunReport>(&path)? {
+                    reports.push(report);
+                }
+            }
+        }
+        reports.sort_by_key(|report| std::cmp::Reverse(report.id));
+        Ok(reports)
+    }
+
+    pub fn get_report(&self, id: u64) -> Result<Option<RunReport>, anyhow::Error> {
+        storage::read_json(&self.report_path(id))
+    }
+
+    fn report_path(&self, id: u64) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use control_core::uom_extensions::velocity::meter_per_minute;
+
+    fn metadata() -> RunMetadata {
+        RunMetadata {
+            operator: "operator".to_string(),
+            material: "PLA".to_string(),
+            material_density: 1.24,
+            recipe: None,
+            order: None,
+        }
+    }
+
+    fn sample(in_tolerance: bool) -> RunSample {
+        RunSample {
+            pull_speed: Some(Velocity::new::<meter_per_minute>(60.0)),
+            diameter: Some(Length::new::<millimeter>(1.75)),
+            in_tolerance: Some(in_tolerance),
+            defects: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_statistics_accumulate() {
+        let mut run = ProductionRun::new(metadata());
+        run.add_sample(&sample(true), Duration::from_secs(1));
+        run.add_sample(&sample(false), Duration::from_secs(1));
+        run.add_sample(&sample(false), Duration::from_secs(1));
+        run.add_sample(
+            &RunSample {
+                defects: vec![DefectRecord {
+                    spool: 2,
+                    position: 120.5,
+                }],
+                ..sample(true)
+            },
+            Duration::from_secs(1),
+        );
+
+        let report = run.report(None);
+        assert_relative_eq!(report.length, 4.0, epsilon = 1e-9);
+        assert_eq!(report.defects[0].spool, 2);
+        assert_relative_eq!(report.time_in_tolerance, 2.0, epsilon = 1e-9);
+        assert_relative_eq!(report.in_tolerance_ratio.unwrap(), 0.5, epsilon = 1e-9);
+        // one excursion out of tolerance counts as one defect
+        assert_eq!(report.defect_count, 1);
+        // 4m of 1.75mm PLA weigh about 11.9g
+        assert_relative_eq!(report.weight, 0.01193, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn test_run_lifecycle() {
+        let dir = std::env::temp_dir().join(format!("qitech-runs-{}", std::process::id()));
+        let mut tracker = BatchTracker::new(dir.clone());
+
+        assert!(tracker.end_run().is_err());
+        tracker.start_run(metadata()).unwrap();
+        assert!(tracker.start_run(metadata()).is_err());
+        tracker.record_defect().unwrap();
+        assert!(tracker.annotate("operator", " ").is_err());
+        tracker
+            .annotate("operator", "changed filter screen")
+            .unwrap();
+        let label = tracker.add_spool(SpoolDefectMap {
+            spool: 1,
+            closed_at: unix_millis(),
+            length: 100.0,
+            min_diameter: None,
+            max_diameter: None,
+            average_diameter: None,
+            segments: Vec::new(),
+        });
+        let report = tracker.end_run().unwrap();
+
+        assert_eq!(report.defect_count, 1);
+        assert_eq!(report.defect_maps[0].spool, 1);
+        assert_eq!(label.run_id, Some(report.id));
+        assert_eq!(report.annotations[0].text, "changed filter screen");
+        assert_eq!(report.annotations[0].run_id, Some(report.id));
+        assert_eq!(tracker.last_label(), Some(&label));
+        assert_eq!(tracker.get_report(report.id).unwrap(), Some(report.clone()));
+        assert_eq!(tracker.list_reports().unwrap(), vec![report]);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,1264 @@
+use crate::storage;
+use control_core::{
+    controllers::biquad::Biquad, rest::unit_value::UnitValue,
+    serial::serial_detection::SerialPortFilter,
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::{
+    collections::BTreeMap,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, LazyLock, RwLock},
+    time::Duration,
+};
+use tracing_subscriber::EnvFilter;
+use uom::si::{f64::Frequency, frequency::hertz};
+
+pub mod reload;
+
+/// File inside [`crate::storage::data_dir`] configuring the server
+///
+/// The server runs with the defaults if the file does not exist, but refuses to start with an
+/// invalid file. Changes are applied by [`reload::reload_config`], except for the API bind
+/// address and the log files which need a restart.
+pub const CONFIG_FILE: &str = "server.toml";
+
+static CONFIG: LazyLock<RwLock<Arc<ServerConfig>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(ServerConfig::default())));
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub api: ApiConfig,
+    pub serial: SerialConfig,
+    pub logging: LoggingConfig,
+    pub machines: MachineDefaults,
+    pub labels: LabelConfig,
+    pub maintenance: MaintenanceConfig,
+    pub federation: FederationConfig,
+    pub mes: MesConfig,
+    pub simulation: SimulationConfig,
+    pub motion: MotionConfig,
+    pub socketio: SocketioConfig,
+    pub mutations: MutationsConfig,
+    pub stack_light: StackLightConfig,
+    pub remote_session: RemoteSessionConfig,
+}
+
+/// HTTP server serving the REST API and socket.io
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    pub bind_address: SocketAddr,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            bind_address: SocketAddr::from(([0, 0, 0, 0], 3001)),
+        }
+    }
+}
+
+/// Serial ports searched for devices, e.g. `allow = ["/dev/ttyUSB*"]`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SerialConfig {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl SerialConfig {
+    pub fn port_filter(&self) -> SerialPortFilter {
+        SerialPortFilter {
+            allow: self.allow.clone(),
+            deny: self.deny.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// Filter directives like `info,ethercrab=warn`, `RUST_LOG` still takes precedence
+    pub filter: Option<String>,
+    /// Writes the log to rolling files in addition to the other outputs
+    pub file: Option<LogFileConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogFileConfig {
+    /// `logs` inside the data directory if not set
+    pub directory: Option<PathBuf>,
+    pub rotation: LogRotation,
+    /// Older files are deleted
+    pub max_files: usize,
+}
+
+impl Default for LogFileConfig {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            rotation: LogRotation::Daily,
+            max_files: 14,
+        }
+    }
+}
+
+impl LogFileConfig {
+    pub fn directory(&self) -> PathBuf {
+        self.directory
+            .clone()
+            .unwrap_or_else(|| storage::data_dir().join("logs"))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Hourly,
+    Daily,
+    Never,
+}
+
+/// Values machines start with until they are changed by a client or a recipe
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MachineDefaults {
+    /// Parallel strands of the line, the laser measures and the winder trims each of them
+    pub strands: usize,
+    pub laser: LaserDefaults,
+    pub winder: WinderDefaults,
+    /// Values of single machines differing from the ones above
+    pub instances: MachineInstances,
+}
+
+impl MachineDefaults {
+    pub const MAX_STRANDS: usize = 8;
+
+    /// Defaults of the laser with `serial`
+    pub fn laser_for(&self, serial: u16) -> LaserDefaults {
+        instance_defaults(&self.laser, &self.instances.laser, serial)
+    }
+
+    /// Defaults of the winder with `serial`
+    pub fn winder_for(&self, serial: u16) -> WinderDefaults {
+        instance_defaults(&self.winder, &self.instances.winder, serial)
+    }
+}
+
+impl Default for MachineDefaults {
+    fn default() -> Self {
+        Self {
+            strands: 1,
+            laser: LaserDefaults::default(),
+            winder: WinderDefaults::default(),
+            instances: MachineInstances::default(),
+        }
+    }
+}
+
+/// Defaults of single machines by serial, e.g. `[machines.instances.winder.7]` with
+/// `puller_speed = 2.0`
+///
+/// A block only lists the values that differ, the others are taken from the defaults of the
+/// machine type. Nested tables like `second_puller` are merged, lists are replaced.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MachineInstances {
+    pub laser: BTreeMap<String, toml::Table>,
+    pub winder: BTreeMap<String, toml::Table>,
+}
+
+/// `defaults` with the block of `serial` applied, the blocks are checked by
+/// [`ServerConfig::validate`]
+fn instance_defaults<T: Serialize + DeserializeOwned + Clone>(
+    defaults: &T,
+    instances: &BTreeMap<String, toml::Table>,
+    serial: u16,
+) -> T {
+    let Some(overrides) = instances.get(&serial.to_string()) else {
+        return defaults.clone();
+    };
+    with_overrides(defaults, overrides).unwrap_or_else(|e| {
+        tracing::warn!("Ignoring the defaults of serial {}: {:?}", serial, e);
+        defaults.clone()
+    })
+}
+
+fn with_overrides<T: Serialize + DeserializeOwned>(
+    defaults: &T,
+    overrides: &toml::Table,
+) -> Result<T, anyhow::Error> {
+    let mut table = toml::Table::try_from(defaults)?;
+    merge_table(&mut table, overrides);
+    Ok(toml::Value::Table(table).try_into()?)
+}
+
+fn merge_table(table: &mut toml::Table, overrides: &toml::Table) {
+    for (key, value) in overrides {
+        match (table.get_mut(key), value) {
+            (Some(toml::Value::Table(table)), toml::Value::Table(overrides)) => {
+                merge_table(table, overrides)
+            }
+            _ => {
+                table.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LaserDefaults {
+    /// mm
+    pub target_diameter: f64,
+    /// mm
+    pub lower_tolerance: f64,
+    /// mm
+    pub higher_tolerance: f64,
+    pub min_max_timeframe_minutes: u64,
+    /// s the diameter must stay in tolerance after a start before alarms and min/max begin
+    pub startup_grace_seconds: f64,
+}
+
+impl Default for LaserDefaults {
+    fn default() -> Self {
+        Self {
+            target_diameter: 1.75,
+            lower_tolerance: 0.05,
+            higher_tolerance: 0.05,
+            min_max_timeframe_minutes: 30,
+            startup_grace_seconds: 30.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct WinderDefaults {
+    /// m/min
+    pub puller_speed: f64,
+    /// mm
+    pub traverse_inner_limit: f64,
+    /// mm
+    pub traverse_outer_limit: f64,
+    /// mm the traverse can move out from its end stop, soft limit of every movement
+    pub traverse_travel: f64,
+    /// m spooled before the automatic action triggers
+    pub required_meters: f64,
+    /// m of filament from the laser to the spool, maps measurements to their spool position
+    pub sensor_offset: f64,
+    /// m of filament from the nozzle to the laser, the diameter regulation compensates the
+    /// distance if set
+    pub nozzle_to_laser: Option<f64>,
+    /// Filters the measured diameter before the diameter regulation, unfiltered if not set
+    pub diameter_filter: Option<DiameterFilterConfig>,
+    /// A the spool motor may draw before the winder stops, not monitored if not set
+    pub spool_max_current: Option<f64>,
+    /// A the puller motor may draw before the winder stops, not monitored if not set
+    pub puller_max_current: Option<f64>,
+    /// Line speed caps while a temperature is too high, the lowest active cap applies
+    pub speed_derating: Vec<SpeedDeratingRule>,
+    /// Second puller axis for heavy filament, single puller if not set
+    pub second_puller: Option<SecondPullerConfig>,
+    /// Outputs wired back to inputs, checked by the self test
+    pub self_test_loopbacks: Vec<SelfTestLoopback>,
+}
+
+impl Default for WinderDefaults {
+    fn default() -> Self {
+        Self {
+            puller_speed: 1.0,
+            traverse_inner_limit: 22.0,
+            traverse_outer_limit: 92.0,
+            traverse_travel: 120.0,
+            required_meters: 250.0,
+            sensor_offset: 2.0,
+            nozzle_to_laser: None,
+            diameter_filter: None,
+            spool_max_current: None,
+            puller_max_current: None,
+            speed_derating: Vec::new(),
+            second_puller: None,
+            self_test_loopbacks: Vec::new(),
+        }
+    }
+}
+
+/// Second puller axis, e.g. `{ role = 5, trim = 1.002, load_sharing = true }`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SecondPullerConfig {
+    /// Role of its EL7031-0030 in the device group of the winder
+    pub role: u16,
+    /// Speed relative to the first axis, above 1.0 tensions the filament between the axes
+    #[serde(default = "default_second_puller_trim")]
+    pub trim: f64,
+    /// Balances the motor currents of both axes by correcting the trim
+    #[serde(default)]
+    pub load_sharing: bool,
+}
+
+impl SecondPullerConfig {
+    pub const MIN_TRIM: f64 = 0.95;
+    pub const MAX_TRIM: f64 = 1.05;
+    /// Roles of the terminals every winder has
+    const WINDER_ROLES: u16 = 5;
+}
+
+const fn default_second_puller_trim() -> f64 {
+    1.0
+}
+
+/// Filters of the measured diameter, e.g. `{ low_pass = 5.0, notch = 2.0 }`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DiameterFilterConfig {
+    /// Hz above which the gauge noise is smoothed, no low pass if not set
+    pub low_pass: Option<f64>,
+    /// Hz of a disturbance the regulation should not follow, e.g. the screw rotation
+    pub notch: Option<f64>,
+    /// Width of the notch, a lower value removes a wider band
+    #[serde(default = "default_notch_q")]
+    pub notch_q: f64,
+}
+
+impl DiameterFilterConfig {
+    /// Low pass first, then the notch, for a regulation that runs every `update_period`
+    pub fn filters(&self, update_period: Duration) -> Result<Vec<Biquad>, anyhow::Error> {
+        if update_period.is_zero() {
+            return Err(anyhow::anyhow!(
+                "[{}::DiameterFilterConfig::filters] The diameter filters need a fixed update period, give the winder a period_us in the scheduler config or run the motion thread",
+                module_path!()
+            ));
+        }
+        let sample_rate = Frequency::new::<hertz>(1.0 / update_period.as_secs_f64());
+        let mut filters = Vec::new();
+        if let Some(cutoff) = self.low_pass {
+            filters.push(Biquad::low_pass(
+                Frequency::new::<hertz>(cutoff),
+                sample_rate,
+                Biquad::BUTTERWORTH_Q,
+            )?);
+        }
+        if let Some(center) = self.notch {
+            filters.push(Biquad::notch(
+                Frequency::new::<hertz>(center),
+                sample_rate,
+                self.notch_q,
+            )?);
+        }
+        Ok(filters)
+    }
+}
+
+const fn default_notch_q() -> f64 {
+    2.0
+}
+
+/// Digital output of the winder wired back to one of its inputs, e.g.
+/// `{ output = "brake", input = "estop_feedback" }`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct SelfTestLoopback {
+    pub output: String,
+    pub input: String,
+}
+
+/// Caps the line speed of the winder while a temperature is above a threshold, e.g.
+/// `{ source = "water_bath", above = 40.0, max_line_speed = 20.0 }`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SpeedDeratingRule {
+    pub source: TemperatureSource,
+    /// °C
+    pub above: f64,
+    /// m/min
+    pub max_line_speed: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TemperatureSource {
+    /// Hottest zone of the water bath of an aquapath
+    WaterBath,
+}
+
+impl TemperatureSource {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::WaterBath => "water bath",
+        }
+    }
+}
+
+/// Labels printed when a spool is finished
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LabelConfig {
+    /// Network printer accepting raw ZPL, e.g. `192.168.1.50:9100`, labels are only kept for
+    /// the API if not set
+    pub printer: Option<SocketAddr>,
+}
+
+/// Service intervals of wearing parts, parts without an interval are counted but never due
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MaintenanceConfig {
+    /// km of filament the puller wheel pulls between services
+    pub puller_wheel_km: Option<f64>,
+    /// mm of diameter the puller wheel may lose by wear before it is replaced, calibrations of
+    /// the pulled length measure the wear
+    pub puller_wheel_max_wear: Option<f64>,
+    /// revolutions of the spool shaft between services
+    pub spool_shaft_revolutions: Option<f64>,
+    /// h the extruder screw turns between services
+    pub screw_hours: Option<f64>,
+}
+
+/// Other control servers whose machines are served by this one, changes need a restart
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FederationConfig {
+    pub poll_interval_ms: u64,
+    pub peers: Vec<FederationPeer>,
+}
+
+impl Default for FederationConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_ms: 1000,
+            peers: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct FederationPeer {
+    /// Prefix of the machine namespaces of the peer, e.g. `line2`
+    pub name: String,
+    /// Base URL of the peer API, e.g. `http://10.0.2.10:3001`
+    pub url: String,
+    /// Sent as `Authorization: Bearer <token>` if the peer requires a session
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// Manufacturing execution system pushing orders to `POST /api/v1/mes/orders`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MesConfig {
+    /// Receives the report of every finished order, completions are not reported if not set
+    pub completion_url: Option<String>,
+    /// Sent as `Authorization: Bearer <token>` with the completions
+    pub token: Option<String>,
+}
+
+/// Virtual line of the `--simulate` mode, changes apply to the running simulation
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimulationConfig {
+    /// rpm of the extruder screw
+    pub screw_speed: f64,
+    /// cm³ extruded per screw revolution
+    pub screw_displacement: f64,
+    /// m of filament between the nozzle and the laser
+    pub laser_distance: f64,
+    /// Diameter variation per screw revolution, relative to the diameter
+    pub diameter_ripple: f64,
+    /// mm
+    pub spool_core_diameter: f64,
+    /// mm
+    pub spool_width: f64,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        // 1.75 mm at 1 m/min
+        Self {
+            screw_speed: 20.0,
+            screw_displacement: 0.12,
+            laser_distance: 1.0,
+            diameter_ripple: 0.01,
+            spool_core_diameter: 100.0,
+            spool_width: 60.0,
+        }
+    }
+}
+
+/// Dedicated thread for the motion controllers of the winders, changes need a restart
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MotionConfig {
+    /// Sync spool, puller and traverse speeds on their own real-time thread instead of in the
+    /// act cycle of the control loop
+    pub dedicated_thread: bool,
+    /// µs between two speed updates
+    pub period_us: u64,
+    /// CPU core the thread is pinned to, not pinned if not set
+    pub core: Option<usize>,
+    /// Mutations of the machines waiting for the thread, more are rejected
+    pub queue_capacity: usize,
+}
+
+impl Default for MotionConfig {
+    fn default() -> Self {
+        Self {
+            dedicated_thread: false,
+            period_us: 1000,
+            core: None,
+            queue_capacity: 64,
+        }
+    }
+}
+
+/// Queues of the socket.io clients, so a client that can't keep up doesn't hold back the
+/// others
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SocketioConfig {
+    /// Events waiting for a client before its oldest live values are dropped, applies to
+    /// clients connecting afterwards
+    pub client_queue_capacity: usize,
+    /// ms a client's queue may stay full before the client is disconnected
+    pub stall_timeout_ms: u64,
+}
+
+impl Default for SocketioConfig {
+    fn default() -> Self {
+        Self {
+            client_queue_capacity: 128,
+            stall_timeout_ms: 10_000,
+        }
+    }
+}
+
+/// Checks of machine mutations from the API and the integrations before they reach the
+/// machine, so a client can't send implausible setpoints
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MutationsConfig {
+    /// Mutations of the same name a machine accepts per second, more are rejected
+    pub max_rate_per_second: f64,
+    /// Allowed range of the value per mutation name
+    pub limits: BTreeMap<String, MutationLimit>,
+}
+
+impl Default for MutationsConfig {
+    fn default() -> Self {
+        let diameter = MutationLimit {
+            field: None,
+            unit: Some("mm".to_string()),
+            min: Some(0.5),
+            max: Some(5.0),
+        };
+        Self {
+            max_rate_per_second: 10.0,
+            limits: BTreeMap::from([
+                ("SetTargetDiameter".to_string(), diameter.clone()),
+                ("SetPullerTargetDiameter".to_string(), diameter.clone()),
+                (
+                    "SetStrandTarget".to_string(),
+                    MutationLimit {
+                        field: Some("target_diameter".to_string()),
+                        ..diameter
+                    },
+                ),
+            ]),
+        }
+    }
+}
+
+/// Range of a mutation value, e.g. `SetPullerTargetDiameter = { unit = "mm", min = 0.5, max = 5.0 }`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MutationLimit {
+    /// Field of the mutation holding the value, the mutation itself is the value if not set
+    pub field: Option<String>,
+    /// Unit of `min` and `max`, values with another unit are converted
+    pub unit: Option<String>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// Mutations restricted while a remote session is active, see [`crate::remote_session`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RemoteSessionConfig {
+    /// Rejected entirely, e.g. heater targets
+    pub blocked: Vec<String>,
+    /// May only lower their value, e.g. speeds
+    pub decrease_only: BTreeMap<String, MutationValue>,
+}
+
+impl Default for RemoteSessionConfig {
+    fn default() -> Self {
+        let in_unit = |unit: &str| MutationValue {
+            field: None,
+            unit: Some(unit.to_string()),
+        };
+        Self {
+            blocked: [
+                "SetFrontHeatingTargetTemperature",
+                "SetMiddleHeatingTemperature",
+                "SetBackHeatingTargetTemperature",
+                "SetNozzleHeatingTemperature",
+                "SetFrontTemperature",
+                "SetBackTemperature",
+            ]
+            .map(String::from)
+            .into(),
+            decrease_only: BTreeMap::from([
+                ("SetPullerTargetSpeed".to_string(), in_unit("m/min")),
+                ("SetPullerManualSpeed".to_string(), in_unit("m/min")),
+                ("SetSpoolMinMaxMaxSpeed".to_string(), in_unit("rpm")),
+                ("SetInverterTargetRpm".to_string(), in_unit("rpm")),
+            ]),
+        }
+    }
+}
+
+/// Value of a mutation, e.g. `SetPullerTargetSpeed = { unit = "m/min" }`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MutationValue {
+    /// Field of the mutation holding the value, the mutation itself is the value if not set
+    pub field: Option<String>,
+    /// Unit values are compared in, values with another unit are converted
+    pub unit: Option<String>,
+}
+
+/// Stack light showing the status of the line on the shop floor, off without an output
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct StackLightConfig {
+    pub output: Option<StackLightOutput>,
+    /// Statuses lighting each color, unlisted colors stay off
+    pub lights: Vec<StackLightRule>,
+    pub interval_ms: u64,
+}
+
+impl Default for StackLightConfig {
+    fn default() -> Self {
+        Self {
+            output: None,
+            lights: vec![
+                StackLightRule {
+                    color: StackLightColor::Green,
+                    statuses: vec![LineStatus::InTolerance],
+                    blink: false,
+                },
+                StackLightRule {
+                    color: StackLightColor::Yellow,
+                    statuses: vec![LineStatus::OutOfTolerance, LineStatus::Warning],
+                    blink: false,
+                },
+                StackLightRule {
+                    color: StackLightColor::Red,
+                    statuses: vec![LineStatus::Alarm],
+                    blink: true,
+                },
+            ],
+            interval_ms: 250,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum StackLightOutput {
+    /// Outputs `stack_light_red`, `stack_light_yellow` and `stack_light_green` of the winder
+    /// with `serial`, assigned to terminal channels by its I/O mapping
+    Winder { serial: u16 },
+    /// LED controller on a serial port, receives a line like `R1 Y0 G0` every interval
+    Serial { port: String, baud_rate: u32 },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct StackLightRule {
+    pub color: StackLightColor,
+    pub statuses: Vec<LineStatus>,
+    /// Flashes at 1 Hz instead of a steady light
+    #[serde(default)]
+    pub blink: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StackLightColor {
+    Red,
+    Yellow,
+    Green,
+}
+
+/// Aggregate status of the line, see [`crate::stack_light::line_status`]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LineStatus {
+    /// No laser monitors the tolerance, e.g. while the line stands
+    Idle,
+    InTolerance,
+    OutOfTolerance,
+    /// A warning alarm is present
+    Warning,
+    /// A critical alarm is present
+    Alarm,
+}
+
+impl LaserDefaults {
+    /// Adds the problems of the values to `problems`, prefixed by `path` like `machines.laser`
+    fn validate(&self, path: &str, problems: &mut Vec<String>) {
+        if !(self.target_diameter.is_finite() && self.target_diameter > 0.0) {
+            problems.push(format!("{}.target_diameter must be positive", path));
+        }
+        for (name, tolerance) in [
+            ("lower_tolerance", self.lower_tolerance),
+            ("higher_tolerance", self.higher_tolerance),
+        ] {
+            if !(tolerance.is_finite() && tolerance >= 0.0) {
+                problems.push(format!("{}.{} must not be negative", path, name));
+            }
+        }
+        if self.min_max_timeframe_minutes == 0 {
+            problems.push(format!(
+                "{}.min_max_timeframe_minutes must be at least 1",
+                path
+            ));
+        }
+        if !(self.startup_grace_seconds.is_finite() && self.startup_grace_seconds >= 0.0) {
+            problems.push(format!(
+                "{}.startup_grace_seconds must not be negative",
+                path
+            ));
+        }
+    }
+}
+
+impl WinderDefaults {
+    /// Adds the problems of the values to `problems`, prefixed by `path` like `machines.winder`
+    fn validate(&self, path: &str, problems: &mut Vec<String>) {
+        if !(self.puller_speed.is_finite() && self.puller_speed >= 0.0) {
+            problems.push(format!("{}.puller_speed must not be negative", path));
+        }
+        if !(self.traverse_inner_limit.is_finite()
+            && self.traverse_outer_limit.is_finite()
+            && 0.0 <= self.traverse_inner_limit
+            && self.traverse_inner_limit < self.traverse_outer_limit)
+        {
+            problems.push(format!(
+                "{}.traverse_inner_limit must be between 0 and traverse_outer_limit",
+                path
+            ));
+        }
+        if !(self.traverse_travel.is_finite() && self.traverse_outer_limit <= self.traverse_travel)
+        {
+            problems.push(format!(
+                "{}.traverse_outer_limit must not exceed traverse_travel",
+                path
+            ));
+        }
+        if !(self.required_meters.is_finite() && self.required_meters > 0.0) {
+            problems.push(format!("{}.required_meters must be positive", path));
+        }
+        if !(self.sensor_offset.is_finite() && self.sensor_offset >= 0.0) {
+            problems.push(format!("{}.sensor_offset must not be negative", path));
+        }
+        if self
+            .nozzle_to_laser
+            .is_some_and(|distance| !(distance.is_finite() && distance > 0.0))
+        {
+            problems.push(format!("{}.nozzle_to_laser must be positive", path));
+        }
+        if let Some(diameter_filter) = &self.diameter_filter {
+            // the sample rate is only known once the winder runs, the filters check the upper
+            // bound when they are created
+            for (name, frequency) in [
+                ("low_pass", diameter_filter.low_pass),
+                ("notch", diameter_filter.notch),
+            ] {
+                if frequency.is_some_and(|frequency| !(frequency.is_finite() && frequency > 0.0)) {
+                    problems.push(format!(
+                        "{}.diameter_filter.{} must be positive",
+                        path, name
+                    ));
+                }
+            }
+            if !(diameter_filter.notch_q.is_finite() && diameter_filter.notch_q > 0.0) {
+                problems.push(format!("{}.diameter_filter.notch_q must be positive", path));
+            }
+        }
+        for (name, max_current) in [
+            ("spool_max_current", self.spool_max_current),
+            ("puller_max_current", self.puller_max_current),
+        ] {
+            if max_current
+                .is_some_and(|max_current| !(max_current.is_finite() && max_current > 0.0))
+            {
+                problems.push(format!("{}.{} must be positive", path, name));
+            }
+        }
+        if let Some(second_puller) = &self.second_puller {
+            if second_puller.role < SecondPullerConfig::WINDER_ROLES {
+                problems.push(format!(
+                    "{}.second_puller.role {} belongs to another terminal",
+                    path, second_puller.role
+                ));
+            }
+            if !(SecondPullerConfig::MIN_TRIM..=SecondPullerConfig::MAX_TRIM)
+                .contains(&second_puller.trim)
+            {
+                problems.push(format!(
+                    "{}.second_puller.trim must be between {} and {}",
+                    path,
+                    SecondPullerConfig::MIN_TRIM,
+                    SecondPullerConfig::MAX_TRIM
+                ));
+            }
+        }
+        for (i, rule) in self.speed_derating.iter().enumerate() {
+            if !rule.above.is_finite() {
+                problems.push(format!(
+                    "{}.speed_derating[{}].above must be finite",
+                    path, i
+                ));
+            }
+            if !(rule.max_line_speed.is_finite() && rule.max_line_speed > 0.0) {
+                problems.push(format!(
+                    "{}.speed_derating[{}].max_line_speed must be positive",
+                    path, i
+                ));
+            }
+        }
+    }
+}
+
+/// Checks the machine defaults of each instance block with the block applied
+fn validate_instances<T: Serialize + DeserializeOwned>(
+    defaults: &T,
+    instances: &BTreeMap<String, toml::Table>,
+    path: &str,
+    validate: fn(&T, &str, &mut Vec<String>),
+    problems: &mut Vec<String>,
+) {
+    for (serial, overrides) in instances {
+        let path = format!("{}.{}", path, serial);
+        if serial.parse::<u16>().is_err() {
+            problems.push(format!("{} is not named by a serial number", path));
+            continue;
+        }
+        match with_overrides(defaults, overrides) {
+            Ok(instance) => validate(&instance, &path, problems),
+            Err(e) => problems.push(format!("{} is invalid: {}", path, e)),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Reads [`CONFIG_FILE`], the defaults if it does not exist
+    pub fn load() -> Result<Self, anyhow::Error> {
+        let path = storage::data_dir().join(CONFIG_FILE);
+        let config = storage::read_toml::<Self>(&path)?.unwrap_or_default();
+        let problems = config.validate();
+        if !problems.is_empty() {
+            return Err(anyhow::anyhow!(
+                "[{}::ServerConfig::load] Invalid config {:?}:\n- {}",
+                module_path!(),
+                path,
+                problems.join("\n- ")
+            ));
+        }
+        Ok(config)
+    }
+
+    /// Problems with values that parse but can't be used, empty if the config is valid
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        for (list, patterns) in [("allow", &self.serial.allow), ("deny", &self.serial.deny)] {
+            if patterns.iter().any(|pattern| pattern.trim().is_empty()) {
+                problems.push(format!("serial.{} contains an empty pattern", list));
+            }
+        }
+
+        if let Some(filter) = &self.logging.filter {
+            if let Err(e) = EnvFilter::try_new(filter) {
+                problems.push(format!("logging.filter '{}' is invalid: {}", filter, e));
+            }
+        }
+
+        if self
+            .logging
+            .file
+            .as_ref()
+            .is_some_and(|file| file.max_files == 0)
+        {
+            problems.push("logging.file.max_files must be at least 1".to_string());
+        }
+
+        if !(1..=MachineDefaults::MAX_STRANDS).contains(&self.machines.strands) {
+            problems.push(format!(
+                "machines.strands must be between 1 and {}",
+                MachineDefaults::MAX_STRANDS
+            ));
+        }
+
+        self.machines
+            .laser
+            .validate("machines.laser", &mut problems);
+        validate_instances(
+            &self.machines.laser,
+            &self.machines.instances.laser,
+            "machines.instances.laser",
+            LaserDefaults::validate,
+            &mut problems,
+        );
+
+        self.machines
+            .winder
+            .validate("machines.winder", &mut problems);
+        validate_instances(
+            &self.machines.winder,
+            &self.machines.instances.winder,
+            "machines.instances.winder",
+            WinderDefaults::validate,
+            &mut problems,
+        );
+
+        let maintenance = &self.maintenance;
+        for (name, interval) in [
+            ("puller_wheel_km", maintenance.puller_wheel_km),
+            ("puller_wheel_max_wear", maintenance.puller_wheel_max_wear),
+            (
+                "spool_shaft_revolutions",
+                maintenance.spool_shaft_revolutions,
+            ),
+            ("screw_hours", maintenance.screw_hours),
+        ] {
+            if interval.is_some_and(|interval| !(interval.is_finite() && interval > 0.0)) {
+                problems.push(format!("maintenance.{} must be positive", name));
+            }
+        }
+
+        let federation = &self.federation;
+        if federation.poll_interval_ms == 0 {
+            problems.push("federation.poll_interval_ms must be positive".to_string());
+        }
+        for (i, peer) in federation.peers.iter().enumerate() {
+            if peer.name.is_empty() || peer.name.contains('/') {
+                problems.push(format!(
+                    "federation.peers.{}.name must not be empty or contain '/'",
+                    i
+                ));
+            } else if federation.peers[..i]
+                .iter()
+                .any(|other| other.name == peer.name)
+            {
+                problems.push(format!(
+                    "federation.peers.{}.name '{}' is used twice",
+                    i, peer.name
+                ));
+            }
+            if !(peer.url.starts_with("http://") || peer.url.starts_with("https://")) {
+                problems.push(format!(
+                    "federation.peers.{}.url must start with http:// or https://",
+                    i
+                ));
+            }
+        }
+
+        if self
+            .mes
+            .completion_url
+            .as_ref()
+            .is_some_and(|url| !(url.starts_with("http://") || url.starts_with("https://")))
+        {
+            problems.push("mes.completion_url must start with http:// or https://".to_string());
+        }
+
+        let simulation = &self.simulation;
+        for (name, value) in [
+            ("screw_displacement", simulation.screw_displacement),
+            ("laser_distance", simulation.laser_distance),
+            ("spool_core_diameter", simulation.spool_core_diameter),
+            ("spool_width", simulation.spool_width),
+        ] {
+            if !(value.is_finite() && value > 0.0) {
+                problems.push(format!("simulation.{} must be positive", name));
+            }
+        }
+        if !(simulation.screw_speed.is_finite() && simulation.screw_speed >= 0.0) {
+            problems.push("simulation.screw_speed must not be negative".to_string());
+        }
+        if !(0.0..1.0).contains(&simulation.diameter_ripple) {
+            problems.push("simulation.diameter_ripple must be between 0 and 1".to_string());
+        }
+
+        if self.motion.period_us == 0 {
+            problems.push("motion.period_us must be positive".to_string());
+        }
+        if self.motion.queue_capacity == 0 {
+            problems.push("motion.queue_capacity must be at least 1".to_string());
+        }
+
+        if self.socketio.client_queue_capacity == 0 {
+            problems.push("socketio.client_queue_capacity must be at least 1".to_string());
+        }
+        if self.socketio.stall_timeout_ms == 0 {
+            problems.push("socketio.stall_timeout_ms must be positive".to_string());
+        }
+
+        let mutations = &self.mutations;
+        if !(mutations.max_rate_per_second.is_finite() && mutations.max_rate_per_second > 0.0) {
+            problems.push("mutations.max_rate_per_second must be positive".to_string());
+        }
+        for (name, limit) in &mutations.limits {
+            if limit
+                .unit
+                .as_ref()
+                .is_some_and(|unit| UnitValue::Bare(1.0).value_in(unit).is_err())
+            {
+                problems.push(format!("mutations.limits.{}.unit is unknown", name));
+            }
+            if let (Some(min), Some(max)) = (limit.min, limit.max) {
+                if min > max {
+                    problems.push(format!("mutations.limits.{}.min must not exceed max", name));
+                }
+            }
+        }
+
+        let remote_session = &self.remote_session;
+        for (name, value) in &remote_session.decrease_only {
+            if value
+                .unit
+                .as_ref()
+                .is_some_and(|unit| UnitValue::Bare(1.0).value_in(unit).is_err())
+            {
+                problems.push(format!(
+                    "remote_session.decrease_only.{}.unit is unknown",
+                    name
+                ));
+            }
+            if remote_session.blocked.contains(name) {
+                problems.push(format!(
+                    "remote_session.decrease_only.{} is also blocked",
+                    name
+                ));
+            }
+        }
+
+        let stack_light = &self.stack_light;
+        if stack_light.interval_ms == 0 {
+            problems.push("stack_light.interval_ms must be positive".to_string());
+        }
+        if let Some(StackLightOutput::Serial { port, baud_rate }) = &stack_light.output {
+            if port.trim().is_empty() {
+                problems.push("stack_light.output.serial.port must not be empty".to_string());
+            }
+            if *baud_rate == 0 {
+                problems.push("stack_light.output.serial.baud_rate must be positive".to_string());
+            }
+        }
+        for (i, rule) in stack_light.lights.iter().enumerate() {
+            if stack_light.lights[..i]
+                .iter()
+                .any(|other| other.color == rule.color)
+            {
+                problems.push(format!(
+                    "stack_light.lights[{}].color {:?} is used twice",
+                    i, rule.color
+                ));
+            }
+        }
+
+        problems
+    }
+}
+
+/// Loads [`CONFIG_FILE`], must run before anything reads [`config`]
+///
+/// On errors the defaults stay in place so logging can still start and report them.
+pub fn init_config() -> Result<(), anyhow::Error> {
+    set_config(ServerConfig::load()?);
+    Ok(())
+}
+
+/// Current config, read it again instead of keeping it to pick up reloads
+pub fn config() -> Arc<ServerConfig> {
+    CONFIG.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+pub fn set_config(config: ServerConfig) {
+    *CONFIG.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(config);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config: ServerConfig = toml::from_str(
+            r#"
+            [api]
+            bind_address = "127.0.0.1:4000"
+
+            [serial]
+            deny = ["/dev/ttyUSB3"]
+
+            [machines.laser]
+            target_diameter = 2.85
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.api.bind_address.port(), 4000);
+        assert_eq!(config.serial.deny, vec!["/dev/ttyUSB3".to_string()]);
+        assert_eq!(config.machines.laser.target_diameter, 2.85);
+        assert_eq!(config.machines.laser.lower_tolerance, 0.05);
+        assert!(config.validate().is_empty());
+
+        let config: ServerConfig = toml::from_str(
+            r#"
+            [logging.file]
+            rotation = "hourly"
+            "#,
+        )
+        .unwrap();
+        let file = config.logging.file.unwrap();
+        assert_eq!(file.rotation, LogRotation::Hourly);
+        assert_eq!(file.max_files, 14);
+
+        let config: ServerConfig = toml::from_str(
+            r#"
+            [machines.winder]
+            speed_derating = [{ source = "water_bath", above = 40.0, max_line_speed = 20.0 }]
+            "#,
+        )
+        .unwrap();
+        let rule = &config.machines.winder.speed_derating[0];
+        assert_eq!(rule.source, TemperatureSource::WaterBath);
+        assert_eq!(rule.max_line_speed, 20.0);
+
+        let mut config: ServerConfig = toml::from_str(
+            r#"
+            [machines.winder]
+            diameter_filter = { low_pass = 5.0, notch = 2.0 }
+            "#,
+        )
+        .unwrap();
+        let diameter_filter = config.machines.winder.diameter_filter.as_ref().unwrap();
+        assert_eq!(diameter_filter.notch_q, 2.0);
+        let period = Duration::from_millis(1);
+        assert_eq!(diameter_filter.filters(period).unwrap().len(), 2);
+        // the winder acts in every loop cycle, whose length isn't fixed
+        assert!(diameter_filter.filters(Duration::ZERO).is_err());
+        assert!(config.validate().is_empty());
+        if let Some(diameter_filter) = &mut config.machines.winder.diameter_filter {
+            diameter_filter.low_pass = Some(-5.0);
+        }
+        assert_eq!(config.validate().len(), 1);
+        // above half the sample rate of the 1ms period
+        if let Some(diameter_filter) = &mut config.machines.winder.diameter_filter {
+            diameter_filter.low_pass = Some(500.0);
+        }
+        assert!(config.validate().is_empty());
+        let diameter_filter = config.machines.winder.diameter_filter.as_ref().unwrap();
+        assert!(diameter_filter.filters(period).is_err());
+
+        let config: ServerConfig = toml::from_str(
+            r#"
+            [stack_light]
+            output = { serial = { port = "/dev/ttyACM0", baud_rate = 9600 } }
+            lights = [{ color = "red", statuses = ["alarm", "warning"], blink = true }]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.stack_light.output,
+            Some(StackLightOutput::Serial {
+                port: "/dev/ttyACM0".to_string(),
+                baud_rate: 9600
+            })
+        );
+        assert_eq!(
+            config.stack_light.lights[0].statuses,
+            vec![LineStatus::Alarm, LineStatus::Warning]
+        );
+        assert_eq!(config.stack_light.interval_ms, 250);
+
+        // typos are errors instead of silently ignored
+        assert!(toml::from_str::<ServerConfig>("[api]\nbind_adress = \"0.0.0.0:3001\"").is_err());
+    }
+
+    #[test]
+    fn test_validate_config() {
+        let mut config = ServerConfig::default();
+        assert!(config.validate().is_empty());
+
+        config.logging.filter = Some("info,ethercrab=loud".to_string());
+        config.machines.winder.traverse_inner_limit = 100.0;
+        config.serial.allow = vec![" ".to_string()];
+        config.machines.strands = 0;
+        let problems = config.validate();
+        assert_eq!(problems.len(), 4, "{:?}", problems);
+        assert!(problems[0].starts_with("serial.allow"));
+    }
+
+    #[test]
+    fn test_instance_defaults() {
+        let config: ServerConfig = toml::from_str(
+            r#"
+            [machines.winder]
+            puller_speed = 2.0
+            second_puller = { role = 5, trim = 1.01 }
+
+            [machines.instances.winder.7]
+            traverse_outer_limit = 110.0
+            second_puller = { load_sharing = true }
+
+            [machines.instances.laser.3]
+            target_diameter = 2.85
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_empty(), "{:?}", config.validate());
+
+        let winder = config.machines.winder_for(7);
+        assert_eq!(winder.puller_speed, 2.0);
+        assert_eq!(winder.traverse_outer_limit, 110.0);
+        assert_eq!(
+            winder.second_puller,
+            Some(SecondPullerConfig {
+                role: 5,
+                trim: 1.01,
+                load_sharing: true
+            })
+        );
+        assert_eq!(config.machines.winder_for(8), config.machines.winder);
+        assert_eq!(config.machines.laser_for(3).target_diameter, 2.85);
+        assert_eq!(config.machines.laser_for(3).lower_tolerance, 0.05);
+
+        let mut config = ServerConfig::default();
+        config.machines.instances.winder = BTreeMap::from([
+            (
+                "7".to_string(),
+                toml::from_str("traverse_outer_limit = 200.0").unwrap(),
+            ),
+            ("spare".to_string(), toml::Table::new()),
+            (
+                "8".to_string(),
+                toml::from_str("puller_sped = 2.0").unwrap(),
+            ),
+        ]);
+        let problems = config.validate();
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        assert_eq!(
+            problems[0],
+            "machines.instances.winder.7.traverse_outer_limit must not exceed traverse_travel"
+        );
+        assert!(problems[1].starts_with("machines.instances.winder.8 is invalid"));
+    }
+}
@@ -0,0 +1,186 @@
+use super::{CONFIG_FILE, ServerConfig, config, set_config};
+use crate::{
+    alarms::notifier::{NOTIFIER_FILE, read_notifier_config, set_notifier_config},
+    app_state::AppState,
+    instrumentation::{INSTRUMENTATION_FILE, InstrumentationConfig},
+    logging::set_log_filter,
+    panic::{PanicDetails, send_panic},
+    scheduler::{SCHEDULER_FILE, SchedulerConfig},
+    socketio::rate_limits::{EMIT_RATES_FILE, read_emit_rates},
+    watchdog::{WATCHDOG_FILE, WatchdogConfig},
+};
+use control_core::socketio::rate_limit::{EmitRateLimits, emit_rate_limits, set_emit_rate_limits};
+use serde::Serialize;
+use signal_hook::{consts::SIGHUP, iterator::Signals};
+use smol::channel::Sender;
+use std::sync::Arc;
+
+/// Outcome of a reload, files that failed to load keep their current settings
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigReload {
+    /// Files whose changes were applied
+    pub changed: Vec<&'static str>,
+    pub errors: Vec<String>,
+}
+
+impl ConfigReload {
+    fn failed(&mut self, file: &str, error: anyhow::Error) {
+        self.errors.push(format!("{}: {:?}", file, error));
+    }
+}
+
+/// Reads all config files again and applies the changes to the running server
+///
+/// Machines keep running, changed machine defaults apply to machines connected afterwards.
+pub async fn reload_config(app_state: &Arc<AppState>) -> ConfigReload {
+    let mut reload = ConfigReload::default();
+
+    match ServerConfig::load() {
+        Ok(server_config) => {
+            let current = config();
+            if server_config.api != current.api
+                || server_config.logging.file != current.logging.file
+            {
+                tracing::warn!(
+                    "Changes to the API bind address and log files apply after a restart"
+                );
+            }
+            let filter_changed = server_config.logging.filter != current.logging.filter;
+            if server_config.serial != current.serial {
+                app_state
+                    .serial_setup
+                    .write()
+                    .await
+                    .serial_detection
+                    .port_filter = server_config.serial.port_filter();
+            }
+            if server_config != *current {
+                set_config(server_config);
+                reload.changed.push(CONFIG_FILE);
+            }
+            // replaces a filter set through the API as well
+            if filter_changed {
+                if let Err(e) = set_log_filter(None) {
+                    reload.failed(CONFIG_FILE, e);
+                }
+            }
+        }
+        Err(e) => reload.failed(CONFIG_FILE, e),
+    }
+
+    match read_emit_rates() {
+        Ok(limits) => {
+            if limits != emit_rate_limits() {
+                set_emit_rate_limits(limits.clone());
+                apply_emit_rates(app_state, &limits).await;
+                reload.changed.push(EMIT_RATES_FILE);
+            }
+        }
+        Err(e) => reload.failed(EMIT_RATES_FILE, e),
+    }
+
+    match WatchdogConfig::read() {
+        Ok(watchdog_config) => {
+            let mut watchdog = app_state.watchdog.write().await;
+            if watchdog.config != watchdog_config {
+                watchdog.config = watchdog_config;
+                reload.changed.push(WATCHDOG_FILE);
+            }
+        }
+        Err(e) => reload.failed(WATCHDOG_FILE, e),
+    }
+
+    match InstrumentationConfig::read() {
+        Ok(instrumentation_config) => {
+            let mut instrumentation = app_state.instrumentation.write().await;
+            if instrumentation.config != instrumentation_config {
+                instrumentation.config = instrumentation_config;
+                reload.changed.push(INSTRUMENTATION_FILE);
+            }
+        }
+        Err(e) => reload.failed(INSTRUMENTATION_FILE, e),
+    }
+
+    match SchedulerConfig::read() {
+        Ok(scheduler_config) => {
+            let mut scheduler = app_state.scheduler.write().await;
+            if scheduler.config != scheduler_config {
+                scheduler.config = scheduler_config;
+                reload.changed.push(SCHEDULER_FILE);
+            }
+        }
+        Err(e) => reload.failed(SCHEDULER_FILE, e),
+    }
+
+    match read_notifier_config() {
+        Ok(notifier_config) => {
+            if set_notifier_config(notifier_config) {
+                reload.changed.push(NOTIFIER_FILE);
+            }
+        }
+        Err(e) => reload.failed(NOTIFIER_FILE, e),
+    }
+
+    for file in &reload.changed {
+        tracing::info!("Applied changes of {}", file);
+    }
+    for error in &reload.errors {
+        tracing::error!("Kept the current config, {}", error);
+    }
+    reload
+}
+
+/// Replaces the emit rates of all existing namespaces, including those of the machines
+async fn apply_emit_rates(app_state: &Arc<AppState>, limits: &EmitRateLimits) {
+    app_state
+        .socketio_setup
+        .namespaces
+        .write()
+        .await
+        .apply_rate_limits(limits);
+
+    let slots: Vec<_> = app_state
+        .machines
+        .read()
+        .await
+        .iter()
+        .map(|(_, slot)| slot.clone())
+        .collect();
+    for slot in slots {
+        let namespace = slot.lock().await.namespace.clone();
+        namespace.lock().await.apply_rate_limits(limits);
+    }
+}
+
+/// Reloads the config on SIGHUP
+pub fn init_config_reload(
+    thread_panic_tx: Sender<PanicDetails>,
+    app_state: Arc<AppState>,
+) -> Result<(), anyhow::Error> {
+    let mut signals = Signals::new([SIGHUP]).map_err(|e| {
+        anyhow::anyhow!(
+            "[{}::init_config_reload] Failed to register signal handler\n{:?}",
+            module_path!(),
+            e
+        )
+    })?;
+
+    std::thread::Builder::new()
+        .name("config-reload".to_owned())
+        .spawn(move || {
+            send_panic(thread_panic_tx);
+            for _ in signals.forever() {
+                tracing::info!("Received SIGHUP, reloading config");
+                smol::block_on(reload_config(&app_state));
+            }
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_config_reload] Failed to spawn config reload thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    Ok(())
+}
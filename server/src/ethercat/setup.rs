@@ -4,6 +4,7 @@ use crate::panic::{PanicDetails, send_panic};
 use crate::socketio::main_namespace::MainNamespaceEvents;
 use crate::socketio::main_namespace::ethercat_devices_event::EthercatDevicesEventBuilder;
 use crate::socketio::main_namespace::machines_event::MachinesEventBuilder;
+use crate::socketio::registry_namespace::sync_registry;
 use crate::{
     app_state::AppState,
     ethercat::config::{MAX_FRAMES, MAX_PDU_DATA, MAX_SUBDEVICES, PDI_LEN},
@@ -238,6 +239,7 @@ pub async fn setup_loop(
         let event = MachinesEventBuilder().build(app_state_clone.clone());
         main_namespace.emit(MainNamespaceEvents::MachinesEvent(event));
     });
+    smol::block_on(sync_registry(&app_state));
 
     // Put group in operational state
     let group_op = match group_preop.into_op(&maindevice).await {
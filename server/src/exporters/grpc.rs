@@ -0,0 +1,384 @@
+use crate::{
+    app_state::AppState,
+    auth::{Role, bearer_token},
+    panic::{PanicDetails, send_panic},
+    rest::{
+        handlers::{
+            auth::authorize_machine_mutation,
+            machine_mutation::mutate_machine,
+            machines::{latest_event, machine_snapshot},
+        },
+        util::ResponseUtilError,
+    },
+    storage,
+};
+use control_core::{
+    machines::identification::{MachineIdentification, MachineIdentificationUnique},
+    rest::mutation::MachineMutationBody,
+    socketio::event::GenericEvent,
+};
+use proto::{
+    ListMachinesRequest, ListMachinesResponse, Machine, MachineEvent, MachineEventsResponse,
+    MachineId, MachineRequest, MutateMachineRequest, MutateMachineResponse,
+    StreamMachineEventsRequest,
+    machine_control_server::{MachineControl, MachineControlServer},
+};
+use serde::{Deserialize, Serialize};
+use smol::channel::Sender;
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
+use tonic::{Request, Response, Status, codegen::BoxStream, metadata::MetadataMap};
+
+/// File inside [`storage::data_dir`] configuring the gRPC API
+///
+/// The API only runs if the file exists, the service is defined in `server/proto/control.proto`.
+pub const GRPC_FILE: &str = "grpc.json";
+
+/// Events a slow stream client is behind before the stream waits for it
+const STREAM_BUFFER: usize = 64;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GrpcConfig {
+    #[serde(default = "default_host")]
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+}
+
+fn default_host() -> String {
+    "0.0.0.0".to_string()
+}
+
+const fn default_port() -> u16 {
+    50051
+}
+
+/// Messages and service generated from `server/proto/control.proto` by `build.rs`
+#[allow(clippy::nursery)]
+pub mod proto {
+    tonic::include_proto!("qitech.control.v1");
+}
+
+impl From<&MachineIdentificationUnique> for MachineId {
+    fn from(machine: &MachineIdentificationUnique) -> Self {
+        Self {
+            vendor: machine.machine_identification.vendor.into(),
+            machine: machine.machine_identification.machine.into(),
+            serial: machine.serial.into(),
+        }
+    }
+}
+
+fn machine_identification_unique(
+    id: Option<MachineId>,
+) -> Result<MachineIdentificationUnique, ResponseUtilError> {
+    let id =
+        id.ok_or_else(|| ResponseUtilError::BadRequest(anyhow::anyhow!("machine is missing")))?;
+    let field = |value: u32| {
+        u16::try_from(value)
+            .map_err(|_| ResponseUtilError::BadRequest(anyhow::anyhow!("Invalid machine {:?}", id)))
+    };
+    Ok(MachineIdentificationUnique {
+        machine_identification: MachineIdentification {
+            vendor: field(id.vendor)?,
+            machine: field(id.machine)?,
+        },
+        serial: field(id.serial)?,
+    })
+}
+
+impl From<ResponseUtilError> for Status {
+    fn from(error: ResponseUtilError) -> Self {
+        match error {
+            ResponseUtilError::Error(e) => Self::internal(e.to_string()),
+            ResponseUtilError::NotFound(e) => Self::not_found(e.to_string()),
+            ResponseUtilError::BadRequest(e) => Self::invalid_argument(e.to_string()),
+            ResponseUtilError::Conflict(e) | ResponseUtilError::Interlock(e) => {
+                Self::failed_precondition(e.to_string())
+            }
+            ResponseUtilError::Unauthorized(e) => Self::unauthenticated(e.to_string()),
+            ResponseUtilError::Forbidden(e) => Self::permission_denied(e.to_string()),
+            ResponseUtilError::TooManyRequests(e) => Self::resource_exhausted(e.to_string()),
+        }
+    }
+}
+
+/// Starts the gRPC API if it is configured
+pub fn init_grpc(
+    thread_panic_tx: Sender<PanicDetails>,
+    app_state: Arc<AppState>,
+) -> Result<(), anyhow::Error> {
+    let Some(config) = storage::read_json::<GrpcConfig>(&storage::data_dir().join(GRPC_FILE))?
+    else {
+        return Ok(());
+    };
+    let address: SocketAddr = format!("{}:{}", config.host, config.port)
+        .parse()
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_grpc] Invalid address {}:{}: {}",
+                module_path!(),
+                config.host,
+                config.port,
+                e
+            )
+        })?;
+    tracing::info!("Serving gRPC on {}", address);
+
+    std::thread::Builder::new()
+        .name("grpc".to_owned())
+        .spawn(move || {
+            send_panic(thread_panic_tx);
+
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .enable_io()
+                .enable_time()
+                .build()
+                .expect("Failed to create runtime");
+
+            rt.block_on(async {
+                let result = tonic::transport::Server::builder()
+                    .add_service(MachineControlServer::new(GrpcService { app_state }))
+                    .serve(address)
+                    .await;
+                if let Err(e) = result {
+                    tracing::error!("Failed to serve gRPC on {}: {:?}", address, e);
+                }
+            });
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_grpc] Failed to spawn grpc thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    Ok(())
+}
+
+/// `qitech.control.v1.MachineControl`
+#[derive(Clone)]
+struct GrpcService {
+    app_state: Arc<AppState>,
+}
+
+#[tonic::async_trait]
+impl MachineControl for GrpcService {
+    type StreamMachineEventsStream = BoxStream<MachineEvent>;
+
+    async fn list_machines(
+        &self,
+        request: Request<ListMachinesRequest>,
+    ) -> Result<Response<ListMachinesResponse>, Status> {
+        list_machines(self.app_state.clone(), request).await
+    }
+
+    async fn get_machine_events(
+        &self,
+        request: Request<MachineRequest>,
+    ) -> Result<Response<MachineEventsResponse>, Status> {
+        get_machine_events(self.app_state.clone(), request).await
+    }
+
+    async fn mutate_machine(
+        &self,
+        request: Request<MutateMachineRequest>,
+    ) -> Result<Response<MutateMachineResponse>, Status> {
+        mutate(self.app_state.clone(), request).await
+    }
+
+    async fn stream_machine_events(
+        &self,
+        request: Request<StreamMachineEventsRequest>,
+    ) -> Result<Response<Self::StreamMachineEventsStream>, Status> {
+        stream_machine_events(self.app_state.clone(), request).await
+    }
+}
+
+/// Reads need at least a viewer session, like the REST API
+async fn authorize_viewer(app_state: &AppState, metadata: &MetadataMap) -> Result<(), Status> {
+    let headers = metadata.clone().into_headers();
+    app_state
+        .auth
+        .read()
+        .await
+        .authorize(bearer_token(&headers), Role::Viewer)
+        .map_err(|e| ResponseUtilError::from(e).into())
+        .map(|_| ())
+}
+
+async fn list_machines(
+    app_state: Arc<AppState>,
+    request: Request<ListMachinesRequest>,
+) -> Result<Response<ListMachinesResponse>, Status> {
+    authorize_viewer(&app_state, request.metadata()).await?;
+    let machines = app_state
+        .get_machine_objs()
+        .into_iter()
+        .map(|machine| Machine {
+            id: Some((&machine.machine_identification_unique).into()),
+            error: machine.error,
+        })
+        .collect();
+    Ok(Response::new(ListMachinesResponse { machines }))
+}
+
+async fn get_machine_events(
+    app_state: Arc<AppState>,
+    request: Request<MachineRequest>,
+) -> Result<Response<MachineEventsResponse>, Status> {
+    authorize_viewer(&app_state, request.metadata()).await?;
+    let machine = machine_identification_unique(request.into_inner().machine)?;
+    let snapshot = machine_snapshot(&app_state, &machine).await?;
+    let events = changed_events(&snapshot.events, &[], &mut HashMap::new());
+    Ok(Response::new(MachineEventsResponse { events }))
+}
+
+async fn mutate(
+    app_state: Arc<AppState>,
+    request: Request<MutateMachineRequest>,
+) -> Result<Response<MutateMachineResponse>, Status> {
+    let headers = request.metadata().clone().into_headers();
+    let request = request.into_inner();
+    let body = MachineMutationBody {
+        machine_identification_unique: machine_identification_unique(request.machine)?,
+        data: serde_json::from_str(&request.mutation_json)
+            .map_err(|e| Status::invalid_argument(format!("Invalid mutation_json: {}", e)))?,
+    };
+    authorize_machine_mutation(&app_state, bearer_token(&headers), &body).await?;
+    mutate_machine(&app_state, body)
+        .await
+        .map_err(ResponseUtilError::from)?;
+    Ok(Response::new(MutateMachineResponse {}))
+}
+
+/// Checks the machine for changed events every interval until the client goes away
+///
+/// The stream ends with an error when the machine disconnects.
+async fn stream_machine_events(
+    app_state: Arc<AppState>,
+    request: Request<StreamMachineEventsRequest>,
+) -> Result<Response<BoxStream<MachineEvent>>, Status> {
+    authorize_viewer(&app_state, request.metadata()).await?;
+    let request = request.into_inner();
+    let machine = machine_identification_unique(request.machine)?;
+    let interval = match request.interval_ms {
+        0 => Duration::from_millis(100),
+        interval_ms => Duration::from_millis(interval_ms.into()),
+    };
+
+    let (tx, rx) = smol::channel::bounded(STREAM_BUFFER);
+    smol::spawn(async move {
+        let mut forwarded = HashMap::new();
+        loop {
+            let events = match machine_snapshot(&app_state, &machine).await {
+                Ok(snapshot) => changed_events(&snapshot.events, &request.events, &mut forwarded),
+                Err(e) => {
+                    let _ = tx.send(Err(e.into())).await;
+                    return;
+                }
+            };
+            for event in events {
+                if tx.send(Ok(event)).await.is_err() {
+                    return;
+                }
+            }
+            if tx.is_closed() {
+                return;
+            }
+            smol::Timer::after(interval).await;
+        }
+    })
+    .detach();
+
+    let stream: BoxStream<MachineEvent> = Box::pin(rx);
+    Ok(Response::new(stream))
+}
+
+/// Latest value of the events newer than the last forwarded one, all events if `names` is empty
+fn changed_events(
+    events: &HashMap<String, Arc<GenericEvent>>,
+    names: &[String],
+    forwarded: &mut HashMap<String, u64>,
+) -> Vec<MachineEvent> {
+    let mut changed: Vec<_> = events
+        .iter()
+        .filter(|(name, _)| names.is_empty() || names.contains(*name))
+        .filter_map(|(name, event)| {
+            let latest = latest_event(event)?;
+            if forwarded.insert(name.clone(), latest.ts) == Some(latest.ts) {
+                return None;
+            }
+            Some(MachineEvent {
+                name: name.clone(),
+                ts: latest.ts,
+                data_json: latest.data.to_string(),
+            })
+        })
+        .collect();
+    changed.sort_by_key(|event| event.ts);
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(name: &str, ts: u64, diameter: f64) -> Arc<GenericEvent> {
+        Arc::new(GenericEvent {
+            name: name.to_string(),
+            data: Box::new(json!({ "diameter": diameter })),
+            ts,
+            mono: 0,
+            droppable: false,
+        })
+    }
+
+    #[test]
+    fn test_changed_events() {
+        let mut events = HashMap::from([
+            ("StateEvent".to_string(), event("StateEvent", 5, 0.0)),
+            (
+                "LiveValuesEvent".to_string(),
+                event("LiveValuesEvent", 10, 1.75),
+            ),
+        ]);
+        let mut forwarded = HashMap::new();
+
+        let changed = changed_events(&events, &[], &mut forwarded);
+        assert_eq!(changed.len(), 2);
+        assert_eq!(changed[0].name, "StateEvent");
+        assert!(changed_events(&events, &[], &mut forwarded).is_empty());
+
+        events.insert(
+            "LiveValuesEvent".to_string(),
+            event("LiveValuesEvent", 11, 1.8),
+        );
+        let changed = changed_events(&events, &["LiveValuesEvent".to_string()], &mut forwarded);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].data_json, r#"{"diameter":1.8}"#);
+    }
+
+    #[test]
+    fn test_machine_id() {
+        let machine = machine_identification_unique(Some(MachineId {
+            vendor: 1,
+            machine: 2,
+            serial: 3,
+        }))
+        .ok()
+        .unwrap();
+        assert_eq!(MachineId::from(&machine).serial, 3);
+
+        assert!(machine_identification_unique(None).is_err());
+        assert!(
+            machine_identification_unique(Some(MachineId {
+                vendor: 1,
+                machine: 2,
+                serial: 70000,
+            }))
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use crate::{
+    app_state::AppState,
+    history::{HistorySample, init::collect_new_events},
+    panic::{PanicDetails, send_panic},
+    storage,
+};
+use control_core::machines::identification::MachineIdentificationUnique;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use smol::channel::Sender;
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
+
+/// File inside [`storage::data_dir`] configuring the exporter
+///
+/// The exporter only runs if the file exists.
+pub const INFLUXDB_FILE: &str = "influxdb.json";
+
+/// Lines kept while the endpoint is unreachable, older lines are dropped first
+const MAX_PENDING_LINES: usize = 100_000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InfluxDbConfig {
+    /// Full write URL, e.g. `http://localhost:8086/api/v2/write?org=qitech&bucket=control`
+    /// or `http://localhost:8086/write?db=control` for InfluxDB 1.x
+    ///
+    /// Only plain http is supported, TLS has to be terminated by a proxy.
+    pub url: String,
+    /// Sent as `Authorization: Token <token>`
+    #[serde(default)]
+    pub token: Option<String>,
+    #[serde(default = "default_interval_ms")]
+    pub interval_ms: u64,
+    /// Machine namespace events that are exported
+    #[serde(default = "default_events")]
+    pub events: Vec<String>,
+    /// Extra tags per machine, keyed by `vendor/machine/serial`
+    #[serde(default)]
+    pub tags: HashMap<String, BTreeMap<String, String>>,
+}
+
+const fn default_interval_ms() -> u64 {
+    1000
+}
+
+fn default_events() -> Vec<String> {
+    vec!["LiveValuesEvent".to_string(), "StateEvent".to_string()]
+}
+
+/// Starts the exporter if it is configured
+pub fn init_influxdb(
+    thread_panic_tx: Sender<PanicDetails>,
+    app_state: Arc<AppState>,
+) -> Result<(), anyhow::Error> {
+    let Some(config) =
+        storage::read_json::<InfluxDbConfig>(&storage::data_dir().join(INFLUXDB_FILE))?
+    else {
+        return Ok(());
+    };
+    tracing::info!("Exporting to InfluxDB at {}", config.url);
+
+    std::thread::Builder::new()
+        .name("influxdb".to_owned())
+        .spawn(move || {
+            send_panic(thread_panic_tx);
+            smol::block_on(async {
+                let mut exported_until = HashMap::new();
+                let mut pending = VecDeque::new();
+                loop {
+                    smol::Timer::after(Duration::from_millis(config.interval_ms)).await;
+
+                    let series =
+                        collect_new_events(&app_state, &config.events, &mut exported_until).await;
+                    for ((machine, event), samples) in series {
+                        let tags = config.tags.get(&machine.to_string());
+                        for sample in samples {
+                            if let Some(line) = to_line(&machine, &event, tags, &sample) {
+                                pending.push_back(line);
+                            }
+                        }
+                    }
+                    while pending.len() > MAX_PENDING_LINES {
+                        pending.pop_front();
+                    }
+                    if pending.is_empty() {
+                        continue;
+                    }
+
+                    let body = pending.iter().cloned().collect::<Vec<_>>().join("\n");
+                    match write(&config, body) {
+                        Ok(()) => pending.clear(),
+                        Err(e) => tracing::warn!(
+                            "Failed to export {} lines to InfluxDB: {:?}",
+                            pending.len(),
+                            e
+                        ),
+                    }
+                }
+            });
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_influxdb] Failed to spawn influxdb thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    Ok(())
+}
+
+fn write(config: &InfluxDbConfig, body: String) -> Result<(), anyhow::Error> {
+    let mut request = ureq::post(&config.url).header("Content-Type", "text/plain; charset=utf-8");
+    if let Some(token) = &config.token {
+        request = request.header("Authorization", &format!("Token {}", token));
+    }
+    request.send(body)?;
+    Ok(())
+}
+
+/// Formats a sample as line protocol
+///
+/// The measurement is the event name, nested fields are joined with `_`.
+/// `null` fields (e.g. no measurement) are left out, `None` if no field is left.
+fn to_line(
+    machine: &MachineIdentificationUnique,
+    event: &str,
+    tags: Option<&BTreeMap<String, String>>,
+    sample: &HistorySample,
+) -> Option<String> {
+    let mut line = escape(event, &[',', ' ']);
+    line.push_str(&format!(
+        ",vendor={},machine={},serial={}",
+        machine.machine_identification.vendor,
+        machine.machine_identification.machine,
+        machine.serial
+    ));
+    for (key, value) in tags.into_iter().flatten() {
+        line.push_str(&format!(
+            ",{}={}",
+            escape(key, &[',', '=', ' ']),
+            escape(value, &[',', '=', ' '])
+        ));
+    }
+
+    let mut fields = vec![];
+    flatten_fields("", &sample.data, &mut fields);
+    if fields.is_empty() {
+        return None;
+    }
+    line.push(' ');
+    line.push_str(&fields.join(","));
+
+    // nanosecond precision is the default of every line protocol endpoint
+    line.push_str(&format!(" {}", sample.ts * 1_000_000));
+    Some(line)
+}
+
+fn flatten_fields(prefix: &str, value: &Value, fields: &mut Vec<String>) {
+    let key = escape(prefix, &[',', '=', ' ']);
+    match value {
+        Value::Object(object) => {
+            for (name, value) in object {
+                let name = if prefix.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}_{}", prefix, name)
+                };
+                flatten_fields(&name, value, fields);
+            }
+        }
+        Value::Number(number) => match number.as_f64() {
+            Some(number) if number.is_finite() => fields.push(format!("{}={}", key, number)),
+            _ => {}
+        },
+        Value::Bool(bool) => fields.push(format!("{}={}", key, bool)),
+        Value::String(string) => {
+            fields.push(format!("{}=\"{}\"", key, escape(string, &['"', '\\'])));
+        }
+        Value::Null | Value::Array(_) => {}
+    }
+}
+
+fn escape(value: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for char in value.chars() {
+        if special.contains(&char) {
+            escaped.push('\\');
+        }
+        escaped.push(char);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use control_core::machines::identification::MachineIdentification;
+    use serde_json::json;
+
+    #[test]
+    fn test_to_line() {
+        let machine = MachineIdentificationUnique {
+            machine_identification: MachineIdentification {
+                vendor: 1,
+                machine: 6,
+            },
+            serial: 42,
+        };
+        let tags = BTreeMap::from([("line".to_string(), "hall 2".to_string())]);
+        let sample = HistorySample {
+            ts: 1_000,
+            data: json!({
+                "diameter": 1.75,
+                "x": null,
+                "mode": "Wind",
+                "puller": { "forward": true },
+            }),
+        };
+
+        assert_eq!(
+            to_line(&machine, "LiveValuesEvent", Some(&tags), &sample).unwrap(),
+            "LiveValuesEvent,vendor=1,machine=6,serial=42,line=hall\\ 2 \
+            diameter=1.75,mode=\"Wind\",puller_forward=true 1000000000"
+        );
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let config: InfluxDbConfig =
+            serde_json::from_value(json!({ "url": "http://localhost:8086/write?db=control" }))
+                .unwrap();
+        assert_eq!(config.interval_ms, 1000);
+        assert_eq!(config.events, default_events());
+        assert!(config.tags.is_empty());
+    }
+}
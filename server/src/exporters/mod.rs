@@ -0,0 +1,6 @@
+pub mod grpc;
+pub mod influxdb;
+pub mod modbus;
+pub mod mqtt;
+pub mod opcua;
+pub mod prometheus;
@@ -0,0 +1,620 @@
+use crate::{
+    app_state::AppState,
+    machines::{VENDOR_QITECH, machine_from_slug},
+    panic::{PanicDetails, send_panic},
+    rest::handlers::machine_mutation::mutate_machine,
+    storage,
+};
+use control_core::{
+    machines::identification::{MachineIdentification, MachineIdentificationUnique},
+    rest::mutation::MachineMutationBody,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use smol::{
+    channel::Sender,
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// File inside [`storage::data_dir`] configuring the register map
+///
+/// The slave only runs if the file exists.
+pub const MODBUS_FILE: &str = "modbus.json";
+
+const READ_COILS: u8 = 0x01;
+const READ_HOLDING_REGISTERS: u8 = 0x03;
+const READ_INPUT_REGISTERS: u8 = 0x04;
+const WRITE_SINGLE_COIL: u8 = 0x05;
+const WRITE_SINGLE_REGISTER: u8 = 0x06;
+const WRITE_MULTIPLE_COILS: u8 = 0x0F;
+const WRITE_MULTIPLE_REGISTERS: u8 = 0x10;
+
+const ILLEGAL_FUNCTION: u8 = 0x01;
+const ILLEGAL_DATA_ADDRESS: u8 = 0x02;
+const ILLEGAL_DATA_VALUE: u8 = 0x03;
+const SERVER_DEVICE_FAILURE: u8 = 0x04;
+const GATEWAY_TARGET_FAILED: u8 = 0x0B;
+
+/// Protocol limits for the quantity of a single request
+const MAX_READ_COILS: u16 = 2000;
+const MAX_READ_REGISTERS: u16 = 125;
+const MAX_WRITE_COILS: u16 = 1968;
+const MAX_WRITE_REGISTERS: u16 = 123;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ModbusConfig {
+    #[serde(default = "default_host")]
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub machines: Vec<ModbusMachineConfig>,
+}
+
+/// Register map of a machine, addressed by the unit id of a request
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ModbusMachineConfig {
+    pub unit_id: u8,
+    /// Machine type, e.g. `laser`
+    pub machine: String,
+    pub serial: u16,
+    /// Setpoints, read from the state event and written through `mutation`
+    #[serde(default)]
+    pub holding_registers: Vec<HoldingRegister>,
+    /// Read only values, usually from the live values event
+    #[serde(default)]
+    pub input_registers: Vec<InputRegister>,
+    /// Enable flags, read from the state event and written through `mutation`
+    #[serde(default)]
+    pub coils: Vec<Coil>,
+}
+
+/// Registers hold `round(value * scale)` as signed 16 bit integer
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HoldingRegister {
+    pub address: u16,
+    /// `.`-separated path into the state event, e.g. `laser_state.target_diameter`
+    pub path: String,
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    /// Mutation the written `value / scale` is sent as, e.g. `SetTargetDiameter`
+    pub mutation: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InputRegister {
+    pub address: u16,
+    #[serde(default = "default_input_event")]
+    pub event: String,
+    /// `.`-separated path into `event`, e.g. `diameter`
+    pub path: String,
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Coil {
+    pub address: u16,
+    /// `.`-separated path to a bool in the state event
+    pub path: String,
+    pub mutation: String,
+}
+
+fn default_host() -> String {
+    "0.0.0.0".to_string()
+}
+
+const fn default_port() -> u16 {
+    502
+}
+
+const fn default_scale() -> f64 {
+    1.0
+}
+
+fn default_input_event() -> String {
+    "LiveValuesEvent".to_string()
+}
+
+impl ModbusMachineConfig {
+    fn machine_identification_unique(&self) -> Option<MachineIdentificationUnique> {
+        Some(MachineIdentificationUnique {
+            machine_identification: MachineIdentification {
+                vendor: VENDOR_QITECH,
+                machine: machine_from_slug(&self.machine)?,
+            },
+            serial: self.serial,
+        })
+    }
+}
+
+/// Starts the slave if it is configured
+pub fn init_modbus(
+    thread_panic_tx: Sender<PanicDetails>,
+    app_state: Arc<AppState>,
+) -> Result<(), anyhow::Error> {
+    let Some(config) = storage::read_json::<ModbusConfig>(&storage::data_dir().join(MODBUS_FILE))?
+    else {
+        return Ok(());
+    };
+
+    let mut units = HashMap::new();
+    for machine in config.machines {
+        let identification = machine.machine_identification_unique().ok_or_else(|| {
+            anyhow::anyhow!(
+                "[{}::init_modbus] Unknown machine {:?}",
+                module_path!(),
+                machine.machine
+            )
+        })?;
+        if units.contains_key(&machine.unit_id) {
+            return Err(anyhow::anyhow!(
+                "[{}::init_modbus] Unit id {} is used twice",
+                module_path!(),
+                machine.unit_id
+            ));
+        }
+        units.insert(machine.unit_id, (identification, machine));
+    }
+    let units = Arc::new(units);
+    let address = format!("{}:{}", config.host, config.port);
+    tracing::info!("Serving Modbus TCP on {}", address);
+
+    std::thread::Builder::new()
+        .name("modbus".to_owned())
+        .spawn(move || {
+            send_panic(thread_panic_tx);
+            smol::block_on(async {
+                let listener = match TcpListener::bind(&address).await {
+                    Ok(listener) => listener,
+                    Err(e) => {
+                        tracing::error!("Failed to bind Modbus TCP to {}: {:?}", address, e);
+                        return;
+                    }
+                };
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, peer)) => {
+                            tracing::debug!("Modbus TCP client connected from {}", peer);
+                            smol::spawn(serve(stream, app_state.clone(), units.clone())).detach();
+                        }
+                        Err(e) => tracing::warn!("Failed to accept Modbus TCP client: {:?}", e),
+                    }
+                }
+            });
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_modbus] Failed to spawn modbus thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    Ok(())
+}
+
+type Units = HashMap<u8, (MachineIdentificationUnique, ModbusMachineConfig)>;
+
+/// Answers the requests of a client until it disconnects
+async fn serve(mut stream: TcpStream, app_state: Arc<AppState>, units: Arc<Units>) {
+    loop {
+        // MBAP header: transaction id, protocol id, length, unit id
+        let mut header = [0u8; 7];
+        if stream.read_exact(&mut header).await.is_err() {
+            return;
+        }
+        let length = u16::from_be_bytes([header[4], header[5]]) as usize;
+        if u16::from_be_bytes([header[2], header[3]]) != 0 || !(2..=254).contains(&length) {
+            return;
+        }
+        let mut pdu = vec![0u8; length - 1];
+        if stream.read_exact(&mut pdu).await.is_err() {
+            return;
+        }
+
+        let unit_id = header[6];
+        let response = match units.get(&unit_id) {
+            Some((machine, config)) => respond(&app_state, machine, config, &pdu).await,
+            None => exception(pdu[0], GATEWAY_TARGET_FAILED),
+        };
+
+        let mut frame = Vec::with_capacity(7 + response.len());
+        frame.extend_from_slice(&header[0..4]);
+        frame.extend_from_slice(&(response.len() as u16 + 1).to_be_bytes());
+        frame.push(unit_id);
+        frame.extend_from_slice(&response);
+        if stream.write_all(&frame).await.is_err() {
+            return;
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Request {
+    ReadCoils { address: u16, count: u16 },
+    ReadHoldingRegisters { address: u16, count: u16 },
+    ReadInputRegisters { address: u16, count: u16 },
+    WriteCoils { address: u16, values: Vec<bool> },
+    WriteRegisters { address: u16, values: Vec<u16> },
+}
+
+/// Parses a request PDU, errors are exception codes
+fn parse_request(pdu: &[u8]) -> Result<Request, u8> {
+    let word = |i: usize| {
+        pdu.get(i..i + 2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+            .ok_or(ILLEGAL_DATA_VALUE)
+    };
+    let address = word(1)?;
+    let quantity = word(3)?;
+    let read_count = |max: u16| {
+        if (1..=max).contains(&quantity) {
+            Ok(quantity)
+        } else {
+            Err(ILLEGAL_DATA_VALUE)
+        }
+    };
+
+    match pdu[0] {
+        READ_COILS => Ok(Request::ReadCoils {
+            address,
+            count: read_count(MAX_READ_COILS)?,
+        }),
+        READ_HOLDING_REGISTERS => Ok(Request::ReadHoldingRegisters {
+            address,
+            count: read_count(MAX_READ_REGISTERS)?,
+        }),
+        READ_INPUT_REGISTERS => Ok(Request::ReadInputRegisters {
+            address,
+            count: read_count(MAX_READ_REGISTERS)?,
+        }),
+        WRITE_SINGLE_COIL => match quantity {
+            0xFF00 => Ok(Request::WriteCoils {
+                address,
+                values: vec![true],
+            }),
+            0x0000 => Ok(Request::WriteCoils {
+                address,
+                values: vec![false],
+            }),
+            _ => Err(ILLEGAL_DATA_VALUE),
+        },
+        WRITE_SINGLE_REGISTER => Ok(Request::WriteRegisters {
+            address,
+            values: vec![quantity],
+        }),
+        WRITE_MULTIPLE_COILS => {
+            let count = read_count(MAX_WRITE_COILS)?;
+            let bytes = pdu.get(6..).ok_or(ILLEGAL_DATA_VALUE)?;
+            if pdu[5] as usize != count.div_ceil(8) as usize || bytes.len() != pdu[5] as usize {
+                return Err(ILLEGAL_DATA_VALUE);
+            }
+            let values = (0..count as usize)
+                .map(|i| bytes[i / 8] & (1 << (i % 8)) != 0)
+                .collect();
+            Ok(Request::WriteCoils { address, values })
+        }
+        WRITE_MULTIPLE_REGISTERS => {
+            let count = read_count(MAX_WRITE_REGISTERS)?;
+            let bytes = pdu.get(6..).ok_or(ILLEGAL_DATA_VALUE)?;
+            if pdu[5] as usize != count as usize * 2 || bytes.len() != pdu[5] as usize {
+                return Err(ILLEGAL_DATA_VALUE);
+            }
+            let values = bytes
+                .chunks_exact(2)
+                .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+                .collect();
+            Ok(Request::WriteRegisters { address, values })
+        }
+        _ => Err(ILLEGAL_FUNCTION),
+    }
+}
+
+fn exception(function: u8, code: u8) -> Vec<u8> {
+    vec![function | 0x80, code]
+}
+
+fn coils_response(values: &[bool]) -> Vec<u8> {
+    let mut bytes = vec![0u8; values.len().div_ceil(8)];
+    for (i, value) in values.iter().enumerate() {
+        if *value {
+            bytes[i / 8] |= 1 << (i % 8);
+        }
+    }
+    let mut response = vec![READ_COILS, bytes.len() as u8];
+    response.extend(bytes);
+    response
+}
+
+fn registers_response(function: u8, values: &[u16]) -> Vec<u8> {
+    let mut response = vec![function, (values.len() * 2) as u8];
+    for value in values {
+        response.extend_from_slice(&value.to_be_bytes());
+    }
+    response
+}
+
+/// Answers a request PDU for a machine
+///
+/// Unmapped addresses inside a read read as zero, writes to them are rejected.
+async fn respond(
+    app_state: &Arc<AppState>,
+    machine: &MachineIdentificationUnique,
+    config: &ModbusMachineConfig,
+    pdu: &[u8],
+) -> Vec<u8> {
+    let function = pdu[0];
+    let request = match parse_request(pdu) {
+        Ok(request) => request,
+        Err(code) => return exception(function, code),
+    };
+
+    match request {
+        Request::ReadCoils { address, count } => {
+            let Some(state) = latest_event(app_state, machine, "StateEvent").await else {
+                return exception(function, SERVER_DEVICE_FAILURE);
+            };
+            let values: Vec<_> = addresses(address, count)
+                .map(|address| {
+                    config
+                        .coils
+                        .iter()
+                        .find(|coil| coil.address == address)
+                        .and_then(|coil| lookup(&state, &coil.path))
+                        .and_then(Value::as_bool)
+                        .unwrap_or(false)
+                })
+                .collect();
+            coils_response(&values)
+        }
+        Request::ReadHoldingRegisters { address, count } => {
+            let Some(state) = latest_event(app_state, machine, "StateEvent").await else {
+                return exception(function, SERVER_DEVICE_FAILURE);
+            };
+            let values: Vec<_> = addresses(address, count)
+                .map(|address| {
+                    config
+                        .holding_registers
+                        .iter()
+                        .find(|register| register.address == address)
+                        .and_then(|register| {
+                            lookup(&state, &register.path)
+                                .and_then(Value::as_f64)
+                                .map(|value| to_register(value, register.scale))
+                        })
+                        .unwrap_or(0)
+                })
+                .collect();
+            registers_response(function, &values)
+        }
+        Request::ReadInputRegisters { address, count } => {
+            let mut events = HashMap::new();
+            let mut values = vec![];
+            for address in addresses(address, count) {
+                let Some(register) = config
+                    .input_registers
+                    .iter()
+                    .find(|register| register.address == address)
+                else {
+                    values.push(0);
+                    continue;
+                };
+                if !events.contains_key(&register.event) {
+                    let event = latest_event(app_state, machine, &register.event).await;
+                    events.insert(register.event.clone(), event);
+                }
+                let value = events[&register.event]
+                    .as_ref()
+                    .and_then(|event| lookup(event, &register.path))
+                    .and_then(Value::as_f64)
+                    .map(|value| to_register(value, register.scale))
+                    .unwrap_or(0);
+                values.push(value);
+            }
+            registers_response(function, &values)
+        }
+        Request::WriteCoils { address, values } => {
+            let mut mutations = vec![];
+            for (address, value) in addresses(address, values.len() as u16).zip(values) {
+                let Some(coil) = config.coils.iter().find(|coil| coil.address == address) else {
+                    return exception(function, ILLEGAL_DATA_ADDRESS);
+                };
+                mutations.push((coil.mutation.clone(), Value::from(value)));
+            }
+            write_response(app_state, machine, pdu, mutations).await
+        }
+        Request::WriteRegisters { address, values } => {
+            let mut mutations = vec![];
+            for (address, raw) in addresses(address, values.len() as u16).zip(values) {
+                let Some(register) = config
+                    .holding_registers
+                    .iter()
+                    .find(|register| register.address == address)
+                else {
+                    return exception(function, ILLEGAL_DATA_ADDRESS);
+                };
+                mutations.push((
+                    register.mutation.clone(),
+                    from_register(raw, register.scale),
+                ));
+            }
+            write_response(app_state, machine, pdu, mutations).await
+        }
+    }
+}
+
+/// Applies the mutations of a write request in address order
+///
+/// Single writes echo the request, multiple writes echo address and quantity.
+async fn write_response(
+    app_state: &Arc<AppState>,
+    machine: &MachineIdentificationUnique,
+    pdu: &[u8],
+    mutations: Vec<(String, Value)>,
+) -> Vec<u8> {
+    for (mutation, value) in mutations {
+        let mut data = Map::new();
+        data.insert(mutation, value);
+        let body = MachineMutationBody {
+            machine_identification_unique: machine.clone(),
+            data: Value::Object(data),
+        };
+        if let Err(e) = mutate_machine(app_state, body).await {
+            tracing::warn!("Modbus write to {} failed: {:?}", machine, e);
+            return exception(pdu[0], SERVER_DEVICE_FAILURE);
+        }
+    }
+    pdu[0..5].to_vec()
+}
+
+fn addresses(address: u16, count: u16) -> impl Iterator<Item = u16> {
+    (0..count).map(move |offset| address.wrapping_add(offset))
+}
+
+/// Latest emitted event of a connected machine
+async fn latest_event(
+    app_state: &Arc<AppState>,
+    machine: &MachineIdentificationUnique,
+    event: &str,
+) -> Option<Value> {
+    let snapshot = {
+        let machines = app_state.machines.read().await;
+        let slot = machines.get(machine)?;
+        let slot = slot.lock_blocking();
+        if !slot.is_connected() {
+            return None;
+        }
+        slot.snapshot.load()
+    };
+    let last = snapshot.get(event)?;
+    serde_json::to_value(&last.data).ok()
+}
+
+/// Value at a `.`-separated path
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |value, key| value.get(key))
+}
+
+fn to_register(value: f64, scale: f64) -> u16 {
+    (value * scale)
+        .round()
+        .clamp(i16::MIN as f64, i16::MAX as f64) as i16 as u16
+}
+
+/// Mutation payload of a written register, integral values are sent as integers
+fn from_register(raw: u16, scale: f64) -> Value {
+    let value = raw as i16 as f64 / scale;
+    if value.fract() == 0.0 {
+        Value::from(value as i64)
+    } else {
+        Value::from(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_read_request() {
+        assert_eq!(
+            parse_request(&[READ_HOLDING_REGISTERS, 0x00, 0x10, 0x00, 0x02]),
+            Ok(Request::ReadHoldingRegisters {
+                address: 16,
+                count: 2
+            })
+        );
+        assert_eq!(
+            parse_request(&[READ_INPUT_REGISTERS, 0x00, 0x00, 0x00, 0x00]),
+            Err(ILLEGAL_DATA_VALUE)
+        );
+        assert_eq!(
+            parse_request(&[0x2B, 0x00, 0x00, 0x00, 0x01]),
+            Err(ILLEGAL_FUNCTION)
+        );
+        assert_eq!(parse_request(&[READ_COILS, 0x00]), Err(ILLEGAL_DATA_VALUE));
+    }
+
+    #[test]
+    fn test_parse_write_request() {
+        assert_eq!(
+            parse_request(&[WRITE_SINGLE_COIL, 0x00, 0x03, 0xFF, 0x00]),
+            Ok(Request::WriteCoils {
+                address: 3,
+                values: vec![true]
+            })
+        );
+        assert_eq!(
+            parse_request(&[WRITE_SINGLE_COIL, 0x00, 0x03, 0x12, 0x34]),
+            Err(ILLEGAL_DATA_VALUE)
+        );
+        assert_eq!(
+            parse_request(&[WRITE_MULTIPLE_COILS, 0x00, 0x00, 0x00, 0x03, 0x01, 0b101]),
+            Ok(Request::WriteCoils {
+                address: 0,
+                values: vec![true, false, true]
+            })
+        );
+        assert_eq!(
+            parse_request(&[
+                WRITE_MULTIPLE_REGISTERS,
+                0x00,
+                0x01,
+                0x00,
+                0x02,
+                0x04,
+                0x06,
+                0xD6,
+                0xFF,
+                0xFF
+            ]),
+            Ok(Request::WriteRegisters {
+                address: 1,
+                values: vec![1750, 0xFFFF]
+            })
+        );
+        assert_eq!(
+            parse_request(&[WRITE_MULTIPLE_REGISTERS, 0x00, 0x01, 0x00, 0x02, 0x04, 0x06]),
+            Err(ILLEGAL_DATA_VALUE)
+        );
+    }
+
+    #[test]
+    fn test_responses() {
+        assert_eq!(
+            coils_response(&[true, false, false, false, false, false, false, false, true]),
+            vec![READ_COILS, 2, 0b0000_0001, 0b0000_0001]
+        );
+        assert_eq!(
+            registers_response(READ_HOLDING_REGISTERS, &[1750, 1]),
+            vec![READ_HOLDING_REGISTERS, 4, 0x06, 0xD6, 0x00, 0x01]
+        );
+        assert_eq!(
+            exception(READ_COILS, ILLEGAL_DATA_ADDRESS),
+            vec![0x81, 0x02]
+        );
+    }
+
+    #[test]
+    fn test_register_scaling() {
+        assert_eq!(to_register(1.75, 1000.0), 1750);
+        assert_eq!(to_register(-2.0, 10.0), (-20i16) as u16);
+        assert_eq!(to_register(1e9, 1.0), i16::MAX as u16);
+        assert_eq!(from_register(1750, 1000.0), json!(1.75));
+        assert_eq!(from_register(5, 1.0), json!(5));
+        assert_eq!(from_register((-20i16) as u16, 10.0), json!(-2));
+    }
+
+    #[test]
+    fn test_lookup() {
+        let state = json!({ "laser_state": { "target_diameter": 1.75 } });
+        assert_eq!(
+            lookup(&state, "laser_state.target_diameter"),
+            Some(&json!(1.75))
+        );
+        assert_eq!(lookup(&state, "laser_state.missing"), None);
+    }
+}
@@ -0,0 +1,293 @@
+use crate::{
+    app_state::AppState,
+    history::init::collect_new_events,
+    machines::{VENDOR_QITECH, machine_from_slug, machine_slug},
+    panic::{PanicDetails, send_panic},
+    rest::handlers::machine_mutation::mutate_machine,
+    storage,
+};
+use control_core::{
+    machines::identification::{MachineIdentification, MachineIdentificationUnique},
+    rest::mutation::{MachineMutationBody, MutationErrorKind, MutationResponse},
+};
+use rumqttc::{Client, Connection, Event, LastWill, MqttOptions, Packet, Publish, QoS};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use smol::channel::Sender;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+/// File inside [`storage::data_dir`] configuring the bridge
+///
+/// The bridge only runs if the file exists.
+pub const MQTT_FILE: &str = "mqtt.json";
+
+/// Machine namespace events that are published and the topic suffix they are published on
+const PUBLISHED_EVENTS: &[(&str, &str)] = &[("LiveValuesEvent", "live"), ("StateEvent", "state")];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MqttConfig {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default = "default_client_id")]
+    pub client_id: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// First level of all topics
+    #[serde(default = "default_topic_prefix")]
+    pub topic_prefix: String,
+    /// Interval the newest events are published in
+    #[serde(default = "default_interval_ms")]
+    pub interval_ms: u64,
+}
+
+const fn default_port() -> u16 {
+    1883
+}
+
+fn default_client_id() -> String {
+    "qitech-control".to_string()
+}
+
+fn default_topic_prefix() -> String {
+    "qitech".to_string()
+}
+
+const fn default_interval_ms() -> u64 {
+    500
+}
+
+impl MqttConfig {
+    /// Topic of a machine, e.g. `qitech/42/laser`
+    fn machine_topic(&self, machine: &MachineIdentificationUnique) -> Option<String> {
+        let slug = machine_slug(machine.machine_identification.machine)?;
+        Some(format!("{}/{}/{}", self.topic_prefix, machine.serial, slug))
+    }
+
+    /// Machine addressed by a command topic `<prefix>/<serial>/<machine>/mutate`
+    fn parse_mutate_topic(&self, topic: &str) -> Option<MachineIdentificationUnique> {
+        let rest = topic.strip_prefix(&self.topic_prefix)?.strip_prefix('/')?;
+        let mut levels = rest.split('/');
+        let serial = levels.next()?.parse().ok()?;
+        let machine = machine_from_slug(levels.next()?)?;
+        if levels.next()? != "mutate" || levels.next().is_some() {
+            return None;
+        }
+        Some(MachineIdentificationUnique {
+            machine_identification: MachineIdentification {
+                vendor: VENDOR_QITECH,
+                machine,
+            },
+            serial,
+        })
+    }
+}
+
+/// Starts the bridge if it is configured
+pub fn init_mqtt(
+    thread_panic_tx: Sender<PanicDetails>,
+    app_state: Arc<AppState>,
+) -> Result<(), anyhow::Error> {
+    let Some(config) = storage::read_json::<MqttConfig>(&storage::data_dir().join(MQTT_FILE))?
+    else {
+        return Ok(());
+    };
+    tracing::info!("Bridging to MQTT broker {}:{}", config.host, config.port);
+
+    let status_topic = format!("{}/status", config.topic_prefix);
+    let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+    options.set_keep_alive(Duration::from_secs(10));
+    options.set_last_will(LastWill::new(
+        &status_topic,
+        "offline",
+        QoS::AtLeastOnce,
+        true,
+    ));
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        options.set_credentials(username, password);
+    }
+    let (client, connection) = Client::new(options, 100);
+    let config = Arc::new(config);
+
+    std::thread::Builder::new()
+        .name("mqtt-connection".to_owned())
+        .spawn({
+            let thread_panic_tx = thread_panic_tx.clone();
+            let app_state = app_state.clone();
+            let client = client.clone();
+            let config = config.clone();
+            move || {
+                send_panic(thread_panic_tx);
+                handle_connection(connection, &client, &config, &app_state, &status_topic);
+            }
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_mqtt] Failed to spawn mqtt connection thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    std::thread::Builder::new()
+        .name("mqtt".to_owned())
+        .spawn(move || {
+            send_panic(thread_panic_tx);
+            smol::block_on(publish_events(&client, &config, &app_state));
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_mqtt] Failed to spawn mqtt thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Drives the connection and executes the received commands
+///
+/// rumqttc reconnects on the next iteration after an error.
+fn handle_connection(
+    mut connection: Connection,
+    client: &Client,
+    config: &MqttConfig,
+    app_state: &Arc<AppState>,
+    status_topic: &str,
+) {
+    for notification in connection.iter() {
+        match notification {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                tracing::info!("Connected to MQTT broker");
+                let _ = client.try_publish(status_topic, QoS::AtLeastOnce, true, "online");
+                let _ = client.try_subscribe(
+                    format!("{}/+/+/mutate", config.topic_prefix),
+                    QoS::AtLeastOnce,
+                );
+            }
+            Ok(Event::Incoming(Packet::Publish(publish))) => {
+                handle_command(client, config, app_state, &publish);
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!("MQTT connection error: {:?}", e);
+                std::thread::sleep(Duration::from_secs(5));
+            }
+        }
+    }
+}
+
+/// Runs a command through the same mutation handler as the REST API
+/// and publishes the result on `<topic>/result`
+fn handle_command(
+    client: &Client,
+    config: &MqttConfig,
+    app_state: &Arc<AppState>,
+    publish: &Publish,
+) {
+    let Some(machine_identification_unique) = config.parse_mutate_topic(&publish.topic) else {
+        tracing::warn!("Ignoring MQTT message on unknown topic {}", publish.topic);
+        return;
+    };
+
+    let response = match serde_json::from_slice(&publish.payload) {
+        Ok(data) => match smol::block_on(mutate_machine(
+            app_state,
+            MachineMutationBody {
+                machine_identification_unique,
+                data,
+            },
+        )) {
+            Ok(()) => MutationResponse::success(),
+            Err(e) => MutationResponse::error(e.kind(), e.to_string()),
+        },
+        Err(e) => MutationResponse::error(
+            MutationErrorKind::Validation,
+            format!("[{}::handle_command] Invalid JSON: {}", module_path!(), e),
+        ),
+    };
+    let _ = client.try_publish(
+        format!("{}/result", publish.topic),
+        QoS::AtLeastOnce,
+        false,
+        json!(response).to_string(),
+    );
+}
+
+/// Publishes the newest events of every machine
+///
+/// State events are retained so new subscribers get the current state immediately.
+async fn publish_events(client: &Client, config: &MqttConfig, app_state: &Arc<AppState>) {
+    let events: Vec<_> = PUBLISHED_EVENTS.iter().map(|(event, _)| *event).collect();
+    let mut published_until = HashMap::new();
+    loop {
+        smol::Timer::after(Duration::from_millis(config.interval_ms)).await;
+
+        let series = collect_new_events(app_state, &events, &mut published_until).await;
+        for ((machine, event), samples) in series {
+            let (Some(topic), Some(last)) = (config.machine_topic(&machine), samples.last()) else {
+                continue;
+            };
+            let Some((_, suffix)) = PUBLISHED_EVENTS.iter().find(|(name, _)| *name == event) else {
+                continue;
+            };
+            let retain = *suffix == "state";
+            let payload = json!({ "ts": last.ts, "data": last.data }).to_string();
+            if let Err(e) = client.try_publish(
+                format!("{}/{}", topic, suffix),
+                QoS::AtMostOnce,
+                retain,
+                payload,
+            ) {
+                tracing::debug!("Failed to publish to MQTT: {:?}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machines::MACHINE_LASER_V1;
+
+    fn config() -> MqttConfig {
+        serde_json::from_value(json!({ "host": "localhost" })).unwrap()
+    }
+
+    fn laser() -> MachineIdentificationUnique {
+        MachineIdentificationUnique {
+            machine_identification: MachineIdentification {
+                vendor: VENDOR_QITECH,
+                machine: MACHINE_LASER_V1,
+            },
+            serial: 42,
+        }
+    }
+
+    #[test]
+    fn test_machine_topic() {
+        assert_eq!(
+            config().machine_topic(&laser()),
+            Some("qitech/42/laser".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_mutate_topic() {
+        let config = config();
+        assert_eq!(
+            config.parse_mutate_topic("qitech/42/laser/mutate"),
+            Some(laser())
+        );
+        assert_eq!(config.parse_mutate_topic("qitech/42/laser/live"), None);
+        assert_eq!(config.parse_mutate_topic("qitech/42/unknown/mutate"), None);
+        assert_eq!(config.parse_mutate_topic("other/42/laser/mutate"), None);
+        assert_eq!(
+            config.parse_mutate_topic("qitech/42/laser/mutate/result"),
+            None
+        );
+    }
+}
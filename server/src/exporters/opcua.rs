@@ -0,0 +1,505 @@
+use crate::{
+    app_state::AppState,
+    history::init::collect_new_events,
+    machines::{
+        MACHINE_AQUAPATH_V1, MACHINE_EXTRUDER_V1, MACHINE_LASER_V1, MACHINE_MOCK,
+        MACHINE_WINDER_V1, machine_slug,
+    },
+    panic::{PanicDetails, send_panic},
+    rest::handlers::machine_mutation::mutate_machine,
+    storage,
+};
+use control_core::{
+    machines::identification::MachineIdentificationUnique, rest::mutation::MachineMutationBody,
+};
+use opcua::server::prelude::{
+    AddressSpace, AttrFnSetter, AttributeSetter, DataTypeId, DateTime, NodeId, ObjectId,
+    ServerBuilder, StatusCode, VariableBuilder, Variant,
+};
+use opcua::sync::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use smol::channel::Sender;
+use std::{collections::HashMap, path::PathBuf, sync::Arc, time::Duration};
+
+/// File inside [`storage::data_dir`] configuring the server
+///
+/// The server only runs if the file exists.
+pub const OPCUA_FILE: &str = "opcua.json";
+
+/// Namespace of all nodes created by the server
+const NAMESPACE_URI: &str = "urn:qitech:control";
+
+/// Machine namespace events that are exposed and the folder they are exposed in
+const EXPOSED_EVENTS: &[(&str, &str)] = &[("LiveValuesEvent", "Live"), ("StateEvent", "State")];
+
+/// State values that are writable and the mutation a write is mapped to
+///
+/// The written value becomes the mutation payload, e.g. writing `1.75` to
+/// `laser_state.target_diameter` mutates the laser with `{"SetTargetDiameter": 1.75}`.
+const SETPOINTS: &[(u16, &str, &str)] = &[
+    // winder
+    (MACHINE_WINDER_V1, "mode_state.mode", "SetMode"),
+    (
+        MACHINE_WINDER_V1,
+        "traverse_state.limit_inner",
+        "SetTraverseLimitInner",
+    ),
+    (
+        MACHINE_WINDER_V1,
+        "traverse_state.limit_outer",
+        "SetTraverseLimitOuter",
+    ),
+    (
+        MACHINE_WINDER_V1,
+        "puller_state.regulation",
+        "SetPullerRegulationMode",
+    ),
+    (
+        MACHINE_WINDER_V1,
+        "puller_state.target_speed",
+        "SetPullerTargetSpeed",
+    ),
+    (
+        MACHINE_WINDER_V1,
+        "puller_state.forward",
+        "SetPullerForward",
+    ),
+    (
+        MACHINE_WINDER_V1,
+        "spool_automatic_action_state.spool_required_meters",
+        "SetSpoolAutomaticRequiredMeters",
+    ),
+    // extruder
+    (MACHINE_EXTRUDER_V1, "mode_state.mode", "SetExtruderMode"),
+    (
+        MACHINE_EXTRUDER_V1,
+        "regulation_state.uses_rpm",
+        "SetInverterRegulation",
+    ),
+    (
+        MACHINE_EXTRUDER_V1,
+        "screw_state.target_rpm",
+        "SetInverterTargetRpm",
+    ),
+    (
+        MACHINE_EXTRUDER_V1,
+        "pressure_state.target_bar",
+        "SetInverterTargetPressure",
+    ),
+    (
+        MACHINE_EXTRUDER_V1,
+        "heating_states.front.target_temperature",
+        "SetFrontHeatingTargetTemperature",
+    ),
+    (
+        MACHINE_EXTRUDER_V1,
+        "heating_states.back.target_temperature",
+        "SetBackHeatingTargetTemperature",
+    ),
+    (
+        MACHINE_EXTRUDER_V1,
+        "heating_states.middle.target_temperature",
+        "SetMiddleHeatingTemperature",
+    ),
+    (
+        MACHINE_EXTRUDER_V1,
+        "heating_states.nozzle.target_temperature",
+        "SetNozzleHeatingTemperature",
+    ),
+    (
+        MACHINE_EXTRUDER_V1,
+        "extruder_settings_state.pressure_limit",
+        "SetExtruderPressureLimit",
+    ),
+    (
+        MACHINE_EXTRUDER_V1,
+        "extruder_settings_state.pressure_limit_enabled",
+        "SetExtruderPressureLimitIsEnabled",
+    ),
+    // laser
+    (
+        MACHINE_LASER_V1,
+        "laser_state.target_diameter",
+        "SetTargetDiameter",
+    ),
+    (
+        MACHINE_LASER_V1,
+        "laser_state.lower_tolerance",
+        "SetLowerTolerance",
+    ),
+    (
+        MACHINE_LASER_V1,
+        "laser_state.higher_tolerance",
+        "SetHigherTolerance",
+    ),
+    (
+        MACHINE_LASER_V1,
+        "laser_state.min_max_timeframe_minutes",
+        "SetMinMaxTimeframe",
+    ),
+    // aquapath
+    (MACHINE_AQUAPATH_V1, "mode_state.mode", "SetAquaPathMode"),
+    (
+        MACHINE_AQUAPATH_V1,
+        "temperature_states.front.target_temperature",
+        "SetFrontTemperature",
+    ),
+    (
+        MACHINE_AQUAPATH_V1,
+        "temperature_states.back.target_temperature",
+        "SetBackTemperature",
+    ),
+    (
+        MACHINE_AQUAPATH_V1,
+        "flow_states.front.should_flow",
+        "SetFrontFlow",
+    ),
+    (
+        MACHINE_AQUAPATH_V1,
+        "flow_states.back.should_flow",
+        "SetBackFlow",
+    ),
+    // mock
+    (MACHINE_MOCK, "mode_state.mode", "SetMode"),
+    (MACHINE_MOCK, "frequency1", "SetFrequency1"),
+    (MACHINE_MOCK, "frequency2", "SetFrequency2"),
+    (MACHINE_MOCK, "frequency3", "SetFrequency3"),
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct OpcUaConfig {
+    #[serde(default = "default_host")]
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// Directory of the server certificate and trusted client certificates,
+    /// defaults to `opcua-pki` inside [`storage::data_dir`]
+    #[serde(default)]
+    pub pki_dir: Option<PathBuf>,
+    /// Interval the nodes are updated in
+    #[serde(default = "default_interval_ms")]
+    pub interval_ms: u64,
+}
+
+fn default_host() -> String {
+    "0.0.0.0".to_string()
+}
+
+const fn default_port() -> u16 {
+    4840
+}
+
+const fn default_interval_ms() -> u64 {
+    500
+}
+
+/// Starts the server if it is configured
+///
+/// Every machine gets a folder `<machine>-<serial>` below `Objects` holding
+/// its live values and state as variables, e.g. `laser-42/State/laser_state.target_diameter`.
+/// Only the anonymous, unencrypted endpoint is offered.
+pub fn init_opcua(
+    thread_panic_tx: Sender<PanicDetails>,
+    app_state: Arc<AppState>,
+) -> Result<(), anyhow::Error> {
+    let Some(config) = storage::read_json::<OpcUaConfig>(&storage::data_dir().join(OPCUA_FILE))?
+    else {
+        return Ok(());
+    };
+    tracing::info!("Serving OPC-UA on {}:{}", config.host, config.port);
+
+    let pki_dir = config
+        .pki_dir
+        .clone()
+        .unwrap_or_else(|| storage::data_dir().join("opcua-pki"));
+    let server = ServerBuilder::new_anonymous("QiTech Control")
+        .application_uri(NAMESPACE_URI)
+        .product_uri(NAMESPACE_URI)
+        .host_and_port(&config.host, config.port)
+        .pki_dir(pki_dir)
+        .create_sample_keypair(true)
+        .discovery_server_url(None)
+        .server()
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "[{}::init_opcua] Invalid OPC-UA server configuration",
+                module_path!()
+            )
+        })?;
+
+    let address_space = server.address_space();
+    let namespace = address_space
+        .write()
+        .register_namespace(NAMESPACE_URI)
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "[{}::init_opcua] Failed to register OPC-UA namespace",
+                module_path!()
+            )
+        })?;
+
+    std::thread::Builder::new()
+        .name("opcua".to_owned())
+        .spawn({
+            let thread_panic_tx = thread_panic_tx.clone();
+            move || {
+                send_panic(thread_panic_tx);
+                server.run();
+            }
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_opcua] Failed to spawn opcua thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    std::thread::Builder::new()
+        .name("opcua-sync".to_owned())
+        .spawn(move || {
+            send_panic(thread_panic_tx);
+            smol::block_on(sync_nodes(&config, &app_state, &address_space, namespace));
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_opcua] Failed to spawn opcua sync thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Mirrors the newest events of every machine into the address space
+///
+/// Nodes are created the first time a value is seen and are kept when a machine disconnects.
+async fn sync_nodes(
+    config: &OpcUaConfig,
+    app_state: &Arc<AppState>,
+    address_space: &Arc<RwLock<AddressSpace>>,
+    namespace: u16,
+) {
+    let events: Vec<_> = EXPOSED_EVENTS.iter().map(|(event, _)| *event).collect();
+    let mut synced_until = HashMap::new();
+    loop {
+        smol::Timer::after(Duration::from_millis(config.interval_ms)).await;
+
+        let series = collect_new_events(app_state, &events, &mut synced_until).await;
+        let now = DateTime::now();
+        let mut address_space = address_space.write();
+        for ((machine, event), samples) in series {
+            let (Some(slug), Some(last)) = (
+                machine_slug(machine.machine_identification.machine),
+                samples.last(),
+            ) else {
+                continue;
+            };
+            let Some((_, folder)) = EXPOSED_EVENTS.iter().find(|(name, _)| *name == event) else {
+                continue;
+            };
+            let machine_folder = format!("{}-{}", slug, machine.serial);
+            let folder_id = NodeId::new(namespace, format!("{}/{}", machine_folder, folder));
+            if !address_space.node_exists(&folder_id) {
+                let machine_folder_id = NodeId::new(namespace, machine_folder.clone());
+                if !address_space.node_exists(&machine_folder_id) {
+                    address_space.add_folder_with_id(
+                        &machine_folder_id,
+                        machine_folder.as_str(),
+                        machine_folder.as_str(),
+                        &ObjectId::ObjectsFolder.into(),
+                    );
+                }
+                address_space.add_folder_with_id(&folder_id, *folder, *folder, &machine_folder_id);
+            }
+
+            let mut values = vec![];
+            flatten(None, &last.data, &mut values);
+            for (path, value) in values {
+                let node_id =
+                    NodeId::new(namespace, format!("{}/{}/{}", machine_folder, folder, path));
+                if address_space.node_exists(&node_id) {
+                    address_space.set_variable_value(node_id, value, &now, &now);
+                    continue;
+                }
+                let Some(data_type) = data_type(&value) else {
+                    // the type of a missing value is unknown, the node is added once it has one
+                    continue;
+                };
+
+                let mut builder = VariableBuilder::new(&node_id, path.as_str(), path.as_str())
+                    .data_type(data_type)
+                    .value(value)
+                    .organized_by(&folder_id);
+                let setpoint = SETPOINTS.iter().find(|(machine_type, state_path, _)| {
+                    *folder == "State"
+                        && *machine_type == machine.machine_identification.machine
+                        && *state_path == path
+                });
+                if let Some((_, _, mutation)) = setpoint {
+                    builder = builder
+                        .writable()
+                        .value_setter(setpoint_setter(app_state, &machine, mutation));
+                }
+                builder.insert(&mut address_space);
+            }
+        }
+    }
+}
+
+/// Maps writes to a setpoint node to the machine mutation
+///
+/// The node itself is not written, it follows the state event emitted by the machine.
+fn setpoint_setter(
+    app_state: &Arc<AppState>,
+    machine: &MachineIdentificationUnique,
+    mutation: &'static str,
+) -> Arc<Mutex<dyn AttributeSetter + Send>> {
+    let app_state = app_state.clone();
+    let machine = machine.clone();
+    Arc::new(Mutex::new(AttrFnSetter::new(
+        move |_node_id, _attribute_id, _index_range, data_value| {
+            let value = data_value
+                .value
+                .as_ref()
+                .and_then(to_json)
+                .ok_or(StatusCode::BadTypeMismatch)?;
+            let mut data = Map::new();
+            data.insert(mutation.to_string(), value);
+            smol::block_on(mutate_machine(
+                &app_state,
+                MachineMutationBody {
+                    machine_identification_unique: machine.clone(),
+                    data: Value::Object(data),
+                },
+            ))
+            .map_err(|e| {
+                tracing::warn!("OPC-UA write {} to {} failed: {:?}", mutation, machine, e);
+                StatusCode::BadInvalidArgument
+            })
+        },
+    )))
+}
+
+/// Flattens the leaves of an event into `.`-separated paths
+///
+/// Arrays are skipped, missing values become [`Variant::Empty`].
+fn flatten(prefix: Option<&str>, value: &Value, out: &mut Vec<(String, Variant)>) {
+    let path = |key: &str| match prefix {
+        Some(prefix) => format!("{}.{}", prefix, key),
+        None => key.to_string(),
+    };
+    if let Value::Object(map) = value {
+        for (key, value) in map {
+            let path = path(key);
+            match value {
+                Value::Object(_) => flatten(Some(&path), value, out),
+                Value::Array(_) => {}
+                Value::Null => out.push((path, Variant::Empty)),
+                Value::Bool(value) => out.push((path, Variant::Boolean(*value))),
+                Value::String(value) => out.push((path, Variant::from(value.as_str()))),
+                Value::Number(number) => {
+                    let variant = if let Some(value) = number.as_i64() {
+                        Variant::Int64(value)
+                    } else if let Some(value) = number.as_u64() {
+                        Variant::UInt64(value)
+                    } else {
+                        Variant::Double(number.as_f64().unwrap_or(f64::NAN))
+                    };
+                    out.push((path, variant));
+                }
+            }
+        }
+    }
+}
+
+const fn data_type(value: &Variant) -> Option<DataTypeId> {
+    match value {
+        Variant::Boolean(_) => Some(DataTypeId::Boolean),
+        Variant::Int64(_) => Some(DataTypeId::Int64),
+        Variant::UInt64(_) => Some(DataTypeId::UInt64),
+        Variant::Double(_) => Some(DataTypeId::Double),
+        Variant::String(_) => Some(DataTypeId::String),
+        _ => None,
+    }
+}
+
+/// Payload of a mutation from a written value
+fn to_json(value: &Variant) -> Option<Value> {
+    match value {
+        Variant::Boolean(value) => Some(Value::from(*value)),
+        Variant::Int64(value) => Some(Value::from(*value)),
+        Variant::UInt64(value) => Some(Value::from(*value)),
+        Variant::Double(value) => Some(Value::from(*value)),
+        Variant::String(value) => value
+            .value()
+            .as_ref()
+            .map(|value| Value::from(value.as_str())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opcua::server::prelude::UAString;
+    use serde_json::json;
+
+    #[test]
+    fn test_flatten() {
+        let mut values = vec![];
+        flatten(
+            None,
+            &json!({
+                "is_default_state": false,
+                "laser_state": { "target_diameter": 1.75, "min_max_timeframe_minutes": 5 },
+                "mode_state": { "mode": "Standby" },
+                "x_diameter": null,
+                "samples": [1, 2],
+            }),
+            &mut values,
+        );
+        values.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            values,
+            vec![
+                ("is_default_state".to_string(), Variant::Boolean(false)),
+                (
+                    "laser_state.min_max_timeframe_minutes".to_string(),
+                    Variant::Int64(5)
+                ),
+                (
+                    "laser_state.target_diameter".to_string(),
+                    Variant::Double(1.75)
+                ),
+                ("mode_state.mode".to_string(), Variant::from("Standby")),
+                ("x_diameter".to_string(), Variant::Empty),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        assert_eq!(to_json(&Variant::Double(1.75)), Some(json!(1.75)));
+        assert_eq!(to_json(&Variant::Int64(5)), Some(json!(5)));
+        assert_eq!(to_json(&Variant::from("Heat")), Some(json!("Heat")));
+        assert_eq!(to_json(&Variant::String(UAString::null())), None);
+        assert_eq!(to_json(&Variant::Empty), None);
+    }
+
+    #[test]
+    fn test_setpoints_are_unique() {
+        for (i, (machine, path, _)) in SETPOINTS.iter().enumerate() {
+            assert!(
+                !SETPOINTS[i + 1..]
+                    .iter()
+                    .any(|(other_machine, other_path, _)| other_machine == machine
+                        && other_path == path),
+                "duplicate setpoint {}",
+                path
+            );
+        }
+    }
+}
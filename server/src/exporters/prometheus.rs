@@ -0,0 +1,296 @@
+use crate::{app_state::AppState, instrumentation::BUCKET_BOUNDS_US};
+use lazy_static::lazy_static;
+use std::{collections::BTreeMap, fmt::Write, sync::Arc, sync::Mutex, time::Duration};
+
+lazy_static! {
+    /// Round trips of serial requests per device path
+    static ref SERIAL_ROUND_TRIPS: Mutex<BTreeMap<String, RoundTrips>> =
+        Mutex::new(BTreeMap::new());
+}
+
+#[derive(Debug, Default, Clone)]
+struct RoundTrips {
+    count: u64,
+    sum: Duration,
+    last: Duration,
+}
+
+/// Records the time from sending a serial request until its response was parsed
+pub fn record_serial_round_trip(path: &str, duration: Duration) {
+    let Ok(mut round_trips) = SERIAL_ROUND_TRIPS.lock() else {
+        return;
+    };
+    let round_trip = round_trips.entry(path.to_string()).or_default();
+    round_trip.count += 1;
+    round_trip.sum += duration;
+    round_trip.last = duration;
+}
+
+/// Writes metrics in the Prometheus text exposition format
+#[derive(Debug, Default)]
+struct MetricsWriter {
+    output: String,
+}
+
+impl MetricsWriter {
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.output, "# HELP {} {}", name, help);
+        let _ = writeln!(self.output, "# TYPE {} {}", name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, String)], value: f64) {
+        self.output.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<_> = labels
+                .iter()
+                .map(|(key, value)| format!("{}=\"{}\"", key, escape_label(value)))
+                .collect();
+            let _ = write!(self.output, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.output, " {}", value);
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Renders all server metrics
+pub async fn render_metrics(app_state: &Arc<AppState>) -> String {
+    let mut writer = MetricsWriter::default();
+
+    // act loop
+    {
+        let (loop_stats, txrx_stats) = {
+            let metrics = app_state.performance_metrics.read().await;
+            (metrics.loop_stats(), metrics.txrx_stats())
+        };
+        let stats = [
+            (
+                "qitech_loop_cycle_seconds",
+                "Cycle time of the act loop over the last 30000 cycles",
+                loop_stats,
+            ),
+            (
+                "qitech_ethercat_txrx_seconds",
+                "EtherCAT tx/rx time over the last 30000 cycles",
+                txrx_stats,
+            ),
+        ];
+        for (name, help, stats) in stats {
+            let Some(stats) = stats else {
+                continue;
+            };
+            writer.family(name, "gauge", help);
+            writer.sample(
+                name,
+                &[("stat", "average".to_string())],
+                stats.average_ms / 1000.0,
+            );
+            writer.sample(
+                name,
+                &[("stat", "p9999".to_string())],
+                stats.percentile_9999_ms / 1000.0,
+            );
+            writer.sample(
+                name,
+                &[("stat", "stddev".to_string())],
+                stats.stddev_ms / 1000.0,
+            );
+        }
+    }
+
+    // act calls per machine
+    {
+        let instrumentation = app_state.instrumentation.read().await;
+        let reports = instrumentation.report();
+        let histograms = [
+            (
+                "qitech_machine_act_seconds",
+                "Duration of the act calls of a machine",
+                reports
+                    .iter()
+                    .map(|report| (&report.machine_identification_unique, &report.act))
+                    .collect::<Vec<_>>(),
+            ),
+            (
+                "qitech_machine_act_jitter_seconds",
+                "Difference between consecutive act intervals of a machine",
+                reports
+                    .iter()
+                    .map(|report| (&report.machine_identification_unique, &report.jitter))
+                    .collect(),
+            ),
+        ];
+        for (name, help, histograms) in histograms {
+            writer.family(name, "histogram", help);
+            for (machine_identification_unique, histogram) in histograms {
+                let machine = machine_identification_unique.to_string();
+                let mut cumulative = 0;
+                for (bucket, count) in histogram.counts.iter().enumerate() {
+                    cumulative += count;
+                    let le = BUCKET_BOUNDS_US
+                        .get(bucket)
+                        .map(|bound| (*bound as f64 / 1e6).to_string())
+                        .unwrap_or_else(|| "+Inf".to_string());
+                    writer.sample(
+                        &format!("{}_bucket", name),
+                        &[("machine", machine.clone()), ("le", le)],
+                        cumulative as f64,
+                    );
+                }
+                let labels = [("machine", machine)];
+                writer.sample(&format!("{}_sum", name), &labels, histogram.sum_us / 1e6);
+                writer.sample(&format!("{}_count", name), &labels, histogram.count as f64);
+            }
+        }
+
+        writer.family(
+            "qitech_machine_act_budget_overruns_total",
+            "counter",
+            "Act calls of a machine that exceeded its cycle budget",
+        );
+        for report in &reports {
+            writer.sample(
+                "qitech_machine_act_budget_overruns_total",
+                &[("machine", report.machine_identification_unique.to_string())],
+                report.overruns as f64,
+            );
+        }
+    }
+
+    // serial devices
+    let round_trips = SERIAL_ROUND_TRIPS
+        .lock()
+        .map(|round_trips| round_trips.clone())
+        .unwrap_or_default();
+    writer.family(
+        "qitech_serial_round_trip_seconds",
+        "summary",
+        "Time from sending a serial request until the response was parsed",
+    );
+    for (path, round_trip) in &round_trips {
+        let labels = [("path", path.clone())];
+        writer.sample(
+            "qitech_serial_round_trip_seconds_sum",
+            &labels,
+            round_trip.sum.as_secs_f64(),
+        );
+        writer.sample(
+            "qitech_serial_round_trip_seconds_count",
+            &labels,
+            round_trip.count as f64,
+        );
+    }
+    writer.family(
+        "qitech_serial_round_trip_last_seconds",
+        "gauge",
+        "Round trip of the latest serial request",
+    );
+    for (path, round_trip) in &round_trips {
+        writer.sample(
+            "qitech_serial_round_trip_last_seconds",
+            &[("path", path.clone())],
+            round_trip.last.as_secs_f64(),
+        );
+    }
+
+    // socketio
+    writer.family(
+        "qitech_socketio_queue_length",
+        "gauge",
+        "Events waiting in the global socketio queue",
+    );
+    writer.sample(
+        "qitech_socketio_queue_length",
+        &[],
+        app_state.socketio_setup.socket_queue_tx.len() as f64,
+    );
+
+    // machines
+    let slots: Vec<_> = app_state
+        .machines
+        .read()
+        .await
+        .iter()
+        .map(|(machine, slot)| {
+            let slot = slot.lock_blocking();
+            (machine.clone(), slot.is_connected(), slot.namespace.clone())
+        })
+        .collect();
+
+    let mut machines = vec![];
+    for (machine, connected, namespace) in slots {
+        let (sockets, events) = {
+            let namespace = namespace.lock().await;
+            (namespace.sockets.len(), namespace.emitted_events)
+        };
+        let labels = [
+            ("vendor", machine.machine_identification.vendor.to_string()),
+            (
+                "machine",
+                machine.machine_identification.machine.to_string(),
+            ),
+            ("serial", machine.serial.to_string()),
+        ];
+        machines.push((labels, connected, sockets, events));
+    }
+
+    writer.family(
+        "qitech_machine_connected",
+        "gauge",
+        "1 if all devices of the machine are connected",
+    );
+    for (labels, connected, _, _) in &machines {
+        writer.sample(
+            "qitech_machine_connected",
+            labels,
+            if *connected { 1.0 } else { 0.0 },
+        );
+    }
+    writer.family(
+        "qitech_machine_events_total",
+        "counter",
+        "Events emitted by the machine, the rate is the update rate of the machine",
+    );
+    for (labels, _, _, events) in &machines {
+        writer.sample("qitech_machine_events_total", labels, *events as f64);
+    }
+    writer.family(
+        "qitech_machine_sockets",
+        "gauge",
+        "Clients subscribed to the machine namespace",
+    );
+    for (labels, _, sockets, _) in &machines {
+        writer.sample("qitech_machine_sockets", labels, *sockets as f64);
+    }
+
+    writer.output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_format() {
+        let mut writer = MetricsWriter::default();
+        writer.family("qitech_test", "gauge", "Test metric");
+        writer.sample("qitech_test", &[], 1.5);
+        writer.sample(
+            "qitech_test",
+            &[("path", "/dev/tty\"USB0\"".to_string())],
+            2.0,
+        );
+        assert_eq!(
+            writer.output,
+            "# HELP qitech_test Test metric\n\
+            # TYPE qitech_test gauge\n\
+            qitech_test 1.5\n\
+            qitech_test{path=\"/dev/tty\\\"USB0\\\"\"} 2\n"
+        );
+    }
+}
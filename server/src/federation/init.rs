@@ -0,0 +1,95 @@
+use super::{FederationStatus, PeerClient};
+use crate::{
+    app_state::AppState,
+    config::config,
+    panic::{PanicDetails, send_panic},
+};
+use smol::channel::Sender;
+use std::{sync::Arc, time::Duration};
+
+/// Starts polling the peers if any are configured
+pub fn init_federation(
+    thread_panic_tx: Sender<PanicDetails>,
+    app_state: Arc<AppState>,
+) -> Result<(), anyhow::Error> {
+    let federation = config().federation.clone();
+    if federation.peers.is_empty() {
+        return Ok(());
+    }
+    tracing::info!("Proxying the machines of {} peers", federation.peers.len());
+
+    std::thread::Builder::new()
+        .name("federation".to_owned())
+        .spawn(move || {
+            send_panic(thread_panic_tx);
+            smol::block_on(async {
+                let mut clients: Vec<_> =
+                    federation.peers.into_iter().map(PeerClient::new).collect();
+                loop {
+                    smol::Timer::after(Duration::from_millis(federation.poll_interval_ms)).await;
+
+                    let mut status = FederationStatus::default();
+                    for client in &mut clients {
+                        poll_peer(&app_state, client, &mut status).await;
+                    }
+                    *app_state.federation.write().await = status;
+                }
+            });
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_federation] Failed to spawn federation thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Forwards the new events of the machines of a peer to their proxy namespaces
+///
+/// An unreachable peer keeps its namespaces, clients stay connected until it is back.
+async fn poll_peer(
+    app_state: &Arc<AppState>,
+    client: &mut PeerClient,
+    status: &mut FederationStatus,
+) {
+    let result = client.machines();
+    status.peers.push(client.status(&result));
+    let machines = match result {
+        Ok(machines) => machines,
+        Err(e) => {
+            tracing::debug!("Failed to poll peer {}: {:?}", client.peer.name, e);
+            return;
+        }
+    };
+
+    let machines: Vec<_> = machines
+        .into_iter()
+        .filter(|machine| machine.error.is_none())
+        .map(|machine| machine.machine_identification_unique)
+        .collect();
+    client.retain_machines(&machines);
+
+    let mut forwarded = Vec::new();
+    for machine in &machines {
+        match client.events(machine) {
+            Ok(events) => forwarded.push((machine, client.new_events(machine, events))),
+            Err(e) => tracing::debug!(
+                "Failed to poll events of {} on peer {}: {:?}",
+                machine,
+                client.peer.name,
+                e
+            ),
+        }
+    }
+
+    let mut namespaces = app_state.socketio_setup.namespaces.write().await;
+    namespaces.sync_remote(&client.peer.name, &machines);
+    for (machine, events) in forwarded {
+        for event in events {
+            namespaces.emit_remote(&client.peer.name, machine, event);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{config::FederationPeer, socketio::main_namespace::machines_event::MachineObj};
+use control_core::{
+    helpers::clock::{monotonic_millis, unix_millis},
+    machines::identification::MachineIdentificationUnique,
+    socketio::{
+        event::{GenericEvent, is_droppable},
+        namespace_id::NamespaceId,
+    },
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+pub mod init;
+
+/// Machine of a peer served by this server
+#[derive(Serialize, Debug, Clone)]
+pub struct FederatedMachine {
+    pub machine_identification_unique: MachineIdentificationUnique,
+    pub error: Option<String>,
+    /// Namespace path the machine is proxied at, `None` while it has an error on the peer
+    pub namespace: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PeerStatus {
+    pub name: String,
+    pub url: String,
+    pub reachable: bool,
+    pub error: Option<String>,
+    /// unix timestamp in milliseconds of the last successful poll
+    pub last_poll: Option<u64>,
+    pub machines: Vec<FederatedMachine>,
+}
+
+/// Peers of the federation mode, empty if no peers are configured
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct FederationStatus {
+    pub peers: Vec<PeerStatus>,
+}
+
+/// Latest value of a machine event as served by the peer
+#[derive(Deserialize, Debug, Clone)]
+pub struct RemoteEvent {
+    /// unix timestamp in milliseconds
+    pub ts: u64,
+    pub data: Value,
+}
+
+/// Polls the machines of another control server over its REST API
+///
+/// The peer is read only, mutations have to go to the peer itself.
+#[derive(Debug)]
+pub struct PeerClient {
+    pub peer: FederationPeer,
+    /// Timestamp of the last forwarded value per machine and event
+    forwarded: HashMap<(MachineIdentificationUnique, String), u64>,
+}
+
+impl PeerClient {
+    const TIMEOUT: Duration = Duration::from_secs(3);
+
+    pub fn new(peer: FederationPeer) -> Self {
+        Self {
+            peer,
+            forwarded: HashMap::new(),
+        }
+    }
+
+    pub fn machines(&self) -> Result<Vec<MachineObj>, anyhow::Error> {
+        self.get("/api/v1/machines")
+    }
+
+    pub fn events(
+        &self,
+        machine: &MachineIdentificationUnique,
+    ) -> Result<BTreeMap<String, RemoteEvent>, anyhow::Error> {
+        self.get(&format!(
+            "/api/v1/machines/{}/{}/{}/events",
+            machine.machine_identification.vendor,
+            machine.machine_identification.machine,
+            machine.serial
+        ))
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, anyhow::Error> {
+        let url = format!("{}{}", self.peer.url.trim_end_matches('/'), path);
+        let mut request = ureq::get(&url)
+            .config()
+            .timeout_global(Some(Self::TIMEOUT))
+            .build();
+        if let Some(token) = &self.peer.token {
+            request = request.header("Authorization", &format!("Bearer {}", token));
+        }
+        let body = request.call()?.body_mut().read_to_string()?;
+        serde_json::from_str(&body).map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::PeerClient::get] Invalid response of {} from {}: {}",
+                module_path!(),
+                path,
+                self.peer.name,
+                e
+            )
+        })
+    }
+
+    /// Events that changed since the last call, to be emitted on the proxy namespace
+    pub fn new_events(
+        &mut self,
+        machine: &MachineIdentificationUnique,
+        events: BTreeMap<String, RemoteEvent>,
+    ) -> Vec<GenericEvent> {
+        events
+            .into_iter()
+            .filter_map(|(name, event)| {
+                let forwarded = self.forwarded.entry((machine.clone(), name.clone()));
+                let last = forwarded.or_insert(0);
+                if event.ts <= *last {
+                    return None;
+                }
+                *last = event.ts;
+                Some(GenericEvent {
+                    droppable: is_droppable(&name),
+                    name,
+                    data: Box::new(event.data),
+                    ts: event.ts,
+                    // the monotonic time of the peer doesn't compare to ours
+                    mono: monotonic_millis(),
+                })
+            })
+            .collect()
+    }
+
+    /// Forgets the events of machines the peer no longer has, they start over when they return
+    pub fn retain_machines(&mut self, machines: &[MachineIdentificationUnique]) {
+        self.forwarded
+            .retain(|(machine, _), _| machines.contains(machine));
+    }
+
+    pub fn namespace_id(&self, machine: &MachineIdentificationUnique) -> NamespaceId {
+        NamespaceId::Remote {
+            server: self.peer.name.clone(),
+            machine: machine.clone(),
+        }
+    }
+
+    pub fn status(&self, result: &Result<Vec<MachineObj>, anyhow::Error>) -> PeerStatus {
+        let (machines, error) = match result {
+            Ok(machines) => (machines.as_slice(), None),
+            Err(e) => (&[][..], Some(e.to_string())),
+        };
+        PeerStatus {
+            name: self.peer.name.clone(),
+            url: self.peer.url.clone(),
+            reachable: error.is_none(),
+            last_poll: error.is_none().then(unix_millis),
+            error,
+            machines: machines
+                .iter()
+                .map(|machine| FederatedMachine {
+                    machine_identification_unique: machine.machine_identification_unique.clone(),
+                    error: machine.error.clone(),
+                    namespace: machine.error.is_none().then(|| {
+                        self.namespace_id(&machine.machine_identification_unique)
+                            .to_string()
+                    }),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use control_core::machines::identification::MachineIdentification;
+    use serde_json::json;
+
+    #[test]
+    fn test_new_events() {
+        let mut client = PeerClient::new(FederationPeer {
+            name: "line2".to_string(),
+            url: "http://10.0.2.10:3001".to_string(),
+            token: None,
+        });
+        let machine = MachineIdentificationUnique {
+            machine_identification: MachineIdentification {
+                vendor: 1,
+                machine: 2,
+            },
+            serial: 3,
+        };
+        let events = |ts| {
+            BTreeMap::from([
+                (
+                    "StateEvent".to_string(),
+                    RemoteEvent {
+                        ts: 5,
+                        data: json!({"mode": "Pull"}),
+                    },
+                ),
+                (
+                    "LiveValuesEvent".to_string(),
+                    RemoteEvent {
+                        ts,
+                        data: json!({"diameter": 1.75}),
+                    },
+                ),
+            ])
+        };
+
+        assert_eq!(client.new_events(&machine, events(10)).len(), 2);
+        // only the live values changed
+        let new_events = client.new_events(&machine, events(11));
+        assert_eq!(new_events.len(), 1);
+        assert_eq!(new_events[0].name, "LiveValuesEvent");
+        assert!(client.new_events(&machine, events(11)).is_empty());
+
+        client.retain_machines(&[]);
+        assert_eq!(client.new_events(&machine, events(11)).len(), 2);
+        assert_eq!(
+            client.namespace_id(&machine).to_string(),
+            "/remote/line2/machine/1/2/3"
+        );
+    }
+}
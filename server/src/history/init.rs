@@ -0,0 +1,123 @@
+use super::{HistorySample, LOGGED_EVENTS};
+use crate::{
+    app_state::AppState,
+    panic::{PanicDetails, send_panic},
+};
+use control_core::{
+    helpers::clock::unix_millis, machines::identification::MachineIdentificationUnique,
+};
+use smol::channel::Sender;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Interval new events are collected from the machine namespaces
+const LOG_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Interval the downsampled tiers are calculated and old samples are deleted
+const ROLLUP_INTERVAL: Duration = Duration::from_secs(60);
+
+pub fn init_history(
+    thread_panic_tx: Sender<PanicDetails>,
+    app_state: Arc<AppState>,
+) -> Result<(), anyhow::Error> {
+    std::thread::Builder::new()
+        .name("history".to_owned())
+        .spawn(move || {
+            send_panic(thread_panic_tx);
+            smol::block_on(async {
+                let mut last_rollup = Instant::now();
+                loop {
+                    smol::Timer::after(LOG_INTERVAL).await;
+
+                    log_new_events(&app_state).await;
+
+                    if last_rollup.elapsed() >= ROLLUP_INTERVAL {
+                        last_rollup = Instant::now();
+                        let now = unix_millis();
+                        let mut history = app_state.history.lock().await;
+                        if let Err(e) = history.rollup(now).and_then(|_| history.prune(now)) {
+                            tracing::error!("Failed to downsample history: {:?}", e);
+                        }
+                    }
+                }
+            });
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_history] Failed to spawn history thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Logs the events emitted since the last call
+///
+/// Also called on shutdown so the last events are not lost.
+pub async fn log_new_events(app_state: &Arc<AppState>) {
+    let mut history = app_state.history.lock().await;
+    let series = collect_new_events(app_state, LOGGED_EVENTS, &mut history.logged_until).await;
+    for ((machine, event), samples) in series {
+        if let Err(e) = history.insert(&machine, &event, &samples) {
+            tracing::error!("Failed to log history of {}: {:?}", machine, e);
+        }
+    }
+}
+
+/// Reads the `events` cached in the machine namespaces that are newer than `logged_until`
+///
+/// `logged_until` is advanced to the newest returned event per series.
+pub(crate) async fn collect_new_events(
+    app_state: &Arc<AppState>,
+    events: &[impl AsRef<str> + Sync],
+    logged_until: &mut HashMap<(MachineIdentificationUnique, String), u64>,
+) -> Vec<((MachineIdentificationUnique, String), Vec<HistorySample>)> {
+    let namespaces: Vec<_> = app_state
+        .machines
+        .read()
+        .await
+        .iter()
+        .filter_map(|(machine, slot)| {
+            let slot = slot.lock_blocking();
+            slot.is_connected()
+                .then(|| (machine.clone(), slot.namespace.clone()))
+        })
+        .collect();
+
+    let mut series = vec![];
+    for (machine, namespace) in namespaces {
+        let namespace = namespace.lock().await;
+        for event in events {
+            let Some(cached) = namespace.events.get(event.as_ref()) else {
+                continue;
+            };
+            let key = (machine.clone(), event.as_ref().to_string());
+            let until = logged_until.get(&key).copied().unwrap_or(0);
+
+            let samples: Vec<_> = cached
+                .iter()
+                .filter(|cached| cached.ts > until)
+                .filter_map(|cached| {
+                    serde_json::to_value(&cached.data)
+                        .map(|data| HistorySample {
+                            ts: cached.ts,
+                            data,
+                        })
+                        .ok()
+                })
+                .collect();
+
+            if let Some(last) = samples.last() {
+                logged_until.insert(key.clone(), last.ts);
+                series.push((key, samples));
+            }
+        }
+        drop(namespace);
+    }
+    series
+}
@@ -0,0 +1,717 @@
+use control_core::{
+    alarms::{Alarm, AlarmSeverity, AlarmState},
+    machines::identification::MachineIdentificationUnique,
+};
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+    time::Duration,
+};
+
+pub mod init;
+
+/// File inside [`crate::storage::data_dir`] holding the time series
+pub const HISTORY_FILE: &str = "history.sqlite";
+
+/// Events of the machine namespaces that are logged
+pub const LOGGED_EVENTS: &[&str] = &["LiveValuesEvent"];
+
+/// Resolution tiers of the time series
+///
+/// Every tier is calculated from the tier before it by averaging over its bucket.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HistoryTier {
+    /// Events as they were emitted
+    Raw,
+    /// Averages over one minute
+    Minute,
+    /// Averages over one hour
+    Hour,
+}
+
+impl HistoryTier {
+    pub const ALL: [Self; 3] = [Self::Raw, Self::Minute, Self::Hour];
+
+    const fn id(self) -> i64 {
+        match self {
+            Self::Raw => 0,
+            Self::Minute => 1,
+            Self::Hour => 2,
+        }
+    }
+
+    /// Size of one bucket, `None` for raw samples
+    pub const fn bucket(self) -> Option<Duration> {
+        match self {
+            Self::Raw => None,
+            Self::Minute => Some(Duration::from_secs(60)),
+            Self::Hour => Some(Duration::from_secs(60 * 60)),
+        }
+    }
+
+    /// How long samples of this tier are kept
+    pub const fn retention(self) -> Duration {
+        match self {
+            Self::Raw => Duration::from_secs(24 * 60 * 60),
+            Self::Minute => Duration::from_secs(30 * 24 * 60 * 60),
+            Self::Hour => Duration::from_secs(365 * 24 * 60 * 60),
+        }
+    }
+
+    /// Tier the samples of this tier are calculated from
+    const fn source(self) -> Option<Self> {
+        match self {
+            Self::Raw => None,
+            Self::Minute => Some(Self::Raw),
+            Self::Hour => Some(Self::Minute),
+        }
+    }
+
+    /// Finest tier that keeps samples back to `from` and doesn't return
+    /// excessive amounts of samples for the range
+    pub fn for_range(from: u64, to: u64, now: u64) -> Self {
+        let range = Duration::from_millis(to.saturating_sub(from));
+        let age = Duration::from_millis(now.saturating_sub(from));
+        if range <= Duration::from_secs(6 * 60 * 60) && age <= Self::Raw.retention() {
+            Self::Raw
+        } else if range <= Duration::from_secs(7 * 24 * 60 * 60) && age <= Self::Minute.retention()
+        {
+            Self::Minute
+        } else {
+            Self::Hour
+        }
+    }
+}
+
+/// One sample of a time series
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HistorySample {
+    /// unix timestamp in milliseconds, start of the bucket for downsampled tiers
+    pub ts: u64,
+    pub data: Value,
+}
+
+/// Note of an operator, e.g. "changed filter screen"
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    /// unix timestamp in milliseconds
+    pub ts: u64,
+    pub user: String,
+    pub text: String,
+    /// run that was open when the note was taken
+    pub run_id: Option<u64>,
+}
+
+/// Filter of the alarm history, all fields are optional
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct AlarmQuery {
+    /// unix timestamp in milliseconds, alarms still active at `from` are included
+    pub from: Option<u64>,
+    /// unix timestamp in milliseconds
+    pub to: Option<u64>,
+    /// `vendor/machine/serial` of the machine that raised the alarm
+    pub machine: Option<String>,
+    /// lowest severity to include
+    pub severity: Option<AlarmSeverity>,
+    /// case insensitive search in code and message
+    pub text: Option<String>,
+    /// defaults to [`DEFAULT_ALARM_LIMIT`], at most [`MAX_ALARM_LIMIT`]
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+pub const DEFAULT_ALARM_LIMIT: u32 = 100;
+pub const MAX_ALARM_LIMIT: u32 = 1000;
+
+/// One page of the alarm history, newest alarms first
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct AlarmPage {
+    /// alarms matching the filter over all pages
+    pub total: u64,
+    pub alarms: Vec<Alarm>,
+}
+
+const fn severity_rank(severity: AlarmSeverity) -> i64 {
+    match severity {
+        AlarmSeverity::Info => 0,
+        AlarmSeverity::Warning => 1,
+        AlarmSeverity::Critical => 2,
+    }
+}
+
+/// Time series of machine events in an embedded sqlite database
+#[derive(Debug)]
+pub struct HistoryStore {
+    connection: Connection,
+    /// Newest logged event per machine and event name
+    pub logged_until: HashMap<(MachineIdentificationUnique, String), u64>,
+}
+
+impl HistoryStore {
+    pub fn open(path: &Path) -> Result<Self, anyhow::Error> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, anyhow::Error> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(connection: Connection) -> Result<Self, anyhow::Error> {
+        connection.execute_batch(
+            "PRAGMA journal_mode = WAL;
+            CREATE TABLE IF NOT EXISTS samples (
+                machine TEXT NOT NULL,
+                event TEXT NOT NULL,
+                tier INTEGER NOT NULL,
+                ts INTEGER NOT NULL,
+                data TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS samples_series ON samples (machine, event, tier, ts);
+            CREATE TABLE IF NOT EXISTS rollups (
+                tier INTEGER PRIMARY KEY,
+                until INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS annotations (
+                ts INTEGER NOT NULL,
+                user TEXT NOT NULL,
+                text TEXT NOT NULL,
+                run_id INTEGER
+            );
+            CREATE INDEX IF NOT EXISTS annotations_ts ON annotations (ts);
+            CREATE TABLE IF NOT EXISTS alarms (
+                id INTEGER PRIMARY KEY,
+                machine TEXT,
+                code TEXT NOT NULL,
+                message TEXT NOT NULL,
+                severity INTEGER NOT NULL,
+                raised_at INTEGER NOT NULL,
+                cleared_at INTEGER,
+                data TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS alarms_raised_at ON alarms (raised_at);",
+        )?;
+        Ok(Self {
+            connection,
+            logged_until: HashMap::new(),
+        })
+    }
+
+    /// Inserts raw samples of one machine event
+    pub fn insert(
+        &mut self,
+        machine: &MachineIdentificationUnique,
+        event: &str,
+        samples: &[HistorySample],
+    ) -> Result<(), anyhow::Error> {
+        let transaction = self.connection.transaction()?;
+        {
+            let mut statement = transaction.prepare_cached(
+                "INSERT INTO samples (machine, event, tier, ts, data) VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for sample in samples {
+                statement.execute(params![
+                    machine.to_string(),
+                    event,
+                    HistoryTier::Raw.id(),
+                    sample.ts as i64,
+                    sample.data.to_string()
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Samples of one machine event between `from` and `to` (both inclusive)
+    pub fn query(
+        &self,
+        machine: &MachineIdentificationUnique,
+        event: &str,
+        tier: HistoryTier,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<HistorySample>, anyhow::Error> {
+        let mut statement = self.connection.prepare_cached(
+            "SELECT ts, data FROM samples
+            WHERE machine = ?1 AND event = ?2 AND tier = ?3 AND ts >= ?4 AND ts <= ?5
+            ORDER BY ts",
+        )?;
+        let rows = statement.query_map(
+            params![
+                machine.to_string(),
+                event,
+                tier.id(),
+                from as i64,
+                to as i64
+            ],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+        )?;
+
+        let mut samples = vec![];
+        for row in rows {
+            let (ts, data) = row?;
+            samples.push(HistorySample {
+                ts: ts as u64,
+                data: serde_json::from_str(&data)?,
+            });
+        }
+        Ok(samples)
+    }
+
+    /// Stores an annotation, annotations are kept as long as the hourly samples
+    pub fn insert_annotation(&mut self, annotation: &Annotation) -> Result<(), anyhow::Error> {
+        self.connection.execute(
+            "INSERT INTO annotations (ts, user, text, run_id) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                annotation.ts as i64,
+                annotation.user,
+                annotation.text,
+                annotation.run_id.map(|id| id as i64)
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Annotations between `from` and `to` (both inclusive), oldest first
+    pub fn annotations(&self, from: u64, to: u64) -> Result<Vec<Annotation>, anyhow::Error> {
+        let mut statement = self.connection.prepare_cached(
+            "SELECT ts, user, text, run_id FROM annotations
+            WHERE ts >= ?1 AND ts <= ?2
+            ORDER BY ts",
+        )?;
+        let rows = statement.query_map(params![from as i64, to as i64], |row| {
+            Ok(Annotation {
+                ts: row.get::<_, i64>(0)? as u64,
+                user: row.get(1)?,
+                text: row.get(2)?,
+                run_id: row.get::<_, Option<i64>>(3)?.map(|id| id as u64),
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Stores the latest state of alarms, alarms are kept as long as the hourly samples
+    pub fn upsert_alarms(&mut self, alarms: &[Alarm]) -> Result<(), anyhow::Error> {
+        let transaction = self.connection.transaction()?;
+        {
+            let mut statement = transaction.prepare_cached(
+                "INSERT OR REPLACE INTO alarms
+                (id, machine, code, message, severity, raised_at, cleared_at, data)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for alarm in alarms {
+                statement.execute(params![
+                    alarm.id as i64,
+                    alarm.machine.as_ref().map(|m| m.to_string()),
+                    alarm.code,
+                    alarm.message,
+                    severity_rank(alarm.severity),
+                    alarm.raised_at as i64,
+                    alarm.cleared_at.map(|ts| ts as i64),
+                    serde_json::to_string(alarm)?
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Highest stored alarm id, `0` without alarms
+    pub fn last_alarm_id(&self) -> Result<u64, anyhow::Error> {
+        let id: Option<i64> =
+            self.connection
+                .query_row("SELECT MAX(id) FROM alarms", [], |row| row.get(0))?;
+        Ok(id.unwrap_or(0) as u64)
+    }
+
+    /// Clears the alarms that were still open when the server stopped
+    pub fn clear_open_alarms(&mut self, now: u64) -> Result<(), anyhow::Error> {
+        let mut open = self.query_open_alarms()?;
+        for alarm in &mut open {
+            alarm.state = AlarmState::Cleared;
+            alarm.cleared_at = Some(now);
+        }
+        self.upsert_alarms(&open)
+    }
+
+    fn query_open_alarms(&self) -> Result<Vec<Alarm>, anyhow::Error> {
+        let mut statement = self
+            .connection
+            .prepare_cached("SELECT data FROM alarms WHERE cleared_at IS NULL")?;
+        let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+        let mut alarms = vec![];
+        for row in rows {
+            alarms.push(serde_json::from_str(&row?)?);
+        }
+        Ok(alarms)
+    }
+
+    /// Alarms matching the filter, newest first
+    pub fn query_alarms(&self, query: &AlarmQuery) -> Result<AlarmPage, anyhow::Error> {
+        const FILTER: &str = "WHERE raised_at <= ?1
+            AND COALESCE(cleared_at, ?1) >= ?2
+            AND (?3 IS NULL OR machine = ?3)
+            AND severity >= ?4
+            AND (?5 IS NULL OR instr(lower(code || ' ' || message), lower(?5)) > 0)";
+        let filter = params![
+            query.to.map_or(i64::MAX, |to| to as i64),
+            query.from.unwrap_or(0) as i64,
+            query.machine,
+            query.severity.map(severity_rank).unwrap_or(0),
+            query.text.as_deref().filter(|text| !text.is_empty())
+        ];
+
+        let total: i64 = self.connection.query_row(
+            &format!("SELECT COUNT(*) FROM alarms {}", FILTER),
+            filter,
+            |row| row.get(0),
+        )?;
+
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_ALARM_LIMIT)
+            .min(MAX_ALARM_LIMIT);
+        let mut statement = self.connection.prepare_cached(&format!(
+            "SELECT data FROM alarms {} ORDER BY raised_at DESC, id DESC LIMIT {} OFFSET {}",
+            FILTER,
+            limit,
+            query.offset.unwrap_or(0)
+        ))?;
+        let rows = statement.query_map(filter, |row| row.get::<_, String>(0))?;
+        let mut alarms = vec![];
+        for row in rows {
+            alarms.push(serde_json::from_str(&row?)?);
+        }
+        Ok(AlarmPage {
+            total: total as u64,
+            alarms,
+        })
+    }
+
+    /// Calculates all complete buckets of the downsampled tiers up to `now`
+    pub fn rollup(&mut self, now: u64) -> Result<(), anyhow::Error> {
+        for tier in HistoryTier::ALL {
+            if let (Some(source), Some(bucket)) = (tier.source(), tier.bucket()) {
+                self.rollup_tier(tier, source, bucket.as_millis() as u64, now)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn rollup_tier(
+        &mut self,
+        tier: HistoryTier,
+        source: HistoryTier,
+        bucket: u64,
+        now: u64,
+    ) -> Result<(), anyhow::Error> {
+        let until: Option<i64> = self
+            .connection
+            .query_row(
+                "SELECT until FROM rollups WHERE tier = ?1",
+                params![tier.id()],
+                |row| row.get(0),
+            )
+            .ok();
+        let start = until.unwrap_or(0);
+        let end = (now / bucket * bucket) as i64;
+        if end <= start {
+            return Ok(());
+        }
+
+        // group the source samples by series and bucket
+        let mut buckets: BTreeMap<(String, String, u64), Vec<Value>> = BTreeMap::new();
+        {
+            let mut statement = self.connection.prepare_cached(
+                "SELECT machine, event, ts, data FROM samples
+                WHERE tier = ?1 AND ts >= ?2 AND ts < ?3",
+            )?;
+            let rows = statement.query_map(params![source.id(), start, end], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            })?;
+            for row in rows {
+                let (machine, event, ts, data) = row?;
+                let bucket_start = ts as u64 / bucket * bucket;
+                buckets
+                    .entry((machine, event, bucket_start))
+                    .or_default()
+                    .push(serde_json::from_str(&data)?);
+            }
+        }
+
+        let transaction = self.connection.transaction()?;
+        {
+            let mut statement = transaction.prepare_cached(
+                "INSERT INTO samples (machine, event, tier, ts, data) VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for ((machine, event, bucket_start), values) in buckets {
+                statement.execute(params![
+                    machine,
+                    event,
+                    tier.id(),
+                    bucket_start as i64,
+                    average(&values).to_string()
+                ])?;
+            }
+            transaction.execute(
+                "INSERT OR REPLACE INTO rollups (tier, until) VALUES (?1, ?2)",
+                params![tier.id(), end],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Deletes samples older than the retention of their tier
+    pub fn prune(&mut self, now: u64) -> Result<(), anyhow::Error> {
+        for tier in HistoryTier::ALL {
+            let cutoff = now.saturating_sub(tier.retention().as_millis() as u64);
+            self.connection.execute(
+                "DELETE FROM samples WHERE tier = ?1 AND ts < ?2",
+                params![tier.id(), cutoff as i64],
+            )?;
+        }
+        let cutoff = now.saturating_sub(HistoryTier::Hour.retention().as_millis() as u64);
+        self.connection.execute(
+            "DELETE FROM annotations WHERE ts < ?1",
+            params![cutoff as i64],
+        )?;
+        self.connection.execute(
+            "DELETE FROM alarms WHERE cleared_at < ?1",
+            params![cutoff as i64],
+        )?;
+        Ok(())
+    }
+}
+
+/// Averages the numeric fields of event payloads
+///
+/// Fields that aren't numbers take the value of the last payload.
+/// `null` values (e.g. no measurement) don't count into the average.
+fn average(values: &[Value]) -> Value {
+    let mut sums: BTreeMap<&str, (f64, u32)> = BTreeMap::new();
+    let mut result = Map::new();
+    for value in values {
+        let Value::Object(fields) = value else {
+            continue;
+        };
+        for (key, field) in fields {
+            if let Some(number) = field.as_f64() {
+                let sum = sums.entry(key).or_insert((0.0, 0));
+                sum.0 += number;
+                sum.1 += 1;
+            } else if !field.is_null() || !result.contains_key(key) {
+                result.insert(key.clone(), field.clone());
+            }
+        }
+    }
+    for (key, (sum, count)) in sums {
+        result.insert(key.to_string(), Value::from(sum / count as f64));
+    }
+    Value::Object(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use control_core::machines::identification::MachineIdentification;
+    use serde_json::json;
+
+    const HOUR: u64 = 60 * 60 * 1000;
+
+    fn machine() -> MachineIdentificationUnique {
+        MachineIdentificationUnique {
+            machine_identification: MachineIdentification {
+                vendor: 1,
+                machine: 6,
+            },
+            serial: 1,
+        }
+    }
+
+    fn sample(ts: u64, diameter: f64) -> HistorySample {
+        HistorySample {
+            ts,
+            data: json!({ "diameter": diameter }),
+        }
+    }
+
+    #[test]
+    fn test_average_skips_nulls() {
+        let average = average(&[
+            json!({ "diameter": 1.0, "mode": "a" }),
+            json!({ "diameter": null, "mode": "b" }),
+            json!({ "diameter": 2.0, "mode": "c" }),
+        ]);
+        assert_eq!(average, json!({ "diameter": 1.5, "mode": "c" }));
+    }
+
+    #[test]
+    fn test_query_range() {
+        let mut store = HistoryStore::open_in_memory().unwrap();
+        let samples: Vec<_> = (0..10).map(|i| sample(i * 1000, i as f64)).collect();
+        store
+            .insert(&machine(), "LiveValuesEvent", &samples)
+            .unwrap();
+
+        let queried = store
+            .query(&machine(), "LiveValuesEvent", HistoryTier::Raw, 2000, 4000)
+            .unwrap();
+        assert_eq!(queried, samples[2..=4].to_vec());
+    }
+
+    #[test]
+    fn test_rollup_and_prune() {
+        let mut store = HistoryStore::open_in_memory().unwrap();
+        // one sample per second over two minutes
+        let samples: Vec<_> = (0..120)
+            .map(|i| sample(i * 1000, if i < 60 { 1.0 } else { 3.0 }))
+            .collect();
+        store
+            .insert(&machine(), "LiveValuesEvent", &samples)
+            .unwrap();
+
+        // only the first minute is complete
+        store.rollup(90 * 1000).unwrap();
+        let minutes = store
+            .query(&machine(), "LiveValuesEvent", HistoryTier::Minute, 0, HOUR)
+            .unwrap();
+        assert_eq!(minutes, vec![sample(0, 1.0)]);
+
+        // the second rollup continues where the first one stopped
+        store.rollup(HOUR).unwrap();
+        let minutes = store
+            .query(&machine(), "LiveValuesEvent", HistoryTier::Minute, 0, HOUR)
+            .unwrap();
+        assert_eq!(minutes, vec![sample(0, 1.0), sample(60 * 1000, 3.0)]);
+        let hours = store
+            .query(&machine(), "LiveValuesEvent", HistoryTier::Hour, 0, HOUR)
+            .unwrap();
+        assert_eq!(hours, vec![sample(0, 2.0)]);
+
+        // raw samples are gone after a day, the minutes stay
+        store.prune(25 * HOUR).unwrap();
+        let raw = store
+            .query(&machine(), "LiveValuesEvent", HistoryTier::Raw, 0, HOUR)
+            .unwrap();
+        assert!(raw.is_empty());
+        let minutes = store
+            .query(&machine(), "LiveValuesEvent", HistoryTier::Minute, 0, HOUR)
+            .unwrap();
+        assert_eq!(minutes.len(), 2);
+    }
+
+    #[test]
+    fn test_annotations() {
+        let mut store = HistoryStore::open_in_memory().unwrap();
+        let annotation = |ts, run_id| Annotation {
+            ts,
+            user: "operator".to_string(),
+            text: "added regrind".to_string(),
+            run_id,
+        };
+        store.insert_annotation(&annotation(HOUR, Some(7))).unwrap();
+        store
+            .insert_annotation(&annotation(2 * HOUR, None))
+            .unwrap();
+
+        assert_eq!(
+            store.annotations(0, HOUR).unwrap(),
+            vec![annotation(HOUR, Some(7))]
+        );
+        store.prune(400 * 24 * HOUR).unwrap();
+        assert!(store.annotations(0, 400 * 24 * HOUR).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_alarm_history() {
+        let mut store = HistoryStore::open_in_memory().unwrap();
+        let alarm = |id, severity, message: &str, raised_at, cleared_at: Option<u64>| Alarm {
+            id,
+            machine: Some(machine()),
+            code: "out_of_tolerance".to_string(),
+            message: message.to_string(),
+            severity,
+            state: if cleared_at.is_some() {
+                AlarmState::Cleared
+            } else {
+                AlarmState::Active
+            },
+            raised_at,
+            acknowledged_at: None,
+            acknowledged_by: None,
+            cleared_at,
+        };
+        let night = alarm(
+            1,
+            AlarmSeverity::Warning,
+            "Diameter low",
+            HOUR,
+            Some(2 * HOUR),
+        );
+        let open = alarm(2, AlarmSeverity::Critical, "Heater failed", 3 * HOUR, None);
+        store.upsert_alarms(&[night.clone(), open.clone()]).unwrap();
+        assert_eq!(store.last_alarm_id().unwrap(), 2);
+
+        let query = |query: AlarmQuery| store.query_alarms(&query).unwrap();
+        assert_eq!(
+            query(AlarmQuery::default()).alarms,
+            vec![open.clone(), night.clone()]
+        );
+        // the open alarm is still active later on
+        let page = query(AlarmQuery {
+            from: Some(4 * HOUR),
+            ..Default::default()
+        });
+        assert_eq!(page.alarms, vec![open.clone()]);
+        let page = query(AlarmQuery {
+            severity: Some(AlarmSeverity::Critical),
+            ..Default::default()
+        });
+        assert_eq!(page.alarms, vec![open]);
+        let page = query(AlarmQuery {
+            text: Some("diameter".to_string()),
+            machine: Some("1/6/1".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(page.alarms, vec![night.clone()]);
+        let page = query(AlarmQuery {
+            limit: Some(1),
+            offset: Some(1),
+            ..Default::default()
+        });
+        assert_eq!(page.total, 2);
+        assert_eq!(page.alarms, vec![night]);
+
+        store.clear_open_alarms(5 * HOUR).unwrap();
+        assert!(store.query_open_alarms().unwrap().is_empty());
+        store.prune(400 * 24 * HOUR).unwrap();
+        let page = store.query_alarms(&AlarmQuery::default()).unwrap();
+        assert_eq!(page.total, 0);
+    }
+
+    #[test]
+    fn test_tier_for_range() {
+        let now = 100 * 24 * HOUR;
+        assert_eq!(
+            HistoryTier::for_range(now - HOUR, now, now),
+            HistoryTier::Raw
+        );
+        assert_eq!(
+            HistoryTier::for_range(now - 48 * HOUR, now, now),
+            HistoryTier::Minute
+        );
+        assert_eq!(HistoryTier::for_range(0, now, now), HistoryTier::Hour);
+    }
+}
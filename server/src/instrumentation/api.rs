@@ -0,0 +1,32 @@
+use super::{BUCKET_BOUNDS_US, MachineActReport};
+use crate::{app_state::AppState, serial::sniffer::api::DiagnosticsNamespaceEvents};
+use control_core::socketio::{event::BuildEvent, namespace::NamespaceCacheingLogic};
+use control_core_derive::BuildEvent;
+use serde::Serialize;
+use std::sync::Arc;
+
+#[derive(Serialize, Debug, Clone, BuildEvent)]
+pub struct PerformanceEvent {
+    /// Upper bounds of the histogram buckets in µs
+    pub bucket_bounds_us: Vec<u64>,
+    pub machines: Vec<MachineActReport>,
+}
+
+pub async fn performance_event(app_state: &Arc<AppState>) -> PerformanceEvent {
+    PerformanceEvent {
+        bucket_bounds_us: BUCKET_BOUNDS_US.to_vec(),
+        machines: app_state.instrumentation.read().await.report(),
+    }
+}
+
+/// Emits the act timing of all machines to the diagnostics namespace
+pub async fn emit_performance(app_state: &Arc<AppState>) {
+    let event = performance_event(app_state).await.build();
+    let diagnostics_namespace = &mut app_state
+        .socketio_setup
+        .namespaces
+        .write()
+        .await
+        .diagnostics_namespace;
+    diagnostics_namespace.emit(DiagnosticsNamespaceEvents::Performance(event));
+}
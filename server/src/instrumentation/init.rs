@@ -0,0 +1,75 @@
+use super::api::emit_performance;
+use crate::{
+    app_state::AppState,
+    panic::{PanicDetails, send_panic},
+};
+use smol::channel::Sender;
+use std::{sync::Arc, time::Duration};
+
+/// Periodically emits the act timing and warns about machines exceeding their cycle budget
+pub fn init_instrumentation(
+    thread_panic_tx: Sender<PanicDetails>,
+    app_state: Arc<AppState>,
+) -> Result<(), anyhow::Error> {
+    let interval = {
+        let instrumentation = app_state.instrumentation.read_blocking();
+        tracing::info!(
+            "Measuring act cycles with a budget of {}µs",
+            instrumentation.config.budget_us
+        );
+        Duration::from_millis(instrumentation.config.report_interval_ms.max(100))
+    };
+
+    std::thread::Builder::new()
+        .name("instrumentation".to_owned())
+        .spawn(move || {
+            send_panic(thread_panic_tx);
+            smol::block_on(async {
+                loop {
+                    smol::Timer::after(interval).await;
+                    report(&app_state).await;
+                }
+            });
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_instrumentation] Failed to spawn instrumentation thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    Ok(())
+}
+
+async fn report(app_state: &Arc<AppState>) {
+    let connected: Vec<_> = app_state
+        .machines
+        .read()
+        .await
+        .iter()
+        .filter(|(_, slot)| {
+            slot.lock_blocking()
+                .machine_connection
+                .to_machine()
+                .is_some()
+        })
+        .map(|(machine_identification_unique, _)| machine_identification_unique.clone())
+        .collect();
+
+    let overruns = {
+        let mut instrumentation = app_state.instrumentation.write().await;
+        instrumentation.retain(&connected);
+        instrumentation.take_overruns()
+    };
+    for (machine_identification_unique, count, longest) in overruns {
+        tracing::warn!(
+            "Machine {} exceeded its cycle budget in {} act cycles, longest took {}µs",
+            machine_identification_unique,
+            count,
+            longest.as_micros()
+        );
+    }
+
+    emit_performance(app_state).await;
+}
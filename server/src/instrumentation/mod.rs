@@ -0,0 +1,347 @@
+use crate::{machines::machine_slug, storage};
+use control_core::machines::identification::MachineIdentificationUnique;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+pub mod api;
+pub mod init;
+pub mod scope;
+
+/// File inside [`crate::storage::data_dir`] configuring the act loop instrumentation
+///
+/// The defaults are used if the file does not exist.
+pub const INSTRUMENTATION_FILE: &str = "instrumentation.json";
+
+/// Upper bounds of the histogram buckets in µs, the last bucket takes everything above
+pub const BUCKET_BOUNDS_US: [u64; 13] = [
+    10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000,
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InstrumentationConfig {
+    /// Time one act call may take before it counts as budget overrun
+    #[serde(default = "default_budget_us")]
+    pub budget_us: u64,
+    /// Budgets of single machine types by slug, e.g. `{"winder": 400}`
+    #[serde(default)]
+    pub machine_budgets_us: BTreeMap<String, u64>,
+    /// Interval of the performance event and the overrun warnings
+    #[serde(default = "default_report_interval_ms")]
+    pub report_interval_ms: u64,
+}
+
+const fn default_budget_us() -> u64 {
+    250
+}
+
+const fn default_report_interval_ms() -> u64 {
+    1000
+}
+
+impl Default for InstrumentationConfig {
+    fn default() -> Self {
+        Self {
+            budget_us: default_budget_us(),
+            machine_budgets_us: BTreeMap::new(),
+            report_interval_ms: default_report_interval_ms(),
+        }
+    }
+}
+
+impl InstrumentationConfig {
+    /// Reads [`INSTRUMENTATION_FILE`], the defaults if it does not exist
+    pub fn read() -> Result<Self, anyhow::Error> {
+        let path = storage::data_dir().join(INSTRUMENTATION_FILE);
+        Ok(storage::read_json::<Self>(&path)?.unwrap_or_default())
+    }
+
+    /// Reads [`INSTRUMENTATION_FILE`], falls back to the defaults so the server still starts
+    pub fn load() -> Self {
+        Self::read().unwrap_or_else(|e| {
+            tracing::error!(
+                "Failed to read instrumentation config, using defaults: {:?}",
+                e
+            );
+            Self::default()
+        })
+    }
+
+    /// Cycle budget of a machine
+    pub fn budget(&self, machine: &MachineIdentificationUnique) -> Duration {
+        let budget_us = machine_slug(machine.machine_identification.machine)
+            .and_then(|slug| self.machine_budgets_us.get(slug))
+            .copied()
+            .unwrap_or(self.budget_us);
+        Duration::from_micros(budget_us)
+    }
+}
+
+/// Histogram over [`BUCKET_BOUNDS_US`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    /// One more bucket than bounds for the values above the last bound
+    counts: [u64; BUCKET_BOUNDS_US.len() + 1],
+    count: u64,
+    sum: Duration,
+    max: Duration,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            counts: [0; BUCKET_BOUNDS_US.len() + 1],
+            count: 0,
+            sum: Duration::ZERO,
+            max: Duration::ZERO,
+        }
+    }
+}
+
+impl Histogram {
+    pub fn record(&mut self, value: Duration) {
+        let value_us = value.as_micros() as u64;
+        let bucket = BUCKET_BOUNDS_US
+            .iter()
+            .position(|bound| value_us <= *bound)
+            .unwrap_or(BUCKET_BOUNDS_US.len());
+        self.counts[bucket] += 1;
+        self.count += 1;
+        self.sum += value;
+        self.max = self.max.max(value);
+    }
+
+    /// Upper bound of the bucket the `quantile` falls into, the maximum for the last bucket
+    pub fn quantile(&self, quantile: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        let rank = ((self.count as f64 * quantile).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return BUCKET_BOUNDS_US
+                    .get(bucket)
+                    .map(|bound| Duration::from_micros(*bound).min(self.max))
+                    .unwrap_or(self.max);
+            }
+        }
+        self.max
+    }
+
+    pub fn report(&self) -> HistogramReport {
+        HistogramReport {
+            counts: self.counts.to_vec(),
+            count: self.count,
+            sum_us: self.sum.as_secs_f64() * 1e6,
+            mean_us: if self.count > 0 {
+                self.sum.as_secs_f64() * 1e6 / self.count as f64
+            } else {
+                0.0
+            },
+            max_us: self.max.as_secs_f64() * 1e6,
+            p50_us: self.quantile(0.5).as_secs_f64() * 1e6,
+            p99_us: self.quantile(0.99).as_secs_f64() * 1e6,
+        }
+    }
+}
+
+/// [`Histogram`] as returned by the API
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct HistogramReport {
+    /// Counts per bucket of [`BUCKET_BOUNDS_US`], the last one counts the values above
+    pub counts: Vec<u64>,
+    pub count: u64,
+    pub sum_us: f64,
+    pub mean_us: f64,
+    pub max_us: f64,
+    /// Upper bounds of the buckets the quantiles fall into
+    pub p50_us: f64,
+    pub p99_us: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MachineActMetrics {
+    /// Duration of the act calls
+    pub act: Histogram,
+    /// Difference between two consecutive intervals from one act call to the next
+    pub jitter: Histogram,
+    last_start: Option<Instant>,
+    last_interval: Option<Duration>,
+    pub budget: Duration,
+    pub overruns: u64,
+    /// Overruns since the last report
+    pub pending_overruns: u64,
+    /// Longest act call since the last report
+    pub pending_max: Duration,
+}
+
+/// [`MachineActMetrics`] as returned by the API
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MachineActReport {
+    pub machine_identification_unique: MachineIdentificationUnique,
+    pub act: HistogramReport,
+    pub jitter: HistogramReport,
+    pub budget_us: f64,
+    pub overruns: u64,
+}
+
+/// Per machine act timing of the control loop
+#[derive(Debug)]
+pub struct ActInstrumentation {
+    pub config: InstrumentationConfig,
+    machines: HashMap<MachineIdentificationUnique, MachineActMetrics>,
+}
+
+impl ActInstrumentation {
+    pub fn new(config: InstrumentationConfig) -> Self {
+        Self {
+            config,
+            machines: HashMap::new(),
+        }
+    }
+
+    /// Records an act call that started at `start` and took `duration`
+    pub fn record(
+        &mut self,
+        machine: &MachineIdentificationUnique,
+        start: Instant,
+        duration: Duration,
+    ) {
+        if !self.machines.contains_key(machine) {
+            let metrics = MachineActMetrics {
+                budget: self.config.budget(machine),
+                ..Default::default()
+            };
+            self.machines.insert(machine.clone(), metrics);
+        }
+        let Some(metrics) = self.machines.get_mut(machine) else {
+            return;
+        };
+
+        metrics.act.record(duration);
+        if let Some(last_start) = metrics.last_start {
+            let interval = start.saturating_duration_since(last_start);
+            if let Some(last_interval) = metrics.last_interval {
+                metrics.jitter.record(interval.abs_diff(last_interval));
+            }
+            metrics.last_interval = Some(interval);
+        }
+        metrics.last_start = Some(start);
+
+        if duration > metrics.budget {
+            metrics.overruns += 1;
+            metrics.pending_overruns += 1;
+            metrics.pending_max = metrics.pending_max.max(duration);
+        }
+    }
+
+    /// Forgets machines that are no longer connected
+    pub fn retain(&mut self, connected: &[MachineIdentificationUnique]) {
+        self.machines
+            .retain(|machine, _| connected.contains(machine));
+    }
+
+    /// Machines that overran their budget since the last call with count and longest call
+    pub fn take_overruns(&mut self) -> Vec<(MachineIdentificationUnique, u64, Duration)> {
+        let mut overruns = vec![];
+        for (machine, metrics) in self.machines.iter_mut() {
+            if metrics.pending_overruns > 0 {
+                overruns.push((
+                    machine.clone(),
+                    metrics.pending_overruns,
+                    metrics.pending_max,
+                ));
+            }
+            metrics.pending_overruns = 0;
+            metrics.pending_max = Duration::ZERO;
+        }
+        overruns
+    }
+
+    pub fn get(&self, machine: &MachineIdentificationUnique) -> Option<&MachineActMetrics> {
+        self.machines.get(machine)
+    }
+
+    pub fn report(&self) -> Vec<MachineActReport> {
+        let mut reports: Vec<_> = self
+            .machines
+            .iter()
+            .map(|(machine, metrics)| MachineActReport {
+                machine_identification_unique: machine.clone(),
+                act: metrics.act.report(),
+                jitter: metrics.jitter.report(),
+                budget_us: metrics.budget.as_secs_f64() * 1e6,
+                overruns: metrics.overruns,
+            })
+            .collect();
+        reports.sort_by_key(|report| report.machine_identification_unique.to_string());
+        reports
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machines::{MACHINE_WINDER_V1, VENDOR_QITECH};
+    use control_core::machines::identification::MachineIdentification;
+
+    fn winder() -> MachineIdentificationUnique {
+        MachineIdentificationUnique {
+            machine_identification: MachineIdentification {
+                vendor: VENDOR_QITECH,
+                machine: MACHINE_WINDER_V1,
+            },
+            serial: 1,
+        }
+    }
+
+    #[test]
+    fn test_histogram() {
+        let mut histogram = Histogram::default();
+        for us in [5, 20, 20, 80, 3_000] {
+            histogram.record(Duration::from_micros(us));
+        }
+        let report = histogram.report();
+        assert_eq!(report.count, 5);
+        assert_eq!(report.counts[0], 1);
+        assert_eq!(report.counts[1], 2);
+        assert_eq!(report.counts[8], 1);
+        assert_eq!(histogram.quantile(0.5), Duration::from_micros(25));
+        // the top bucket is capped by the maximum
+        assert_eq!(histogram.quantile(0.99), Duration::from_micros(3_000));
+    }
+
+    #[test]
+    fn test_jitter_and_overruns() {
+        let mut config = InstrumentationConfig::default();
+        config.machine_budgets_us.insert("winder".to_string(), 100);
+        let mut instrumentation = ActInstrumentation::new(config);
+        let start = Instant::now();
+
+        // intervals of 500µs, 500µs and 700µs
+        for (offset_us, duration_us) in [(0, 50), (500, 50), (1_000, 150), (1_700, 50)] {
+            instrumentation.record(
+                &winder(),
+                start + Duration::from_micros(offset_us),
+                Duration::from_micros(duration_us),
+            );
+        }
+
+        let metrics = instrumentation.get(&winder()).unwrap();
+        assert_eq!(metrics.budget, Duration::from_micros(100));
+        assert_eq!(metrics.act.report().count, 4);
+        assert_eq!(metrics.jitter.report().count, 2);
+        assert_eq!(metrics.jitter.max, Duration::from_micros(200));
+
+        assert_eq!(
+            instrumentation.take_overruns(),
+            vec![(winder(), 1, Duration::from_micros(150))]
+        );
+        assert!(instrumentation.take_overruns().is_empty());
+        assert_eq!(instrumentation.report()[0].overruns, 1);
+    }
+}
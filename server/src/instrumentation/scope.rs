@@ -0,0 +1,134 @@
+//! Streams the armed channels of [`control_core::scope`] to the diagnostics namespace
+
+use crate::{app_state::AppState, serial::sniffer::api::DiagnosticsNamespaceEvents};
+use control_core::{
+    scope::{self, MAX_ARMED, ScopeChannelInfo, ScopeSample},
+    socketio::{event::BuildEvent, namespace::NamespaceCacheingLogic},
+};
+use control_core_derive::BuildEvent;
+use serde::{Deserialize, Serialize};
+use smol::channel::Receiver;
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+/// Samples waiting for the stream, 8 channels probed every millisecond fill it in 2 s
+const SAMPLE_QUEUE_LEN: usize = 16_384;
+
+/// Interval of the scope events
+const STREAM_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Deserialize, Serialize, Debug)]
+pub enum Mutation {
+    /// Streams the samples of the named channels, replaces the armed channels
+    Arm(Vec<String>),
+    Disarm,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ScopeState {
+    pub channels: Vec<ScopeChannelInfo>,
+    pub max_armed: usize,
+    /// Samples dropped because the stream fell behind, since the channels were armed
+    pub dropped: u64,
+}
+
+pub fn state() -> ScopeState {
+    ScopeState {
+        channels: scope::channels(),
+        max_armed: MAX_ARMED,
+        dropped: scope::dropped_samples(),
+    }
+}
+
+/// Samples of one channel, `t` in µs on the time base of the event `mono`
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct ScopeTrace {
+    pub t: Vec<u64>,
+    pub values: Vec<f64>,
+}
+
+#[derive(Serialize, Debug, Clone, BuildEvent)]
+pub struct ScopeEvent {
+    /// Samples since the last event by channel name
+    pub traces: BTreeMap<String, ScopeTrace>,
+}
+
+/// Arms the channels and streams their samples until they are disarmed or armed again
+pub fn arm(names: Vec<String>, app_state: Arc<AppState>) -> Result<(), anyhow::Error> {
+    let (tx, rx) = smol::channel::bounded(SAMPLE_QUEUE_LEN);
+    scope::arm(names.clone(), tx)?;
+    std::thread::Builder::new()
+        .name("scope".to_owned())
+        .spawn(move || smol::block_on(stream(&app_state, rx)))
+        .map_err(|e| {
+            scope::disarm();
+            anyhow::anyhow!(
+                "[{}::arm] Failed to spawn scope thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+    tracing::info!("Armed scope channels {:?}", names);
+    Ok(())
+}
+
+/// Ends once the sink is dropped by disarming or arming again
+async fn stream(app_state: &Arc<AppState>, rx: Receiver<ScopeSample>) {
+    loop {
+        smol::Timer::after(STREAM_INTERVAL).await;
+        let samples: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        if samples.is_empty() {
+            if rx.is_closed() {
+                return;
+            }
+            continue;
+        }
+        let event = ScopeEvent {
+            traces: traces(samples),
+        }
+        .build();
+        app_state
+            .socketio_setup
+            .namespaces
+            .write()
+            .await
+            .diagnostics_namespace
+            .emit(DiagnosticsNamespaceEvents::Scope(event));
+    }
+}
+
+fn traces(samples: Vec<ScopeSample>) -> BTreeMap<String, ScopeTrace> {
+    let mut traces: BTreeMap<String, ScopeTrace> = BTreeMap::new();
+    for sample in samples {
+        let trace = traces.entry(sample.channel.to_string()).or_default();
+        trace.t.push(sample.t);
+        trace.values.push(sample.value);
+    }
+    traces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traces() {
+        let sample = |channel: &str, t, value| ScopeSample {
+            channel: channel.into(),
+            t,
+            value,
+        };
+        let traces = traces(vec![
+            sample("winder/1/diameter_loop/p", 1000, 0.5),
+            sample("winder/1/diameter_loop/i", 1000, 0.1),
+            sample("winder/1/diameter_loop/p", 2000, 0.25),
+        ]);
+        assert_eq!(
+            traces["winder/1/diameter_loop/p"],
+            ScopeTrace {
+                t: vec![1000, 2000],
+                values: vec![0.5, 0.25],
+            }
+        );
+        assert_eq!(traces["winder/1/diameter_loop/i"].values, vec![0.1]);
+    }
+}
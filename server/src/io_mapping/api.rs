@@ -0,0 +1,127 @@
+use super::{IO_MAPPINGS, MachineIoMapping, MachineIoSignals};
+use crate::{
+    app_state::AppState,
+    machines::{
+        digital_io::DigitalSignalState, machine_default_io_mapping, machine_io_signals,
+        winder2::Winder2,
+    },
+};
+use control_core::machines::identification::MachineIdentificationUnique;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Deserialize, Serialize, Debug)]
+pub enum Mutation {
+    /// Assign the digital signals of a machine to terminal channels
+    SetIoMapping {
+        machine_identification_unique: MachineIdentificationUnique,
+        mapping: MachineIoMapping,
+    },
+    /// Go back to the default wiring of the machine type
+    ResetIoMapping {
+        machine_identification_unique: MachineIdentificationUnique,
+    },
+}
+
+/// I/O mapping of a machine as returned by the API
+#[derive(Serialize, Debug, Clone)]
+pub struct IoMappingState {
+    pub machine_identification_unique: MachineIdentificationUnique,
+    pub signals: MachineIoSignals,
+    pub mapping: MachineIoMapping,
+    /// Whether the mapping differs from the default wiring
+    pub custom: bool,
+    /// Values of the assigned signals, empty while the machine is not connected
+    pub inputs: Vec<DigitalSignalState>,
+    pub outputs: Vec<DigitalSignalState>,
+}
+
+/// Signals of a machine type, fails for machines without configurable I/O
+pub fn signals_of(
+    machine_identification_unique: &MachineIdentificationUnique,
+) -> Result<MachineIoSignals, anyhow::Error> {
+    machine_io_signals(machine_identification_unique.machine_identification.machine).ok_or_else(
+        || {
+            anyhow::anyhow!(
+                "[{}::signals_of] Machine {} has no configurable I/O",
+                module_path!(),
+                machine_identification_unique
+            )
+        },
+    )
+}
+
+/// Mapping of all machines with configurable I/O that are known to the machine manager
+pub async fn io_mapping_states(app_state: &Arc<AppState>) -> Vec<IoMappingState> {
+    let machines: Vec<_> = app_state
+        .machines
+        .read()
+        .await
+        .iter()
+        .map(|(machine_identification_unique, slot)| {
+            let machine = slot.lock_blocking().machine_connection.to_machine();
+            (machine_identification_unique.clone(), machine)
+        })
+        .collect();
+
+    let mut states = vec![];
+    for (machine_identification_unique, machine) in machines {
+        let Ok(signals) = signals_of(&machine_identification_unique) else {
+            continue;
+        };
+        let stored = IO_MAPPINGS
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&machine_identification_unique)
+            .cloned();
+        let mut state = IoMappingState {
+            custom: stored.is_some(),
+            mapping: stored.unwrap_or_else(|| {
+                machine_default_io_mapping(
+                    machine_identification_unique.machine_identification.machine,
+                )
+            }),
+            machine_identification_unique,
+            signals,
+            inputs: vec![],
+            outputs: vec![],
+        };
+
+        if let Some(machine) = machine {
+            let guard = machine.lock().await;
+            if let Some(winder) = guard.as_any().downcast_ref::<Winder2>() {
+                state.mapping = winder.io.mapping().clone();
+                (state.inputs, state.outputs) = winder.io.states();
+            }
+        }
+        states.push(state);
+    }
+    states.sort_by_key(|state| state.machine_identification_unique.to_string());
+    states
+}
+
+/// Assigns the signals of a running machine to other channels
+///
+/// Machines that are not connected pick the mapping up when they are created.
+pub async fn apply_io_mapping(
+    app_state: &Arc<AppState>,
+    machine_identification_unique: &MachineIdentificationUnique,
+    mapping: MachineIoMapping,
+) -> Result<(), anyhow::Error> {
+    let machine = {
+        let machines = app_state.machines.read().await;
+        machines
+            .get(machine_identification_unique)
+            .and_then(|slot| slot.lock_blocking().machine_connection.to_machine())
+    };
+    let Some(machine) = machine else {
+        return Ok(());
+    };
+
+    let mut machine = machine.lock().await;
+    if let Some(winder) = machine.as_any_mut().downcast_mut::<Winder2>() {
+        tracing::info!("Applying I/O mapping to winder {:?}", mapping);
+        return winder.set_io_mapping(mapping);
+    }
+    Ok(())
+}
@@ -0,0 +1,274 @@
+use crate::storage;
+use control_core::machines::identification::MachineIdentificationUnique;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::PathBuf,
+    sync::RwLock,
+};
+
+pub mod api;
+
+/// File inside [`crate::storage::data_dir`] the I/O mappings are stored in
+pub const IO_MAPPING_FILE: &str = "io_mapping.json";
+
+lazy_static! {
+    /// Mappings of all machines, read when a machine is created
+    pub static ref IO_MAPPINGS: RwLock<IoMappingStore> =
+        RwLock::new(IoMappingStore::load(storage::data_dir().join(IO_MAPPING_FILE)));
+}
+
+/// A channel of a digital terminal in the device group of a machine
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DigitalChannel {
+    /// Role of the terminal in the device group
+    pub role: u16,
+    /// Channel of the terminal starting at 1, e.g. 2 for `DI2`
+    pub channel: u8,
+    /// Low active signal, e.g. a normally closed door contact
+    #[serde(default)]
+    pub inverted: bool,
+}
+
+impl DigitalChannel {
+    pub const fn new(role: u16, channel: u8) -> Self {
+        Self {
+            role,
+            channel,
+            inverted: false,
+        }
+    }
+
+    /// Identifies the terminal channel regardless of its polarity
+    pub const fn key(&self) -> (u16, u8) {
+        (self.role, self.channel)
+    }
+}
+
+/// Signals a machine type reads and drives
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MachineIoSignals {
+    pub inputs: &'static [&'static str],
+    pub outputs: &'static [&'static str],
+}
+
+/// Assignment of the signals of a machine to terminal channels
+///
+/// Unassigned inputs read as inactive, unassigned outputs are not driven.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct MachineIoMapping {
+    #[serde(default)]
+    pub inputs: BTreeMap<String, DigitalChannel>,
+    #[serde(default)]
+    pub outputs: BTreeMap<String, DigitalChannel>,
+}
+
+impl MachineIoMapping {
+    /// Checks that all signals exist and no output channel is driven twice
+    pub fn validate(&self, signals: &MachineIoSignals) -> Result<(), anyhow::Error> {
+        for (signal, channel) in self.inputs.iter().chain(self.outputs.iter()) {
+            if channel.channel == 0 {
+                return Err(anyhow::anyhow!(
+                    "[{}::MachineIoMapping::validate] Channels of {} start at 1",
+                    module_path!(),
+                    signal
+                ));
+            }
+        }
+        if let Some(signal) = self
+            .inputs
+            .keys()
+            .find(|signal| !signals.inputs.contains(&signal.as_str()))
+        {
+            return Err(anyhow::anyhow!(
+                "[{}::MachineIoMapping::validate] Unknown input {}",
+                module_path!(),
+                signal
+            ));
+        }
+        if let Some(signal) = self
+            .outputs
+            .keys()
+            .find(|signal| !signals.outputs.contains(&signal.as_str()))
+        {
+            return Err(anyhow::anyhow!(
+                "[{}::MachineIoMapping::validate] Unknown output {}",
+                module_path!(),
+                signal
+            ));
+        }
+
+        let mut driven = HashSet::new();
+        for (signal, channel) in &self.outputs {
+            if !driven.insert(channel.key()) {
+                return Err(anyhow::anyhow!(
+                    "[{}::MachineIoMapping::validate] Output {} uses channel {} of role {} twice",
+                    module_path!(),
+                    signal,
+                    channel.channel,
+                    channel.role
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct IoMappingEntry {
+    pub machine_identification_unique: MachineIdentificationUnique,
+    pub mapping: MachineIoMapping,
+}
+
+/// Persisted I/O mappings, machines without an entry use their default wiring
+#[derive(Debug)]
+pub struct IoMappingStore {
+    path: PathBuf,
+    mappings: HashMap<MachineIdentificationUnique, MachineIoMapping>,
+}
+
+impl IoMappingStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            mappings: HashMap::new(),
+        }
+    }
+
+    /// Loads the mappings from `path`
+    ///
+    /// A missing or broken file results in an empty store so the machines start with their
+    /// default wiring.
+    pub fn load(path: PathBuf) -> Self {
+        let mut store = Self::new(path);
+        match storage::read_json::<Vec<IoMappingEntry>>(&store.path) {
+            Ok(Some(entries)) => {
+                for entry in entries {
+                    store
+                        .mappings
+                        .insert(entry.machine_identification_unique, entry.mapping);
+                }
+                tracing::info!("Loaded {} I/O mappings", store.mappings.len());
+            }
+            Ok(None) => {}
+            Err(e) => tracing::error!("Failed to load I/O mappings: {:?}", e),
+        }
+        store
+    }
+
+    pub fn get(&self, machine: &MachineIdentificationUnique) -> Option<&MachineIoMapping> {
+        self.mappings.get(machine)
+    }
+
+    pub fn list(&self) -> Vec<IoMappingEntry> {
+        let mut entries: Vec<_> = self
+            .mappings
+            .iter()
+            .map(|(machine, mapping)| IoMappingEntry {
+                machine_identification_unique: machine.clone(),
+                mapping: mapping.clone(),
+            })
+            .collect();
+        entries.sort_by_key(|entry| entry.machine_identification_unique.to_string());
+        entries
+    }
+
+    /// Inserts or replaces the mapping of a machine and writes the store to disk
+    pub fn save(
+        &mut self,
+        machine: MachineIdentificationUnique,
+        mapping: MachineIoMapping,
+    ) -> Result<(), anyhow::Error> {
+        self.mappings.insert(machine, mapping);
+        self.persist()
+    }
+
+    /// Removes the mapping of a machine so it falls back to its default wiring
+    pub fn reset(&mut self, machine: &MachineIdentificationUnique) -> Result<(), anyhow::Error> {
+        if self.mappings.remove(machine).is_none() {
+            return Err(anyhow::anyhow!(
+                "[{}::IoMappingStore::reset] Machine {} has no I/O mapping",
+                module_path!(),
+                machine
+            ));
+        }
+        self.persist()
+    }
+
+    fn persist(&self) -> Result<(), anyhow::Error> {
+        storage::write_json(&self.path, &self.list())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use control_core::machines::identification::MachineIdentification;
+
+    const SIGNALS: MachineIoSignals = MachineIoSignals {
+        inputs: &["end_stop", "door_contact"],
+        outputs: &["cutter", "brake"],
+    };
+
+    fn machine() -> MachineIdentificationUnique {
+        MachineIdentificationUnique {
+            machine_identification: MachineIdentification {
+                vendor: 1,
+                machine: 2,
+            },
+            serial: 7,
+        }
+    }
+
+    fn mapping() -> MachineIoMapping {
+        MachineIoMapping {
+            inputs: BTreeMap::from([("end_stop".to_string(), DigitalChannel::new(3, 1))]),
+            outputs: BTreeMap::from([("cutter".to_string(), DigitalChannel::new(5, 2))]),
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(mapping().validate(&SIGNALS).is_ok());
+
+        let mut unknown = mapping();
+        unknown
+            .inputs
+            .insert("spool_sensor".to_string(), DigitalChannel::new(3, 2));
+        assert!(unknown.validate(&SIGNALS).is_err());
+
+        let mut twice = mapping();
+        twice.outputs.insert(
+            "brake".to_string(),
+            DigitalChannel {
+                inverted: true,
+                ..DigitalChannel::new(5, 2)
+            },
+        );
+        assert!(twice.validate(&SIGNALS).is_err());
+
+        let mut zero = mapping();
+        zero.inputs
+            .insert("door_contact".to_string(), DigitalChannel::new(3, 0));
+        assert!(zero.validate(&SIGNALS).is_err());
+    }
+
+    #[test]
+    fn test_store_roundtrip() {
+        let path = std::env::temp_dir()
+            .join(format!("qitech-io-mapping-{}", std::process::id()))
+            .join(IO_MAPPING_FILE);
+
+        let mut store = IoMappingStore::new(path.clone());
+        store.save(machine(), mapping()).unwrap();
+
+        let loaded = IoMappingStore::load(path.clone());
+        assert_eq!(loaded.get(&machine()), Some(&mapping()));
+
+        let mut loaded = loaded;
+        loaded.reset(&machine()).unwrap();
+        assert!(loaded.reset(&machine()).is_err());
+        assert!(IoMappingStore::load(path).get(&machine()).is_none());
+    }
+}
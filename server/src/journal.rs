@@ -0,0 +1,166 @@
+use crate::{
+    panic::{PanicDetails, send_panic},
+    storage,
+};
+use control_core::machines::identification::MachineIdentificationUnique;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use smol::channel::{Receiver, Sender};
+use std::{
+    marker::PhantomData,
+    path::PathBuf,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+/// Directory inside [`crate::storage::data_dir`] with one journal file per machine
+pub const JOURNAL_DIR: &str = "journal";
+
+/// Pending writes, a full queue drops the snapshot and the next interval writes a newer one
+const JOURNAL_QUEUE_SIZE: usize = 64;
+
+static JOURNAL_TX: OnceLock<Sender<(PathBuf, Value)>> = OnceLock::new();
+
+/// Periodic snapshots of the controller state of one machine
+///
+/// Snapshots are serialized in the control loop but written by the journal thread, so a slow
+/// disk never delays an act cycle. Every file is replaced atomically, after a crash the last
+/// complete snapshot is read back by [`Journal::load`].
+#[derive(Debug)]
+pub struct Journal<T> {
+    path: PathBuf,
+    interval: Duration,
+    last_record: Option<Instant>,
+    snapshot: PhantomData<fn() -> T>,
+}
+
+impl<T: Serialize + DeserializeOwned> Journal<T> {
+    pub fn new(
+        machine_identification_unique: &MachineIdentificationUnique,
+        interval: Duration,
+    ) -> Self {
+        Self::in_dir(JOURNAL_DIR, machine_identification_unique, interval)
+    }
+
+    /// Journal in another directory inside [`crate::storage::data_dir`], for state that is
+    /// kept apart from the controller state
+    pub fn in_dir(
+        dir: &str,
+        machine_identification_unique: &MachineIdentificationUnique,
+        interval: Duration,
+    ) -> Self {
+        let machine_identification = &machine_identification_unique.machine_identification;
+        Self {
+            path: storage::data_dir().join(dir).join(format!(
+                "{}-{}-{}.json",
+                machine_identification.vendor,
+                machine_identification.machine,
+                machine_identification_unique.serial
+            )),
+            interval,
+            last_record: None,
+            snapshot: PhantomData,
+        }
+    }
+
+    /// Last snapshot written before the server stopped, `None` if there is none or it is unreadable
+    pub fn load(&self) -> Option<T> {
+        match storage::read_json::<T>(&self.path) {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                tracing::warn!("Ignoring the journal, starting from scratch: {:?}", e);
+                None
+            }
+        }
+    }
+
+    /// Whether the interval since the last snapshot elapsed
+    pub fn is_due(&self, now: Instant) -> bool {
+        self.last_record
+            .is_none_or(|last_record| now.duration_since(last_record) >= self.interval)
+    }
+
+    /// Records a snapshot, regardless of [`Journal::is_due`]
+    pub fn record(&mut self, now: Instant, snapshot: T) {
+        self.last_record = Some(now);
+
+        let Some(journal_tx) = JOURNAL_TX.get() else {
+            return;
+        };
+        let value = match serde_json::to_value(&snapshot) {
+            Ok(value) => value,
+            Err(e) => {
+                tracing::error!("Failed to serialize journal snapshot: {:?}", e);
+                return;
+            }
+        };
+        if journal_tx.try_send((self.path.clone(), value)).is_err() {
+            tracing::warn!(
+                "Journal queue is full, dropped a snapshot of {:?}",
+                self.path
+            );
+        }
+    }
+}
+
+/// Starts the thread writing the journal files, without it nothing is journaled
+pub fn init_journal(thread_panic_tx: Sender<PanicDetails>) -> Result<(), anyhow::Error> {
+    let (journal_tx, journal_rx) = smol::channel::bounded(JOURNAL_QUEUE_SIZE);
+
+    std::thread::Builder::new()
+        .name("journal".to_owned())
+        .spawn(move || {
+            send_panic(thread_panic_tx);
+            smol::block_on(write_journal(journal_rx));
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_journal] Failed to spawn journal thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    JOURNAL_TX.set(journal_tx).map_err(|_| {
+        anyhow::anyhow!(
+            "[{}::init_journal] Journal is already initialized",
+            module_path!()
+        )
+    })
+}
+
+async fn write_journal(journal_rx: Receiver<(PathBuf, Value)>) {
+    while let Ok((path, value)) = journal_rx.recv().await {
+        if let Err(e) = storage::write_json(&path, &value) {
+            tracing::error!("Failed to write journal\n{:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use control_core::machines::identification::MachineIdentification;
+
+    #[test]
+    fn test_journal_interval() {
+        let machine_identification_unique = MachineIdentificationUnique {
+            machine_identification: MachineIdentification {
+                vendor: 1,
+                machine: 2,
+            },
+            serial: 3,
+        };
+        let mut journal =
+            Journal::<u64>::new(&machine_identification_unique, Duration::from_secs(5));
+        assert!(journal.path.ends_with("journal/1-2-3.json"));
+
+        let start = Instant::now();
+        assert!(journal.is_due(start));
+
+        // without the journal thread snapshots are dropped but still restart the interval
+        journal.record(start, 1);
+        assert!(!journal.is_due(start + Duration::from_secs(4)));
+        assert!(journal.is_due(start + Duration::from_secs(5)));
+    }
+}
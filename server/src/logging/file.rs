@@ -0,0 +1,49 @@
+use crate::config::{LogFileConfig, LogRotation};
+use std::sync::OnceLock;
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{RollingFileAppender, Rotation},
+};
+use tracing_subscriber::Layer;
+
+/// Flushes the buffered log lines when the server exits
+static WORKER_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+
+/// Log lines written to rolling files, off the thread that logs them
+pub fn init_file_tracing<S>(
+    config: &LogFileConfig,
+) -> Result<Box<dyn Layer<S> + Send + Sync + 'static>, anyhow::Error>
+where
+    S: tracing::Subscriber + for<'lookup> tracing_subscriber::registry::LookupSpan<'lookup>,
+{
+    let directory = config.directory();
+    let appender = RollingFileAppender::builder()
+        .rotation(match config.rotation {
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        })
+        .filename_prefix("server")
+        .filename_suffix("log")
+        .max_log_files(config.max_files)
+        .build(&directory)
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_file_tracing] Failed to create log files in {:?}: {}",
+                module_path!(),
+                directory,
+                e
+            )
+        })?;
+
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+    let _ = WORKER_GUARD.set(guard);
+
+    let layer = tracing_subscriber::fmt::layer()
+        .with_ansi(false)
+        .with_thread_names(true)
+        .with_line_number(true)
+        .with_target(true)
+        .with_writer(writer);
+    Ok(Box::new(layer))
+}
@@ -1,4 +1,10 @@
-use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
+use crate::config::config;
+use std::sync::OnceLock;
+use tracing_subscriber::{
+    EnvFilter, Registry, layer::SubscriberExt, reload, util::SubscriberInitExt,
+};
+
+pub mod file;
 
 #[cfg(feature = "tracing-fmt")]
 pub mod fmt;
@@ -9,29 +15,87 @@ pub mod journald;
 #[cfg(feature = "tracing-otel")]
 pub mod opentelemetry;
 
+/// Set very strict filters for the noisy OpenTelemetry components
+const DEFAULT_FILTER: &str = "info,\
+    tower_http=debug,\
+    axum=debug,\
+    ethercrab=info,\
+    h2=error,\
+    tower=error,\
+    tonic=error,\
+    hyper=error,\
+    opentelemetry_otlp=error";
+
+static FILTER_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// Filter directives from `RUST_LOG`, then the server config, then the default
+///
+/// Use RUST_LOG env var to control logging, e.g.:
+/// RUST_LOG=info,h2=error,tower=error,tonic=error,hyper=error,opentelemetry_otlp=error
+pub fn configured_log_filter() -> String {
+    std::env::var(EnvFilter::DEFAULT_ENV)
+        .ok()
+        .into_iter()
+        .chain(config().logging.filter.clone())
+        .find(|filter| EnvFilter::try_new(filter).is_ok())
+        .unwrap_or_else(|| DEFAULT_FILTER.to_string())
+}
+
+/// Filter directives in effect, `None` before [`init_tracing`]
+pub fn log_filter() -> Option<String> {
+    FILTER_HANDLE
+        .get()?
+        .with_current(|filter| filter.to_string())
+        .ok()
+}
+
+/// Replaces the filter without a restart, e.g. `info,server::machines::winder2=debug`
+///
+/// `None` returns to [`configured_log_filter`].
+pub fn set_log_filter(directives: Option<&str>) -> Result<String, anyhow::Error> {
+    let directives = directives.map_or_else(configured_log_filter, str::to_string);
+    let filter = EnvFilter::try_new(&directives).map_err(|e| {
+        anyhow::anyhow!(
+            "[{}::set_log_filter] Invalid filter '{}': {}",
+            module_path!(),
+            directives,
+            e
+        )
+    })?;
+    let handle = FILTER_HANDLE.get().ok_or_else(|| {
+        anyhow::anyhow!(
+            "[{}::set_log_filter] Tracing is not initialized",
+            module_path!()
+        )
+    })?;
+    handle.reload(filter).map_err(|e| {
+        anyhow::anyhow!(
+            "[{}::set_log_filter] Failed to replace the filter: {}",
+            module_path!(),
+            e
+        )
+    })?;
+    tracing::info!("Log filter changed to {}", directives);
+    Ok(directives)
+}
+
 /// Initialize the basic tracing system (without OpenTelemetry if enabled)
 /// OpenTelemetry layer is deferred until async runtime is available
 pub fn init_tracing() {
-    // First try to get filter from env, then use default
-    // Use RUST_LOG env var to control logging, e.g.:
-    // RUST_LOG=info,h2=error,tower=error,tonic=error,hyper=error,opentelemetry_otlp=error
-    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
-        // Set very strict filters for the noisy OpenTelemetry components
-        EnvFilter::new(
-            "info,\
-             tower_http=debug,\
-             axum=debug,\
-             ethercrab=info,\
-             h2=error,\
-             tower=error,\
-             tonic=error,\
-             hyper=error,\
-             opentelemetry_otlp=error",
-        )
-    });
+    let (env_filter, filter_handle) = reload::Layer::new(EnvFilter::new(configured_log_filter()));
+    let _ = FILTER_HANDLE.set(filter_handle);
 
     let subscriber = tracing_subscriber::registry().with(env_filter);
 
+    // Add rolling log files if configured
+    let file_layer = config().logging.file.as_ref().map(file::init_file_tracing);
+    let (file_layer, file_error) = match file_layer {
+        Some(Ok(layer)) => (Some(layer), None),
+        Some(Err(e)) => (None, Some(e)),
+        None => (None, None),
+    };
+    let subscriber = subscriber.with(file_layer);
+
     // Add fmt layer if enabled
     let subscriber = {
         #[cfg(feature = "tracing-fmt")]
@@ -73,4 +137,8 @@ pub fn init_tracing() {
     };
 
     subscriber.init();
+
+    if let Some(e) = file_error {
+        tracing::error!("Failed to open log files, logging without them: {:?}", e);
+    }
 }
@@ -1,7 +1,6 @@
 use crate::app_state::AppState;
 use crate::panic::{PanicDetails, send_panic};
 use bitvec::prelude::*;
-use control_core::machines::connection::MachineConnection;
 use control_core::realtime::{set_core_affinity, set_realtime_priority};
 use smol::channel::Sender;
 use std::sync::Arc;
@@ -36,8 +35,9 @@ pub fn init_loop(
                 );
             }
 
-            loop {
-                let res = smol::block_on(rt.run(async { loop_once(app_state.clone()).await }));
+            for cycle in 0u64.. {
+                let res =
+                    smol::block_on(rt.run(async { loop_once(app_state.clone(), cycle).await }));
 
                 if let Err(err) = res {
                     tracing::error!("Loop failed\n{:?}", err);
@@ -58,8 +58,12 @@ pub fn init_loop(
     Ok(())
 }
 
+/// `cycle` counts the loop iterations, it is recorded on the span to tell cycles apart in traces
 #[instrument(skip(app_state))]
-pub async fn loop_once<'maindevice>(app_state: Arc<AppState>) -> Result<(), anyhow::Error> {
+pub async fn loop_once<'maindevice>(
+    app_state: Arc<AppState>,
+    cycle: u64,
+) -> Result<(), anyhow::Error> {
     // Record cycle start for performance metrics
     {
         let mut metrics = app_state.performance_metrics.write().await;
@@ -143,20 +147,73 @@ pub async fn loop_once<'maindevice>(app_state: Arc<AppState>) -> Result<(), anyh
 
         let machine_guard = app_state.machines.read().await;
         let now = std::time::Instant::now();
+        let mut act_durations = vec![];
+
+        let connected: Vec<_> = machine_guard
+            .iter()
+            .filter_map(|(machine_identification_unique, slot)| {
+                let machine = slot.lock_blocking().machine_connection.to_machine()?;
+                Some((machine_identification_unique.clone(), machine))
+            })
+            .collect();
+        let connected_ids: Vec<_> = connected
+            .iter()
+            .map(|(machine_identification_unique, _)| machine_identification_unique.clone())
+            .collect();
+
+        let mut scheduler = app_state.scheduler.write().await;
+        for (machine_identification_unique, priority) in scheduler.due(&connected_ids, now) {
+            // under load the remaining lower priority machines wait for the next cycle
+            if !scheduler.fits(priority, now.elapsed()) {
+                scheduler.defer(&machine_identification_unique);
+                continue;
+            }
+            let Some((_, machine)) = connected
+                .iter()
+                .find(|(machine, _)| *machine == machine_identification_unique)
+            else {
+                continue;
+            };
+            // if the machine is currenlty locked (likely processing API call)
+            // we skip the machine, it stays due
+            if let Some(mut machine_guard) = machine.try_lock() {
+                let span = trace_span!(
+                    "loop_once_act_machine",
+                    machine = %machine_identification_unique
+                );
+                let _enter = span.enter();
+                // execute machine
+                let act_start = Instant::now();
+                machine_guard.act(now);
+                scheduler.acted(&machine_identification_unique, now);
+                act_durations.push((
+                    machine_identification_unique,
+                    act_start,
+                    act_start.elapsed(),
+                ));
+            }
+        }
 
-        for machine in machine_guard.iter() {
-            let connection = &machine.1.lock_blocking().machine_connection;
-            if let MachineConnection::Connected(machine) = connection {
-                // if the machine is currenlty locked (likely processing API call)
-                // we skip the machine
-                if let Some(mut machine_guard) = machine.try_lock() {
-                    let span = trace_span!("loop_once_act_machine",);
-                    let _enter = span.enter();
-                    // execute machine
-                    machine_guard.act(now);
+        // send the batched events as one frame, namespaces locked by a subscribing socket
+        // are flushed next time
+        if scheduler.emit_due(now, now.elapsed()) {
+            for (_, slot) in machine_guard.iter() {
+                let slot = slot.lock_blocking();
+                if let Some(mut namespace) = slot.namespace.try_lock() {
+                    namespace.flush();
                 }
             }
         }
+        scheduler.cycle(now.elapsed());
+        drop(scheduler);
+
+        // report act cycles to the watchdog and the instrumentation
+        let mut watchdog = app_state.watchdog.write().await;
+        let mut instrumentation = app_state.instrumentation.write().await;
+        for (machine_identification_unique, act_start, act_duration) in act_durations {
+            instrumentation.record(&machine_identification_unique, act_start, act_duration);
+            watchdog.record(&machine_identification_unique, act_start, act_duration);
+        }
     }
 
     // only if we have an ethercat setup
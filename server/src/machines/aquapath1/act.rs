@@ -1,6 +1,6 @@
 use super::{AquaPathV1, AquaPathV1Mode};
-use control_core::machines::new::MachineAct;
-use std::time::{Duration, Instant};
+use control_core::machines::{new::MachineAct, values::WATER_TEMPERATURE};
+use std::time::Instant;
 
 impl MachineAct for AquaPathV1 {
     fn act(&mut self, now_ts: Instant) {
@@ -13,14 +13,30 @@ impl MachineAct for AquaPathV1 {
             }
         }
 
-        let now = Instant::now();
-
         self.front_controller.update(now_ts);
         self.back_controller.update(now_ts);
 
-        if now.duration_since(self.last_measurement_emit) > Duration::from_secs_f64(1.0 / 30.0) {
-            self.emit_live_values();
-            self.last_measurement_emit = now;
+        // the hottest zone limits what the line may do, e.g. derates the winder
+        let water_temperature = self
+            .front_controller
+            .current_temperature
+            .max(self.back_controller.current_temperature);
+        self.values.publish(
+            &self.machine_identification_unique,
+            WATER_TEMPERATURE,
+            water_temperature,
+            now_ts,
+        );
+
+        // the namespace limits the emit rate
+        self.emit_live_values();
+    }
+
+    fn act_safe_stop(&mut self) -> bool {
+        // turns cooling, heating and the pump off
+        if self.mode != AquaPathV1Mode::Standby {
+            self.set_mode_state(AquaPathV1Mode::Standby);
         }
+        true
     }
 }
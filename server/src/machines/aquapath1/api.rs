@@ -1,22 +1,22 @@
-use std::{sync::Arc, time::Duration};
+use std::sync::Arc;
 
 use super::{AquaPathV1, AquaPathV1Mode};
 use control_core::{
-    machines::api::MachineApi,
+    machines::{api::MachineApi, schema::MachineApiTypes},
     socketio::{
-        event::{Event, GenericEvent},
-        namespace::{
-            CacheFn, CacheableEvents, Namespace, NamespaceCacheingLogic, cache_duration,
-            cache_first_and_last_event,
-        },
+        event::Event,
+        namespace::Namespace,
+        units::{FieldSchema, SchemaEvent},
     },
 };
+use control_core_derive::{BuildEvent, NamespaceEvents};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use smol::lock::Mutex;
-use tracing::instrument;
+use uom::si::{thermodynamic_temperature::degree_celsius, volume_rate::liter_per_minute};
 
-#[derive(Serialize, Debug, Clone, Default)]
+#[derive(Serialize, Debug, Clone, Default, BuildEvent, JsonSchema)]
 pub struct LiveValuesEvent {
     pub front_flow: f64,
     pub back_flow: f64,
@@ -27,12 +27,21 @@ pub struct LiveValuesEvent {
 }
 
 impl LiveValuesEvent {
-    pub fn build(&self) -> Event<Self> {
-        Event::new("LiveValuesEvent", self.clone())
+    pub fn schema() -> SchemaEvent {
+        let flow = || FieldSchema::of::<liter_per_minute>().min(0.0);
+        let temperature = || FieldSchema::of::<degree_celsius>();
+        SchemaEvent::new([
+            ("front_flow", flow()),
+            ("back_flow", flow()),
+            ("front_temperature", temperature()),
+            ("back_temperature", temperature()),
+            ("front_temp_reservoir", temperature()),
+            ("back_temp_reservoir", temperature()),
+        ])
     }
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, BuildEvent, JsonSchema)]
 pub struct StateEvent {
     pub is_default_state: bool,
     /// mode state
@@ -41,46 +50,46 @@ pub struct StateEvent {
     pub temperature_states: TempStates,
 }
 
-impl StateEvent {
-    pub fn build(&self) -> Event<Self> {
-        Event::new("StateEvent", self.clone())
-    }
-}
-
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, JsonSchema)]
 pub struct TempStates {
     pub front: TempState,
     pub back: TempState,
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, JsonSchema)]
 pub struct TempState {
     pub temperature: f64,
     pub target_temperature: f64,
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, JsonSchema)]
 pub struct ModeState {
     pub mode: AquaPathV1Mode,
 }
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, JsonSchema)]
 pub struct FlowStates {
     pub front: FlowState,
     pub back: FlowState,
 }
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, JsonSchema)]
 pub struct FlowState {
     pub flow: f64,
     pub should_flow: bool,
 }
 
+#[derive(NamespaceEvents)]
+#[namespace_events(namespace = AquaPathV1Namespace)]
 pub enum AquaPathV1Events {
+    #[cache(duration_secs = 3600)]
     LiveValues(Event<LiveValuesEvent>),
+    #[cache(one)]
+    Schema(Event<SchemaEvent>),
+    #[cache(first_and_last)]
     State(Event<StateEvent>),
 }
 
-#[derive(Deserialize, Serialize)]
-enum Mutation {
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub enum Mutation {
     //Mode
     SetAquaPathMode(AquaPathV1Mode),
 
@@ -96,39 +105,14 @@ pub struct AquaPathV1Namespace {
     pub namespace: Arc<Mutex<Namespace>>,
 }
 
-impl NamespaceCacheingLogic<AquaPathV1Events> for AquaPathV1Namespace {
-    #[instrument(skip_all)]
-    fn emit(&mut self, events: AquaPathV1Events) {
-        let event = Arc::new(events.event_value());
-        let buffer_fn = events.event_cache_fn();
-
-        let mut namespace = self.namespace.lock_blocking();
-        namespace.emit(event, &buffer_fn);
-    }
-}
-
-impl CacheableEvents<AquaPathV1Events> for AquaPathV1Events {
-    fn event_value(&self) -> GenericEvent {
-        match self {
-            AquaPathV1Events::LiveValues(event) => event.into(),
-            AquaPathV1Events::State(event) => event.into(),
-        }
-    }
-
-    fn event_cache_fn(&self) -> CacheFn {
-        let cache_one_hour = cache_duration(Duration::from_secs(60 * 60), Duration::from_secs(1));
-        let cache_first_and_last = cache_first_and_last_event();
-
-        match self {
-            AquaPathV1Events::LiveValues(_) => cache_one_hour,
-            AquaPathV1Events::State(_) => cache_first_and_last,
-        }
-    }
+impl MachineApiTypes for AquaPathV1 {
+    type Mutation = Mutation;
+    type Events = AquaPathV1Events;
 }
 
 impl MachineApi for AquaPathV1 {
     fn api_mutate(&mut self, request_body: Value) -> Result<(), anyhow::Error> {
-        let control: Mutation = serde_json::from_value(request_body)?;
+        let control = Self::parse_mutation(request_body)?;
         match control {
             Mutation::SetAquaPathMode(mode) => self.set_mode_state(mode),
             Mutation::SetBackTemperature(temperature) => {
@@ -1,11 +1,15 @@
 use control_core::{
-    machines::identification::{MachineIdentification, MachineIdentificationUnique},
-    socketio::namespace::NamespaceCacheingLogic,
+    machines::{
+        identification::{MachineIdentification, MachineIdentificationUnique},
+        values::MachineValueBus,
+    },
+    socketio::{event::BuildEvent, namespace::NamespaceCacheingLogic},
 };
 
 use control_core_derive::Machine;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::time::Instant;
+use std::sync::Arc;
 use uom::si::{
     f64::{ThermodynamicTemperature, VolumeRate},
     thermodynamic_temperature::degree_celsius,
@@ -31,7 +35,7 @@ pub mod controller;
 pub mod new;
 // pub mod temperature_controller;
 
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
 pub enum AquaPathV1Mode {
     Standby,
     Auto,
@@ -81,9 +85,10 @@ impl Default for Flow {
 #[derive(Debug, Machine)]
 pub struct AquaPathV1 {
     machine_identification_unique: MachineIdentificationUnique,
+    /// Publishes the water temperature for other machines
+    values: Arc<MachineValueBus>,
     namespace: AquaPathV1Namespace,
     mode: AquaPathV1Mode,
-    last_measurement_emit: Instant,
     front_controller: Controller,
     back_controller: Controller,
 }
@@ -121,6 +126,11 @@ impl AquaPathV1 {
         self.namespace.emit(AquaPathV1Events::LiveValues(event));
     }
 
+    pub fn emit_schema(&mut self) {
+        let event = LiveValuesEvent::schema().build();
+        self.namespace.emit(AquaPathV1Events::Schema(event));
+    }
+
     pub fn emit_state(&mut self) {
         let state = StateEvent {
             is_default_state: false,
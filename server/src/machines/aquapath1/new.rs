@@ -30,7 +30,7 @@ use ethercat_hal::{
         temperature_input::TemperatureInput,
     },
 };
-use std::time::{Duration, Instant};
+use std::time::Duration;
 use uom::si::{f64::ThermodynamicTemperature, thermodynamic_temperature::degree_celsius};
 
 impl MachineNewTrait for AquaPathV1 {
@@ -339,14 +339,15 @@ impl MachineNewTrait for AquaPathV1 {
 
             let mut water_cooling = Self {
                 machine_identification_unique: params.get_machine_identification_unique(),
+                values: params.values.clone(),
                 namespace: AquaPathV1Namespace {
                     namespace: params.namespace.clone(),
                 },
                 mode: AquaPathV1Mode::Standby,
-                last_measurement_emit: Instant::now(),
                 front_controller,
                 back_controller,
             };
+            water_cooling.emit_schema();
             water_cooling.emit_state();
 
             Ok(water_cooling)
@@ -1,16 +1,24 @@
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
-use super::BufferV1;
+use super::{BufferV1, BufferV1Mode};
 use control_core::machines::new::MachineAct;
 
 impl MachineAct for BufferV1 {
     fn act(&mut self, now: Instant) {
-        // if last measurement is older than 1 second, emit a new measurement
-        if now.duration_since(self.last_measurement_emit) > Duration::from_secs_f64(1.0 / 30.0) {
-            // Emit live values at 30 FPS
-            self.emit_live_values();
+        self.sync_payoff_speed(now);
 
-            self.last_measurement_emit = now;
+        if std::mem::take(&mut self.emit_state_pending) {
+            self.emit_state();
         }
+
+        // the namespace limits the emit rate
+        self.emit_live_values();
+    }
+
+    fn act_safe_stop(&mut self) -> bool {
+        if self.mode != BufferV1Mode::Standby {
+            self.set_mode_state(BufferV1Mode::Standby);
+        }
+        true
     }
 }
@@ -1,76 +1,118 @@
-use std::{sync::Arc, time::Duration};
+use std::sync::Arc;
 
 use super::{BufferV1, BufferV1Mode};
+use crate::machines::gain_history::GainHistoryEntry;
 use control_core::{
+    alarms::{AlarmCondition, AlarmSeverity},
     machines::{
         api::MachineApi, connection::MachineCrossConnectionState,
-        identification::MachineIdentificationUnique,
+        identification::MachineIdentificationUnique, schema::MachineApiTypes,
     },
     socketio::{
-        event::{Event, GenericEvent},
-        namespace::{
-            CacheFn, CacheableEvents, Namespace, NamespaceCacheingLogic, cache_duration,
-            cache_one_event,
-        },
+        event::Event,
+        namespace::Namespace,
+        units::{DisplayQuantity, EventUnits, FieldSchema, SchemaEvent},
     },
 };
+use control_core_derive::{BuildEvent, NamespaceEvents};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use smol::lock::Mutex;
-use tracing::instrument;
-
-#[derive(Serialize, Debug, Clone, Default)]
-pub struct LiveValuesEvent {}
+use uom::si::ratio::percent;
+
+#[derive(Serialize, Debug, Clone, Default, BuildEvent, JsonSchema)]
+pub struct LiveValuesEvent {
+    /// fill level of the accumulator in %
+    pub fill_level: f64,
+    /// payoff speed in m/min
+    pub payoff_speed: f64,
+    pub units: EventUnits,
+}
 
 impl LiveValuesEvent {
-    pub fn build(&self) -> Event<Self> {
-        Event::new("LiveValuesEvent", self.clone())
+    pub const UNITS: EventUnits = EventUnits(&[("payoff_speed", DisplayQuantity::LineSpeed)]);
+
+    pub fn schema() -> SchemaEvent {
+        SchemaEvent::new([
+            ("fill_level", FieldSchema::of::<percent>().range(0.0, 100.0)),
+            (
+                "payoff_speed",
+                FieldSchema::display(DisplayQuantity::LineSpeed).min(0.0),
+            ),
+        ])
     }
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, BuildEvent, JsonSchema)]
 pub struct StateEvent {
     /// mode state
     pub mode_state: ModeState,
+    /// payoff state
+    pub payoff_state: PayoffState,
     /// connected machine state
     pub connected_machine_state: MachineCrossConnectionState,
 }
 
-impl StateEvent {
-    pub fn build(&self) -> Event<Self> {
-        Event::new("StateEvent", self.clone())
-    }
-}
-
+#[derive(NamespaceEvents)]
+#[namespace_events(namespace = Buffer1Namespace)]
 pub enum BufferV1Events {
+    #[cache(duration_secs = 3600)]
     LiveValues(Event<LiveValuesEvent>),
+    #[cache(one)]
+    Schema(Event<SchemaEvent>),
+    #[cache(one)]
     State(Event<StateEvent>),
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, JsonSchema)]
 pub struct ModeState {
     pub mode: BufferV1Mode,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+pub struct PayoffState {
+    /// fill level the payoff regulates to in %
+    pub fill_setpoint: f64,
+    pub pid_settings: PidSettings,
+    /// gains the payoff had before, newest first
+    pub pid_history: Vec<GainHistoryEntry<PidSettings>>,
+}
+
+/// PID gains in m/min per % fill level error
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct PidSettings {
+    pub ki: f64,
+    pub kp: f64,
+    pub kd: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
 pub enum Mode {
     Standby,
     FillingBuffer,
     EmptyingBuffer,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
 pub struct ConnectedMachineState {
     /// Connected Machine
     pub machine_identification_unique: Option<MachineIdentificationUnique>,
     pub is_available: bool,
 }
 
-#[derive(Deserialize, Serialize)]
-enum Mutation {
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub enum Mutation {
     // Mode
     SetBufferMode(BufferV1Mode),
 
+    // Payoff
+    /// Fill level the payoff regulates to in %
+    SetPayoffFillSetpoint(f64),
+    SetPayoffPidSettings(PidSettings),
+    /// Goes back to the PID gains of the payoff before their last change
+    RevertPayoffPidSettings,
+
     // Connected Machine
     SetConnectedMachine(MachineIdentificationUnique),
 
@@ -83,41 +125,21 @@ pub struct Buffer1Namespace {
     pub namespace: Arc<Mutex<Namespace>>,
 }
 
-impl NamespaceCacheingLogic<BufferV1Events> for Buffer1Namespace {
-    #[instrument(skip_all)]
-    fn emit(&mut self, events: BufferV1Events) {
-        let event = Arc::new(events.event_value());
-        let buffer_fn = events.event_cache_fn();
-
-        let mut namespace = self.namespace.lock_blocking();
-        namespace.emit(event, &buffer_fn);
-    }
-}
-
-impl CacheableEvents<Self> for BufferV1Events {
-    fn event_value(&self) -> GenericEvent {
-        match self {
-            Self::LiveValues(event) => event.into(),
-            Self::State(event) => event.into(),
-        }
-    }
-
-    fn event_cache_fn(&self) -> CacheFn {
-        let cache_one_hour = cache_duration(Duration::from_secs(60 * 60), Duration::from_secs(1));
-        let cache_one = cache_one_event();
-
-        match self {
-            Self::LiveValues(_) => cache_one_hour,
-            Self::State(_) => cache_one,
-        }
-    }
+impl MachineApiTypes for BufferV1 {
+    type Mutation = Mutation;
+    type Events = BufferV1Events;
 }
 
 impl MachineApi for BufferV1 {
     fn api_mutate(&mut self, request_body: Value) -> Result<(), anyhow::Error> {
-        let mutation: Mutation = serde_json::from_value(request_body)?;
+        let mutation = Self::parse_mutation(request_body)?;
         match mutation {
             Mutation::SetBufferMode(mode) => self.set_mode_state(mode),
+            Mutation::SetPayoffFillSetpoint(fill_setpoint) => {
+                self.payoff_set_fill_setpoint(fill_setpoint)?
+            }
+            Mutation::SetPayoffPidSettings(settings) => self.payoff_configure_pid(settings)?,
+            Mutation::RevertPayoffPidSettings => self.payoff_revert_pid()?,
             Mutation::SetConnectedMachine(machine_identification_unique) => {
                 self.set_connected_winder(machine_identification_unique);
             }
@@ -131,4 +153,16 @@ impl MachineApi for BufferV1 {
     fn api_event_namespace(&mut self) -> Arc<Mutex<Namespace>> {
         self.namespace.namespace.clone()
     }
+
+    fn api_alarms(&self) -> Vec<AlarmCondition> {
+        if self.is_full() {
+            vec![AlarmCondition::new(
+                "accumulator_full",
+                "Accumulator is full, the spool change has to be finished",
+                AlarmSeverity::Warning,
+            )]
+        } else {
+            Vec::new()
+        }
+    }
 }
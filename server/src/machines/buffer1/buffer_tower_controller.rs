@@ -1,27 +1,37 @@
+use control_core::converters::linear_step_converter::LinearStepConverter;
 use ethercat_hal::io::stepper_velocity_el70x1::StepperVelocityEL70x1;
+use uom::si::f64::Velocity;
 
 #[derive(Debug)]
 pub struct BufferTowerController {
     enabled: bool,
-    /// Stepper driver. Controls buffer stepper motor
+    /// Stepper driver. Controls the payoff motor of the buffer
     pub stepper_driver: StepperVelocityEL70x1,
+    /// Converter for the payoff roller
+    pub converter: LinearStepConverter,
 }
 
 impl BufferTowerController {
-    pub const fn new(driver: StepperVelocityEL70x1) -> Self {
+    pub const fn new(driver: StepperVelocityEL70x1, converter: LinearStepConverter) -> Self {
         Self {
             enabled: false,
             stepper_driver: driver,
+            converter,
         }
     }
+
     pub fn set_enabled(&mut self, enabled: bool) {
         self.enabled = enabled;
-        if enabled {
-            self.stepper_driver.set_enabled(true);
-            let _ = self.stepper_driver.set_speed(10.0);
-        } else {
-            self.stepper_driver.set_enabled(false);
-            let _ = self.stepper_driver.set_speed(0.0);
+        self.stepper_driver.set_enabled(enabled);
+        let _ = self.stepper_driver.set_speed(0.0);
+    }
+
+    /// Sets the filament speed of the payoff
+    pub fn set_speed(&mut self, speed: Velocity) {
+        if !self.enabled {
+            return;
         }
+        let steps_per_second = self.converter.velocity_to_steps(speed);
+        let _ = self.stepper_driver.set_speed(steps_per_second);
     }
 }
@@ -0,0 +1,68 @@
+use ethercat_hal::io::analog_input::{AnalogInput, physical::AnalogInputValue};
+use uom::si::electric_potential::volt;
+
+/// Position sensor of the dancer carriage
+///
+/// The carriage moves the filament loops of the accumulator apart,
+/// 0V is the empty and 10V the full accumulator.
+#[derive(Debug)]
+pub struct Dancer {
+    pub analog_input: AnalogInput,
+}
+
+impl Dancer {
+    pub const fn new(analog_input: AnalogInput) -> Self {
+        Self { analog_input }
+    }
+
+    const fn volts_to_fill_level(volts: f64) -> f64 {
+        (volts * 10.0).clamp(0.0, 100.0)
+    }
+
+    fn get_volts(&self) -> f64 {
+        match self.analog_input.get_physical() {
+            AnalogInputValue::Potential(v) => v.get::<volt>(),
+            _ => panic!("Expected a potential value"),
+        }
+    }
+
+    /// Fill level of the accumulator in %
+    pub fn get_fill_level(&self) -> f64 {
+        Self::volts_to_fill_level(self.get_volts())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use ethercat_hal::io::{
+        analog_input::{AnalogInputInput, physical::AnalogInputRange},
+        analog_input_dummy::AnalogInputDummy,
+    };
+    use uom::si::f64::ElectricPotential;
+
+    #[test]
+    fn test_fill_level() {
+        let mut analog_input_dummy = AnalogInputDummy::new(AnalogInputRange::Potential {
+            min: ElectricPotential::new::<volt>(0.0),
+            max: ElectricPotential::new::<volt>(10.0),
+            min_raw: 0,
+            max_raw: i16::MAX,
+        });
+        let dancer = Dancer::new(analog_input_dummy.analog_input());
+
+        analog_input_dummy.set_input(AnalogInputInput {
+            normalized: 0.25,
+            wiring_error: false,
+        });
+        assert_relative_eq!(dancer.get_fill_level(), 25.0, epsilon = 1e-3);
+
+        // the carriage can't be further out than empty or full
+        analog_input_dummy.set_input(AnalogInputInput {
+            normalized: -0.1,
+            wiring_error: false,
+        });
+        assert_relative_eq!(dancer.get_fill_level(), 0.0);
+    }
+}
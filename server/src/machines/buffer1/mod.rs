@@ -1,33 +1,64 @@
 pub mod act;
 pub mod api;
 pub mod buffer_tower_controller;
+pub mod dancer;
 pub mod new;
+pub mod payoff_speed_controller;
 
-use api::{Buffer1Namespace, BufferV1Events, LiveValuesEvent, ModeState, StateEvent};
+use api::{
+    Buffer1Namespace, BufferV1Events, LiveValuesEvent, ModeState, PayoffState, PidSettings,
+    StateEvent,
+};
 use buffer_tower_controller::BufferTowerController;
 use control_core::machines::connection::{CrossConnectableMachine, MachineCrossConnection};
 use control_core::{
     machines::{
         identification::{MachineIdentification, MachineIdentificationUnique},
         manager::MachineManager,
+        values::{LINE_SPEED, MachineValueBus, MachineValueSubscription},
     },
-    socketio::namespace::NamespaceCacheingLogic,
+    socketio::{event::BuildEvent, namespace::NamespaceCacheingLogic},
+    uom_extensions::velocity::meter_per_minute,
 };
 use control_core_derive::Machine;
+use dancer::Dancer;
+use payoff_speed_controller::PayoffSpeedController;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use smol::lock::RwLock;
-use std::{sync::Weak, time::Instant};
+use std::{
+    sync::{Arc, Weak},
+    time::{Duration, Instant},
+};
+use uom::si::f64::Velocity;
 
-use crate::machines::{MACHINE_BUFFER_V1, VENDOR_QITECH, winder2::Winder2};
+use crate::machines::{
+    MACHINE_BUFFER_V1, VENDOR_QITECH, gain_history::GainHistory, winder2::Winder2,
+};
 
 #[derive(Debug, Machine)]
 pub struct BufferV1 {
+    // drivers
+    pub dancer: Dancer,
+
     // controllers
     pub buffer_tower_controller: BufferTowerController,
+    pub payoff_speed_controller: PayoffSpeedController,
+    /// PID gains of the payoff before they were changed
+    payoff_pid_history: GainHistory<PidSettings>,
+
+    /// fill level of the last cycle in %
+    fill_level: f64,
+    /// state changed outside of the API, emitted in the next cycle
+    emit_state_pending: bool,
+
+    // values of other machines
+    values: Arc<MachineValueBus>,
+    /// line speed of the connected winder
+    line_speed: Option<MachineValueSubscription<Velocity>>,
 
     // socketio
     namespace: Buffer1Namespace,
-    last_measurement_emit: Instant,
 
     // machine connection
     pub machine_manager: Weak<RwLock<MachineManager>>,
@@ -57,18 +88,38 @@ impl BufferV1 {
         vendor: VENDOR_QITECH,
         machine: MACHINE_BUFFER_V1,
     };
+    /// The line speed of the winder is older than a few cycles when the winder stopped acting
+    const LINE_SPEED_MAX_AGE: Duration = Duration::from_millis(500);
+
     pub fn emit_live_values(&mut self) {
-        let live_values = LiveValuesEvent {};
+        let live_values = LiveValuesEvent {
+            fill_level: self.fill_level,
+            payoff_speed: self
+                .payoff_speed_controller
+                .last_speed
+                .get::<meter_per_minute>(),
+            units: LiveValuesEvent::UNITS,
+        };
 
         let event = live_values.build();
         self.namespace.emit(BufferV1Events::LiveValues(event));
     }
 
+    pub fn emit_schema(&mut self) {
+        let event = LiveValuesEvent::schema().build();
+        self.namespace.emit(BufferV1Events::Schema(event));
+    }
+
     pub fn emit_state(&mut self) {
         let state = StateEvent {
             mode_state: ModeState {
                 mode: self.mode.clone(),
             },
+            payoff_state: PayoffState {
+                fill_setpoint: self.payoff_speed_controller.get_fill_setpoint(),
+                pid_settings: self.payoff_pid_settings(),
+                pid_history: self.payoff_pid_history.entries(),
+            },
             connected_machine_state: self.connected_winder.to_state(),
         };
 
@@ -76,45 +127,109 @@ impl BufferV1 {
         self.namespace.emit(BufferV1Events::State(event));
     }
 
-    // To be implemented
-    fn fill_buffer(&mut self) {
-        todo!();
+    /// Subscribes to the line speed once a winder is connected
+    /// called by `act`
+    fn sync_line_speed_subscription(&mut self) {
+        if !self.connected_winder.is_connected() {
+            self.line_speed = None;
+        } else if self.line_speed.is_none() {
+            self.line_speed = self
+                .connected_winder
+                .to_state()
+                .machine_identification_unique
+                .map(|winder| {
+                    self.values
+                        .subscribe(winder, LINE_SPEED, Self::LINE_SPEED_MAX_AGE)
+                });
+        }
+    }
+
+    /// Regulates the payoff to the fill level setpoint
+    /// called by `act`
+    pub fn sync_payoff_speed(&mut self, t: Instant) {
+        self.sync_line_speed_subscription();
+        self.fill_level = self.dancer.get_fill_level();
+
+        // a stale line speed would keep the payoff running after the winder stopped
+        let line_speed = self
+            .line_speed
+            .as_ref()
+            .and_then(|line_speed| line_speed.read(t).fresh());
+        let speed = self
+            .payoff_speed_controller
+            .calc_speed(t, self.fill_level, line_speed);
+        self.buffer_tower_controller.set_speed(speed);
+    }
+
+    pub const fn is_full(&self) -> bool {
+        self.fill_level >= 100.0
     }
 
-    fn empty_buffer(&mut self) {
-        todo!();
+    /// Set fill level setpoint in %
+    pub fn payoff_set_fill_setpoint(&mut self, fill_setpoint: f64) -> Result<(), anyhow::Error> {
+        self.payoff_speed_controller
+            .set_fill_setpoint(fill_setpoint)?;
+        self.emit_state();
+        Ok(())
+    }
+
+    const fn payoff_pid_settings(&self) -> PidSettings {
+        let pid = self.payoff_speed_controller.get_pid();
+        PidSettings {
+            ki: pid.get_ki(),
+            kp: pid.get_kp(),
+            kd: pid.get_kd(),
+        }
+    }
+
+    pub fn payoff_configure_pid(&mut self, settings: PidSettings) -> Result<(), anyhow::Error> {
+        let previous = self.payoff_pid_settings();
+        self.payoff_speed_controller
+            .configure_pid(settings.kp, settings.ki, settings.kd)?;
+        self.payoff_pid_history.record(previous, &settings);
+        self.emit_state();
+        Ok(())
+    }
+
+    pub fn payoff_revert_pid(&mut self) -> Result<(), anyhow::Error> {
+        let settings = self.payoff_pid_history.pop().ok_or_else(|| {
+            anyhow::anyhow!(
+                "[{}::BufferV1::payoff_revert_pid] The PID gains were not changed",
+                module_path!()
+            )
+        })?;
+        self.payoff_speed_controller
+            .configure_pid(settings.kp, settings.ki, settings.kd)?;
+        tracing::info!(
+            "Reverted the payoff PID gains of {} to kp {}, ki {}, kd {}",
+            self.machine_identification_unique,
+            settings.kp,
+            settings.ki,
+            settings.kd
+        );
+        self.emit_state();
+        Ok(())
     }
 
     // Turn off motor and do nothing
     fn switch_to_standby(&mut self) {
-        match self.mode {
-            BufferV1Mode::Standby => (),
-            BufferV1Mode::FillingBuffer => {}
-            BufferV1Mode::EmptyingBuffer => {}
-        };
         self.mode = BufferV1Mode::Standby;
+        self.payoff_speed_controller.set_enabled(false);
         self.buffer_tower_controller.set_enabled(false);
     }
 
-    // Turn on motor and fill buffer
+    // Hold the payoff, the accumulator stores the line
     fn switch_to_filling(&mut self) {
-        match self.mode {
-            BufferV1Mode::Standby => self.fill_buffer(),
-            BufferV1Mode::FillingBuffer => (),
-            BufferV1Mode::EmptyingBuffer => {}
-        };
         self.mode = BufferV1Mode::FillingBuffer;
+        self.payoff_speed_controller.set_enabled(false);
         self.buffer_tower_controller.set_enabled(true);
     }
 
-    // Turn on motor reverse and empty buffer
+    // Run the payoff, the accumulator empties down to the fill level setpoint
     fn switch_to_emptying(&mut self) {
-        match self.mode {
-            BufferV1Mode::Standby => self.empty_buffer(),
-            BufferV1Mode::FillingBuffer => {}
-            BufferV1Mode::EmptyingBuffer => (),
-        };
         self.mode = BufferV1Mode::EmptyingBuffer;
+        self.payoff_speed_controller.set_enabled(true);
+        self.buffer_tower_controller.set_enabled(true);
     }
 
     fn switch_mode(&mut self, mode: BufferV1Mode) {
@@ -134,6 +249,14 @@ impl BufferV1 {
         self.emit_state();
     }
 
+    /// Mode change of the connected winder
+    ///
+    /// Called while the winder is locked, emitting the state now would lock it again.
+    pub fn follow_winder_mode(&mut self, mode: BufferV1Mode) {
+        self.switch_mode(mode);
+        self.emit_state_pending = true;
+    }
+
     /// Connecting/Disconnecting machine
     /// set connected winder
     pub fn set_connected_winder(
@@ -149,6 +272,7 @@ impl BufferV1 {
 
         self.connected_winder
             .set_connected_machine(&machine_identification_unique);
+        self.line_speed = None;
 
         self.emit_state();
 
@@ -172,9 +296,12 @@ impl BufferV1 {
     }
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
 pub enum BufferV1Mode {
+    /// Payoff off
     Standby,
+    /// Payoff stopped, the accumulator stores the line while the spool is changed
     FillingBuffer,
+    /// Payoff follows the line and keeps the accumulator at the fill level setpoint
     EmptyingBuffer,
 }
@@ -1,6 +1,5 @@
-use std::time::Instant;
-
 use anyhow::Error;
+use control_core::converters::linear_step_converter::LinearStepConverter;
 use control_core::machines::connection::MachineCrossConnection;
 use control_core::machines::new::{
     MachineNewHardware, MachineNewParams, MachineNewTrait, validate_no_role_dublicates,
@@ -14,19 +13,27 @@ use ethercat_hal::{
     devices::{
         EthercatDeviceUsed,
         ek1100::{EK1100, EK1100_IDENTITY_A},
-        el7031_0030::{EL7031_0030, EL7031_0030_IDENTITY_A},
+        el7031_0030::{EL7031_0030, EL7031_0030_IDENTITY_A, EL7031_0030AnalogInputPort},
         el7041_0052::{EL7041_0052, EL7041_0052_IDENTITY_A, EL7041_0052Port},
     },
-    io::stepper_velocity_el70x1::StepperVelocityEL70x1,
+    io::{analog_input::AnalogInput, stepper_velocity_el70x1::StepperVelocityEL70x1},
     shared_config,
     shared_config::el70x1::{EL70x1OperationMode, StmMotorConfiguration},
 };
 
 use crate::machines::buffer1::BufferV1Mode;
 use crate::machines::buffer1::buffer_tower_controller::BufferTowerController;
+use crate::machines::buffer1::dancer::Dancer;
+use crate::machines::buffer1::payoff_speed_controller::PayoffSpeedController;
+use crate::machines::gain_history::GainHistory;
 use crate::machines::get_ethercat_device;
 
 use super::{BufferV1, api::Buffer1Namespace};
+use control_core::uom_extensions::velocity::meter_per_minute;
+use uom::si::{
+    f64::{Length, Velocity},
+    length::millimeter,
+};
 
 impl MachineNewTrait for BufferV1 {
     fn new<'maindevice>(
@@ -86,7 +93,7 @@ impl MachineNewTrait for BufferV1 {
                 device_guard.set_used(true);
             }
 
-            // Role 2 - Stepper Puller EL7031-0030
+            // Role 2 - Dancer position on AI1 of the EL7031-0030
             let (el7031, subdevice) = get_ethercat_device::<EL7031_0030>(
                 hardware,
                 params,
@@ -122,10 +129,10 @@ impl MachineNewTrait for BufferV1 {
             }
 
             // Controller
-            let buffer_tower_controller = BufferTowerController::new(StepperVelocityEL70x1::new(
-                el7041.clone(),
-                EL7041_0052Port::STM1,
-            ));
+            let buffer_tower_controller = BufferTowerController::new(
+                StepperVelocityEL70x1::new(el7041.clone(), EL7041_0052Port::STM1),
+                LinearStepConverter::from_diameter(200, Length::new::<millimeter>(80.0)),
+            );
 
             let machine_identification_unique = params.get_machine_identification_unique();
 
@@ -134,9 +141,18 @@ impl MachineNewTrait for BufferV1 {
                 namespace: Buffer1Namespace {
                     namespace: params.namespace.clone(),
                 },
-                last_measurement_emit: Instant::now(),
                 mode: BufferV1Mode::Standby,
+                dancer: Dancer::new(AnalogInput::new(el7031, EL7031_0030AnalogInputPort::AI1)),
                 buffer_tower_controller,
+                payoff_speed_controller: PayoffSpeedController::new(
+                    20.0,
+                    Velocity::new::<meter_per_minute>(60.0),
+                ),
+                payoff_pid_history: GainHistory::new(),
+                fill_level: 0.0,
+                emit_state_pending: false,
+                values: params.values.clone(),
+                line_speed: None,
                 machine_manager: params.machine_manager.clone(),
                 machine_identification_unique: machine_identification_unique.clone(),
                 connected_winder: MachineCrossConnection::new(
@@ -144,6 +160,7 @@ impl MachineNewTrait for BufferV1 {
                     &machine_identification_unique,
                 ),
             };
+            buffer.emit_schema();
             buffer.emit_state();
             Ok(buffer)
         })
@@ -0,0 +1,158 @@
+use std::time::Instant;
+
+use control_core::{controllers::pid::PidController, uom_extensions::velocity::meter_per_minute};
+use uom::{ConstZero, si::f64::Velocity};
+
+/// Speed of the payoff that pulls the filament out of the accumulator
+///
+/// The payoff runs at the line speed of the connected winder, a PID on the dancer position
+/// corrects the speed so the fill level stays at the setpoint. Without a line speed the PID
+/// regulates alone.
+#[derive(Debug)]
+pub struct PayoffSpeedController {
+    enabled: bool,
+    /// Gains in m/min per % fill level error
+    pid: PidController,
+    /// Fill level to regulate to in %
+    fill_setpoint: f64,
+    max_speed: Velocity,
+    pub last_speed: Velocity,
+}
+
+impl PayoffSpeedController {
+    pub const fn new(fill_setpoint: f64, max_speed: Velocity) -> Self {
+        Self {
+            enabled: false,
+            pid: PidController::new(0.5, 0.05, 0.0),
+            fill_setpoint,
+            max_speed,
+            last_speed: Velocity::ZERO,
+        }
+    }
+
+    pub const fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        // the integral of the last run would kick the payoff
+        self.pid.reset();
+    }
+
+    pub const fn get_fill_setpoint(&self) -> f64 {
+        self.fill_setpoint
+    }
+
+    pub fn set_fill_setpoint(&mut self, fill_setpoint: f64) -> Result<(), anyhow::Error> {
+        if !(0.0..=100.0).contains(&fill_setpoint) {
+            return Err(anyhow::anyhow!(
+                "[{}::PayoffSpeedController::set_fill_setpoint] Fill setpoint must be between 0% and 100%, got {}%",
+                module_path!(),
+                fill_setpoint
+            ));
+        }
+        self.fill_setpoint = fill_setpoint;
+        Ok(())
+    }
+
+    pub const fn get_pid(&self) -> &PidController {
+        &self.pid
+    }
+
+    pub fn configure_pid(&mut self, kp: f64, ki: f64, kd: f64) -> Result<(), anyhow::Error> {
+        if [kp, ki, kd]
+            .iter()
+            .any(|gain| !gain.is_finite() || *gain < 0.0)
+        {
+            return Err(anyhow::anyhow!(
+                "[{}::PayoffSpeedController::configure_pid] Gains must not be negative",
+                module_path!()
+            ));
+        }
+        self.pid.set_gains(kp, ki, kd);
+        Ok(())
+    }
+
+    /// Computes the payoff speed for the current fill level in %
+    pub fn calc_speed(
+        &mut self,
+        t: Instant,
+        fill_level: f64,
+        line_speed: Option<Velocity>,
+    ) -> Velocity {
+        self.last_speed = match self.enabled {
+            true => {
+                // a fuller accumulator has to pay off faster
+                let correction = self.pid.update(fill_level - self.fill_setpoint, t);
+                let speed = line_speed.unwrap_or(Velocity::ZERO)
+                    + Velocity::new::<meter_per_minute>(correction);
+                // the payoff can't push filament back into the accumulator
+                speed.max(Velocity::ZERO).min(self.max_speed)
+            }
+            false => Velocity::ZERO,
+        };
+        self.last_speed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use control_core::helpers::clock::{Clock, ManualClock};
+    use std::time::Duration;
+
+    fn controller() -> PayoffSpeedController {
+        let mut controller =
+            PayoffSpeedController::new(20.0, Velocity::new::<meter_per_minute>(60.0));
+        controller.configure_pid(0.5, 0.0, 0.0).unwrap();
+        controller.set_enabled(true);
+        controller
+    }
+
+    #[test]
+    fn test_follows_line_speed_at_setpoint() {
+        let clock = ManualClock::new();
+        let mut controller = controller();
+        let line_speed = Velocity::new::<meter_per_minute>(10.0);
+
+        let speed = controller.calc_speed(clock.now(), 20.0, Some(line_speed));
+        assert_relative_eq!(speed.get::<meter_per_minute>(), 10.0);
+
+        // too full pays off faster, too empty slower
+        clock.advance(Duration::from_millis(10));
+        let speed = controller.calc_speed(clock.now(), 30.0, Some(line_speed));
+        assert_relative_eq!(speed.get::<meter_per_minute>(), 15.0, epsilon = 1e-9);
+        clock.advance(Duration::from_millis(10));
+        let speed = controller.calc_speed(clock.now(), 10.0, Some(line_speed));
+        assert_relative_eq!(speed.get::<meter_per_minute>(), 5.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_speed_limits() {
+        let clock = ManualClock::new();
+        let mut controller = controller();
+
+        let speed = controller.calc_speed(clock.now(), 0.0, None);
+        assert_eq!(speed, Velocity::ZERO);
+
+        clock.advance(Duration::from_millis(10));
+        let speed = controller.calc_speed(
+            clock.now(),
+            100.0,
+            Some(Velocity::new::<meter_per_minute>(50.0)),
+        );
+        assert_relative_eq!(speed.get::<meter_per_minute>(), 60.0);
+
+        controller.set_enabled(false);
+        let speed = controller.calc_speed(clock.now(), 100.0, None);
+        assert_eq!(speed, Velocity::ZERO);
+    }
+
+    #[test]
+    fn test_settings() {
+        let mut controller = controller();
+        assert!(controller.set_fill_setpoint(101.0).is_err());
+        assert!(controller.set_fill_setpoint(80.0).is_ok());
+        assert!(controller.configure_pid(-1.0, 0.0, 0.0).is_err());
+        assert!(controller.configure_pid(1.0, 0.1, 0.0).is_ok());
+        assert_relative_eq!(controller.get_pid().get_kp(), 1.0);
+    }
+}
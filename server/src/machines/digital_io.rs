@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+
+use control_core::machines::new::{
+    MachineNewHardwareEthercat, MachineNewParams, get_subdevice_by_index,
+};
+use ethercat_hal::devices::{
+    el1002::{EL1002, EL1002_IDENTITY_A, EL1002Port},
+    el1008::{EL1008, EL1008_IDENTITY_A, EL1008Port},
+    el2002::{EL2002, EL2002_IDENTITY_A, EL2002_IDENTITY_B, EL2002Port},
+    el2004::{EL2004, EL2004_IDENTITY_A, EL2004Port},
+    el2008::{EL2008, EL2008_IDENTITY_A, EL2008_IDENTITY_B, EL2008Port},
+    el7031::{EL7031, EL7031_IDENTITY_A, EL7031_IDENTITY_B, EL7031DigitalInputPort},
+    subdevice_identity_to_tuple,
+};
+use ethercat_hal::io::{digital_input::DigitalInput, digital_output::DigitalOutput};
+use serde::Serialize;
+
+use super::{get_device_ident, get_ethercat_device};
+use crate::io_mapping::{DigitalChannel, MachineIoMapping, MachineIoSignals};
+
+/// Digital channels of all supported terminals in the device group of a machine
+///
+/// Built once when the machine is created so the signals can be reassigned while it runs.
+#[derive(Debug, Default)]
+pub struct DigitalIoPool {
+    inputs: HashMap<(u16, u8), DigitalInput>,
+    outputs: HashMap<(u16, u8), DigitalOutput>,
+}
+
+impl DigitalIoPool {
+    pub async fn new<
+        'maindevice,
+        'subdevices,
+        'device_identifications_identified,
+        'ethercat_devices,
+        'machine_new_hardware_etehrcat,
+        'machine_new_hardware_serial,
+        'machine_new_hardware,
+    >(
+        hardware: &&MachineNewHardwareEthercat<'maindevice, 'subdevices, 'ethercat_devices>,
+        params: &MachineNewParams<
+            'maindevice,
+            'subdevices,
+            'device_identifications_identified,
+            'ethercat_devices,
+            'machine_new_hardware_etehrcat,
+            'machine_new_hardware_serial,
+            'machine_new_hardware,
+        >,
+    ) -> Result<Self, anyhow::Error> {
+        let mut pool = Self::default();
+        for device in params.device_group.iter() {
+            let role = device.device_machine_identification.role;
+            let subdevice_index = get_device_ident(params, role).await?.subdevice_index;
+            let subdevice = get_subdevice_by_index(hardware.subdevices, subdevice_index)?;
+            let identity = subdevice_identity_to_tuple(&subdevice.identity());
+
+            if identity == EL1002_IDENTITY_A {
+                let device = get_ethercat_device::<EL1002>(hardware, params, role, vec![identity])
+                    .await?
+                    .0;
+                for (channel, port) in [(1, EL1002Port::DI1), (2, EL1002Port::DI2)] {
+                    pool.inputs
+                        .insert((role, channel), DigitalInput::new(device.clone(), port));
+                }
+            } else if identity == EL1008_IDENTITY_A {
+                let device = get_ethercat_device::<EL1008>(hardware, params, role, vec![identity])
+                    .await?
+                    .0;
+                let ports = [
+                    EL1008Port::DI1,
+                    EL1008Port::DI2,
+                    EL1008Port::DI3,
+                    EL1008Port::DI4,
+                    EL1008Port::DI5,
+                    EL1008Port::DI6,
+                    EL1008Port::DI7,
+                    EL1008Port::DI8,
+                ];
+                for port in ports {
+                    let channel = port.to_bit_index() as u8 + 1;
+                    pool.inputs
+                        .insert((role, channel), DigitalInput::new(device.clone(), port));
+                }
+            } else if identity == EL7031_IDENTITY_A || identity == EL7031_IDENTITY_B {
+                let device = get_ethercat_device::<EL7031>(hardware, params, role, vec![identity])
+                    .await?
+                    .0;
+                let ports = [
+                    (1, EL7031DigitalInputPort::DI1),
+                    (2, EL7031DigitalInputPort::DI2),
+                ];
+                for (channel, port) in ports {
+                    pool.inputs
+                        .insert((role, channel), DigitalInput::new(device.clone(), port));
+                }
+            } else if identity == EL2002_IDENTITY_A || identity == EL2002_IDENTITY_B {
+                let device = get_ethercat_device::<EL2002>(hardware, params, role, vec![identity])
+                    .await?
+                    .0;
+                for (channel, port) in [(1, EL2002Port::DO1), (2, EL2002Port::DO2)] {
+                    pool.outputs
+                        .insert((role, channel), DigitalOutput::new(device.clone(), port));
+                }
+            } else if identity == EL2004_IDENTITY_A {
+                let device = get_ethercat_device::<EL2004>(hardware, params, role, vec![identity])
+                    .await?
+                    .0;
+                let ports = [
+                    (1, EL2004Port::DO1),
+                    (2, EL2004Port::DO2),
+                    (3, EL2004Port::DO3),
+                    (4, EL2004Port::DO4),
+                ];
+                for (channel, port) in ports {
+                    pool.outputs
+                        .insert((role, channel), DigitalOutput::new(device.clone(), port));
+                }
+            } else if identity == EL2008_IDENTITY_A || identity == EL2008_IDENTITY_B {
+                let device = get_ethercat_device::<EL2008>(hardware, params, role, vec![identity])
+                    .await?
+                    .0;
+                let ports = [
+                    (1, EL2008Port::DO1),
+                    (2, EL2008Port::DO2),
+                    (3, EL2008Port::DO3),
+                    (4, EL2008Port::DO4),
+                    (5, EL2008Port::DO5),
+                    (6, EL2008Port::DO6),
+                    (7, EL2008Port::DO7),
+                    (8, EL2008Port::DO8),
+                ];
+                for (channel, port) in ports {
+                    pool.outputs
+                        .insert((role, channel), DigitalOutput::new(device.clone(), port));
+                }
+            }
+        }
+        Ok(pool)
+    }
+
+    /// Adds a channel that is not part of an EtherCAT device group, e.g. a simulated one
+    pub fn insert_input(&mut self, role: u16, channel: u8, input: DigitalInput) {
+        self.inputs.insert((role, channel), input);
+    }
+
+    /// Adds a channel that is not part of an EtherCAT device group, e.g. a simulated one
+    pub fn insert_output(&mut self, role: u16, channel: u8, output: DigitalOutput) {
+        self.outputs.insert((role, channel), output);
+    }
+}
+
+/// State of an assigned signal as reported by the API
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct DigitalSignalState {
+    pub signal: String,
+    pub channel: DigitalChannel,
+    /// Logical value after applying [`DigitalChannel::inverted`], `None` if it can't be read
+    pub value: Option<bool>,
+}
+
+/// Signals of a machine resolved to the channels of its [`DigitalIoPool`]
+#[derive(Debug)]
+pub struct MappedDigitalIo {
+    signals: MachineIoSignals,
+    pool: DigitalIoPool,
+    mapping: MachineIoMapping,
+}
+
+impl MappedDigitalIo {
+    pub fn new(
+        signals: MachineIoSignals,
+        pool: DigitalIoPool,
+        mapping: MachineIoMapping,
+    ) -> Result<Self, anyhow::Error> {
+        let mut io = Self {
+            signals,
+            pool,
+            mapping: MachineIoMapping::default(),
+        };
+        io.set_mapping(mapping)?;
+        Ok(io)
+    }
+
+    pub const fn mapping(&self) -> &MachineIoMapping {
+        &self.mapping
+    }
+
+    /// Reassigns the signals, outputs losing their channel are switched off
+    ///
+    /// The current mapping is kept if a channel does not exist in the device group.
+    pub fn set_mapping(&mut self, mapping: MachineIoMapping) -> Result<(), anyhow::Error> {
+        mapping.validate(&self.signals)?;
+        for (signal, channel) in &mapping.inputs {
+            if !self.pool.inputs.contains_key(&channel.key()) {
+                return Err(anyhow::anyhow!(
+                    "[{}::MappedDigitalIo::set_mapping] Input {} is assigned to channel {} of role {} which is not a digital input",
+                    module_path!(),
+                    signal,
+                    channel.channel,
+                    channel.role
+                ));
+            }
+        }
+        for (signal, channel) in &mapping.outputs {
+            if !self.pool.outputs.contains_key(&channel.key()) {
+                return Err(anyhow::anyhow!(
+                    "[{}::MappedDigitalIo::set_mapping] Output {} is assigned to channel {} of role {} which is not a digital output",
+                    module_path!(),
+                    signal,
+                    channel.channel,
+                    channel.role
+                ));
+            }
+        }
+
+        // don't leave released channels driven
+        for (signal, channel) in &self.mapping.outputs {
+            if mapping.outputs.get(signal).map(DigitalChannel::key) != Some(channel.key()) {
+                self.set_channel(channel, false);
+            }
+        }
+        self.mapping = mapping;
+        Ok(())
+    }
+
+    /// Logical value of an input, `None` if it is unassigned or can't be read
+    pub fn input(&self, signal: &str) -> Option<bool> {
+        let channel = self.mapping.inputs.get(signal)?;
+        let value = self.pool.inputs.get(&channel.key())?.get_value().ok()?;
+        Some(value != channel.inverted)
+    }
+
+    /// Logical value of an output, `None` if it is unassigned
+    pub fn output(&self, signal: &str) -> Option<bool> {
+        let channel = self.mapping.outputs.get(signal)?;
+        let value = self.pool.outputs.get(&channel.key())?.get();
+        Some(value != channel.inverted)
+    }
+
+    /// Drives an output, does nothing if it is unassigned
+    pub fn set_output(&self, signal: &str, value: bool) {
+        if let Some(channel) = self.mapping.outputs.get(signal) {
+            self.set_channel(channel, value);
+        }
+    }
+
+    fn set_channel(&self, channel: &DigitalChannel, value: bool) {
+        if let Some(output) = self.pool.outputs.get(&channel.key()) {
+            output.set(value != channel.inverted);
+        }
+    }
+
+    /// Current values of all assigned signals
+    pub fn states(&self) -> (Vec<DigitalSignalState>, Vec<DigitalSignalState>) {
+        let inputs = self
+            .mapping
+            .inputs
+            .iter()
+            .map(|(signal, channel)| DigitalSignalState {
+                signal: signal.clone(),
+                channel: *channel,
+                value: self.input(signal),
+            })
+            .collect();
+        let outputs = self
+            .mapping
+            .outputs
+            .iter()
+            .map(|(signal, channel)| DigitalSignalState {
+                signal: signal.clone(),
+                channel: *channel,
+                value: self.output(signal),
+            })
+            .collect();
+        (inputs, outputs)
+    }
+}
@@ -1,9 +1,11 @@
 #[cfg(not(feature = "mock-machine"))]
 use crate::machines::extruder1::ExtruderV2;
 #[cfg(not(feature = "mock-machine"))]
-use control_core::machines::new::MachineAct;
+use control_core::machines::{new::MachineAct, values::SCREW_SPEED};
 #[cfg(not(feature = "mock-machine"))]
-use std::time::{Duration, Instant};
+use std::time::Instant;
+#[cfg(not(feature = "mock-machine"))]
+use uom::si::angular_velocity::revolution_per_minute;
 
 #[cfg(not(feature = "mock-machine"))]
 impl MachineAct for ExtruderV2 {
@@ -29,14 +31,29 @@ impl MachineAct for ExtruderV2 {
             self.switch_to_heat();
         }
 
-        let now = Instant::now();
+        let screw_speed = self.screw_speed_controller.get_motor_status().rpm;
+        self.values.publish(
+            &self.machine_identification_unique,
+            SCREW_SPEED,
+            screw_speed,
+            now,
+        );
+
+        // counts the screw hours until the next service
+        self.sync_maintenance(now);
+
+        self.maybe_emit_state_event();
+        // the namespace limits the emit rate
+        self.emit_live_values();
+        self.emit_maintenance();
+    }
 
-        // more than 33ms have passed since last emit (30 "fps" target)
-        if now.duration_since(self.last_measurement_emit) > Duration::from_secs_f64(1.0 / 30.0) {
-            self.maybe_emit_state_event();
-            // Emit live values at 30 FPS
-            self.emit_live_values();
-            self.last_measurement_emit = now;
+    fn act_safe_stop(&mut self) -> bool {
+        // turns the heaters and the motor off, the inverter ramps the screw down
+        if self.mode != super::ExtruderV2Mode::Standby {
+            self.set_mode_state(super::ExtruderV2Mode::Standby);
         }
+        let rpm = self.screw_speed_controller.get_motor_status().rpm;
+        rpm.get::<revolution_per_minute>().abs() < 1.0
     }
 }
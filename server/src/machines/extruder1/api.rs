@@ -1,4 +1,8 @@
 use super::{ExtruderV2Mode, mitsubishi_cs80::MotorStatus};
+use crate::machines::{
+    gain_history::GainHistoryEntry,
+    maintenance::{MaintenanceEvent, MaintenancePart},
+};
 
 #[cfg(not(feature = "mock-machine"))]
 use super::ExtruderV2;
@@ -7,27 +11,31 @@ use super::ExtruderV2;
 use crate::machines::extruder1::HeatingType;
 
 #[cfg(not(feature = "mock-machine"))]
+use control_core::alarms::{AlarmCondition, AlarmSeverity};
 use control_core::machines::api::MachineApi;
+#[cfg(not(feature = "mock-machine"))]
+use control_core::machines::{schema::MachineApiTypes, self_test::SelfTestReport};
 use control_core::socketio::{
-    event::{Event, GenericEvent},
-    namespace::{
-        CacheFn, CacheableEvents, Namespace, NamespaceCacheingLogic, cache_duration,
-        cache_first_and_last_event,
-    },
+    event::Event,
+    namespace::Namespace,
+    units::{FieldSchema, SchemaEvent},
 };
-use control_core_derive::BuildEvent;
+use control_core_derive::{BuildEvent, NamespaceEvents};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 #[cfg(not(feature = "mock-machine"))]
 use serde_json::Value;
 use smol::lock::Mutex;
-use std::{sync::Arc, time::Duration};
-use tracing::instrument;
+use std::sync::Arc;
+#[cfg(not(feature = "mock-machine"))]
+use std::time::Instant;
 use uom::si::{
     angular_velocity::revolution_per_minute, electric_current::ampere, electric_potential::volt,
-    frequency::hertz,
+    energy::kilowatt_hour, frequency::hertz, power::watt, pressure,
+    thermodynamic_temperature::degree_celsius,
 };
 
-#[derive(Debug, Clone, Default, Serialize)]
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
 pub struct MotorStatusValues {
     pub screw_rpm: f64, // rpm of motor
     pub frequency: f64, // frequency of motor
@@ -51,7 +59,7 @@ impl From<MotorStatus> for MotorStatusValues {
     }
 }
 
-#[derive(Serialize, Debug, Clone, Default)]
+#[derive(Serialize, Debug, Clone, Default, BuildEvent, JsonSchema)]
 pub struct LiveValuesEvent {
     /// screw rpm
     pub motor_status: MotorStatusValues,
@@ -80,12 +88,38 @@ pub struct LiveValuesEvent {
 }
 
 impl LiveValuesEvent {
-    pub fn build(&self) -> Event<Self> {
-        Event::new("LiveValuesEvent", self.clone())
+    pub fn schema() -> SchemaEvent {
+        // the heaters are switched off above 300 °C
+        let temperature = || FieldSchema::of::<degree_celsius>().range(0.0, 300.0);
+        let power = || FieldSchema::of::<watt>().min(0.0);
+        SchemaEvent::new([
+            (
+                "motor_status.screw_rpm",
+                FieldSchema::of::<revolution_per_minute>(),
+            ),
+            ("motor_status.frequency", FieldSchema::of::<hertz>()),
+            ("motor_status.voltage", FieldSchema::of::<volt>()),
+            ("motor_status.current", FieldSchema::of::<ampere>()),
+            ("motor_status.power", FieldSchema::of::<watt>()),
+            ("pressure", FieldSchema::of::<pressure::bar>().min(0.0)),
+            ("nozzle_temperature", temperature()),
+            ("front_temperature", temperature()),
+            ("back_temperature", temperature()),
+            ("middle_temperature", temperature()),
+            ("nozzle_power", power()),
+            ("front_power", power()),
+            ("back_power", power()),
+            ("middle_power", power()),
+            ("combined_power", power()),
+            (
+                "total_energy_kwh",
+                FieldSchema::of::<kilowatt_hour>().min(0.0),
+            ),
+        ])
     }
 }
 
-#[derive(Serialize, Debug, Clone, PartialEq, BuildEvent)]
+#[derive(Serialize, Debug, Clone, PartialEq, BuildEvent, JsonSchema)]
 pub struct StateEvent {
     pub is_default_state: bool,
     /// rotation state
@@ -108,33 +142,33 @@ pub struct StateEvent {
     pub pid_settings: PidSettingsStates,
 }
 
-#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
 pub struct RotationState {
     pub forward: bool,
 }
 
-#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
 pub struct ModeState {
     pub mode: ExtruderV2Mode,
 }
 
-#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
 pub struct RegulationState {
     pub uses_rpm: bool,
 }
 
-#[derive(Serialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Debug, Clone, PartialEq, JsonSchema)]
 pub struct PressureState {
     pub target_bar: f64,
     pub wiring_error: bool,
 }
 
-#[derive(Serialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Debug, Clone, PartialEq, JsonSchema)]
 pub struct ScrewState {
     pub target_rpm: f64,
 }
 
-#[derive(Serialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Debug, Clone, PartialEq, JsonSchema)]
 pub struct HeatingStates {
     pub nozzle: HeatingState,
     pub front: HeatingState,
@@ -142,19 +176,19 @@ pub struct HeatingStates {
     pub middle: HeatingState,
 }
 
-#[derive(Serialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Debug, Clone, PartialEq, JsonSchema)]
 pub struct HeatingState {
     pub target_temperature: f64,
     pub wiring_error: bool,
 }
 
-#[derive(Serialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Debug, Clone, PartialEq, JsonSchema)]
 pub struct ExtruderSettingsState {
     pub pressure_limit: f64,
     pub pressure_limit_enabled: bool,
 }
 
-#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
 pub struct InverterStatusState {
     /// RUN (Inverter running)
     pub running: bool,
@@ -176,25 +210,35 @@ pub struct InverterStatusState {
     pub fault_occurence: bool,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
 pub struct PidSettings {
     pub ki: f64,
     pub kp: f64,
     pub kd: f64,
 }
 
-#[derive(Serialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Debug, Clone, PartialEq, JsonSchema)]
 pub struct PidSettingsStates {
     pub temperature: PidSettings,
     pub pressure: PidSettings,
+    /// gains the pressure regulation had before, newest first
+    pub pressure_history: Vec<GainHistoryEntry<PidSettings>>,
 }
 
+#[derive(NamespaceEvents)]
+#[namespace_events(namespace = ExtruderV2Namespace)]
 pub enum ExtruderV2Events {
+    #[cache(duration_secs = 3600)]
     LiveValues(Event<LiveValuesEvent>),
+    #[cache(one)]
+    Schema(Event<SchemaEvent>),
+    #[cache(first_and_last)]
     State(Event<StateEvent>),
+    #[cache(first_and_last)]
+    Maintenance(Event<MaintenanceEvent>),
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, JsonSchema)]
 pub enum Mutation {
     /// INVERTER
     /// Frequency Control
@@ -217,51 +261,87 @@ pub enum Mutation {
 
     // Pid Configure
     SetPressurePidSettings(PidSettings),
+    /// Goes back to the gains of the pressure regulation before their last change
+    RevertPressurePidSettings,
 
     // Reset
     ResetInverter(bool),
+    /// Starts counting the screw hours again after the screw was serviced
+    ResetMaintenanceCounter(MaintenancePart),
 }
 
-#[derive(Debug)]
-pub struct ExtruderV2Namespace {
-    pub namespace: Arc<Mutex<Namespace>>,
+/// Extruder section of a recipe
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct ExtruderV2Recipe {
+    /// nozzle temperature in celsius
+    pub nozzle_temperature: f64,
+    /// front temperature in celsius
+    pub front_temperature: f64,
+    /// middle temperature in celsius
+    pub middle_temperature: f64,
+    /// back temperature in celsius
+    pub back_temperature: f64,
+    /// regulate screw by rpm (true) or by pressure (false)
+    pub uses_rpm: bool,
+    /// target screw rpm
+    pub target_rpm: f64,
+    /// target pressure in bar
+    pub target_pressure: f64,
 }
 
-impl NamespaceCacheingLogic<ExtruderV2Events> for ExtruderV2Namespace {
-    #[instrument(skip_all)]
-    fn emit(&mut self, events: ExtruderV2Events) {
-        let event = Arc::new(events.event_value());
-        let buffer_fn = events.event_cache_fn();
-
-        let mut namespace = self.namespace.lock_blocking();
-        namespace.emit(event, &buffer_fn);
+impl ExtruderV2Recipe {
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        let temperatures = [
+            self.nozzle_temperature,
+            self.front_temperature,
+            self.middle_temperature,
+            self.back_temperature,
+        ];
+        if temperatures.iter().any(|t| !(0.0..=400.0).contains(t)) {
+            return Err(anyhow::anyhow!(
+                "Heating temperatures must be between 0 and 400 °C"
+            ));
+        }
+        if self.target_rpm < 0.0 || self.target_pressure < 0.0 {
+            return Err(anyhow::anyhow!(
+                "Target rpm and pressure must not be negative"
+            ));
+        }
+        Ok(())
     }
-}
 
-impl CacheableEvents<Self> for ExtruderV2Events {
-    fn event_value(&self) -> GenericEvent {
-        match self {
-            Self::LiveValues(event) => event.into(),
-            Self::State(event) => event.into(),
-        }
+    /// The recipe expressed as the mutations a client would send
+    ///
+    /// Shared by the real and the mock extruder so both apply recipes the same way.
+    pub fn to_mutations(&self) -> Vec<Mutation> {
+        vec![
+            Mutation::SetNozzleHeatingTemperature(self.nozzle_temperature),
+            Mutation::SetFrontHeatingTargetTemperature(self.front_temperature),
+            Mutation::SetMiddleHeatingTemperature(self.middle_temperature),
+            Mutation::SetBackHeatingTargetTemperature(self.back_temperature),
+            Mutation::SetInverterTargetRpm(self.target_rpm),
+            Mutation::SetInverterTargetPressure(self.target_pressure),
+            Mutation::SetInverterRegulation(self.uses_rpm),
+        ]
     }
+}
 
-    fn event_cache_fn(&self) -> CacheFn {
-        let cache_one_hour = cache_duration(Duration::from_secs(60 * 60), Duration::from_secs(1));
-        let cache_first_and_last = cache_first_and_last_event();
+#[derive(Debug)]
+pub struct ExtruderV2Namespace {
+    pub namespace: Arc<Mutex<Namespace>>,
+}
 
-        match self {
-            Self::LiveValues(_) => cache_one_hour,
-            Self::State(_) => cache_first_and_last,
-        }
-    }
+#[cfg(not(feature = "mock-machine"))]
+impl MachineApiTypes for ExtruderV2 {
+    type Mutation = Mutation;
+    type Events = ExtruderV2Events;
 }
 
 #[cfg(not(feature = "mock-machine"))]
 impl MachineApi for ExtruderV2 {
     fn api_mutate(&mut self, request_body: Value) -> Result<(), anyhow::Error> {
         // there are multiple Modbus Frames that are "prebuilt"
-        let control: Mutation = serde_json::from_value(request_body)?;
+        let control = Self::parse_mutation(request_body)?;
         match control {
             Mutation::SetExtruderMode(mode) => self.set_mode_state(mode),
             Mutation::SetInverterRotationDirection(forward) => self.set_rotation_state(forward),
@@ -269,6 +349,9 @@ impl MachineApi for ExtruderV2 {
             Mutation::SetInverterTargetPressure(bar) => self.set_target_pressure(bar),
             Mutation::SetInverterTargetRpm(rpm) => self.set_target_rpm(rpm),
             Mutation::ResetInverter(_) => self.reset_inverter(),
+            Mutation::ResetMaintenanceCounter(part) => {
+                self.maintenance.reset(part, Instant::now())?
+            }
 
             Mutation::SetFrontHeatingTargetTemperature(temp) => {
                 self.set_target_temperature(temp, HeatingType::Front)
@@ -292,6 +375,7 @@ impl MachineApi for ExtruderV2 {
             Mutation::SetPressurePidSettings(settings) => {
                 self.configure_pressure_pid(settings);
             }
+            Mutation::RevertPressurePidSettings => self.revert_pressure_pid()?,
         }
         Ok(())
     }
@@ -299,4 +383,62 @@ impl MachineApi for ExtruderV2 {
     fn api_event_namespace(&mut self) -> Arc<Mutex<Namespace>> {
         self.namespace.namespace.clone()
     }
+
+    fn api_alarms(&self) -> Vec<AlarmCondition> {
+        let mut alarms = Vec::new();
+        if self.screw_speed_controller.get_wiring_error() {
+            alarms.push(AlarmCondition::new(
+                "pressure_sensor_wiring_error",
+                "Pressure sensor wiring error",
+                AlarmSeverity::Critical,
+            ));
+        }
+        let heating_zones = [
+            ("nozzle", &self.temperature_controller_nozzle),
+            ("front", &self.temperature_controller_front),
+            ("middle", &self.temperature_controller_middle),
+            ("back", &self.temperature_controller_back),
+        ];
+        for (zone, controller) in heating_zones {
+            if controller.heating.wiring_error {
+                alarms.push(AlarmCondition::new(
+                    &format!("{}_heating_wiring_error", zone),
+                    format!("Temperature sensor wiring error in {} heating zone", zone),
+                    AlarmSeverity::Critical,
+                ));
+            }
+        }
+        alarms.extend(self.maintenance.alarms());
+        alarms
+    }
+
+    fn api_apply_recipe(&mut self, section: Value) -> Result<(), anyhow::Error> {
+        let recipe: ExtruderV2Recipe = serde_json::from_value(section)?;
+        recipe.validate()?;
+        for mutation in recipe.to_mutations() {
+            self.api_mutate(serde_json::to_value(mutation)?)?;
+        }
+        Ok(())
+    }
+
+    fn api_current_mutation(&self, name: &str) -> Option<Value> {
+        let mutation = match name {
+            "SetInverterTargetRpm" => Mutation::SetInverterTargetRpm(
+                self.screw_speed_controller
+                    .target_rpm
+                    .get::<revolution_per_minute>(),
+            ),
+            _ => return None,
+        };
+        serde_json::to_value(mutation).ok()
+    }
+
+    fn api_run_self_test(&mut self) -> Result<(), anyhow::Error> {
+        self.run_self_test();
+        Ok(())
+    }
+
+    fn api_self_test_report(&self) -> Option<SelfTestReport> {
+        self.self_test.clone()
+    }
 }
@@ -9,6 +9,8 @@ use crate::machines::extruder1::{
     },
 };
 #[cfg(not(feature = "mock-machine"))]
+use crate::machines::maintenance::MaintenanceEvent;
+#[cfg(not(feature = "mock-machine"))]
 use control_core::helpers::hasher_serializer::hash_with_serde_model;
 #[cfg(not(feature = "mock-machine"))]
 use control_core::socketio::event::BuildEvent;
@@ -111,11 +113,8 @@ impl ExtruderV2 {
                     kp: 0.0,
                     kd: 0.0,
                 },
-                pressure: PidSettings {
-                    ki: self.screw_speed_controller.pid.get_ki(),
-                    kp: self.screw_speed_controller.pid.get_kp(),
-                    kd: self.screw_speed_controller.pid.get_kd(),
-                },
+                pressure: self.pressure_pid_settings(),
+                pressure_history: self.pressure_pid_history.entries(),
             },
         }
     }
@@ -123,6 +122,11 @@ impl ExtruderV2 {
 
 #[cfg(not(feature = "mock-machine"))]
 impl ExtruderV2 {
+    pub fn emit_schema(&mut self) {
+        let event = LiveValuesEvent::schema().build();
+        self.namespace.emit(ExtruderV2Events::Schema(event));
+    }
+
     pub fn emit_state(&mut self) {
         let state = self.build_state_event();
         let hash = hash_with_serde_model(self.screw_speed_controller.get_inverter_status());
@@ -195,6 +199,23 @@ impl ExtruderV2 {
         self.namespace.emit(ExtruderV2Events::LiveValues(event));
     }
 
+    /// Emits the maintenance counters, limited to the emit rate of the event
+    pub fn emit_maintenance(&mut self) {
+        if !self
+            .namespace
+            .namespace
+            .lock_blocking()
+            .is_due("MaintenanceEvent")
+        {
+            return;
+        }
+        let event = MaintenanceEvent {
+            counters: self.maintenance.values(),
+        };
+        self.namespace
+            .emit(ExtruderV2Events::Maintenance(event.build()));
+    }
+
     // === Steuerungsfunktionen mit emit_state ===
 
     pub fn set_nozzle_pressure_limit_is_enabled(&mut self, enabled: bool) {
@@ -273,10 +294,42 @@ impl ExtruderV2 {
         self.emit_state();
     }
 
+    const fn pressure_pid_settings(&self) -> PidSettings {
+        let pid = &self.screw_speed_controller.pid;
+        PidSettings {
+            ki: pid.get_ki(),
+            kp: pid.get_kp(),
+            kd: pid.get_kd(),
+        }
+    }
+
     pub fn configure_pressure_pid(&mut self, settings: PidSettings) {
+        let previous = self.pressure_pid_settings();
+        self.screw_speed_controller
+            .pid
+            .set_gains(settings.kp, settings.ki, settings.kd);
+        self.pressure_pid_history.record(previous, &settings);
+        self.emit_state();
+    }
+
+    pub fn revert_pressure_pid(&mut self) -> Result<(), anyhow::Error> {
+        let settings = self.pressure_pid_history.pop().ok_or_else(|| {
+            anyhow::anyhow!(
+                "[{}::ExtruderV2::revert_pressure_pid] The PID gains were not changed",
+                module_path!()
+            )
+        })?;
         self.screw_speed_controller
             .pid
-            .configure(settings.ki, settings.kp, settings.kd);
+            .set_gains(settings.kp, settings.ki, settings.kd);
+        tracing::info!(
+            "Reverted the pressure PID gains of {} to kp {}, ki {}, kd {}",
+            self.machine_identification_unique,
+            settings.kp,
+            settings.ki,
+            settings.kd
+        );
         self.emit_state();
+        Ok(())
     }
 }
@@ -1,15 +1,18 @@
-use crate::machines::extruder1::mock::ExtruderV2;
+use crate::machines::extruder1::{ExtruderV2Mode, mock::ExtruderV2};
 use control_core::machines::new::MachineAct;
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
 impl MachineAct for ExtruderV2 {
-    fn act(&mut self, now: Instant) {
-        // more than 33ms have passed since last emit (30 "fps" target)
-        if now.duration_since(self.last_measurement_emit) > Duration::from_secs_f64(1.0 / 30.0) {
-            self.maybe_emit_state_event();
-            // Emit live values at 30 FPS
-            self.emit_live_values();
-            self.last_measurement_emit = now;
+    fn act(&mut self, _now: Instant) {
+        self.maybe_emit_state_event();
+        // the namespace limits the emit rate
+        self.emit_live_values();
+    }
+
+    fn act_safe_stop(&mut self) -> bool {
+        if self.mode != ExtruderV2Mode::Standby {
+            self.set_mode_state(ExtruderV2Mode::Standby);
         }
+        true
     }
 }
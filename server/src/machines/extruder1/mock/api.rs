@@ -1,13 +1,22 @@
-use crate::machines::extruder1::{HeatingType, api::Mutation, mock::ExtruderV2};
-use control_core::machines::api::MachineApi;
+use crate::machines::extruder1::{
+    HeatingType,
+    api::{ExtruderV2Events, ExtruderV2Recipe, Mutation},
+    mock::ExtruderV2,
+};
+use control_core::machines::{api::MachineApi, schema::MachineApiTypes};
 use control_core::socketio::namespace::Namespace;
 use smol::lock::Mutex;
 use std::sync::Arc;
 
+impl MachineApiTypes for ExtruderV2 {
+    type Mutation = Mutation;
+    type Events = ExtruderV2Events;
+}
+
 impl MachineApi for ExtruderV2 {
     fn api_mutate(&mut self, request_body: serde_json::Value) -> Result<(), anyhow::Error> {
         // there are multiple Modbus Frames that are "prebuilt"
-        let control: Mutation = serde_json::from_value(request_body)?;
+        let control = Self::parse_mutation(request_body)?;
         match control {
             Mutation::SetExtruderMode(mode) => self.set_mode_state(mode),
             Mutation::SetInverterRotationDirection(forward) => self.set_rotation_state(forward),
@@ -15,6 +24,7 @@ impl MachineApi for ExtruderV2 {
             Mutation::SetInverterTargetPressure(bar) => self.set_target_pressure(bar),
             Mutation::SetInverterTargetRpm(rpm) => self.set_target_rpm(rpm),
             Mutation::ResetInverter(_) => (),
+            Mutation::ResetMaintenanceCounter(_) => (),
             Mutation::SetFrontHeatingTargetTemperature(temp) => {
                 self.set_target_temperature(temp, HeatingType::Front)
             }
@@ -37,6 +47,7 @@ impl MachineApi for ExtruderV2 {
             Mutation::SetPressurePidSettings(settings) => {
                 self.configure_pressure_pid(settings);
             }
+            Mutation::RevertPressurePidSettings => self.revert_pressure_pid()?,
         }
         Ok(())
     }
@@ -44,4 +55,13 @@ impl MachineApi for ExtruderV2 {
     fn api_event_namespace(&mut self) -> Arc<Mutex<Namespace>> {
         self.namespace.namespace.clone()
     }
+
+    fn api_apply_recipe(&mut self, section: serde_json::Value) -> Result<(), anyhow::Error> {
+        let recipe: ExtruderV2Recipe = serde_json::from_value(section)?;
+        recipe.validate()?;
+        for mutation in recipe.to_mutations() {
+            self.api_mutate(serde_json::to_value(mutation)?)?;
+        }
+        Ok(())
+    }
 }
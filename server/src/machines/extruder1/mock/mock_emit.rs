@@ -27,6 +27,11 @@ impl ExtruderV2 {
 }
 
 impl ExtruderV2 {
+    pub fn emit_schema(&mut self) {
+        let event = LiveValuesEvent::schema().build();
+        self.namespace.emit(ExtruderV2Events::Schema(event));
+    }
+
     pub fn emit_state(&mut self) {
         let state = self.build_state_event();
         let hash = hash_with_serde_model(self.inverter_status_state.clone());
@@ -128,9 +133,23 @@ impl ExtruderV2 {
     }
 
     pub fn configure_pressure_pid(&mut self, settings: PidSettings) {
-        self.pid_settings.pressure.ki = settings.ki;
-        self.pid_settings.pressure.kp = settings.kp;
-        self.pid_settings.pressure.kd = settings.kd;
+        let previous = std::mem::replace(&mut self.pid_settings.pressure, settings);
+        self.pressure_pid_history
+            .record(previous, &self.pid_settings.pressure);
+        self.pid_settings.pressure_history = self.pressure_pid_history.entries();
+        self.emit_state();
+    }
+
+    pub fn revert_pressure_pid(&mut self) -> Result<(), anyhow::Error> {
+        let settings = self.pressure_pid_history.pop().ok_or_else(|| {
+            anyhow::anyhow!(
+                "[{}::ExtruderV2::revert_pressure_pid] The PID gains were not changed",
+                module_path!()
+            )
+        })?;
+        self.pid_settings.pressure = settings;
+        self.pid_settings.pressure_history = self.pressure_pid_history.entries();
         self.emit_state();
+        Ok(())
     }
 }
@@ -14,10 +14,11 @@ use crate::machines::{
         ExtruderV2Mode,
         api::{
             ExtruderSettingsState, ExtruderV2Namespace, HeatingStates, InverterStatusState,
-            ModeState, MotorStatusValues, PidSettingsStates, PressureState, RegulationState,
-            RotationState, ScrewState,
+            ModeState, MotorStatusValues, PidSettings, PidSettingsStates, PressureState,
+            RegulationState, RotationState, ScrewState,
         },
     },
+    gain_history::GainHistory,
 };
 
 // Just checking mock-machine feature here to exclude these modules from compilation entirely
@@ -35,7 +36,6 @@ pub mod new;
 pub struct ExtruderV2 {
     machine_identification_unique: MachineIdentificationUnique,
     namespace: ExtruderV2Namespace,
-    last_measurement_emit: Instant,
     last_status_hash: Option<u64>,
     mode: ExtruderV2Mode,
     /// Energy tracking for total consumption calculation
@@ -65,6 +65,8 @@ pub struct ExtruderV2 {
     pub inverter_status_state: InverterStatusState,
     /// pid settings
     pub pid_settings: PidSettingsStates,
+    /// Gains of the pressure regulation before they were changed
+    pub pressure_pid_history: GainHistory<PidSettings>,
 
     pub motor_status: MotorStatusValues,
     /// pressure in bar
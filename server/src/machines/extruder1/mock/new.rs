@@ -7,6 +7,7 @@ use crate::machines::extruder1::{
     },
     mock::ExtruderV2,
 };
+use crate::machines::gain_history::GainHistory;
 
 impl control_core::machines::new::MachineNewTrait for ExtruderV2 {
     fn new(
@@ -29,14 +30,11 @@ impl control_core::machines::new::MachineNewTrait for ExtruderV2 {
             }
         }
 
-        let now = std::time::Instant::now();
-
         let mut extruder_mock_machine = Self {
             machine_identification_unique: params.get_machine_identification_unique(),
             namespace: ExtruderV2Namespace {
                 namespace: params.namespace.clone(),
             },
-            last_measurement_emit: now,
 
             mode: ExtruderV2Mode::Standby, // Start in standby mode
             emitted_default_state: false,
@@ -98,7 +96,9 @@ impl control_core::machines::new::MachineNewTrait for ExtruderV2 {
                     kp: 0.0,
                     kd: 0.0,
                 },
+                pressure_history: Vec::new(),
             },
+            pressure_pid_history: GainHistory::new(),
             motor_status: MotorStatusValues {
                 screw_rpm: 0.0,
                 frequency: 0.0,
@@ -123,6 +123,7 @@ impl control_core::machines::new::MachineNewTrait for ExtruderV2 {
             target_pressure: 0.0,
         };
 
+        extruder_mock_machine.emit_schema();
         extruder_mock_machine.emit_state();
 
         Ok(extruder_mock_machine)
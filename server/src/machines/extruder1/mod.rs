@@ -1,12 +1,19 @@
 #[cfg(not(feature = "mock-machine"))]
-use std::time::Instant;
+use std::{sync::Arc, time::Instant};
 
 #[cfg(not(feature = "mock-machine"))]
-use control_core::machines::identification::{MachineIdentification, MachineIdentificationUnique};
+use control_core::machines::{
+    identification::{MachineIdentification, MachineIdentificationUnique},
+    self_test::{SelfTestReport, SelfTestResult},
+    values::MachineValueBus,
+};
 #[cfg(not(feature = "mock-machine"))]
 use control_core_derive::Machine;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 #[cfg(not(feature = "mock-machine"))]
+use uom::si::angular_velocity::revolution_per_minute;
+#[cfg(not(feature = "mock-machine"))]
 use uom::si::electric_current::ampere;
 #[cfg(not(feature = "mock-machine"))]
 use uom::si::electric_potential::volt;
@@ -16,9 +23,12 @@ use uom::si::{f64::ThermodynamicTemperature, thermodynamic_temperature::degree_c
 use crate::machines::{
     MACHINE_EXTRUDER_V1, VENDOR_QITECH,
     extruder1::{
-        api::ExtruderV2Namespace, screw_speed_controller::ScrewSpeedController,
+        api::{ExtruderV2Namespace, PidSettings},
+        screw_speed_controller::ScrewSpeedController,
         temperature_controller::TemperatureController,
     },
+    gain_history::GainHistory,
+    maintenance::{MaintenanceCounters, MaintenancePart},
 };
 
 pub mod act;
@@ -30,7 +40,7 @@ pub mod new;
 pub mod screw_speed_controller;
 pub mod temperature_controller;
 
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
 pub enum ExtruderV2Mode {
     Standby,
     Heat,
@@ -68,10 +78,13 @@ pub enum HeatingType {
 pub struct ExtruderV2 {
     machine_identification_unique: MachineIdentificationUnique,
     namespace: ExtruderV2Namespace,
-    last_measurement_emit: Instant,
+    /// Publishes the screw speed for other machines
+    values: Arc<MachineValueBus>,
     last_status_hash: Option<u64>,
     mode: ExtruderV2Mode,
     screw_speed_controller: ScrewSpeedController,
+    /// Gains of the pressure regulation before they were changed
+    pressure_pid_history: GainHistory<PidSettings>,
     temperature_controller_front: TemperatureController,
     temperature_controller_middle: TemperatureController,
     temperature_controller_back: TemperatureController,
@@ -81,6 +94,12 @@ pub struct ExtruderV2 {
     total_energy_kwh: f64,
     last_energy_calculation_time: Option<Instant>,
 
+    /// Hours the screw turned since its last service
+    maintenance: MaintenanceCounters,
+
+    /// Report of the last self test
+    self_test: Option<SelfTestReport>,
+
     /// will be initalized as false and set to true by `emit_state`
     /// This way we can signal to the client that the first state emission is a default state
     emitted_default_state: bool,
@@ -137,6 +156,24 @@ impl ExtruderV2 {
         self.last_energy_calculation_time = Some(now);
     }
 
+    /// Slower is a standing screw, rpm
+    const SCREW_TURNING_SPEED: f64 = 1.0;
+
+    /// Counts the hours the screw turns, called by `act`
+    fn sync_maintenance(&mut self, now: Instant) {
+        let dt = self.maintenance.elapsed(now);
+        let rpm = self
+            .screw_speed_controller
+            .get_motor_status()
+            .rpm
+            .get::<revolution_per_minute>();
+        if rpm.abs() >= Self::SCREW_TURNING_SPEED {
+            self.maintenance
+                .add(MaintenancePart::ExtruderScrew, dt.as_secs_f64() / 3600.0);
+        }
+        self.maintenance.sync_journal(now);
+    }
+
     // Funktionen ohne emit_state bleiben hier
 
     // Set all relais to ZERO
@@ -208,4 +245,57 @@ impl ExtruderV2 {
     fn reset_inverter(&mut self) {
         self.screw_speed_controller.inverter.reset_inverter();
     }
+
+    /// Checks the connection to the inverter and the wiring of the sensors, nothing moves
+    /// or heats up
+    fn run_self_test(&mut self) {
+        let mut report = SelfTestReport::new();
+        let inverter = &self.screw_speed_controller.inverter;
+        match (
+            inverter.modbus_serial_interface.is_initialized(),
+            inverter.status.fault_occurence,
+        ) {
+            (false, _) => report.add(
+                "inverter",
+                SelfTestResult::Failed,
+                "serial interface not initialized",
+            ),
+            (true, true) => report.add(
+                "inverter",
+                SelfTestResult::Failed,
+                "responds with a fault, reset the inverter",
+            ),
+            (true, false) => report.add("inverter", SelfTestResult::Passed, "responds"),
+        }
+        let sensors = [
+            (
+                "pressure sensor",
+                self.screw_speed_controller.get_wiring_error(),
+            ),
+            (
+                "nozzle temperature sensor",
+                self.temperature_controller_nozzle.heating.wiring_error,
+            ),
+            (
+                "front temperature sensor",
+                self.temperature_controller_front.heating.wiring_error,
+            ),
+            (
+                "middle temperature sensor",
+                self.temperature_controller_middle.heating.wiring_error,
+            ),
+            (
+                "back temperature sensor",
+                self.temperature_controller_back.heating.wiring_error,
+            ),
+        ];
+        for (name, wiring_error) in sensors {
+            match wiring_error {
+                true => report.add(name, SelfTestResult::Failed, "wiring error"),
+                false => report.add(name, SelfTestResult::Passed, "wired"),
+            }
+        }
+        report.finish();
+        self.self_test = Some(report);
+    }
 }
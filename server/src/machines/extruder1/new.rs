@@ -1,6 +1,8 @@
 #[cfg(not(feature = "mock-machine"))]
 use crate::machines::get_ethercat_device;
 #[cfg(not(feature = "mock-machine"))]
+use crate::machines::maintenance::{MaintenanceCounters, MaintenancePart};
+#[cfg(not(feature = "mock-machine"))]
 use anyhow::Error;
 #[cfg(not(feature = "mock-machine"))]
 use control_core::machines::new::MachineNewHardware;
@@ -27,7 +29,6 @@ use ethercat_hal::devices::{
 #[cfg(not(feature = "mock-machine"))]
 use std::time::Duration;
 #[cfg(not(feature = "mock-machine"))]
-use std::time::Instant;
 #[cfg(not(feature = "mock-machine"))]
 use uom::si::angular_velocity::AngularVelocity;
 #[cfg(not(feature = "mock-machine"))]
@@ -54,6 +55,8 @@ use uom::si::thermodynamic_temperature::{ThermodynamicTemperature, degree_celsiu
 
 #[cfg(not(feature = "mock-machine"))]
 use crate::machines::extruder1::temperature_controller::TemperatureController;
+#[cfg(not(feature = "mock-machine"))]
+use crate::machines::gain_history::GainHistory;
 
 #[cfg(not(feature = "mock-machine"))]
 use super::{
@@ -226,18 +229,25 @@ impl MachineNewTrait for ExtruderV2 {
                 namespace: ExtruderV2Namespace {
                     namespace: params.namespace.clone(),
                 },
-                last_measurement_emit: Instant::now(),
+                values: params.values.clone(),
                 mode: ExtruderV2Mode::Standby,
                 total_energy_kwh: 0.0,
                 last_energy_calculation_time: None,
+                maintenance: MaintenanceCounters::new(
+                    &params.get_machine_identification_unique(),
+                    &[MaintenancePart::ExtruderScrew],
+                ),
                 temperature_controller_front,
                 temperature_controller_middle,
                 temperature_controller_back,
                 temperature_controller_nozzle,
                 screw_speed_controller,
+                pressure_pid_history: GainHistory::new(),
+                self_test: None,
                 emitted_default_state: false,
                 last_status_hash: None,
             };
+            extruder.emit_schema();
             extruder.emit_state();
             Ok(extruder)
         })
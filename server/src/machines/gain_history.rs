@@ -0,0 +1,101 @@
+use control_core::helpers::clock::unix_millis;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// Gains a controller had before they were changed through the API, so a detuned loop can be
+/// reverted
+#[derive(Debug, Clone)]
+pub struct GainHistory<T> {
+    /// Oldest first
+    entries: VecDeque<GainHistoryEntry<T>>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
+pub struct GainHistoryEntry<T> {
+    pub gains: T,
+    /// unix timestamp in milliseconds at which the gains were replaced
+    pub replaced_at: u64,
+}
+
+impl<T: Clone + PartialEq> GainHistory<T> {
+    /// Gain sets kept, the oldest one is forgotten
+    pub const CAPACITY: usize = 16;
+
+    pub const fn new() -> Self {
+        Self {
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// Remembers `previous` when it was replaced by `next`, unchanged gains are not recorded
+    pub fn record(&mut self, previous: T, next: &T) {
+        self.record_at(previous, next, unix_millis());
+    }
+
+    fn record_at(&mut self, previous: T, next: &T, replaced_at: u64) {
+        if previous == *next {
+            return;
+        }
+        if self.entries.len() >= Self::CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(GainHistoryEntry {
+            gains: previous,
+            replaced_at,
+        });
+    }
+
+    /// Gains before the last change, reverting them again goes back another step
+    pub fn pop(&mut self) -> Option<T> {
+        self.entries.pop_back().map(|entry| entry.gains)
+    }
+
+    /// Newest first
+    pub fn entries(&self) -> Vec<GainHistoryEntry<T>> {
+        self.entries.iter().rev().cloned().collect()
+    }
+}
+
+impl<T: Clone + PartialEq> Default for GainHistory<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gain_history() {
+        let mut history = GainHistory::new();
+        history.record_at(1.0, &2.0, 100);
+        // unchanged gains
+        history.record_at(2.0, &2.0, 200);
+        history.record_at(2.0, &3.0, 300);
+        assert_eq!(
+            history.entries(),
+            vec![
+                GainHistoryEntry {
+                    gains: 2.0,
+                    replaced_at: 300
+                },
+                GainHistoryEntry {
+                    gains: 1.0,
+                    replaced_at: 100
+                }
+            ]
+        );
+
+        assert_eq!(history.pop(), Some(2.0));
+        assert_eq!(history.pop(), Some(1.0));
+        assert_eq!(history.pop(), None);
+
+        for gains in 0..=GainHistory::<f64>::CAPACITY {
+            history.record_at(gains as f64, &-1.0, 0);
+        }
+        assert_eq!(history.entries().len(), GainHistory::<f64>::CAPACITY);
+        assert_eq!(history.entries().last().map(|entry| entry.gains), Some(1.0));
+    }
+}
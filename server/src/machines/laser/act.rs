@@ -1,6 +1,6 @@
 use super::LaserMachine;
 use control_core::machines::new::MachineAct;
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
 /// Implements the `MachineAct` trait for the `LaserMachine`.
 ///
@@ -12,24 +12,21 @@ use std::time::{Duration, Instant};
 ///
 /// # Description
 /// This method is called to perform periodic actions for the `LaserMachine`. Specifically:
-/// - It checks if the time elapsed since the last measurement emission exceeds 20 milliseconds.
-/// - If the condition is met, it asynchronously emits live values at 60 FPS.
-///
-/// The method ensures that the diameter value is updated approximately 60 times per second.
+/// - It reads the latest measurement of the laser.
+/// - It adopts the target diameter of a recipe applied to the winder.
+/// - It starts the tolerance monitoring once the diameter settled after a start.
+/// - It emits live values and the min/max diameter, the namespace limits their emit rate.
+/// - It analyzes the diameter for periodic disturbances.
+/// - It compares the diameter to the extrusion to detect a dirty lens.
 ///
 impl MachineAct for LaserMachine {
     fn act(&mut self, now: Instant) {
         self.update();
-        // more than 33ms have passed since last emit (30 "fps" target)
-        if now.duration_since(self.last_measurement_emit) > Duration::from_secs_f64(1.0 / 30.0) {
-            self.emit_live_values();
-            self.last_measurement_emit = now;
-        }
-
-        // Emit min/max diameter every second
-        if now.duration_since(self.last_minmax_emit) > Duration::from_secs(1) {
-            self.emit_min_max_diameter();
-            self.last_minmax_emit = now;
-        }
+        self.adopt_diameter_target();
+        self.monitor_tolerance(now);
+        self.detect_lens_drift(now);
+        self.emit_live_values();
+        self.emit_min_max_diameter();
+        self.emit_disturbances(now);
     }
 }
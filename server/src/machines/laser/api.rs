@@ -1,66 +1,153 @@
-use super::LaserMachine;
+use super::{LaserMachine, tolerance_monitor::MonitoringPhase};
 use control_core::{
-    machines::api::MachineApi,
+    alarms::{AlarmCondition, AlarmSeverity},
+    machines::{
+        api::MachineApi, identification::MachineCapabilities, schema::MachineApiTypes,
+        self_test::SelfTestReport,
+    },
+    rest::unit_value::UnitValue,
     socketio::{
-        event::{Event, GenericEvent},
-        namespace::{
-            CacheFn, CacheableEvents, Namespace, NamespaceCacheingLogic, cache_duration,
-            cache_first_and_last_event,
-        },
+        event::Event,
+        namespace::Namespace,
+        units::{DisplayQuantity, EventUnits, FieldSchema, SchemaEvent},
     },
 };
-use control_core_derive::BuildEvent;
+use control_core_derive::{BuildEvent, NamespaceEvents};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use smol::lock::Mutex;
-use std::{sync::Arc, time::Duration};
-use tracing::instrument;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use uom::si::{
+    length::millimeter,
+    ratio::ratio,
+    time::{minute, second},
+};
 
-#[derive(Serialize, Debug, Clone, Default)]
+#[derive(Serialize, Debug, Clone, Default, BuildEvent, JsonSchema)]
 pub struct LiveValuesEvent {
     /// diameter measurement in mm
     pub diameter: f64,
     pub x_diameter: Option<f64>,
     pub y_diameter: Option<f64>,
     pub roundness: Option<f64>,
+    /// diameter of every strand in mm, 0 for strands without a measurement
+    pub strand_diameters: Vec<f64>,
+    /// estimated contamination of the lens from the drift of the diameter, 0 for a clean lens
+    /// and 1 once the diameter reads 5% too small, `None` while the baseline is learned
+    pub lens_contamination: Option<f64>,
+    pub units: EventUnits,
 }
 
 impl LiveValuesEvent {
-    pub fn build(&self) -> Event<Self> {
-        Event::new("LiveValuesEvent", self.clone())
+    pub const UNITS: EventUnits = EventUnits(&[
+        ("diameter", DisplayQuantity::Diameter),
+        ("x_diameter", DisplayQuantity::Diameter),
+        ("y_diameter", DisplayQuantity::Diameter),
+        ("strand_diameters", DisplayQuantity::Diameter),
+    ]);
+
+    pub fn schema() -> SchemaEvent {
+        let diameter = || FieldSchema::display(DisplayQuantity::Diameter).min(0.0);
+        SchemaEvent::new([
+            ("diameter", diameter()),
+            ("x_diameter", diameter()),
+            ("y_diameter", diameter()),
+            ("roundness", FieldSchema::of::<ratio>().range(0.0, 1.0)),
+            ("strand_diameters", diameter()),
+            (
+                "lens_contamination",
+                FieldSchema::of::<ratio>().range(0.0, 1.0),
+            ),
+        ])
     }
 }
 
-#[derive(Serialize, Debug, Clone, Default)]
+#[derive(Serialize, Debug, Clone, Default, BuildEvent, JsonSchema)]
 pub struct MinMaxDiameterEvent {
     /// minimum diameter in the timeframe in mm
     pub min_diameter: Option<f64>,
     /// maximum diameter in the timeframe in mm  
     pub max_diameter: Option<f64>,
+    /// worst roundness in the timeframe, 1 is perfectly round
+    pub min_roundness: Option<f64>,
+    /// mean roundness in the timeframe
+    pub avg_roundness: Option<f64>,
     /// timeframe in minutes
     pub timeframe_minutes: u64,
+    pub units: EventUnits,
 }
 
 impl MinMaxDiameterEvent {
-    pub fn build(&self) -> Event<Self> {
-        Event::new("MinMaxDiameterEvent", self.clone())
-    }
+    pub const UNITS: EventUnits = EventUnits(&[
+        ("min_diameter", DisplayQuantity::Diameter),
+        ("max_diameter", DisplayQuantity::Diameter),
+    ]);
+}
+
+#[derive(Serialize, Debug, Clone, BuildEvent, JsonSchema)]
+pub struct DisturbancesEvent {
+    /// strongest periodic disturbances of the diameter first
+    pub peaks: Vec<DisturbancePeakValues>,
+    /// screw rotation frequency of the extruder in Hz
+    pub screw_frequency: Option<f64>,
+    /// line speed of the winder in m/min
+    pub line_speed: Option<f64>,
+    pub units: EventUnits,
+}
+
+impl DisturbancesEvent {
+    pub const UNITS: EventUnits = EventUnits(&[
+        ("peaks.amplitude", DisplayQuantity::Diameter),
+        ("peaks.wavelength", DisplayQuantity::Position),
+        ("line_speed", DisplayQuantity::LineSpeed),
+    ]);
+}
+
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+pub struct DisturbancePeakValues {
+    /// frequency in Hz
+    pub frequency: f64,
+    /// amplitude around the mean diameter in mm
+    pub amplitude: f64,
+    /// frequency relative to the screw rotation, 1 is once per revolution
+    pub screw_order: Option<f64>,
+    /// filament length from one peak to the next in mm
+    pub wavelength: Option<f64>,
 }
 
-#[derive(Serialize, Debug, Clone, BuildEvent)]
+#[derive(Serialize, Debug, Clone, BuildEvent, JsonSchema)]
 pub struct StateEvent {
     pub is_default_state: bool,
     /// laser state
     pub laser_state: LaserState,
+    pub units: EventUnits,
 }
 
 impl StateEvent {
-    pub fn build(&self) -> Event<Self> {
-        Event::new("StateEvent", self.clone())
-    }
+    pub const UNITS: EventUnits = EventUnits(&[
+        ("laser_state.higher_tolerance", DisplayQuantity::Diameter),
+        ("laser_state.lower_tolerance", DisplayQuantity::Diameter),
+        ("laser_state.target_diameter", DisplayQuantity::Diameter),
+        (
+            "laser_state.strand_targets.target_diameter",
+            DisplayQuantity::Diameter,
+        ),
+        (
+            "laser_state.strand_targets.lower_tolerance",
+            DisplayQuantity::Diameter,
+        ),
+        (
+            "laser_state.strand_targets.higher_tolerance",
+            DisplayQuantity::Diameter,
+        ),
+    ]);
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, JsonSchema)]
 pub struct LaserState {
     /// higher tolerance in mm
     pub higher_tolerance: f64,
@@ -70,78 +157,188 @@ pub struct LaserState {
     pub target_diameter: f64,
     /// timeframe for min/max tracking in minutes
     pub min_max_timeframe_minutes: u64,
+    /// time in s the diameter must stay in tolerance after a start before monitoring begins
+    pub startup_grace: f64,
+    /// alarms and min/max tracking are active while monitoring
+    pub monitoring_phase: MonitoringPhase,
+    /// target and tolerances of every strand
+    pub strand_targets: Vec<StrandTargetSettings>,
 }
 
-pub enum LaserEvents {
-    LiveValues(Event<LiveValuesEvent>),
-    State(Event<StateEvent>),
-    MinMaxDiameter(Event<MinMaxDiameterEvent>),
+/// Target of one strand, lengths in mm
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct StrandTargetSettings {
+    /// index of the strand, starting at 0
+    pub strand: usize,
+    pub target_diameter: f64,
+    pub lower_tolerance: f64,
+    pub higher_tolerance: f64,
 }
 
-#[derive(Debug)]
-pub struct LaserMachineNamespace {
-    pub namespace: Arc<Mutex<Namespace>>,
+impl StrandTargetSettings {
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        validate_target(
+            self.target_diameter,
+            self.lower_tolerance,
+            self.higher_tolerance,
+        )
+    }
 }
 
-impl CacheableEvents<Self> for LaserEvents {
-    fn event_value(&self) -> GenericEvent {
-        match self {
-            Self::LiveValues(event) => event.into(),
-            Self::State(event) => event.into(),
-            Self::MinMaxDiameter(event) => event.into(),
-        }
+/// Laser section of a recipe
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct LaserRecipe {
+    /// target diameter in mm
+    pub target_diameter: f64,
+    /// lower tolerance in mm
+    pub lower_tolerance: f64,
+    /// higher tolerance in mm
+    pub higher_tolerance: f64,
+}
+
+impl LaserRecipe {
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        validate_target(
+            self.target_diameter,
+            self.lower_tolerance,
+            self.higher_tolerance,
+        )
     }
+}
 
-    fn event_cache_fn(&self) -> CacheFn {
-        let cache_one_hour = cache_duration(Duration::from_secs(60 * 60), Duration::from_secs(1));
-        let cache_first_and_last = cache_first_and_last_event();
+/// Target, tolerances and monitoring of a laser, see [`MachineApi::api_export_config`]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct LaserConfig {
+    #[serde(flatten)]
+    pub recipe: LaserRecipe,
+    /// timeframe of the min/max diameter in minutes
+    pub min_max_timeframe_minutes: u64,
+    /// time the diameter must stay in tolerance after a start in s
+    pub startup_grace: f64,
+    /// target of every strand
+    pub strand_targets: Vec<StrandTargetSettings>,
+}
 
-        match self {
-            Self::LiveValues(_) => cache_one_hour,
-            Self::State(_) => cache_first_and_last,
-            Self::MinMaxDiameter(_) => cache_first_and_last,
+impl LaserConfig {
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        self.recipe.validate()?;
+        if self.min_max_timeframe_minutes < 1 {
+            return Err(anyhow::anyhow!(
+                "Min/max timeframe must be at least one minute"
+            ));
         }
+        if !(self.startup_grace.is_finite() && self.startup_grace >= 0.0) {
+            return Err(anyhow::anyhow!("Startup grace must not be negative"));
+        }
+        for strand_target in &self.strand_targets {
+            strand_target.validate()?;
+        }
+        Ok(())
     }
 }
 
-#[derive(Deserialize, Serialize)]
+fn validate_target(
+    target_diameter: f64,
+    lower_tolerance: f64,
+    higher_tolerance: f64,
+) -> Result<(), anyhow::Error> {
+    if target_diameter <= 0.0 {
+        return Err(anyhow::anyhow!("Target diameter must be positive"));
+    }
+    if lower_tolerance < 0.0 || higher_tolerance < 0.0 {
+        return Err(anyhow::anyhow!("Tolerances must not be negative"));
+    }
+    if lower_tolerance >= target_diameter {
+        return Err(anyhow::anyhow!(
+            "Lower tolerance must be smaller than the target diameter"
+        ));
+    }
+    Ok(())
+}
+
+#[derive(NamespaceEvents)]
+#[namespace_events(namespace = LaserMachineNamespace)]
+pub enum LaserEvents {
+    #[cache(duration_secs = 3600)]
+    LiveValues(Event<LiveValuesEvent>),
+    #[cache(one)]
+    Schema(Event<SchemaEvent>),
+    #[cache(first_and_last)]
+    State(Event<StateEvent>),
+    #[cache(first_and_last)]
+    MinMaxDiameter(Event<MinMaxDiameterEvent>),
+    #[cache(first_and_last)]
+    Disturbances(Event<DisturbancesEvent>),
+}
+
+#[derive(Debug)]
+pub struct LaserMachineNamespace {
+    pub namespace: Arc<Mutex<Namespace>>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
 /// All values in the Mutation enum should be positive.
+///
 /// This ensures that the parameters for setting tolerances and target diameter
 /// are valid and meaningful within the context of the LaserMachine's operation.
-enum Mutation {
-    SetTargetDiameter(f64),
-    SetLowerTolerance(f64),
-    SetHigherTolerance(f64),
-    SetMinMaxTimeframe(u64),
+/// Bare lengths are in mm, the timeframe in minutes and the startup grace in seconds.
+/// Target and tolerances apply to all strands, replacing targets set per strand.
+pub enum Mutation {
+    SetTargetDiameter(UnitValue),
+    SetLowerTolerance(UnitValue),
+    SetHigherTolerance(UnitValue),
+    SetMinMaxTimeframe(UnitValue),
+    /// Time the diameter must stay in tolerance after a start before alarms and min/max begin
+    SetStartupGrace(UnitValue),
+    /// Target and tolerances of a single strand
+    SetStrandTarget(StrandTargetSettings),
+    /// Learns the lens drift baseline again after the lens was cleaned
+    ResetLensDrift,
 }
 
-impl NamespaceCacheingLogic<LaserEvents> for LaserMachineNamespace {
-    #[instrument(skip_all)]
-    fn emit(&mut self, events: LaserEvents) {
-        let event = Arc::new(events.event_value());
-        let buffer_fn = events.event_cache_fn();
-
-        let mut namespace = self.namespace.lock_blocking();
-        namespace.emit(event, &buffer_fn);
-    }
+impl MachineApiTypes for LaserMachine {
+    type Mutation = Mutation;
+    type Events = LaserEvents;
 }
 
 impl MachineApi for LaserMachine {
     fn api_mutate(&mut self, request_body: Value) -> Result<(), anyhow::Error> {
-        let mutation: Mutation = serde_json::from_value(request_body)?;
+        let mutation = Self::parse_mutation(request_body)?;
         match mutation {
             Mutation::SetHigherTolerance(higher_tolerance) => {
-                self.set_higher_tolerance(higher_tolerance)
+                self.set_higher_tolerance(higher_tolerance.length("mm")?.get::<millimeter>())
             }
             Mutation::SetLowerTolerance(lower_tolerance) => {
-                self.set_lower_tolerance(lower_tolerance);
+                self.set_lower_tolerance(lower_tolerance.length("mm")?.get::<millimeter>());
             }
             Mutation::SetTargetDiameter(target_diameter) => {
-                self.set_target_diameter(target_diameter);
+                self.set_target_diameter(target_diameter.length("mm")?.get::<millimeter>());
+            }
+            Mutation::SetMinMaxTimeframe(timeframe) => {
+                let timeframe_minutes = timeframe.time("min")?.get::<minute>().round();
+                if timeframe_minutes < 1.0 {
+                    return Err(anyhow::anyhow!(
+                        "[{}::api_mutate] Min/max timeframe must be at least one minute",
+                        module_path!()
+                    ));
+                }
+                self.set_min_max_timeframe(timeframe_minutes as u64);
+            }
+            Mutation::SetStartupGrace(startup_grace) => {
+                let startup_grace = startup_grace.time("s")?.get::<second>();
+                if !(startup_grace.is_finite() && startup_grace >= 0.0) {
+                    return Err(anyhow::anyhow!(
+                        "[{}::api_mutate] Startup grace must not be negative",
+                        module_path!()
+                    ));
+                }
+                self.set_startup_grace(Duration::from_secs_f64(startup_grace));
             }
-            Mutation::SetMinMaxTimeframe(timeframe_minutes) => {
-                self.set_min_max_timeframe(timeframe_minutes);
+            Mutation::SetStrandTarget(settings) => {
+                settings.validate()?;
+                self.set_strand_target(&settings)?;
             }
+            Mutation::ResetLensDrift => self.reset_lens_drift(),
         }
         Ok(())
     }
@@ -149,4 +346,66 @@ impl MachineApi for LaserMachine {
     fn api_event_namespace(&mut self) -> Arc<Mutex<Namespace>> {
         self.namespace.namespace.clone()
     }
+
+    fn api_capabilities(&self) -> MachineCapabilities {
+        MachineCapabilities::default().with_laser_axes(self.axes)
+    }
+
+    fn api_alarms(&self) -> Vec<AlarmCondition> {
+        let mut alarms = Vec::new();
+        // the baseline survives stops, a dirty lens stays dirty until it is cleaned
+        if self.is_lens_dirty() {
+            alarms.push(AlarmCondition::new(
+                "lens_dirty",
+                "Diameter drifts down without a change of the extrusion, clean the lens",
+                AlarmSeverity::Warning,
+            ));
+        }
+        if !self.is_monitoring_tolerance() {
+            return alarms;
+        }
+        // a single strand is the diameter itself
+        if self.strand_count() > 1 {
+            alarms.extend(self.strands_out_of_tolerance().into_iter().map(|strand| {
+                AlarmCondition::new(
+                    &format!("strand_{}_out_of_tolerance", strand),
+                    format!("Diameter of strand {} is out of tolerance", strand + 1),
+                    AlarmSeverity::Warning,
+                )
+            }));
+        } else if self.is_in_tolerance() == Some(false) {
+            alarms.push(AlarmCondition::new(
+                "out_of_tolerance",
+                "Diameter is out of tolerance",
+                AlarmSeverity::Warning,
+            ));
+        }
+        alarms
+    }
+
+    fn api_export_config(&self) -> Result<Value, anyhow::Error> {
+        Ok(serde_json::to_value(self.export_config())?)
+    }
+
+    fn api_import_config(&mut self, config: Value) -> Result<(), anyhow::Error> {
+        let config: LaserConfig = serde_json::from_value(config)?;
+        config.validate()?;
+        self.import_config(&config)
+    }
+
+    fn api_apply_recipe(&mut self, section: Value) -> Result<(), anyhow::Error> {
+        let recipe: LaserRecipe = serde_json::from_value(section)?;
+        recipe.validate()?;
+        self.apply_recipe(&recipe);
+        Ok(())
+    }
+
+    fn api_run_self_test(&mut self) -> Result<(), anyhow::Error> {
+        self.run_self_test(Instant::now());
+        Ok(())
+    }
+
+    fn api_self_test_report(&self) -> Option<SelfTestReport> {
+        self.self_test_report()
+    }
 }
@@ -0,0 +1,236 @@
+use std::{
+    collections::VecDeque,
+    f64::consts::PI,
+    time::{Duration, Instant},
+};
+
+use uom::si::{
+    angular_velocity::revolution_per_second,
+    f64::{AngularVelocity, Frequency, Length, Velocity},
+    frequency::hertz,
+    length::millimeter,
+    velocity::millimeter_per_second,
+};
+
+/// Periodic component of the diameter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisturbancePeak {
+    pub frequency: Frequency,
+    /// Amplitude of the oscillation around the mean diameter
+    pub amplitude: Length,
+}
+
+impl DisturbancePeak {
+    /// Frequency relative to the screw rotation, 1 is once per screw revolution
+    pub fn screw_order(&self, screw_speed: AngularVelocity) -> Option<f64> {
+        let screw_frequency = screw_speed.get::<revolution_per_second>().abs();
+        (screw_frequency > 1e-3).then(|| self.frequency.get::<hertz>() / screw_frequency)
+    }
+
+    /// Filament length from one peak to the next, compare to the puller or roller circumferences
+    pub fn wavelength(&self, line_speed: Velocity) -> Option<Length> {
+        let line_speed = line_speed.get::<millimeter_per_second>().abs();
+        (line_speed > 0.0)
+            .then(|| Length::new::<millimeter>(line_speed / self.frequency.get::<hertz>()))
+    }
+}
+
+/// Finds periodic disturbances in the diameter signal
+///
+/// Samples the diameter at a fixed rate and runs a Goertzel filter for every DFT bin of the last
+/// [`DisturbanceAnalyzer::WINDOW`] samples. The strongest local maxima of the spectrum are the
+/// dominant disturbances, e.g. the screw rotation or an eccentric roller.
+#[derive(Debug)]
+pub struct DisturbanceAnalyzer {
+    samples: VecDeque<f64>,
+    next_sample: Option<Instant>,
+    next_analysis: Option<Instant>,
+}
+
+impl DisturbanceAnalyzer {
+    /// 20 Hz, disturbances up to 10 Hz are found
+    pub const SAMPLE_INTERVAL: Duration = Duration::from_millis(50);
+    /// 25.6 s of history, a resolution of about 0.04 Hz
+    pub const WINDOW: usize = 512;
+    pub const ANALYSIS_INTERVAL: Duration = Duration::from_secs(5);
+    /// Number of reported peaks
+    pub const PEAKS: usize = 3;
+    /// Smaller amplitudes in mm are below the resolution of the gauges
+    const MIN_AMPLITUDE: f64 = 0.0005;
+
+    pub const fn new() -> Self {
+        Self {
+            samples: VecDeque::new(),
+            next_sample: None,
+            next_analysis: None,
+        }
+    }
+
+    /// Samples the diameter in mm, call it every cycle
+    pub fn add_sample(&mut self, now: Instant, diameter: f64) {
+        let next_sample = *self.next_sample.get_or_insert(now);
+        if now < next_sample {
+            return;
+        }
+        // missed samples would distort the spectrum, start over after a gap
+        if now.duration_since(next_sample) > Self::SAMPLE_INTERVAL * 4 {
+            self.clear();
+            self.next_sample = Some(now + Self::SAMPLE_INTERVAL);
+        } else {
+            self.next_sample = Some(next_sample + Self::SAMPLE_INTERVAL);
+        }
+
+        self.samples.push_back(diameter);
+        if self.samples.len() > Self::WINDOW {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Drops the history, e.g. while there is no measurement
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.next_sample = None;
+    }
+
+    /// Strongest disturbances first
+    ///
+    /// `None` until the history is full and between analyses.
+    pub fn analyze(&mut self, now: Instant) -> Option<Vec<DisturbancePeak>> {
+        if self.samples.len() < Self::WINDOW
+            || self
+                .next_analysis
+                .is_some_and(|next_analysis| now < next_analysis)
+        {
+            return None;
+        }
+        self.next_analysis = Some(now + Self::ANALYSIS_INTERVAL);
+
+        let spectrum = self.spectrum();
+        let bin_width = 1.0 / (Self::SAMPLE_INTERVAL.as_secs_f64() * Self::WINDOW as f64);
+        let mut peaks: Vec<_> = (1..spectrum.len() - 1)
+            .filter(|&bin| {
+                spectrum[bin] > spectrum[bin - 1]
+                    && spectrum[bin] >= spectrum[bin + 1]
+                    && spectrum[bin] >= Self::MIN_AMPLITUDE
+            })
+            .map(|bin| DisturbancePeak {
+                frequency: Frequency::new::<hertz>(bin as f64 * bin_width),
+                amplitude: Length::new::<millimeter>(spectrum[bin]),
+            })
+            .collect();
+        peaks.sort_by(|a, b| b.amplitude.value.total_cmp(&a.amplitude.value));
+        peaks.truncate(Self::PEAKS);
+        Some(peaks)
+    }
+
+    /// Amplitude of every DFT bin below the Nyquist frequency in mm
+    fn spectrum(&self) -> Vec<f64> {
+        let count = self.samples.len();
+        let mean = self.samples.iter().sum::<f64>() / count as f64;
+        // Hann window against leakage of strong peaks into their neighbours
+        let windowed: Vec<f64> = self
+            .samples
+            .iter()
+            .enumerate()
+            .map(|(i, sample)| {
+                let window = 0.5f64.mul_add(-(2.0 * PI * i as f64 / count as f64).cos(), 0.5);
+                (sample - mean) * window
+            })
+            .collect();
+        // coherent gain of the Hann window
+        let gain = count as f64 / 2.0;
+
+        (0..count / 2)
+            .map(|bin| {
+                let coefficient = 2.0 * (2.0 * PI * bin as f64 / count as f64).cos();
+                let (s1, s2) = windowed.iter().fold((0.0, 0.0), |(s1, s2), sample| {
+                    (coefficient.mul_add(s1, sample - s2), s1)
+                });
+                let power = (-coefficient * s1).mul_add(s2, s1.mul_add(s1, s2 * s2));
+                2.0 * power.max(0.0).sqrt() / gain
+            })
+            .collect()
+    }
+}
+
+impl Default for DisturbanceAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use uom::si::angular_velocity::revolution_per_minute;
+
+    /// Frequency of a DFT bin
+    fn bin(bin: usize) -> f64 {
+        bin as f64 / (DisturbanceAnalyzer::SAMPLE_INTERVAL.as_secs_f64() * 512.0)
+    }
+
+    fn fill(analyzer: &mut DisturbanceAnalyzer, start: Instant, signal: impl Fn(f64) -> f64) {
+        for i in 0..DisturbanceAnalyzer::WINDOW {
+            let t = DisturbanceAnalyzer::SAMPLE_INTERVAL * i as u32;
+            analyzer.add_sample(start + t, signal(t.as_secs_f64()));
+        }
+    }
+
+    #[test]
+    fn test_finds_disturbances() {
+        let mut analyzer = DisturbanceAnalyzer::new();
+        let start = Instant::now();
+        let screw = bin(13);
+        let roller = bin(51);
+        fill(&mut analyzer, start, |t| {
+            let screw = 0.02 * (2.0 * PI * screw * t).sin();
+            let roller = 0.01 * (2.0 * PI * roller * t).cos();
+            1.75 + screw + roller
+        });
+
+        let now = start + Duration::from_secs(30);
+        let peaks = analyzer.analyze(now).unwrap();
+        assert_relative_eq!(peaks[0].frequency.get::<hertz>(), screw, epsilon = 1e-9);
+        assert_relative_eq!(peaks[0].amplitude.get::<millimeter>(), 0.02, epsilon = 1e-6);
+        assert_relative_eq!(peaks[1].frequency.get::<hertz>(), roller, epsilon = 1e-9);
+        assert_relative_eq!(peaks[1].amplitude.get::<millimeter>(), 0.01, epsilon = 1e-6);
+
+        // rate limited
+        assert!(analyzer.analyze(now + Duration::from_secs(1)).is_none());
+    }
+
+    #[test]
+    fn test_needs_full_history() {
+        let mut analyzer = DisturbanceAnalyzer::new();
+        let start = Instant::now();
+        fill(&mut analyzer, start, |_| 1.75);
+        assert_eq!(analyzer.analyze(start), Some(Vec::new()));
+
+        // a gap starts over
+        let later = start + Duration::from_secs(60);
+        analyzer.add_sample(later, 1.75);
+        assert!(analyzer.analyze(later + Duration::from_secs(10)).is_none());
+    }
+
+    #[test]
+    fn test_correlation() {
+        let peak = DisturbancePeak {
+            frequency: Frequency::new::<hertz>(1.0),
+            amplitude: Length::new::<millimeter>(0.01),
+        };
+        let order = peak.screw_order(AngularVelocity::new::<revolution_per_minute>(30.0));
+        assert_relative_eq!(order.unwrap(), 2.0, epsilon = 1e-9);
+        assert!(
+            peak.screw_order(AngularVelocity::new::<revolution_per_minute>(0.0))
+                .is_none()
+        );
+
+        let wavelength = peak.wavelength(Velocity::new::<millimeter_per_second>(250.0));
+        assert_relative_eq!(
+            wavelength.unwrap().get::<millimeter>(),
+            250.0,
+            epsilon = 1e-9
+        );
+    }
+}
@@ -0,0 +1,277 @@
+use control_core::uom_extensions::velocity::meter_per_minute;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+use uom::si::{
+    angular_velocity::revolution_per_minute,
+    f64::{AngularVelocity, Length, Velocity},
+    length::millimeter,
+};
+
+/// Directory inside [`crate::storage::data_dir`] with the lens drift baseline of every laser
+pub const LENS_DRIFT_DIR: &str = "lens_drift";
+
+/// Learned state of a [`LensDriftDetector`], journaled so the baseline survives restarts
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct LensDriftSnapshot {
+    pub baseline: Option<f64>,
+    pub windows: Vec<f64>,
+}
+
+/// Detects a dirty lens from a slow downward drift of the measured diameter
+///
+/// The extruder pushes a fixed volume per screw revolution which the puller draws to the
+/// diameter, so `diameter² × line speed / screw speed` stays constant while the process runs
+/// steadily, whatever the speeds are. Dirt on the lens makes the gauge read smaller and lowers
+/// this flow constant over hours, also when the winder regulates the measured diameter back to
+/// its target. The mean of the first [`Self::BASELINE_WINDOWS`] minutes is the baseline, the last
+/// [`Self::RECENT_WINDOWS`] minutes are compared to it. A jump from one minute to the next is a
+/// process change like a new material and starts a new baseline.
+#[derive(Debug)]
+pub struct LensDriftDetector {
+    /// mean flow constant of a clean lens, `None` while it is learned
+    baseline: Option<f64>,
+    /// mean flow constant of the latest minutes, oldest first
+    windows: VecDeque<f64>,
+    window_start: Option<Instant>,
+    window_sum: f64,
+    window_samples: u32,
+    next_sample: Option<Instant>,
+}
+
+impl LensDriftDetector {
+    pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+    pub const WINDOW: Duration = Duration::from_secs(60);
+    /// Minutes averaged into the baseline
+    pub const BASELINE_WINDOWS: usize = 30;
+    /// Minutes averaged into the value compared to the baseline
+    pub const RECENT_WINDOWS: usize = 10;
+    /// Minutes the trend is fitted over, 8 h
+    pub const TREND_WINDOWS: usize = 480;
+    /// Contamination raising the maintenance alarm
+    pub const ALARM_CONTAMINATION: f64 = 0.5;
+    /// Minutes with fewer samples are dropped, the line didn't run through them
+    const MIN_WINDOW_SAMPLES: u32 = 45;
+    /// Relative change of the flow constant between two minutes that counts as a process change
+    const STEP_CHANGE: f64 = 0.02;
+    /// Share of the diameter the gauge reads too small at a contamination of 1
+    const FULL_CONTAMINATION: f64 = 0.05;
+    /// Below this screw speed in rpm or line speed in m/min the line counts as standing
+    const MIN_SPEED: f64 = 0.1;
+
+    pub fn from_snapshot(snapshot: LensDriftSnapshot) -> Self {
+        Self {
+            baseline: snapshot.baseline,
+            windows: snapshot.windows.into(),
+            window_start: None,
+            window_sum: 0.0,
+            window_samples: 0,
+            next_sample: None,
+        }
+    }
+
+    pub fn snapshot(&self) -> LensDriftSnapshot {
+        LensDriftSnapshot {
+            baseline: self.baseline,
+            windows: self.windows.iter().copied().collect(),
+        }
+    }
+
+    /// Samples the flow constant, call it every cycle while the line produces in tolerance
+    ///
+    /// Returns whether a minute was completed.
+    pub fn add_sample(
+        &mut self,
+        now: Instant,
+        diameter: Length,
+        screw_speed: AngularVelocity,
+        line_speed: Velocity,
+    ) -> bool {
+        if self
+            .next_sample
+            .is_some_and(|next_sample| now < next_sample)
+        {
+            return false;
+        }
+        self.next_sample = Some(now + Self::SAMPLE_INTERVAL);
+
+        let diameter = diameter.get::<millimeter>();
+        let screw_speed = screw_speed.get::<revolution_per_minute>().abs();
+        let line_speed = line_speed.get::<meter_per_minute>().abs();
+        if diameter <= 0.0 || screw_speed < Self::MIN_SPEED || line_speed < Self::MIN_SPEED {
+            self.interrupt();
+            return false;
+        }
+
+        let window_start = *self.window_start.get_or_insert(now);
+        self.window_sum += diameter * diameter * line_speed / screw_speed;
+        self.window_samples += 1;
+        if now.duration_since(window_start) < Self::WINDOW {
+            return false;
+        }
+
+        let completed = self.window_samples >= Self::MIN_WINDOW_SAMPLES;
+        if completed {
+            self.push_window(self.window_sum / self.window_samples as f64);
+        }
+        self.interrupt();
+        completed
+    }
+
+    /// Drops the current minute, e.g. while the line stands or starts up
+    pub const fn interrupt(&mut self) {
+        self.window_start = None;
+        self.window_sum = 0.0;
+        self.window_samples = 0;
+    }
+
+    /// Learns a new baseline, e.g. after the lens was cleaned
+    pub fn reset(&mut self) {
+        self.baseline = None;
+        self.windows.clear();
+        self.interrupt();
+    }
+
+    /// Estimated contamination of the lens, 0 for a clean lens and 1 once the gauge reads
+    /// [`Self::FULL_CONTAMINATION`] too small
+    ///
+    /// `None` while the baseline is learned.
+    pub fn contamination(&self) -> Option<f64> {
+        let baseline = self.baseline?;
+        if self.windows.len() < Self::RECENT_WINDOWS {
+            return None;
+        }
+        let recent = self
+            .windows
+            .iter()
+            .rev()
+            .take(Self::RECENT_WINDOWS)
+            .sum::<f64>()
+            / Self::RECENT_WINDOWS as f64;
+        let apparent_loss = 1.0 - (recent / baseline).sqrt();
+        Some((apparent_loss / Self::FULL_CONTAMINATION).clamp(0.0, 1.0))
+    }
+
+    /// Whether the diameter drifts down far enough to clean the lens
+    pub fn is_dirty(&self) -> bool {
+        self.contamination()
+            .is_some_and(|contamination| contamination >= Self::ALARM_CONTAMINATION)
+            && self.trend().is_some_and(|trend| trend < 0.0)
+    }
+
+    fn push_window(&mut self, flow_constant: f64) {
+        let step = self
+            .windows
+            .back()
+            .is_some_and(|last| (flow_constant / last - 1.0).abs() > Self::STEP_CHANGE);
+        if step {
+            self.baseline = None;
+            self.windows.clear();
+        }
+
+        self.windows.push_back(flow_constant);
+        if self.windows.len() > Self::TREND_WINDOWS {
+            self.windows.pop_front();
+        }
+        if self.baseline.is_none() && self.windows.len() >= Self::BASELINE_WINDOWS {
+            self.baseline = Some(self.windows.iter().sum::<f64>() / self.windows.len() as f64);
+        }
+    }
+
+    /// Least squares slope of the flow constant per minute
+    fn trend(&self) -> Option<f64> {
+        let n = self.windows.len() as f64;
+        if n < 2.0 {
+            return None;
+        }
+        let mean_x = (n - 1.0) / 2.0;
+        let mean_y = self.windows.iter().sum::<f64>() / n;
+        let (covariance, variance) =
+            self.windows
+                .iter()
+                .enumerate()
+                .fold((0.0, 0.0), |(covariance, variance), (x, y)| {
+                    let dx = x as f64 - mean_x;
+                    (dx.mul_add(y - mean_y, covariance), dx.mul_add(dx, variance))
+                });
+        Some(covariance / variance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Samples the minute after `start` every second, returns the start of the next minute
+    fn run_minute(
+        detector: &mut LensDriftDetector,
+        start: Instant,
+        diameter: f64,
+        screw_speed: f64,
+        line_speed: f64,
+    ) -> Instant {
+        for second in 0..=60 {
+            detector.add_sample(
+                start + Duration::from_secs(second),
+                Length::new::<millimeter>(diameter),
+                AngularVelocity::new::<revolution_per_minute>(screw_speed),
+                Velocity::new::<meter_per_minute>(line_speed),
+            );
+        }
+        start + Duration::from_secs(61)
+    }
+
+    #[test]
+    fn test_gradual_drift() {
+        let mut detector = LensDriftDetector::from_snapshot(LensDriftSnapshot::default());
+        let mut now = Instant::now();
+        for _ in 0..LensDriftDetector::BASELINE_WINDOWS {
+            now = run_minute(&mut detector, now, 1.75, 60.0, 20.0);
+        }
+        assert!(detector.contamination().unwrap() < 1e-9);
+
+        // the extruder runs faster, the puller follows, the flow constant stays
+        for _ in 0..60 {
+            now = run_minute(&mut detector, now, 1.75, 90.0, 30.0);
+        }
+        assert!(detector.contamination().unwrap() < 1e-9);
+        assert!(!detector.is_dirty());
+
+        // the gauge reads 0.0005 mm less every minute
+        for minute in 1..=200 {
+            now = run_minute(
+                &mut detector,
+                now,
+                0.0005f64.mul_add(-minute as f64, 1.75),
+                90.0,
+                30.0,
+            );
+        }
+        assert!(detector.contamination().unwrap() > 0.99);
+        assert!(detector.is_dirty());
+
+        // the baseline is kept across restarts
+        let restored = LensDriftDetector::from_snapshot(detector.snapshot());
+        assert!(restored.is_dirty());
+
+        detector.reset();
+        assert_eq!(detector.contamination(), None);
+    }
+
+    #[test]
+    fn test_step_is_a_process_change() {
+        let mut detector = LensDriftDetector::from_snapshot(LensDriftSnapshot::default());
+        let mut now = Instant::now();
+        for _ in 0..40 {
+            now = run_minute(&mut detector, now, 1.75, 60.0, 20.0);
+        }
+        assert!(detector.contamination().is_some());
+
+        // another material draws to a smaller diameter at the same speeds
+        run_minute(&mut detector, now, 1.70, 60.0, 20.0);
+        assert_eq!(detector.contamination(), None);
+        assert_eq!(detector.snapshot().windows.len(), 1);
+    }
+}
@@ -1,27 +1,51 @@
 use crate::{
+    journal::Journal,
     machines::{MACHINE_LASER_V1, VENDOR_QITECH},
-    serial::devices::laser::Laser,
+    serial::devices::laser::LaserData,
 };
 use api::{
-    LaserEvents, LaserMachineNamespace, LaserState, LiveValuesEvent, MinMaxDiameterEvent,
-    StateEvent,
+    DisturbancePeakValues, DisturbancesEvent, LaserConfig, LaserEvents, LaserMachineNamespace,
+    LaserRecipe, LaserState, LiveValuesEvent, MinMaxDiameterEvent, StateEvent,
+    StrandTargetSettings,
 };
 use control_core::{
-    machines::identification::{MachineIdentification, MachineIdentificationUnique},
-    socketio::namespace::NamespaceCacheingLogic,
+    helpers::clock::{Clock, SystemClock},
+    machines::{
+        identification::{MachineIdentification, MachineIdentificationUnique},
+        self_test::{SelfTestReport, SelfTestResult},
+        values::{
+            DIAMETER, DIAMETER_TARGET, IN_TOLERANCE, LINE_SPEED, MachineValueBus, SCREW_SPEED,
+        },
+    },
+    socketio::{event::BuildEvent, namespace::NamespaceCacheingLogic},
+    uom_extensions::velocity::meter_per_minute,
 };
 use control_core_derive::Machine;
-use smol::lock::RwLock;
+use disturbance::DisturbanceAnalyzer;
+use lens_drift::{LensDriftDetector, LensDriftSnapshot};
 use std::{
     collections::VecDeque,
     sync::Arc,
     time::{Duration, Instant},
 };
-use uom::si::{f64::Length, length::millimeter};
+use tokio::sync::watch;
+use tolerance_monitor::ToleranceMonitor;
+use uom::{
+    ConstZero,
+    si::{
+        angular_velocity::revolution_per_second,
+        f64::{AngularVelocity, Length, Velocity},
+        frequency::hertz,
+        length::millimeter,
+    },
+};
 
 pub mod act;
 pub mod api;
+pub mod disturbance;
+pub mod lens_drift;
 pub mod new;
+pub mod tolerance_monitor;
 
 #[derive(Debug, Clone)]
 pub struct DiameterMeasurement {
@@ -33,25 +57,78 @@ pub struct DiameterMeasurement {
 pub struct DiameterTracker {
     measurements: VecDeque<DiameterMeasurement>,
     timeframe_duration: Duration,
+    clock: Arc<dyn Clock>,
 }
 
 impl DiameterTracker {
     pub fn new(timeframe_minutes: u64) -> Self {
+        Self::with_clock(timeframe_minutes, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(timeframe_minutes: u64, clock: Arc<dyn Clock>) -> Self {
         Self {
             measurements: VecDeque::new(),
             timeframe_duration: Duration::from_secs(timeframe_minutes * 60),
+            clock,
         }
     }
 
-    pub fn add_measurement(&mut self, diameter: f64, timestamp: Instant) {
-        // Add the new measurement
+    /// Records a diameter at the current time of the clock
+    pub fn add_measurement(&mut self, diameter: f64) {
         self.measurements.push_back(DiameterMeasurement {
             diameter,
-            timestamp,
+            timestamp: self.clock.now(),
         });
+        self.remove_expired();
+    }
+
+    /// Min and max of the measurements within the timeframe, expired ones are ignored
+    /// even if no new measurement arrived since
+    pub fn get_min_max(&self) -> (Option<f64>, Option<f64>) {
+        let cutoff = self.cutoff();
+        let mut min: Option<f64> = None;
+        let mut max: Option<f64> = None;
+
+        for measurement in &self.measurements {
+            if cutoff.is_some_and(|cutoff| measurement.timestamp < cutoff) {
+                continue;
+            }
+            min = Some(min.map_or(measurement.diameter, |min| min.min(measurement.diameter)));
+            max = Some(max.map_or(measurement.diameter, |max| max.max(measurement.diameter)));
+        }
+
+        (min, max)
+    }
+
+    /// Mean of the measurements within the timeframe
+    pub fn get_mean(&self) -> Option<f64> {
+        let cutoff = self.cutoff();
+        let (sum, count) = self
+            .measurements
+            .iter()
+            .filter(|measurement| cutoff.is_none_or(|cutoff| measurement.timestamp >= cutoff))
+            .fold((0.0, 0usize), |(sum, count), measurement| {
+                (sum + measurement.diameter, count + 1)
+            });
+        (count > 0).then(|| sum / count as f64)
+    }
+
+    pub fn set_timeframe(&mut self, timeframe_minutes: u64) {
+        self.timeframe_duration = Duration::from_secs(timeframe_minutes * 60);
+
+        // Clean up measurements that are now outside the new timeframe
+        self.remove_expired();
+    }
+
+    /// `None` while the clock is closer to its origin than the timeframe
+    fn cutoff(&self) -> Option<Instant> {
+        self.clock.now().checked_sub(self.timeframe_duration)
+    }
 
-        // Remove old measurements outside the timeframe
-        let cutoff = timestamp - self.timeframe_duration;
+    fn remove_expired(&mut self) {
+        let Some(cutoff) = self.cutoff() else {
+            return;
+        };
         while let Some(front) = self.measurements.front() {
             if front.timestamp < cutoff {
                 self.measurements.pop_front();
@@ -60,41 +137,39 @@ impl DiameterTracker {
             }
         }
     }
+}
 
-    pub fn get_min_max(&self) -> (Option<f64>, Option<f64>) {
-        if self.measurements.is_empty() {
-            return (None, None);
-        }
+/// Roundness over the same timeframe as the diameter, for ovality QA
+///
+/// Only the worst and the mean roundness are of interest, a perfectly round filament is 1.
+#[derive(Debug)]
+pub struct RoundnessTracker {
+    tracker: DiameterTracker,
+}
 
-        let mut min = f64::INFINITY;
-        let mut max = f64::NEG_INFINITY;
+impl RoundnessTracker {
+    pub fn new(timeframe_minutes: u64) -> Self {
+        Self::with_clock(timeframe_minutes, Arc::new(SystemClock))
+    }
 
-        for measurement in &self.measurements {
-            if measurement.diameter < min {
-                min = measurement.diameter;
-            }
-            if measurement.diameter > max {
-                max = measurement.diameter;
-            }
+    pub fn with_clock(timeframe_minutes: u64, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            tracker: DiameterTracker::with_clock(timeframe_minutes, clock),
         }
+    }
 
-        (Some(min), Some(max))
+    pub fn add_measurement(&mut self, roundness: f64) {
+        self.tracker.add_measurement(roundness);
     }
 
-    pub fn set_timeframe(&mut self, timeframe_minutes: u64) {
-        self.timeframe_duration = Duration::from_secs(timeframe_minutes * 60);
+    /// Min and mean roundness within the timeframe
+    pub fn get_min_avg(&self) -> (Option<f64>, Option<f64>) {
+        let (min, _) = self.tracker.get_min_max();
+        (min, self.tracker.get_mean())
+    }
 
-        // Clean up measurements that are now outside the new timeframe
-        if let Some(latest) = self.measurements.back() {
-            let cutoff = latest.timestamp - self.timeframe_duration;
-            while let Some(front) = self.measurements.front() {
-                if front.timestamp < cutoff {
-                    self.measurements.pop_front();
-                } else {
-                    break;
-                }
-            }
-        }
+    pub fn set_timeframe(&mut self, timeframe_minutes: u64) {
+        self.tracker.set_timeframe(timeframe_minutes);
     }
 }
 
@@ -103,24 +178,43 @@ pub struct LaserMachine {
     machine_identification_unique: MachineIdentificationUnique,
 
     // drivers
-    laser: Arc<RwLock<Laser>>,
+    /// Latest measurement published by the laser device thread
+    laser_data: watch::Receiver<Option<LaserData>>,
+    /// Number of axes the gauge measures
+    axes: u8,
+    /// Publishes the diameter for other machines
+    values: Arc<MachineValueBus>,
 
     // socketio
     namespace: LaserMachineNamespace,
-    last_measurement_emit: Instant,
-    last_minmax_emit: Instant,
 
     // laser values
     diameter: Length,
     x_diameter: Option<Length>,
     y_diameter: Option<Length>,
     roundness: Option<f64>,
+    /// diameter of every strand, zero for strands without a measurement
+    strand_diameters: Vec<Length>,
 
     // diameter tracking for min/max over timeframe
     diameter_tracker: DiameterTracker,
+    roundness_tracker: RoundnessTracker,
+    /// periodic disturbances of the diameter
+    disturbance_analyzer: DisturbanceAnalyzer,
+    /// holds back tolerance alarms and min/max tracking while the line starts up
+    tolerance_monitor: ToleranceMonitor,
+    /// slow downward drift of the diameter from a dirty lens
+    lens_drift: LensDriftDetector,
+    lens_drift_journal: Journal<LensDriftSnapshot>,
+    /// Report of the last self test
+    self_test: Option<SelfTestReport>,
 
     //laser target configuration
     laser_target: LaserTarget,
+    /// target of every strand, the length is the strand count of the line
+    strand_targets: Vec<StrandTarget>,
+    /// when the target was last set, targets a winder published before are not adopted
+    target_set_at: Instant,
 
     /// Will be initialized as false and set to true by emit_state
     /// This way we can signal to the client that the first state emission is a default state
@@ -133,6 +227,11 @@ impl LaserMachine {
         machine: MACHINE_LASER_V1,
     };
 
+    /// Older screw and line speeds don't belong to the analyzed history anymore
+    const CORRELATION_MAX_AGE: Duration = Duration::from_secs(1);
+    /// Gauges measure many times a second, an older measurement fails the self test
+    const SELF_TEST_MAX_AGE: Duration = Duration::from_secs(1);
+
     ///diameter in mm
     pub fn emit_live_values(&mut self) {
         let diameter = self.diameter.get::<millimeter>();
@@ -145,45 +244,198 @@ impl LaserMachine {
             x_diameter,
             y_diameter,
             roundness,
+            strand_diameters: self
+                .strand_diameters
+                .iter()
+                .map(|diameter| diameter.get::<millimeter>())
+                .collect(),
+            lens_contamination: self.lens_drift.contamination(),
+            units: LiveValuesEvent::UNITS,
         };
+        // an adaptive emit rate follows the diameter
+        self.namespace
+            .namespace
+            .lock_blocking()
+            .track_value("LiveValuesEvent", diameter);
         self.namespace
             .emit(LaserEvents::LiveValues(live_values.build()));
     }
 
     pub fn emit_min_max_diameter(&mut self) {
+        // scanning the tracked measurements is expensive, skip it while the event is rate limited
+        if !self
+            .namespace
+            .namespace
+            .lock_blocking()
+            .is_due("MinMaxDiameterEvent")
+        {
+            return;
+        }
         let (min_diameter, max_diameter) = self.get_min_max_diameter();
+        let (min_roundness, avg_roundness) = self.roundness_tracker.get_min_avg();
         let min_max_event = MinMaxDiameterEvent {
             min_diameter,
             max_diameter,
+            min_roundness,
+            avg_roundness,
             timeframe_minutes: self.laser_target.min_max_timeframe_minutes,
+            units: MinMaxDiameterEvent::UNITS,
         };
         self.namespace
             .emit(LaserEvents::MinMaxDiameter(min_max_event.build()));
     }
 
-    pub fn build_state_event(&self) -> StateEvent {
-        let laser = LaserState {
+    /// Analyzes the diameter for periodic disturbances and emits them with the screw speed and
+    /// line speed of the line to tell their mechanical cause
+    pub fn emit_disturbances(&mut self, now: Instant) {
+        match self.diameter > Length::ZERO {
+            true => self
+                .disturbance_analyzer
+                .add_sample(now, self.diameter.get::<millimeter>()),
+            false => self.disturbance_analyzer.clear(),
+        }
+        let Some(peaks) = self.disturbance_analyzer.analyze(now) else {
+            return;
+        };
+
+        let (screw_speed, line_speed) = self.line_speeds(now);
+        let event = DisturbancesEvent {
+            peaks: peaks
+                .iter()
+                .map(|peak| DisturbancePeakValues {
+                    frequency: peak.frequency.get::<hertz>(),
+                    amplitude: peak.amplitude.get::<millimeter>(),
+                    screw_order: screw_speed.and_then(|speed| peak.screw_order(speed)),
+                    wavelength: line_speed
+                        .and_then(|speed| peak.wavelength(speed))
+                        .map(|wavelength| wavelength.get::<millimeter>()),
+                })
+                .collect(),
+            screw_frequency: screw_speed.map(|speed| speed.get::<revolution_per_second>().abs()),
+            line_speed: line_speed.map(|speed| speed.get::<meter_per_minute>()),
+            units: DisturbancesEvent::UNITS,
+        };
+        self.namespace
+            .emit(LaserEvents::Disturbances(event.build()));
+    }
+
+    /// Feeds the lens drift detection while the diameter is monitored, see [`LensDriftDetector`]
+    pub fn detect_lens_drift(&mut self, now: Instant) {
+        let (screw_speed, line_speed) = self.line_speeds(now);
+        match (self.is_monitoring_tolerance(), screw_speed, line_speed) {
+            (true, Some(screw_speed), Some(line_speed)) => {
+                if self
+                    .lens_drift
+                    .add_sample(now, self.diameter, screw_speed, line_speed)
+                {
+                    self.lens_drift_journal
+                        .record(now, self.lens_drift.snapshot());
+                }
+            }
+            _ => self.lens_drift.interrupt(),
+        }
+    }
+
+    /// Learns the lens drift baseline again, after the lens was cleaned
+    pub fn reset_lens_drift(&mut self) {
+        self.lens_drift.reset();
+        self.lens_drift_journal
+            .record(Instant::now(), self.lens_drift.snapshot());
+    }
+
+    pub fn is_lens_dirty(&self) -> bool {
+        self.lens_drift.is_dirty()
+    }
+
+    /// Checks that the device thread still talks to the gauge and gets fresh measurements
+    pub fn run_self_test(&mut self, now: Instant) {
+        let mut report = SelfTestReport::new();
+        match self.laser_data.has_changed() {
+            Ok(_) => report.add("gauge", SelfTestResult::Passed, "device thread running"),
+            Err(_) => report.add(
+                "gauge",
+                SelfTestResult::Failed,
+                "device thread stopped, reconnect the gauge",
+            ),
+        }
+        let measurement = self.laser_data.borrow().clone();
+        match measurement {
+            Some(data) => {
+                let age = now.saturating_duration_since(data.last_timestamp);
+                let detail = format!(
+                    "{:.3} mm measured {} ms ago",
+                    data.diameter.get::<millimeter>(),
+                    age.as_millis()
+                );
+                match age <= Self::SELF_TEST_MAX_AGE {
+                    true => report.add("measurement", SelfTestResult::Passed, detail),
+                    false => report.add("measurement", SelfTestResult::Failed, detail),
+                }
+            }
+            None => report.add(
+                "measurement",
+                SelfTestResult::Failed,
+                "gauge hasn't sent a measurement",
+            ),
+        }
+        report.finish();
+        self.self_test = Some(report);
+    }
+
+    pub fn self_test_report(&self) -> Option<SelfTestReport> {
+        self.self_test.clone()
+    }
+
+    /// Screw speed of the extruder and line speed of the winder, `None` if they are stale
+    fn line_speeds(&self, now: Instant) -> (Option<AngularVelocity>, Option<Velocity>) {
+        let screw_speed = self
+            .values
+            .latest(SCREW_SPEED)
+            .filter(|sample| !sample.is_stale(now, Self::CORRELATION_MAX_AGE))
+            .map(|sample| sample.value);
+        let line_speed = self
+            .values
+            .latest(LINE_SPEED)
+            .filter(|sample| !sample.is_stale(now, Self::CORRELATION_MAX_AGE))
+            .map(|sample| sample.value);
+        (screw_speed, line_speed)
+    }
+
+    fn laser_state(&self) -> LaserState {
+        LaserState {
             higher_tolerance: self.laser_target.higher_tolerance.get::<millimeter>(),
             lower_tolerance: self.laser_target.lower_tolerance.get::<millimeter>(),
             target_diameter: self.laser_target.diameter.get::<millimeter>(),
             min_max_timeframe_minutes: self.laser_target.min_max_timeframe_minutes,
-        };
+            startup_grace: self.tolerance_monitor.get_startup_grace().as_secs_f64(),
+            monitoring_phase: self.tolerance_monitor.get_phase(),
+            strand_targets: self
+                .strand_targets
+                .iter()
+                .enumerate()
+                .map(|(strand, target)| target.to_settings(strand))
+                .collect(),
+        }
+    }
 
+    pub fn build_state_event(&self) -> StateEvent {
         StateEvent {
             is_default_state: false,
-            laser_state: laser,
+            laser_state: self.laser_state(),
+            units: StateEvent::UNITS,
         }
     }
 
+    pub fn emit_schema(&mut self) {
+        self.namespace
+            .emit(LaserEvents::Schema(LiveValuesEvent::schema().build()));
+    }
+
     pub fn emit_state(&mut self) {
         let state = StateEvent {
             is_default_state: !std::mem::replace(&mut self.emitted_default_state, true),
-            laser_state: LaserState {
-                higher_tolerance: self.laser_target.higher_tolerance.get::<millimeter>(),
-                lower_tolerance: self.laser_target.lower_tolerance.get::<millimeter>(),
-                target_diameter: self.laser_target.diameter.get::<millimeter>(),
-                min_max_timeframe_minutes: self.laser_target.min_max_timeframe_minutes,
-            },
+            laser_state: self.laser_state(),
+            units: StateEvent::UNITS,
         };
 
         self.namespace.emit(LaserEvents::State(state.build()));
@@ -191,25 +443,198 @@ impl LaserMachine {
 
     pub fn set_higher_tolerance(&mut self, higher_tolerance: f64) {
         self.laser_target.higher_tolerance = Length::new::<millimeter>(higher_tolerance);
+        self.reset_strand_targets();
         self.emit_state();
     }
 
     pub fn set_lower_tolerance(&mut self, lower_tolerance: f64) {
         self.laser_target.lower_tolerance = Length::new::<millimeter>(lower_tolerance);
+        self.reset_strand_targets();
         self.emit_state();
     }
 
     pub fn set_target_diameter(&mut self, target_diameter: f64) {
         self.laser_target.diameter = Length::new::<millimeter>(target_diameter);
+        self.reset_strand_targets();
+        self.emit_state();
+    }
+
+    /// Overrides the target of one strand until the common target changes
+    pub fn set_strand_target(
+        &mut self,
+        settings: &StrandTargetSettings,
+    ) -> Result<(), anyhow::Error> {
+        let strand_count = self.strand_count();
+        let Some(target) = self.strand_targets.get_mut(settings.strand) else {
+            return Err(anyhow::anyhow!(
+                "[{}::LaserMachine::set_strand_target] Strand {} doesn't exist, the line has {} strands",
+                module_path!(),
+                settings.strand,
+                strand_count
+            ));
+        };
+        *target = StrandTarget::from_settings(settings);
         self.emit_state();
+        Ok(())
+    }
+
+    /// Gives every strand the common target
+    fn reset_strand_targets(&mut self) {
+        let target = self.laser_target.strand_target();
+        self.strand_targets.fill(target);
+    }
+
+    pub fn strand_count(&self) -> usize {
+        self.strand_targets.len()
+    }
+
+    /// Time the diameter must stay in tolerance before monitoring starts, applies to the next start
+    pub fn set_startup_grace(&mut self, startup_grace: Duration) {
+        self.tolerance_monitor.set_startup_grace(startup_grace);
+        self.emit_state();
+    }
+
+    /// Alarms and min/max tracking wait for the startup grace, see [`ToleranceMonitor`]
+    pub fn monitor_tolerance(&mut self, now: Instant) {
+        let in_tolerance = self
+            .is_in_tolerance()
+            .map(|in_tolerance| in_tolerance && self.strands_out_of_tolerance().is_empty());
+        if self.tolerance_monitor.update(now, in_tolerance) {
+            self.emit_state();
+        }
+
+        if self.tolerance_monitor.is_monitoring() {
+            self.diameter_tracker
+                .add_measurement(self.diameter.get::<millimeter>());
+            // a roundness of 0 means there is no filament in the gauge
+            if let Some(roundness) = self.roundness.filter(|roundness| *roundness > 0.0) {
+                self.roundness_tracker.add_measurement(roundness);
+            }
+            if let Some(in_tolerance) = in_tolerance {
+                self.values.publish(
+                    &self.machine_identification_unique,
+                    IN_TOLERANCE,
+                    in_tolerance,
+                    now,
+                );
+            }
+        }
+    }
+
+    pub const fn is_monitoring_tolerance(&self) -> bool {
+        self.tolerance_monitor.is_monitoring()
     }
 
     pub fn set_min_max_timeframe(&mut self, timeframe_minutes: u64) {
         self.laser_target.min_max_timeframe_minutes = timeframe_minutes;
         self.diameter_tracker.set_timeframe(timeframe_minutes);
+        self.roundness_tracker.set_timeframe(timeframe_minutes);
+        self.emit_state();
+    }
+
+    /// Applies target and tolerances of a recipe with a single state emission
+    pub fn apply_recipe(&mut self, recipe: &LaserRecipe) {
+        self.target_set_at = Instant::now();
+        self.laser_target.diameter = Length::new::<millimeter>(recipe.target_diameter);
+        self.laser_target.lower_tolerance = Length::new::<millimeter>(recipe.lower_tolerance);
+        self.laser_target.higher_tolerance = Length::new::<millimeter>(recipe.higher_tolerance);
+        self.reset_strand_targets();
         self.emit_state();
     }
 
+    /// Adopts the target diameter a winder of the line published with its recipe
+    ///
+    /// A recipe applied to the laser itself afterwards wins, so a recipe with a laser section
+    /// keeps its tolerances. Without tolerances from the winder the laser keeps its own.
+    pub fn adopt_diameter_target(&mut self) {
+        let Some(target) = self.values.latest(DIAMETER_TARGET) else {
+            return;
+        };
+        if target.published <= self.target_set_at {
+            return;
+        }
+        self.target_set_at = target.published;
+
+        let (lower_tolerance, higher_tolerance) = target.value.tolerances.unwrap_or((
+            self.laser_target.lower_tolerance,
+            self.laser_target.higher_tolerance,
+        ));
+        let recipe = LaserRecipe {
+            target_diameter: target.value.diameter.get::<millimeter>(),
+            lower_tolerance: lower_tolerance.get::<millimeter>(),
+            higher_tolerance: higher_tolerance.get::<millimeter>(),
+        };
+        if let Err(e) = recipe.validate() {
+            tracing::warn!(
+                "Laser {} ignores the target diameter of the winder: {}",
+                self.machine_identification_unique,
+                e
+            );
+            return;
+        }
+        tracing::info!(
+            "Laser {} adopted the target diameter {} mm of the winder",
+            self.machine_identification_unique,
+            recipe.target_diameter
+        );
+        self.apply_recipe(&recipe);
+    }
+
+    pub fn export_config(&self) -> LaserConfig {
+        let laser_state = self.laser_state();
+        LaserConfig {
+            recipe: LaserRecipe {
+                target_diameter: laser_state.target_diameter,
+                lower_tolerance: laser_state.lower_tolerance,
+                higher_tolerance: laser_state.higher_tolerance,
+            },
+            min_max_timeframe_minutes: laser_state.min_max_timeframe_minutes,
+            startup_grace: laser_state.startup_grace,
+            strand_targets: laser_state.strand_targets,
+        }
+    }
+
+    /// Applies a validated configuration, the strand count has to match the line
+    pub fn import_config(&mut self, config: &LaserConfig) -> Result<(), anyhow::Error> {
+        if config.strand_targets.len() != self.strand_count() {
+            return Err(anyhow::anyhow!(
+                "[{}::LaserMachine::import_config] Configuration has {} strands, the line has {}",
+                module_path!(),
+                config.strand_targets.len(),
+                self.strand_count()
+            ));
+        }
+        self.apply_recipe(&config.recipe);
+        self.set_min_max_timeframe(config.min_max_timeframe_minutes);
+        self.set_startup_grace(Duration::from_secs_f64(config.startup_grace));
+        for strand_target in &config.strand_targets {
+            self.set_strand_target(strand_target)?;
+        }
+        Ok(())
+    }
+
+    pub const fn get_diameter(&self) -> Length {
+        self.diameter
+    }
+
+    /// Whether the measured diameter is inside the target tolerances
+    ///
+    /// `None` while the laser doesn't measure a filament.
+    pub fn is_in_tolerance(&self) -> Option<bool> {
+        self.laser_target.strand_target().contains(self.diameter)
+    }
+
+    /// Indices of the measured strands outside of their tolerances
+    pub fn strands_out_of_tolerance(&self) -> Vec<usize> {
+        self.strand_targets
+            .iter()
+            .zip(&self.strand_diameters)
+            .enumerate()
+            .filter(|(_, (target, diameter))| target.contains(**diameter) == Some(false))
+            .map(|(strand, _)| strand)
+            .collect()
+    }
+
     pub fn get_min_max_diameter(&self) -> (Option<f64>, Option<f64>) {
         self.diameter_tracker.get_min_max()
     }
@@ -237,18 +662,32 @@ impl LaserMachine {
     }
 
     pub fn update(&mut self) {
-        let laser_data = smol::block_on(async { self.laser.read().await.get_data().await });
+        // a closed channel means the device thread stopped and the value is stale
+        let laser_data = match self.laser_data.has_changed() {
+            Ok(_) => self.laser_data.borrow_and_update().clone(),
+            Err(_) => None,
+        };
         let diameter_mm = laser_data
             .as_ref()
             .map(|data| data.diameter.get::<millimeter>())
             .unwrap_or(0.0);
 
         self.diameter = Length::new::<millimeter>(diameter_mm);
-
-        // Add diameter measurement to tracker if we have valid data
-        if diameter_mm > 0.0 {
-            self.diameter_tracker
-                .add_measurement(diameter_mm, Instant::now());
+        let strands = laser_data
+            .as_ref()
+            .map(|data| data.strands.as_slice())
+            .unwrap_or_default();
+        for (strand, diameter) in self.strand_diameters.iter_mut().enumerate() {
+            *diameter = strands.get(strand).copied().unwrap_or(Length::ZERO);
+        }
+        // a stopped gauge doesn't publish, subscribers see the diameter going stale
+        if let Some(data) = &laser_data {
+            self.values.publish(
+                &self.machine_identification_unique,
+                DIAMETER,
+                data.diameter,
+                data.last_timestamp,
+            );
         }
 
         self.x_diameter = laser_data
@@ -272,3 +711,138 @@ pub struct LaserTarget {
     higher_tolerance: Length,
     min_max_timeframe_minutes: u64, // timeframe in minutes for min/max tracking
 }
+
+impl LaserTarget {
+    /// Target and tolerances shared by all strands
+    pub fn strand_target(&self) -> StrandTarget {
+        StrandTarget {
+            diameter: self.diameter,
+            lower_tolerance: self.lower_tolerance,
+            higher_tolerance: self.higher_tolerance,
+        }
+    }
+}
+
+/// Target diameter and tolerances of one strand
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StrandTarget {
+    diameter: Length,
+    lower_tolerance: Length,
+    higher_tolerance: Length,
+}
+
+impl StrandTarget {
+    fn from_settings(settings: &StrandTargetSettings) -> Self {
+        Self {
+            diameter: Length::new::<millimeter>(settings.target_diameter),
+            lower_tolerance: Length::new::<millimeter>(settings.lower_tolerance),
+            higher_tolerance: Length::new::<millimeter>(settings.higher_tolerance),
+        }
+    }
+
+    fn to_settings(self, strand: usize) -> StrandTargetSettings {
+        StrandTargetSettings {
+            strand,
+            target_diameter: self.diameter.get::<millimeter>(),
+            lower_tolerance: self.lower_tolerance.get::<millimeter>(),
+            higher_tolerance: self.higher_tolerance.get::<millimeter>(),
+        }
+    }
+
+    /// Whether `diameter` is inside the tolerances
+    ///
+    /// `None` while the laser doesn't measure a filament.
+    pub fn contains(&self, diameter: Length) -> Option<bool> {
+        if diameter <= Length::ZERO {
+            return None;
+        }
+        let lower = self.diameter - self.lower_tolerance;
+        let higher = self.diameter + self.higher_tolerance;
+        Some(diameter >= lower && diameter <= higher)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use control_core::helpers::clock::ManualClock;
+
+    #[test]
+    fn test_diameter_tracker_timeframe() {
+        let clock = ManualClock::new();
+        let mut tracker = DiameterTracker::with_clock(1, Arc::new(clock.clone()));
+        // leave room below the first measurement for the cutoff
+        clock.advance(Duration::from_secs(600));
+
+        tracker.add_measurement(1.80);
+        clock.advance(Duration::from_secs(30));
+        tracker.add_measurement(1.70);
+        clock.advance(Duration::from_secs(20));
+        tracker.add_measurement(1.75);
+        assert_eq!(tracker.get_min_max(), (Some(1.70), Some(1.80)));
+
+        // the 1.80 leaves the minute, the others are still within
+        clock.advance(Duration::from_secs(11));
+        assert_eq!(tracker.get_min_max(), (Some(1.70), Some(1.75)));
+
+        clock.advance(Duration::from_secs(50));
+        assert_eq!(tracker.get_min_max(), (None, None));
+    }
+
+    #[test]
+    fn test_strand_target_contains() {
+        let target = StrandTarget::from_settings(&StrandTargetSettings {
+            strand: 1,
+            target_diameter: 1.75,
+            lower_tolerance: 0.05,
+            higher_tolerance: 0.1,
+        });
+        let mm = Length::new::<millimeter>;
+
+        assert_eq!(target.contains(mm(1.71)), Some(true));
+        assert_eq!(target.contains(mm(1.84)), Some(true));
+        assert_eq!(target.contains(mm(1.69)), Some(false));
+        assert_eq!(target.contains(mm(1.86)), Some(false));
+        // no filament in the gauge
+        assert_eq!(target.contains(Length::ZERO), None);
+        assert_eq!(target.to_settings(1).strand, 1);
+    }
+
+    #[test]
+    fn test_diameter_tracker_set_timeframe() {
+        let clock = ManualClock::new();
+        let mut tracker = DiameterTracker::with_clock(5, Arc::new(clock.clone()));
+        clock.advance(Duration::from_secs(600));
+
+        tracker.add_measurement(2.0);
+        clock.advance(Duration::from_secs(120));
+        tracker.add_measurement(1.5);
+        assert_eq!(tracker.get_min_max(), (Some(1.5), Some(2.0)));
+
+        tracker.set_timeframe(1);
+        assert_eq!(tracker.get_min_max(), (Some(1.5), Some(1.5)));
+        assert_eq!(tracker.measurements.len(), 1);
+    }
+
+    #[test]
+    fn test_roundness_tracker() {
+        let clock = ManualClock::new();
+        let mut tracker = RoundnessTracker::with_clock(1, Arc::new(clock.clone()));
+        clock.advance(Duration::from_secs(600));
+        assert_eq!(tracker.get_min_avg(), (None, None));
+
+        tracker.add_measurement(0.96);
+        clock.advance(Duration::from_secs(40));
+        tracker.add_measurement(0.99);
+        tracker.add_measurement(1.0);
+        let (min, avg) = tracker.get_min_avg();
+        assert_eq!(min, Some(0.96));
+        assert!((avg.unwrap() - 0.983_333).abs() < 1e-6);
+
+        // the 0.96 leaves the minute
+        clock.advance(Duration::from_secs(21));
+        let (min, avg) = tracker.get_min_avg();
+        assert_eq!(min, Some(0.99));
+        assert!((avg.unwrap() - 0.995).abs() < 1e-9);
+    }
+}
@@ -1,10 +1,22 @@
-use std::time::Instant;
+use tokio::sync::watch;
 
-use crate::serial::{devices::laser::Laser, registry::SERIAL_DEVICE_REGISTRY};
+use crate::config::config;
+use crate::journal::Journal;
+use crate::serial::{
+    devices::laser::{Laser, LaserData},
+    registry::SERIAL_DEVICE_REGISTRY,
+};
 
-use super::{DiameterTracker, LaserMachine, LaserTarget, api::LaserMachineNamespace};
+use super::{
+    DiameterTracker, LaserMachine, LaserTarget, RoundnessTracker,
+    api::LaserMachineNamespace,
+    disturbance::DisturbanceAnalyzer,
+    lens_drift::{LENS_DRIFT_DIR, LensDriftDetector},
+    tolerance_monitor::ToleranceMonitor,
+};
 use anyhow::Error;
 use control_core::machines::new::{MachineNewHardware, MachineNewTrait};
+use std::time::{Duration, Instant};
 use uom::ConstZero;
 use uom::si::{f64::Length, length::millimeter};
 
@@ -15,46 +27,87 @@ impl MachineNewTrait for LaserMachine {
     where
         Self: Sized,
     {
-        let hardware_serial = match params.hardware {
-            MachineNewHardware::Serial(serial) => *serial,
-            _ => return Err(Error::msg("Invalid hardware type for LaserMachine")),
-        };
-
-        // downcast the hardware_serial to Arc<RwLock<Laser>>
-
-        let laser = match smol::block_on(
-            SERIAL_DEVICE_REGISTRY.downcast_arc_rwlock::<Laser>(hardware_serial.device.clone()),
-        ) {
-            Ok(laser) => laser,
-            Err(_) => return Err(Error::msg("Failed to downcast to Laser")),
-        };
+        let (laser_data, axes) = laser_from_hardware(params)?;
+        let machine_identification_unique = params.get_machine_identification_unique();
         // set laser target configuration
+        let machine_defaults = config().machines.clone();
+        let defaults = &machine_defaults.laser_for(machine_identification_unique.serial);
         let laser_target = LaserTarget {
-            higher_tolerance: Length::new::<millimeter>(0.05),
-            lower_tolerance: Length::new::<millimeter>(0.05),
-            diameter: Length::new::<millimeter>(1.75),
-            min_max_timeframe_minutes: 30, // Default 30 minutes
+            higher_tolerance: Length::new::<millimeter>(defaults.higher_tolerance),
+            lower_tolerance: Length::new::<millimeter>(defaults.lower_tolerance),
+            diameter: Length::new::<millimeter>(defaults.target_diameter),
+            min_max_timeframe_minutes: defaults.min_max_timeframe_minutes,
         };
+        // the baseline is learned over hours, a restart continues with it
+        let lens_drift_journal = Journal::in_dir(
+            LENS_DRIFT_DIR,
+            &machine_identification_unique,
+            LensDriftDetector::WINDOW,
+        );
+        let lens_drift =
+            LensDriftDetector::from_snapshot(lens_drift_journal.load().unwrap_or_default());
         let mut laser_machine = Self {
-            machine_identification_unique: params.get_machine_identification_unique(),
-            laser,
+            machine_identification_unique,
+            laser_data,
+            axes,
+            values: params.values.clone(),
             namespace: LaserMachineNamespace {
                 namespace: params.namespace.clone(),
             },
-            last_measurement_emit: Instant::now(),
-            last_minmax_emit: Instant::now(),
+            strand_targets: vec![laser_target.strand_target(); machine_defaults.strands],
             laser_target: laser_target.clone(),
+            target_set_at: Instant::now(),
             diameter_tracker: DiameterTracker::new(laser_target.min_max_timeframe_minutes),
+            roundness_tracker: RoundnessTracker::new(laser_target.min_max_timeframe_minutes),
+            disturbance_analyzer: DisturbanceAnalyzer::new(),
+            tolerance_monitor: ToleranceMonitor::new(Duration::from_secs_f64(
+                defaults.startup_grace_seconds,
+            )),
+            lens_drift,
+            lens_drift_journal,
+            self_test: None,
             emitted_default_state: false,
             diameter: Length::ZERO,
             x_diameter: None,
             y_diameter: None,
             roundness: None,
+            strand_diameters: vec![Length::ZERO; machine_defaults.strands],
         };
 
         // Emit initial state
+        laser_machine.emit_schema();
         laser_machine.emit_state();
 
         Ok(laser_machine)
     }
+
+    fn rebind(
+        &mut self,
+        params: &control_core::machines::new::MachineNewParams<'_, '_, '_, '_, '_, '_, '_>,
+    ) -> Result<(), Error> {
+        // the gauge may have been replaced by another model
+        (self.laser_data, self.axes) = laser_from_hardware(params)?;
+        self.emit_state();
+        Ok(())
+    }
+}
+
+fn laser_from_hardware(
+    params: &control_core::machines::new::MachineNewParams<'_, '_, '_, '_, '_, '_, '_>,
+) -> Result<(watch::Receiver<Option<LaserData>>, u8), Error> {
+    let hardware_serial = match params.hardware {
+        MachineNewHardware::Serial(serial) => *serial,
+        _ => return Err(Error::msg("Invalid hardware type for LaserMachine")),
+    };
+
+    // downcast the hardware_serial to Arc<RwLock<Laser>> and subscribe to its measurements
+    match smol::block_on(
+        SERIAL_DEVICE_REGISTRY.downcast_arc_rwlock::<Laser>(hardware_serial.device.clone()),
+    ) {
+        Ok(laser) => {
+            let laser = laser.read_blocking();
+            Ok((laser.subscribe(), laser.axes))
+        }
+        Err(_) => Err(Error::msg("Failed to downcast to Laser")),
+    }
 }
@@ -0,0 +1,124 @@
+use std::time::{Duration, Instant};
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum MonitoringPhase {
+    /// No filament in the gauge
+    NoFilament,
+    /// Waiting for the diameter to settle in tolerance after the filament entered the gauge
+    Startup,
+    /// Tolerance alarms and statistics are active
+    Monitoring,
+}
+
+/// Holds back tolerance alarms and statistics while the line starts up
+///
+/// Monitoring begins once the diameter was in tolerance for the startup grace without a break,
+/// so the transient of every start doesn't raise alarms. It ends when the filament leaves the
+/// gauge, the next filament starts with a new grace.
+#[derive(Debug)]
+pub struct ToleranceMonitor {
+    startup_grace: Duration,
+    phase: MonitoringPhase,
+    /// Start of the current in tolerance stretch during startup
+    in_tolerance_since: Option<Instant>,
+}
+
+impl ToleranceMonitor {
+    pub const fn new(startup_grace: Duration) -> Self {
+        Self {
+            startup_grace,
+            phase: MonitoringPhase::NoFilament,
+            in_tolerance_since: None,
+        }
+    }
+
+    /// Advances the phase, `in_tolerance` is `None` without filament
+    ///
+    /// Returns whether the phase changed.
+    pub fn update(&mut self, now: Instant, in_tolerance: Option<bool>) -> bool {
+        let phase = match (self.phase, in_tolerance) {
+            (_, None) => MonitoringPhase::NoFilament,
+            (MonitoringPhase::Monitoring, Some(_)) => MonitoringPhase::Monitoring,
+            (_, Some(false)) => MonitoringPhase::Startup,
+            (_, Some(true)) => {
+                let since = *self.in_tolerance_since.get_or_insert(now);
+                match now.duration_since(since) >= self.startup_grace {
+                    true => MonitoringPhase::Monitoring,
+                    false => MonitoringPhase::Startup,
+                }
+            }
+        };
+        if phase != MonitoringPhase::Startup || in_tolerance != Some(true) {
+            self.in_tolerance_since = None;
+        }
+
+        let changed = phase != self.phase;
+        self.phase = phase;
+        changed
+    }
+
+    /// Applies to the next startup
+    pub const fn set_startup_grace(&mut self, startup_grace: Duration) {
+        self.startup_grace = startup_grace;
+    }
+
+    pub const fn get_startup_grace(&self) -> Duration {
+        self.startup_grace
+    }
+
+    pub const fn get_phase(&self) -> MonitoringPhase {
+        self.phase
+    }
+
+    pub const fn is_monitoring(&self) -> bool {
+        matches!(self.phase, MonitoringPhase::Monitoring)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRACE: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn test_startup_grace() {
+        let mut monitor = ToleranceMonitor::new(GRACE);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert!(!monitor.update(at(0), None));
+        assert!(monitor.update(at(1), Some(false)));
+        assert_eq!(monitor.get_phase(), MonitoringPhase::Startup);
+
+        // leaving the tolerance restarts the grace
+        monitor.update(at(2), Some(true));
+        monitor.update(at(8), Some(false));
+        monitor.update(at(9), Some(true));
+        assert!(!monitor.update(at(18), Some(true)));
+        assert!(monitor.update(at(19), Some(true)));
+        assert!(monitor.is_monitoring());
+
+        // out of tolerance while monitoring is what the alarms are for
+        assert!(!monitor.update(at(20), Some(false)));
+        assert!(monitor.is_monitoring());
+
+        // the next filament starts over
+        assert!(monitor.update(at(21), None));
+        monitor.update(at(22), Some(true));
+        assert_eq!(monitor.get_phase(), MonitoringPhase::Startup);
+    }
+
+    #[test]
+    fn test_without_grace() {
+        let mut monitor = ToleranceMonitor::new(Duration::ZERO);
+        let now = Instant::now();
+        monitor.update(now, Some(false));
+        assert!(!monitor.is_monitoring());
+        monitor.update(now, Some(true));
+        assert!(monitor.is_monitoring());
+    }
+}
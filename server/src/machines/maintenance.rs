@@ -0,0 +1,277 @@
+use crate::{config::config, journal::Journal};
+use control_core::{
+    alarms::{AlarmCondition, AlarmSeverity},
+    helpers::clock::unix_millis,
+    machines::identification::MachineIdentificationUnique,
+};
+use control_core_derive::BuildEvent;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Directory inside [`crate::storage::data_dir`] with the maintenance counters of every machine
+pub const MAINTENANCE_DIR: &str = "maintenance";
+
+/// Part of a machine that wears with use
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum MaintenancePart {
+    /// Counts the km of filament pulled
+    PullerWheel,
+    /// Counts the revolutions of the spool shaft
+    SpoolShaft,
+    /// Counts the hours the extruder screw turned
+    ExtruderScrew,
+}
+
+impl MaintenancePart {
+    pub const fn unit(self) -> &'static str {
+        match self {
+            Self::PullerWheel => "km",
+            Self::SpoolShaft => "revolutions",
+            Self::ExtruderScrew => "h",
+        }
+    }
+
+    /// Service interval from the config, `None` if the part is never due
+    pub fn service_interval(self) -> Option<f64> {
+        let maintenance = &config().maintenance;
+        match self {
+            Self::PullerWheel => maintenance.puller_wheel_km,
+            Self::SpoolShaft => maintenance.spool_shaft_revolutions,
+            Self::ExtruderScrew => maintenance.screw_hours,
+        }
+    }
+
+    const fn code(self) -> &'static str {
+        match self {
+            Self::PullerWheel => "maintenance_due_puller_wheel",
+            Self::SpoolShaft => "maintenance_due_spool_shaft",
+            Self::ExtruderScrew => "maintenance_due_extruder_screw",
+        }
+    }
+}
+
+/// Wear of one part since its last service
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct MaintenanceCounter {
+    pub part: MaintenancePart,
+    /// in the unit of the part
+    pub value: f64,
+    /// unix timestamp in milliseconds, `None` until the first service was recorded
+    pub serviced_at: Option<u64>,
+}
+
+impl MaintenanceCounter {
+    pub fn is_due(&self) -> bool {
+        self.part
+            .service_interval()
+            .is_some_and(|interval| self.value >= interval)
+    }
+}
+
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+pub struct MaintenanceValues {
+    pub part: MaintenancePart,
+    /// since the last service in the unit of the part
+    pub value: f64,
+    pub unit: String,
+    pub service_interval: Option<f64>,
+    pub due: bool,
+    /// unix timestamp in milliseconds
+    pub serviced_at: Option<u64>,
+}
+
+#[derive(Serialize, Debug, Clone, BuildEvent, JsonSchema)]
+pub struct MaintenanceEvent {
+    pub counters: Vec<MaintenanceValues>,
+}
+
+/// Wear counters of the parts of one machine
+///
+/// The machine counts in its act cycle, the counters are journaled to [`MAINTENANCE_DIR`] so
+/// they keep counting across restarts until the part is serviced.
+#[derive(Debug)]
+pub struct MaintenanceCounters {
+    counters: Vec<MaintenanceCounter>,
+    journal: Journal<Vec<MaintenanceCounter>>,
+    last_update: Option<Instant>,
+}
+
+impl MaintenanceCounters {
+    const JOURNAL_INTERVAL: Duration = Duration::from_secs(60);
+
+    /// Counters of `parts`, resumed from the journal
+    pub fn new(
+        machine_identification_unique: &MachineIdentificationUnique,
+        parts: &[MaintenancePart],
+    ) -> Self {
+        let journal = Journal::in_dir(
+            MAINTENANCE_DIR,
+            machine_identification_unique,
+            Self::JOURNAL_INTERVAL,
+        );
+        let saved = journal.load().unwrap_or_default();
+        Self::with_saved(journal, parts, &saved)
+    }
+
+    fn with_saved(
+        journal: Journal<Vec<MaintenanceCounter>>,
+        parts: &[MaintenancePart],
+        saved: &[MaintenanceCounter],
+    ) -> Self {
+        let counters = parts
+            .iter()
+            .map(|&part| {
+                saved
+                    .iter()
+                    .find(|counter| counter.part == part)
+                    .copied()
+                    .unwrap_or(MaintenanceCounter {
+                        part,
+                        value: 0.0,
+                        serviced_at: None,
+                    })
+            })
+            .collect();
+        Self {
+            counters,
+            journal,
+            last_update: None,
+        }
+    }
+
+    /// Time since the last call, zero on the first one
+    pub fn elapsed(&mut self, now: Instant) -> Duration {
+        self.last_update
+            .replace(now)
+            .map_or(Duration::ZERO, |last| now.saturating_duration_since(last))
+    }
+
+    /// Counts wear of a part, parts the machine doesn't have are ignored
+    pub fn add(&mut self, part: MaintenancePart, amount: f64) {
+        if let Some(counter) = self
+            .counters
+            .iter_mut()
+            .find(|counter| counter.part == part)
+        {
+            counter.value += amount.abs();
+        }
+    }
+
+    /// Journals the counters every [`Self::JOURNAL_INTERVAL`], called by `act`
+    pub fn sync_journal(&mut self, now: Instant) {
+        if self.journal.is_due(now) {
+            self.journal.record(now, self.counters.clone());
+        }
+    }
+
+    /// Starts counting again after the part was serviced
+    pub fn reset(&mut self, part: MaintenancePart, now: Instant) -> Result<(), anyhow::Error> {
+        let counter = self
+            .counters
+            .iter_mut()
+            .find(|counter| counter.part == part)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "[{}::MaintenanceCounters::reset] The machine has no {:?}",
+                    module_path!(),
+                    part
+                )
+            })?;
+        counter.value = 0.0;
+        counter.serviced_at = Some(unix_millis());
+        self.journal.record(now, self.counters.clone());
+        Ok(())
+    }
+
+    pub fn values(&self) -> Vec<MaintenanceValues> {
+        self.counters
+            .iter()
+            .map(|counter| MaintenanceValues {
+                part: counter.part,
+                value: counter.value,
+                unit: counter.part.unit().to_string(),
+                service_interval: counter.part.service_interval(),
+                due: counter.is_due(),
+                serviced_at: counter.serviced_at,
+            })
+            .collect()
+    }
+
+    /// One alarm per part due for service
+    pub fn alarms(&self) -> Vec<AlarmCondition> {
+        self.counters
+            .iter()
+            .filter(|counter| counter.is_due())
+            .map(|counter| {
+                AlarmCondition::new(
+                    counter.part.code(),
+                    format!(
+                        "{:?} is due for service after {:.0} {}",
+                        counter.part,
+                        counter.value,
+                        counter.part.unit()
+                    ),
+                    AlarmSeverity::Warning,
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use control_core::machines::identification::MachineIdentification;
+
+    fn journal() -> Journal<Vec<MaintenanceCounter>> {
+        let machine_identification_unique = MachineIdentificationUnique {
+            machine_identification: MachineIdentification {
+                vendor: 1,
+                machine: 2,
+            },
+            serial: 3,
+        };
+        Journal::in_dir(
+            MAINTENANCE_DIR,
+            &machine_identification_unique,
+            Duration::from_secs(60),
+        )
+    }
+
+    #[test]
+    fn test_counters() {
+        let saved = [MaintenanceCounter {
+            part: MaintenancePart::PullerWheel,
+            value: 12.5,
+            serviced_at: Some(1),
+        }];
+        let mut counters = MaintenanceCounters::with_saved(
+            journal(),
+            &[MaintenancePart::PullerWheel, MaintenancePart::SpoolShaft],
+            &saved,
+        );
+        counters.add(MaintenancePart::PullerWheel, -0.5);
+        counters.add(MaintenancePart::SpoolShaft, 100.0);
+        // not a part of this machine
+        counters.add(MaintenancePart::ExtruderScrew, 1.0);
+
+        let values = counters.values();
+        assert_eq!(values.len(), 2);
+        assert_relative_eq!(values[0].value, 13.0);
+        assert_relative_eq!(values[1].value, 100.0);
+
+        let now = Instant::now();
+        assert_eq!(counters.elapsed(now), Duration::ZERO);
+        assert_eq!(
+            counters.elapsed(now + Duration::from_secs(2)),
+            Duration::from_secs(2)
+        );
+
+        counters.reset(MaintenancePart::SpoolShaft, now).unwrap();
+        assert_relative_eq!(counters.values()[1].value, 0.0);
+        assert!(counters.values()[1].serviced_at.is_some());
+        assert!(counters.reset(MaintenancePart::ExtruderScrew, now).is_err());
+    }
+}
@@ -1,7 +1,7 @@
 use control_core::machines::new::MachineAct;
 
 use super::MockMachine;
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
 /// Implements the `MachineAct` trait for the `MockMachine`.
 ///
@@ -13,19 +13,11 @@ use std::time::{Duration, Instant};
 ///
 /// # Description
 /// This method is called to perform periodic actions for the `MockMachine`. Specifically:
-/// - It checks if the time elapsed since the last measurement emission exceeds 33 milliseconds.
-/// - If the condition is met and the machine is in Running mode, it emits a sine wave data event.
+/// - It emits a sine wave data event, the namespace limits the rate to about 30 per second.
 /// - State events (frequency, mode) are only emitted when values change, not continuously.
 ///
-/// The method ensures that the sine wave value is updated approximately 60 times per second (16ms intervals) when running.
-///
 impl MachineAct for MockMachine {
-    fn act(&mut self, now: Instant) {
-        // Only emit live values if machine is in Running mode
-        // The live values are updated approximately 30 times per second
-        if now.duration_since(self.last_measurement_emit) > Duration::from_secs_f64(1.0 / 30.0) {
-            self.emit_live_values();
-            self.last_measurement_emit = now;
-        }
+    fn act(&mut self, _now: Instant) {
+        self.emit_live_values();
     }
 }
@@ -1,28 +1,27 @@
 use super::MockMachine;
 use control_core::{
-    machines::api::MachineApi,
+    machines::{api::MachineApi, schema::MachineApiTypes},
     socketio::{
-        event::{Event, GenericEvent},
-        namespace::{
-            CacheFn, CacheableEvents, Namespace, NamespaceCacheingLogic, cache_duration,
-            cache_first_and_last_event,
-        },
+        event::Event,
+        namespace::Namespace,
+        units::{FieldSchema, SchemaEvent},
     },
 };
-use control_core_derive::BuildEvent;
+use control_core_derive::{BuildEvent, NamespaceEvents};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use smol::lock::Mutex;
-use std::{sync::Arc, time::Duration};
-use tracing::instrument;
+use std::sync::Arc;
+use uom::si::ratio::ratio;
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
 pub enum Mode {
     Standby,
     Running,
 }
 
-#[derive(Serialize, Debug, Clone, Default)]
+#[derive(Serialize, Debug, Clone, Default, BuildEvent, JsonSchema)]
 pub struct LiveValuesEvent {
     pub amplitude_sum: f64,
     pub amplitude1: f64,
@@ -31,12 +30,18 @@ pub struct LiveValuesEvent {
 }
 
 impl LiveValuesEvent {
-    pub fn build(&self) -> Event<Self> {
-        Event::new("LiveValuesEvent", self.clone())
+    pub fn schema() -> SchemaEvent {
+        let amplitude = || FieldSchema::of::<ratio>().range(-1.0, 1.0);
+        SchemaEvent::new([
+            ("amplitude_sum", FieldSchema::of::<ratio>().range(-3.0, 3.0)),
+            ("amplitude1", amplitude()),
+            ("amplitude2", amplitude()),
+            ("amplitude3", amplitude()),
+        ])
     }
 }
 
-#[derive(Serialize, Debug, Clone, PartialEq, BuildEvent)]
+#[derive(Serialize, Debug, Clone, PartialEq, BuildEvent, JsonSchema)]
 pub struct StateEvent {
     pub is_default_state: bool,
     /// sine wave frequencies in millihertz
@@ -47,14 +52,20 @@ pub struct StateEvent {
     pub mode_state: ModeState,
 }
 
-#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
 pub struct ModeState {
     /// current mode
     pub mode: Mode,
 }
 
+#[derive(NamespaceEvents)]
+#[namespace_events(namespace = MockMachineNamespace)]
 pub enum MockEvents {
+    #[cache(duration_secs = 3600)]
     LiveValues(Event<LiveValuesEvent>),
+    #[cache(one)]
+    Schema(Event<SchemaEvent>),
+    #[cache(first_and_last)]
     State(Event<StateEvent>),
 }
 
@@ -63,28 +74,9 @@ pub struct MockMachineNamespace {
     pub namespace: Arc<Mutex<Namespace>>,
 }
 
-impl CacheableEvents<Self> for MockEvents {
-    fn event_value(&self) -> GenericEvent {
-        match self {
-            Self::LiveValues(event) => event.into(),
-            Self::State(event) => event.into(),
-        }
-    }
-
-    fn event_cache_fn(&self) -> CacheFn {
-        let cache_one_hour = cache_duration(Duration::from_secs(60 * 60), Duration::from_secs(1));
-        let cache_first_and_last = cache_first_and_last_event();
-
-        match self {
-            Self::LiveValues(_) => cache_one_hour,
-            Self::State(_) => cache_first_and_last,
-        }
-    }
-}
-
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, JsonSchema)]
 /// Mutation for controlling the mock machine
-enum Mutation {
+pub enum Mutation {
     /// Set the frequency of the sine wave in millihertz
     SetFrequency1(f64),
     SetFrequency2(f64),
@@ -92,20 +84,14 @@ enum Mutation {
     SetMode(Mode),
 }
 
-impl NamespaceCacheingLogic<MockEvents> for MockMachineNamespace {
-    #[instrument(skip_all)]
-    fn emit(&mut self, events: MockEvents) {
-        let event = Arc::new(events.event_value());
-        let buffer_fn = events.event_cache_fn();
-
-        let mut namespace = self.namespace.lock_blocking();
-        namespace.emit(event, &buffer_fn);
-    }
+impl MachineApiTypes for MockMachine {
+    type Mutation = Mutation;
+    type Events = MockEvents;
 }
 
 impl MachineApi for MockMachine {
     fn api_mutate(&mut self, request_body: Value) -> Result<(), anyhow::Error> {
-        let mutation: Mutation = serde_json::from_value(request_body)?;
+        let mutation = Self::parse_mutation(request_body)?;
         match mutation {
             Mutation::SetFrequency1(frequency) => {
                 self.set_frequency1(frequency);
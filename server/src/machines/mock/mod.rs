@@ -24,7 +24,6 @@ pub struct MockMachine {
 
     // socketio
     namespace: MockMachineNamespace,
-    last_measurement_emit: Instant,
 
     // mock machine specific fields
     t_0: Instant,
@@ -76,6 +75,11 @@ impl MockMachine {
             .emit(MockEvents::LiveValues(live_values.build()));
     }
 
+    pub fn emit_schema(&mut self) {
+        self.namespace
+            .emit(MockEvents::Schema(LiveValuesEvent::schema().build()));
+    }
+
     /// Emit the current state of the mock machine only if values have changed
     pub fn emit_state(&mut self) {
         info!(
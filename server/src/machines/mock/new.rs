@@ -36,7 +36,6 @@ impl MachineNewTrait for MockMachine {
             namespace: MockMachineNamespace {
                 namespace: params.namespace.clone(),
             },
-            last_measurement_emit: now,
             t_0: now, // Initialize start time to current time
             frequency1: Frequency::new::<hertz>(0.1), // Default frequency1 of 100 mHz
             frequency2: Frequency::new::<hertz>(0.2), // Default frequency2 of 200 mHz
@@ -46,6 +45,7 @@ impl MachineNewTrait for MockMachine {
             last_emitted_event: None,
         };
 
+        mock_machine.emit_schema();
         mock_machine.emit_state();
 
         Ok(mock_machine)
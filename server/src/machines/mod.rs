@@ -11,13 +11,20 @@ use ethercat_hal::devices::{
     EthercatDevice, SubDeviceIdentityTuple, downcast_device, subdevice_identity_to_tuple,
 };
 use ethercrab::{SubDevice, SubDeviceRef};
+use serde::Serialize;
+
+use crate::io_mapping::{MachineIoMapping, MachineIoSignals};
 use smol::lock::RwLock;
 
 pub mod aquapath1;
 pub mod buffer1;
+pub mod digital_io;
 pub mod extruder1;
+pub mod gain_history;
 pub mod laser;
+pub mod maintenance;
 pub mod mock;
+pub mod mutation_limits;
 pub mod registry;
 pub mod winder2;
 
@@ -29,6 +36,75 @@ pub const MACHINE_MOCK: u16 = 0x0007;
 pub const MACHINE_BUFFER_V1: u16 = 0x0008;
 pub const MACHINE_AQUAPATH_V1: u16 = 0x0009;
 
+/// Short name of a machine type, used where machines are addressed by name (e.g. MQTT topics)
+pub const fn machine_slug(machine: u16) -> Option<&'static str> {
+    match machine {
+        MACHINE_WINDER_V1 => Some("winder"),
+        MACHINE_EXTRUDER_V1 => Some("extruder"),
+        MACHINE_LASER_V1 => Some("laser"),
+        MACHINE_MOCK => Some("mock"),
+        MACHINE_BUFFER_V1 => Some("buffer"),
+        MACHINE_AQUAPATH_V1 => Some("aquapath"),
+        _ => None,
+    }
+}
+
+/// What a machine type offers, so clients can build their UI without knowing machine types
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MachineCapability {
+    /// Accepts its section of a recipe
+    Recipes,
+    /// Can be connected to another machine, e.g. a winder to a buffer
+    CrossConnection,
+    Winding,
+    Extrusion,
+    DiameterMeasurement,
+    Buffering,
+    Cooling,
+}
+
+pub const fn machine_capabilities(machine: u16) -> &'static [MachineCapability] {
+    use MachineCapability::*;
+    match machine {
+        MACHINE_WINDER_V1 => &[Recipes, CrossConnection, Winding],
+        MACHINE_EXTRUDER_V1 => &[Recipes, Extrusion],
+        MACHINE_LASER_V1 => &[Recipes, DiameterMeasurement],
+        MACHINE_BUFFER_V1 => &[CrossConnection, Buffering],
+        MACHINE_AQUAPATH_V1 => &[Cooling],
+        _ => &[],
+    }
+}
+
+/// Digital signals a machine type exposes for assignment to terminal channels
+pub const fn machine_io_signals(machine: u16) -> Option<MachineIoSignals> {
+    match machine {
+        MACHINE_WINDER_V1 => Some(winder2::Winder2::IO_SIGNALS),
+        _ => None,
+    }
+}
+
+/// Wiring a machine type uses until its I/O mapping is changed
+pub fn machine_default_io_mapping(machine: u16) -> MachineIoMapping {
+    match machine {
+        MACHINE_WINDER_V1 => winder2::Winder2::default_io_mapping(),
+        _ => MachineIoMapping::default(),
+    }
+}
+
+/// Machine type of a [`machine_slug`]
+pub fn machine_from_slug(slug: &str) -> Option<u16> {
+    [
+        MACHINE_WINDER_V1,
+        MACHINE_EXTRUDER_V1,
+        MACHINE_LASER_V1,
+        MACHINE_MOCK,
+        MACHINE_BUFFER_V1,
+        MACHINE_AQUAPATH_V1,
+    ]
+    .into_iter()
+    .find(|machine| machine_slug(*machine) == Some(slug))
+}
+
 async fn get_device_ident<
     'maindevice,
     'subdevices,
@@ -0,0 +1,241 @@
+use crate::{auth::mutation_name, config::MutationLimit};
+use control_core::{
+    machines::identification::MachineIdentificationUnique, rest::unit_value::UnitValue,
+};
+use control_core_derive::BuildEvent;
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+/// Why a mutation was rejected before it reached the machine
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind")]
+pub enum MutationRejection {
+    /// The value is outside the configured range
+    OutOfRange {
+        value: f64,
+        min: Option<f64>,
+        max: Option<f64>,
+        unit: Option<String>,
+    },
+    /// The machine got more mutations of this name than the configured rate
+    RateLimited { retry_after_ms: u64 },
+    /// The mutation is restricted while a remote session is active
+    RemoteSession { reason: String },
+}
+
+impl std::fmt::Display for MutationRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OutOfRange {
+                value,
+                min,
+                max,
+                unit,
+            } => {
+                let bound = |bound: &Option<f64>| bound.map_or("-".to_string(), |b| b.to_string());
+                let unit = unit
+                    .as_ref()
+                    .map_or(String::new(), |unit| format!(" {}", unit));
+                write!(
+                    f,
+                    "{}{} is outside of {} to {}{}",
+                    value,
+                    unit,
+                    bound(min),
+                    bound(max),
+                    unit
+                )
+            }
+            Self::RateLimited { retry_after_ms } => {
+                write!(f, "Too many changes, retry in {} ms", retry_after_ms)
+            }
+            Self::RemoteSession { reason } => {
+                write!(f, "Not allowed during a remote session, {}", reason)
+            }
+        }
+    }
+}
+
+/// Emitted on the machine namespace when a mutation was rejected
+#[derive(Serialize, Debug, Clone, BuildEvent)]
+pub struct MutationRejectedEvent {
+    /// name of the rejected mutation
+    pub mutation: String,
+    #[serde(flatten)]
+    pub rejection: MutationRejection,
+}
+
+/// Checks the value of a mutation against its limit
+///
+/// Mutations without a limit and values that don't have the expected shape pass, the
+/// machine rejects malformed mutations itself.
+pub fn check_limits(
+    mutation: &Value,
+    limits: &BTreeMap<String, MutationLimit>,
+) -> Result<(), MutationRejection> {
+    let Some(name) = mutation_name(mutation) else {
+        return Ok(());
+    };
+    let Some(limit) = limits.get(name) else {
+        return Ok(());
+    };
+    let Some(value) = mutation_value(mutation, limit.field.as_deref(), limit.unit.as_deref())
+    else {
+        return Ok(());
+    };
+
+    let below = limit.min.is_some_and(|min| value < min);
+    let above = limit.max.is_some_and(|max| value > max);
+    if below || above {
+        return Err(MutationRejection::OutOfRange {
+            value,
+            min: limit.min,
+            max: limit.max,
+            unit: limit.unit.clone(),
+        });
+    }
+    Ok(())
+}
+
+/// Value of a mutation in `unit`, read from `field` of the mutation if set
+///
+/// `None` if the mutation has no such value or it can't be converted.
+pub fn mutation_value(mutation: &Value, field: Option<&str>, unit: Option<&str>) -> Option<f64> {
+    let payload = mutation.get(mutation_name(mutation)?)?;
+    let payload = match field {
+        Some(field) => payload.get(field)?,
+        None => payload,
+    };
+    let value = serde_json::from_value::<UnitValue>(payload.clone()).ok()?;
+    unit.map_or(
+        match value {
+            UnitValue::Bare(value) | UnitValue::Tagged { value, .. } => Some(value),
+        },
+        |unit| value.value_in(unit).ok(),
+    )
+}
+
+/// Token bucket of one machine and mutation name
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+/// Limits how often a machine accepts a mutation of the same name
+///
+/// Each machine and mutation name has a bucket refilled at the configured rate, holding up
+/// to a second worth of mutations, so a short burst like dragging a slider passes.
+#[derive(Debug, Default)]
+pub struct MutationRateLimiter {
+    buckets: HashMap<(MachineIdentificationUnique, String), Bucket>,
+}
+
+impl MutationRateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn check(
+        &mut self,
+        machine: &MachineIdentificationUnique,
+        mutation: &str,
+        rate_per_second: f64,
+        now: Instant,
+    ) -> Result<(), MutationRejection> {
+        let capacity = rate_per_second.max(1.0);
+        let bucket = self
+            .buckets
+            .entry((machine.clone(), mutation.to_string()))
+            .or_insert(Bucket {
+                tokens: capacity,
+                last: now,
+            });
+
+        let elapsed = now.saturating_duration_since(bucket.last).as_secs_f64();
+        bucket.tokens = elapsed
+            .mul_add(rate_per_second, bucket.tokens)
+            .min(capacity);
+        bucket.last = now;
+        if bucket.tokens < 1.0 {
+            let retry_after = Duration::from_secs_f64((1.0 - bucket.tokens) / rate_per_second);
+            return Err(MutationRejection::RateLimited {
+                retry_after_ms: retry_after.as_millis().max(1) as u64,
+            });
+        }
+        bucket.tokens -= 1.0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MutationsConfig;
+    use approx::assert_relative_eq;
+    use control_core::machines::identification::MachineIdentification;
+    use serde_json::json;
+
+    #[test]
+    fn test_limits() {
+        let limits = MutationsConfig::default().limits;
+        assert!(check_limits(&json!({"SetPullerTargetDiameter": 1.75}), &limits).is_ok());
+        assert!(check_limits(&json!({"SetPullerTargetDiameter": 7.0}), &limits).is_err());
+        // 0.01 in are 0.254 mm
+        let Err(MutationRejection::OutOfRange { value, min, .. }) = check_limits(
+            &json!({"SetTargetDiameter": {"value": 0.01, "unit": "in"}}),
+            &limits,
+        ) else {
+            panic!("0.254 mm is below the limit");
+        };
+        assert_relative_eq!(value, 0.254, epsilon = 1e-9);
+        assert_eq!(min, Some(0.5));
+        assert!(
+            check_limits(
+                &json!({"SetStrandTarget": {"strand": 0, "target_diameter": 0.1, "lower_tolerance": 0.05, "higher_tolerance": 0.05}}),
+                &limits
+            )
+            .is_err()
+        );
+        assert!(check_limits(&json!({"SetPullerTargetSpeed": 1000.0}), &limits).is_ok());
+        assert!(check_limits(&json!("ResetSpoolProgress"), &limits).is_ok());
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let machine = MachineIdentificationUnique {
+            machine_identification: MachineIdentification {
+                vendor: 1,
+                machine: 2,
+            },
+            serial: 1,
+        };
+        let mut limiter = MutationRateLimiter::new();
+        let now = Instant::now();
+
+        // a burst of one second passes
+        for _ in 0..2 {
+            assert!(limiter.check(&machine, "SetMode", 2.0, now).is_ok());
+        }
+        assert_eq!(
+            limiter.check(&machine, "SetMode", 2.0, now),
+            Err(MutationRejection::RateLimited {
+                retry_after_ms: 500
+            })
+        );
+        // other mutations have their own bucket
+        assert!(
+            limiter
+                .check(&machine, "SetPullerForward", 2.0, now)
+                .is_ok()
+        );
+
+        let later = now + Duration::from_millis(500);
+        assert!(limiter.check(&machine, "SetMode", 2.0, later).is_ok());
+        assert!(limiter.check(&machine, "SetMode", 2.0, later).is_err());
+    }
+}
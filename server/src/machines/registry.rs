@@ -7,19 +7,84 @@ use crate::machines::extruder1::ExtruderV2;
 use crate::machines::{
     aquapath1::AquaPathV1, buffer1::BufferV1, laser::LaserMachine, winder2::Winder2,
 };
-use control_core::machines::registry::MachineRegistry;
+use control_core::machines::registry::{MachinePlugin, MachineRegistry};
 use lazy_static::lazy_static;
 
+/// Machines built by QiTech
+pub struct QiTechMachines;
+
+impl MachinePlugin for QiTechMachines {
+    fn name(&self) -> &'static str {
+        "qitech"
+    }
+
+    fn register(&self, registry: &mut MachineRegistry) {
+        registry.register::<Winder2>(Winder2::MACHINE_IDENTIFICATION);
+        registry.register::<LaserMachine>(LaserMachine::MACHINE_IDENTIFICATION);
+        registry.register::<ExtruderV2>(ExtruderV2::MACHINE_IDENTIFICATION);
+        #[cfg(feature = "mock-machine")]
+        registry.register::<MockMachine>(MockMachine::MACHINE_IDENTIFICATION);
+        registry.register::<BufferV1>(BufferV1::MACHINE_IDENTIFICATION);
+        registry.register::<AquaPathV1>(AquaPathV1::MACHINE_IDENTIFICATION);
+    }
+}
+
 lazy_static! {
     pub static ref MACHINE_REGISTRY: MachineRegistry = {
         let mut mc = MachineRegistry::new();
-        mc.register::<Winder2>(Winder2::MACHINE_IDENTIFICATION);
-        mc.register::<LaserMachine>(LaserMachine::MACHINE_IDENTIFICATION);
-        mc.register::<ExtruderV2>(ExtruderV2::MACHINE_IDENTIFICATION);
-        #[cfg(feature = "mock-machine")]
-        mc.register::<MockMachine>(MockMachine::MACHINE_IDENTIFICATION);
-        mc.register::<BufferV1>(BufferV1::MACHINE_IDENTIFICATION);
-        mc.register::<AquaPathV1>(AquaPathV1::MACHINE_IDENTIFICATION);
+        if let Err(e) = mc.register_plugin(&QiTechMachines) {
+            tracing::error!("{:?}", e);
+        }
+        // machines of crates linked into the server, e.g. proprietary machines of integrators
+        for plugin in MachineRegistry::submitted_plugins() {
+            match mc.register_plugin(plugin) {
+                Ok(()) => tracing::info!("Registered machine plugin {}", plugin.name()),
+                Err(e) => tracing::error!("{:?}", e),
+            }
+        }
         mc
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use control_core::machines::identification::MachineIdentification;
+
+    /// Registers a new machine type and one QiTech already registers
+    struct ConflictingMachines;
+
+    const ACME_WINDER: MachineIdentification = MachineIdentification {
+        vendor: 0xAC,
+        machine: 1,
+    };
+
+    impl MachinePlugin for ConflictingMachines {
+        fn name(&self) -> &'static str {
+            "conflicting"
+        }
+
+        fn register(&self, registry: &mut MachineRegistry) {
+            registry.register::<Winder2>(ACME_WINDER);
+            registry.register::<LaserMachine>(LaserMachine::MACHINE_IDENTIFICATION);
+        }
+    }
+
+    #[test]
+    fn test_duplicate_machine_identification() {
+        let mut registry = MachineRegistry::new();
+        registry.register_plugin(&QiTechMachines).unwrap();
+        let registered = registry.api_schemas().len();
+
+        assert!(registry.register_plugin(&QiTechMachines).is_err());
+        // nothing of the plugin is registered, not even its new machine type
+        assert!(registry.register_plugin(&ConflictingMachines).is_err());
+        assert_eq!(registry.api_schemas().len(), registered);
+        assert!(
+            !registry
+                .api_schemas()
+                .iter()
+                .any(|(machine_identification, _)| *machine_identification == ACME_WINDER)
+        );
+    }
+}
@@ -1,9 +1,72 @@
-use super::Winder2;
+use super::{Winder2, Winder2Mode};
 use control_core::machines::new::MachineAct;
-use std::time::{Duration, Instant};
+use control_core::uom_extensions::velocity::meter_per_minute;
+use std::time::Instant;
+use uom::si::angular_velocity::revolution_per_minute;
 
 impl MachineAct for Winder2 {
     fn act(&mut self, now: Instant) {
+        // micro-moves the motors and switches the loopback outputs during commissioning
+        self.act_self_test(now);
+
+        // the motion thread syncs the speeds if it runs
+        if !self.dedicated_motion {
+            self.act_motion(now);
+        }
+
+        // automatically stops or pulls after N Meters if enabled
+        self.stop_or_pull_spool(now);
+
+        // positions the diameter measurements on the line
+        self.sync_line_position(now);
+
+        // holds in time to stop at the target length of the length ramp
+        self.sync_length_ramp(now);
+
+        // maps the tolerance measured at the laser to the spool
+        self.sync_length_correlation(now);
+
+        // ends the cutter pulse
+        self.sync_cutter(now);
+
+        // counts run time and produced length
+        self.sync_production(now);
+
+        // counts the wear until the next service
+        self.sync_maintenance(now);
+
+        // compares the spool speed to the pulled length
+        self.detect_spool_slip(now);
+
+        // stops on a jammed spool or puller
+        self.monitor_motor_load(now);
+
+        // caps the line speed while the water bath is too hot
+        self.apply_speed_derating(now);
+
+        // restricts the mutations of the line during remote support
+        self.publish_remote_key_switch(now);
+
+        if self.traverse_controller.did_change_state() {
+            self.emit_state();
+        }
+
+        // the namespace limits the emit rate
+        self.emit_live_values();
+        self.emit_production();
+        self.emit_maintenance();
+        self.emit_puller_axes();
+
+        // keeps the wound length across crashes and restarts
+        self.sync_journal(now);
+    }
+
+    fn act_motion(&mut self, now: Instant) {
+        // the self test drives the motors on its own
+        if self.is_self_test_running() {
+            return;
+        }
+
         // sync the spool speed
         self.sync_spool_speed(now);
 
@@ -12,18 +75,36 @@ impl MachineAct for Winder2 {
 
         // sync the traverse speed
         self.sync_traverse_speed();
+    }
 
-        // automatically stops or pulls after N Meters if enabled
-        self.stop_or_pull_spool(now);
-
-        if self.traverse_controller.did_change_state() {
-            self.emit_state();
-        }
+    fn has_dedicated_motion(&self) -> bool {
+        self.dedicated_motion
+    }
 
-        // more than 33ms have passed since last emit (30 "fps" target)
-        if now.duration_since(self.last_measurement_emit) > Duration::from_secs_f64(1.0 / 30.0) {
-            self.emit_live_values();
-            self.last_measurement_emit = now;
+    fn act_safe_stop(&mut self) -> bool {
+        match self.mode {
+            // hold keeps the motors enabled while spool and puller ramp down
+            Winder2Mode::Wind | Winder2Mode::Pull => {
+                self.set_mode(&Winder2Mode::Hold);
+                false
+            }
+            Winder2Mode::Hold => {
+                let spool_rpm = self
+                    .spool_speed_controller
+                    .get_speed()
+                    .get::<revolution_per_minute>();
+                let puller_speed = self
+                    .puller_speed_controller
+                    .last_speed
+                    .get::<meter_per_minute>();
+                if spool_rpm.abs() < 0.1 && puller_speed.abs() < 0.01 {
+                    self.set_mode(&Winder2Mode::Standby);
+                    true
+                } else {
+                    false
+                }
+            }
+            Winder2Mode::Standby => true,
         }
     }
 }
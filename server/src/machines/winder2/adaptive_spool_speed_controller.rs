@@ -418,6 +418,15 @@ impl AdaptiveSpoolSpeedController {
         self.speed_factor
     }
 
+    /// Restores a previously learned speed factor, e.g. after a restart mid-spool
+    pub fn set_speed_factor(&mut self, speed_factor: Length) {
+        self.speed_factor = Length::new::<centimeter>(
+            speed_factor
+                .get::<centimeter>()
+                .clamp(Self::FACTOR_MIN, Self::FACTOR_MAX),
+        );
+    }
+
     // Getters and setters for the new configurable parameters
     pub const fn get_tension_target(&self) -> f64 {
         self.tension_target
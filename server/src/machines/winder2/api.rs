@@ -1,19 +1,37 @@
-use super::{Winder2, Winder2Mode, puller_speed_controller::PullerRegulationMode};
+use super::{
+    Winder2, Winder2Mode,
+    diameter_loop::DiameterLoopMode,
+    machine_config::Winder2Config,
+    puller_speed_controller::PullerRegulationMode,
+    puller_wear::PullerCalibration,
+    spool::SpoolType,
+    traverse_controller::HomingStatus,
+    winding_pattern::{WindingPattern, WindingPatternPlanner},
+};
+use crate::machines::{
+    gain_history::GainHistoryEntry,
+    maintenance::{MaintenanceEvent, MaintenancePart},
+};
 use control_core::{
+    alarms::{AlarmCondition, AlarmSeverity},
     machines::{
-        api::MachineApi, connection::MachineCrossConnectionState,
-        identification::MachineIdentificationUnique,
+        api::MachineApi,
+        connection::MachineCrossConnectionState,
+        identification::{MachineCapabilities, MachineIdentificationUnique},
+        schema::MachineApiTypes,
+        self_test::SelfTestReport,
     },
+    rest::unit_value::UnitValue,
     socketio::{
-        event::{Event, GenericEvent},
-        namespace::{
-            CacheFn, CacheableEvents, Namespace, NamespaceCacheingLogic, cache_duration,
-            cache_first_and_last_event,
-        },
+        event::Event,
+        namespace::Namespace,
+        units::{DisplayQuantity, EventUnits, FieldSchema, SchemaEvent},
     },
 };
 
-use control_core_derive::BuildEvent;
+use control_core::uom_extensions::velocity::meter_per_minute;
+use control_core_derive::{BuildEvent, NamespaceEvents};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use smol::lock::Mutex;
@@ -21,9 +39,16 @@ use std::{
     sync::Arc,
     time::{Duration, Instant},
 };
-use tracing::instrument;
+use uom::si::{
+    angle::degree,
+    angular_velocity::revolution_per_minute,
+    electric_current::ampere,
+    f64::Length,
+    length::{meter, millimeter},
+    time::second,
+};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub enum Mode {
     Standby,
     Hold,
@@ -53,34 +78,59 @@ impl From<Mode> for Winder2Mode {
     }
 }
 
-#[derive(Deserialize, Serialize)]
-enum Mutation {
+#[derive(Deserialize, Serialize, JsonSchema)]
+pub enum Mutation {
     // Traverse
-    /// Position in mm from home point
-    SetTraverseLimitOuter(f64),
-    /// Position in mm from home point
-    SetTraverseLimitInner(f64),
-    /// Step size in mm for traverse movement
-    SetTraverseStepSize(f64),
-    /// Padding in mm for traverse movement limits
-    SetTraversePadding(f64),
+    /// Position from home point, bare values in mm
+    SetTraverseLimitOuter(UnitValue),
+    /// Position from home point, bare values in mm
+    SetTraverseLimitInner(UnitValue),
+    /// Step size for traverse movement, bare values in mm
+    SetTraverseStepSize(UnitValue),
+    /// Padding for traverse movement limits, bare values in mm
+    SetTraversePadding(UnitValue),
+    SetTraverseWindingPattern(WindingPattern),
+    /// Crossing angle of the wild wind pattern in degrees
+    SetTraverseCrossingAngle(f64),
     GotoTraverseLimitOuter,
     GotoTraverseLimitInner,
     /// Find home point
     GotoTraverseHome,
+    /// Stop homing, the traverse is not homed afterwards
+    AbortTraverseHome,
     EnableTraverseLaserpointer(bool),
 
     // Puller
     /// on = speed, off = stop
     SetPullerRegulationMode(PullerRegulationMode),
-    SetPullerTargetSpeed(f64),
-    SetPullerTargetDiameter(f64),
+    /// Bare values in m/min
+    SetPullerTargetSpeed(UnitValue),
+    /// Bare values in mm
+    SetPullerTargetDiameter(UnitValue),
     SetPullerForward(bool),
+    /// Speed of one strand relative to the puller speed
+    SetPullerStrandTrim(StrandTrim),
+    /// Gains of the diameter regulation, changed without a jump of the speed
+    SetPullerDiameterLoopGains(DiameterLoopGains),
+    /// Goes back to the gains of the diameter regulation before their last change
+    RevertPullerDiameterLoopGains,
+    /// Switches the diameter regulation between PID and operator without a speed jump
+    SetPullerDiameterLoopMode(DiameterLoopMode),
+    /// Line speed while the diameter regulation is manual, bare values in m/min
+    SetPullerManualSpeed(UnitValue),
+    /// Adjusts the puller circumference to the wear of the wheel
+    CalibratePullerLength(PullerLengthCalibration),
+    /// Speed of the second puller axis relative to the first one
+    SetPullerSecondAxisTrim(f64),
+    /// Balances the motor currents of both puller axes
+    SetPullerLoadSharing(bool),
 
     // Spool Speed Controller
     SetSpoolRegulationMode(super::spool_speed_controller::SpoolSpeedControllerType),
-    SetSpoolMinMaxMinSpeed(f64),
-    SetSpoolMinMaxMaxSpeed(f64),
+    /// Bare values in rpm
+    SetSpoolMinMaxMinSpeed(UnitValue),
+    /// Bare values in rpm
+    SetSpoolMinMaxMaxSpeed(UnitValue),
 
     // Adaptive Spool Speed Controller Parameters
     SetSpoolAdaptiveTensionTarget(f64),
@@ -90,9 +140,37 @@ enum Mutation {
     SetSpoolAdaptiveDeaccelerationUrgencyMultiplier(f64),
 
     // Spool Auto Stop/Pull
-    SetSpoolAutomaticRequiredMeters(f64),
+    /// Bare values in m
+    SetSpoolAutomaticRequiredMeters(UnitValue),
     SetSpoolAutomaticAction(SpoolAutomaticActionMode),
     ResetSpoolProgress,
+    /// Winds the given length and stops at it, bare values in m; setting a mode cancels it
+    StartLengthRamp(UnitValue),
+    /// Filament length from the laser to the spool, bare values in m
+    SetSensorOffset(UnitValue),
+
+    // Spool
+    /// Name of a stored spool type, fails if the traverse limits are wider than the spool
+    SelectSpoolType(String),
+    ClearSpoolType,
+
+    // Production
+    /// Starts the production counters of a new shift
+    ResetShiftCounters,
+
+    // Maintenance
+    /// Starts counting the wear again after the part was serviced
+    ResetMaintenanceCounter(MaintenancePart),
+
+    // Cutter
+    /// Pulses the cutter output, fails if the guard is open or the line is too slow
+    Cut,
+    /// Bare values in ms
+    SetCutterPulseTime(UnitValue),
+    /// Line speed required for a cut, bare values in m/min
+    SetCutterMinLineSpeed(UnitValue),
+    /// Cut when the automatic spool action triggers
+    SetCutOnSpoolChange(bool),
 
     // Tension Arm
     ZeroTensionArmAngle,
@@ -107,7 +185,7 @@ enum Mutation {
     DisconnectMachine(MachineIdentificationUnique),
 }
 
-#[derive(Serialize, Debug, Clone, Default)]
+#[derive(Serialize, Debug, Clone, Default, BuildEvent, JsonSchema)]
 pub struct LiveValuesEvent {
     /// traverse position in mm
     pub traverse_position: Option<f64>,
@@ -115,19 +193,169 @@ pub struct LiveValuesEvent {
     pub puller_speed: f64,
     /// spool rpm
     pub spool_rpm: f64,
+    /// current drawn by the spool motor in A, missing if the terminal doesn't report it
+    pub spool_current: Option<f64>,
+    /// current drawn by the puller motor in A
+    pub puller_current: Option<f64>,
     /// tension arm angle in degrees
     pub tension_arm_angle: f64,
     // spool progress in meters (pulled distance of filament)
     pub spool_progress: f64,
+    /// share of the spool capacity wound (0.0-1.0), missing without a spool type
+    pub spool_fill: Option<f64>,
+    /// time until the spool is full or the automatic action triggers in s at the current
+    /// line speed, missing while the line stands or without a spool type and automatic action
+    pub spool_time_to_full: Option<f64>,
+    /// length wound by the length ramp in m, missing without one
+    pub ramp_length: Option<f64>,
+    /// traverse movement per spool revolution in mm
+    pub traverse_pitch: f64,
+    /// crossing angle of the winding pattern in degrees
+    pub crossing_angle: f64,
+    /// speed of every strand in m/min
+    pub strand_speeds: Vec<f64>,
+    /// outer loop of the puller, diameter regulation
+    pub diameter_loop: DiameterLoopValues,
+    /// inner loop of the puller, speed ramp
+    pub speed_loop: SpeedLoopValues,
+    pub units: EventUnits,
+}
+
+#[derive(Serialize, Debug, Clone, Default, JsonSchema)]
+pub struct DiameterLoopValues {
+    /// loop is closed
+    pub active: bool,
+    /// measured diameter in mm, missing without a recent measurement
+    pub measured_diameter: Option<f64>,
+    /// measured minus target diameter in mm
+    pub error: Option<f64>,
+    /// line speed setpoint for the speed loop in m/min
+    pub speed_setpoint: f64,
+}
+
+#[derive(Serialize, Debug, Clone, Default, JsonSchema)]
+pub struct SpeedLoopValues {
+    /// line speed setpoint in m/min
+    pub setpoint: f64,
+    /// ramped line speed commanded to the motor in m/min
+    pub speed: f64,
 }
 
 impl LiveValuesEvent {
-    pub fn build(&self) -> Event<Self> {
-        Event::new("LiveValuesEvent", self.clone())
+    pub const UNITS: EventUnits = EventUnits(&[
+        ("traverse_position", DisplayQuantity::Position),
+        ("puller_speed", DisplayQuantity::LineSpeed),
+        ("spool_progress", DisplayQuantity::FilamentLength),
+        ("ramp_length", DisplayQuantity::FilamentLength),
+        ("traverse_pitch", DisplayQuantity::Position),
+        ("strand_speeds", DisplayQuantity::LineSpeed),
+        ("diameter_loop.measured_diameter", DisplayQuantity::Diameter),
+        ("diameter_loop.error", DisplayQuantity::Diameter),
+        ("diameter_loop.speed_setpoint", DisplayQuantity::LineSpeed),
+        ("speed_loop.setpoint", DisplayQuantity::LineSpeed),
+        ("speed_loop.speed", DisplayQuantity::LineSpeed),
+    ]);
+
+    pub fn schema() -> SchemaEvent {
+        let line_speed = || FieldSchema::display(DisplayQuantity::LineSpeed).min(0.0);
+        let position = || FieldSchema::display(DisplayQuantity::Position).min(0.0);
+        SchemaEvent::new([
+            ("traverse_position", position()),
+            ("puller_speed", line_speed()),
+            ("spool_rpm", FieldSchema::of::<revolution_per_minute>()),
+            ("spool_current", FieldSchema::of::<ampere>().min(0.0)),
+            ("puller_current", FieldSchema::of::<ampere>().min(0.0)),
+            (
+                "tension_arm_angle",
+                FieldSchema::of::<degree>().range(0.0, 360.0),
+            ),
+            (
+                "spool_progress",
+                FieldSchema::display(DisplayQuantity::FilamentLength).min(0.0),
+            ),
+            ("spool_time_to_full", FieldSchema::of::<second>().min(0.0)),
+            ("traverse_pitch", position()),
+            (
+                "crossing_angle",
+                FieldSchema::of::<degree>()
+                    .range(0.0, WindingPatternPlanner::MAX_CROSSING_ANGLE_DEG),
+            ),
+            ("strand_speeds", line_speed()),
+            (
+                "diameter_loop.measured_diameter",
+                FieldSchema::display(DisplayQuantity::Diameter).min(0.0),
+            ),
+            (
+                "diameter_loop.error",
+                FieldSchema::display(DisplayQuantity::Diameter),
+            ),
+            ("diameter_loop.speed_setpoint", line_speed()),
+            ("speed_loop.setpoint", line_speed()),
+            ("speed_loop.speed", line_speed()),
+        ])
     }
 }
 
-#[derive(Serialize, Debug, Clone, BuildEvent)]
+#[derive(Serialize, Debug, Clone, BuildEvent, JsonSchema)]
+pub struct ProductionEvent {
+    /// counters since the last shift reset
+    pub shift: ProductionValues,
+    /// counters since the winder was set up
+    pub lifetime: ProductionValues,
+    /// unix time in ms the shift counters were reset
+    pub shift_started_at: u64,
+    pub units: EventUnits,
+}
+
+impl ProductionEvent {
+    pub const UNITS: EventUnits = EventUnits(&[
+        ("shift.produced_length", DisplayQuantity::FilamentLength),
+        ("shift.scrap_length", DisplayQuantity::FilamentLength),
+        ("lifetime.produced_length", DisplayQuantity::FilamentLength),
+        ("lifetime.scrap_length", DisplayQuantity::FilamentLength),
+    ]);
+}
+
+/// Status of every puller axis, the first one followed by the second one of a dual puller
+#[derive(Serialize, Debug, Clone, BuildEvent, JsonSchema)]
+pub struct PullerAxesEvent {
+    pub axes: Vec<PullerAxisValues>,
+}
+
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+pub struct PullerAxisValues {
+    /// whether the motor is powered
+    pub enabled: bool,
+    /// speed of the motor in m/min
+    pub speed: f64,
+    /// speed relative to the first axis, including the load sharing correction
+    pub ratio: f64,
+    /// current drawn by the motor in A, missing if the terminal doesn't report it
+    pub current: Option<f64>,
+}
+
+/// OEE components are missing until their period has data
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+pub struct ProductionValues {
+    /// time the puller ran in s
+    pub run_time: f64,
+    /// time the puller stood still outside of standby in s
+    pub stopped_time: f64,
+    /// pulled length in m
+    pub produced_length: f64,
+    /// length pulled out of tolerance in m
+    pub scrap_length: f64,
+    /// share of the planned time running (0.0-1.0)
+    pub availability: Option<f64>,
+    /// produced length relative to the target speed (0.0-1.0)
+    pub performance: Option<f64>,
+    /// share of the produced length in tolerance (0.0-1.0)
+    pub quality: Option<f64>,
+    /// overall equipment effectiveness (0.0-1.0)
+    pub oee: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone, BuildEvent, JsonSchema)]
 pub struct StateEvent {
     pub is_default_state: bool,
     /// traverse state
@@ -136,17 +364,55 @@ pub struct StateEvent {
     pub puller_state: PullerState,
     /// spool automatic action state and progress
     pub spool_automatic_action_state: SpoolAutomaticActionState,
+    /// selected spool type and its capacity
+    pub spool_state: SpoolState,
+    /// current spool and where measured filament ends up
+    pub spool_tracking_state: SpoolTrackingState,
     /// mode state
     pub mode_state: ModeState,
     /// tension arm state
     pub tension_arm_state: TensionArmState,
     /// spool speed controller state
     pub spool_speed_controller_state: SpoolSpeedControllerState,
+    /// cutter state
+    pub cutter_state: CutterState,
     /// connected machine state
     pub connected_machine_state: MachineCrossConnectionState,
+    /// line speed cap while a temperature is too high, not derated if `None`
+    pub speed_derating: Option<SpeedDeratingState>,
+    /// winding a fixed length, missing without one
+    pub length_ramp: Option<LengthRampState>,
+    pub units: EventUnits,
 }
 
-#[derive(Serialize, Debug, Clone)]
+impl StateEvent {
+    pub const UNITS: EventUnits = EventUnits(&[
+        ("traverse_state.limit_inner", DisplayQuantity::Position),
+        ("traverse_state.limit_outer", DisplayQuantity::Position),
+        ("traverse_state.position_in", DisplayQuantity::Position),
+        ("traverse_state.position_out", DisplayQuantity::Position),
+        ("traverse_state.step_size", DisplayQuantity::Position),
+        ("traverse_state.padding", DisplayQuantity::Position),
+        ("traverse_state.travel", DisplayQuantity::Position),
+        ("puller_state.target_speed", DisplayQuantity::LineSpeed),
+        ("puller_state.target_diameter", DisplayQuantity::Diameter),
+        ("puller_state.manual_speed", DisplayQuantity::LineSpeed),
+        ("cutter_state.min_line_speed", DisplayQuantity::LineSpeed),
+        ("speed_derating.max_line_speed", DisplayQuantity::LineSpeed),
+        ("length_ramp.target_length", DisplayQuantity::FilamentLength),
+        ("spool_state.capacity", DisplayQuantity::FilamentLength),
+        (
+            "spool_tracking_state.sensor_offset",
+            DisplayQuantity::FilamentLength,
+        ),
+        (
+            "spool_automatic_action_state.spool_required_meters",
+            DisplayQuantity::FilamentLength,
+        ),
+    ]);
+}
+
+#[derive(Serialize, Debug, Clone, JsonSchema)]
 pub struct TraverseState {
     /// min position in mm
     pub limit_inner: f64,
@@ -164,6 +430,10 @@ pub struct TraverseState {
     pub is_homed: bool,
     /// if is homing
     pub is_going_home: bool,
+    /// homing progress
+    pub homing_status: HomingStatus,
+    /// soft limit in mm, the traverse never moves further out than this from the end stop
+    pub travel: f64,
     /// if is traversing
     pub is_traversing: bool,
     /// laserpointer is on
@@ -172,6 +442,10 @@ pub struct TraverseState {
     pub step_size: f64,
     /// padding in mm
     pub padding: f64,
+    /// winding pattern
+    pub winding_pattern: WindingPattern,
+    /// crossing angle of the wild wind pattern in degrees
+    pub crossing_angle: f64,
     /// can go in (to inner limit)
     pub can_go_in: bool,
     /// can go out (to outer limit)
@@ -180,7 +454,7 @@ pub struct TraverseState {
     pub can_go_home: bool,
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, JsonSchema)]
 pub struct PullerState {
     /// regulation type
     pub regulation: PullerRegulationMode,
@@ -190,22 +464,124 @@ pub struct PullerState {
     pub target_diameter: f64,
     /// forward rotation direction
     pub forward: bool,
+    /// speed of every strand relative to the puller speed
+    pub strand_trims: Vec<f64>,
+    /// gains of the diameter regulation
+    pub diameter_loop_gains: DiameterLoopGains,
+    /// gains the diameter regulation had before, newest first
+    pub diameter_loop_gain_history: Vec<GainHistoryEntry<DiameterLoopGains>>,
+    /// whether the PID or the operator sets the speed in diameter regulation
+    pub diameter_loop_mode: DiameterLoopMode,
+    /// line speed of the manual diameter regulation in m/min, the target speed until set
+    pub manual_speed: Option<f64>,
+    /// wear of the puller wheel measured by the length calibrations
+    pub wear: PullerWearState,
+    /// second puller axis, missing on a single puller
+    pub second_axis: Option<SecondPullerState>,
+}
+
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+pub struct SecondPullerState {
+    /// speed relative to the first axis
+    pub trim: f64,
+    /// whether the trim is corrected to balance the motor currents
+    pub load_sharing: bool,
+}
+
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+pub struct PullerWearState {
+    /// effective over nominal circumference of the wheel
+    pub factor: f64,
+    /// diameter the wheel pulls with in mm
+    pub effective_diameter: f64,
+    /// diameter the wheel lost in mm
+    pub wear: f64,
+    /// calibrations since the wheel was replaced, oldest first
+    pub history: Vec<PullerCalibration>,
+}
+
+/// Length the winder counted and the operator measured, e.g. on a pulled off sample
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+pub struct PullerLengthCalibration {
+    /// Bare values in m
+    pub counted: UnitValue,
+    /// Bare values in m
+    pub measured: UnitValue,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct SpeedDeratingState {
+    /// why the line speed is derated, e.g. `water bath above 40 °C`
+    pub reason: String,
+    /// max line speed in m/min
+    pub max_line_speed: f64,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum LengthRampPhase {
+    /// ramping up or running at the target speed
+    Running,
+    /// holding, the line ramps down to the target length
+    Stopping,
+    /// the line stands at the target length
+    Done,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct LengthRampState {
+    /// length to wind in m
+    pub target_length: f64,
+    pub phase: LengthRampPhase,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct DiameterLoopGains {
+    /// m/min per mm of diameter error
+    pub kp: f64,
+    /// m/min per mm·s of diameter error
+    pub ki: f64,
+    /// m/min per mm/s of diameter error
+    pub kd: f64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+pub struct StrandTrim {
+    /// strand index starting at 0
+    pub strand: usize,
+    /// speed relative to the puller speed
+    pub trim: f64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
 pub enum SpoolAutomaticActionMode {
     NoAction,
     Pull,
     Hold,
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, JsonSchema)]
 pub struct SpoolAutomaticActionState {
     pub spool_required_meters: f64,
     pub spool_automatic_action_mode: SpoolAutomaticActionMode,
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+pub struct SpoolState {
+    /// selected spool type, missing if none is selected
+    pub spool_type: Option<SpoolType>,
+    /// filament length the spool holds at the target diameter in m
+    pub capacity: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+pub struct SpoolTrackingState {
+    /// number of the current spool, counts up with every spool change
+    pub spool: u32,
+    /// filament length from the laser to the spool in m
+    pub sensor_offset: f64,
+}
+
+#[derive(Serialize, Debug, Clone, JsonSchema)]
 pub struct ModeState {
     /// mode
     pub mode: Mode,
@@ -213,13 +589,29 @@ pub struct ModeState {
     pub can_wind: bool,
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+pub struct CutterState {
+    /// pulse time in ms
+    pub pulse_time: f64,
+    /// line speed required for a cut in m/min
+    pub min_line_speed: f64,
+    /// cut when the automatic spool action triggers
+    pub cut_on_spool_change: bool,
+    /// guard door contact is closed
+    pub guard_closed: bool,
+    /// cutter output is on
+    pub is_cutting: bool,
+    /// can cut
+    pub can_cut: bool,
+}
+
+#[derive(Serialize, Debug, Clone, JsonSchema)]
 pub struct TensionArmState {
     /// is zeroed
     pub zeroed: bool,
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, JsonSchema)]
 pub struct SpoolSpeedControllerState {
     /// regulation mode
     pub regulation_mode: super::spool_speed_controller::SpoolSpeedControllerType,
@@ -239,9 +631,112 @@ pub struct SpoolSpeedControllerState {
     pub adaptive_deacceleration_urgency_multiplier: f64,
 }
 
+/// Winder section of a recipe
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct Winder2Recipe {
+    /// puller regulation (diameter control strategy)
+    pub puller_regulation: PullerRegulationMode,
+    /// puller target speed in m/min
+    pub puller_target_speed: f64,
+    /// puller target diameter in mm
+    pub puller_target_diameter: f64,
+    /// lower tolerance of the diameter in mm, passed on to the laser with the target diameter
+    #[serde(default)]
+    pub diameter_lower_tolerance: Option<f64>,
+    /// higher tolerance of the diameter in mm, set together with the lower tolerance
+    #[serde(default)]
+    pub diameter_higher_tolerance: Option<f64>,
+    /// spool regulation mode
+    pub spool_regulation_mode: super::spool_speed_controller::SpoolSpeedControllerType,
+    /// min speed in rpm for minmax mode
+    pub spool_minmax_min_speed: f64,
+    /// max speed in rpm for minmax mode
+    pub spool_minmax_max_speed: f64,
+    /// inner traverse limit in mm
+    pub traverse_limit_inner: f64,
+    /// outer traverse limit in mm
+    pub traverse_limit_outer: f64,
+    /// traverse step size in mm
+    pub traverse_step_size: f64,
+    /// traverse padding in mm
+    pub traverse_padding: f64,
+    /// winding pattern, precision if missing
+    #[serde(default)]
+    pub traverse_winding_pattern: WindingPattern,
+    /// crossing angle of the wild wind pattern in degrees
+    #[serde(default = "default_crossing_angle")]
+    pub traverse_crossing_angle: f64,
+}
+
+const fn default_crossing_angle() -> f64 {
+    WindingPatternPlanner::DEFAULT_CROSSING_ANGLE_DEG
+}
+
+impl Winder2Recipe {
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.puller_target_speed < 0.0 {
+            return Err(anyhow::anyhow!("Puller target speed must not be negative"));
+        }
+        match (
+            self.diameter_lower_tolerance,
+            self.diameter_higher_tolerance,
+        ) {
+            (Some(lower), Some(higher)) if lower < 0.0 || higher < 0.0 => {
+                return Err(anyhow::anyhow!("Diameter tolerances must not be negative"));
+            }
+            (Some(_), None) | (None, Some(_)) => {
+                return Err(anyhow::anyhow!(
+                    "Lower and higher diameter tolerance must be set together"
+                ));
+            }
+            _ => {}
+        }
+        if self.spool_minmax_min_speed < 0.0
+            || self.spool_minmax_min_speed > self.spool_minmax_max_speed
+        {
+            return Err(anyhow::anyhow!(
+                "Spool min speed must be between 0 and the max speed"
+            ));
+        }
+        if !Winder2::validate_traverse_limits(
+            Length::new::<millimeter>(self.traverse_limit_inner),
+            Length::new::<millimeter>(self.traverse_limit_outer),
+        ) {
+            return Err(anyhow::anyhow!(
+                "Outer traverse limit must be at least 0.9mm larger than the inner limit"
+            ));
+        }
+        if self.traverse_step_size <= 0.0 || self.traverse_padding < 0.0 {
+            return Err(anyhow::anyhow!(
+                "Traverse step size must be positive and padding must not be negative"
+            ));
+        }
+        if !WindingPatternPlanner::is_valid_crossing_angle(self.traverse_crossing_angle) {
+            return Err(anyhow::anyhow!(
+                "Traverse crossing angle must be between {}° and {}°",
+                WindingPatternPlanner::MIN_CROSSING_ANGLE_DEG,
+                WindingPatternPlanner::MAX_CROSSING_ANGLE_DEG
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(NamespaceEvents)]
+#[namespace_events(namespace = Winder2Namespace)]
 pub enum Winder2Events {
+    #[cache(duration_secs = 3600)]
     LiveValues(Event<LiveValuesEvent>),
+    #[cache(one)]
+    Schema(Event<SchemaEvent>),
+    #[cache(first_and_last)]
     State(Event<StateEvent>),
+    #[cache(first_and_last)]
+    Production(Event<ProductionEvent>),
+    #[cache(first_and_last)]
+    Maintenance(Event<MaintenanceEvent>),
+    #[cache(first_and_last)]
+    PullerAxes(Event<PullerAxesEvent>),
 }
 
 #[derive(Debug)]
@@ -249,56 +744,77 @@ pub struct Winder2Namespace {
     pub namespace: Arc<Mutex<Namespace>>,
 }
 
-impl NamespaceCacheingLogic<Winder2Events> for Winder2Namespace {
-    #[instrument(skip_all)]
-    fn emit(&mut self, events: Winder2Events) {
-        let event = Arc::new(events.event_value());
-        let buffer_fn = events.event_cache_fn();
-
-        let mut namespace = self.namespace.lock_blocking();
-        namespace.emit(event, &buffer_fn);
-    }
-}
-
-impl CacheableEvents<Self> for Winder2Events {
-    fn event_value(&self) -> GenericEvent {
-        match self {
-            Self::LiveValues(event) => event.into(),
-            Self::State(event) => event.into(),
-        }
-    }
-
-    fn event_cache_fn(&self) -> CacheFn {
-        let cache_one_hour = cache_duration(Duration::from_secs(60 * 60), Duration::from_secs(1));
-        let cache_first_and_last = cache_first_and_last_event();
-
-        match self {
-            Self::LiveValues(_) => cache_one_hour,
-            Self::State(_) => cache_first_and_last,
-        }
-    }
+impl MachineApiTypes for Winder2 {
+    type Mutation = Mutation;
+    type Events = Winder2Events;
 }
 
 impl MachineApi for Winder2 {
     fn api_mutate(&mut self, request_body: Value) -> Result<(), anyhow::Error> {
-        let mutation: Mutation = serde_json::from_value(request_body)?;
+        let mutation = Self::parse_mutation(request_body)?;
         match mutation {
             Mutation::EnableTraverseLaserpointer(enable) => self.set_laser(enable),
-            Mutation::SetMode(mode) => self.set_mode(&mode.into()),
-            Mutation::SetTraverseLimitOuter(limit) => self.traverse_set_limit_outer(limit),
-            Mutation::SetTraverseLimitInner(limit) => self.traverse_set_limit_inner(limit),
-            Mutation::SetTraverseStepSize(size) => self.traverse_set_step_size(size),
-            Mutation::SetTraversePadding(padding) => self.traverse_set_padding(padding),
+            Mutation::SetMode(mode) => {
+                self.cancel_length_ramp();
+                self.set_mode(&mode.into())
+            }
+            Mutation::SetTraverseLimitOuter(limit) => {
+                self.traverse_set_limit_outer(limit.length("mm")?.get::<millimeter>())
+            }
+            Mutation::SetTraverseLimitInner(limit) => {
+                self.traverse_set_limit_inner(limit.length("mm")?.get::<millimeter>())
+            }
+            Mutation::SetTraverseStepSize(size) => {
+                self.traverse_set_step_size(size.length("mm")?.get::<millimeter>())
+            }
+            Mutation::SetTraversePadding(padding) => {
+                self.traverse_set_padding(padding.length("mm")?.get::<millimeter>())
+            }
+            Mutation::SetTraverseWindingPattern(pattern) => {
+                self.traverse_set_winding_pattern(pattern)
+            }
+            Mutation::SetTraverseCrossingAngle(angle) => self.traverse_set_crossing_angle(angle)?,
             Mutation::GotoTraverseLimitOuter => self.traverse_goto_limit_outer(),
             Mutation::GotoTraverseLimitInner => self.traverse_goto_limit_inner(),
             Mutation::GotoTraverseHome => self.traverse_goto_home(),
+            Mutation::AbortTraverseHome => self.traverse_abort_home(),
             Mutation::SetPullerRegulationMode(regulation) => self.puller_set_regulation(regulation),
-            Mutation::SetPullerTargetSpeed(value) => self.puller_set_target_speed(value),
-            Mutation::SetPullerTargetDiameter(_) => todo!(),
+            Mutation::SetPullerTargetSpeed(value) => {
+                self.puller_set_target_speed(value.velocity("m/min")?.get::<meter_per_minute>())
+            }
+            Mutation::SetPullerTargetDiameter(value) => {
+                self.puller_set_target_diameter(value.length("mm")?.get::<millimeter>())
+            }
             Mutation::SetPullerForward(value) => self.puller_set_forward(value),
+            Mutation::SetPullerStrandTrim(strand_trim) => {
+                self.puller_set_strand_trim(strand_trim)?
+            }
+            Mutation::SetPullerDiameterLoopGains(gains) => {
+                self.puller_set_diameter_loop_gains(gains)?
+            }
+            Mutation::RevertPullerDiameterLoopGains => self.puller_revert_diameter_loop_gains()?,
+            Mutation::SetPullerDiameterLoopMode(mode) => self.puller_set_diameter_loop_mode(mode),
+            Mutation::SetPullerManualSpeed(value) => {
+                self.puller_set_manual_speed(value.velocity("m/min")?.get::<meter_per_minute>())?
+            }
+            Mutation::CalibratePullerLength(calibration) => self.puller_calibrate_length(
+                calibration.counted.length("m")?,
+                calibration.measured.length("m")?,
+                Instant::now(),
+            )?,
+            Mutation::SetPullerSecondAxisTrim(trim) => self.puller_set_second_axis_trim(trim)?,
+            Mutation::SetPullerLoadSharing(enabled) => self.puller_set_load_sharing(enabled)?,
             Mutation::SetSpoolRegulationMode(mode) => self.spool_set_regulation_mode(mode),
-            Mutation::SetSpoolMinMaxMinSpeed(speed) => self.spool_set_minmax_min_speed(speed),
-            Mutation::SetSpoolMinMaxMaxSpeed(speed) => self.spool_set_minmax_max_speed(speed),
+            Mutation::SetSpoolMinMaxMinSpeed(speed) => self.spool_set_minmax_min_speed(
+                speed
+                    .angular_velocity("rpm")?
+                    .get::<revolution_per_minute>(),
+            ),
+            Mutation::SetSpoolMinMaxMaxSpeed(speed) => self.spool_set_minmax_max_speed(
+                speed
+                    .angular_velocity("rpm")?
+                    .get::<revolution_per_minute>(),
+            ),
             Mutation::SetSpoolAdaptiveTensionTarget(value) => {
                 self.spool_set_adaptive_tension_target(value)
             }
@@ -314,11 +830,31 @@ impl MachineApi for Winder2 {
             Mutation::SetSpoolAdaptiveDeaccelerationUrgencyMultiplier(value) => {
                 self.spool_set_adaptive_deacceleration_urgency_multiplier(value)
             }
-            Mutation::SetSpoolAutomaticRequiredMeters(meters) => {
-                self.set_spool_automatic_required_meters(meters)
+            Mutation::SetSpoolAutomaticRequiredMeters(length) => {
+                self.set_spool_automatic_required_meters(length.length("m")?.get::<meter>())
             }
             Mutation::SetSpoolAutomaticAction(mode) => self.set_spool_automatic_mode(mode),
             Mutation::ResetSpoolProgress => self.stop_or_pull_spool_reset(Instant::now()),
+            Mutation::StartLengthRamp(length) => {
+                self.start_length_ramp(length.length("m")?, Instant::now())?
+            }
+            Mutation::SetSensorOffset(offset) => {
+                self.set_sensor_offset(offset.length("m")?.get::<meter>())?
+            }
+            Mutation::SelectSpoolType(name) => self.spool_select_type(&name)?,
+            Mutation::ClearSpoolType => self.spool_clear_type(),
+            Mutation::ResetShiftCounters => self.reset_shift_counters(Instant::now()),
+            Mutation::ResetMaintenanceCounter(part) => {
+                self.reset_maintenance_counter(part, Instant::now())?
+            }
+            Mutation::Cut => self.cut(Instant::now())?,
+            Mutation::SetCutterPulseTime(time) => self.cutter_set_pulse_time(
+                Duration::try_from_secs_f64(time.time("ms")?.get::<second>())?,
+            )?,
+            Mutation::SetCutterMinLineSpeed(speed) => {
+                self.cutter_set_min_line_speed(speed.velocity("m/min")?.get::<meter_per_minute>())?
+            }
+            Mutation::SetCutOnSpoolChange(enabled) => self.cutter_set_cut_on_spool_change(enabled),
             Mutation::ZeroTensionArmAngle => self.tension_arm_zero(),
             Mutation::SetConnectedMachine(machine_identification_unique) => {
                 self.set_connected_buffer(machine_identification_unique)
@@ -333,4 +869,111 @@ impl MachineApi for Winder2 {
     fn api_event_namespace(&mut self) -> Arc<Mutex<Namespace>> {
         self.namespace.namespace.clone()
     }
+
+    fn api_capabilities(&self) -> MachineCapabilities {
+        MachineCapabilities::default()
+            .with(MachineCapabilities::HAS_TRAVERSE, true)
+            .with(MachineCapabilities::HAS_TENSION_SENSOR, true)
+            .with(
+                MachineCapabilities::HAS_CUTTER,
+                self.io.output(Self::IO_CUTTER).is_some(),
+            )
+    }
+
+    fn api_alarms(&self) -> Vec<AlarmCondition> {
+        let mut alarms = Vec::new();
+        if self.is_filament_broken() {
+            alarms.push(AlarmCondition::new(
+                "filament_break",
+                "Tension arm dropped, filament may be broken",
+                AlarmSeverity::Critical,
+            ));
+        }
+        if self.puller_speed_controller.is_diameter_signal_lost() {
+            alarms.push(AlarmCondition::new(
+                "diameter_signal_lost",
+                "No diameter measurement, the puller holds its speed",
+                AlarmSeverity::Warning,
+            ));
+        }
+        if self.is_motor_overloaded() {
+            alarms.push(AlarmCondition::new(
+                "motor_overload",
+                "A motor drew more than its maximum current and the winder stopped, check for a jam",
+                AlarmSeverity::Critical,
+            ));
+        }
+        if self.is_spool_slipping() {
+            alarms.push(AlarmCondition::new(
+                "spool_slip",
+                "Spool turns faster than the filament is wound, check the belt and the spool clamping",
+                AlarmSeverity::Warning,
+            ));
+        }
+        if self.spool_fill().is_some_and(|fill| fill >= 1.0) {
+            alarms.push(AlarmCondition::new(
+                "spool_full",
+                "Spool is full, change it before the filament runs over the flanges",
+                AlarmSeverity::Warning,
+            ));
+        }
+        if self.puller_wear.is_worn() {
+            alarms.push(AlarmCondition::new(
+                "puller_wheel_worn",
+                format!(
+                    "Puller wheel lost {:.2} mm of its diameter, replace it",
+                    self.puller_wear.wear().get::<millimeter>()
+                ),
+                AlarmSeverity::Warning,
+            ));
+        }
+        alarms.extend(self.maintenance.alarms());
+        alarms
+    }
+
+    fn api_current_mutation(&self, name: &str) -> Option<Value> {
+        let puller = &self.puller_speed_controller;
+        let (value, unit) = match name {
+            "SetPullerTargetSpeed" => (puller.target_speed.get::<meter_per_minute>(), "m/min"),
+            "SetPullerManualSpeed" => (
+                puller
+                    .diameter_loop
+                    .get_manual_speed()
+                    .unwrap_or(puller.target_speed)
+                    .get::<meter_per_minute>(),
+                "m/min",
+            ),
+            "SetSpoolMinMaxMaxSpeed" => (
+                self.spool_speed_controller
+                    .get_minmax_max_speed()
+                    .get::<revolution_per_minute>(),
+                "rpm",
+            ),
+            _ => return None,
+        };
+        Some(serde_json::json!({ name: { "value": value, "unit": unit } }))
+    }
+
+    fn api_export_config(&self) -> Result<Value, anyhow::Error> {
+        Ok(serde_json::to_value(self.export_config())?)
+    }
+
+    fn api_import_config(&mut self, config: Value) -> Result<(), anyhow::Error> {
+        let config: Winder2Config = serde_json::from_value(config)?;
+        self.import_config(&config)
+    }
+
+    fn api_apply_recipe(&mut self, section: Value) -> Result<(), anyhow::Error> {
+        let recipe: Winder2Recipe = serde_json::from_value(section)?;
+        recipe.validate()?;
+        self.apply_recipe(&recipe)
+    }
+
+    fn api_run_self_test(&mut self) -> Result<(), anyhow::Error> {
+        self.run_self_test()
+    }
+
+    fn api_self_test_report(&self) -> Option<SelfTestReport> {
+        self.self_test.as_ref().map(|test| test.report.clone())
+    }
 }
@@ -0,0 +1,188 @@
+use std::time::{Duration, Instant};
+
+use control_core::{
+    rest::mutation::{MutationError, MutationErrorKind},
+    uom_extensions::velocity::meter_per_minute,
+};
+use uom::si::f64::Velocity;
+
+/// Sequences the pulse of the cutter output
+///
+/// A cut is only started while the guard is closed and the line runs at least at the
+/// minimum speed, so the cut end is pulled away from the knife. Opening the guard ends the pulse.
+#[derive(Debug)]
+pub struct Cutter {
+    pulse_time: Duration,
+    min_line_speed: Velocity,
+    /// Cut when the automatic spool action triggers
+    cut_on_spool_change: bool,
+    /// Set while the output is on
+    cutting_until: Option<Instant>,
+}
+
+impl Cutter {
+    pub const MIN_PULSE_TIME: Duration = Duration::from_millis(10);
+    pub const MAX_PULSE_TIME: Duration = Duration::from_secs(5);
+
+    pub fn new() -> Self {
+        Self {
+            pulse_time: Duration::from_millis(200),
+            min_line_speed: Velocity::new::<meter_per_minute>(0.5),
+            cut_on_spool_change: false,
+            cutting_until: None,
+        }
+    }
+
+    pub const fn get_pulse_time(&self) -> Duration {
+        self.pulse_time
+    }
+
+    pub fn set_pulse_time(&mut self, pulse_time: Duration) -> Result<(), anyhow::Error> {
+        if !(Self::MIN_PULSE_TIME..=Self::MAX_PULSE_TIME).contains(&pulse_time) {
+            return Err(anyhow::anyhow!(
+                "[{}::Cutter::set_pulse_time] Pulse time must be between {:?} and {:?}, got {:?}",
+                module_path!(),
+                Self::MIN_PULSE_TIME,
+                Self::MAX_PULSE_TIME,
+                pulse_time
+            ));
+        }
+        self.pulse_time = pulse_time;
+        Ok(())
+    }
+
+    pub fn get_min_line_speed(&self) -> Velocity {
+        self.min_line_speed
+    }
+
+    pub fn set_min_line_speed(&mut self, speed: Velocity) -> Result<(), anyhow::Error> {
+        if !speed.value.is_finite() || speed.value < 0.0 {
+            return Err(anyhow::anyhow!(
+                "[{}::Cutter::set_min_line_speed] Minimum line speed must not be negative",
+                module_path!()
+            ));
+        }
+        self.min_line_speed = speed;
+        Ok(())
+    }
+
+    pub const fn get_cut_on_spool_change(&self) -> bool {
+        self.cut_on_spool_change
+    }
+
+    pub const fn set_cut_on_spool_change(&mut self, enabled: bool) {
+        self.cut_on_spool_change = enabled;
+    }
+
+    pub const fn is_cutting(&self) -> bool {
+        self.cutting_until.is_some()
+    }
+
+    /// Why a cut can't start right now, `None` if it can
+    pub fn interlock(&self, guard_closed: bool, line_speed: Velocity) -> Option<&'static str> {
+        if self.is_cutting() {
+            Some("a cut is already in progress")
+        } else if !guard_closed {
+            Some("the guard is open")
+        } else if line_speed.abs() < self.min_line_speed {
+            Some("the line is slower than the minimum line speed")
+        } else {
+            None
+        }
+    }
+
+    /// Starts the pulse, the output has to be switched on by the caller
+    pub fn start(
+        &mut self,
+        now: Instant,
+        guard_closed: bool,
+        line_speed: Velocity,
+    ) -> Result<(), anyhow::Error> {
+        if let Some(reason) = self.interlock(guard_closed, line_speed) {
+            return Err(MutationError::new(
+                MutationErrorKind::Interlock,
+                format!("[{}::Cutter::start] Can't cut, {}", module_path!(), reason),
+            )
+            .into());
+        }
+        self.cutting_until = Some(now + self.pulse_time);
+        Ok(())
+    }
+
+    /// Ends the pulse after the pulse time or when the guard opens
+    ///
+    /// Returns `true` in the cycle the pulse ended, the output has to be switched off then.
+    pub fn update(&mut self, now: Instant, guard_closed: bool) -> bool {
+        match self.cutting_until {
+            Some(until) if now >= until || !guard_closed => {
+                self.cutting_until = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Default for Cutter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line_speed(speed: f64) -> Velocity {
+        Velocity::new::<meter_per_minute>(speed)
+    }
+
+    #[test]
+    fn test_pulse() {
+        let mut cutter = Cutter::new();
+        let now = Instant::now();
+
+        cutter.start(now, true, line_speed(10.0)).unwrap();
+        assert!(cutter.is_cutting());
+        assert!(cutter.start(now, true, line_speed(10.0)).is_err());
+
+        assert!(!cutter.update(now + Duration::from_millis(199), true));
+        assert!(cutter.is_cutting());
+        assert!(cutter.update(now + Duration::from_millis(200), true));
+        assert!(!cutter.is_cutting());
+        assert!(!cutter.update(now + Duration::from_millis(201), true));
+    }
+
+    #[test]
+    fn test_interlocks() {
+        let mut cutter = Cutter::new();
+        let now = Instant::now();
+
+        let open_guard = cutter.start(now, false, line_speed(10.0)).unwrap_err();
+        assert_eq!(
+            MutationError::kind_of(&open_guard),
+            MutationErrorKind::Interlock
+        );
+        assert!(cutter.start(now, true, line_speed(0.1)).is_err());
+        assert!(!cutter.is_cutting());
+
+        // reverse pulling counts as running
+        cutter.start(now, true, line_speed(-10.0)).unwrap();
+        // opening the guard ends the pulse right away
+        assert!(cutter.update(now, false));
+        assert!(!cutter.is_cutting());
+    }
+
+    #[test]
+    fn test_settings() {
+        let mut cutter = Cutter::new();
+        assert!(cutter.set_pulse_time(Duration::from_millis(1)).is_err());
+        assert!(cutter.set_pulse_time(Duration::from_secs(10)).is_err());
+        cutter.set_pulse_time(Duration::from_millis(500)).unwrap();
+        assert_eq!(cutter.get_pulse_time(), Duration::from_millis(500));
+
+        assert!(cutter.set_min_line_speed(line_speed(-1.0)).is_err());
+        cutter.set_min_line_speed(line_speed(0.0)).unwrap();
+        assert!(cutter.start(Instant::now(), true, line_speed(0.0)).is_ok());
+    }
+}
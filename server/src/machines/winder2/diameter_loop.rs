@@ -0,0 +1,548 @@
+use std::time::{Duration, Instant};
+
+use super::line_position::LineTrail;
+use control_core::{
+    controllers::{biquad::Biquad, pid::PidController},
+    scope::ScopeChannel,
+    uom_extensions::velocity::meter_per_minute,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uom::{
+    ConstZero,
+    si::{
+        f64::{Length, Velocity},
+        length::{centimeter, millimeter},
+        ratio::ratio,
+    },
+};
+
+/// Outer loop of the puller cascade
+///
+/// Turns the diameter error into the line speed setpoint of the jerk limited speed loop in the
+/// [`super::puller_speed_controller::PullerSpeedController`]. The target speed is the feed
+/// forward and the PID corrects around it, a too thick filament is pulled faster.
+///
+/// The gains are in m/min per mm of diameter error.
+///
+/// In [`DiameterLoopMode::Manual`] the operator sets the line speed and the loop only
+/// measures. Switching between the modes is bumpless, see [`DiameterLoop::set_mode`].
+///
+/// The laser measures the filament formed at the nozzle after it travelled to the gauge, so a
+/// correction only shows after that distance. With the distance set the loop remembers its
+/// setpoints by line position and regulates on the diameter the filament has at the nozzle now:
+/// the mass flow stays the same, the diameter scales with the root of the speed ratio between
+/// the setpoint the measured piece was pulled with and the current one. This holds at every
+/// line speed, unlike a fixed delay.
+///
+/// Optional filters smooth the measured diameter before the loop regulates on it, e.g. a notch
+/// for the ripple of the screw rotation the puller can't correct anyway.
+#[derive(Debug)]
+pub struct DiameterLoop {
+    pid: PidController,
+    mode: DiameterLoopMode,
+    /// Line speed in manual mode, the base speed until set or frozen
+    manual_speed: Option<Velocity>,
+    /// The PID starts from the current correction at the next update with a measurement
+    transfer: bool,
+    /// The loop ran since the last reset
+    active: bool,
+    /// Measured diameter of the last update, unfiltered
+    measured: Option<Length>,
+    /// Applied in order to the measured diameter, one sample per update with a measurement
+    filters: Vec<Biquad>,
+    /// The filters were settled at a measurement since the last reset or gap
+    filters_settled: bool,
+    /// Measured minus target diameter of the last update, compensated for the distance from the
+    /// nozzle to the laser
+    error: Option<Length>,
+    /// Filament length from the nozzle to the laser, not compensated if not set
+    nozzle_to_laser: Option<Length>,
+    /// Pulled length at the next update
+    position: Length,
+    /// Pulled length when the measurement of the next update was taken
+    measured_at: Option<Length>,
+    /// Setpoints by the pulled length they were set at
+    setpoints: LineTrail<Length, Velocity>,
+    /// Correction of the base speed, kept while the measurement is missing
+    correction: Velocity,
+    setpoint: Velocity,
+    scope: Option<DiameterLoopScope>,
+}
+
+/// Probes of the loop for commissioning, see [`control_core::scope`]
+#[derive(Debug)]
+struct DiameterLoopScope {
+    measured: ScopeChannel,
+    p: ScopeChannel,
+    i: ScopeChannel,
+    d: ScopeChannel,
+    setpoint: ScopeChannel,
+}
+
+impl DiameterLoop {
+    pub const DEFAULT_KP: f64 = 10.0;
+    pub const DEFAULT_KI: f64 = 2.0;
+    pub const DEFAULT_KD: f64 = 0.0;
+    /// The correction stays within this fraction of the base speed
+    pub const MAX_CORRECTION: f64 = 0.5;
+    /// Smooths the derivative of the gauge noise
+    const DERIVATIVE_FILTER: Duration = Duration::from_millis(500);
+    /// Setpoints closer together are not remembered
+    const SETPOINT_SPACING_CM: f64 = 1.0;
+    /// Covers 40 m from the nozzle to the laser
+    const SETPOINT_HISTORY: usize = 4096;
+
+    pub fn new() -> Self {
+        let mut pid = PidController::new(Self::DEFAULT_KP, Self::DEFAULT_KI, Self::DEFAULT_KD);
+        pid.set_derivative_filter(Self::DERIVATIVE_FILTER);
+        Self {
+            pid,
+            mode: DiameterLoopMode::Auto,
+            manual_speed: None,
+            transfer: false,
+            active: false,
+            measured: None,
+            filters: Vec::new(),
+            filters_settled: false,
+            error: None,
+            nozzle_to_laser: None,
+            position: Length::ZERO,
+            measured_at: None,
+            setpoints: LineTrail::new(Self::SETPOINT_HISTORY),
+            correction: Velocity::ZERO,
+            setpoint: Velocity::ZERO,
+            scope: None,
+        }
+    }
+
+    /// Replaces the filters of the measured diameter, they start settled at the next measurement
+    pub fn set_filters(&mut self, filters: Vec<Biquad>) {
+        self.filters = filters;
+        self.filters_settled = false;
+    }
+
+    /// Measured diameter after the filters
+    ///
+    /// The filters start settled at the first measurement, so the loop doesn't regulate on their
+    /// step response after a gap of the gauge.
+    fn filter(&mut self, measured: Length) -> Length {
+        let mut value = measured.get::<millimeter>();
+        for filter in &mut self.filters {
+            if !self.filters_settled {
+                filter.reset_to(value);
+            }
+            value = filter.filter(value);
+        }
+        self.filters_settled = true;
+        Length::new::<millimeter>(value)
+    }
+
+    pub const fn set_nozzle_to_laser(&mut self, distance: Option<Length>) {
+        self.nozzle_to_laser = distance;
+    }
+
+    /// Positions of the line for the next update, the pulled length now and when the
+    /// measurement was taken
+    pub const fn set_line_position(&mut self, position: Length, measured_at: Option<Length>) {
+        self.position = position;
+        self.measured_at = measured_at;
+    }
+
+    /// Diameter the filament has at the nozzle now, the measured one without history
+    fn at_nozzle(&self, measured: Length) -> Length {
+        let (Some(distance), Some(measured_at)) = (self.nozzle_to_laser, self.measured_at) else {
+            return measured;
+        };
+        let Some(pulled_with) = self.setpoints.at(measured_at - distance) else {
+            return measured;
+        };
+        if pulled_with <= Velocity::ZERO || self.setpoint <= Velocity::ZERO {
+            return measured;
+        }
+        measured * (pulled_with / self.setpoint).get::<ratio>().sqrt()
+    }
+
+    fn record_setpoint(&mut self) {
+        let due = self.setpoints.last_key().is_none_or(|last| {
+            (self.position - last).get::<centimeter>() >= Self::SETPOINT_SPACING_CM
+        });
+        if due {
+            self.setpoints.push(self.position, self.setpoint);
+        }
+    }
+
+    /// Registers the scope channels `<prefix>/diameter_loop/*`
+    pub fn register_scope(&mut self, prefix: &str) {
+        let channel =
+            |signal: &str| ScopeChannel::new(format!("{}/diameter_loop/{}", prefix, signal));
+        self.scope = Some(DiameterLoopScope {
+            measured: channel("measured"),
+            p: channel("p"),
+            i: channel("i"),
+            d: channel("d"),
+            setpoint: channel("setpoint"),
+        });
+    }
+
+    /// Changes the gains without a jump of the setpoint
+    pub fn set_gains(&mut self, kp: f64, ki: f64, kd: f64) -> Result<(), anyhow::Error> {
+        if [kp, ki, kd]
+            .iter()
+            .any(|gain| !gain.is_finite() || *gain < 0.0)
+        {
+            return Err(anyhow::anyhow!(
+                "[{}::DiameterLoop::set_gains] Gains must not be negative, got kp {}, ki {}, kd {}",
+                module_path!(),
+                kp,
+                ki,
+                kd
+            ));
+        }
+        self.pid.set_gains(kp, ki, kd);
+        Ok(())
+    }
+
+    pub const fn get_gains(&self) -> (f64, f64, f64) {
+        (self.pid.get_kp(), self.pid.get_ki(), self.pid.get_kd())
+    }
+
+    pub const fn get_mode(&self) -> DiameterLoopMode {
+        self.mode
+    }
+
+    /// Switches between manual and automatic control without a jump of the setpoint
+    ///
+    /// Going to manual freezes the last setpoint as the manual speed, going to auto starts the
+    /// PID from the manual speed.
+    pub fn set_mode(&mut self, mode: DiameterLoopMode) {
+        if mode == self.mode {
+            return;
+        }
+        match mode {
+            DiameterLoopMode::Manual => {
+                self.manual_speed = self.active.then_some(self.setpoint);
+            }
+            DiameterLoopMode::Auto => self.transfer = self.active,
+        }
+        self.mode = mode;
+    }
+
+    pub const fn get_manual_speed(&self) -> Option<Velocity> {
+        self.manual_speed
+    }
+
+    /// Sets the line speed of the manual mode, kept for the next switch to manual
+    pub fn set_manual_speed(&mut self, speed: Velocity) -> Result<(), anyhow::Error> {
+        if !speed.get::<meter_per_minute>().is_finite() || speed < Velocity::ZERO {
+            return Err(anyhow::anyhow!(
+                "[{}::DiameterLoop::set_manual_speed] Manual speed must not be negative, got {} m/min",
+                module_path!(),
+                speed.get::<meter_per_minute>()
+            ));
+        }
+        self.manual_speed = Some(speed);
+        Ok(())
+    }
+
+    /// Returns the line speed setpoint for the speed loop
+    ///
+    /// Without a measurement the last correction is held until the gauge is back.
+    pub fn update(
+        &mut self,
+        t: Instant,
+        base_speed: Velocity,
+        target_diameter: Length,
+        measured: Option<Length>,
+    ) -> Velocity {
+        self.active = true;
+        self.measured = measured;
+        self.error = match measured {
+            Some(measured) => {
+                let filtered = self.filter(measured);
+                Some(self.at_nozzle(filtered) - target_diameter)
+            }
+            None => {
+                self.filters_settled = false;
+                None
+            }
+        };
+
+        let base = base_speed.get::<meter_per_minute>();
+        if self.mode == DiameterLoopMode::Manual {
+            // the correction follows the manual speed for the transfer back to auto
+            self.setpoint = self.manual_speed.unwrap_or(base_speed).max(Velocity::ZERO);
+            self.correction = self.setpoint - base_speed;
+            self.record_setpoint();
+            self.probe(t);
+            return self.setpoint;
+        }
+
+        let correction = match self.error {
+            Some(error) if self.transfer => {
+                self.transfer = false;
+                let correction = self.correction.get::<meter_per_minute>();
+                self.pid
+                    .initialize(correction, error.get::<millimeter>(), t);
+                correction
+            }
+            Some(error) => self.pid.update(error.get::<millimeter>(), t),
+            None => {
+                self.pid.hold(t);
+                self.correction.get::<meter_per_minute>()
+            }
+        };
+        let limit = base.abs() * Self::MAX_CORRECTION;
+        self.correction = Velocity::new::<meter_per_minute>(correction.clamp(-limit, limit));
+        self.setpoint = Velocity::new::<meter_per_minute>(
+            (base + self.correction.get::<meter_per_minute>()).max(0.0),
+        );
+        self.record_setpoint();
+        self.probe(t);
+        self.setpoint
+    }
+
+    fn probe(&self, t: Instant) {
+        let Some(scope) = &self.scope else {
+            return;
+        };
+        if let Some(measured) = self.measured {
+            scope.measured.probe(t, measured.get::<millimeter>());
+        }
+        let (p, i, d) = self.pid.terms();
+        scope.p.probe(t, p);
+        scope.i.probe(t, i);
+        scope.d.probe(t, d);
+        scope
+            .setpoint
+            .probe(t, self.setpoint.get::<meter_per_minute>());
+    }
+
+    /// Opens the loop, the next update starts without correction
+    ///
+    /// The mode and the manual speed are kept, the setpoints of the filament on its way to the
+    /// laser are forgotten.
+    pub fn reset(&mut self) {
+        self.pid.reset();
+        self.setpoints.clear();
+        self.transfer = false;
+        self.active = false;
+        self.measured = None;
+        self.filters_settled = false;
+        self.error = None;
+        self.correction = Velocity::ZERO;
+        self.setpoint = Velocity::ZERO;
+    }
+
+    pub const fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// The loop runs but holds its correction because the measurement is missing
+    pub const fn is_holding(&self) -> bool {
+        self.active && self.measured.is_none()
+    }
+
+    pub const fn get_measured(&self) -> Option<Length> {
+        self.measured
+    }
+
+    pub const fn get_error(&self) -> Option<Length> {
+        self.error
+    }
+
+    pub const fn get_setpoint(&self) -> Velocity {
+        self.setpoint
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum DiameterLoopMode {
+    /// The PID corrects the line speed
+    Auto,
+    /// The operator sets the line speed
+    Manual,
+}
+
+impl Default for DiameterLoop {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use std::f64::consts::PI;
+    use uom::si::{f64::Frequency, frequency::hertz, length::meter};
+
+    const DT: Duration = Duration::from_millis(10);
+
+    fn mm(value: f64) -> Length {
+        Length::new::<millimeter>(value)
+    }
+
+    fn m_min(value: f64) -> Velocity {
+        Velocity::new::<meter_per_minute>(value)
+    }
+
+    #[test]
+    fn test_thick_filament_pulls_faster() {
+        let mut diameter_loop = DiameterLoop::new();
+        diameter_loop.set_gains(10.0, 0.0, 0.0).unwrap();
+        let t = Instant::now();
+
+        let setpoint = diameter_loop.update(t, m_min(10.0), mm(1.75), Some(mm(1.80)));
+        assert_relative_eq!(setpoint.get::<meter_per_minute>(), 10.5, epsilon = 1e-9);
+        let setpoint = diameter_loop.update(t + DT, m_min(10.0), mm(1.75), Some(mm(1.70)));
+        assert_relative_eq!(setpoint.get::<meter_per_minute>(), 9.5, epsilon = 1e-9);
+        assert_relative_eq!(
+            diameter_loop.get_error().unwrap().get::<millimeter>(),
+            -0.05,
+            epsilon = 1e-9
+        );
+
+        // limited to half the base speed
+        let setpoint = diameter_loop.update(t + 2 * DT, m_min(10.0), mm(1.75), Some(mm(3.0)));
+        assert_relative_eq!(setpoint.get::<meter_per_minute>(), 15.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_holds_without_measurement() {
+        let mut diameter_loop = DiameterLoop::new();
+        let mut t = Instant::now();
+        let mut setpoint = Velocity::ZERO;
+        for _ in 0..100 {
+            setpoint = diameter_loop.update(t, m_min(10.0), mm(1.75), Some(mm(1.78)));
+            t += DT;
+        }
+
+        for _ in 0..100 {
+            let held = diameter_loop.update(t, m_min(10.0), mm(1.75), None);
+            assert_relative_eq!(
+                held.get::<meter_per_minute>(),
+                setpoint.get::<meter_per_minute>()
+            );
+            t += DT;
+        }
+        assert!(diameter_loop.is_holding());
+
+        // continues without integrating the gap
+        let resumed = diameter_loop.update(t, m_min(10.0), mm(1.75), Some(mm(1.78)));
+        assert!(!diameter_loop.is_holding());
+        assert_relative_eq!(
+            resumed.get::<meter_per_minute>(),
+            setpoint.get::<meter_per_minute>() + 2.0 * 0.03 * 0.01,
+            epsilon = 1e-9
+        );
+
+        diameter_loop.reset();
+        assert!(!diameter_loop.is_active());
+        assert!(diameter_loop.set_gains(-1.0, 0.0, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_bumpless_transfer() {
+        let mut diameter_loop = DiameterLoop::new();
+        let mut t = Instant::now();
+        let mut setpoint = Velocity::ZERO;
+        for _ in 0..100 {
+            setpoint = diameter_loop.update(t, m_min(10.0), mm(1.75), Some(mm(1.78)));
+            t += DT;
+        }
+
+        // manual starts at the last setpoint
+        diameter_loop.set_mode(DiameterLoopMode::Manual);
+        let manual = diameter_loop.update(t, m_min(10.0), mm(1.75), Some(mm(1.80)));
+        assert_relative_eq!(
+            manual.get::<meter_per_minute>(),
+            setpoint.get::<meter_per_minute>()
+        );
+        diameter_loop.set_manual_speed(m_min(11.0)).unwrap();
+        assert!(diameter_loop.set_manual_speed(m_min(-1.0)).is_err());
+        t += DT;
+        diameter_loop.update(t, m_min(10.0), mm(1.75), Some(mm(1.80)));
+
+        // auto continues from the manual speed
+        diameter_loop.set_mode(DiameterLoopMode::Auto);
+        t += DT;
+        let auto = diameter_loop.update(t, m_min(10.0), mm(1.75), Some(mm(1.80)));
+        assert_relative_eq!(auto.get::<meter_per_minute>(), 11.0, epsilon = 1e-9);
+        t += DT;
+        let next = diameter_loop.update(t, m_min(10.0), mm(1.75), Some(mm(1.80)));
+        assert_relative_eq!(
+            next.get::<meter_per_minute>(),
+            11.0 + 2.0 * 0.05 * 0.01,
+            epsilon = 1e-9
+        );
+    }
+
+    #[test]
+    fn test_filtered_measurement() {
+        let mut diameter_loop = DiameterLoop::new();
+        diameter_loop.set_gains(10.0, 0.0, 0.0).unwrap();
+        diameter_loop.set_filters(vec![
+            Biquad::notch(
+                Frequency::new::<hertz>(2.0),
+                Frequency::new::<hertz>(100.0),
+                2.0,
+            )
+            .unwrap(),
+        ]);
+        let mut t = Instant::now();
+
+        // starts settled at the first measurement instead of ramping up from zero
+        diameter_loop.update(t, m_min(10.0), mm(1.75), Some(mm(1.80)));
+        assert_relative_eq!(
+            diameter_loop.get_error().unwrap().get::<millimeter>(),
+            0.05,
+            epsilon = 1e-9
+        );
+
+        // a 2 Hz ripple of the screw doesn't reach the line speed
+        let mut peak: f64 = 0.0;
+        for i in 0..2000 {
+            let ripple = 0.02 * (2.0 * PI * 2.0 * f64::from(i) / 100.0).sin();
+            diameter_loop.update(t, m_min(10.0), mm(1.75), Some(mm(1.75 + ripple)));
+            if i >= 1000 {
+                peak = peak.max(diameter_loop.get_error().unwrap().get::<millimeter>().abs());
+            }
+            t += DT;
+        }
+        assert!(peak < 0.001, "{}", peak);
+        // the raw measurement is still reported
+        assert!(diameter_loop.get_measured().is_some());
+    }
+
+    #[test]
+    fn test_nozzle_to_laser_compensation() {
+        let mut diameter_loop = DiameterLoop::new();
+        diameter_loop.set_gains(10.0, 0.0, 0.0).unwrap();
+        diameter_loop.set_nozzle_to_laser(Some(Length::new::<meter>(1.0)));
+        let mut t = Instant::now();
+        let m = Length::new::<meter>;
+
+        // 2 m pulled at 10 m/min in tolerance
+        for cm in 0..=200 {
+            diameter_loop.set_line_position(m(f64::from(cm) / 100.0), None);
+            diameter_loop.update(t, m_min(10.0), mm(1.75), Some(mm(1.75)));
+            t += DT;
+        }
+        diameter_loop.update(t, m_min(20.0), mm(1.75), Some(mm(1.75)));
+
+        // the piece at the laser was pulled at 10 m/min, at 20 m/min it is thinner now
+        diameter_loop.set_line_position(m(2.05), Some(m(2.05)));
+        diameter_loop.update(t + DT, m_min(20.0), mm(1.75), Some(mm(1.75)));
+        assert_relative_eq!(
+            diameter_loop.get_error().unwrap().get::<millimeter>(),
+            1.75 * 0.5_f64.sqrt() - 1.75,
+            epsilon = 1e-9
+        );
+
+        // without the distance the measurement counts as is
+        diameter_loop.set_nozzle_to_laser(None);
+        diameter_loop.update(t + 2 * DT, m_min(20.0), mm(1.75), Some(mm(1.75)));
+        assert_relative_eq!(diameter_loop.get_error().unwrap().get::<millimeter>(), 0.0);
+    }
+}
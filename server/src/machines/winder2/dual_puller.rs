@@ -0,0 +1,135 @@
+use crate::config::SecondPullerConfig;
+use ethercat_hal::io::stepper_velocity_el70x1::StepperVelocityEL70x1;
+use std::time::Instant;
+use uom::si::{electric_current::ampere, f64::ElectricCurrent};
+
+/// Second puller axis of heavy filament, following the first one with a speed ratio
+///
+/// The axis gets the speed of the first one after its jerk limited ramp, so both accelerate
+/// together. The ratio is the configured trim, with load sharing corrected by the difference
+/// of the motor currents: the axis drawing more current pulls against the other one and is
+/// slowed down, until both share the load.
+#[derive(Debug)]
+pub struct SecondPuller {
+    pub stepper: StepperVelocityEL70x1,
+    /// speed relative to the first axis
+    trim: f64,
+    load_sharing: bool,
+    /// relative correction of the trim balancing the currents
+    correction: f64,
+    last_update: Option<Instant>,
+}
+
+impl SecondPuller {
+    /// Bound of the correction, a larger one would hide a mechanical problem
+    const MAX_CORRECTION: f64 = 0.01;
+    /// Correction per second and A of current difference
+    const LOAD_SHARING_GAIN: f64 = 0.0005;
+
+    pub const fn new(stepper: StepperVelocityEL70x1, trim: f64, load_sharing: bool) -> Self {
+        Self {
+            stepper,
+            trim,
+            load_sharing,
+            correction: 0.0,
+            last_update: None,
+        }
+    }
+
+    pub const fn trim(&self) -> f64 {
+        self.trim
+    }
+
+    pub fn set_trim(&mut self, trim: f64) -> Result<(), anyhow::Error> {
+        if !(SecondPullerConfig::MIN_TRIM..=SecondPullerConfig::MAX_TRIM).contains(&trim) {
+            return Err(anyhow::anyhow!(
+                "[{}::SecondPuller::set_trim] Trim must be between {} and {}",
+                module_path!(),
+                SecondPullerConfig::MIN_TRIM,
+                SecondPullerConfig::MAX_TRIM
+            ));
+        }
+        self.trim = trim;
+        Ok(())
+    }
+
+    pub const fn load_sharing(&self) -> bool {
+        self.load_sharing
+    }
+
+    pub const fn set_load_sharing(&mut self, load_sharing: bool) {
+        self.load_sharing = load_sharing;
+        if !load_sharing {
+            self.correction = 0.0;
+        }
+    }
+
+    /// Speed of this axis relative to the first one
+    pub fn ratio(&self) -> f64 {
+        self.trim * (1.0 + self.correction)
+    }
+
+    /// Balances the load by the currents of the first and this axis, call it every cycle
+    ///
+    /// The correction is kept while the line stands, so it starts balanced again.
+    pub fn share_load(
+        &mut self,
+        now: Instant,
+        pulling: bool,
+        first_current: Option<ElectricCurrent>,
+        second_current: Option<ElectricCurrent>,
+    ) {
+        let dt = self
+            .last_update
+            .replace(now)
+            .map(|last_update| now.saturating_duration_since(last_update).as_secs_f64());
+        let (Some(dt), Some(first), Some(second)) = (dt, first_current, second_current) else {
+            return;
+        };
+        if !(self.load_sharing && pulling) {
+            return;
+        }
+        let difference = second.get::<ampere>() - first.get::<ampere>();
+        self.correction = (Self::LOAD_SHARING_GAIN * difference)
+            .mul_add(-dt, self.correction)
+            .clamp(-Self::MAX_CORRECTION, Self::MAX_CORRECTION);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethercat_hal::{
+        io::stepper_velocity_el70x1_dummy::StepperVelocityEL70x1Dummy,
+        shared_config::el70x1::EL70x1SpeedRange,
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn test_load_sharing() {
+        let stepper = StepperVelocityEL70x1Dummy::new(EL70x1SpeedRange::Steps1000, 1);
+        let mut puller = SecondPuller::new(stepper.stepper_velocity(), 1.002, true);
+        assert!(puller.set_trim(2.0).is_err());
+        let amps = ElectricCurrent::new::<ampere>;
+        let mut now = Instant::now();
+
+        // the second axis draws more, it is slowed down until the correction is bound
+        for _ in 0..1000 {
+            now += Duration::from_millis(100);
+            puller.share_load(now, true, Some(amps(1.0)), Some(amps(2.0)));
+        }
+        assert!((puller.ratio() - 1.002 * 0.99).abs() < 1e-9);
+
+        // a standing line keeps the correction
+        puller.share_load(
+            now + Duration::from_secs(10),
+            false,
+            Some(amps(0.0)),
+            Some(amps(2.0)),
+        );
+        assert!((puller.ratio() - 1.002 * 0.99).abs() < 1e-9);
+
+        puller.set_load_sharing(false);
+        assert!((puller.ratio() - 1.002).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,111 @@
+use super::{Winder2, Winder2Mode, api::Mode, production::ProductionStats};
+use control_core::{helpers::clock::unix_millis, uom_extensions::velocity::meter_per_minute};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use uom::si::{
+    angular_velocity::revolution_per_minute,
+    f64::Length,
+    length::{centimeter, meter},
+};
+
+/// Controller state of a winder that survives a crash or restart
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Winder2Journal {
+    /// Unix time in milliseconds
+    pub saved_at: u64,
+    pub mode: Mode,
+    /// m/min, last commanded puller speed
+    pub puller_speed: f64,
+    /// rpm, last commanded spool speed
+    pub spool_speed: f64,
+    /// m wound onto the current spool
+    pub spool_progress: f64,
+    /// number of the current spool
+    #[serde(default = "first_spool")]
+    pub spool: u32,
+    /// cm, learned by the adaptive spool speed controller, grows with the spool radius
+    pub spool_speed_factor: f64,
+    /// m pulled since the journal was created
+    pub pulled_length: f64,
+    /// shift and lifetime production counters, missing in journals of older versions
+    #[serde(default)]
+    pub production: ProductionStats,
+}
+
+const fn first_spool() -> u32 {
+    1
+}
+
+impl Winder2 {
+    /// How often the controller state is journaled while the winder is not in standby
+    pub const JOURNAL_INTERVAL: Duration = Duration::from_secs(5);
+
+    fn journal_snapshot(&self) -> Winder2Journal {
+        Winder2Journal {
+            saved_at: unix_millis(),
+            mode: self.mode.clone().into(),
+            puller_speed: self
+                .puller_speed_controller
+                .last_speed
+                .get::<meter_per_minute>(),
+            spool_speed: self
+                .spool_speed_controller
+                .get_speed()
+                .get::<revolution_per_minute>(),
+            spool_progress: self.spool_automatic_action.progress.get::<meter>(),
+            spool: self.spool_automatic_action.spool,
+            spool_speed_factor: self
+                .spool_speed_controller
+                .get_adaptive_speed_factor()
+                .get::<centimeter>(),
+            pulled_length: self.pulled_length.get::<meter>(),
+            production: self.production.clone(),
+        }
+    }
+
+    /// Journals the controller state, called by `act`
+    ///
+    /// Nothing changes in standby, the snapshot taken when entering it stays valid.
+    pub fn sync_journal(&mut self, now: Instant) {
+        if self.mode != Winder2Mode::Standby && self.journal.is_due(now) {
+            self.record_journal(now);
+        }
+    }
+
+    /// Journals the controller state right away, e.g. after a mode change or a new spool
+    pub fn record_journal(&mut self, now: Instant) {
+        let snapshot = self.journal_snapshot();
+        self.journal.record(now, snapshot);
+    }
+
+    /// Resumes the length accounting and the learned spool radius of the last run
+    ///
+    /// The last speeds are not applied, the winder always starts in standby.
+    pub fn restore_journal(&mut self) {
+        let Some(journal) = self.journal.load() else {
+            return;
+        };
+
+        self.spool_automatic_action.progress = Length::new::<meter>(journal.spool_progress);
+        self.spool_automatic_action.spool = journal.spool;
+        self.pulled_length = Length::new::<meter>(journal.pulled_length);
+        self.production = journal.production;
+        self.spool_speed_controller
+            .set_adaptive_speed_factor(Length::new::<centimeter>(journal.spool_speed_factor));
+
+        tracing::info!(
+            "{} resumes with {:.1} m on the spool from the journal of {}",
+            self.machine_identification_unique,
+            journal.spool_progress,
+            journal.saved_at
+        );
+        if !matches!(journal.mode, Mode::Standby) {
+            tracing::warn!(
+                "{} stopped in {:?} mode at {:.1} m/min, check the spool before winding on",
+                self.machine_identification_unique,
+                journal.mode,
+                journal.puller_speed
+            );
+        }
+    }
+}
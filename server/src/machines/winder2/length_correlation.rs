@@ -0,0 +1,341 @@
+use std::{collections::VecDeque, time::Instant};
+
+use control_core::{
+    helpers::clock::unix_millis,
+    machines::values::{DIAMETER, IN_TOLERANCE},
+};
+use uom::{
+    ConstZero,
+    si::{
+        f64::Length,
+        length::{centimeter, meter, millimeter},
+    },
+};
+
+use super::Winder2;
+use crate::batches::defect_map::{DefectSegment, SpoolDefectMap};
+
+/// Position of filament on a spool
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpoolPosition {
+    /// Counts up with every spool change, starting at 1
+    pub spool: u32,
+    /// Length wound onto the spool before this position
+    pub position: Length,
+}
+
+/// Diameter measured at the laser
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LaserMeasurement {
+    pub diameter: Length,
+    pub in_tolerance: bool,
+}
+
+/// Maps measurements at the laser to the filament reaching the spool
+///
+/// The filament measured at the laser reaches the spool once the sensor offset more is pulled.
+/// Measurements wait in a queue until then, so a spool change in between puts them on the new
+/// spool.
+#[derive(Debug)]
+pub struct LengthCorrelator<T> {
+    /// Filament length from the laser to the spool
+    offset: Length,
+    /// Measurements with the pulled length they reach the spool at, oldest first
+    pending: VecDeque<(Length, T)>,
+}
+
+impl<T> LengthCorrelator<T> {
+    /// Closer measurements are dropped, limits the queue to the offset divided by this
+    const RESOLUTION: f64 = 1.0;
+
+    pub const fn new(offset: Length) -> Self {
+        Self {
+            offset,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Queues a measurement taken at the laser when `pulled_length` was pulled
+    pub fn add(&mut self, pulled_length: Length, measurement: T) {
+        let arrives_at = pulled_length + self.offset;
+        if self
+            .pending
+            .back()
+            .is_some_and(|(last, _)| (arrives_at - *last).get::<centimeter>() < Self::RESOLUTION)
+        {
+            return;
+        }
+        self.pending.push_back((arrives_at, measurement));
+    }
+
+    /// Measurements of the filament that reached the spool at `pulled_length`, oldest first
+    pub fn arrived(&mut self, pulled_length: Length) -> Vec<T> {
+        let count = self
+            .pending
+            .iter()
+            .take_while(|(arrives_at, _)| *arrives_at <= pulled_length)
+            .count();
+        self.pending
+            .drain(..count)
+            .map(|(_, measurement)| measurement)
+            .collect()
+    }
+
+    /// Moves the queued measurements along with the offset
+    pub fn set_offset(&mut self, offset: Length) {
+        let change = offset - self.offset;
+        for (arrives_at, _) in &mut self.pending {
+            *arrives_at += change;
+        }
+        self.offset = offset;
+    }
+
+    pub const fn get_offset(&self) -> Length {
+        self.offset
+    }
+}
+
+/// Collects the out of tolerance segments and the diameter of the spool being wound
+#[derive(Debug, Default)]
+pub struct DefectMapBuilder {
+    segments: Vec<DefectSegment>,
+    /// Segment the spool is winding right now
+    open: Option<DefectSegment>,
+    /// mm
+    min_diameter: Option<f64>,
+    /// mm
+    max_diameter: Option<f64>,
+    diameter_sum: f64,
+    diameter_count: u64,
+}
+
+impl DefectMapBuilder {
+    pub const fn new() -> Self {
+        Self {
+            segments: Vec::new(),
+            open: None,
+            min_diameter: None,
+            max_diameter: None,
+            diameter_sum: 0.0,
+            diameter_count: 0,
+        }
+    }
+
+    /// Adds a measurement that reached the spool at `position`
+    ///
+    /// Returns whether it starts a new segment.
+    pub fn add(&mut self, position: Length, measurement: LaserMeasurement) -> bool {
+        let position = position.get::<meter>();
+        let diameter = measurement.diameter.get::<millimeter>();
+        self.min_diameter = Some(self.min_diameter.map_or(diameter, |min| min.min(diameter)));
+        self.max_diameter = Some(self.max_diameter.map_or(diameter, |max| max.max(diameter)));
+        self.diameter_sum += diameter;
+        self.diameter_count += 1;
+
+        if measurement.in_tolerance {
+            self.close_segment(position);
+            return false;
+        }
+
+        match &mut self.open {
+            Some(segment) => {
+                segment.end = position;
+                segment.min_diameter = segment.min_diameter.min(diameter);
+                segment.max_diameter = segment.max_diameter.max(diameter);
+                false
+            }
+            None => {
+                self.open = Some(DefectSegment {
+                    start: position,
+                    end: position,
+                    min_diameter: diameter,
+                    max_diameter: diameter,
+                });
+                true
+            }
+        }
+    }
+
+    /// Returns the map of the spool with `length` wound, the next spool starts without segments
+    ///
+    /// A segment still open ends with the spool.
+    pub fn finish(&mut self, spool: u32, length: Length) -> SpoolDefectMap {
+        let length = length.get::<meter>();
+        self.close_segment(length);
+        let finished = std::mem::take(self);
+        SpoolDefectMap {
+            spool,
+            closed_at: unix_millis(),
+            length,
+            min_diameter: finished.min_diameter,
+            max_diameter: finished.max_diameter,
+            average_diameter: (finished.diameter_count > 0)
+                .then(|| finished.diameter_sum / finished.diameter_count as f64),
+            segments: finished.segments,
+        }
+    }
+
+    fn close_segment(&mut self, position: f64) {
+        if let Some(mut segment) = self.open.take() {
+            segment.end = position;
+            self.segments.push(segment);
+        }
+    }
+}
+
+impl Winder2 {
+    /// Defects kept until the batch sampler takes them, older ones are dropped
+    const MAX_SPOOL_DEFECTS: usize = 1000;
+    /// Closed spool maps kept until the batch sampler takes them, older ones are dropped
+    const MAX_CLOSED_DEFECT_MAPS: usize = 16;
+
+    /// Follows the measured filament to the spool, called by `act` after the pulled length grew
+    pub fn sync_length_correlation(&mut self, now: Instant) {
+        let diameter = self
+            .values
+            .latest(DIAMETER)
+            .filter(|sample| !sample.is_stale(now, Self::DIAMETER_MAX_AGE));
+        let in_tolerance = self
+            .values
+            .latest(IN_TOLERANCE)
+            .filter(|sample| !sample.is_stale(now, Self::DIAMETER_MAX_AGE));
+        if let (Some(diameter), Some(in_tolerance)) = (diameter, in_tolerance) {
+            let measurement = LaserMeasurement {
+                diameter: diameter.value,
+                in_tolerance: in_tolerance.value,
+            };
+            // where the line was when the laser measured, not when the value arrived here
+            let measured_at = self.line_position_at(diameter.published);
+            self.length_correlator.add(measured_at, measurement);
+        }
+
+        for measurement in self.length_correlator.arrived(self.pulled_length) {
+            let position = self.spool_automatic_action.progress;
+            if self.defect_map.add(position, measurement) {
+                if self.spool_defects.len() >= Self::MAX_SPOOL_DEFECTS {
+                    self.spool_defects.remove(0);
+                }
+                self.spool_defects.push(SpoolPosition {
+                    spool: self.spool_automatic_action.spool,
+                    position,
+                });
+            }
+        }
+    }
+
+    /// Positions where out of tolerance filament starts on the spools since the last call
+    pub fn take_spool_defects(&mut self) -> Vec<SpoolPosition> {
+        std::mem::take(&mut self.spool_defects)
+    }
+
+    /// Closes the defect map of the spool, called before the next spool starts
+    ///
+    /// Spools nothing was wound onto have no map.
+    pub fn close_defect_map(&mut self) {
+        let length = self.spool_automatic_action.progress;
+        let defect_map = self
+            .defect_map
+            .finish(self.spool_automatic_action.spool, length);
+        if length <= Length::ZERO {
+            return;
+        }
+        if self.closed_defect_maps.len() >= Self::MAX_CLOSED_DEFECT_MAPS {
+            self.closed_defect_maps.remove(0);
+        }
+        self.closed_defect_maps.push(defect_map);
+    }
+
+    /// Maps of the spools closed since the last call
+    pub fn take_closed_defect_maps(&mut self) -> Vec<SpoolDefectMap> {
+        std::mem::take(&mut self.closed_defect_maps)
+    }
+
+    /// Sets the filament length from the laser to the spool in m
+    pub fn set_sensor_offset(&mut self, sensor_offset: f64) -> Result<(), anyhow::Error> {
+        if !(sensor_offset.is_finite() && sensor_offset >= 0.0) {
+            return Err(anyhow::anyhow!(
+                "[{}::Winder2::set_sensor_offset] Sensor offset must not be negative, got {} m",
+                module_path!(),
+                sensor_offset
+            ));
+        }
+        self.length_correlator
+            .set_offset(Length::new::<meter>(sensor_offset));
+        self.emit_state();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(value: f64) -> Length {
+        Length::new::<meter>(value)
+    }
+
+    #[test]
+    fn test_arrives_after_offset() {
+        let mut correlator = LengthCorrelator::new(m(2.0));
+        correlator.add(m(10.0), 'a');
+        // below the resolution
+        correlator.add(m(10.005), 'x');
+        correlator.add(m(10.5), 'b');
+
+        assert!(correlator.arrived(m(11.9)).is_empty());
+        assert_eq!(correlator.arrived(m(12.0)), vec!['a']);
+        assert_eq!(correlator.arrived(m(13.0)), vec!['b']);
+        assert!(correlator.arrived(m(20.0)).is_empty());
+    }
+
+    #[test]
+    fn test_defect_map() {
+        let measurement = |diameter, in_tolerance| LaserMeasurement {
+            diameter: Length::new::<millimeter>(diameter),
+            in_tolerance,
+        };
+        let mut builder = DefectMapBuilder::new();
+        assert!(!builder.add(m(1.0), measurement(1.75, true)));
+        assert!(builder.add(m(2.0), measurement(1.82, false)));
+        assert!(!builder.add(m(3.0), measurement(1.85, false)));
+        assert!(!builder.add(m(4.0), measurement(1.75, true)));
+        // runs on until the spool is full
+        assert!(builder.add(m(9.0), measurement(1.60, false)));
+
+        let map = builder.finish(1, m(10.0));
+        assert_eq!(map.spool, 1);
+        assert_eq!(map.min_diameter, Some(1.60));
+        assert_eq!(map.max_diameter, Some(1.85));
+        assert_eq!(
+            map.segments,
+            vec![
+                DefectSegment {
+                    start: 2.0,
+                    end: 4.0,
+                    min_diameter: 1.82,
+                    max_diameter: 1.85,
+                },
+                DefectSegment {
+                    start: 9.0,
+                    end: 10.0,
+                    min_diameter: 1.60,
+                    max_diameter: 1.60,
+                },
+            ]
+        );
+        let map = builder.finish(2, m(10.0));
+        assert!(map.segments.is_empty());
+        assert!(map.average_diameter.is_none());
+    }
+
+    #[test]
+    fn test_set_offset() {
+        let mut correlator = LengthCorrelator::new(m(2.0));
+        correlator.add(m(0.0), 'a');
+        correlator.set_offset(m(3.0));
+        assert_eq!(correlator.get_offset(), m(3.0));
+
+        assert!(correlator.arrived(m(2.5)).is_empty());
+        assert_eq!(correlator.arrived(m(3.0)), vec!['a']);
+    }
+}
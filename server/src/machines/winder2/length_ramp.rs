@@ -0,0 +1,109 @@
+use super::api::{LengthRampPhase, LengthRampState};
+use std::time::Instant;
+use uom::{
+    ConstZero,
+    si::{
+        f64::{Length, Time, Velocity},
+        length::meter,
+        time::second,
+    },
+};
+
+/// Winds a fixed length, e.g. for a sample
+///
+/// The puller ramps up as usual. Once the remaining length is within the predicted stopping
+/// distance the winder holds, so the line comes to a stand at the target instead of after it.
+#[derive(Debug)]
+pub struct LengthRamp {
+    target: Length,
+    wound: Length,
+    phase: LengthRampPhase,
+    last_update: Instant,
+}
+
+impl LengthRamp {
+    pub fn new(target: Length, now: Instant) -> Self {
+        Self {
+            target,
+            wound: Length::ZERO,
+            phase: LengthRampPhase::Running,
+            last_update: now,
+        }
+    }
+
+    /// Integrates the line speed, returns the phase it changed to
+    pub fn update(
+        &mut self,
+        now: Instant,
+        speed: Velocity,
+        stopping_distance: Length,
+    ) -> Option<LengthRampPhase> {
+        let dt = Time::new::<second>(now.duration_since(self.last_update).as_secs_f64());
+        self.last_update = now;
+        self.wound += speed.abs() * dt;
+
+        let next = match self.phase {
+            LengthRampPhase::Running if self.target - self.wound <= stopping_distance => {
+                LengthRampPhase::Stopping
+            }
+            LengthRampPhase::Stopping if speed == Velocity::ZERO => LengthRampPhase::Done,
+            _ => return None,
+        };
+        self.phase = next;
+        Some(next)
+    }
+
+    pub const fn phase(&self) -> LengthRampPhase {
+        self.phase
+    }
+
+    pub const fn wound(&self) -> Length {
+        self.wound
+    }
+
+    pub fn state(&self) -> LengthRampState {
+        LengthRampState {
+            target_length: self.target.get::<meter>(),
+            phase: self.phase,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use control_core::{
+        helpers::clock::{Clock, ManualClock},
+        uom_extensions::velocity::meter_per_minute,
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn test_length_ramp() {
+        let clock = ManualClock::new();
+        let mut ramp = LengthRamp::new(Length::new::<meter>(10.0), clock.now());
+        let speed = Velocity::new::<meter_per_minute>(60.0);
+        let stopping = Length::new::<meter>(2.0);
+
+        // 1 m/s for 7 s
+        for _ in 0..7 {
+            clock.advance(Duration::from_secs(1));
+            assert_eq!(ramp.update(clock.now(), speed, stopping), None);
+        }
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(
+            ramp.update(clock.now(), speed, stopping),
+            Some(LengthRampPhase::Stopping)
+        );
+        assert!((ramp.wound().get::<meter>() - 8.0).abs() < 1e-9);
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(ramp.update(clock.now(), speed / 2.0, stopping), None);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(
+            ramp.update(clock.now(), Velocity::ZERO, Length::ZERO),
+            Some(LengthRampPhase::Done)
+        );
+        assert_eq!(ramp.state().phase, LengthRampPhase::Done);
+    }
+}
@@ -0,0 +1,103 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use uom::si::f64::Length;
+
+use super::Winder2;
+
+/// Values along the line, indexed by an increasing key like the time or the pulled length
+#[derive(Debug)]
+pub struct LineTrail<K, V> {
+    /// Oldest first
+    entries: VecDeque<(K, V)>,
+    capacity: usize,
+}
+
+impl<K: PartialOrd + Copy, V: Copy> LineTrail<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Appends `value` at `key`, keys not after the last one are ignored
+    pub fn push(&mut self, key: K, value: V) {
+        if self.last_key().is_some_and(|last| key <= last) {
+            return;
+        }
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((key, value));
+    }
+
+    pub fn last_key(&self) -> Option<K> {
+        self.entries.back().map(|(key, _)| *key)
+    }
+
+    /// Value of the last entry at or before `key`, `None` before the first one
+    pub fn at(&self, key: K) -> Option<V> {
+        let index = self.entries.partition_point(|(entry, _)| *entry <= key);
+        index
+            .checked_sub(1)
+            .and_then(|index| self.entries.get(index))
+            .map(|(_, value)| *value)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Winder2 {
+    /// Spacing of the line positions, limits the error of a measurement's position to the
+    /// length pulled in this time
+    const LINE_POSITION_INTERVAL: Duration = Duration::from_millis(10);
+    /// 10 s, covers the measurements accepted by [`Self::DIAMETER_MAX_AGE`]
+    pub(super) const LINE_POSITION_HISTORY: usize = 1024;
+
+    /// Records the pulled length over time, called by `act`
+    pub fn sync_line_position(&mut self, now: Instant) {
+        let due = self
+            .line_position
+            .last_key()
+            .is_none_or(|last| now.saturating_duration_since(last) >= Self::LINE_POSITION_INTERVAL);
+        if due {
+            self.line_position.push(now, self.pulled_length);
+        }
+    }
+
+    /// Pulled length when a measurement was taken at `t`, the current one for measurements
+    /// older than the history
+    pub fn line_position_at(&self, t: Instant) -> Length {
+        self.line_position.at(t).unwrap_or(self.pulled_length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_trail() {
+        let mut trail = LineTrail::new(3);
+        trail.push(1.0, 'a');
+        trail.push(2.0, 'b');
+        // keys only increase
+        trail.push(2.0, 'x');
+        trail.push(3.0, 'c');
+
+        assert_eq!(trail.at(0.5), None);
+        assert_eq!(trail.at(1.0), Some('a'));
+        assert_eq!(trail.at(2.5), Some('b'));
+        assert_eq!(trail.at(10.0), Some('c'));
+
+        // the oldest entry makes room
+        trail.push(4.0, 'd');
+        assert_eq!(trail.at(1.5), None);
+        assert_eq!(trail.at(4.0), Some('d'));
+    }
+}
@@ -0,0 +1,270 @@
+use super::{
+    Winder2,
+    api::{DiameterLoopGains, SpoolAutomaticActionMode, StrandTrim, Winder2Recipe},
+    spool::SpoolType,
+};
+use control_core::uom_extensions::velocity::meter_per_minute;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use uom::si::{
+    angle::degree,
+    angular_velocity::revolution_per_minute,
+    f64::Length,
+    length::{meter, millimeter},
+};
+
+/// Everything an operator or engineer set up on a winder
+///
+/// Extends the recipe with the settings that belong to the line rather than the product, so
+/// identical lines can be set up from one export.
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct Winder2Config {
+    #[serde(flatten)]
+    pub recipe: Winder2Recipe,
+    /// forward rotation direction of the puller
+    pub puller_forward: bool,
+    /// speed of every strand relative to the puller speed
+    pub puller_strand_trims: Vec<f64>,
+    /// gains of the diameter regulation
+    pub puller_diameter_loop_gains: DiameterLoopGains,
+    pub spool_adaptive_tension_target: f64,
+    pub spool_adaptive_radius_learning_rate: f64,
+    pub spool_adaptive_max_speed_multiplier: f64,
+    pub spool_adaptive_acceleration_factor: f64,
+    pub spool_adaptive_deacceleration_urgency_multiplier: f64,
+    /// spool length of the automatic action in m
+    pub spool_automatic_required_meters: f64,
+    pub spool_automatic_action_mode: SpoolAutomaticActionMode,
+    /// selected spool type with its geometry, so it doesn't have to be stored on the target
+    pub spool_type: Option<SpoolType>,
+    /// filament length from the laser to the spool in m
+    pub sensor_offset: f64,
+    /// cutter pulse time in ms
+    pub cutter_pulse_time: f64,
+    /// line speed required for a cut in m/min
+    pub cutter_min_line_speed: f64,
+    pub cut_on_spool_change: bool,
+}
+
+impl Winder2 {
+    /// Settings of the recipe as currently set
+    pub fn current_recipe(&self) -> Winder2Recipe {
+        Winder2Recipe {
+            puller_regulation: self.puller_speed_controller.regulation_mode.clone(),
+            puller_target_speed: self
+                .puller_speed_controller
+                .target_speed
+                .get::<meter_per_minute>(),
+            puller_target_diameter: self
+                .puller_speed_controller
+                .target_diameter
+                .get::<millimeter>(),
+            diameter_lower_tolerance: self
+                .diameter_tolerances
+                .map(|(lower, _)| lower.get::<millimeter>()),
+            diameter_higher_tolerance: self
+                .diameter_tolerances
+                .map(|(_, higher)| higher.get::<millimeter>()),
+            spool_regulation_mode: self.spool_speed_controller.get_type().clone(),
+            spool_minmax_min_speed: self
+                .spool_speed_controller
+                .get_minmax_min_speed()
+                .get::<revolution_per_minute>(),
+            spool_minmax_max_speed: self
+                .spool_speed_controller
+                .get_minmax_max_speed()
+                .get::<revolution_per_minute>(),
+            traverse_limit_inner: self
+                .traverse_controller
+                .get_limit_inner()
+                .get::<millimeter>(),
+            traverse_limit_outer: self
+                .traverse_controller
+                .get_limit_outer()
+                .get::<millimeter>(),
+            traverse_step_size: self.traverse_controller.get_step_size().get::<millimeter>(),
+            traverse_padding: self.traverse_controller.get_padding().get::<millimeter>(),
+            traverse_winding_pattern: self.winding_pattern_planner.get_pattern(),
+            traverse_crossing_angle: self
+                .winding_pattern_planner
+                .get_crossing_angle()
+                .get::<degree>(),
+        }
+    }
+
+    pub fn export_config(&self) -> Winder2Config {
+        let (kp, ki, kd) = self.puller_speed_controller.diameter_loop.get_gains();
+        Winder2Config {
+            recipe: self.current_recipe(),
+            puller_forward: self.puller_speed_controller.forward,
+            puller_strand_trims: self.puller_speed_controller.get_strand_trims().to_vec(),
+            puller_diameter_loop_gains: DiameterLoopGains { kp, ki, kd },
+            spool_adaptive_tension_target: self
+                .spool_speed_controller
+                .get_adaptive_tension_target(),
+            spool_adaptive_radius_learning_rate: self
+                .spool_speed_controller
+                .get_adaptive_radius_learning_rate(),
+            spool_adaptive_max_speed_multiplier: self
+                .spool_speed_controller
+                .get_adaptive_max_speed_multiplier(),
+            spool_adaptive_acceleration_factor: self
+                .spool_speed_controller
+                .get_adaptive_acceleration_factor(),
+            spool_adaptive_deacceleration_urgency_multiplier: self
+                .spool_speed_controller
+                .get_adaptive_deacceleration_urgency_multiplier(),
+            spool_automatic_required_meters: self
+                .spool_automatic_action
+                .target_length
+                .get::<meter>(),
+            spool_automatic_action_mode: self.spool_automatic_action.mode.clone(),
+            spool_type: self.spool_type.clone(),
+            sensor_offset: self.length_correlator.get_offset().get::<meter>(),
+            cutter_pulse_time: self.cutter.get_pulse_time().as_secs_f64() * 1000.0,
+            cutter_min_line_speed: self.cutter.get_min_line_speed().get::<meter_per_minute>(),
+            cut_on_spool_change: self.cutter.get_cut_on_spool_change(),
+        }
+    }
+
+    /// Applies an exported configuration, not allowed while winding
+    ///
+    /// The recipe part and the spool are checked before anything changes, the other settings
+    /// are applied in order and stop at the first invalid one.
+    pub fn import_config(&mut self, config: &Winder2Config) -> Result<(), anyhow::Error> {
+        config.recipe.validate()?;
+        let strands = self.puller_speed_controller.get_strand_trims().len();
+        if config.puller_strand_trims.len() != strands {
+            return Err(anyhow::anyhow!(
+                "[{}::Winder2::import_config] Configuration has {} strands, the line has {}",
+                module_path!(),
+                config.puller_strand_trims.len(),
+                strands
+            ));
+        }
+        if let Some(spool_type) = &config.spool_type {
+            spool_type.geometry.validate()?;
+            let fits = spool_type.geometry.fits_traverse(
+                Length::new::<millimeter>(config.recipe.traverse_limit_inner),
+                Length::new::<millimeter>(config.recipe.traverse_limit_outer),
+            );
+            if !fits {
+                return Err(anyhow::anyhow!(
+                    "[{}::Winder2::import_config] Traverse limits are wider than spool {}",
+                    module_path!(),
+                    spool_type.name
+                ));
+            }
+        }
+
+        // the recipe limits are checked against the imported spool instead of the current one
+        let previous_spool_type = self.spool_type.take();
+        if let Err(e) = self.apply_recipe(&config.recipe) {
+            self.spool_type = previous_spool_type;
+            return Err(e);
+        }
+        self.spool_type = config.spool_type.clone();
+
+        self.puller_set_forward(config.puller_forward);
+        for (strand, trim) in config.puller_strand_trims.iter().enumerate() {
+            self.puller_set_strand_trim(StrandTrim {
+                strand,
+                trim: *trim,
+            })?;
+        }
+        self.puller_set_diameter_loop_gains(config.puller_diameter_loop_gains.clone())?;
+
+        self.spool_set_adaptive_tension_target(config.spool_adaptive_tension_target);
+        self.spool_set_adaptive_radius_learning_rate(config.spool_adaptive_radius_learning_rate);
+        self.spool_set_adaptive_max_speed_multiplier(config.spool_adaptive_max_speed_multiplier);
+        self.spool_set_adaptive_acceleration_factor(config.spool_adaptive_acceleration_factor);
+        self.spool_set_adaptive_deacceleration_urgency_multiplier(
+            config.spool_adaptive_deacceleration_urgency_multiplier,
+        );
+        self.set_spool_automatic_required_meters(config.spool_automatic_required_meters);
+        self.set_spool_automatic_mode(config.spool_automatic_action_mode.clone());
+
+        self.set_sensor_offset(config.sensor_offset)?;
+        self.cutter_set_pulse_time(Duration::try_from_secs_f64(
+            config.cutter_pulse_time / 1000.0,
+        )?)?;
+        self.cutter_set_min_line_speed(config.cutter_min_line_speed)?;
+        self.cutter_set_cut_on_spool_change(config.cut_on_spool_change);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machines::winder2::{
+        puller_speed_controller::PullerRegulationMode,
+        spool::SpoolGeometry,
+        spool_speed_controller::SpoolSpeedControllerType,
+        winding_pattern::{WindingPattern, WindingPatternPlanner},
+    };
+
+    #[test]
+    fn test_recipe_fields_are_flat() {
+        let config = Winder2Config {
+            recipe: Winder2Recipe {
+                puller_regulation: PullerRegulationMode::Diameter,
+                puller_target_speed: 20.0,
+                puller_target_diameter: 1.75,
+                diameter_lower_tolerance: Some(0.05),
+                diameter_higher_tolerance: Some(0.05),
+                spool_regulation_mode: SpoolSpeedControllerType::Adaptive,
+                spool_minmax_min_speed: 0.0,
+                spool_minmax_max_speed: 150.0,
+                traverse_limit_inner: 22.0,
+                traverse_limit_outer: 92.0,
+                traverse_step_size: 1.75,
+                traverse_padding: 0.88,
+                traverse_winding_pattern: WindingPattern::default(),
+                traverse_crossing_angle: WindingPatternPlanner::DEFAULT_CROSSING_ANGLE_DEG,
+            },
+            puller_forward: true,
+            puller_strand_trims: vec![1.0],
+            puller_diameter_loop_gains: DiameterLoopGains {
+                kp: 10.0,
+                ki: 2.0,
+                kd: 0.0,
+            },
+            spool_adaptive_tension_target: 0.7,
+            spool_adaptive_radius_learning_rate: 0.5,
+            spool_adaptive_max_speed_multiplier: 4.0,
+            spool_adaptive_acceleration_factor: 0.2,
+            spool_adaptive_deacceleration_urgency_multiplier: 15.0,
+            spool_automatic_required_meters: 250.0,
+            spool_automatic_action_mode: SpoolAutomaticActionMode::Pull,
+            spool_type: Some(SpoolType {
+                name: "Masterspool".to_string(),
+                geometry: SpoolGeometry {
+                    core_diameter: 102.0,
+                    flange_diameter: 200.0,
+                    width: 70.0,
+                },
+            }),
+            sensor_offset: 1.5,
+            cutter_pulse_time: 200.0,
+            cutter_min_line_speed: 1.0,
+            cut_on_spool_change: false,
+        };
+
+        // a recipe can be taken from an exported configuration as is
+        let value = serde_json::to_value(&config).unwrap();
+        let recipe: Winder2Recipe = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(recipe, config.recipe);
+        let imported: Winder2Config = serde_json::from_value(value).unwrap();
+        assert_eq!(imported.spool_type, config.spool_type);
+
+        // the laser needs both tolerances or none
+        let mut recipe = recipe;
+        assert!(recipe.validate().is_ok());
+        recipe.diameter_higher_tolerance = None;
+        assert!(recipe.validate().is_err());
+        recipe.diameter_lower_tolerance = None;
+        assert!(recipe.validate().is_ok());
+    }
+}
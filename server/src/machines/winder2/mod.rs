@@ -2,39 +2,79 @@ pub mod act;
 pub mod adaptive_spool_speed_controller;
 pub mod api;
 pub mod clamp_revolution;
+pub mod cutter;
+pub mod diameter_loop;
+pub mod dual_puller;
 pub mod filament_tension;
+pub mod journal;
+pub mod length_correlation;
+pub mod length_ramp;
+pub mod line_position;
+pub mod machine_config;
 pub mod minmax_spool_speed_controller;
+pub mod motor_load;
 pub mod new;
+pub mod production;
 pub mod puller_speed_controller;
+pub mod puller_wear;
+pub mod self_test;
+pub mod slip_detection;
+pub mod speed_derating;
+pub mod spool;
 pub mod spool_speed_controller;
 pub mod tension_arm;
 pub mod traverse_controller;
+pub mod winding_pattern;
 
-use std::{fmt::Debug, sync::Weak, time::Instant};
+use std::{
+    fmt::Debug,
+    sync::{Arc, Weak},
+    time::{Duration, Instant},
+};
 
 use api::{
-    LiveValuesEvent, ModeState, PullerState, SpoolAutomaticActionMode, SpoolAutomaticActionState,
-    SpoolSpeedControllerState, StateEvent, TensionArmState, TraverseState, Winder2Events,
-    Winder2Namespace,
+    CutterState, DiameterLoopGains, DiameterLoopValues, LengthRampPhase, LiveValuesEvent,
+    ModeState, PullerAxesEvent, PullerAxisValues, PullerState, PullerWearState, SecondPullerState,
+    SpeedLoopValues, SpoolAutomaticActionMode, SpoolAutomaticActionState,
+    SpoolSpeedControllerState, SpoolState, SpoolTrackingState, StateEvent, StrandTrim,
+    TensionArmState, TraverseState, Winder2Events, Winder2Namespace, Winder2Recipe,
 };
 use control_core::socketio::event::BuildEvent;
 use control_core::{
-    converters::angular_step_converter::AngularStepConverter,
+    converters::{
+        angular_step_converter::AngularStepConverter, linear_step_converter::LinearStepConverter,
+    },
     machines::{
         connection::{CrossConnectableMachine, MachineCrossConnection},
         identification::{MachineIdentification, MachineIdentificationUnique},
         manager::MachineManager,
+        values::{
+            DIAMETER, DIAMETER_TARGET, DiameterTarget, LINE_SPEED, MachineValueBus,
+            REMOTE_KEY_SWITCH, WATER_TEMPERATURE,
+        },
     },
+    rest::mutation::{MutationError, MutationErrorKind},
     socketio::namespace::NamespaceCacheingLogic,
     uom_extensions::velocity::meter_per_minute,
 };
 use control_core_derive::Machine;
-use ethercat_hal::io::{
-    digital_input::DigitalInput, digital_output::DigitalOutput,
-    stepper_velocity_el70x1::StepperVelocityEL70x1,
-};
+use cutter::Cutter;
+use diameter_loop::DiameterLoopMode;
+use dual_puller::SecondPuller;
+use ethercat_hal::io::stepper_velocity_el70x1::StepperVelocityEL70x1;
+use journal::Winder2Journal;
+use length_correlation::{DefectMapBuilder, LaserMeasurement, LengthCorrelator, SpoolPosition};
+use length_ramp::LengthRamp;
+use line_position::LineTrail;
+use motor_load::{OverloadMonitor, motor_current};
+use production::ProductionStats;
 use puller_speed_controller::{PullerRegulationMode, PullerSpeedController};
+use puller_wear::PullerWearModel;
+use self_test::WinderSelfTest;
+use slip_detection::SlipDetector;
 use smol::lock::RwLock;
+use speed_derating::SpeedDerating;
+use spool::{SPOOL_TYPES, SpoolType, time_to_wind};
 use spool_speed_controller::SpoolSpeedController;
 use tension_arm::TensionArm;
 use traverse_controller::TraverseController;
@@ -42,14 +82,26 @@ use uom::{
     ConstZero,
     si::{
         angle::degree,
-        angular_velocity::revolution_per_minute,
-        f64::{Length, Velocity},
+        angular_velocity::{revolution_per_minute, revolution_per_second},
+        electric_current::ampere,
+        f64::{Angle, ElectricCurrent, Length, Velocity},
         length::{meter, millimeter},
         velocity::meter_per_second,
     },
 };
-
-use crate::machines::{MACHINE_WINDER_V1, VENDOR_QITECH, buffer1::BufferV1};
+use winding_pattern::{WindingPattern, WindingPatternPlanner};
+
+use crate::batches::defect_map::SpoolDefectMap;
+use crate::config::TemperatureSource;
+use crate::io_mapping::{DigitalChannel, MachineIoMapping, MachineIoSignals};
+use crate::journal::Journal;
+use crate::machines::{
+    MACHINE_WINDER_V1, VENDOR_QITECH,
+    buffer1::{BufferV1, BufferV1Mode},
+    digital_io::MappedDigitalIo,
+    gain_history::GainHistory,
+    maintenance::{MaintenanceCounters, MaintenanceEvent, MaintenancePart},
+};
 
 #[derive(Debug)]
 pub struct SpoolAutomaticAction {
@@ -57,6 +109,8 @@ pub struct SpoolAutomaticAction {
     progress_last_check: Instant,
     pub target_length: Length,
     pub mode: SpoolAutomaticActionMode,
+    /// Number of the current spool, counts up with every spool change
+    pub spool: u32,
 }
 
 #[derive(Debug, Machine)]
@@ -64,21 +118,26 @@ pub struct Winder2 {
     // drivers
     pub traverse: StepperVelocityEL70x1,
     pub puller: StepperVelocityEL70x1,
+    /// Follows the puller on dual puller lines for heavy filament
+    pub second_puller: Option<SecondPuller>,
     pub spool: StepperVelocityEL70x1,
     pub tension_arm: TensionArm,
-    pub laser: DigitalOutput,
+    /// End switches, door contacts, e-stop feedback, laser pointer, cutter and brake
+    pub io: MappedDigitalIo,
 
     // controllers
     pub traverse_controller: TraverseController,
-    pub traverse_end_stop: DigitalInput,
+    pub winding_pattern_planner: WindingPatternPlanner,
+    pub cutter: Cutter,
 
     // socketio
     namespace: Winder2Namespace,
-    last_measurement_emit: Instant,
 
     // machine connection
     pub machine_manager: Weak<RwLock<MachineManager>>,
     pub machine_identification_unique: MachineIdentificationUnique,
+    /// Publishes the line speed for other machines
+    pub values: Arc<MachineValueBus>,
 
     // connected machines
     pub connected_buffer: MachineCrossConnection<Winder2, BufferV1>,
@@ -95,13 +154,54 @@ pub struct Winder2 {
 
     // spool automatic action state
     pub spool_automatic_action: SpoolAutomaticAction,
+    /// Selected spool, the traverse limits have to fit between its flanges
+    pub spool_type: Option<SpoolType>,
+    /// Lower and higher diameter tolerance of the last recipe, passed on to the laser
+    diameter_tolerances: Option<(Length, Length)>,
+    /// Filament pulled over all spools, kept across restarts by the journal
+    pub pulled_length: Length,
+    /// Pulled length over time, the position of the line when a diameter was measured
+    line_position: LineTrail<Instant, Length>,
+    journal: Journal<Winder2Journal>,
+    /// Shift and lifetime counters, the lifetime is kept across restarts by the journal
+    pub production: ProductionStats,
+    production_last_update: Option<Instant>,
+    /// Follows the tolerance measured at the laser to the spool
+    length_correlator: LengthCorrelator<LaserMeasurement>,
+    /// Out of tolerance segments of the spool being wound
+    defect_map: DefectMapBuilder,
+    /// Out of tolerance starts on the spools, taken by the batch sampler
+    spool_defects: Vec<SpoolPosition>,
+    /// Defect maps of the closed spools, taken by the batch sampler
+    closed_defect_maps: Vec<SpoolDefectMap>,
+    /// Wear of the puller wheel and the spool shaft since their last service
+    maintenance: MaintenanceCounters,
+    /// Spool turning without winding, e.g. on a slipping belt
+    slip_detector: SlipDetector,
+    /// Stop the winder when a motor draws too much current, e.g. on a jam
+    spool_overload: OverloadMonitor,
+    puller_overload: OverloadMonitor,
+    /// Caps the line speed while a temperature of the line is too high
+    speed_derating: SpeedDerating,
+    /// Winds a fixed length and stops at it
+    length_ramp: Option<LengthRamp>,
 
     // control circuit puller
     pub puller_speed_controller: PullerSpeedController,
+    /// Gains of the diameter regulation before they were changed
+    diameter_loop_gain_history: GainHistory<DiameterLoopGains>,
+    /// Effective diameter of the puller wheel, the converter of the controller pulls with it
+    puller_wear: PullerWearModel,
+
+    /// Commissioning checks, the motors belong to them while they run
+    self_test: Option<WinderSelfTest>,
 
     /// Will be initialized as false and set to true by emit_state
     /// This way we can signal to the client that the first state emission is a default state
     emitted_default_state: bool,
+
+    /// Spool, puller and traverse speeds are synced by the motion thread instead of in act
+    dedicated_motion: bool,
 }
 
 impl CrossConnectableMachine<Winder2, BufferV1> for Winder2 {
@@ -116,16 +216,73 @@ impl Winder2 {
         machine: MACHINE_WINDER_V1,
     };
 
+    /// Older diameters are a lost signal for the diameter regulation
+    const DIAMETER_MAX_AGE: Duration = Duration::from_secs(1);
+    /// Temperatures change slowly, but a lost source must not keep the line derated
+    const TEMPERATURE_MAX_AGE: Duration = Duration::from_secs(5);
+
+    /// Tension arm angle below which the arm is considered to have dropped,
+    /// the spool speed controllers regulate between 20° and 90°
+    const FILAMENT_BREAK_ANGLE_DEG: f64 = 10.0;
+
+    pub const IO_TRAVERSE_END_STOP: &str = "traverse_end_stop";
+    pub const IO_DOOR_CONTACT: &str = "door_contact";
+    pub const IO_ESTOP_FEEDBACK: &str = "estop_feedback";
+    pub const IO_REMOTE_KEY_SWITCH: &str = "remote_key_switch";
+    pub const IO_LASER: &str = "laser";
+    pub const IO_CUTTER: &str = "cutter";
+    pub const IO_BRAKE: &str = "brake";
+    pub const IO_STACK_LIGHT_RED: &str = "stack_light_red";
+    pub const IO_STACK_LIGHT_YELLOW: &str = "stack_light_yellow";
+    pub const IO_STACK_LIGHT_GREEN: &str = "stack_light_green";
+
+    pub const IO_SIGNALS: MachineIoSignals = MachineIoSignals {
+        inputs: &[
+            Self::IO_TRAVERSE_END_STOP,
+            Self::IO_DOOR_CONTACT,
+            Self::IO_ESTOP_FEEDBACK,
+            Self::IO_REMOTE_KEY_SWITCH,
+        ],
+        outputs: &[
+            Self::IO_LASER,
+            Self::IO_CUTTER,
+            Self::IO_BRAKE,
+            Self::IO_STACK_LIGHT_RED,
+            Self::IO_STACK_LIGHT_YELLOW,
+            Self::IO_STACK_LIGHT_GREEN,
+        ],
+    };
+
+    /// Wiring of the standard winder: end stop on DI1 of the traverse stepper (role 3),
+    /// laser pointer on DO1 of the EL2002 (role 1)
+    pub fn default_io_mapping() -> MachineIoMapping {
+        MachineIoMapping {
+            inputs: [(
+                Self::IO_TRAVERSE_END_STOP.to_string(),
+                DigitalChannel::new(3, 1),
+            )]
+            .into(),
+            outputs: [(Self::IO_LASER.to_string(), DigitalChannel::new(1, 1))].into(),
+        }
+    }
+
     /// Implement Traverse
     fn set_laser(&mut self, value: bool) {
-        self.laser.set(value);
+        self.io.set_output(Self::IO_LASER, value);
+        self.emit_state();
+    }
+
+    /// Assigns the digital signals to other terminal channels
+    pub fn set_io_mapping(&mut self, mapping: MachineIoMapping) -> Result<(), anyhow::Error> {
+        self.io.set_mapping(mapping)?;
         self.emit_state();
+        Ok(())
     }
 
     /// Validates that traverse limits maintain proper constraints:
     /// - Inner limit must be smaller than outer limit
     /// - At least 0.9mm difference between inner and outer limits
-    fn validate_traverse_limits(inner: Length, outer: Length) -> bool {
+    pub(crate) fn validate_traverse_limits(inner: Length, outer: Length) -> bool {
         outer > inner + Length::new::<millimeter>(0.9)
     }
 
@@ -134,7 +291,12 @@ impl Winder2 {
         let current_outer = self.traverse_controller.get_limit_outer();
 
         // Validate the new inner limit against current outer limit
-        if !Self::validate_traverse_limits(new_inner, current_outer) {
+        if !Self::validate_traverse_limits(new_inner, current_outer)
+            || !self
+                .traverse_controller
+                .within_travel(new_inner, current_outer)
+            || !self.spool_fits_traverse(new_inner, current_outer)
+        {
             // Don't update if validation fails - keep the current value
             return;
         }
@@ -147,7 +309,12 @@ impl Winder2 {
         let current_inner = self.traverse_controller.get_limit_inner();
 
         // Validate the new outer limit against current inner limit
-        if !Self::validate_traverse_limits(current_inner, new_outer) {
+        if !Self::validate_traverse_limits(current_inner, new_outer)
+            || !self
+                .traverse_controller
+                .within_travel(current_inner, new_outer)
+            || !self.spool_fits_traverse(current_inner, new_outer)
+        {
             // Don't update if validation fails - keep the current value
             return;
         }
@@ -156,6 +323,81 @@ impl Winder2 {
         self.emit_state();
     }
 
+    /// Any limits fit while no spool type is selected
+    fn spool_fits_traverse(&self, inner: Length, outer: Length) -> bool {
+        self.spool_type
+            .as_ref()
+            .is_none_or(|spool_type| spool_type.geometry.fits_traverse(inner, outer))
+    }
+
+    /// Selects a stored spool type, fails if the current traverse limits are wider than the
+    /// spool
+    pub fn spool_select_type(&mut self, name: &str) -> Result<(), anyhow::Error> {
+        let geometry = SPOOL_TYPES
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(name)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "[{}::Winder2::spool_select_type] Spool type {} doesn't exist",
+                    module_path!(),
+                    name
+                )
+            })?;
+        let limit_inner = self.traverse_controller.get_limit_inner();
+        let limit_outer = self.traverse_controller.get_limit_outer();
+        if !geometry.fits_traverse(limit_inner, limit_outer) {
+            return Err(anyhow::anyhow!(
+                "[{}::Winder2::spool_select_type] Traverse limits {} to {} mm are wider than the spool width of {} mm",
+                module_path!(),
+                limit_inner.get::<millimeter>(),
+                limit_outer.get::<millimeter>(),
+                geometry.width
+            ));
+        }
+        self.spool_type = Some(SpoolType {
+            name: name.to_string(),
+            geometry,
+        });
+        self.emit_state();
+        Ok(())
+    }
+
+    pub fn spool_clear_type(&mut self) {
+        self.spool_type = None;
+        self.emit_state();
+    }
+
+    /// Share of the selected spool filled with the current spool progress, wound at the
+    /// target diameter
+    pub fn spool_fill(&self) -> Option<f64> {
+        self.spool_type.as_ref().map(|spool_type| {
+            spool_type.geometry.fill(
+                self.spool_automatic_action.progress,
+                self.puller_speed_controller.target_diameter,
+            )
+        })
+    }
+
+    /// Filament length until the spool is full or the automatic action triggers, whichever
+    /// comes first, unknown without a spool type and automatic action
+    pub fn spool_remaining(&self) -> Option<Length> {
+        let capacity = self.spool_type.as_ref().map(|spool_type| {
+            spool_type
+                .geometry
+                .capacity(self.puller_speed_controller.target_diameter)
+        });
+        let required = match self.spool_automatic_action.mode {
+            SpoolAutomaticActionMode::NoAction => None,
+            _ => Some(self.spool_automatic_action.target_length),
+        };
+        [capacity, required]
+            .into_iter()
+            .flatten()
+            .reduce(|a, b| a.min(b))
+            .map(|limit| limit - self.spool_automatic_action.progress)
+    }
+
     pub fn traverse_set_step_size(&mut self, step_size: f64) {
         let step_size = Length::new::<millimeter>(step_size);
         self.traverse_controller.set_step_size(step_size);
@@ -168,6 +410,18 @@ impl Winder2 {
         self.emit_state();
     }
 
+    pub fn traverse_set_winding_pattern(&mut self, pattern: WindingPattern) {
+        self.winding_pattern_planner.set_pattern(pattern);
+        self.emit_state();
+    }
+
+    pub fn traverse_set_crossing_angle(&mut self, angle: f64) -> Result<(), anyhow::Error> {
+        self.winding_pattern_planner
+            .set_crossing_angle(Angle::new::<degree>(angle))?;
+        self.emit_state();
+        Ok(())
+    }
+
     pub fn traverse_goto_limit_inner(&mut self) {
         if self.can_go_in() {
             self.traverse_controller.goto_limit_inner();
@@ -189,6 +443,86 @@ impl Winder2 {
         self.emit_state();
     }
 
+    /// Winding needs the traverse homed again afterwards
+    pub fn traverse_abort_home(&mut self) {
+        self.traverse_controller.abort_homing();
+        self.emit_state();
+    }
+
+    /// Publishes the key switch of remote sessions for [`crate::remote_session`], if assigned
+    pub fn publish_remote_key_switch(&self, now: Instant) {
+        if let Some(turned) = self.io.input(Self::IO_REMOTE_KEY_SWITCH) {
+            self.values.publish(
+                &self.machine_identification_unique,
+                REMOTE_KEY_SWITCH,
+                turned,
+                now,
+            );
+        }
+    }
+
+    /// Unassigned door contacts count as open, the cutter can't be used without one
+    fn is_guard_closed(&self) -> bool {
+        self.io.input(Self::IO_DOOR_CONTACT).unwrap_or(false)
+    }
+
+    /// Can cut capability check
+    pub fn can_cut(&self) -> bool {
+        self.io.output(Self::IO_CUTTER).is_some()
+            && self
+                .cutter
+                .interlock(
+                    self.is_guard_closed(),
+                    self.puller_speed_controller.last_speed,
+                )
+                .is_none()
+    }
+
+    /// Starts a cutter pulse, fails if an interlock blocks it
+    pub fn cut(&mut self, now: Instant) -> Result<(), anyhow::Error> {
+        if self.io.output(Self::IO_CUTTER).is_none() {
+            return Err(anyhow::anyhow!(
+                "[{}::Winder2::cut] No output is assigned to the cutter",
+                module_path!()
+            ));
+        }
+        self.cutter.start(
+            now,
+            self.is_guard_closed(),
+            self.puller_speed_controller.last_speed,
+        )?;
+        self.io.set_output(Self::IO_CUTTER, true);
+        self.emit_state();
+        Ok(())
+    }
+
+    /// Ends the cutter pulse
+    /// called by `act`
+    pub fn sync_cutter(&mut self, now: Instant) {
+        if self.cutter.update(now, self.is_guard_closed()) {
+            self.io.set_output(Self::IO_CUTTER, false);
+            self.emit_state();
+        }
+    }
+
+    pub fn cutter_set_pulse_time(&mut self, pulse_time: Duration) -> Result<(), anyhow::Error> {
+        self.cutter.set_pulse_time(pulse_time)?;
+        self.emit_state();
+        Ok(())
+    }
+
+    pub fn cutter_set_min_line_speed(&mut self, speed: f64) -> Result<(), anyhow::Error> {
+        self.cutter
+            .set_min_line_speed(Velocity::new::<meter_per_minute>(speed))?;
+        self.emit_state();
+        Ok(())
+    }
+
+    pub fn cutter_set_cut_on_spool_change(&mut self, enabled: bool) {
+        self.cutter.set_cut_on_spool_change(enabled);
+        self.emit_state();
+    }
+
     pub fn emit_live_values(&mut self) {
         let angle_deg = self.tension_arm.get_angle().get::<degree>();
 
@@ -216,6 +550,10 @@ impl Winder2 {
             .steps_to_angular_velocity(self.spool.get_speed() as f64)
             .get::<revolution_per_minute>();
 
+        let (spool_current, puller_current) = self.motor_currents();
+        let plan = self.winding_pattern_planner.get_plan();
+        let diameter_loop = &self.puller_speed_controller.diameter_loop;
+
         let live_values = LiveValuesEvent {
             traverse_position: self
                 .traverse_controller
@@ -223,8 +561,48 @@ impl Winder2 {
                 .map(|x| x.get::<millimeter>()),
             puller_speed: puller_speed.get::<meter_per_minute>(),
             spool_rpm,
+            spool_current: spool_current.map(|current| current.get::<ampere>()),
+            puller_current: puller_current.map(|current| current.get::<ampere>()),
             tension_arm_angle: angle_deg,
             spool_progress: self.spool_automatic_action.progress.get::<meter>(),
+            spool_fill: self.spool_fill(),
+            ramp_length: self
+                .length_ramp
+                .as_ref()
+                .map(|ramp| ramp.wound().get::<meter>()),
+            spool_time_to_full: self
+                .spool_remaining()
+                .and_then(|remaining| time_to_wind(remaining, puller_speed.abs()))
+                .map(|eta| eta.as_secs_f64()),
+            traverse_pitch: plan.pitch.get::<millimeter>(),
+            crossing_angle: plan.crossing_angle.get::<degree>(),
+            strand_speeds: self
+                .puller_speed_controller
+                .strand_speeds()
+                .iter()
+                .map(|speed| speed.get::<meter_per_minute>())
+                .collect(),
+            diameter_loop: DiameterLoopValues {
+                active: diameter_loop.is_active(),
+                measured_diameter: diameter_loop
+                    .get_measured()
+                    .map(|diameter| diameter.get::<millimeter>()),
+                error: diameter_loop
+                    .get_error()
+                    .map(|error| error.get::<millimeter>()),
+                speed_setpoint: diameter_loop.get_setpoint().get::<meter_per_minute>(),
+            },
+            speed_loop: SpeedLoopValues {
+                setpoint: self
+                    .puller_speed_controller
+                    .get_speed_setpoint()
+                    .get::<meter_per_minute>(),
+                speed: self
+                    .puller_speed_controller
+                    .last_speed
+                    .get::<meter_per_minute>(),
+            },
+            units: LiveValuesEvent::UNITS,
         };
 
         let event = live_values.build();
@@ -255,10 +633,17 @@ impl Winder2 {
                 is_going_out: self.traverse_controller.is_going_out(),
                 is_homed: self.traverse_controller.is_homed(),
                 is_going_home: self.traverse_controller.is_going_home(),
+                homing_status: self.traverse_controller.homing_status(),
+                travel: self.traverse_controller.get_travel().get::<millimeter>(),
                 is_traversing: self.traverse_controller.is_traversing(),
-                laserpointer: self.laser.get(),
+                laserpointer: self.io.output(Self::IO_LASER).unwrap_or(false),
                 step_size: self.traverse_controller.get_step_size().get::<millimeter>(),
                 padding: self.traverse_controller.get_padding().get::<millimeter>(),
+                winding_pattern: self.winding_pattern_planner.get_pattern(),
+                crossing_angle: self
+                    .winding_pattern_planner
+                    .get_crossing_angle()
+                    .get::<degree>(),
                 can_go_in: self.can_go_in(),
                 can_go_out: self.can_go_out(),
                 can_go_home: self.can_go_home(),
@@ -274,6 +659,28 @@ impl Winder2 {
                     .target_diameter
                     .get::<millimeter>(),
                 forward: self.puller_speed_controller.forward,
+                strand_trims: self.puller_speed_controller.get_strand_trims().to_vec(),
+                diameter_loop_gains: self.puller_diameter_loop_gains(),
+                diameter_loop_gain_history: self.diameter_loop_gain_history.entries(),
+                diameter_loop_mode: self.puller_speed_controller.diameter_loop.get_mode(),
+                manual_speed: self
+                    .puller_speed_controller
+                    .diameter_loop
+                    .get_manual_speed()
+                    .map(|speed| speed.get::<meter_per_minute>()),
+                second_axis: self
+                    .second_puller
+                    .as_ref()
+                    .map(|second_puller| SecondPullerState {
+                        trim: second_puller.trim(),
+                        load_sharing: second_puller.load_sharing(),
+                    }),
+                wear: PullerWearState {
+                    factor: self.puller_wear.factor(),
+                    effective_diameter: self.puller_wear.effective_diameter().get::<millimeter>(),
+                    wear: self.puller_wear.wear().get::<millimeter>(),
+                    history: self.puller_wear.history().to_vec(),
+                },
             },
             mode_state: ModeState {
                 mode: self.mode.clone().into(),
@@ -310,10 +717,39 @@ impl Winder2 {
                 spool_required_meters: self.spool_automatic_action.target_length.get::<meter>(),
                 spool_automatic_action_mode: self.spool_automatic_action.mode.clone(),
             },
+            spool_state: SpoolState {
+                spool_type: self.spool_type.clone(),
+                capacity: self.spool_type.as_ref().map(|spool_type| {
+                    spool_type
+                        .geometry
+                        .capacity(self.puller_speed_controller.target_diameter)
+                        .get::<meter>()
+                }),
+            },
+            spool_tracking_state: SpoolTrackingState {
+                spool: self.spool_automatic_action.spool,
+                sensor_offset: self.length_correlator.get_offset().get::<meter>(),
+            },
+            cutter_state: CutterState {
+                pulse_time: self.cutter.get_pulse_time().as_secs_f64() * 1000.0,
+                min_line_speed: self.cutter.get_min_line_speed().get::<meter_per_minute>(),
+                cut_on_spool_change: self.cutter.get_cut_on_spool_change(),
+                guard_closed: self.is_guard_closed(),
+                is_cutting: self.cutter.is_cutting(),
+                can_cut: self.can_cut(),
+            },
             connected_machine_state: self.connected_buffer.to_state(),
+            speed_derating: self.speed_derating.state(),
+            length_ramp: self.length_ramp.as_ref().map(LengthRamp::state),
+            units: StateEvent::UNITS,
         }
     }
 
+    pub fn emit_schema(&mut self) {
+        let event = LiveValuesEvent::schema().build();
+        self.namespace.emit(Winder2Events::Schema(event));
+    }
+
     pub fn emit_state(&mut self) {
         let state_event = self.build_state_event();
         let event = state_event.build();
@@ -321,11 +757,14 @@ impl Winder2 {
     }
 
     pub fn sync_traverse_speed(&mut self) {
-        self.traverse_controller.update_speed(
-            &mut self.traverse,
-            &self.traverse_end_stop,
+        let end_stop = self.io.input(Self::IO_TRAVERSE_END_STOP).unwrap_or(false);
+        let plan = self.winding_pattern_planner.plan(
             self.spool_speed_controller.get_speed(),
-        )
+            self.puller_speed_controller.last_speed,
+            self.traverse_controller.get_step_size(),
+        );
+        self.traverse_controller
+            .update_speed(&mut self.traverse, end_stop, plan.speed)
     }
 
     /// Can wind capability check
@@ -336,6 +775,13 @@ impl Winder2 {
             && !self.traverse_controller.is_going_home()
     }
 
+    /// Tension arm dropped while winding, which happens when the filament breaks
+    pub fn is_filament_broken(&self) -> bool {
+        self.mode == Winder2Mode::Wind
+            && self.tension_arm.zeroed
+            && self.tension_arm.get_angle().get::<degree>() < Self::FILAMENT_BREAK_ANGLE_DEG
+    }
+
     /// Can go to inner limit capability check
     pub fn can_go_in(&self) -> bool {
         // Check if traverse is homed, not in standby, not traversing
@@ -377,6 +823,12 @@ impl Winder2 {
         let should_update = *mode != Winder2Mode::Wind || self.can_wind();
 
         if should_update {
+            // starting again acknowledges a motor overload
+            if matches!(mode, Winder2Mode::Wind | Winder2Mode::Pull) {
+                self.spool_overload.reset();
+                self.puller_overload.reset();
+            }
+
             // all transitions are allowed
             self.mode = mode.clone();
 
@@ -384,10 +836,26 @@ impl Winder2 {
             self.set_spool_mode(mode);
             self.set_puller_mode(mode);
             self.set_traverse_mode(mode);
+            self.sync_buffer_mode();
+            self.record_journal(Instant::now());
         }
         self.emit_state();
     }
 
+    /// Lets the connected buffer follow the mode
+    ///
+    /// While pulling without winding the buffer stores the line, so the spool can be changed
+    /// without stopping the extrusion. Winding again empties the buffer down to its setpoint.
+    fn sync_buffer_mode(&self) {
+        let mode = match self.mode {
+            Winder2Mode::Standby | Winder2Mode::Hold => BufferV1Mode::Standby,
+            Winder2Mode::Pull => BufferV1Mode::FillingBuffer,
+            Winder2Mode::Wind => BufferV1Mode::EmptyingBuffer,
+        };
+        self.connected_buffer
+            .with_connected_machine(|buffer| buffer.follow_winder_mode(mode));
+    }
+
     /// Apply the mode changes to the spool
     ///
     /// It contains a transition matrix for atomic changes.
@@ -513,6 +981,14 @@ impl Winder2 {
     ///
     /// It contains a transition matrix for atomic changes.
     /// It will set [`Self::puller_mode`]
+    /// Powers the motors of every puller axis
+    fn set_puller_enabled(&mut self, enabled: bool) {
+        self.puller.set_enabled(enabled);
+        if let Some(second_puller) = &mut self.second_puller {
+            second_puller.stepper.set_enabled(enabled);
+        }
+    }
+
     fn set_puller_mode(&mut self, mode: &Winder2Mode) {
         // Convert to `Winder2Mode` to `PullerMode`
         let mode: PullerMode = mode.clone().into();
@@ -523,18 +999,18 @@ impl Winder2 {
                 PullerMode::Standby => {}
                 PullerMode::Hold => {
                     // From [`PullerMode::Standby`] to [`PullerMode::Hold`]
-                    self.puller.set_enabled(true);
+                    self.set_puller_enabled(true);
                 }
                 PullerMode::Pull => {
                     // From [`PullerMode::Standby`] to [`PullerMode::Pull`]
-                    self.puller.set_enabled(true);
+                    self.set_puller_enabled(true);
                     self.puller_speed_controller.set_enabled(true);
                 }
             },
             PullerMode::Hold => match mode {
                 PullerMode::Standby => {
                     // From [`PullerMode::Hold`] to [`PullerMode::Standby`]
-                    self.puller.set_enabled(false);
+                    self.set_puller_enabled(false);
                 }
                 PullerMode::Hold => {}
                 PullerMode::Pull => {
@@ -545,7 +1021,7 @@ impl Winder2 {
             PullerMode::Pull => match mode {
                 PullerMode::Standby => {
                     // From [`PullerMode::Pull`] to [`PullerMode::Standby`]
-                    self.puller.set_enabled(false);
+                    self.set_puller_enabled(false);
                     self.puller_speed_controller.set_enabled(false);
                 }
                 PullerMode::Hold => {
@@ -611,6 +1087,12 @@ impl Winder2 {
         }
 
         if self.spool_automatic_action.progress >= self.spool_automatic_action.target_length {
+            // cut while the line still runs, before hold ramps it down
+            if self.cutter.get_cut_on_spool_change() {
+                if let Err(e) = self.cut(now) {
+                    tracing::warn!("Spool change without cut: {:?}", e);
+                }
+            }
             match self.spool_automatic_action.mode {
                 SpoolAutomaticActionMode::NoAction => (),
                 SpoolAutomaticActionMode::Pull => {
@@ -625,9 +1107,15 @@ impl Winder2 {
         }
     }
 
-    pub const fn stop_or_pull_spool_reset(&mut self, now: Instant) {
+    /// Starts a new spool
+    pub fn stop_or_pull_spool_reset(&mut self, now: Instant) {
+        // a defect running on starts a new segment on the new spool
+        self.close_defect_map();
         self.spool_automatic_action.progress = Length::ZERO;
+        self.spool_automatic_action.spool += 1;
         self.spool_automatic_action.progress_last_check = now;
+        self.record_journal(now);
+        self.emit_state();
     }
 
     pub fn calculate_spool_auto_progress_(&mut self, now: Instant) {
@@ -646,18 +1134,294 @@ impl Winder2 {
 
         // Update total meters pulled
         self.spool_automatic_action.progress += meters_pulled_this_interval;
+        self.pulled_length += meters_pulled_this_interval;
         self.spool_automatic_action.progress_last_check = now;
     }
 
+    /// Counts the wear of the puller wheel and the spool shaft, called by `act`
+    pub fn sync_maintenance(&mut self, now: Instant) {
+        let dt = self.maintenance.elapsed(now).as_secs_f64();
+        let pulled = self
+            .puller_speed_controller
+            .last_speed
+            .get::<meter_per_second>()
+            * dt;
+        let revolutions = self
+            .spool_speed_controller
+            .get_speed()
+            .get::<revolution_per_second>()
+            * dt;
+        self.maintenance
+            .add(MaintenancePart::PullerWheel, pulled / 1000.0);
+        self.maintenance
+            .add(MaintenancePart::SpoolShaft, revolutions);
+        self.maintenance.sync_journal(now);
+    }
+
+    /// Compares the spool speed to the pulled length to detect a slipping spool, called by `act`
+    pub fn detect_spool_slip(&mut self, now: Instant) {
+        if self.mode != Winder2Mode::Wind {
+            self.slip_detector.interrupt();
+            return;
+        }
+        let was_slipping = self.slip_detector.is_slipping();
+        self.slip_detector.add_sample(
+            now,
+            self.spool_speed_controller.get_speed(),
+            self.puller_speed_controller.last_speed,
+            self.spool_type
+                .as_ref()
+                .map(|spool_type| &spool_type.geometry),
+        );
+        if self.slip_detector.is_slipping() && !was_slipping {
+            tracing::warn!(
+                "Spool of winder {} slips, the filament would be wound on a radius of {:?} mm",
+                self.machine_identification_unique,
+                self.slip_detector
+                    .implied_radius()
+                    .map(|radius| radius.get::<millimeter>())
+            );
+        }
+    }
+
+    /// Current drawn by the spool and the puller motor, `None` without info data
+    ///
+    /// The puller current is the one of the axis drawing more on a dual puller.
+    pub fn motor_currents(&self) -> (Option<ElectricCurrent>, Option<ElectricCurrent>) {
+        let second_puller_current = self
+            .second_puller
+            .as_ref()
+            .and_then(|second_puller| second_puller.stepper.get_info_data())
+            .map(motor_current);
+        let puller_current = match (
+            self.puller.get_info_data().map(motor_current),
+            second_puller_current,
+        ) {
+            (Some(first), Some(second)) => Some(first.max(second)),
+            (first, second) => first.or(second),
+        };
+        (
+            self.spool.get_info_data().map(motor_current),
+            puller_current,
+        )
+    }
+
+    /// Stops the winder when a motor is overloaded, called by `act`
+    pub fn monitor_motor_load(&mut self, now: Instant) {
+        if !matches!(self.mode, Winder2Mode::Wind | Winder2Mode::Pull) {
+            return;
+        }
+        let (spool_current, puller_current) = self.motor_currents();
+        for (motor, monitor, current) in [
+            ("spool", &mut self.spool_overload, spool_current),
+            ("puller", &mut self.puller_overload, puller_current),
+        ] {
+            if monitor.update(now, current) {
+                tracing::error!(
+                    "{} motor of winder {} is overloaded with {:?} A, stopping",
+                    motor,
+                    self.machine_identification_unique,
+                    current.map(|current| current.get::<ampere>())
+                );
+            }
+        }
+        if self.is_motor_overloaded() {
+            // hold ramps spool and puller down instead of cutting the power
+            self.set_mode(&Winder2Mode::Hold);
+        }
+    }
+
+    /// Caps the puller speed by the derating rules, called by `act`
+    pub fn apply_speed_derating(&mut self, now: Instant) {
+        let values = &self.values;
+        let changed = self.speed_derating.update(|source| {
+            let key = match source {
+                TemperatureSource::WaterBath => WATER_TEMPERATURE,
+            };
+            values
+                .latest(key)
+                .filter(|sample| !sample.is_stale(now, Self::TEMPERATURE_MAX_AGE))
+                .map(|sample| sample.value)
+        });
+        if !changed {
+            return;
+        }
+        let max_line_speed = self.speed_derating.max_line_speed();
+        self.puller_speed_controller.set_max_speed(max_line_speed);
+        match self.speed_derating.state() {
+            Some(state) => tracing::warn!(
+                "Line speed of winder {} is derated to {} m/min, {}",
+                self.machine_identification_unique,
+                state.max_line_speed,
+                state.reason
+            ),
+            None => tracing::info!(
+                "Line speed of winder {} is no longer derated",
+                self.machine_identification_unique
+            ),
+        }
+        self.emit_state();
+    }
+
+    /// Winds `target` in wind mode and holds once the line would stop at it
+    pub fn start_length_ramp(&mut self, target: Length, now: Instant) -> Result<(), anyhow::Error> {
+        if target <= Length::ZERO {
+            return Err(anyhow::anyhow!(
+                "[{}::Winder2::start_length_ramp] Target length has to be positive",
+                module_path!()
+            ));
+        }
+        if !self.can_wind() {
+            return Err(anyhow::anyhow!(
+                "[{}::Winder2::start_length_ramp] Zero the tension arm and home the traverse first",
+                module_path!()
+            ));
+        }
+        self.length_ramp = Some(LengthRamp::new(target, now));
+        self.set_mode(&Winder2Mode::Wind);
+        Ok(())
+    }
+
+    pub fn cancel_length_ramp(&mut self) {
+        if self.length_ramp.take().is_some() {
+            self.emit_state();
+        }
+    }
+
+    /// Holds once the remaining length is within the stopping distance, called by `act`
+    pub fn sync_length_ramp(&mut self, now: Instant) {
+        let Some(ramp) = &mut self.length_ramp else {
+            return;
+        };
+        // stopped by something else, e.g. the spool automatic action or a safe stop
+        if ramp.phase() == LengthRampPhase::Running && self.mode != Winder2Mode::Wind {
+            tracing::info!(
+                "Length ramp of winder {} ended by a mode change after {:.2} m",
+                self.machine_identification_unique,
+                ramp.wound().get::<meter>()
+            );
+            self.cancel_length_ramp();
+            return;
+        }
+        let phase = ramp.update(
+            now,
+            self.puller_speed_controller.last_speed,
+            self.puller_speed_controller.stopping_distance(),
+        );
+        match phase {
+            Some(LengthRampPhase::Stopping) => self.set_mode(&Winder2Mode::Hold),
+            Some(LengthRampPhase::Done) => {
+                tracing::info!(
+                    "Length ramp of winder {} stopped after {:.2} m",
+                    self.machine_identification_unique,
+                    ramp.wound().get::<meter>()
+                );
+                self.emit_state();
+            }
+            Some(LengthRampPhase::Running) | None => (),
+        }
+    }
+
+    pub const fn is_motor_overloaded(&self) -> bool {
+        self.spool_overload.is_tripped() || self.puller_overload.is_tripped()
+    }
+
+    pub const fn is_spool_slipping(&self) -> bool {
+        self.slip_detector.is_slipping()
+    }
+
+    /// Emits the status of every puller axis, limited to the emit rate of the event
+    pub fn emit_puller_axes(&mut self) {
+        if !self
+            .namespace
+            .namespace
+            .lock_blocking()
+            .is_due("PullerAxesEvent")
+        {
+            return;
+        }
+        let axis = |stepper: &StepperVelocityEL70x1, ratio: f64| {
+            let angular_velocity = self
+                .puller_speed_controller
+                .converter
+                .steps_to_angular_velocity(stepper.get_speed() as f64);
+            PullerAxisValues {
+                enabled: stepper.is_enabled(),
+                speed: self
+                    .puller_speed_controller
+                    .angular_velocity_to_speed(angular_velocity)
+                    .get::<meter_per_minute>(),
+                ratio,
+                current: stepper
+                    .get_info_data()
+                    .map(|info_data| motor_current(info_data).get::<ampere>()),
+            }
+        };
+        let mut axes = vec![axis(&self.puller, 1.0)];
+        if let Some(second_puller) = &self.second_puller {
+            axes.push(axis(&second_puller.stepper, second_puller.ratio()));
+        }
+        let event = PullerAxesEvent { axes };
+        self.namespace
+            .emit(Winder2Events::PullerAxes(event.build()));
+    }
+
+    /// Emits the maintenance counters, limited to the emit rate of the event
+    pub fn emit_maintenance(&mut self) {
+        if !self
+            .namespace
+            .namespace
+            .lock_blocking()
+            .is_due("MaintenanceEvent")
+        {
+            return;
+        }
+        let event = MaintenanceEvent {
+            counters: self.maintenance.values(),
+        };
+        self.namespace
+            .emit(Winder2Events::Maintenance(event.build()));
+    }
+
     /// Implement Puller
     /// called by `act`
     pub fn sync_puller_speed(&mut self, t: Instant) {
-        let angular_velocity = self.puller_speed_controller.calc_angular_velocity(t);
+        let diameter = self
+            .values
+            .latest(DIAMETER)
+            .filter(|sample| !sample.is_stale(t, Self::DIAMETER_MAX_AGE));
+        let measured_at = diameter.map(|sample| self.line_position_at(sample.published));
+        self.puller_speed_controller
+            .diameter_loop
+            .set_line_position(self.pulled_length, measured_at);
+        let diameter = diameter.map(|sample| sample.value);
+        let angular_velocity = self
+            .puller_speed_controller
+            .calc_angular_velocity(t, diameter);
         let steps_per_second = self
             .puller_speed_controller
             .converter
             .angular_velocity_to_steps(angular_velocity);
         let _ = self.puller.set_speed(steps_per_second);
+        if let Some(second_puller) = &mut self.second_puller {
+            // behind the same ramp as the first axis
+            second_puller.share_load(
+                t,
+                self.puller_speed_controller.get_speed_setpoint() > Velocity::ZERO,
+                self.puller.get_info_data().map(motor_current),
+                second_puller.stepper.get_info_data().map(motor_current),
+            );
+            let _ = second_puller
+                .stepper
+                .set_speed(steps_per_second * second_puller.ratio());
+        }
+
+        self.values.publish(
+            &self.machine_identification_unique,
+            LINE_SPEED,
+            self.puller_speed_controller.last_speed,
+            t,
+        );
     }
 
     pub fn puller_set_regulation(&mut self, puller_regulation_mode: PullerRegulationMode) {
@@ -689,6 +1453,128 @@ impl Winder2 {
         self.emit_state();
     }
 
+    pub fn puller_set_strand_trim(&mut self, strand_trim: StrandTrim) -> Result<(), anyhow::Error> {
+        self.puller_speed_controller
+            .set_strand_trim(strand_trim.strand, strand_trim.trim)?;
+        self.emit_state();
+        Ok(())
+    }
+
+    /// Adjusts the puller circumference by a length the winder counted and the operator measured
+    pub fn puller_calibrate_length(
+        &mut self,
+        counted: Length,
+        measured: Length,
+        now: Instant,
+    ) -> Result<(), anyhow::Error> {
+        self.puller_wear.calibrate(counted, measured, now)?;
+        self.sync_puller_diameter();
+        self.emit_state();
+        Ok(())
+    }
+
+    /// Pulls with the effective diameter of the wheel
+    fn sync_puller_diameter(&mut self) {
+        let converter = &mut self.puller_speed_controller.converter;
+        *converter = LinearStepConverter::from_diameter(
+            converter.steps_per_revolution(),
+            self.puller_wear.effective_diameter(),
+        );
+    }
+
+    /// Starts counting the wear of a part again after it was serviced
+    pub fn reset_maintenance_counter(
+        &mut self,
+        part: MaintenancePart,
+        now: Instant,
+    ) -> Result<(), anyhow::Error> {
+        self.maintenance.reset(part, now)?;
+        if part == MaintenancePart::PullerWheel {
+            // a new wheel has its nominal diameter
+            self.puller_wear.reset(now);
+            self.sync_puller_diameter();
+            self.emit_state();
+        }
+        Ok(())
+    }
+
+    pub fn puller_set_second_axis_trim(&mut self, trim: f64) -> Result<(), anyhow::Error> {
+        let second_puller = self.second_puller.as_mut().ok_or_else(|| {
+            anyhow::anyhow!(
+                "[{}::Winder2::puller_set_second_axis_trim] The winder has no second puller axis",
+                module_path!()
+            )
+        })?;
+        second_puller.set_trim(trim)?;
+        self.emit_state();
+        Ok(())
+    }
+
+    pub fn puller_set_load_sharing(&mut self, enabled: bool) -> Result<(), anyhow::Error> {
+        let second_puller = self.second_puller.as_mut().ok_or_else(|| {
+            anyhow::anyhow!(
+                "[{}::Winder2::puller_set_load_sharing] The winder has no second puller axis",
+                module_path!()
+            )
+        })?;
+        second_puller.set_load_sharing(enabled);
+        self.emit_state();
+        Ok(())
+    }
+
+    const fn puller_diameter_loop_gains(&self) -> DiameterLoopGains {
+        let (kp, ki, kd) = self.puller_speed_controller.diameter_loop.get_gains();
+        DiameterLoopGains { kp, ki, kd }
+    }
+
+    pub fn puller_set_diameter_loop_gains(
+        &mut self,
+        gains: DiameterLoopGains,
+    ) -> Result<(), anyhow::Error> {
+        let previous = self.puller_diameter_loop_gains();
+        self.puller_speed_controller
+            .diameter_loop
+            .set_gains(gains.kp, gains.ki, gains.kd)?;
+        self.diameter_loop_gain_history.record(previous, &gains);
+        self.emit_state();
+        Ok(())
+    }
+
+    pub fn puller_revert_diameter_loop_gains(&mut self) -> Result<(), anyhow::Error> {
+        let gains = self.diameter_loop_gain_history.pop().ok_or_else(|| {
+            anyhow::anyhow!(
+                "[{}::Winder2::puller_revert_diameter_loop_gains] The gains were not changed",
+                module_path!()
+            )
+        })?;
+        self.puller_speed_controller
+            .diameter_loop
+            .set_gains(gains.kp, gains.ki, gains.kd)?;
+        tracing::info!(
+            "Reverted the diameter loop gains of {} to kp {}, ki {}, kd {}",
+            self.machine_identification_unique,
+            gains.kp,
+            gains.ki,
+            gains.kd
+        );
+        self.emit_state();
+        Ok(())
+    }
+
+    pub fn puller_set_diameter_loop_mode(&mut self, mode: DiameterLoopMode) {
+        self.puller_speed_controller.diameter_loop.set_mode(mode);
+        self.emit_state();
+    }
+
+    /// Set the manual line speed of the diameter regulation in m/min
+    pub fn puller_set_manual_speed(&mut self, speed: f64) -> Result<(), anyhow::Error> {
+        self.puller_speed_controller
+            .diameter_loop
+            .set_manual_speed(Velocity::new::<meter_per_minute>(speed))?;
+        self.emit_state();
+        Ok(())
+    }
+
     // Spool Speed Controller API methods
     pub fn spool_set_regulation_mode(
         &mut self,
@@ -754,6 +1640,106 @@ impl Winder2 {
         self.emit_state();
     }
 
+    /// Applies a validated recipe
+    ///
+    /// Traverse limits are written as a pair since setting them one by one
+    /// could be rejected against the previous limits. Not allowed while winding.
+    pub fn apply_recipe(&mut self, recipe: &Winder2Recipe) -> Result<(), anyhow::Error> {
+        if self.mode == Winder2Mode::Wind {
+            return Err(MutationError::new(
+                MutationErrorKind::NotReady,
+                format!(
+                    "[{}::Winder2::apply_recipe] Recipes can't be applied while winding",
+                    module_path!()
+                ),
+            )
+            .into());
+        }
+
+        let limit_inner = Length::new::<millimeter>(recipe.traverse_limit_inner);
+        let limit_outer = Length::new::<millimeter>(recipe.traverse_limit_outer);
+        if !self
+            .traverse_controller
+            .within_travel(limit_inner, limit_outer)
+        {
+            return Err(anyhow::anyhow!(
+                "[{}::Winder2::apply_recipe] Traverse limits must lie within the travel of {} mm",
+                module_path!(),
+                self.traverse_controller.get_travel().get::<millimeter>()
+            ));
+        }
+        if !self.spool_fits_traverse(limit_inner, limit_outer) {
+            return Err(anyhow::anyhow!(
+                "[{}::Winder2::apply_recipe] Traverse limits are wider than the selected spool",
+                module_path!()
+            ));
+        }
+
+        self.puller_speed_controller
+            .set_regulation_mode(recipe.puller_regulation.clone());
+        self.puller_speed_controller
+            .set_target_speed(Velocity::new::<meter_per_minute>(
+                recipe.puller_target_speed,
+            ));
+        self.puller_speed_controller
+            .set_target_diameter(Length::new::<millimeter>(recipe.puller_target_diameter));
+        self.diameter_tolerances = recipe
+            .diameter_lower_tolerance
+            .zip(recipe.diameter_higher_tolerance)
+            .map(|(lower, higher)| {
+                (
+                    Length::new::<millimeter>(lower),
+                    Length::new::<millimeter>(higher),
+                )
+            });
+
+        self.spool_speed_controller
+            .set_type(recipe.spool_regulation_mode.clone());
+        // raise the max first so the min is never validated against a smaller old max
+        let min_speed = uom::si::f64::AngularVelocity::new::<revolution_per_minute>(
+            recipe.spool_minmax_min_speed,
+        );
+        let max_speed = uom::si::f64::AngularVelocity::new::<revolution_per_minute>(
+            recipe.spool_minmax_max_speed,
+        );
+        if max_speed >= self.spool_speed_controller.get_minmax_min_speed() {
+            self.spool_speed_controller
+                .set_minmax_max_speed(max_speed)?;
+            self.spool_speed_controller
+                .set_minmax_min_speed(min_speed)?;
+        } else {
+            self.spool_speed_controller
+                .set_minmax_min_speed(min_speed)?;
+            self.spool_speed_controller
+                .set_minmax_max_speed(max_speed)?;
+        }
+
+        self.traverse_controller.set_limit_inner(limit_inner);
+        self.traverse_controller.set_limit_outer(limit_outer);
+        self.traverse_controller
+            .set_step_size(Length::new::<millimeter>(recipe.traverse_step_size));
+        self.traverse_controller
+            .set_padding(Length::new::<millimeter>(recipe.traverse_padding));
+        self.winding_pattern_planner
+            .set_pattern(recipe.traverse_winding_pattern);
+        self.winding_pattern_planner
+            .set_crossing_angle(Angle::new::<degree>(recipe.traverse_crossing_angle))?;
+
+        // the laser of the line adopts the target, so it is entered once
+        self.values.publish(
+            &self.machine_identification_unique,
+            DIAMETER_TARGET,
+            DiameterTarget {
+                diameter: self.puller_speed_controller.target_diameter,
+                tolerances: self.diameter_tolerances,
+            },
+            Instant::now(),
+        );
+
+        self.emit_state();
+        Ok(())
+    }
+
     /// implement machine connection
     /// set connected buffer
     pub fn set_connected_buffer(
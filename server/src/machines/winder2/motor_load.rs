@@ -0,0 +1,103 @@
+use std::time::{Duration, Instant};
+use uom::si::{electric_current::milliampere, f64::ElectricCurrent};
+
+/// Current of a stepper from the coil currents the terminal reports as info data
+///
+/// The coils are driven with a sine and a cosine, so their combined amplitude is the current
+/// the motor draws regardless of the rotor position.
+pub fn motor_current(info_data: [u16; 2]) -> ElectricCurrent {
+    let [coil_a, coil_b] = info_data.map(|current| f64::from(current as i16));
+    ElectricCurrent::new::<milliampere>(coil_a.hypot(coil_b))
+}
+
+/// Trips once a motor draws more than its threshold, e.g. on a jammed spool or puller
+///
+/// Stays tripped until [`Self::reset`], so the stop isn't undone once the current drops with
+/// the stopped motor.
+#[derive(Debug)]
+pub struct OverloadMonitor {
+    /// `None` disables the monitor
+    threshold: Option<ElectricCurrent>,
+    over_since: Option<Instant>,
+    tripped: bool,
+}
+
+impl OverloadMonitor {
+    /// Acceleration draws peaks above the threshold, a jam holds it
+    const HOLD: Duration = Duration::from_millis(500);
+
+    pub const fn new(threshold: Option<ElectricCurrent>) -> Self {
+        Self {
+            threshold,
+            over_since: None,
+            tripped: false,
+        }
+    }
+
+    /// Returns whether the monitor tripped with this sample
+    pub fn update(&mut self, now: Instant, current: Option<ElectricCurrent>) -> bool {
+        let over = self
+            .threshold
+            .zip(current)
+            .is_some_and(|(threshold, current)| current > threshold);
+        if !over {
+            self.over_since = None;
+            return false;
+        }
+        let over_since = *self.over_since.get_or_insert(now);
+        if self.tripped || now.duration_since(over_since) < Self::HOLD {
+            return false;
+        }
+        self.tripped = true;
+        true
+    }
+
+    pub const fn is_tripped(&self) -> bool {
+        self.tripped
+    }
+
+    pub const fn reset(&mut self) {
+        self.over_since = None;
+        self.tripped = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uom::si::electric_current::ampere;
+
+    #[test]
+    fn test_motor_current() {
+        // negative coil currents arrive as two's complement
+        let current = motor_current([(-1200i16) as u16, 1600]);
+        assert!((current.get::<ampere>() - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_overload() {
+        let amps = ElectricCurrent::new::<ampere>;
+        let mut monitor = OverloadMonitor::new(Some(amps(2.0)));
+        let now = Instant::now();
+
+        // a peak while accelerating passes
+        assert!(!monitor.update(now, Some(amps(2.5))));
+        assert!(!monitor.update(now + Duration::from_millis(300), Some(amps(1.5))));
+        assert!(!monitor.update(now + Duration::from_millis(600), Some(amps(2.5))));
+        assert!(!monitor.update(now + Duration::from_millis(900), Some(amps(2.5))));
+        assert!(monitor.update(now + Duration::from_millis(1100), Some(amps(2.5))));
+        assert!(!monitor.update(now + Duration::from_millis(1200), Some(amps(2.5))));
+
+        // the stopped motor doesn't clear the overload
+        monitor.update(now + Duration::from_millis(1300), Some(amps(0.0)));
+        assert!(monitor.is_tripped());
+        monitor.reset();
+        assert!(!monitor.is_tripped());
+
+        // without a threshold or a measured current nothing trips
+        let mut disabled = OverloadMonitor::new(None);
+        disabled.update(now, Some(amps(10.0)));
+        assert!(!disabled.update(now + Duration::from_secs(1), Some(amps(10.0))));
+        assert!(!monitor.update(now + Duration::from_secs(5), None));
+    }
+}
@@ -3,10 +3,29 @@ use std::time::Instant;
 use super::api::Winder2Namespace;
 use super::tension_arm::TensionArm;
 use super::{Winder2, Winder2Mode};
+use crate::config::config;
+use crate::io_mapping::IO_MAPPINGS;
+use crate::journal::Journal;
+use crate::machines::digital_io::{DigitalIoPool, MappedDigitalIo};
+use crate::machines::gain_history::GainHistory;
 use crate::machines::get_ethercat_device;
+use crate::machines::maintenance::{MaintenanceCounters, MaintenancePart};
+use crate::machines::winder2::cutter::Cutter;
+use crate::machines::winder2::dual_puller::SecondPuller;
+use crate::machines::winder2::length_correlation::{DefectMapBuilder, LengthCorrelator};
+use crate::machines::winder2::line_position::LineTrail;
+use crate::machines::winder2::motor_load::OverloadMonitor;
+use crate::machines::winder2::production::ProductionStats;
 use crate::machines::winder2::puller_speed_controller::PullerSpeedController;
+use crate::machines::winder2::puller_wear::PullerWearModel;
+use crate::machines::winder2::slip_detection::SlipDetector;
+use crate::machines::winder2::speed_derating::SpeedDerating;
 use crate::machines::winder2::spool_speed_controller::SpoolSpeedController;
 use crate::machines::winder2::traverse_controller::TraverseController;
+use crate::machines::winder2::winding_pattern::{WindingPattern, WindingPatternPlanner};
+use crate::motion::{motion_thread_running, motion_update_period};
+use crate::serial::registry::SERIAL_DEVICE_REGISTRY;
+use crate::simulation::winder::SimulatedWinder;
 use anyhow::Error;
 use control_core::converters::angular_step_converter::AngularStepConverter;
 use control_core::converters::linear_step_converter::LinearStepConverter;
@@ -18,11 +37,11 @@ use control_core::machines::new::{
 use control_core::uom_extensions::velocity::meter_per_minute;
 use ethercat_hal::coe::ConfigurableDevice;
 use ethercat_hal::devices::ek1100::EK1100;
-use ethercat_hal::devices::el2002::{EL2002, EL2002_IDENTITY_B, EL2002Port};
+use ethercat_hal::devices::el2002::{EL2002, EL2002_IDENTITY_B};
 use ethercat_hal::devices::el7031::coe::EL7031Configuration;
 use ethercat_hal::devices::el7031::pdo::EL7031PredefinedPdoAssignment;
 use ethercat_hal::devices::el7031::{
-    EL7031, EL7031_IDENTITY_A, EL7031_IDENTITY_B, EL7031DigitalInputPort, EL7031StepperPort,
+    EL7031, EL7031_IDENTITY_A, EL7031_IDENTITY_B, EL7031StepperPort,
 };
 use ethercat_hal::devices::el7031_0030::coe::EL7031_0030Configuration;
 use ethercat_hal::devices::el7031_0030::pdo::EL7031_0030PredefinedPdoAssignment;
@@ -30,16 +49,17 @@ use ethercat_hal::devices::el7031_0030::{
     self, EL7031_0030, EL7031_0030_IDENTITY_A, EL7031_0030AnalogInputPort, EL7031_0030StepperPort,
 };
 use ethercat_hal::devices::el7041_0052::coe::EL7041_0052Configuration;
+use ethercat_hal::devices::el7041_0052::pdo::EL7041_0052PredefinedPdoAssignment;
 use ethercat_hal::devices::el7041_0052::{EL7041_0052, EL7041_0052_IDENTITY_A, EL7041_0052Port};
 use ethercat_hal::devices::{ek1100::EK1100_IDENTITY_A, el2002::EL2002_IDENTITY_A};
 use ethercat_hal::io::analog_input::AnalogInput;
-use ethercat_hal::io::digital_input::DigitalInput;
-use ethercat_hal::io::digital_output::DigitalOutput;
 use ethercat_hal::io::stepper_velocity_el70x1::StepperVelocityEL70x1;
 use ethercat_hal::shared_config;
 use ethercat_hal::shared_config::el70x1::{EL70x1OperationMode, StmMotorConfiguration};
 use uom::ConstZero;
-use uom::si::f64::{Length, Velocity};
+use uom::si::angle::degree;
+use uom::si::electric_current::ampere;
+use uom::si::f64::{Angle, ElectricCurrent, Length, Velocity};
 use uom::si::length::{centimeter, meter, millimeter};
 
 impl MachineNewTrait for Winder2 {
@@ -52,31 +72,38 @@ impl MachineNewTrait for Winder2 {
 
         let hardware = match &params.hardware {
             MachineNewHardware::Ethercat(x) => x,
-            _ => {
-                return Err(anyhow::anyhow!(
-                    "[{}::MachineNewTrait/Winder2::new] MachineNewHardware is not Ethercat",
-                    module_path!()
-                ));
+            MachineNewHardware::Serial(serial) => {
+                // a winder on a serial device only exists in simulation mode
+                let winder = smol::block_on(
+                    SERIAL_DEVICE_REGISTRY
+                        .downcast_arc_rwlock::<SimulatedWinder>(serial.device.clone()),
+                )?;
+                let hardware = simulated_hardware(&winder.read_blocking());
+                return Self::from_hardware(params, hardware);
             }
         };
 
+        let second_puller_config = config()
+            .machines
+            .winder_for(params.get_machine_identification_unique().serial)
+            .second_puller;
+
         // using block_on because making this funciton async creates a lifetime issue
         // if its async the compiler thinks &subdevices is persisted in the future which might never execute
         // so we can't drop subdevices unless this machine is dropped, which is bad
-        smol::block_on(async {
+        let hardware = smol::block_on(async {
             // Role 0: Buscoupler EK1100
             let _ek1100 =
                 get_ethercat_device::<EK1100>(hardware, params, 0, vec![EK1100_IDENTITY_A]).await?;
 
             // Role 1: 2x Digital outputs EL2002
-            let el2002 = get_ethercat_device::<EL2002>(
+            let _el2002 = get_ethercat_device::<EL2002>(
                 hardware,
                 params,
                 1,
                 vec![EL2002_IDENTITY_A, EL2002_IDENTITY_B],
             )
-            .await?
-            .0;
+            .await?;
 
             // Role 2: Stepper Spool EL7041-0052
             let el7041 = {
@@ -97,6 +124,9 @@ impl MachineNewTrait for Winder2 {
                         max_current: 2800,
                         ..Default::default()
                     },
+                    // the coil currents are monitored for overloads
+                    pdo_assignment:
+                        EL7041_0052PredefinedPdoAssignment::VelocityControlCompactWithInfoData,
                     ..Default::default()
                 };
 
@@ -144,6 +174,20 @@ impl MachineNewTrait for Winder2 {
             };
 
             // Role 4: Stepper Puller EL7031-0030
+            let el7031_0030_config = EL7031_0030Configuration {
+                stm_features: el7031_0030::coe::StmFeatures {
+                    operation_mode: EL70x1OperationMode::DirectVelocity,
+                    speed_range: shared_config::el70x1::EL70x1SpeedRange::Steps1000,
+                    ..Default::default()
+                },
+                stm_motor: StmMotorConfiguration {
+                    max_current: 2700,
+                    ..Default::default()
+                },
+                pdo_assignment:
+                    EL7031_0030PredefinedPdoAssignment::VelocityControlCompactWithInfoData,
+                ..Default::default()
+            };
             let el7031_0030 = {
                 let device = get_ethercat_device::<EL7031_0030>(
                     hardware,
@@ -152,20 +196,6 @@ impl MachineNewTrait for Winder2 {
                     vec![EL7031_0030_IDENTITY_A],
                 )
                 .await?;
-
-                let el7031_0030_config = EL7031_0030Configuration {
-                    stm_features: el7031_0030::coe::StmFeatures {
-                        operation_mode: EL70x1OperationMode::DirectVelocity,
-                        speed_range: shared_config::el70x1::EL70x1SpeedRange::Steps1000,
-                        ..Default::default()
-                    },
-                    stm_motor: StmMotorConfiguration {
-                        max_current: 2700,
-                        ..Default::default()
-                    },
-                    pdo_assignment: EL7031_0030PredefinedPdoAssignment::VelocityControlCompact,
-                    ..Default::default()
-                };
                 device
                     .0
                     .write()
@@ -175,70 +205,206 @@ impl MachineNewTrait for Winder2 {
                 device.0
             };
 
-            let mode = Winder2Mode::Standby;
-
-            let machine_id = params
-                .device_group
-                .first()
-                .expect("device group must have at least one device")
-                .device_machine_identification
-                .machine_identification_unique
-                .clone();
+            // Optional second puller axis EL7031-0030 at the configured role
+            let second_puller = match &second_puller_config {
+                Some(second_puller_config) => {
+                    let device = get_ethercat_device::<EL7031_0030>(
+                        hardware,
+                        params,
+                        second_puller_config.role,
+                        vec![EL7031_0030_IDENTITY_A],
+                    )
+                    .await?;
+                    device
+                        .0
+                        .write()
+                        .await
+                        .write_config(&device.1, &el7031_0030_config)
+                        .await?;
+                    Some(StepperVelocityEL70x1::new(
+                        device.0,
+                        EL7031_0030StepperPort::STM1,
+                    ))
+                }
+                None => None,
+            };
 
-            let mut new = Self {
-                traverse: StepperVelocityEL70x1::new(el7031.clone(), EL7031StepperPort::STM1),
-                traverse_end_stop: DigitalInput::new(el7031, EL7031DigitalInputPort::DI1),
+            Ok::<_, Error>(Winder2Hardware {
+                traverse: StepperVelocityEL70x1::new(el7031, EL7031StepperPort::STM1),
                 puller: StepperVelocityEL70x1::new(
                     el7031_0030.clone(),
                     EL7031_0030StepperPort::STM1,
                 ),
+                second_puller,
                 spool: StepperVelocityEL70x1::new(el7041, EL7041_0052Port::STM1),
-                tension_arm: TensionArm::new(AnalogInput::new(
-                    el7031_0030,
-                    EL7031_0030AnalogInputPort::AI1,
-                )),
-                laser: DigitalOutput::new(el2002, EL2002Port::DO1),
-                namespace: Winder2Namespace {
-                    namespace: params.namespace.clone(),
-                },
-                mode: mode.clone(),
-                spool_step_converter: AngularStepConverter::new(200),
-                spool_speed_controller: SpoolSpeedController::new(),
-                last_measurement_emit: Instant::now(),
-                spool_mode: mode.clone().into(),
-                traverse_mode: mode.clone().into(),
-                puller_mode: mode.into(),
-                puller_speed_controller: PullerSpeedController::new(
-                    Velocity::new::<meter_per_minute>(1.0),
-                    Length::new::<millimeter>(1.75),
-                    LinearStepConverter::from_diameter(
-                        200,                            // Assuming 200 steps per revolution for the puller stepper,
-                        Length::new::<centimeter>(8.0), // 8cm diameter of the puller wheel
-                    ),
-                ),
-                traverse_controller: TraverseController::new(
-                    Length::new::<millimeter>(22.0), // Default inner limit
-                    Length::new::<millimeter>(92.0), // Default outer limit
-                    64,                              // Microsteps
-                ),
-                emitted_default_state: false,
-                spool_automatic_action: super::SpoolAutomaticAction {
-                    progress: Length::ZERO,
-                    progress_last_check: Instant::now(),
-                    target_length: Length::new::<meter>(250.0),
-                    mode: super::api::SpoolAutomaticActionMode::NoAction,
-                },
-                machine_manager: params.machine_manager.clone(),
-                machine_identification_unique: machine_id,
-                connected_buffer: MachineCrossConnection::new(
-                    params.machine_manager.clone(),
-                    &params.get_machine_identification_unique(),
+                tension_arm: AnalogInput::new(el7031_0030, EL7031_0030AnalogInputPort::AI1),
+                // digital signals go to the channels configured in the I/O mapping,
+                // additional terminals (e.g. an EL1008 for door contacts) can be added as roles 5+
+                io_pool: DigitalIoPool::new(hardware, params).await?,
+            })
+        })?;
+
+        Self::from_hardware(params, hardware)
+    }
+}
+
+/// Drivers of a winder, from its EtherCAT terminals or from a [`SimulatedWinder`]
+struct Winder2Hardware {
+    traverse: StepperVelocityEL70x1,
+    puller: StepperVelocityEL70x1,
+    second_puller: Option<StepperVelocityEL70x1>,
+    spool: StepperVelocityEL70x1,
+    tension_arm: AnalogInput,
+    io_pool: DigitalIoPool,
+}
+
+/// Simulated drivers on the channels of the default I/O mapping
+fn simulated_hardware(winder: &SimulatedWinder) -> Winder2Hardware {
+    let mut io_pool = DigitalIoPool::default();
+    io_pool.insert_input(3, 1, winder.traverse_end_stop.digital_input());
+    io_pool.insert_output(1, 1, winder.laser_pointer.digital_output());
+    Winder2Hardware {
+        traverse: winder.traverse.stepper_velocity(),
+        puller: winder.puller.stepper_velocity(),
+        second_puller: None,
+        spool: winder.spool.stepper_velocity(),
+        tension_arm: winder.tension_arm.analog_input(),
+        io_pool,
+    }
+}
+
+impl Winder2 {
+    fn from_hardware(params: &MachineNewParams, hardware: Winder2Hardware) -> Result<Self, Error> {
+        let mode = Winder2Mode::Standby;
+
+        let machine_id = params
+            .device_group
+            .first()
+            .expect("device group must have at least one device")
+            .device_machine_identification
+            .machine_identification_unique
+            .clone();
+
+        let io_mapping = IO_MAPPINGS
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&machine_id)
+            .cloned()
+            .unwrap_or_else(Self::default_io_mapping);
+        let io = MappedDigitalIo::new(Self::IO_SIGNALS, hardware.io_pool, io_mapping)?;
+
+        let machine_defaults = config().machines.clone();
+        let defaults = &machine_defaults.winder_for(machine_id.serial);
+        // 8cm diameter of the puller wheel when new
+        let puller_wear = PullerWearModel::new(&machine_id, Length::new::<centimeter>(8.0));
+        let mut new = Self {
+            traverse: hardware.traverse,
+            puller: hardware.puller,
+            second_puller: hardware
+                .second_puller
+                .zip(defaults.second_puller.as_ref())
+                .map(|(stepper, second_puller)| {
+                    SecondPuller::new(stepper, second_puller.trim, second_puller.load_sharing)
+                }),
+            spool: hardware.spool,
+            tension_arm: TensionArm::new(hardware.tension_arm),
+            io,
+            namespace: Winder2Namespace {
+                namespace: params.namespace.clone(),
+            },
+            values: params.values.clone(),
+            mode: mode.clone(),
+            spool_step_converter: AngularStepConverter::new(200),
+            spool_speed_controller: SpoolSpeedController::new(),
+            spool_mode: mode.clone().into(),
+            traverse_mode: mode.clone().into(),
+            puller_mode: mode.into(),
+            puller_speed_controller: PullerSpeedController::new(
+                Velocity::new::<meter_per_minute>(defaults.puller_speed),
+                Length::new::<millimeter>(1.75),
+                LinearStepConverter::from_diameter(
+                    200, // Assuming 200 steps per revolution for the puller stepper,
+                    puller_wear.effective_diameter(),
                 ),
-            };
+                machine_defaults.strands,
+            ),
+            diameter_loop_gain_history: GainHistory::new(),
+            puller_wear,
+            traverse_controller: TraverseController::new(
+                Length::new::<millimeter>(defaults.traverse_inner_limit),
+                Length::new::<millimeter>(defaults.traverse_outer_limit),
+                Length::new::<millimeter>(defaults.traverse_travel),
+                64, // Microsteps
+            ),
+            cutter: Cutter::new(),
+            winding_pattern_planner: WindingPatternPlanner::new(
+                WindingPattern::Precision,
+                Angle::new::<degree>(WindingPatternPlanner::DEFAULT_CROSSING_ANGLE_DEG),
+            ),
+            self_test: None,
+            emitted_default_state: false,
+            dedicated_motion: motion_thread_running(),
+            spool_automatic_action: super::SpoolAutomaticAction {
+                progress: Length::ZERO,
+                progress_last_check: Instant::now(),
+                target_length: Length::new::<meter>(defaults.required_meters),
+                mode: super::api::SpoolAutomaticActionMode::NoAction,
+                spool: 1,
+            },
+            spool_type: None,
+            diameter_tolerances: None,
+            pulled_length: Length::ZERO,
+            journal: Journal::new(&machine_id, Self::JOURNAL_INTERVAL),
+            production: ProductionStats::default(),
+            production_last_update: None,
+            length_correlator: LengthCorrelator::new(Length::new::<meter>(defaults.sensor_offset)),
+            defect_map: DefectMapBuilder::new(),
+            spool_defects: Vec::new(),
+            closed_defect_maps: Vec::new(),
+            maintenance: MaintenanceCounters::new(
+                &machine_id,
+                &[MaintenancePart::PullerWheel, MaintenancePart::SpoolShaft],
+            ),
+            slip_detector: SlipDetector::new(),
+            spool_overload: OverloadMonitor::new(
+                defaults
+                    .spool_max_current
+                    .map(ElectricCurrent::new::<ampere>),
+            ),
+            puller_overload: OverloadMonitor::new(
+                defaults
+                    .puller_max_current
+                    .map(ElectricCurrent::new::<ampere>),
+            ),
+            speed_derating: SpeedDerating::new(defaults.speed_derating.clone()),
+            length_ramp: None,
+            line_position: LineTrail::new(Self::LINE_POSITION_HISTORY),
+            machine_manager: params.machine_manager.clone(),
+            machine_identification_unique: machine_id,
+            connected_buffer: MachineCrossConnection::new(
+                params.machine_manager.clone(),
+                &params.get_machine_identification_unique(),
+            ),
+        };
+
+        new.restore_journal();
+        new.puller_speed_controller
+            .diameter_loop
+            .set_nozzle_to_laser(defaults.nozzle_to_laser.map(Length::new::<meter>));
+        if let Some(diameter_filter) = &defaults.diameter_filter {
+            new.puller_speed_controller.diameter_loop.set_filters(
+                diameter_filter
+                    .filters(motion_update_period(&new.machine_identification_unique))?,
+            );
+        }
+        new.puller_speed_controller.register_scope(&format!(
+            "winder/{}",
+            new.machine_identification_unique.serial
+        ));
 
-            // initalize events
-            new.emit_state();
-            Ok(new)
-        })
+        // initalize events
+        new.emit_schema();
+        new.emit_state();
+        Ok(new)
     }
 }
@@ -0,0 +1,227 @@
+use std::time::{Duration, Instant};
+
+use control_core::{
+    helpers::clock::unix_millis,
+    machines::values::IN_TOLERANCE,
+    socketio::{event::BuildEvent, namespace::NamespaceCacheingLogic},
+};
+use serde::{Deserialize, Serialize};
+use uom::si::{f64::Velocity, velocity::meter_per_second};
+
+use super::{
+    Winder2, Winder2Mode,
+    api::{ProductionEvent, ProductionValues, Winder2Events},
+};
+
+/// Production of one period, e.g. a shift or the lifetime of the winder
+///
+/// Only time outside of standby is planned production time, so a winder switched to standby
+/// over night doesn't lower its availability.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct ProductionCounters {
+    /// s the puller pulled filament
+    pub run_time: f64,
+    /// s the puller stood still outside of standby
+    pub stopped_time: f64,
+    /// m pulled
+    pub produced_length: f64,
+    /// m the run time yields at the target speed
+    pub target_length: f64,
+    /// m pulled while the diameter was out of tolerance
+    pub scrap_length: f64,
+}
+
+impl ProductionCounters {
+    /// Share of the planned time the puller ran
+    pub fn availability(&self) -> Option<f64> {
+        let planned_time = self.run_time + self.stopped_time;
+        (planned_time > 0.0).then(|| self.run_time / planned_time)
+    }
+
+    /// Produced length relative to running at the target speed
+    pub fn performance(&self) -> Option<f64> {
+        (self.target_length > 0.0).then(|| self.produced_length / self.target_length)
+    }
+
+    /// Share of the produced length in tolerance
+    pub fn quality(&self) -> Option<f64> {
+        (self.produced_length > 0.0)
+            .then(|| (self.produced_length - self.scrap_length) / self.produced_length)
+    }
+
+    /// Overall equipment effectiveness, the product of availability, performance and quality
+    pub fn oee(&self) -> Option<f64> {
+        Some(self.availability()? * self.performance()? * self.quality()?)
+    }
+
+    fn record(&mut self, dt: f64, speed: f64, target_speed: f64, in_tolerance: Option<bool>) {
+        if speed < ProductionStats::MIN_RUNNING_SPEED {
+            self.stopped_time += dt;
+            return;
+        }
+        let length = speed * dt;
+        self.run_time += dt;
+        self.produced_length += length;
+        self.target_length = target_speed.mul_add(dt, self.target_length);
+        if in_tolerance == Some(false) {
+            self.scrap_length += length;
+        }
+    }
+}
+
+/// Production counters of the current shift and the lifetime of the winder
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProductionStats {
+    pub shift: ProductionCounters,
+    pub lifetime: ProductionCounters,
+    /// Unix time in milliseconds the shift counters were reset
+    pub shift_started_at: u64,
+}
+
+impl ProductionStats {
+    /// m/s, 0.01 m/min, slower is a standstill
+    const MIN_RUNNING_SPEED: f64 = 0.01 / 60.0;
+
+    /// Counts `dt` of planned production time
+    ///
+    /// `in_tolerance` is `None` without a diameter measurement, the length then counts as good.
+    pub fn record(
+        &mut self,
+        dt: Duration,
+        speed: Velocity,
+        target_speed: Velocity,
+        in_tolerance: Option<bool>,
+    ) {
+        let dt = dt.as_secs_f64();
+        let speed = speed.get::<meter_per_second>().abs();
+        let target_speed = target_speed.get::<meter_per_second>().abs();
+        for counters in [&mut self.shift, &mut self.lifetime] {
+            counters.record(dt, speed, target_speed, in_tolerance);
+        }
+    }
+
+    /// Starts a new shift, the lifetime counters keep counting
+    pub fn reset_shift(&mut self) {
+        self.shift = ProductionCounters::default();
+        self.shift_started_at = unix_millis();
+    }
+}
+
+impl Default for ProductionStats {
+    fn default() -> Self {
+        Self {
+            shift: ProductionCounters::default(),
+            lifetime: ProductionCounters::default(),
+            shift_started_at: unix_millis(),
+        }
+    }
+}
+
+impl From<&ProductionCounters> for ProductionValues {
+    fn from(counters: &ProductionCounters) -> Self {
+        Self {
+            run_time: counters.run_time,
+            stopped_time: counters.stopped_time,
+            produced_length: counters.produced_length,
+            scrap_length: counters.scrap_length,
+            availability: counters.availability(),
+            performance: counters.performance(),
+            quality: counters.quality(),
+            oee: counters.oee(),
+        }
+    }
+}
+
+impl Winder2 {
+    /// Counts the production since the last call, called by `act`
+    pub fn sync_production(&mut self, now: Instant) {
+        let Some(last) = self.production_last_update.replace(now) else {
+            return;
+        };
+        // standby is no planned production time
+        if self.mode == Winder2Mode::Standby {
+            return;
+        }
+        let in_tolerance = self
+            .values
+            .latest(IN_TOLERANCE)
+            .filter(|sample| !sample.is_stale(now, Self::DIAMETER_MAX_AGE))
+            .map(|sample| sample.value);
+        self.production.record(
+            now.saturating_duration_since(last),
+            self.puller_speed_controller.last_speed,
+            self.puller_speed_controller.get_target_speed(),
+            in_tolerance,
+        );
+    }
+
+    /// Emits the counters, limited to the emit rate of the event
+    pub fn emit_production(&mut self) {
+        if !self
+            .namespace
+            .namespace
+            .lock_blocking()
+            .is_due("ProductionEvent")
+        {
+            return;
+        }
+        let event = ProductionEvent {
+            shift: (&self.production.shift).into(),
+            lifetime: (&self.production.lifetime).into(),
+            shift_started_at: self.production.shift_started_at,
+            units: ProductionEvent::UNITS,
+        };
+        self.namespace
+            .emit(Winder2Events::Production(event.build()));
+    }
+
+    /// Starts the counters of a new shift, the next emitted counters show the reset
+    pub fn reset_shift_counters(&mut self, now: Instant) {
+        self.production.reset_shift();
+        self.record_journal(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use control_core::uom_extensions::velocity::meter_per_minute;
+
+    fn m_min(value: f64) -> Velocity {
+        Velocity::new::<meter_per_minute>(value)
+    }
+
+    #[test]
+    fn test_oee() {
+        let mut stats = ProductionStats::default();
+        let minute = Duration::from_secs(60);
+        // 2 min at 80 % of the target speed, one of them out of tolerance
+        stats.record(minute, m_min(8.0), m_min(10.0), Some(true));
+        stats.record(minute, m_min(8.0), m_min(10.0), Some(false));
+        // 2 min stopped
+        stats.record(minute * 2, m_min(0.0), m_min(10.0), None);
+
+        let shift = stats.shift;
+        assert_relative_eq!(shift.run_time, 120.0);
+        assert_relative_eq!(shift.stopped_time, 120.0);
+        assert_relative_eq!(shift.produced_length, 16.0, epsilon = 1e-9);
+        assert_relative_eq!(shift.scrap_length, 8.0, epsilon = 1e-9);
+        assert_relative_eq!(shift.availability().unwrap(), 0.5);
+        assert_relative_eq!(shift.performance().unwrap(), 0.8, epsilon = 1e-9);
+        assert_relative_eq!(shift.quality().unwrap(), 0.5, epsilon = 1e-9);
+        assert_relative_eq!(shift.oee().unwrap(), 0.2, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_reset_shift() {
+        let mut stats = ProductionStats::default();
+        assert!(stats.shift.oee().is_none());
+        stats.record(Duration::from_secs(60), m_min(-5.0), m_min(5.0), None);
+        stats.reset_shift();
+
+        assert_eq!(stats.shift, ProductionCounters::default());
+        assert_relative_eq!(stats.lifetime.produced_length, 5.0, epsilon = 1e-9);
+        assert_relative_eq!(stats.lifetime.quality().unwrap(), 1.0);
+    }
+}
@@ -3,37 +3,67 @@ use std::time::Instant;
 use control_core::{
     controllers::second_degree_motion::linear_jerk_speed_controller::LinearJerkSpeedController,
     converters::linear_step_converter::LinearStepConverter,
+    scope::ScopeChannel,
     uom_extensions::{
         acceleration::meter_per_minute_per_second, jerk::meter_per_minute_per_second_squared,
         velocity::meter_per_minute,
     },
 };
+use schemars::JsonSchema;
+
+use super::diameter_loop::DiameterLoop;
 use serde::{Deserialize, Serialize};
 use uom::{
     ConstZero,
     si::f64::{Acceleration, AngularVelocity, Jerk, Length, Velocity},
 };
 
+/// Cascade of the puller
+///
+/// The outer [`DiameterLoop`] turns the diameter error into a line speed setpoint, in speed
+/// regulation the target speed is the setpoint. The inner loop ramps the motor to the setpoint
+/// with limited acceleration and jerk. Each loop is enabled on its own, the outer one by the
+/// regulation mode and the inner one by the winder mode.
 #[derive(Debug)]
 pub struct PullerSpeedController {
+    /// Inner loop runs, the puller stops otherwise
     enabled: bool,
     pub target_speed: Velocity,
     pub target_diameter: Length,
     pub regulation_mode: PullerRegulationMode,
     /// Forward rotation direction. If false, applies negative sign to speed
     pub forward: bool,
+    /// Outer loop, closed in diameter regulation while the puller runs
+    pub diameter_loop: DiameterLoop,
+    /// Setpoint of the inner loop at the last update, without direction
+    speed_setpoint: Velocity,
+    /// Cap of the setpoint, e.g. while the line speed is derated
+    max_speed: Option<Velocity>,
     /// Linear acceleration controller to dampen speed change
     acceleration_controller: LinearJerkSpeedController,
+    /// Limits of the inner loop
+    acceleration: Acceleration,
+    jerk: Jerk,
     /// Converter for linear to angular transformations
     pub converter: LinearStepConverter,
     pub last_speed: Velocity,
+    /// Speed of every strand relative to the puller speed
+    ///
+    /// Trims the strands of multi-strand lines running on different grooves of the puller wheel.
+    strand_trims: Vec<f64>,
+    /// Output of the ramp for commissioning, see [`control_core::scope`]
+    scope: Option<ScopeChannel>,
 }
 
 impl PullerSpeedController {
+    pub const MIN_STRAND_TRIM: f64 = 0.5;
+    pub const MAX_STRAND_TRIM: f64 = 1.5;
+
     pub fn new(
         target_speed: Velocity,
         target_diameter: Length,
         converter: LinearStepConverter,
+        strands: usize,
     ) -> Self {
         let acceleration = Acceleration::new::<meter_per_minute_per_second>(5.0);
         let jerk = Jerk::new::<meter_per_minute_per_second_squared>(10.0);
@@ -45,16 +75,29 @@ impl PullerSpeedController {
             target_diameter,
             regulation_mode: PullerRegulationMode::Speed,
             forward: true,
+            diameter_loop: DiameterLoop::new(),
+            speed_setpoint: Velocity::ZERO,
+            max_speed: None,
             acceleration_controller: LinearJerkSpeedController::new_simple(
                 Some(speed),
                 acceleration,
                 jerk,
             ),
+            acceleration,
+            jerk,
             converter,
             last_speed: Velocity::ZERO,
+            strand_trims: vec![1.0; strands],
+            scope: None,
         }
     }
 
+    /// Registers the scope channels `<prefix>/ramp/speed` and those of the diameter loop
+    pub fn register_scope(&mut self, prefix: &str) {
+        self.scope = Some(ScopeChannel::new(format!("{}/ramp/speed", prefix)));
+        self.diameter_loop.register_scope(prefix);
+    }
+
     pub const fn set_enabled(&mut self, enabled: bool) {
         self.enabled = enabled;
     }
@@ -75,18 +118,41 @@ impl PullerSpeedController {
         self.forward = forward;
     }
 
-    fn update_speed(&mut self, t: Instant) -> Velocity {
-        let speed = match self.enabled {
-            true => match self.regulation_mode {
-                PullerRegulationMode::Speed => self.target_speed,
-                PullerRegulationMode::Diameter => unimplemented!(),
-            },
-            false => Velocity::ZERO,
+    pub const fn set_max_speed(&mut self, max_speed: Option<Velocity>) {
+        self.max_speed = max_speed;
+    }
+
+    fn update_speed(&mut self, t: Instant, diameter: Option<Length>) -> Velocity {
+        // outer loop, open while the puller stands still so it doesn't wind up
+        self.speed_setpoint = match (self.enabled, &self.regulation_mode) {
+            (true, PullerRegulationMode::Diameter) => {
+                self.diameter_loop
+                    .update(t, self.target_speed, self.target_diameter, diameter)
+            }
+            (true, PullerRegulationMode::Speed) => {
+                self.diameter_loop.reset();
+                self.target_speed
+            }
+            (false, _) => {
+                self.diameter_loop.reset();
+                Velocity::ZERO
+            }
         };
+        if let Some(max_speed) = self.max_speed {
+            self.speed_setpoint = self.speed_setpoint.min(max_speed);
+        }
 
-        let speed = if self.forward { speed } else { -speed };
+        let speed = if self.forward {
+            self.speed_setpoint
+        } else {
+            -self.speed_setpoint
+        };
 
+        // inner loop
         let speed = self.acceleration_controller.update(speed, t);
+        if let Some(scope) = &self.scope {
+            scope.probe(t, speed.get::<meter_per_minute>());
+        }
 
         self.last_speed = speed;
         speed
@@ -102,18 +168,244 @@ impl PullerSpeedController {
         self.converter.angular_velocity_to_velocity(angular_speed)
     }
 
-    pub fn calc_angular_velocity(&mut self, t: Instant) -> AngularVelocity {
-        let speed = self.update_speed(t);
+    /// Runs the cascade, `diameter` is the measured diameter for the diameter regulation
+    pub fn calc_angular_velocity(
+        &mut self,
+        t: Instant,
+        diameter: Option<Length>,
+    ) -> AngularVelocity {
+        let speed = self.update_speed(t, diameter);
         self.speed_to_angular_velocity(speed)
     }
 
     pub fn get_target_speed(&self) -> Velocity {
         self.target_speed
     }
+
+    /// Setpoint of the inner loop at the last update
+    pub const fn get_speed_setpoint(&self) -> Velocity {
+        self.speed_setpoint
+    }
+
+    /// Length the line runs on when the inner loop ramps down from the current speed
+    ///
+    /// Ramping down from a constant speed `v` takes `v / a + a / j` with the jerk limited
+    /// acceleration `a`, the line covers half the distance it would at `v`.
+    pub fn stopping_distance(&self) -> Length {
+        let speed = self.last_speed.abs();
+        let duration = speed / self.acceleration + self.acceleration / self.jerk;
+        speed * duration / 2.0
+    }
+
+    /// Diameter regulation is selected but the measured diameter is missing
+    pub const fn is_diameter_signal_lost(&self) -> bool {
+        self.diameter_loop.is_holding()
+    }
+
+    pub fn get_strand_trims(&self) -> &[f64] {
+        &self.strand_trims
+    }
+
+    pub fn set_strand_trim(&mut self, strand: usize, trim: f64) -> Result<(), anyhow::Error> {
+        if !(Self::MIN_STRAND_TRIM..=Self::MAX_STRAND_TRIM).contains(&trim) {
+            return Err(anyhow::anyhow!(
+                "[{}::PullerSpeedController::set_strand_trim] Trim must be between {} and {}, got {}",
+                module_path!(),
+                Self::MIN_STRAND_TRIM,
+                Self::MAX_STRAND_TRIM,
+                trim
+            ));
+        }
+        let strands = self.strand_trims.len();
+        let Some(strand_trim) = self.strand_trims.get_mut(strand) else {
+            return Err(anyhow::anyhow!(
+                "[{}::PullerSpeedController::set_strand_trim] Strand {} doesn't exist, the line has {} strands",
+                module_path!(),
+                strand,
+                strands
+            ));
+        };
+        *strand_trim = trim;
+        Ok(())
+    }
+
+    /// Speed of every strand at the last puller speed
+    pub fn strand_speeds(&self) -> Vec<Velocity> {
+        self.strand_trims
+            .iter()
+            .map(|trim| self.last_speed * *trim)
+            .collect()
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
 pub enum PullerRegulationMode {
     Speed,
     Diameter,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use control_core::helpers::clock::{Clock, ManualClock};
+    use std::time::Duration;
+    use uom::si::length::millimeter;
+
+    const DT: Duration = Duration::from_millis(10);
+
+    fn controller() -> PullerSpeedController {
+        PullerSpeedController::new(
+            Velocity::new::<meter_per_minute>(10.0),
+            Length::new::<millimeter>(1.75),
+            LinearStepConverter::from_diameter(200, Length::new::<millimeter>(80.0)),
+            2,
+        )
+    }
+
+    /// Steps the controller for `duration` and returns the speed in m/min after every step
+    fn run(
+        controller: &mut PullerSpeedController,
+        clock: &ManualClock,
+        duration: Duration,
+    ) -> Vec<f64> {
+        let steps = duration.as_millis() / DT.as_millis();
+        (0..steps)
+            .map(|_| {
+                clock.advance(DT);
+                controller.calc_angular_velocity(clock.now(), None);
+                controller.last_speed.get::<meter_per_minute>()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_disabled_stays_at_zero() {
+        let clock = ManualClock::new();
+        let mut controller = controller();
+
+        let speeds = run(&mut controller, &clock, Duration::from_secs(2));
+        assert!(speeds.iter().all(|&speed| speed == 0.0));
+    }
+
+    #[test]
+    fn test_stopping_distance() {
+        let clock = ManualClock::new();
+        let mut controller = controller();
+        controller.set_enabled(true);
+        run(&mut controller, &clock, Duration::from_secs(6));
+        let predicted = controller.stopping_distance().get::<millimeter>();
+
+        controller.set_enabled(false);
+        let stopped: f64 = run(&mut controller, &clock, Duration::from_secs(6))
+            .iter()
+            .map(|speed| speed * 1000.0 / 60.0 * DT.as_secs_f64())
+            .sum();
+        // 10 m/min take 2.5 s to stop, which covers about 208 mm
+        assert!((predicted - 208.3).abs() < 0.1, "{}", predicted);
+        assert!(
+            (stopped - predicted).abs() < 10.0,
+            "{} != {}",
+            stopped,
+            predicted
+        );
+    }
+
+    #[test]
+    fn test_ramp_respects_acceleration_limit() {
+        let clock = ManualClock::new();
+        let mut controller = controller();
+        controller.calc_angular_velocity(clock.now(), None);
+        controller.set_enabled(true);
+
+        let speeds = run(&mut controller, &clock, Duration::from_secs(6));
+
+        let mut last = 0.0;
+        for &speed in &speeds {
+            // 5 m/min/s at 10 ms steps
+            assert!(speed - last <= 0.05 + 1e-6, "{} -> {}", last, speed);
+            assert!(speed >= last - 1e-6);
+            last = speed;
+        }
+        // jerk limited, the first step can't jump to full acceleration
+        assert!(speeds[0] < 0.05);
+        // 10 m/min at 5 m/min/s takes at least 2 s
+        assert!(speeds[190] < 10.0);
+        assert!((last - 10.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_reverse_ramps_down_through_zero() {
+        let clock = ManualClock::new();
+        let mut controller = controller();
+        controller.calc_angular_velocity(clock.now(), None);
+        controller.set_enabled(true);
+        run(&mut controller, &clock, Duration::from_secs(6));
+
+        controller.set_forward(false);
+        let speeds = run(&mut controller, &clock, Duration::from_secs(1));
+        // still moving forward after a second of deceleration
+        assert!(speeds.iter().all(|&speed| speed > 0.0));
+
+        let speeds = run(&mut controller, &clock, Duration::from_secs(8));
+        assert!((speeds[speeds.len() - 1] + 10.0).abs() < 0.01);
+        assert!(speeds.iter().all(|&speed| speed >= -10.0 - 1e-6));
+    }
+
+    #[test]
+    fn test_strand_trims() {
+        let clock = ManualClock::new();
+        let mut controller = controller();
+        assert!(controller.set_strand_trim(2, 1.0).is_err());
+        assert!(controller.set_strand_trim(1, 2.0).is_err());
+        controller.set_strand_trim(1, 1.1).unwrap();
+        assert_eq!(controller.get_strand_trims(), &[1.0, 1.1]);
+
+        controller.calc_angular_velocity(clock.now(), None);
+        controller.set_enabled(true);
+        run(&mut controller, &clock, Duration::from_secs(6));
+        let speeds = controller.strand_speeds();
+        assert!((speeds[0].get::<meter_per_minute>() - 10.0).abs() < 0.01);
+        assert!((speeds[1].get::<meter_per_minute>() - 11.0).abs() < 0.01);
+
+        // a derated line caps the puller, the trims still apply
+        controller.set_max_speed(Some(Velocity::new::<meter_per_minute>(5.0)));
+        run(&mut controller, &clock, Duration::from_secs(6));
+        let speeds = controller.strand_speeds();
+        assert!((speeds[0].get::<meter_per_minute>() - 5.0).abs() < 0.01);
+        assert!((speeds[1].get::<meter_per_minute>() - 5.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_diameter_cascade() {
+        let clock = ManualClock::new();
+        let mut controller = controller();
+        controller.set_regulation_mode(PullerRegulationMode::Diameter);
+        controller.diameter_loop.set_gains(10.0, 0.0, 0.0).unwrap();
+        controller.calc_angular_velocity(clock.now(), None);
+        // the outer loop stays open while the puller stands still
+        assert!(!controller.diameter_loop.is_active());
+
+        controller.set_enabled(true);
+        for _ in 0..600 {
+            clock.advance(DT);
+            controller.calc_angular_velocity(clock.now(), Some(Length::new::<millimeter>(1.8)));
+        }
+        // 0.05 mm too thick pulls 0.5 m/min faster
+        let setpoint = controller.get_speed_setpoint().get::<meter_per_minute>();
+        assert!((setpoint - 10.5).abs() < 1e-6);
+        assert!((controller.last_speed.get::<meter_per_minute>() - 10.5).abs() < 0.01);
+
+        clock.advance(DT);
+        controller.calc_angular_velocity(clock.now(), None);
+        assert!(controller.is_diameter_signal_lost());
+        assert!(
+            (controller.get_speed_setpoint().get::<meter_per_minute>() - setpoint).abs() < 1e-9
+        );
+
+        controller.set_regulation_mode(PullerRegulationMode::Speed);
+        clock.advance(DT);
+        controller.calc_angular_velocity(clock.now(), None);
+        assert!(!controller.is_diameter_signal_lost());
+        assert!((controller.get_speed_setpoint().get::<meter_per_minute>() - 10.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,235 @@
+use crate::{config::config, journal::Journal};
+use control_core::{
+    helpers::clock::unix_millis, machines::identification::MachineIdentificationUnique,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use uom::si::{
+    f64::Length,
+    length::{meter, millimeter},
+};
+
+/// Directory inside [`crate::storage::data_dir`] with the puller wear of every winder
+pub const PULLER_WEAR_DIR: &str = "puller_wear";
+
+/// Length calibration of the operator, the filament the winder counted measured by hand
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, JsonSchema)]
+pub struct PullerCalibration {
+    /// unix timestamp in milliseconds
+    pub at: u64,
+    /// m the winder counted
+    pub counted: f64,
+    /// m the operator measured
+    pub measured: f64,
+    /// correction factor of the circumference after the calibration
+    pub factor: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct PullerWearSnapshot {
+    factor: f64,
+    history: Vec<PullerCalibration>,
+}
+
+/// Effective circumference of a wearing puller wheel
+///
+/// A worn wheel pulls less filament per revolution than its nominal diameter, so the counted
+/// length runs ahead of the real one. Every calibration moves the correction factor a share of
+/// the measured deviation towards it, so a single sloppy measurement barely changes the
+/// speed while repeated ones follow the wear. The factor is journaled to [`PULLER_WEAR_DIR`]
+/// until the wheel is replaced.
+#[derive(Debug)]
+pub struct PullerWearModel {
+    nominal_diameter: Length,
+    /// effective over nominal circumference
+    factor: f64,
+    history: Vec<PullerCalibration>,
+    journal: Journal<PullerWearSnapshot>,
+}
+
+impl PullerWearModel {
+    /// Share of the measured deviation a calibration applies
+    const GAIN: f64 = 0.3;
+    /// Deviations beyond are a measuring mistake, not wear
+    const MAX_DEVIATION: f64 = 0.1;
+    /// m, shorter lengths can't be measured precise enough to resolve wear
+    const MIN_LENGTH: f64 = 1.0;
+    const MAX_HISTORY: usize = 50;
+
+    /// Model of the wheel with `nominal_diameter`, resumed from the journal
+    pub fn new(
+        machine_identification_unique: &MachineIdentificationUnique,
+        nominal_diameter: Length,
+    ) -> Self {
+        // recorded on every change, the interval is never checked
+        let journal = Journal::in_dir(
+            PULLER_WEAR_DIR,
+            machine_identification_unique,
+            Duration::ZERO,
+        );
+        let saved = journal.load().unwrap_or_default();
+        Self::with_saved(journal, nominal_diameter, saved)
+    }
+
+    fn with_saved(
+        journal: Journal<PullerWearSnapshot>,
+        nominal_diameter: Length,
+        saved: PullerWearSnapshot,
+    ) -> Self {
+        let factor = match saved.factor.is_finite() && saved.factor > 0.0 {
+            true => saved.factor,
+            false => 1.0,
+        };
+        Self {
+            nominal_diameter,
+            factor,
+            history: saved.history,
+            journal,
+        }
+    }
+
+    /// Adjusts the circumference by a length the winder counted and the operator measured
+    pub fn calibrate(
+        &mut self,
+        counted: Length,
+        measured: Length,
+        now: Instant,
+    ) -> Result<(), anyhow::Error> {
+        let counted = counted.get::<meter>();
+        let measured = measured.get::<meter>();
+        if !(counted >= Self::MIN_LENGTH && measured >= Self::MIN_LENGTH) {
+            return Err(anyhow::anyhow!(
+                "[{}::PullerWearModel::calibrate] Lengths must be at least {} m",
+                module_path!(),
+                Self::MIN_LENGTH
+            ));
+        }
+        let deviation = measured / counted - 1.0;
+        if deviation.abs() > Self::MAX_DEVIATION {
+            return Err(anyhow::anyhow!(
+                "[{}::PullerWearModel::calibrate] Measured length deviates {:.1} % from the counted one, measure again",
+                module_path!(),
+                deviation * 100.0
+            ));
+        }
+
+        self.factor *= Self::GAIN.mul_add(deviation, 1.0);
+        self.history.push(PullerCalibration {
+            at: unix_millis(),
+            counted,
+            measured,
+            factor: self.factor,
+        });
+        if self.history.len() > Self::MAX_HISTORY {
+            self.history.remove(0);
+        }
+        self.record(now);
+        Ok(())
+    }
+
+    /// Starts from the nominal diameter after the wheel was replaced
+    pub fn reset(&mut self, now: Instant) {
+        self.factor = 1.0;
+        self.history.clear();
+        self.record(now);
+    }
+
+    fn record(&mut self, now: Instant) {
+        self.journal.record(
+            now,
+            PullerWearSnapshot {
+                factor: self.factor,
+                history: self.history.clone(),
+            },
+        );
+    }
+
+    pub const fn factor(&self) -> f64 {
+        self.factor
+    }
+
+    pub fn history(&self) -> &[PullerCalibration] {
+        &self.history
+    }
+
+    /// Diameter the wheel pulls with
+    pub fn effective_diameter(&self) -> Length {
+        self.nominal_diameter * self.factor
+    }
+
+    /// Diameter the wheel lost
+    pub fn wear(&self) -> Length {
+        self.nominal_diameter - self.effective_diameter()
+    }
+
+    /// Whether the wheel lost more than `maintenance.puller_wheel_max_wear` of its diameter
+    pub fn is_worn(&self) -> bool {
+        config()
+            .maintenance
+            .puller_wheel_max_wear
+            .is_some_and(|max_wear| self.wear().get::<millimeter>() > max_wear)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use control_core::machines::identification::MachineIdentification;
+
+    #[test]
+    fn test_calibrate() {
+        let machine_identification_unique = MachineIdentificationUnique {
+            machine_identification: MachineIdentification {
+                vendor: 1,
+                machine: 2,
+            },
+            serial: 3,
+        };
+        let journal = Journal::in_dir(
+            PULLER_WEAR_DIR,
+            &machine_identification_unique,
+            Duration::ZERO,
+        );
+        let mut model = PullerWearModel::with_saved(
+            journal,
+            Length::new::<millimeter>(80.0),
+            PullerWearSnapshot::default(),
+        );
+        assert_eq!(model.factor(), 1.0);
+        let now = Instant::now();
+
+        // the worn wheel pulled 1 % less than counted
+        for _ in 0..20 {
+            let counted = Length::new::<meter>(100.0);
+            let measured = counted * (0.99 / model.factor());
+            model.calibrate(counted, measured, now).unwrap();
+        }
+        assert!((model.factor() - 0.99).abs() < 1e-4);
+        assert!((model.wear().get::<millimeter>() - 0.8).abs() < 0.01);
+        assert_eq!(model.history().len(), 20);
+
+        // a single calibration moves the factor a share of its deviation only
+        let factor = model.factor();
+        let counted = Length::new::<meter>(100.0);
+        model
+            .calibrate(counted, Length::new::<meter>(101.0), now)
+            .unwrap();
+        assert!((model.factor() / factor - 1.003).abs() < 1e-9);
+
+        assert!(
+            model
+                .calibrate(counted, Length::new::<meter>(120.0), now)
+                .is_err()
+        );
+        assert!(
+            model
+                .calibrate(Length::new::<meter>(0.5), Length::new::<meter>(0.5), now)
+                .is_err()
+        );
+
+        model.reset(now);
+        assert_eq!(model.factor(), 1.0);
+        assert!(model.history().is_empty());
+    }
+}
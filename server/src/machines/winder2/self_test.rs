@@ -0,0 +1,437 @@
+use super::{Winder2, Winder2Mode};
+use crate::config::{SelfTestLoopback, config};
+use control_core::machines::self_test::{SelfTestReport, SelfTestResult};
+use ethercat_hal::io::stepper_velocity_el70x1::StepperVelocityEL70x1;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Speed of a micro-move in steps per second, a few degrees in [`MICRO_MOVE_TIME`]
+const MICRO_MOVE_SPEED: f64 = 200.0;
+/// Time an axis moves out, it moves back for the same time to where it started
+const MICRO_MOVE_TIME: Duration = Duration::from_millis(250);
+/// Steps an axis has to follow each direction with, well below the commanded 50 steps
+const MICRO_MOVE_MIN_STEPS: i128 = 10;
+/// Time a disabled drive gets to power its motor before it moves
+const ENABLE_TIME: Duration = Duration::from_millis(100);
+/// Time an input gets to follow its looped back output
+const LOOPBACK_SETTLE_TIME: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SelfTestAxis {
+    Traverse,
+    Puller,
+    Spool,
+}
+
+impl SelfTestAxis {
+    const ALL: [Self; 3] = [Self::Traverse, Self::Puller, Self::Spool];
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Traverse => "traverse",
+            Self::Puller => "puller",
+            Self::Spool => "spool",
+        }
+    }
+}
+
+#[derive(Debug)]
+enum PendingCheck {
+    MicroMove(SelfTestAxis),
+    Loopback(SelfTestLoopback),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum MovePhase {
+    Enabling,
+    Out { start: i128 },
+    Back { start: i128, out: i128 },
+}
+
+/// Check waiting for feedback of the hardware
+#[derive(Debug)]
+enum RunningCheck {
+    MicroMove {
+        axis: SelfTestAxis,
+        phase: MovePhase,
+        since: Instant,
+        was_enabled: bool,
+    },
+    Loopback {
+        loopback: SelfTestLoopback,
+        /// input with the output on, read once the output is switched off
+        high: Option<Option<bool>>,
+        since: Instant,
+        restore: bool,
+    },
+}
+
+/// Self test of the winder, one check after another over the cycles
+///
+/// The motors only move in standby and belong to the test while it runs, leaving standby
+/// interrupts it.
+#[derive(Debug)]
+pub struct WinderSelfTest {
+    pub report: SelfTestReport,
+    pending: VecDeque<PendingCheck>,
+    running: Option<RunningCheck>,
+}
+
+impl WinderSelfTest {
+    pub const fn is_running(&self) -> bool {
+        !self.report.finished
+    }
+}
+
+/// Result of a micro-move from the positions before, after moving out and after moving back
+fn micro_move_result(start: i128, out: i128, back: i128) -> (SelfTestResult, String) {
+    let moved_out = out - start;
+    let moved_back = back - out;
+    let detail = format!(
+        "moved {} steps out and {} steps back",
+        moved_out, moved_back
+    );
+    let followed = moved_out.abs() >= MICRO_MOVE_MIN_STEPS
+        && moved_back.abs() >= MICRO_MOVE_MIN_STEPS
+        && moved_out.signum() != moved_back.signum();
+    match followed {
+        true => (SelfTestResult::Passed, detail),
+        false => (
+            SelfTestResult::Failed,
+            format!("{}, no feedback of the motor", detail),
+        ),
+    }
+}
+
+/// Result of a loopback from the input read with the output on and off
+fn loopback_result(high: Option<bool>, low: Option<bool>) -> (SelfTestResult, String) {
+    match (high, low) {
+        (Some(true), Some(false)) => (
+            SelfTestResult::Passed,
+            "input follows the output".to_string(),
+        ),
+        _ => (
+            SelfTestResult::Failed,
+            format!(
+                "input read {:?} with the output on and {:?} with it off",
+                high, low
+            ),
+        ),
+    }
+}
+
+fn loopback_name(loopback: &SelfTestLoopback) -> String {
+    format!("loopback {} to {}", loopback.output, loopback.input)
+}
+
+impl Winder2 {
+    /// Starts the self test: drive status and end stop right away, then a micro-move of every
+    /// axis and the configured loopbacks
+    pub fn run_self_test(&mut self) -> Result<(), anyhow::Error> {
+        if self.is_self_test_running() {
+            return Err(anyhow::anyhow!(
+                "[{}::Winder2::run_self_test] Self test is already running",
+                module_path!()
+            ));
+        }
+        if self.mode != Winder2Mode::Standby {
+            return Err(anyhow::anyhow!(
+                "[{}::Winder2::run_self_test] Winder has to be in standby",
+                module_path!()
+            ));
+        }
+
+        let mut report = SelfTestReport::new();
+        for axis in SelfTestAxis::ALL {
+            let name = format!("{} drive", axis.name());
+            match self.self_test_stepper(axis).get_info_data() {
+                Some([a, b]) => report.add(
+                    &name,
+                    SelfTestResult::Passed,
+                    format!("coil currents {} mA and {} mA", a, b),
+                ),
+                None => report.add(
+                    &name,
+                    SelfTestResult::Skipped,
+                    "info data not in the PDO assignment",
+                ),
+            }
+        }
+        match self.io.input(Self::IO_TRAVERSE_END_STOP) {
+            Some(active) => report.add(
+                "traverse end stop",
+                SelfTestResult::Passed,
+                format!("reads {}", if active { "active" } else { "inactive" }),
+            ),
+            None => report.add(
+                "traverse end stop",
+                SelfTestResult::Failed,
+                "not assigned, the traverse can't home",
+            ),
+        }
+
+        let mut pending: VecDeque<PendingCheck> = SelfTestAxis::ALL
+            .into_iter()
+            .map(PendingCheck::MicroMove)
+            .collect();
+        pending.extend(
+            config()
+                .machines
+                .winder_for(self.machine_identification_unique.serial)
+                .self_test_loopbacks
+                .into_iter()
+                .map(PendingCheck::Loopback),
+        );
+        tracing::info!(
+            "Self test of {} started",
+            self.machine_identification_unique
+        );
+        self.self_test = Some(WinderSelfTest {
+            report,
+            pending,
+            running: None,
+        });
+        Ok(())
+    }
+
+    pub fn is_self_test_running(&self) -> bool {
+        self.self_test
+            .as_ref()
+            .is_some_and(WinderSelfTest::is_running)
+    }
+
+    /// Advances the self test, called by `act`
+    pub fn act_self_test(&mut self, now: Instant) {
+        let Some(mut test) = self.self_test.take() else {
+            return;
+        };
+        if test.is_running() {
+            if self.mode == Winder2Mode::Standby {
+                self.step_self_test(&mut test, now);
+            } else {
+                self.interrupt_self_test(&mut test);
+            }
+        }
+        self.self_test = Some(test);
+    }
+
+    const fn self_test_stepper(&mut self, axis: SelfTestAxis) -> &mut StepperVelocityEL70x1 {
+        match axis {
+            SelfTestAxis::Traverse => &mut self.traverse,
+            SelfTestAxis::Puller => &mut self.puller,
+            SelfTestAxis::Spool => &mut self.spool,
+        }
+    }
+
+    fn step_self_test(&mut self, test: &mut WinderSelfTest, now: Instant) {
+        let running = match test.running.take() {
+            Some(running) => running,
+            None => match test.pending.pop_front() {
+                Some(PendingCheck::MicroMove(axis)) => {
+                    let stepper = self.self_test_stepper(axis);
+                    let was_enabled = stepper.is_enabled();
+                    stepper.set_enabled(true);
+                    RunningCheck::MicroMove {
+                        axis,
+                        phase: MovePhase::Enabling,
+                        since: now,
+                        was_enabled,
+                    }
+                }
+                Some(PendingCheck::Loopback(loopback)) => {
+                    let output = self.io.output(&loopback.output);
+                    let input = self.io.input(&loopback.input);
+                    let (Some(restore), Some(_)) = (output, input) else {
+                        let unassigned = match output {
+                            None => &loopback.output,
+                            Some(_) => &loopback.input,
+                        };
+                        test.report.add(
+                            &loopback_name(&loopback),
+                            SelfTestResult::Skipped,
+                            format!("{} is not assigned", unassigned),
+                        );
+                        return;
+                    };
+                    self.io.set_output(&loopback.output, true);
+                    RunningCheck::Loopback {
+                        loopback,
+                        high: None,
+                        since: now,
+                        restore,
+                    }
+                }
+                None => {
+                    test.report.finish();
+                    tracing::info!(
+                        "Self test of {} finished, passed: {}",
+                        self.machine_identification_unique,
+                        test.report.passed
+                    );
+                    return;
+                }
+            },
+        };
+        test.running = self.step_check(running, &mut test.report, now);
+    }
+
+    /// Advances a check, `None` once its result is in the report
+    fn step_check(
+        &mut self,
+        running: RunningCheck,
+        report: &mut SelfTestReport,
+        now: Instant,
+    ) -> Option<RunningCheck> {
+        match running {
+            RunningCheck::MicroMove {
+                axis,
+                phase,
+                since,
+                was_enabled,
+            } => {
+                let elapsed = now.saturating_duration_since(since);
+                let stepper = self.self_test_stepper(axis);
+                let (next, speed) = match phase {
+                    MovePhase::Enabling if elapsed >= ENABLE_TIME => (
+                        Some(MovePhase::Out {
+                            start: stepper.get_position(),
+                        }),
+                        MICRO_MOVE_SPEED,
+                    ),
+                    MovePhase::Out { start } if elapsed >= MICRO_MOVE_TIME => (
+                        Some(MovePhase::Back {
+                            start,
+                            out: stepper.get_position(),
+                        }),
+                        -MICRO_MOVE_SPEED,
+                    ),
+                    MovePhase::Back { start, out } if elapsed >= MICRO_MOVE_TIME => {
+                        let (result, detail) =
+                            micro_move_result(start, out, stepper.get_position());
+                        report.add(&format!("{} micro-move", axis.name()), result, detail);
+                        (None, 0.0)
+                    }
+                    _ => {
+                        return Some(RunningCheck::MicroMove {
+                            axis,
+                            phase,
+                            since,
+                            was_enabled,
+                        });
+                    }
+                };
+                if let Err(e) = stepper.set_speed(speed) {
+                    report.add(
+                        &format!("{} micro-move", axis.name()),
+                        SelfTestResult::Failed,
+                        format!("drive rejected the speed: {}", e),
+                    );
+                    let _ = stepper.set_speed(0.0);
+                    stepper.set_enabled(was_enabled);
+                    return None;
+                }
+                match next {
+                    Some(phase) => Some(RunningCheck::MicroMove {
+                        axis,
+                        phase,
+                        since: now,
+                        was_enabled,
+                    }),
+                    None => {
+                        stepper.set_enabled(was_enabled);
+                        None
+                    }
+                }
+            }
+            RunningCheck::Loopback {
+                loopback,
+                high,
+                since,
+                restore,
+            } => {
+                if now.saturating_duration_since(since) < LOOPBACK_SETTLE_TIME {
+                    return Some(RunningCheck::Loopback {
+                        loopback,
+                        high,
+                        since,
+                        restore,
+                    });
+                }
+                let input = self.io.input(&loopback.input);
+                match high {
+                    None => {
+                        self.io.set_output(&loopback.output, false);
+                        Some(RunningCheck::Loopback {
+                            loopback,
+                            high: Some(input),
+                            since: now,
+                            restore,
+                        })
+                    }
+                    Some(high) => {
+                        self.io.set_output(&loopback.output, restore);
+                        let (result, detail) = loopback_result(high, input);
+                        report.add(&loopback_name(&loopback), result, detail);
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    /// Stops the running check, the mode now decides about the motors
+    fn interrupt_self_test(&mut self, test: &mut WinderSelfTest) {
+        let name = match test.running.take() {
+            Some(RunningCheck::MicroMove { axis, .. }) => {
+                let _ = self.self_test_stepper(axis).set_speed(0.0);
+                format!("{} micro-move", axis.name())
+            }
+            Some(RunningCheck::Loopback {
+                loopback, restore, ..
+            }) => {
+                self.io.set_output(&loopback.output, restore);
+                loopback_name(&loopback)
+            }
+            None => "self test".to_string(),
+        };
+        test.pending.clear();
+        test.report.add(
+            &name,
+            SelfTestResult::Failed,
+            "interrupted, the winder left standby",
+        );
+        test.report.finish();
+        tracing::warn!(
+            "Self test of {} interrupted",
+            self.machine_identification_unique
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test_results() {
+        assert_eq!(micro_move_result(100, 148, 101).0, SelfTestResult::Passed);
+        // inverted axes count down first
+        assert_eq!(micro_move_result(100, 52, 99).0, SelfTestResult::Passed);
+        // disconnected motor, the counter doesn't move
+        assert_eq!(micro_move_result(100, 100, 100).0, SelfTestResult::Failed);
+        // moved out but not back
+        assert_eq!(micro_move_result(100, 148, 148).0, SelfTestResult::Failed);
+
+        assert_eq!(
+            loopback_result(Some(true), Some(false)).0,
+            SelfTestResult::Passed
+        );
+        // input stuck on
+        assert_eq!(
+            loopback_result(Some(true), Some(true)).0,
+            SelfTestResult::Failed
+        );
+    }
+}
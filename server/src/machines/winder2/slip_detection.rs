@@ -0,0 +1,183 @@
+use super::spool::SpoolGeometry;
+use std::time::{Duration, Instant};
+use uom::si::{
+    angular_velocity::radian_per_second,
+    f64::{AngularVelocity, Length, Velocity},
+    length::millimeter,
+    velocity::meter_per_second,
+};
+
+/// Detects a spool that turns without winding, e.g. a slipping belt or a loose spool
+///
+/// The spool winds the length the puller pulls, so the pulled length over the commanded spool
+/// angle is the radius the filament is wound on. A slipping spool needs more revolutions for
+/// the same length, the tension arm makes the controller command them, and the implied radius
+/// drops below the core of the spool. Each [`Self::WINDOW`] is checked on its own, the spool
+/// counts as slipping after [`Self::SLIPPING_WINDOWS`] impossible windows in a row.
+#[derive(Debug, Default)]
+pub struct SlipDetector {
+    window_start: Option<Instant>,
+    last_sample: Option<Instant>,
+    /// spool angle in rad and pulled length in m since the window started
+    angle: f64,
+    length: f64,
+    /// implied radius of the latest window in mm
+    implied_radius: Option<f64>,
+    impossible_windows: u32,
+}
+
+impl SlipDetector {
+    /// Long enough to average the tension arm regulating around its target
+    pub const WINDOW: Duration = Duration::from_secs(10);
+    const SLIPPING_WINDOWS: u32 = 3;
+    /// Share the implied radius may lie outside of the spool, the tension arm stores filament
+    const MARGIN: f64 = 0.15;
+    /// Radii of spools winders wind on, used without a selected spool type, in mm
+    const MIN_RADIUS: f64 = 20.0;
+    const MAX_RADIUS: f64 = 200.0;
+    /// Below this line speed in m/s the window is dropped, the line starts or stops
+    const MIN_LINE_SPEED: f64 = 0.005;
+    /// Samples further apart are a gap, e.g. after the machine was paused
+    const MAX_SAMPLE_GAP: Duration = Duration::from_secs(1);
+
+    pub const fn new() -> Self {
+        Self {
+            window_start: None,
+            last_sample: None,
+            angle: 0.0,
+            length: 0.0,
+            implied_radius: None,
+            impossible_windows: 0,
+        }
+    }
+
+    /// Samples the commanded spool speed and the line speed, call it every cycle while winding
+    pub fn add_sample(
+        &mut self,
+        now: Instant,
+        spool_speed: AngularVelocity,
+        line_speed: Velocity,
+        geometry: Option<&SpoolGeometry>,
+    ) {
+        let line_speed = line_speed.get::<meter_per_second>().abs();
+        let gap = self
+            .last_sample
+            .map(|last_sample| now.saturating_duration_since(last_sample));
+        self.last_sample = Some(now);
+        if line_speed < Self::MIN_LINE_SPEED {
+            self.interrupt();
+            return;
+        }
+        let Some(dt) = gap.filter(|gap| *gap <= Self::MAX_SAMPLE_GAP) else {
+            self.window_start = Some(now);
+            self.angle = 0.0;
+            self.length = 0.0;
+            return;
+        };
+
+        let dt = dt.as_secs_f64();
+        self.angle += spool_speed.get::<radian_per_second>().abs() * dt;
+        self.length += line_speed * dt;
+        let window_start = *self.window_start.get_or_insert(now);
+        if now.duration_since(window_start) < Self::WINDOW {
+            return;
+        }
+
+        let implied_radius = if self.angle > 0.0 {
+            self.length / self.angle * 1000.0
+        } else {
+            f64::INFINITY
+        };
+        let (min, max) = geometry.map_or((Self::MIN_RADIUS, Self::MAX_RADIUS), |geometry| {
+            (geometry.core_diameter / 2.0, geometry.flange_diameter / 2.0)
+        });
+        let possible = implied_radius >= min * (1.0 - Self::MARGIN)
+            && implied_radius <= max * (1.0 + Self::MARGIN);
+        self.impossible_windows = if possible {
+            0
+        } else {
+            self.impossible_windows + 1
+        };
+        self.implied_radius = Some(implied_radius);
+        self.window_start = Some(now);
+        self.angle = 0.0;
+        self.length = 0.0;
+    }
+
+    /// Drops the current window, e.g. while the spool doesn't wind
+    pub const fn interrupt(&mut self) {
+        self.window_start = None;
+        self.last_sample = None;
+        self.angle = 0.0;
+        self.length = 0.0;
+        self.implied_radius = None;
+        self.impossible_windows = 0;
+    }
+
+    /// Radius the filament was wound on in the latest window
+    pub fn implied_radius(&self) -> Option<Length> {
+        self.implied_radius
+            .filter(|radius| radius.is_finite())
+            .map(Length::new::<millimeter>)
+    }
+
+    pub const fn is_slipping(&self) -> bool {
+        self.impossible_windows >= Self::SLIPPING_WINDOWS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use control_core::uom_extensions::velocity::meter_per_minute;
+    use std::f64::consts::PI;
+    use uom::si::angular_velocity::revolution_per_minute;
+
+    /// Winds for `seconds` at 10 m/min with the spool speed of `radius` mm
+    fn wind(
+        detector: &mut SlipDetector,
+        start: Instant,
+        seconds: u64,
+        radius: f64,
+        geometry: Option<&SpoolGeometry>,
+    ) -> Instant {
+        let rpm = 10_000.0 / (2.0 * PI * radius);
+        for tick in 1..=seconds * 10 {
+            detector.add_sample(
+                start + Duration::from_millis(tick * 100),
+                AngularVelocity::new::<revolution_per_minute>(rpm),
+                Velocity::new::<meter_per_minute>(10.0),
+                geometry,
+            );
+        }
+        start + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn test_slip() {
+        let geometry = SpoolGeometry {
+            core_diameter: 100.0,
+            flange_diameter: 200.0,
+            width: 60.0,
+        };
+        let mut detector = SlipDetector::new();
+        let mut now = Instant::now();
+
+        now = wind(&mut detector, now, 60, 70.0, Some(&geometry));
+        assert!(!detector.is_slipping());
+        let radius = detector.implied_radius().unwrap().get::<millimeter>();
+        assert!((radius - 70.0).abs() < 1.0);
+
+        // the belt slips, the spool turns as fast as on a 30 mm radius
+        now = wind(&mut detector, now, 20, 30.0, Some(&geometry));
+        assert!(!detector.is_slipping());
+        now = wind(&mut detector, now, 20, 30.0, Some(&geometry));
+        assert!(detector.is_slipping());
+
+        detector.interrupt();
+        assert!(!detector.is_slipping());
+        // 30 mm is a possible radius for spools winders usually wind on
+        wind(&mut detector, now, 60, 30.0, None);
+        assert!(!detector.is_slipping());
+    }
+}
@@ -0,0 +1,110 @@
+use super::api::SpeedDeratingState;
+use crate::config::{SpeedDeratingRule, TemperatureSource};
+use control_core::uom_extensions::velocity::meter_per_minute;
+use uom::si::{
+    f64::{ThermodynamicTemperature, Velocity},
+    thermodynamic_temperature::degree_celsius,
+};
+
+/// Caps the line speed while a temperature is above the threshold of a rule
+///
+/// A rule activates above its threshold and releases [`Self::HYSTERESIS`] below it, so a
+/// temperature regulating around the threshold doesn't toggle the cap. The lowest cap of the
+/// active rules applies.
+#[derive(Debug, Default)]
+pub struct SpeedDerating {
+    rules: Vec<SpeedDeratingRule>,
+    active: Vec<bool>,
+}
+
+impl SpeedDerating {
+    /// °C
+    const HYSTERESIS: f64 = 1.0;
+
+    pub fn new(rules: Vec<SpeedDeratingRule>) -> Self {
+        let active = vec![false; rules.len()];
+        Self { rules, active }
+    }
+
+    /// Evaluates the rules on the current temperatures, `None` for a source without a recent
+    /// value releases its rules
+    ///
+    /// Returns whether the applied derating changed.
+    pub fn update(
+        &mut self,
+        temperature: impl Fn(TemperatureSource) -> Option<ThermodynamicTemperature>,
+    ) -> bool {
+        let before = self.state();
+        for (rule, active) in self.rules.iter().zip(self.active.iter_mut()) {
+            let Some(temperature) = temperature(rule.source) else {
+                *active = false;
+                continue;
+            };
+            let temperature = temperature.get::<degree_celsius>();
+            *active = match *active {
+                true => temperature > rule.above - Self::HYSTERESIS,
+                false => temperature > rule.above,
+            };
+        }
+        self.state() != before
+    }
+
+    fn applied(&self) -> Option<&SpeedDeratingRule> {
+        self.rules
+            .iter()
+            .zip(&self.active)
+            .filter(|(_, active)| **active)
+            .map(|(rule, _)| rule)
+            .min_by(|a, b| a.max_line_speed.total_cmp(&b.max_line_speed))
+    }
+
+    pub fn max_line_speed(&self) -> Option<Velocity> {
+        self.applied()
+            .map(|rule| Velocity::new::<meter_per_minute>(rule.max_line_speed))
+    }
+
+    pub fn state(&self) -> Option<SpeedDeratingState> {
+        self.applied().map(|rule| SpeedDeratingState {
+            reason: format!("{} above {} °C", rule.source.name(), rule.above),
+            max_line_speed: rule.max_line_speed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speed_derating() {
+        let rule = |above, max_line_speed| SpeedDeratingRule {
+            source: TemperatureSource::WaterBath,
+            above,
+            max_line_speed,
+        };
+        let mut derating = SpeedDerating::new(vec![rule(40.0, 20.0), rule(50.0, 5.0)]);
+        let mut update = |celsius: Option<f64>| {
+            derating.update(|_| celsius.map(ThermodynamicTemperature::new::<degree_celsius>))
+        };
+
+        assert!(!update(Some(35.0)));
+        assert!(update(Some(41.0)));
+        // the lowest active cap applies
+        assert!(update(Some(51.0)));
+        assert!(!update(Some(52.0)));
+        // released below the hysteresis only
+        assert!(!update(Some(49.5)));
+        assert!(update(Some(48.5)));
+        assert_eq!(
+            derating.state(),
+            Some(SpeedDeratingState {
+                reason: "water bath above 40 °C".to_string(),
+                max_line_speed: 20.0,
+            })
+        );
+
+        // a lost temperature releases the cap
+        assert!(derating.update(|_| None));
+        assert!(derating.max_line_speed().is_none());
+    }
+}
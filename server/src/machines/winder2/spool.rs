@@ -0,0 +1,288 @@
+use crate::storage;
+use lazy_static::lazy_static;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, f64::consts::PI, path::PathBuf, sync::RwLock, time::Duration};
+use uom::si::{
+    f64::{Length, Velocity},
+    length::{meter, millimeter},
+    velocity::meter_per_second,
+};
+
+/// File inside [`crate::storage::data_dir`] the spool types are stored in
+pub const SPOOL_TYPES_FILE: &str = "spool_types.json";
+
+lazy_static! {
+    /// Spool types a winder can select, shared by all winders
+    pub static ref SPOOL_TYPES: RwLock<SpoolTypeStore> =
+        RwLock::new(SpoolTypeStore::load(storage::data_dir().join(SPOOL_TYPES_FILE)));
+}
+
+/// Share of the space between core and flanges filled with filament, round filament wound in
+/// layers leaves gaps
+const PACKING_DENSITY: f64 = PI / 4.0;
+
+/// Dimensions of an empty spool in mm
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, JsonSchema)]
+pub struct SpoolGeometry {
+    /// diameter the filament is wound on in mm
+    pub core_diameter: f64,
+    /// outer diameter of the flanges in mm, the spool is full when the filament reaches it
+    pub flange_diameter: f64,
+    /// inner width between the flanges in mm
+    pub width: f64,
+}
+
+impl SpoolGeometry {
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if [self.core_diameter, self.flange_diameter, self.width]
+            .iter()
+            .any(|value| !value.is_finite() || *value <= 0.0)
+        {
+            return Err(anyhow::anyhow!(
+                "[{}::SpoolGeometry::validate] Spool dimensions must be positive, got {:?}",
+                module_path!(),
+                self
+            ));
+        }
+        if self.flange_diameter <= self.core_diameter {
+            return Err(anyhow::anyhow!(
+                "[{}::SpoolGeometry::validate] Flange diameter {} mm must be larger than the core diameter {} mm",
+                module_path!(),
+                self.flange_diameter,
+                self.core_diameter
+            ));
+        }
+        Ok(())
+    }
+
+    /// The traverse lays the filament between its limits, which must not be wider than the
+    /// spool
+    pub fn fits_traverse(&self, limit_inner: Length, limit_outer: Length) -> bool {
+        // the limits are stored in meters, a span of exactly the width can come back a hair wider
+        (limit_outer - limit_inner).get::<millimeter>() <= self.width + 1e-9
+    }
+
+    /// Filament length the spool holds
+    pub fn capacity(&self, filament_diameter: Length) -> Length {
+        self.wound_length(self.flange_diameter / 2.0, filament_diameter)
+    }
+
+    /// Share of the capacity wound after `wound`, can exceed 1 on an overfull spool
+    pub fn fill(&self, wound: Length, filament_diameter: Length) -> f64 {
+        let capacity = self.capacity(filament_diameter).get::<meter>();
+        if capacity > 0.0 {
+            wound.get::<meter>() / capacity
+        } else {
+            0.0
+        }
+    }
+
+    /// Filament length wound up to an outer radius of `radius` mm
+    fn wound_length(&self, radius: f64, filament_diameter: Length) -> Length {
+        let core_radius = self.core_diameter / 2.0;
+        let filament_diameter = filament_diameter.get::<millimeter>();
+        if filament_diameter <= 0.0 || radius <= core_radius {
+            return Length::new::<meter>(0.0);
+        }
+        let volume = PI * radius.mul_add(radius, -(core_radius * core_radius)) * self.width;
+        let cross_section = PI / 4.0 * filament_diameter * filament_diameter;
+        Length::new::<millimeter>(volume * PACKING_DENSITY / cross_section)
+    }
+}
+
+/// Below this line speed in m/s the line counts as standing, no time is estimated
+const MIN_LINE_SPEED: f64 = 0.001;
+
+/// Time to wind `remaining` at `line_speed`, unknown while the line stands
+pub fn time_to_wind(remaining: Length, line_speed: Velocity) -> Option<Duration> {
+    let line_speed = line_speed.get::<meter_per_second>();
+    if line_speed < MIN_LINE_SPEED {
+        return None;
+    }
+    Duration::try_from_secs_f64(remaining.get::<meter>().max(0.0) / line_speed).ok()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct SpoolType {
+    pub name: String,
+    pub geometry: SpoolGeometry,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub enum SpoolTypeMutation {
+    /// Add a spool type or replace the one with the same name
+    SaveSpoolType(SpoolType),
+    /// Remove a spool type by name
+    DeleteSpoolType(String),
+}
+
+/// Persisted spool types by name
+#[derive(Debug)]
+pub struct SpoolTypeStore {
+    path: PathBuf,
+    types: BTreeMap<String, SpoolGeometry>,
+}
+
+impl SpoolTypeStore {
+    pub const fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            types: BTreeMap::new(),
+        }
+    }
+
+    /// Loads the spool types from `path`, a missing or broken file results in an empty store
+    pub fn load(path: PathBuf) -> Self {
+        let mut store = Self::new(path);
+        match storage::read_json::<Vec<SpoolType>>(&store.path) {
+            Ok(Some(types)) => {
+                for spool_type in types {
+                    store.types.insert(spool_type.name, spool_type.geometry);
+                }
+                tracing::info!("Loaded {} spool types", store.types.len());
+            }
+            Ok(None) => {}
+            Err(e) => tracing::error!("Failed to load spool types: {:?}", e),
+        }
+        store
+    }
+
+    pub fn get(&self, name: &str) -> Option<SpoolGeometry> {
+        self.types.get(name).copied()
+    }
+
+    pub fn list(&self) -> Vec<SpoolType> {
+        self.types
+            .iter()
+            .map(|(name, geometry)| SpoolType {
+                name: name.clone(),
+                geometry: *geometry,
+            })
+            .collect()
+    }
+
+    /// Inserts or replaces a spool type and writes the store to disk
+    pub fn save(&mut self, spool_type: SpoolType) -> Result<(), anyhow::Error> {
+        if spool_type.name.trim().is_empty() {
+            return Err(anyhow::anyhow!(
+                "[{}::SpoolTypeStore::save] Spool types need a name",
+                module_path!()
+            ));
+        }
+        spool_type.geometry.validate()?;
+        self.types.insert(spool_type.name, spool_type.geometry);
+        self.persist()
+    }
+
+    /// Removes a spool type, winders that selected it keep its geometry
+    pub fn remove(&mut self, name: &str) -> Result<(), anyhow::Error> {
+        if self.types.remove(name).is_none() {
+            return Err(anyhow::anyhow!(
+                "[{}::SpoolTypeStore::remove] Spool type {} doesn't exist",
+                module_path!(),
+                name
+            ));
+        }
+        self.persist()
+    }
+
+    fn persist(&self) -> Result<(), anyhow::Error> {
+        storage::write_json(&self.path, &self.list())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn mm(value: f64) -> Length {
+        Length::new::<millimeter>(value)
+    }
+
+    #[test]
+    fn test_geometry() {
+        let spool = SpoolGeometry {
+            core_diameter: 100.0,
+            flange_diameter: 200.0,
+            width: 60.0,
+        };
+        assert!(spool.validate().is_ok());
+        assert!(
+            SpoolGeometry {
+                flange_diameter: 90.0,
+                ..spool
+            }
+            .validate()
+            .is_err()
+        );
+
+        // the packing density cancels the π/4 of the filament cross section
+        let capacity = spool.capacity(mm(1.75)).get::<meter>();
+        assert_relative_eq!(
+            capacity,
+            (200.0f64.powi(2) - 100.0f64.powi(2)) / 4.0 * PI * 60.0 / 1.75f64.powi(2) / 1000.0,
+            epsilon = 1e-9
+        );
+        assert_relative_eq!(
+            spool.fill(Length::new::<meter>(capacity / 2.0), mm(1.75)),
+            0.5
+        );
+
+        assert!(spool.fits_traverse(mm(10.0), mm(70.0)));
+        assert!(!spool.fits_traverse(mm(10.0), mm(71.0)));
+    }
+
+    #[test]
+    fn test_time_to_wind() {
+        let speed = Velocity::new::<meter_per_second>(0.5);
+        assert_eq!(
+            time_to_wind(Length::new::<meter>(300.0), speed),
+            Some(Duration::from_secs(600))
+        );
+        assert_eq!(
+            time_to_wind(Length::new::<meter>(-1.0), speed),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            time_to_wind(
+                Length::new::<meter>(300.0),
+                Velocity::new::<meter_per_second>(0.0)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_store_roundtrip() {
+        let path = std::env::temp_dir()
+            .join(format!("qitech-spool-types-{}", std::process::id()))
+            .join(SPOOL_TYPES_FILE);
+        let spool_type = SpoolType {
+            name: "Masterspool".to_string(),
+            geometry: SpoolGeometry {
+                core_diameter: 102.0,
+                flange_diameter: 200.0,
+                width: 55.0,
+            },
+        };
+
+        let mut store = SpoolTypeStore::new(path.clone());
+        store.save(spool_type.clone()).unwrap();
+        assert!(
+            store
+                .save(SpoolType {
+                    name: " ".to_string(),
+                    ..spool_type
+                })
+                .is_err()
+        );
+
+        let mut loaded = SpoolTypeStore::load(path.clone());
+        assert_eq!(loaded.list(), vec![spool_type.clone()]);
+        loaded.remove(&spool_type.name).unwrap();
+        assert!(loaded.remove(&spool_type.name).is_err());
+        assert!(SpoolTypeStore::load(path).get(&spool_type.name).is_none());
+    }
+}
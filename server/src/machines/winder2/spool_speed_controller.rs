@@ -6,11 +6,12 @@ use crate::machines::winder2::{
 use control_core::controllers::second_degree_motion::acceleration_position_controller::MotionControllerError;
 
 use super::tension_arm::TensionArm;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::time::Instant;
-use uom::si::f64::AngularVelocity;
+use uom::si::f64::{AngularVelocity, Length};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub enum SpoolSpeedControllerType {
     Adaptive,
     MinMax,
@@ -133,6 +134,15 @@ impl SpoolSpeedController {
     }
 
     // Adaptive controller parameter getters and setters
+    /// Learned by the adaptive controller, grows with the radius of the wound spool
+    pub fn get_adaptive_speed_factor(&self) -> Length {
+        self.adaptive_controller.get_speed_factor()
+    }
+
+    pub fn set_adaptive_speed_factor(&mut self, speed_factor: Length) {
+        self.adaptive_controller.set_speed_factor(speed_factor);
+    }
+
     pub const fn get_adaptive_tension_target(&self) -> f64 {
         self.adaptive_controller.get_tension_target()
     }
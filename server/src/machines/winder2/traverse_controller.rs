@@ -1,9 +1,9 @@
 use std::time::Instant;
 
 use control_core::converters::linear_step_converter::LinearStepConverter;
-use ethercat_hal::io::{
-    digital_input::DigitalInput, stepper_velocity_el70x1::StepperVelocityEL70x1,
-};
+use ethercat_hal::io::stepper_velocity_el70x1::StepperVelocityEL70x1;
+use schemars::JsonSchema;
+use serde::Serialize;
 use uom::{
     ConstZero,
     si::{
@@ -20,6 +20,8 @@ pub struct TraverseController {
     position: Length,
     limit_inner: Length,
     limit_outer: Length,
+    /// Soft limit of every movement except homing, the end stop is the lower soft limit
+    travel: Length,
     step_size: Length,
     padding: Length,
     state: State,
@@ -58,6 +60,17 @@ pub enum State {
     Traversing(TraversingState),
 }
 
+/// Homing progress reported to clients
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum HomingStatus {
+    /// Position unknown, only homing moves the traverse
+    NotHomed,
+    /// Searching or validating the end stop
+    Homing,
+    /// Position known, movements are limited to the travel
+    Homed,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum TraversingState {
     /// Like [`State::GoingOut`] but
@@ -101,12 +114,13 @@ pub enum HomingState {
 }
 
 impl TraverseController {
-    pub fn new(limit_inner: Length, limit_outer: Length, microsteps: u8) -> Self {
+    pub fn new(limit_inner: Length, limit_outer: Length, travel: Length, microsteps: u8) -> Self {
         Self {
             enabled: false,
             position: Length::ZERO,
             limit_inner,
             limit_outer,
+            travel,
             step_size: Length::new::<millimeter>(1.75), // Default step size
             padding: Length::new::<millimeter>(0.88),   // Default padding
             state: State::NotHomed,
@@ -153,6 +167,15 @@ impl TraverseController {
         self.limit_outer
     }
 
+    pub fn get_travel(&self) -> Length {
+        self.travel
+    }
+
+    /// Limits must lie between the end stop and the travel
+    pub fn within_travel(&self, limit_inner: Length, limit_outer: Length) -> bool {
+        limit_inner >= Length::ZERO && limit_outer <= self.travel
+    }
+
     pub fn get_step_size(&self) -> Length {
         self.step_size
     }
@@ -194,8 +217,27 @@ impl TraverseController {
         self.state = State::Homing(HomingState::Initialize);
     }
 
+    /// Interlocked, the traverse only starts traversing once homed
     pub const fn start_traversing(&mut self) {
-        self.state = State::Traversing(TraversingState::GoingOut);
+        if self.is_homed() && !self.is_going_home() {
+            self.state = State::Traversing(TraversingState::GoingOut);
+        }
+    }
+
+    /// Stops homing, the position is unknown afterwards
+    pub const fn abort_homing(&mut self) {
+        if self.is_going_home() {
+            let old_state = std::mem::replace(&mut self.state, State::NotHomed);
+            self.did_change_state |= self.update_did_change_state(&old_state);
+        }
+    }
+
+    pub const fn homing_status(&self) -> HomingStatus {
+        match self.state {
+            State::NotHomed => HomingStatus::NotHomed,
+            State::Homing(_) => HomingStatus::Homing,
+            _ => HomingStatus::Homed,
+        }
     }
 
     pub const fn is_homed(&self) -> bool {
@@ -277,11 +319,13 @@ impl TraverseController {
     /// Calculates a desired speed based on the current state and the end stop status.
     ///
     /// Positive speed moved out, negative speed moves in.
+    /// `end_stop` is true while the traverse end stop is triggered.
+    /// `traverse_speed` is the absolute speed while traversing, planned by the winding pattern.
     fn get_speed(
         &mut self,
         traverse: &mut StepperVelocityEL70x1,
-        traverse_end_stop: &DigitalInput,
-        spool_speed: AngularVelocity,
+        end_stop: bool,
+        traverse_speed: Velocity,
     ) -> Velocity {
         // Don't move if not enabled or in a state that doesn't result in movement
         if !self.enabled {
@@ -314,7 +358,7 @@ impl TraverseController {
             State::Homing(homing_state) => match homing_state {
                 HomingState::Initialize => {
                     // If endstop is triggered, escape the endstop
-                    if traverse_end_stop.get_value().unwrap_or(false) {
+                    if end_stop {
                         self.state = State::Homing(HomingState::EscapeEndstop);
                     } else {
                         // If endstop is not triggered, move to the endstop
@@ -323,20 +367,20 @@ impl TraverseController {
                 }
                 HomingState::EscapeEndstop => {
                     // Move out until endstop is not triggered anymore
-                    if !traverse_end_stop.get_value().unwrap_or(false) {
+                    if !end_stop {
                         self.state = State::Homing(HomingState::FindEndstopFineDistancing);
                     }
                 }
                 HomingState::FindEndstopFineDistancing => {
                     // Move out until endstop is not triggered anymore
-                    if !traverse_end_stop.get_value().unwrap_or(false) {
+                    if !end_stop {
                         // Find endstop fine
                         self.state = State::Homing(HomingState::FindEndtopFine);
                     }
                 }
                 HomingState::FindEndtopFine => {
                     // If endstop is reached change to idle
-                    if traverse_end_stop.get_value().unwrap_or(false) {
+                    if end_stop {
                         // Set poition of traverse to 0
                         traverse.set_position(0);
                         // Put Into Idle
@@ -345,7 +389,7 @@ impl TraverseController {
                 }
                 HomingState::FindEndstopCoarse => {
                     // Move to endstop
-                    if traverse_end_stop.get_value().unwrap_or(false) {
+                    if end_stop {
                         // Move awaiy from endstop
                         self.state = State::Homing(HomingState::FindEndstopFineDistancing);
                     }
@@ -397,7 +441,7 @@ impl TraverseController {
 
         // Speed
 
-        match &self.state {
+        let speed = match &self.state {
             State::NotHomed => Velocity::ZERO, // Not homed, no movement
             State::Idle => Velocity::ZERO,     // No movement in idle state
             State::GoingIn => {
@@ -457,14 +501,29 @@ impl TraverseController {
                 }
                 TraversingState::TraversingIn => self.speed_to_position(
                     self.limit_inner + self.padding - Length::new::<millimeter>(0.01),
-                    Self::calculate_traverse_speed(spool_speed, self.step_size),
+                    traverse_speed,
                 ),
                 TraversingState::TraversingOut => self.speed_to_position(
                     self.limit_outer - self.padding + Length::new::<millimeter>(0.01),
-                    Self::calculate_traverse_speed(spool_speed, self.step_size),
+                    traverse_speed,
                 ),
             },
+        };
+
+        self.limit_to_travel(speed)
+    }
+
+    /// Stops movements past the end stop or the travel, homing has to find the end stop
+    fn limit_to_travel(&self, speed: Velocity) -> Velocity {
+        if matches!(self.state, State::Homing(_)) {
+            return speed;
+        }
+        if (self.position <= Length::ZERO && speed < Velocity::ZERO)
+            || (self.position >= self.travel && speed > Velocity::ZERO)
+        {
+            return Velocity::ZERO;
         }
+        speed
     }
 
     /// Calculate the traverse speed
@@ -490,10 +549,10 @@ impl TraverseController {
     pub fn update_speed(
         &mut self,
         traverse: &mut StepperVelocityEL70x1,
-        traverse_end_stop: &DigitalInput,
-        spool_speed: AngularVelocity,
+        end_stop: bool,
+        traverse_speed: Velocity,
     ) {
-        let speed = self.get_speed(traverse, traverse_end_stop, spool_speed);
+        let speed = self.get_speed(traverse, end_stop, traverse_speed);
         let steps_per_second = self.fullstep_converter.velocity_to_steps(speed);
         // ignore if we can't set speed
         let _ = traverse.set_speed(steps_per_second);
@@ -518,4 +577,77 @@ mod tests {
             epsilon = f64::EPSILON
         );
     }
+
+    fn controller() -> TraverseController {
+        TraverseController::new(
+            Length::new::<millimeter>(22.0),
+            Length::new::<millimeter>(92.0),
+            Length::new::<millimeter>(120.0),
+            64,
+        )
+    }
+
+    #[test]
+    fn test_traversing_requires_homing() {
+        let mut controller = controller();
+        controller.start_traversing();
+        assert!(!controller.is_traversing());
+        assert_eq!(controller.homing_status(), HomingStatus::NotHomed);
+
+        controller.goto_home();
+        assert_eq!(controller.homing_status(), HomingStatus::Homing);
+        controller.start_traversing();
+        assert!(!controller.is_traversing());
+
+        controller.state = State::Idle;
+        controller.start_traversing();
+        assert!(controller.is_traversing());
+        assert_eq!(controller.homing_status(), HomingStatus::Homed);
+    }
+
+    #[test]
+    fn test_abort_homing() {
+        let mut controller = controller();
+        controller.state = State::Idle;
+        controller.abort_homing();
+        assert_eq!(controller.homing_status(), HomingStatus::Homed);
+
+        controller.goto_home();
+        controller.did_change_state();
+        controller.abort_homing();
+        assert_eq!(controller.homing_status(), HomingStatus::NotHomed);
+        assert!(controller.did_change_state());
+        assert_eq!(controller.get_current_position(), None);
+    }
+
+    #[test]
+    fn test_limit_to_travel() {
+        let mut controller = controller();
+        let out = Velocity::new::<millimeter_per_second>(10.0);
+        controller.state = State::GoingOut;
+
+        controller.position = Length::new::<millimeter>(50.0);
+        assert_eq!(controller.limit_to_travel(out), out);
+        assert_eq!(controller.limit_to_travel(-out), -out);
+
+        controller.position = Length::new::<millimeter>(120.0);
+        assert_eq!(controller.limit_to_travel(out), Velocity::ZERO);
+        assert_eq!(controller.limit_to_travel(-out), -out);
+
+        controller.position = Length::new::<millimeter>(-0.1);
+        assert_eq!(controller.limit_to_travel(-out), Velocity::ZERO);
+
+        // homing searches the end stop below the known position
+        controller.state = State::Homing(HomingState::FindEndstopCoarse);
+        assert_eq!(controller.limit_to_travel(-out), -out);
+
+        assert!(controller.within_travel(
+            Length::new::<millimeter>(0.0),
+            Length::new::<millimeter>(120.0)
+        ));
+        assert!(!controller.within_travel(
+            Length::new::<millimeter>(22.0),
+            Length::new::<millimeter>(121.0)
+        ));
+    }
 }
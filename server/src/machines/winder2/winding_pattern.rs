@@ -0,0 +1,266 @@
+use super::traverse_controller::TraverseController;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uom::{
+    ConstZero,
+    si::{
+        angle::{degree, radian},
+        angular_velocity::revolution_per_second,
+        f64::{Angle, AngularVelocity, Length, Velocity},
+        length::millimeter,
+        velocity::millimeter_per_second,
+    },
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, JsonSchema)]
+pub enum WindingPattern {
+    /// The traverse moves a fixed pitch per spool revolution
+    /// The crossing angle gets smaller as the spool grows
+    #[default]
+    Precision,
+
+    /// The traverse follows the filament speed at a fixed crossing angle
+    /// The pitch gets larger as the spool grows
+    WildWind,
+}
+
+/// Traverse movement of one control cycle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TraversePlan {
+    /// Absolute traverse speed while traversing
+    pub speed: Velocity,
+    /// Traverse movement per spool revolution
+    pub pitch: Length,
+    /// Angle between the filament and the spool circumference
+    pub crossing_angle: Angle,
+}
+
+/// Coordinates the traverse with the spool for the selected [`WindingPattern`]
+#[derive(Debug)]
+pub struct WindingPatternPlanner {
+    pattern: WindingPattern,
+    /// Crossing angle of [`WindingPattern::WildWind`]
+    crossing_angle: Angle,
+    /// Plan of the last cycle
+    plan: TraversePlan,
+}
+
+impl WindingPatternPlanner {
+    pub const DEFAULT_CROSSING_ANGLE_DEG: f64 = 10.0;
+    pub const MIN_CROSSING_ANGLE_DEG: f64 = 0.1;
+    pub const MAX_CROSSING_ANGLE_DEG: f64 = 30.0;
+
+    pub const fn is_valid_crossing_angle(degrees: f64) -> bool {
+        degrees >= Self::MIN_CROSSING_ANGLE_DEG && degrees <= Self::MAX_CROSSING_ANGLE_DEG
+    }
+
+    pub fn new(pattern: WindingPattern, crossing_angle: Angle) -> Self {
+        Self {
+            pattern,
+            crossing_angle,
+            plan: TraversePlan {
+                speed: Velocity::ZERO,
+                pitch: Length::ZERO,
+                crossing_angle: Angle::ZERO,
+            },
+        }
+    }
+
+    pub const fn get_pattern(&self) -> WindingPattern {
+        self.pattern
+    }
+
+    pub const fn set_pattern(&mut self, pattern: WindingPattern) {
+        self.pattern = pattern;
+    }
+
+    pub fn get_crossing_angle(&self) -> Angle {
+        self.crossing_angle
+    }
+
+    pub fn set_crossing_angle(&mut self, crossing_angle: Angle) -> Result<(), anyhow::Error> {
+        let degrees = crossing_angle.get::<degree>();
+        if !Self::is_valid_crossing_angle(degrees) {
+            return Err(anyhow::anyhow!(
+                "[{}::WindingPatternPlanner::set_crossing_angle] Crossing angle must be between {}° and {}°, got {}°",
+                module_path!(),
+                Self::MIN_CROSSING_ANGLE_DEG,
+                Self::MAX_CROSSING_ANGLE_DEG,
+                degrees
+            ));
+        }
+        self.crossing_angle = crossing_angle;
+        Ok(())
+    }
+
+    pub const fn get_plan(&self) -> TraversePlan {
+        self.plan
+    }
+
+    /// Computes the traverse speed for this cycle
+    ///
+    /// - `spool_speed`: current spool speed
+    /// - `filament_speed`: speed the filament arrives at the spool, which is the puller speed
+    /// - `step_size`: traverse pitch of [`WindingPattern::Precision`]
+    ///
+    /// The filament lays down at the circumferential speed of the spool, so the crossing angle is
+    /// `tan(angle) = traverse speed / filament speed` independent of the current spool diameter.
+    pub fn plan(
+        &mut self,
+        spool_speed: AngularVelocity,
+        filament_speed: Velocity,
+        step_size: Length,
+    ) -> TraversePlan {
+        let revolutions_per_second = spool_speed.get::<revolution_per_second>().abs();
+        let filament_speed = filament_speed.abs();
+
+        self.plan = match self.pattern {
+            WindingPattern::Precision => {
+                let speed =
+                    TraverseController::calculate_traverse_speed(spool_speed, step_size).abs();
+                TraversePlan {
+                    speed,
+                    pitch: step_size,
+                    crossing_angle: match filament_speed > Velocity::ZERO {
+                        true => Angle::new::<radian>((speed / filament_speed).value.atan()),
+                        false => Angle::ZERO,
+                    },
+                }
+            }
+            WindingPattern::WildWind => {
+                let speed = filament_speed * self.crossing_angle.tan().value;
+                TraversePlan {
+                    speed,
+                    pitch: match revolutions_per_second > 0.0 {
+                        true => Length::new::<millimeter>(
+                            speed.get::<millimeter_per_second>() / revolutions_per_second,
+                        ),
+                        false => Length::ZERO,
+                    },
+                    crossing_angle: self.crossing_angle,
+                }
+            }
+        };
+        self.plan
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use control_core::uom_extensions::velocity::meter_per_minute;
+    use uom::si::angular_velocity::revolution_per_minute;
+
+    #[test]
+    fn test_precision_keeps_pitch() {
+        let mut planner =
+            WindingPatternPlanner::new(WindingPattern::Precision, Angle::new::<degree>(5.0));
+        let step_size = Length::new::<millimeter>(1.75);
+        let filament_speed = Velocity::new::<meter_per_minute>(10.0);
+
+        // the spool slows down as it grows, the pitch stays and the angle shrinks
+        let small = planner.plan(
+            AngularVelocity::new::<revolution_per_minute>(60.0),
+            filament_speed,
+            step_size,
+        );
+        let large = planner.plan(
+            AngularVelocity::new::<revolution_per_minute>(30.0),
+            filament_speed,
+            step_size,
+        );
+
+        assert_relative_eq!(small.speed.get::<millimeter_per_second>(), 1.75);
+        assert_relative_eq!(large.pitch.get::<millimeter>(), 1.75);
+        assert!(large.crossing_angle < small.crossing_angle);
+        assert_relative_eq!(
+            small.crossing_angle.tan().value,
+            1.75 / (10_000.0 / 60.0),
+            epsilon = 1e-12
+        );
+    }
+
+    #[test]
+    fn test_wild_wind_keeps_crossing_angle() {
+        let mut planner =
+            WindingPatternPlanner::new(WindingPattern::WildWind, Angle::new::<degree>(10.0));
+        let step_size = Length::new::<millimeter>(1.75);
+        let filament_speed = Velocity::new::<meter_per_minute>(6.0);
+
+        let small = planner.plan(
+            AngularVelocity::new::<revolution_per_minute>(60.0),
+            filament_speed,
+            step_size,
+        );
+        let large = planner.plan(
+            AngularVelocity::new::<revolution_per_minute>(30.0),
+            filament_speed,
+            step_size,
+        );
+
+        let expected_speed = 100.0 * 10f64.to_radians().tan();
+        assert_relative_eq!(
+            small.speed.get::<millimeter_per_second>(),
+            expected_speed,
+            epsilon = 1e-9
+        );
+        assert_relative_eq!(
+            large.speed.get::<millimeter_per_second>(),
+            expected_speed,
+            epsilon = 1e-9
+        );
+        assert_relative_eq!(
+            large.pitch.get::<millimeter>(),
+            2.0 * small.pitch.get::<millimeter>()
+        );
+        assert_relative_eq!(large.crossing_angle.get::<degree>(), 10.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_stopped_spool() {
+        let mut planner =
+            WindingPatternPlanner::new(WindingPattern::WildWind, Angle::new::<degree>(10.0));
+        let plan = planner.plan(
+            AngularVelocity::ZERO,
+            Velocity::ZERO,
+            Length::new::<millimeter>(1.75),
+        );
+        assert_eq!(plan.speed, Velocity::ZERO);
+        assert_eq!(plan.pitch, Length::ZERO);
+
+        planner.set_pattern(WindingPattern::Precision);
+        let plan = planner.plan(
+            AngularVelocity::ZERO,
+            Velocity::ZERO,
+            Length::new::<millimeter>(1.75),
+        );
+        assert_eq!(plan.crossing_angle, Angle::ZERO);
+    }
+
+    #[test]
+    fn test_crossing_angle_range() {
+        let mut planner =
+            WindingPatternPlanner::new(WindingPattern::WildWind, Angle::new::<degree>(10.0));
+        assert!(
+            planner
+                .set_crossing_angle(Angle::new::<degree>(0.0))
+                .is_err()
+        );
+        assert!(
+            planner
+                .set_crossing_angle(Angle::new::<degree>(45.0))
+                .is_err()
+        );
+        assert!(
+            planner
+                .set_crossing_angle(Angle::new::<degree>(7.5))
+                .is_ok()
+        );
+        assert_relative_eq!(
+            planner.get_crossing_angle().get::<degree>(),
+            7.5,
+            epsilon = 1e-9
+        );
+    }
+}
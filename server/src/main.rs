@@ -9,15 +9,38 @@ static GLOBAL: Jemalloc = Jemalloc;
 #[global_allocator]
 static ALLOC: dhat::Alloc = dhat::Alloc;
 
+use alarms::init::init_alarms;
+use alarms::notifier::init_notifier;
 use app_state::AppState;
 #[cfg(feature = "mock-machine")]
 use mock::init::init_mock;
 use std::{sync::Arc, time::Duration};
 
+use batches::init::init_batches;
+use config::init_config;
+use config::reload::init_config_reload;
+use exporters::grpc::init_grpc;
+use exporters::influxdb::init_influxdb;
+use exporters::modbus::init_modbus;
+use exporters::mqtt::init_mqtt;
+use exporters::opcua::init_opcua;
+use federation::init::init_federation;
+use history::init::init_history;
+use instrumentation::init::init_instrumentation;
+use journal::init_journal;
 use r#loop::init_loop;
+use motion::init_motion;
+use recipes::init::init_recipes;
 use rest::init::init_api;
+use sequences::init::init_sequences;
+use serial::firmware::init::init_firmware;
 #[cfg(not(feature = "mock-machine"))]
 use serial::init::init_serial;
+use serial::sniffer::init::init_sniffer;
+use shutdown::init_shutdown;
+use simulation::init::init_simulation;
+use stack_light::init_stack_light;
+use watchdog::init::init_watchdog;
 
 #[cfg(all(not(target_env = "msvc"), not(feature = "dhat-heap")))]
 use jemalloc_stats::init_jemalloc_stats;
@@ -26,19 +49,41 @@ use jemalloc_stats::init_jemalloc_stats;
 use crate::ethercat::init::init_ethercat;
 use crate::panic::init_panic;
 use crate::socketio::queue::init_socketio_queue;
+use crate::socketio::rate_limits::init_emit_rates;
 
+pub mod alarms;
 pub mod app_state;
+pub mod auth;
+pub mod batches;
+pub mod config;
 pub mod ethercat;
+pub mod exporters;
+pub mod federation;
+pub mod history;
+pub mod instrumentation;
+pub mod io_mapping;
+pub mod journal;
 pub mod logging;
 pub mod r#loop;
 pub mod machines;
+pub mod mes;
 #[cfg(feature = "mock-machine")]
 pub mod mock;
+pub mod motion;
 pub mod panic;
 pub mod performance_metrics;
+pub mod recipes;
+pub mod remote_session;
 pub mod rest;
+pub mod scheduler;
+pub mod sequences;
 pub mod serial;
+pub mod shutdown;
+pub mod simulation;
 pub mod socketio;
+pub mod stack_light;
+pub mod storage;
+pub mod watchdog;
 
 #[cfg(all(not(target_env = "msvc"), not(feature = "dhat-heap")))]
 pub mod jemalloc_stats;
@@ -46,8 +91,14 @@ pub mod jemalloc_stats;
 fn main() {
     // Initialize panic handling
     let thread_panic_tx = init_panic();
+    // the config selects the log levels, its errors are reported once logging runs
+    let config_result = init_config();
     logging::init_tracing();
     tracing::info!("Tracing initialized successfully");
+    if let Err(e) = config_result {
+        tracing::error!("{:?}", e);
+        panic!("Failed to load server config");
+    }
 
     // lock memory (not working thus commented out)
     // if let Err(e) = lock_memory() {
@@ -59,6 +110,21 @@ fn main() {
     #[cfg(all(not(target_env = "msvc"), not(feature = "dhat-heap")))]
     init_jemalloc_stats();
 
+    // runs the machines on a virtual line instead of detecting hardware
+    let simulate = std::env::args().any(|arg| arg == "--simulate");
+
+    // the monotonic time of events counts from here
+    control_core::helpers::clock::init_monotonic_clock();
+
+    // records the socketio traffic from the start, e.g. to catch a bug after a restart
+    if std::env::args().any(|arg| arg == "--record") {
+        if let Err(e) = socketio::recording::start() {
+            tracing::error!("{:?}", e);
+        }
+    }
+
+    // namespaces pick up the rate limits when they are created
+    init_emit_rates();
     let app_state = Arc::new(AppState::new());
 
     // Spawn init thread
@@ -72,10 +138,55 @@ fn main() {
                 init_dhat_heap_profiling();
 
                 init_socketio_queue(thread_panic_tx.clone(), app_state.clone());
+                init_shutdown(thread_panic_tx.clone(), app_state.clone())
+                    .expect("Failed to initialize shutdown");
+                init_config_reload(thread_panic_tx.clone(), app_state.clone())
+                    .expect("Failed to initialize config reload");
+                init_journal(thread_panic_tx.clone()).expect("Failed to initialize journal");
+                init_recipes(app_state.clone());
+                init_sequences(app_state.clone());
+                init_batches(thread_panic_tx.clone(), app_state.clone())
+                    .expect("Failed to initialize batches");
+                init_alarms(thread_panic_tx.clone(), app_state.clone())
+                    .expect("Failed to initialize alarms");
+                init_notifier(thread_panic_tx.clone(), app_state.clone())
+                    .expect("Failed to initialize alarm notifier");
+                init_stack_light(thread_panic_tx.clone(), app_state.clone())
+                    .expect("Failed to initialize stack light");
+                init_sniffer(thread_panic_tx.clone(), app_state.clone())
+                    .expect("Failed to initialize serial sniffer");
+                init_firmware(thread_panic_tx.clone(), app_state.clone())
+                    .expect("Failed to initialize serial firmware updates");
+                init_history(thread_panic_tx.clone(), app_state.clone())
+                    .expect("Failed to initialize history");
+                init_influxdb(thread_panic_tx.clone(), app_state.clone())
+                    .expect("Failed to initialize InfluxDB exporter");
+                init_mqtt(thread_panic_tx.clone(), app_state.clone())
+                    .expect("Failed to initialize MQTT bridge");
+                init_opcua(thread_panic_tx.clone(), app_state.clone())
+                    .expect("Failed to initialize OPC-UA server");
+                init_modbus(thread_panic_tx.clone(), app_state.clone())
+                    .expect("Failed to initialize Modbus TCP slave");
+                init_grpc(thread_panic_tx.clone(), app_state.clone())
+                    .expect("Failed to initialize gRPC API");
+                init_federation(thread_panic_tx.clone(), app_state.clone())
+                    .expect("Failed to initialize federation");
                 init_api(thread_panic_tx.clone(), app_state.clone())
                     .expect("Failed to initialize API");
                 init_loop(thread_panic_tx.clone(), app_state.clone())
                     .expect("Failed to initialize loop");
+                init_motion(thread_panic_tx.clone(), app_state.clone())
+                    .expect("Failed to initialize motion thread");
+                init_watchdog(thread_panic_tx.clone(), app_state.clone())
+                    .expect("Failed to initialize watchdog");
+                init_instrumentation(thread_panic_tx.clone(), app_state.clone())
+                    .expect("Failed to initialize instrumentation");
+
+                if simulate {
+                    init_simulation(thread_panic_tx, app_state)
+                        .expect("Failed to initialize simulation");
+                    return;
+                }
 
                 #[cfg(feature = "mock-machine")]
                 init_mock(app_state.clone()).expect("Failed to initialize mock machines");
@@ -0,0 +1,36 @@
+use super::{MesOrder, OrderStore};
+use crate::{app_state::AppState, batches::api::BatchesNamespaceEvents};
+use control_core::socketio::{event::BuildEvent, namespace::NamespaceCacheingLogic};
+use control_core_derive::BuildEvent;
+use serde::Serialize;
+use std::sync::Arc;
+
+#[derive(Serialize, Debug, Clone, BuildEvent)]
+pub struct OrdersEvent {
+    /// orders pushed by the MES, the next one first
+    pub queued: Vec<MesOrder>,
+    /// order the open run produces
+    pub active: Option<MesOrder>,
+}
+
+impl From<&OrderStore> for OrdersEvent {
+    fn from(orders: &OrderStore) -> Self {
+        Self {
+            queued: orders.queued().iter().cloned().collect(),
+            active: orders.active().cloned(),
+        }
+    }
+}
+
+/// Emits the MES orders to the batches namespace
+pub async fn emit_orders(app_state: &Arc<AppState>) {
+    let event = OrdersEvent::from(&*app_state.mes_orders.read().await).build();
+
+    let batches_namespace = &mut app_state
+        .socketio_setup
+        .namespaces
+        .write()
+        .await
+        .batches_namespace;
+    batches_namespace.emit(BatchesNamespaceEvents::Orders(event));
+}
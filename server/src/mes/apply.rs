@@ -0,0 +1,114 @@
+use super::{MesOrder, OrderCompletion, report_completion};
+use crate::{
+    app_state::AppState,
+    batches::{RunMetadata, RunReport},
+    config::config,
+    recipes::{
+        api::{RecipeAppliedEvent, RecipesNamespaceEvents},
+        apply::apply_recipe,
+    },
+};
+use control_core::socketio::{event::BuildEvent, namespace::NamespaceCacheingLogic};
+use std::sync::Arc;
+
+/// Applies the recipe of the next queued order and opens its run
+///
+/// The order stays at the front of the queue if either fails.
+pub async fn apply_next_order(
+    app_state: &Arc<AppState>,
+    operator: &str,
+) -> Result<(), anyhow::Error> {
+    if let Some(run) = app_state.batches.read().await.current() {
+        return Err(anyhow::anyhow!(
+            "[{}::apply_next_order] Run {} is still open",
+            module_path!(),
+            run.id
+        ));
+    }
+    let order = app_state
+        .mes_orders
+        .write()
+        .await
+        .take_next()
+        .ok_or_else(|| {
+            anyhow::anyhow!("[{}::apply_next_order] No order is queued", module_path!())
+        })?;
+
+    match start_order(app_state, operator, &order).await {
+        Ok(()) => app_state.mes_orders.write().await.activate(order),
+        Err(e) => {
+            app_state.mes_orders.write().await.requeue(order);
+            Err(e)
+        }
+    }
+}
+
+async fn start_order(
+    app_state: &Arc<AppState>,
+    operator: &str,
+    order: &MesOrder,
+) -> Result<(), anyhow::Error> {
+    let results = apply_recipe(app_state, &order.recipe).await?;
+    app_state
+        .recipes
+        .write()
+        .await
+        .set_last_applied(&order.recipe.name);
+    let event = RecipeAppliedEvent {
+        name: order.recipe.name.clone(),
+        results,
+    }
+    .build();
+    app_state
+        .socketio_setup
+        .namespaces
+        .write()
+        .await
+        .recipes_namespace
+        .emit(RecipesNamespaceEvents::RecipeApplied(event));
+
+    app_state.batches.write().await.start_run(RunMetadata {
+        operator: operator.to_string(),
+        material: order.material.clone(),
+        material_density: order.material_density,
+        recipe: Some(order.recipe.name.clone()),
+        order: Some(order.order_id.clone()),
+    })?;
+    tracing::info!("Started MES order {}", order.order_id);
+    Ok(())
+}
+
+/// Ends the order of a closed run and reports it to the MES in the background
+pub async fn complete_order(app_state: &Arc<AppState>, report: &RunReport) {
+    let Some(order_id) = &report.metadata.order else {
+        return;
+    };
+    let order = match app_state.mes_orders.write().await.complete(order_id) {
+        Ok(Some(order)) => order,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::error!("Failed to complete MES order {}: {:?}", order_id, e);
+            return;
+        }
+    };
+    tracing::info!("Completed MES order {}", order.order_id);
+
+    let mes = config().mes.clone();
+    let Some(url) = mes.completion_url else {
+        return;
+    };
+    let completion = OrderCompletion {
+        order_id: order.order_id,
+        report: report.clone(),
+    };
+    smol::spawn(smol::unblock(move || {
+        if let Err(e) = report_completion(&url, mes.token.as_deref(), &completion) {
+            tracing::error!(
+                "Failed to report completion of MES order {}: {:?}",
+                completion.order_id,
+                e
+            );
+        }
+    }))
+    .detach();
+}
@@ -0,0 +1,225 @@
+use crate::{batches::RunReport, recipes::Recipe, storage};
+use control_core::helpers::clock::unix_millis;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, path::PathBuf};
+
+pub mod api;
+pub mod apply;
+
+/// File inside [`storage::data_dir`] holding the queued and the active order
+pub const MES_ORDERS_FILE: &str = "mes_orders.json";
+
+/// Production order pushed by an MES, e.g. `POST /api/v1/mes/orders`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MesOrder {
+    /// Id of the order in the MES, reported back on completion
+    pub order_id: String,
+    /// Applied to the line when the operator starts the order
+    pub recipe: Recipe,
+    pub material: String,
+    /// material density in g/cm³
+    pub material_density: f64,
+    /// unix timestamp in milliseconds, set when the order is received
+    #[serde(default)]
+    #[schemars(skip)]
+    pub received_at: u64,
+}
+
+impl MesOrder {
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.order_id.trim().is_empty() {
+            return Err(anyhow::anyhow!("Order id must not be empty"));
+        }
+        if !(self.material_density.is_finite() && self.material_density > 0.0) {
+            return Err(anyhow::anyhow!(
+                "Material density of order {} must be positive",
+                self.order_id
+            ));
+        }
+        self.recipe.validate()
+    }
+}
+
+/// Sent to the completion URL of the MES when the run of an order is closed
+#[derive(Serialize, Debug, Clone)]
+pub struct OrderCompletion {
+    pub order_id: String,
+    pub report: RunReport,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+struct PersistedOrders {
+    queued: VecDeque<MesOrder>,
+    active: Option<MesOrder>,
+}
+
+/// Orders waiting for the operator and the order in production, persisted as a JSON file
+#[derive(Debug)]
+pub struct OrderStore {
+    path: PathBuf,
+    orders: PersistedOrders,
+}
+
+impl OrderStore {
+    /// Orders beyond this are rejected until the operator works off the queue
+    const MAX_QUEUED: usize = 100;
+
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            orders: PersistedOrders::default(),
+        }
+    }
+
+    /// Loads the orders from `path`
+    ///
+    /// A missing or broken file results in an empty queue so the server still starts.
+    pub fn load(path: PathBuf) -> Self {
+        let mut store = Self::new(path);
+        match storage::read_json::<PersistedOrders>(&store.path) {
+            Ok(Some(orders)) => store.orders = orders,
+            Ok(None) => {}
+            Err(e) => tracing::error!("Failed to load MES orders: {:?}", e),
+        }
+        store
+    }
+
+    pub const fn queued(&self) -> &VecDeque<MesOrder> {
+        &self.orders.queued
+    }
+
+    pub const fn active(&self) -> Option<&MesOrder> {
+        self.orders.active.as_ref()
+    }
+
+    /// Queues an order behind the others, order ids must be unique
+    pub fn enqueue(&mut self, mut order: MesOrder) -> Result<(), anyhow::Error> {
+        order.validate()?;
+        if self.contains(&order.order_id) {
+            return Err(anyhow::anyhow!(
+                "[{}::OrderStore::enqueue] Order {} is already queued",
+                module_path!(),
+                order.order_id
+            ));
+        }
+        if self.orders.queued.len() >= Self::MAX_QUEUED {
+            return Err(anyhow::anyhow!(
+                "[{}::OrderStore::enqueue] The queue is full with {} orders",
+                module_path!(),
+                Self::MAX_QUEUED
+            ));
+        }
+        order.received_at = unix_millis();
+        tracing::info!("Queued MES order {}", order.order_id);
+        self.orders.queued.push_back(order);
+        self.persist()
+    }
+
+    /// Takes the next order to start, [`Self::requeue`] it if it can't be started
+    pub fn take_next(&mut self) -> Option<MesOrder> {
+        self.orders.queued.pop_front()
+    }
+
+    /// Puts an order that couldn't be started back to the front of the queue
+    pub fn requeue(&mut self, order: MesOrder) {
+        self.orders.queued.push_front(order);
+    }
+
+    /// Marks an order as in production
+    pub fn activate(&mut self, order: MesOrder) -> Result<(), anyhow::Error> {
+        self.orders.active = Some(order);
+        self.persist()
+    }
+
+    /// Ends the active order if the closed run belongs to it
+    pub fn complete(&mut self, order_id: &str) -> Result<Option<MesOrder>, anyhow::Error> {
+        if self.active().is_none_or(|order| order.order_id != order_id) {
+            return Ok(None);
+        }
+        let order = self.orders.active.take();
+        self.persist()?;
+        Ok(order)
+    }
+
+    fn contains(&self, order_id: &str) -> bool {
+        self.orders
+            .queued
+            .iter()
+            .chain(self.orders.active.as_ref())
+            .any(|order| order.order_id == order_id)
+    }
+
+    fn persist(&self) -> Result<(), anyhow::Error> {
+        storage::write_json(&self.path, &self.orders)
+    }
+}
+
+/// Posts the completion of an order to the MES
+pub fn report_completion(
+    url: &str,
+    token: Option<&str>,
+    completion: &OrderCompletion,
+) -> Result<(), anyhow::Error> {
+    let mut request = ureq::post(url).header("Content-Type", "application/json");
+    if let Some(token) = token {
+        request = request.header("Authorization", &format!("Bearer {}", token));
+    }
+    request.send(serde_json::to_string(completion)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn order(order_id: &str) -> MesOrder {
+        serde_json::from_value(json!({
+            "order_id": order_id,
+            "recipe": {
+                "name": "PLA 1.75",
+                "laser": {
+                    "target_diameter": 1.75,
+                    "lower_tolerance": 0.05,
+                    "higher_tolerance": 0.05
+                }
+            },
+            "material": "PLA",
+            "material_density": 1.24
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_queue() {
+        let path = std::env::temp_dir().join(format!("mes_orders_{}.json", std::process::id()));
+        let mut store = OrderStore::new(path.clone());
+        store.enqueue(order("A-1")).unwrap();
+        store.enqueue(order("A-2")).unwrap();
+        assert!(store.enqueue(order("A-1")).is_err());
+        assert!(store.enqueue(order(" ")).is_err());
+
+        let next = store.take_next().unwrap();
+        assert_eq!(next.order_id, "A-1");
+        assert!(next.received_at > 0);
+        store.activate(next).unwrap();
+        // still in production
+        assert!(store.enqueue(order("A-1")).is_err());
+        assert!(store.complete("A-2").unwrap().is_none());
+        assert_eq!(store.complete("A-1").unwrap().unwrap().order_id, "A-1");
+
+        let loaded = OrderStore::load(path.clone());
+        assert_eq!(loaded.queued().len(), 1);
+        assert!(loaded.active().is_none());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_unknown_fields() {
+        let mut value = serde_json::to_value(order("A-1")).unwrap();
+        value["quantity"] = json!(3);
+        assert!(serde_json::from_value::<MesOrder>(value).is_err());
+    }
+}
@@ -6,6 +6,7 @@ use {
     crate::machines::registry::MACHINE_REGISTRY,
     crate::serial::devices::mock::MockSerialDevice,
     crate::socketio::main_namespace::{MainNamespaceEvents, machines_event::MachinesEventBuilder},
+    crate::socketio::registry_namespace::sync_registry,
     control_core::{
         serial::{SerialDeviceNew, SerialDeviceNewParams},
         socketio::namespace::NamespaceCacheingLogic,
@@ -22,6 +23,8 @@ pub fn init_mock(app_state: Arc<AppState>) -> Result<(), anyhow::Error> {
         let serial_params = SerialDeviceNewParams {
             path: "/dev/mock-serial".to_string(),
             device_thread_panic_tx,
+            serial_device_identification: None,
+            usb_serial_number: None,
         };
 
         // Create the mock serial device
@@ -40,15 +43,18 @@ pub fn init_mock(app_state: Arc<AppState>) -> Result<(), anyhow::Error> {
                 }
 
                 // Notify clients via socketio about the new machine
-                let app_state_event = app_state.clone();
-                let main_namespace = &mut app_state_event
-                    .socketio_setup
-                    .namespaces
-                    .write()
-                    .await
-                    .main_namespace;
-                let event = MachinesEventBuilder().build(app_state_event.clone());
-                main_namespace.emit(MainNamespaceEvents::MachinesEvent(event));
+                {
+                    let app_state_event = app_state.clone();
+                    let main_namespace = &mut app_state_event
+                        .socketio_setup
+                        .namespaces
+                        .write()
+                        .await
+                        .main_namespace;
+                    let event = MachinesEventBuilder().build(app_state_event.clone());
+                    main_namespace.emit(MainNamespaceEvents::MachinesEvent(event));
+                }
+                sync_registry(&app_state).await;
                 Ok::<(), anyhow::Error>(())
             }
             Err(e) => {
@@ -72,15 +78,18 @@ pub fn init_mock(app_state: Arc<AppState>) -> Result<(), anyhow::Error> {
                 }
 
                 // Notify clients via socketio about the new machine
-                let app_state_event = app_state.clone();
-                let main_namespace = &mut app_state_event
-                    .socketio_setup
-                    .namespaces
-                    .write()
-                    .await
-                    .main_namespace;
-                let event = MachinesEventBuilder().build(app_state_event.clone());
-                main_namespace.emit(MainNamespaceEvents::MachinesEvent(event));
+                {
+                    let app_state_event = app_state.clone();
+                    let main_namespace = &mut app_state_event
+                        .socketio_setup
+                        .namespaces
+                        .write()
+                        .await
+                        .main_namespace;
+                    let event = MachinesEventBuilder().build(app_state_event.clone());
+                    main_namespace.emit(MainNamespaceEvents::MachinesEvent(event));
+                }
+                sync_registry(&app_state).await;
                 Ok(())
             }
             Err(e) => {
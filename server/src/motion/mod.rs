@@ -0,0 +1,395 @@
+use crate::{
+    app_state::AppState,
+    config::config,
+    panic::{PanicDetails, send_panic},
+    scheduler::SchedulerConfig,
+};
+use control_core::{
+    machines::{
+        Machine, connection::MachineSlotGeneric, identification::MachineIdentificationUnique,
+    },
+    realtime::{set_core_affinity, set_realtime_priority},
+};
+use smol::{
+    channel::{Receiver, Sender, TrySendError},
+    lock::Mutex,
+};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use tracing::trace_span;
+
+/// Set once the motion thread runs, machines created afterwards leave their motion control
+/// to it
+static MOTION_THREAD_RUNNING: AtomicBool = AtomicBool::new(false);
+
+pub fn motion_thread_running() -> bool {
+    MOTION_THREAD_RUNNING.load(Ordering::Relaxed)
+}
+
+/// Time between two motion updates of a machine created now, the period of the motion
+/// thread or the act period the scheduler gives it, zero if it acts in every loop cycle
+pub fn motion_update_period(machine: &MachineIdentificationUnique) -> Duration {
+    if motion_thread_running() {
+        Duration::from_micros(config().motion.period_us)
+    } else {
+        SchedulerConfig::load().schedule(machine).period()
+    }
+}
+
+/// Work for a machine driven by the motion thread, applied between two motion updates
+///
+/// Runs on the real-time thread, so it may only change the state of the machine and emit
+/// to its namespace, which batches the events for the act loop. Machine mutations do just
+/// that, the winder hands its journal to the journal thread instead of writing it. Work
+/// that blocks, like file or network I/O, belongs to the async side.
+pub type MotionCommand = Box<dyn FnOnce(&mut dyn Machine) + Send>;
+
+/// Command with the machine it applies to, so the motion thread doesn't look the machine up
+/// in the registry
+pub struct QueuedMotionCommand {
+    pub machine: Arc<Mutex<dyn Machine>>,
+    pub command: MotionCommand,
+}
+
+/// Bounded queue from the async side to the motion thread
+///
+/// Mutations go through the queue instead of locking the machine, so API calls can't delay
+/// a motion update. The motion thread holds back at most `capacity` commands of locked
+/// machines, the rest stays in the queue until it has room.
+pub struct MotionSetup {
+    pub commands_tx: Sender<QueuedMotionCommand>,
+    pub commands_rx: Receiver<QueuedMotionCommand>,
+    capacity: usize,
+}
+
+impl MotionSetup {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        let (commands_tx, commands_rx) = smol::channel::bounded(capacity);
+        Self {
+            commands_tx,
+            commands_rx,
+            capacity,
+        }
+    }
+
+    /// Queues a command for a machine, fails if the queue is full
+    pub fn queue(
+        &self,
+        machine: Arc<Mutex<dyn Machine>>,
+        command: MotionCommand,
+    ) -> Result<(), anyhow::Error> {
+        self.commands_tx
+            .try_send(QueuedMotionCommand { machine, command })
+            .map_err(|e| match e {
+                TrySendError::Full(_) => anyhow::anyhow!(
+                    "[{}::MotionSetup::queue] Motion command queue is full",
+                    module_path!()
+                ),
+                TrySendError::Closed(_) => anyhow::anyhow!(
+                    "[{}::MotionSetup::queue] Motion command queue is closed",
+                    module_path!()
+                ),
+            })
+    }
+}
+
+/// Starts the motion thread if `motion.dedicated_thread` is enabled
+///
+/// The thread sleeps with the OS timer instead of the smol reactor, so socketio traffic on
+/// the executors does not add jitter to the speed updates.
+pub fn init_motion(
+    thread_panic_tx: Sender<PanicDetails>,
+    app_state: Arc<AppState>,
+) -> Result<(), anyhow::Error> {
+    let motion_config = config().motion.clone();
+    if !motion_config.dedicated_thread {
+        return Ok(());
+    }
+    let period = Duration::from_micros(motion_config.period_us);
+
+    std::thread::Builder::new()
+        .name("motion".to_owned())
+        .spawn(move || {
+            send_panic(thread_panic_tx);
+
+            if let Some(core) = motion_config.core {
+                let _ = set_core_affinity(core);
+            }
+            if let Err(e) = set_realtime_priority() {
+                tracing::error!(
+                    "[{}::init_motion] Failed to set real-time priority \n{:?}",
+                    module_path!(),
+                    e
+                );
+            }
+            MOTION_THREAD_RUNNING.store(true, Ordering::Relaxed);
+            tracing::info!("Running motion control every {}µs", period.as_micros());
+
+            let mut pending = Vec::new();
+            let mut next = Instant::now();
+            loop {
+                motion_once(&app_state, &mut pending);
+
+                next += period;
+                let now = Instant::now();
+                match next.checked_duration_since(now) {
+                    Some(remaining) => std::thread::sleep(remaining),
+                    // don't catch up on missed updates
+                    None => next = now,
+                }
+            }
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_motion] Failed to spawn motion thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Never waits for a lock, whatever is locked elsewhere is left for the next update
+fn motion_once(app_state: &AppState, pending: &mut Vec<QueuedMotionCommand>) {
+    let span = trace_span!("motion_once");
+    let _enter = span.enter();
+
+    // the registry is only written while machines are added or removed
+    let (connected, complete) = match app_state.machines.try_read() {
+        Some(machines) => connected_machines(machines.iter().map(|(_, slot)| slot)),
+        None => (Vec::new(), false),
+    };
+
+    // commands first, so mutations apply to the next speed update
+    receive_commands(&app_state.motion, pending);
+    if complete {
+        drop_removed(pending, &connected);
+    }
+    apply_commands(pending);
+
+    let now = Instant::now();
+    for machine in &connected {
+        // the act cycle holds the lock, the machine gets the next update
+        if let Some(mut machine) = machine.try_lock() {
+            if machine.has_dedicated_motion() {
+                machine.act_motion(now);
+            }
+        }
+    }
+}
+
+/// Machines of the connected slots and whether every slot could be read
+///
+/// A removed machine can only be told from one whose slot is locked if every slot was read.
+fn connected_machines<'a>(
+    slots: impl Iterator<Item = &'a Arc<Mutex<MachineSlotGeneric>>>,
+) -> (Vec<Arc<Mutex<dyn Machine>>>, bool) {
+    let mut connected = Vec::new();
+    let mut complete = true;
+    for slot in slots {
+        match slot.try_lock() {
+            Some(slot) => connected.extend(slot.machine_connection.to_machine()),
+            None => complete = false,
+        }
+    }
+    (connected, complete)
+}
+
+/// Takes commands from the queue while fewer than `capacity` are pending
+fn receive_commands(motion: &MotionSetup, pending: &mut Vec<QueuedMotionCommand>) {
+    let room = motion.capacity.saturating_sub(pending.len());
+    pending.extend(std::iter::from_fn(|| motion.commands_rx.try_recv().ok()).take(room));
+}
+
+/// Drops the commands of machines that were removed or disconnected
+///
+/// Dropping a command drops its reply channel, so the caller learns that the mutation wasn't
+/// applied.
+fn drop_removed(pending: &mut Vec<QueuedMotionCommand>, connected: &[Arc<Mutex<dyn Machine>>]) {
+    pending.retain(|queued| {
+        let registered = connected
+            .iter()
+            .any(|machine| Arc::ptr_eq(machine, &queued.machine));
+        if !registered {
+            tracing::warn!("Dropped a motion command of a machine that is no longer connected");
+        }
+        registered
+    });
+}
+
+/// Applies the commands of the machines that aren't locked, in the order they were queued
+///
+/// Commands of a locked machine, e.g. by its act cycle, stay pending together with the later
+/// commands of the same machine.
+fn apply_commands(pending: &mut Vec<QueuedMotionCommand>) {
+    let mut waiting: Vec<QueuedMotionCommand> = Vec::new();
+    for queued in pending.drain(..) {
+        if waiting
+            .iter()
+            .any(|waiting| Arc::ptr_eq(&waiting.machine, &queued.machine))
+        {
+            waiting.push(queued);
+            continue;
+        }
+        let machine = queued.machine.clone();
+        match machine.try_lock() {
+            Some(mut machine) => (queued.command)(&mut *machine),
+            None => waiting.push(queued),
+        }
+    }
+    *pending = waiting;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use control_core::{
+        machines::{
+            api::MachineApi,
+            identification::{MachineIdentification, MachineIdentificationUnique},
+            new::{MachineAct, MachineNewParams, MachineNewTrait},
+        },
+        socketio::namespace::Namespace,
+    };
+    use control_core_derive::Machine;
+    use serde_json::{Value, json};
+
+    #[derive(Debug, Machine)]
+    struct MotionMachine {
+        machine_identification_unique: MachineIdentificationUnique,
+        mutations: Vec<Value>,
+    }
+
+    impl MachineNewTrait for MotionMachine {
+        fn new(
+            _params: &MachineNewParams<'_, '_, '_, '_, '_, '_, '_>,
+        ) -> Result<Self, anyhow::Error> {
+            Err(anyhow::anyhow!("only created by the tests"))
+        }
+    }
+
+    impl MachineAct for MotionMachine {
+        fn act(&mut self, _now: Instant) {}
+
+        fn has_dedicated_motion(&self) -> bool {
+            true
+        }
+    }
+
+    impl MachineApi for MotionMachine {
+        fn api_mutate(&mut self, value: Value) -> Result<(), anyhow::Error> {
+            self.mutations.push(value);
+            Ok(())
+        }
+
+        fn api_event_namespace(&mut self) -> Arc<Mutex<Namespace>> {
+            Arc::new(Mutex::new(Namespace::new(smol::channel::unbounded().0)))
+        }
+    }
+
+    fn motion_machine() -> Arc<Mutex<MotionMachine>> {
+        Arc::new(Mutex::new(MotionMachine {
+            machine_identification_unique: MachineIdentificationUnique {
+                machine_identification: MachineIdentification {
+                    vendor: 1,
+                    machine: 2,
+                },
+                serial: 1,
+            },
+            mutations: Vec::new(),
+        }))
+    }
+
+    #[test]
+    fn test_queue_is_bounded() {
+        let motion = MotionSetup::new(1);
+        let machine = motion_machine();
+        assert!(motion.queue(machine.clone(), Box::new(|_| {})).is_ok());
+        assert!(motion.queue(machine, Box::new(|_| {})).is_err());
+        assert_eq!(motion.commands_rx.len(), 1);
+    }
+
+    #[test]
+    fn test_mutation_completes() {
+        let motion = MotionSetup::new(4);
+        let machine = motion_machine();
+        let (reply_tx, reply_rx) = smol::channel::bounded(1);
+        motion
+            .queue(
+                machine.clone(),
+                Box::new(move |machine| {
+                    let _ = reply_tx.try_send(machine.api_mutate(json!({ "SetMode": "Pull" })));
+                }),
+            )
+            .unwrap();
+        let mut pending: Vec<_> =
+            std::iter::from_fn(|| motion.commands_rx.try_recv().ok()).collect();
+
+        // the act cycle holds the machine, the mutation waits for the next update
+        let act = machine.lock_blocking();
+        apply_commands(&mut pending);
+        assert_eq!(pending.len(), 1);
+        assert!(reply_rx.try_recv().is_err());
+        drop(act);
+
+        apply_commands(&mut pending);
+        assert!(pending.is_empty());
+        assert!(reply_rx.try_recv().unwrap().is_ok());
+        assert_eq!(
+            machine.lock_blocking().mutations,
+            vec![json!({ "SetMode": "Pull" })]
+        );
+    }
+
+    #[test]
+    fn test_pending_is_capped() {
+        let motion = MotionSetup::new(2);
+        let machine = motion_machine();
+        for _ in 0..2 {
+            motion.queue(machine.clone(), Box::new(|_| {})).unwrap();
+        }
+        let mut pending = vec![QueuedMotionCommand {
+            machine: machine.clone(),
+            command: Box::new(|_| {}),
+        }];
+
+        receive_commands(&motion, &mut pending);
+        assert_eq!(pending.len(), 2);
+        // the rest waits in the queue, which rejects further commands
+        assert_eq!(motion.commands_rx.len(), 1);
+        assert!(motion.queue(machine.clone(), Box::new(|_| {})).is_ok());
+        assert!(motion.queue(machine, Box::new(|_| {})).is_err());
+    }
+
+    #[test]
+    fn test_removed_machine_fails_reply() {
+        let machine = motion_machine();
+        let other = motion_machine();
+        let (reply_tx, reply_rx) = smol::channel::bounded::<Result<(), anyhow::Error>>(1);
+        let mut pending = vec![
+            QueuedMotionCommand {
+                machine: machine.clone(),
+                command: Box::new(move |machine| {
+                    let _ = reply_tx.try_send(machine.api_mutate(json!({ "SetMode": "Pull" })));
+                }),
+            },
+            QueuedMotionCommand {
+                machine: other.clone(),
+                command: Box::new(|_| {}),
+            },
+        ];
+
+        let connected: Vec<Arc<Mutex<dyn Machine>>> = vec![other];
+        drop_removed(&mut pending, &connected);
+        assert_eq!(pending.len(), 1);
+        assert!(reply_rx.try_recv().unwrap_err().is_closed());
+        assert!(machine.lock_blocking().mutations.is_empty());
+    }
+}
@@ -66,6 +66,16 @@ impl EthercatPerformanceMetrics {
         self.loop_times.push_back(duration);
     }
 
+    /// Statistics of the tx_rx times in the window, `None` without measurements
+    pub fn txrx_stats(&self) -> Option<MetricsStats> {
+        (!self.txrx_times.is_empty()).then(|| calculate_stats(&self.txrx_times))
+    }
+
+    /// Statistics of the loop cycle times in the window, `None` without measurements
+    pub fn loop_stats(&self) -> Option<MetricsStats> {
+        (!self.loop_times.is_empty()).then(|| calculate_stats(&self.loop_times))
+    }
+
     /// Logs metrics if enough time has passed
     fn maybe_log_metrics(&mut self) {
         let now = Instant::now();
@@ -103,10 +113,10 @@ impl EthercatPerformanceMetrics {
 
 /// Statistical metrics for a set of duration measurements
 #[derive(Debug)]
-struct MetricsStats {
-    average_ms: f64,
-    percentile_9999_ms: f64,
-    stddev_ms: f64,
+pub struct MetricsStats {
+    pub average_ms: f64,
+    pub percentile_9999_ms: f64,
+    pub stddev_ms: f64,
 }
 
 /// Calculates statistical metrics for a collection of durations
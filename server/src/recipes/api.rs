@@ -0,0 +1,108 @@
+use super::Recipe;
+use crate::app_state::AppState;
+use control_core::{
+    machines::identification::MachineIdentificationUnique,
+    socketio::{
+        event::{BuildEvent, Event, GenericEvent},
+        namespace::{CacheFn, CacheableEvents, Namespace, NamespaceCacheingLogic, cache_one_event},
+        namespace_id::NamespaceId,
+    },
+};
+use control_core_derive::BuildEvent;
+use serde::{Deserialize, Serialize};
+use smol::channel::Sender;
+use socketioxide::extract::SocketRef;
+use std::sync::Arc;
+use tracing::instrument;
+
+#[derive(Serialize, Debug, Clone, BuildEvent)]
+pub struct RecipesEvent {
+    /// all stored recipes
+    pub recipes: Vec<Recipe>,
+    /// name of the recipe applied last
+    pub last_applied: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, BuildEvent)]
+pub struct RecipeAppliedEvent {
+    pub name: String,
+    pub results: Vec<RecipeApplyResult>,
+}
+
+/// Outcome of applying a recipe section to one machine
+#[derive(Serialize, Debug, Clone)]
+pub struct RecipeApplyResult {
+    pub machine_identification_unique: MachineIdentificationUnique,
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub enum Mutation {
+    /// Insert or replace a recipe by its name
+    SaveRecipe(Recipe),
+    DeleteRecipe(String),
+    /// Apply a recipe by name to all connected machines
+    ApplyRecipe(String),
+}
+
+pub enum RecipesNamespaceEvents {
+    Recipes(Event<RecipesEvent>),
+    RecipeApplied(Event<RecipeAppliedEvent>),
+}
+
+impl CacheableEvents<Self> for RecipesNamespaceEvents {
+    fn event_value(&self) -> GenericEvent {
+        match self {
+            Self::Recipes(event) => event.into(),
+            Self::RecipeApplied(event) => event.into(),
+        }
+    }
+
+    fn event_cache_fn(&self) -> CacheFn {
+        match self {
+            Self::Recipes(_) => cache_one_event(),
+            Self::RecipeApplied(_) => cache_one_event(),
+        }
+    }
+}
+
+pub struct RecipesRoom {
+    pub namespace: Namespace,
+}
+
+impl RecipesRoom {
+    pub fn new(socket_queue_tx: Sender<(SocketRef, Arc<GenericEvent>)>) -> Self {
+        Self {
+            namespace: Namespace::new(socket_queue_tx).with_id(NamespaceId::Recipes),
+        }
+    }
+}
+
+impl NamespaceCacheingLogic<RecipesNamespaceEvents> for RecipesRoom {
+    #[instrument(skip_all)]
+    fn emit(&mut self, event: RecipesNamespaceEvents) {
+        let buffer_fn = event.event_cache_fn();
+        let generic_event = Arc::new(event.event_value());
+        self.namespace.emit(generic_event, &buffer_fn);
+    }
+}
+
+/// Emits the current list of recipes to the recipes namespace
+pub async fn emit_recipes(app_state: &Arc<AppState>) {
+    let event = {
+        let recipes = app_state.recipes.read().await;
+        RecipesEvent {
+            recipes: recipes.list(),
+            last_applied: recipes.last_applied(),
+        }
+        .build()
+    };
+
+    let recipes_namespace = &mut app_state
+        .socketio_setup
+        .namespaces
+        .write()
+        .await
+        .recipes_namespace;
+    recipes_namespace.emit(RecipesNamespaceEvents::Recipes(event));
+}
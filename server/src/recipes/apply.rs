@@ -0,0 +1,109 @@
+use super::{Recipe, api::RecipeApplyResult};
+use crate::{app_state::AppState, remote_session::RemoteSession};
+use control_core::{
+    machines::identification::MachineIdentificationUnique,
+    rest::mutation::{MutationError, MutationErrorKind},
+};
+use std::{sync::Arc, time::Instant};
+
+/// Applies every section of a recipe to all connected machines of the matching type
+///
+/// Fails without touching any machine if a section has no connected machine or during a
+/// remote session, which restricts the speeds and heater targets a recipe sets.
+/// Errors of single machines are reported per machine and don't abort the other machines.
+pub async fn apply_recipe(
+    app_state: &Arc<AppState>,
+    recipe: &Recipe,
+) -> Result<Vec<RecipeApplyResult>, anyhow::Error> {
+    recipe.validate()?;
+    let sections = recipe.sections()?;
+
+    let machines_guard = app_state.machines.read().await;
+
+    let key_switch = RemoteSession::key_switch(&machines_guard.values, Instant::now());
+    if let Err(rejection) = app_state
+        .remote_session
+        .read()
+        .await
+        .check_recipe(key_switch)
+    {
+        return Err(MutationError::new(
+            MutationErrorKind::Forbidden,
+            format!(
+                "[{}::apply_recipe] Rejected recipe {}: {}",
+                module_path!(),
+                recipe.name,
+                rejection
+            ),
+        )
+        .into());
+    }
+
+    // pair each section with the connected machines it applies to
+    let mut targets = vec![];
+    let mut missing = vec![];
+    for (machine_identification, section) in sections {
+        let machines: Vec<_> = machines_guard
+            .iter()
+            .filter(|(unique, _)| unique.machine_identification == machine_identification)
+            .filter_map(|(unique, slot)| {
+                let machine = slot.lock_blocking().machine_connection.to_machine();
+                machine.map(|machine| (unique.clone(), machine))
+            })
+            .collect();
+
+        if machines.is_empty() {
+            missing.push(machine_identification);
+            continue;
+        }
+        targets.push((machines, section));
+    }
+    drop(machines_guard);
+
+    if !missing.is_empty() {
+        return Err(anyhow::anyhow!(
+            "[{}::apply_recipe] Recipe {} needs machines that are not connected: {:?}",
+            module_path!(),
+            recipe.name,
+            missing
+        ));
+    }
+
+    let mut results = vec![];
+    for (machines, section) in targets {
+        for (machine_identification_unique, machine) in machines {
+            let result = machine.lock().await.api_apply_recipe(section.clone());
+            app_state
+                .remote_session
+                .write()
+                .await
+                .forget(&machine_identification_unique);
+            results.push(result_for(machine_identification_unique, result));
+        }
+    }
+
+    tracing::info!(
+        "Applied recipe {} to {} machines",
+        recipe.name,
+        results.len()
+    );
+
+    Ok(results)
+}
+
+fn result_for(
+    machine_identification_unique: MachineIdentificationUnique,
+    result: Result<(), anyhow::Error>,
+) -> RecipeApplyResult {
+    if let Err(e) = &result {
+        tracing::warn!(
+            "Failed to apply recipe to machine={} error={:?}",
+            machine_identification_unique,
+            e
+        );
+    }
+    RecipeApplyResult {
+        machine_identification_unique,
+        error: result.err().map(|e| e.to_string()),
+    }
+}
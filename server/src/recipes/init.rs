@@ -0,0 +1,8 @@
+use super::api::emit_recipes;
+use crate::app_state::AppState;
+use std::sync::Arc;
+
+/// Publishes the stored recipes so clients get them on connect
+pub fn init_recipes(app_state: Arc<AppState>) {
+    smol::block_on(emit_recipes(&app_state));
+}
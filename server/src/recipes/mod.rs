@@ -0,0 +1,216 @@
+#[cfg(feature = "mock-machine")]
+use crate::machines::extruder1::mock::ExtruderV2;
+
+#[cfg(not(feature = "mock-machine"))]
+use crate::machines::extruder1::ExtruderV2;
+
+use crate::{
+    machines::{
+        extruder1::api::ExtruderV2Recipe,
+        laser::{LaserMachine, api::LaserRecipe},
+        winder2::{Winder2, api::Winder2Recipe},
+    },
+    storage,
+};
+use control_core::machines::identification::MachineIdentification;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::BTreeMap, path::PathBuf};
+
+pub mod api;
+pub mod apply;
+pub mod init;
+
+/// File inside [`storage::data_dir`] holding all recipes
+pub const RECIPES_FILE: &str = "recipes.json";
+
+/// Named set of settings for the whole line
+///
+/// Every section is optional, a recipe only touches the machines it has a section for.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct Recipe {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub laser: Option<LaserRecipe>,
+    pub winder: Option<Winder2Recipe>,
+    pub extruder: Option<ExtruderV2Recipe>,
+}
+
+impl Recipe {
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.name.trim().is_empty() {
+            return Err(anyhow::anyhow!("Recipe name must not be empty"));
+        }
+        if self.laser.is_none() && self.winder.is_none() && self.extruder.is_none() {
+            return Err(anyhow::anyhow!(
+                "Recipe {} has no machine sections",
+                self.name
+            ));
+        }
+        if let Some(laser) = &self.laser {
+            laser.validate()?;
+        }
+        if let Some(winder) = &self.winder {
+            winder.validate()?;
+        }
+        if let Some(extruder) = &self.extruder {
+            extruder.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Machine sections paired with the machine type they apply to
+    pub fn sections(&self) -> Result<Vec<(MachineIdentification, Value)>, anyhow::Error> {
+        let mut sections = vec![];
+        if let Some(laser) = &self.laser {
+            sections.push((
+                LaserMachine::MACHINE_IDENTIFICATION,
+                serde_json::to_value(laser)?,
+            ));
+        }
+        if let Some(winder) = &self.winder {
+            sections.push((
+                Winder2::MACHINE_IDENTIFICATION,
+                serde_json::to_value(winder)?,
+            ));
+        }
+        if let Some(extruder) = &self.extruder {
+            sections.push((
+                ExtruderV2::MACHINE_IDENTIFICATION,
+                serde_json::to_value(extruder)?,
+            ));
+        }
+        Ok(sections)
+    }
+}
+
+/// Recipes persisted as a JSON file
+#[derive(Debug)]
+pub struct RecipeStore {
+    path: PathBuf,
+    recipes: BTreeMap<String, Recipe>,
+    last_applied: Option<String>,
+}
+
+impl RecipeStore {
+    pub const fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            recipes: BTreeMap::new(),
+            last_applied: None,
+        }
+    }
+
+    /// Loads the recipes from `path`
+    ///
+    /// A missing or broken file results in an empty store so the server still starts.
+    pub fn load(path: PathBuf) -> Self {
+        let mut store = Self::new(path);
+        match storage::read_json::<Vec<Recipe>>(&store.path) {
+            Ok(Some(recipes)) => {
+                for recipe in recipes {
+                    store.recipes.insert(recipe.name.clone(), recipe);
+                }
+                tracing::info!("Loaded {} recipes", store.recipes.len());
+            }
+            Ok(None) => {}
+            Err(e) => tracing::error!("Failed to load recipes: {:?}", e),
+        }
+        store
+    }
+
+    pub fn list(&self) -> Vec<Recipe> {
+        self.recipes.values().cloned().collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Recipe> {
+        self.recipes.get(name)
+    }
+
+    pub fn last_applied(&self) -> Option<String> {
+        self.last_applied.clone()
+    }
+
+    pub fn set_last_applied(&mut self, name: &str) {
+        self.last_applied = Some(name.to_string());
+    }
+
+    /// Inserts or replaces a recipe and writes the store to disk
+    pub fn save(&mut self, recipe: Recipe) -> Result<(), anyhow::Error> {
+        recipe.validate()?;
+        self.recipes.insert(recipe.name.clone(), recipe);
+        self.persist()
+    }
+
+    /// Removes a recipe and writes the store to disk
+    pub fn delete(&mut self, name: &str) -> Result<(), anyhow::Error> {
+        if self.recipes.remove(name).is_none() {
+            return Err(anyhow::anyhow!("Recipe {} not found", name));
+        }
+        if self.last_applied.as_deref() == Some(name) {
+            self.last_applied = None;
+        }
+        self.persist()
+    }
+
+    fn persist(&self) -> Result<(), anyhow::Error> {
+        storage::write_json(&self.path, &self.list())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn laser_recipe(name: &str) -> Recipe {
+        Recipe {
+            name: name.to_string(),
+            description: String::new(),
+            laser: Some(LaserRecipe {
+                target_diameter: 1.75,
+                lower_tolerance: 0.05,
+                higher_tolerance: 0.05,
+            }),
+            winder: None,
+            extruder: None,
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("qitech-recipes-{}-{}", name, std::process::id()))
+            .join(RECIPES_FILE)
+    }
+
+    #[test]
+    fn test_recipe_without_sections_is_invalid() {
+        let mut recipe = laser_recipe("empty");
+        recipe.laser = None;
+        assert!(recipe.validate().is_err());
+    }
+
+    #[test]
+    fn test_recipe_sections_match_machines() {
+        let recipe = laser_recipe("pla");
+        let sections = recipe.sections().unwrap();
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].0, LaserMachine::MACHINE_IDENTIFICATION);
+    }
+
+    #[test]
+    fn test_store_roundtrip() {
+        let path = temp_path("roundtrip");
+        let mut store = RecipeStore::new(path.clone());
+        store.save(laser_recipe("pla")).unwrap();
+        store.save(laser_recipe("petg")).unwrap();
+        store.delete("pla").unwrap();
+
+        let loaded = RecipeStore::load(path.clone());
+        assert!(loaded.get("pla").is_none());
+        assert_eq!(loaded.get("petg"), Some(&laser_recipe("petg")));
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
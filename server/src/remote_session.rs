@@ -0,0 +1,287 @@
+//! Remote support sessions
+//!
+//! While a remote session is active, mutations that could endanger people at the line are
+//! restricted: blocked mutations are rejected and decrease-only ones may only lower their
+//! value, e.g. a supporter can slow the puller down but not speed it up. A session is active
+//! while the key switch input `remote_key_switch` of a winder is turned or while it was
+//! started at the local UI, so nobody can lift the restrictions from afar.
+
+use crate::{
+    config::RemoteSessionConfig,
+    machines::mutation_limits::{MutationRejection, mutation_value},
+};
+use control_core::{
+    helpers::clock::unix_millis,
+    machines::{
+        identification::MachineIdentificationUnique,
+        values::{MachineValueBus, REMOTE_KEY_SWITCH},
+    },
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// A key switch value older is from a disconnected winder and counts as not turned
+const KEY_SWITCH_MAX_AGE: Duration = Duration::from_secs(1);
+
+#[derive(Deserialize, Serialize, Debug)]
+pub enum Mutation {
+    /// Only accepted from the local UI, see [`crate::rest::handlers::remote_session`]
+    StartRemoteSession,
+    EndRemoteSession,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct RemoteSessionState {
+    pub active: bool,
+    /// unix timestamp in milliseconds the local UI started the session
+    pub started_locally_at: Option<u64>,
+    pub key_switch: bool,
+}
+
+/// Remote session state and the last value of every decrease-only mutation
+#[derive(Debug, Default)]
+pub struct RemoteSession {
+    started_locally_at: Option<u64>,
+    /// values the machines got last, the bound of decreases during a session
+    last_values: HashMap<(MachineIdentificationUnique, String), f64>,
+}
+
+impl RemoteSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the key switch is turned
+    pub fn key_switch(values: &MachineValueBus, now: Instant) -> bool {
+        values
+            .latest(REMOTE_KEY_SWITCH)
+            .is_some_and(|sample| !sample.is_stale(now, KEY_SWITCH_MAX_AGE) && sample.value)
+    }
+
+    /// Starts or ends the session of the local UI, the key switch keeps it active
+    pub fn set_local(&mut self, active: bool) {
+        match (active, self.started_locally_at) {
+            (true, None) => {
+                tracing::info!("Remote session started at the local UI");
+                self.started_locally_at = Some(unix_millis());
+            }
+            (false, Some(_)) => {
+                tracing::info!("Remote session ended at the local UI");
+                self.started_locally_at = None;
+            }
+            _ => {}
+        }
+    }
+
+    pub const fn state(&self, key_switch: bool) -> RemoteSessionState {
+        RemoteSessionState {
+            active: key_switch || self.started_locally_at.is_some(),
+            started_locally_at: self.started_locally_at,
+            key_switch,
+        }
+    }
+
+    /// Checks a mutation against the restrictions, passes everything without a session
+    ///
+    /// `current` is the mutation reproducing the current state of the machine, see
+    /// [`control_core::machines::api::MachineApi::api_current_mutation`].
+    pub fn check(
+        &self,
+        machine: &MachineIdentificationUnique,
+        mutation: &Value,
+        name: &str,
+        current: Option<&Value>,
+        key_switch: bool,
+        config: &RemoteSessionConfig,
+    ) -> Result<(), MutationRejection> {
+        if !self.state(key_switch).active {
+            return Ok(());
+        }
+        if config.blocked.iter().any(|blocked| blocked == name) {
+            return Err(MutationRejection::RemoteSession {
+                reason: format!("{} is blocked", name),
+            });
+        }
+        let Some(decrease_only) = config.decrease_only.get(name) else {
+            return Ok(());
+        };
+        let field = decrease_only.field.as_deref();
+        let unit = decrease_only.unit.as_deref();
+        let value = mutation_value(mutation, field, unit);
+        // the machine knows its setting even after a recipe or an import changed it, the
+        // values applied through the API are left for machines that don't report it
+        let bound = current
+            .and_then(|current| mutation_value(current, field, unit))
+            .or_else(|| {
+                self.last_values
+                    .get(&(machine.clone(), name.to_string()))
+                    .copied()
+            });
+        match (value, bound) {
+            (Some(value), Some(bound)) if value <= bound => Ok(()),
+            (Some(_), Some(bound)) => Err(MutationRejection::RemoteSession {
+                reason: format!("{} may not exceed {}", name, bound),
+            }),
+            // stopping is a decrease from any value
+            (Some(value), None) if value <= 0.0 => Ok(()),
+            // without a known value any other decrease can't be told from an increase
+            _ => Err(MutationRejection::RemoteSession {
+                reason: format!("{} has no known value to decrease", name),
+            }),
+        }
+    }
+
+    /// Recipes set speeds and heater targets at once, they wait until the session ended
+    pub fn check_recipe(&self, key_switch: bool) -> Result<(), MutationRejection> {
+        if self.state(key_switch).active {
+            return Err(MutationRejection::RemoteSession {
+                reason: "recipes can't be applied during a remote session".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Remembers the value of an applied decrease-only mutation
+    pub fn record(
+        &mut self,
+        machine: &MachineIdentificationUnique,
+        mutation: &Value,
+        name: &str,
+        config: &RemoteSessionConfig,
+    ) {
+        let Some(decrease_only) = config.decrease_only.get(name) else {
+            return;
+        };
+        if let Some(value) = mutation_value(
+            mutation,
+            decrease_only.field.as_deref(),
+            decrease_only.unit.as_deref(),
+        ) {
+            self.last_values
+                .insert((machine.clone(), name.to_string()), value);
+        }
+    }
+
+    /// Drops the recorded values of a machine whose settings changed outside of mutations,
+    /// e.g. by a recipe or a configuration import
+    pub fn forget(&mut self, machine: &MachineIdentificationUnique) {
+        self.last_values
+            .retain(|(recorded, _), _| recorded != machine);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use control_core::machines::identification::MachineIdentification;
+    use serde_json::json;
+
+    #[test]
+    fn test_remote_session() {
+        let winder = MachineIdentificationUnique {
+            machine_identification: MachineIdentification {
+                vendor: 1,
+                machine: 2,
+            },
+            serial: 1,
+        };
+        let config = RemoteSessionConfig::default();
+        let mut session = RemoteSession::new();
+        let speed = |value: f64| json!({ "SetPullerTargetSpeed": value });
+        let check = |session: &RemoteSession, mutation: &Value, key_switch: bool| {
+            let name = crate::auth::mutation_name(mutation).unwrap();
+            session.check(&winder, mutation, name, None, key_switch, &config)
+        };
+
+        // everything passes without a session
+        assert!(check(&session, &speed(50.0), false).is_ok());
+        assert!(
+            check(
+                &session,
+                &json!({ "SetNozzleHeatingTemperature": 250.0 }),
+                false
+            )
+            .is_ok()
+        );
+        session.record(&winder, &speed(10.0), "SetPullerTargetSpeed", &config);
+
+        // the key switch starts a session
+        assert!(check(&session, &speed(5.0), true).is_ok());
+        assert!(check(&session, &speed(12.0), true).is_err());
+        // 10 m/min are 166.7 mm/s
+        assert!(
+            check(
+                &session,
+                &json!({ "SetPullerTargetSpeed": { "value": 200.0, "unit": "mm/s" } }),
+                true
+            )
+            .is_err()
+        );
+        assert!(
+            check(
+                &session,
+                &json!({ "SetNozzleHeatingTemperature": 200.0 }),
+                true
+            )
+            .is_err()
+        );
+        assert!(check(&session, &json!({ "SetPullerManualSpeed": 1.0 }), true).is_err());
+        assert!(check(&session, &json!({ "SetPullerForward": true }), true).is_ok());
+
+        assert!(session.check_recipe(false).is_ok());
+        assert!(session.check_recipe(true).is_err());
+
+        session.set_local(true);
+        assert!(session.state(false).active);
+        assert!(check(&session, &speed(12.0), false).is_err());
+        assert!(session.check_recipe(false).is_err());
+        session.set_local(false);
+        assert!(check(&session, &speed(12.0), false).is_ok());
+    }
+
+    #[test]
+    fn test_decrease_only_bound() {
+        let winder = MachineIdentificationUnique {
+            machine_identification: MachineIdentification {
+                vendor: 1,
+                machine: 2,
+            },
+            serial: 1,
+        };
+        let config = RemoteSessionConfig::default();
+        let mut session = RemoteSession::new();
+        session.set_local(true);
+        let speed = |value: f64| json!({ "SetPullerTargetSpeed": value });
+        let check = |session: &RemoteSession, mutation: &Value, current: Option<&Value>| {
+            session.check(
+                &winder,
+                mutation,
+                "SetPullerTargetSpeed",
+                current,
+                false,
+                &config,
+            )
+        };
+
+        // nothing known, only stopping passes
+        assert!(check(&session, &speed(0.0), None).is_ok());
+        assert!(check(&session, &speed(5.0), None).is_err());
+
+        // the current state of the machine bounds the decrease
+        let current = json!({ "SetPullerTargetSpeed": { "value": 8.0, "unit": "m/min" } });
+        assert!(check(&session, &speed(8.0), Some(&current)).is_ok());
+        assert!(check(&session, &speed(9.0), Some(&current)).is_err());
+
+        // and wins over a value a recipe changed since it was recorded
+        session.record(&winder, &speed(10.0), "SetPullerTargetSpeed", &config);
+        assert!(check(&session, &speed(9.0), None).is_ok());
+        assert!(check(&session, &speed(9.0), Some(&current)).is_err());
+
+        session.forget(&winder);
+        assert!(check(&session, &speed(5.0), None).is_err());
+    }
+}
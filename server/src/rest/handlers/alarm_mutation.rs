@@ -0,0 +1,81 @@
+use super::auth::authorize_mutation;
+use crate::{
+    alarms::{
+        api::{AlarmsEvent, Mutation, emit_alarms},
+        history::persist_alarm_changes,
+    },
+    app_state::AppState,
+    auth::Role,
+    history::AlarmQuery,
+    rest::util::{ResponseUtil, ResponseUtilError},
+};
+use axum::{
+    Json,
+    body::Body,
+    extract::{Query, State},
+    http::{HeaderMap, Response},
+};
+use control_core::{helpers::clock::unix_millis, rest::mutation::MutationResponse};
+use std::sync::Arc;
+
+#[axum::debug_handler]
+pub async fn post_alarm_mutate(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<Mutation>,
+) -> Response<Body> {
+    let detail = serde_json::to_value(&body).unwrap_or_default();
+    let principal = match authorize_mutation(
+        &app_state,
+        &headers,
+        Role::Operator,
+        "alarms/mutate",
+        &detail,
+    )
+    .await
+    {
+        Ok(principal) => principal,
+        Err(e) => return e.into(),
+    };
+
+    tracing::info!("Mutating alarms data={:?}", body);
+    let now = unix_millis();
+    let result = {
+        let mut alarms = app_state.alarms.write().await;
+        match body {
+            Mutation::Acknowledge(id) => alarms.acknowledge(id, &principal.user, now),
+            Mutation::AcknowledgeAll => {
+                alarms.acknowledge_all(&principal.user, now);
+                Ok(())
+            }
+        }
+    };
+    persist_alarm_changes(&app_state).await;
+    emit_alarms(&app_state).await;
+    match result {
+        Ok(_) => ResponseUtil::ok(MutationResponse::success()),
+        Err(e) => ResponseUtilError::NotFound(e).into(),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn get_alarms(State(app_state): State<Arc<AppState>>) -> Response<Body> {
+    let alarms = app_state.alarms.read().await;
+    ResponseUtil::ok(AlarmsEvent {
+        active: alarms.active(),
+        cleared: alarms.cleared(),
+    })
+}
+
+/// Alarms of the past, e.g. to review a shift
+#[axum::debug_handler]
+pub async fn get_alarm_history(
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<AlarmQuery>,
+) -> Response<Body> {
+    let page = app_state.history.lock().await.query_alarms(&query);
+    match page {
+        Ok(page) => ResponseUtil::ok(page),
+        Err(e) => ResponseUtilError::Error(e).into(),
+    }
+}
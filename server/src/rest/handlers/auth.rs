@@ -0,0 +1,125 @@
+use crate::{
+    app_state::AppState,
+    auth::{Principal, Role, bearer_token},
+    rest::util::{ResponseUtil, ResponseUtilError},
+};
+use axum::{
+    Json,
+    body::Body,
+    extract::{Request, State},
+    http::{HeaderMap, Response},
+    middleware::Next,
+};
+use control_core::rest::mutation::{MachineMutationBody, MutationResponse};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::sync::Arc;
+
+#[derive(Deserialize, Debug)]
+pub struct LoginBody {
+    pub name: String,
+    pub password: String,
+}
+
+/// Starts a session, the returned token is sent as `Authorization: Bearer <token>`
+#[axum::debug_handler]
+pub async fn post_login(
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<LoginBody>,
+) -> Response<Body> {
+    let session = app_state
+        .auth
+        .write()
+        .await
+        .login(&body.name, &body.password);
+    match session {
+        Ok(session) => {
+            tracing::info!("User {} logged in as {:?}", session.user, session.role);
+            ResponseUtil::ok(session)
+        }
+        Err(e) => ResponseUtilError::from(e).into(),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn post_logout(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    if let Some(token) = bearer_token(&headers) {
+        app_state.auth.write().await.logout(token);
+    }
+    ResponseUtil::ok(MutationResponse::success())
+}
+
+/// User and role of the session
+#[axum::debug_handler]
+pub async fn get_session(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    match app_state
+        .auth
+        .read()
+        .await
+        .authorize(bearer_token(&headers), Role::Viewer)
+    {
+        Ok(principal) => ResponseUtil::ok(principal),
+        Err(e) => ResponseUtilError::from(e).into(),
+    }
+}
+
+/// Middleware rejecting requests without at least a viewer session
+pub async fn require_viewer(
+    State(app_state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response<Body> {
+    let authorized = app_state
+        .auth
+        .read()
+        .await
+        .authorize(bearer_token(request.headers()), Role::Viewer);
+    match authorized {
+        Ok(_) => next.run(request).await,
+        Err(e) => ResponseUtilError::from(e).into(),
+    }
+}
+
+/// Authorizes a mutation of the REST API and passes it to the audit hooks
+pub async fn authorize_mutation(
+    app_state: &Arc<AppState>,
+    headers: &HeaderMap,
+    required: Role,
+    action: &str,
+    detail: &Value,
+) -> Result<Principal, ResponseUtilError> {
+    app_state
+        .auth
+        .read()
+        .await
+        .authorize_mutation(bearer_token(headers), required, action, detail)
+        .map_err(ResponseUtilError::from)
+}
+
+/// [`authorize_mutation`] with the role required by the machine mutation
+///
+/// Takes the session token, since mutations also arrive over socket.io.
+pub async fn authorize_machine_mutation(
+    app_state: &Arc<AppState>,
+    token: Option<&str>,
+    body: &MachineMutationBody<Value>,
+) -> Result<Principal, ResponseUtilError> {
+    let auth = app_state.auth.read().await;
+    let detail = json!({
+        "machine": body.machine_identification_unique,
+        "mutation": body.data,
+    });
+    auth.authorize_mutation(
+        token,
+        auth.mutation_role(&body.data),
+        "machine/mutate",
+        &detail,
+    )
+    .map_err(ResponseUtilError::from)
+}
@@ -0,0 +1,131 @@
+use super::auth::authorize_mutation;
+use crate::{
+    app_state::AppState,
+    auth::Role,
+    batches::{
+        api::{BatchesNamespaceEvents, Mutation, RunEndedEvent, emit_run_state},
+        label::SpoolLabel,
+    },
+    mes::{
+        api::emit_orders,
+        apply::{apply_next_order, complete_order},
+    },
+    rest::util::{ResponseUtil, ResponseUtilError},
+};
+use axum::{
+    Json,
+    body::Body,
+    extract::{Path, State},
+    http::{HeaderMap, Response},
+};
+use control_core::{
+    rest::mutation::MutationResponse,
+    socketio::{event::BuildEvent, namespace::NamespaceCacheingLogic},
+};
+use serde::Serialize;
+use std::sync::Arc;
+
+#[axum::debug_handler]
+pub async fn post_batch_mutate(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<Mutation>,
+) -> Response<Body> {
+    let detail = serde_json::to_value(&body).unwrap_or_default();
+    let principal = match authorize_mutation(
+        &app_state,
+        &headers,
+        Role::Operator,
+        "batches/mutate",
+        &detail,
+    )
+    .await
+    {
+        Ok(principal) => principal,
+        Err(e) => return e.into(),
+    };
+    let result = _post_batch_mutate(&app_state, &principal.user, body).await;
+    emit_run_state(&app_state).await;
+    emit_orders(&app_state).await;
+    match result {
+        Ok(_) => ResponseUtil::ok(MutationResponse::success()),
+        Err(e) => ResponseUtilError::Error(e).into(),
+    }
+}
+
+async fn _post_batch_mutate(
+    app_state: &Arc<AppState>,
+    user: &str,
+    mutation: Mutation,
+) -> Result<(), anyhow::Error> {
+    tracing::info!("Mutating batches data={:?}", mutation);
+
+    match mutation {
+        Mutation::StartRun(metadata) => app_state.batches.write().await.start_run(metadata),
+        Mutation::RecordDefect => app_state.batches.write().await.record_defect(),
+        Mutation::Annotate(text) => {
+            let annotation = app_state.batches.write().await.annotate(user, &text)?;
+            app_state
+                .history
+                .lock()
+                .await
+                .insert_annotation(&annotation)
+        }
+        Mutation::ApplyNextOrder => apply_next_order(app_state, user).await,
+        Mutation::EndRun => {
+            let report = app_state.batches.write().await.end_run()?;
+            complete_order(app_state, &report).await;
+            let event = RunEndedEvent { report }.build();
+            app_state
+                .socketio_setup
+                .namespaces
+                .write()
+                .await
+                .batches_namespace
+                .emit(BatchesNamespaceEvents::RunEnded(event));
+            Ok(())
+        }
+    }
+}
+
+#[axum::debug_handler]
+pub async fn get_runs(State(app_state): State<Arc<AppState>>) -> Response<Body> {
+    let reports = app_state.batches.read().await.list_reports();
+    match reports {
+        Ok(reports) => ResponseUtil::ok(reports),
+        Err(e) => ResponseUtilError::Error(e).into(),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn get_run(
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<u64>,
+) -> Response<Body> {
+    let report = app_state.batches.read().await.get_report(id);
+    match report {
+        Ok(Some(report)) => ResponseUtil::ok(report),
+        Ok(None) => ResponseUtil::not_found(&format!("Run {} not found", id)),
+        Err(e) => ResponseUtilError::Error(e).into(),
+    }
+}
+
+#[derive(Serialize)]
+struct LabelResponse {
+    label: SpoolLabel,
+    /// the label as sent to a ZPL printer
+    zpl: String,
+}
+
+/// Label of the spool finished last, for printing from a client
+#[axum::debug_handler]
+pub async fn get_label(State(app_state): State<Arc<AppState>>) -> Response<Body> {
+    let label = app_state.batches.read().await.last_label().cloned();
+    match label {
+        Some(label) => ResponseUtil::ok(LabelResponse {
+            zpl: label.to_zpl(),
+            label,
+        }),
+        None => ResponseUtil::not_found("No spool was finished yet"),
+    }
+}
@@ -0,0 +1,32 @@
+use super::auth::authorize_mutation;
+use crate::{
+    app_state::AppState, auth::Role, config::reload::reload_config, rest::util::ResponseUtil,
+};
+use axum::{
+    body::Body,
+    extract::State,
+    http::{HeaderMap, Response},
+};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Reloads the config files, same as sending SIGHUP to the server
+#[axum::debug_handler]
+pub async fn post_config_reload(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    if let Err(e) = authorize_mutation(
+        &app_state,
+        &headers,
+        Role::Engineer,
+        "config/reload",
+        &Value::Null,
+    )
+    .await
+    {
+        return e.into();
+    }
+
+    ResponseUtil::ok(reload_config(&app_state).await)
+}
@@ -0,0 +1,10 @@
+use crate::{app_state::AppState, rest::util::ResponseUtil};
+use axum::{body::Body, extract::State, http::Response};
+use std::sync::Arc;
+
+/// Peers of the federation mode with their machines and proxy namespaces
+#[axum::debug_handler]
+pub async fn get_federation(State(app_state): State<Arc<AppState>>) -> Response<Body> {
+    let federation = app_state.federation.read().await;
+    ResponseUtil::ok(&*federation)
+}
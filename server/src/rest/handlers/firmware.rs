@@ -0,0 +1,71 @@
+use super::auth::authorize_mutation;
+use crate::{
+    app_state::AppState,
+    auth::Role,
+    rest::util::{ResponseUtil, ResponseUtilError},
+    serial::firmware::{
+        self,
+        api::{FirmwareUploadQuery, emit_firmware, firmware_event, updatable_ports},
+    },
+};
+use axum::{
+    body::{Body, Bytes},
+    extract::{Query, State},
+    http::{HeaderMap, Response},
+};
+use control_core::{rest::mutation::MutationResponse, serial::firmware::FirmwareImage};
+use serde_json::json;
+use std::sync::Arc;
+
+/// Schedules the request body as firmware image for a serial device
+#[axum::debug_handler]
+pub async fn post_firmware_upload(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<FirmwareUploadQuery>,
+    body: Bytes,
+) -> Response<Body> {
+    let detail = json!({
+        "path": query.path,
+        "version": query.version,
+        "size": body.len(),
+    });
+    if let Err(e) = authorize_mutation(
+        &app_state,
+        &headers,
+        Role::Engineer,
+        "serial/firmware/upload",
+        &detail,
+    )
+    .await
+    {
+        return e.into();
+    }
+
+    let result = if body.is_empty() {
+        Err(ResponseUtilError::BadRequest(anyhow::anyhow!(
+            "Firmware image is empty"
+        )))
+    } else if !updatable_ports(&app_state).await.contains(&query.path) {
+        Err(ResponseUtilError::BadRequest(anyhow::anyhow!(
+            "No serial device with firmware update support at {}",
+            query.path
+        )))
+    } else {
+        let image = FirmwareImage {
+            version: query.version,
+            data: body.to_vec(),
+        };
+        firmware::schedule(&query.path, image).map_err(ResponseUtilError::Conflict)
+    };
+    emit_firmware(&app_state).await;
+    match result {
+        Ok(_) => ResponseUtil::ok(MutationResponse::success()),
+        Err(e) => e.into(),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn get_firmware(State(app_state): State<Arc<AppState>>) -> Response<Body> {
+    ResponseUtil::ok(firmware_event(&app_state).await)
+}
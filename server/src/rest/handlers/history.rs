@@ -0,0 +1,84 @@
+use crate::{
+    app_state::AppState,
+    history::{HistorySample, HistoryTier},
+    rest::util::{ResponseUtil, ResponseUtilError},
+};
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::Response,
+};
+use control_core::{
+    helpers::clock::unix_millis,
+    machines::identification::{MachineIdentification, MachineIdentificationUnique},
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Deserialize, Debug)]
+pub struct HistoryQuery {
+    /// name of the logged event, defaults to live values
+    pub event: Option<String>,
+    /// unix timestamp in milliseconds
+    pub from: u64,
+    /// unix timestamp in milliseconds, defaults to now
+    pub to: Option<u64>,
+    /// picked from the length of the range if not given
+    pub tier: Option<HistoryTier>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct HistoryResponse {
+    pub tier: HistoryTier,
+    pub samples: Vec<HistorySample>,
+}
+
+#[axum::debug_handler]
+pub async fn get_history(
+    State(app_state): State<Arc<AppState>>,
+    Path((vendor, machine, serial)): Path<(u16, u16, u16)>,
+    Query(query): Query<HistoryQuery>,
+) -> Response<Body> {
+    let machine = MachineIdentificationUnique {
+        machine_identification: MachineIdentification { vendor, machine },
+        serial,
+    };
+    let now = unix_millis();
+    let to = query.to.unwrap_or(now);
+    let tier = query
+        .tier
+        .unwrap_or_else(|| HistoryTier::for_range(query.from, to, now));
+    let event = query.event.as_deref().unwrap_or("LiveValuesEvent");
+
+    let samples = app_state
+        .history
+        .lock()
+        .await
+        .query(&machine, event, tier, query.from, to);
+    match samples {
+        Ok(samples) => ResponseUtil::ok(HistoryResponse { tier, samples }),
+        Err(e) => ResponseUtilError::Error(e).into(),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AnnotationsQuery {
+    /// unix timestamp in milliseconds
+    pub from: u64,
+    /// unix timestamp in milliseconds, defaults to now
+    pub to: Option<u64>,
+}
+
+/// Operator notes to show along the time series
+#[axum::debug_handler]
+pub async fn get_annotations(
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<AnnotationsQuery>,
+) -> Response<Body> {
+    let to = query.to.unwrap_or_else(unix_millis);
+    let annotations = app_state.history.lock().await.annotations(query.from, to);
+    match annotations {
+        Ok(annotations) => ResponseUtil::ok(annotations),
+        Err(e) => ResponseUtilError::Error(e).into(),
+    }
+}
@@ -0,0 +1,11 @@
+use crate::{
+    app_state::AppState, instrumentation::api::performance_event, rest::util::ResponseUtil,
+};
+use axum::{body::Body, extract::State, http::Response};
+use std::sync::Arc;
+
+/// Act duration and jitter histograms of all connected machines
+#[axum::debug_handler]
+pub async fn get_instrumentation(State(app_state): State<Arc<AppState>>) -> Response<Body> {
+    ResponseUtil::ok(performance_event(&app_state).await)
+}
@@ -0,0 +1,96 @@
+use super::auth::authorize_mutation;
+use crate::{
+    app_state::AppState,
+    auth::Role,
+    io_mapping::{
+        IO_MAPPINGS,
+        api::{Mutation, apply_io_mapping, io_mapping_states, signals_of},
+    },
+    machines::machine_default_io_mapping,
+    rest::util::{ResponseUtil, ResponseUtilError},
+};
+use axum::{
+    Json,
+    body::Body,
+    extract::State,
+    http::{HeaderMap, Response},
+};
+use control_core::rest::mutation::MutationResponse;
+use std::sync::Arc;
+
+#[axum::debug_handler]
+pub async fn post_io_mapping_mutate(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<Mutation>,
+) -> Response<Body> {
+    // rewiring changes what the machine reacts to, so it is an engineering task
+    let detail = serde_json::to_value(&body).unwrap_or_default();
+    if let Err(e) = authorize_mutation(
+        &app_state,
+        &headers,
+        Role::Engineer,
+        "io-mapping/mutate",
+        &detail,
+    )
+    .await
+    {
+        return e.into();
+    }
+    match _post_io_mapping_mutate(&app_state, body).await {
+        Ok(_) => ResponseUtil::ok(MutationResponse::success()),
+        Err(e) => e.into(),
+    }
+}
+
+async fn _post_io_mapping_mutate(
+    app_state: &Arc<AppState>,
+    mutation: Mutation,
+) -> Result<(), ResponseUtilError> {
+    tracing::info!("Mutating I/O mapping data={:?}", mutation);
+
+    match mutation {
+        Mutation::SetIoMapping {
+            machine_identification_unique,
+            mapping,
+        } => {
+            let signals = signals_of(&machine_identification_unique)
+                .map_err(ResponseUtilError::BadRequest)?;
+            mapping
+                .validate(&signals)
+                .map_err(ResponseUtilError::BadRequest)?;
+
+            // a running machine checks the channels against its terminals before anything is saved
+            apply_io_mapping(app_state, &machine_identification_unique, mapping.clone())
+                .await
+                .map_err(ResponseUtilError::BadRequest)?;
+            IO_MAPPINGS
+                .write()
+                .unwrap_or_else(|e| e.into_inner())
+                .save(machine_identification_unique, mapping)
+                .map_err(ResponseUtilError::Error)
+        }
+        Mutation::ResetIoMapping {
+            machine_identification_unique,
+        } => {
+            IO_MAPPINGS
+                .write()
+                .unwrap_or_else(|e| e.into_inner())
+                .reset(&machine_identification_unique)
+                .map_err(ResponseUtilError::NotFound)?;
+
+            let mapping = machine_default_io_mapping(
+                machine_identification_unique.machine_identification.machine,
+            );
+            apply_io_mapping(app_state, &machine_identification_unique, mapping)
+                .await
+                .map_err(ResponseUtilError::Error)
+        }
+    }
+}
+
+/// I/O mappings and signal values of all machines with configurable I/O
+#[axum::debug_handler]
+pub async fn get_io_mapping(State(app_state): State<Arc<AppState>>) -> Response<Body> {
+    ResponseUtil::ok(io_mapping_states(&app_state).await)
+}
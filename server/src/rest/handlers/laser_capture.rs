@@ -0,0 +1,108 @@
+use super::auth::authorize_mutation;
+use crate::{
+    app_state::AppState,
+    auth::Role,
+    rest::util::{ResponseUtil, ResponseUtilError},
+    serial::{
+        devices::laser::{
+            Laser,
+            capture::{self, Mutation},
+        },
+        registry::SERIAL_DEVICE_REGISTRY,
+    },
+};
+use axum::{
+    Json,
+    body::Body,
+    extract::{Query, State},
+    http::{HeaderMap, Response},
+};
+use control_core::rest::mutation::MutationResponse;
+use serde::Deserialize;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+#[derive(Deserialize, Debug)]
+pub struct LaserCaptureQuery {
+    /// Path of the gauge port, e.g. `/dev/ttyUSB0`
+    pub path: String,
+}
+
+#[axum::debug_handler]
+pub async fn post_laser_capture_mutate(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<Mutation>,
+) -> Response<Body> {
+    let detail = serde_json::to_value(&body).unwrap_or_default();
+    if let Err(e) = authorize_mutation(
+        &app_state,
+        &headers,
+        Role::Engineer,
+        "serial/laser-capture/mutate",
+        &detail,
+    )
+    .await
+    {
+        return e.into();
+    }
+
+    tracing::info!("Mutating laser capture data={:?}", body);
+    let result = match body {
+        Mutation::Start { path, duration_ms } => {
+            if is_laser(&app_state, &path).await {
+                capture::start(&path, Duration::from_millis(duration_ms), Instant::now())
+                    .map_err(ResponseUtilError::Conflict)
+            } else {
+                Err(ResponseUtilError::BadRequest(anyhow::anyhow!(
+                    "No laser at {}",
+                    path
+                )))
+            }
+        }
+        Mutation::Clear { path } => {
+            if capture::clear(&path) {
+                Ok(())
+            } else {
+                Err(ResponseUtilError::NotFound(anyhow::anyhow!(
+                    "Laser {} has no capture",
+                    path
+                )))
+            }
+        }
+    };
+    match result {
+        Ok(_) => ResponseUtil::ok(MutationResponse::success()),
+        Err(e) => e.into(),
+    }
+}
+
+/// Latest capture of a gauge, `complete` is false while it records
+#[axum::debug_handler]
+pub async fn get_laser_capture(Query(query): Query<LaserCaptureQuery>) -> Response<Body> {
+    match capture::get(&query.path, Instant::now()) {
+        Some(capture) => ResponseUtil::ok(capture),
+        None => ResponseUtil::not_found(&format!("Laser {} has no capture", query.path)),
+    }
+}
+
+/// Whether a gauge driven by the laser device thread is connected at `path`
+async fn is_laser(app_state: &Arc<AppState>, path: &str) -> bool {
+    let device = app_state
+        .serial_setup
+        .read()
+        .await
+        .serial_detection
+        .ports
+        .get(path)
+        .map(|(_, _, device)| device.clone());
+    match device {
+        Some(device) => SERIAL_DEVICE_REGISTRY
+            .downcast_arc_rwlock::<Laser>(device)
+            .await
+            .is_ok(),
+        None => false,
+    }
+}
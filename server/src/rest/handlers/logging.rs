@@ -0,0 +1,59 @@
+use super::auth::authorize_mutation;
+use crate::{
+    app_state::AppState,
+    auth::Role,
+    logging::{log_filter, set_log_filter},
+    rest::util::ResponseUtil,
+};
+use axum::{
+    Json,
+    body::Body,
+    extract::State,
+    http::{HeaderMap, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LogFilter {
+    /// Filter directives like `info,server::machines::winder2=debug`
+    ///
+    /// `None` in a request resets the filter to the configured one.
+    pub filter: Option<String>,
+}
+
+/// Log filter currently in effect
+#[axum::debug_handler]
+pub async fn get_log_filter() -> Response<Body> {
+    ResponseUtil::ok(LogFilter {
+        filter: log_filter(),
+    })
+}
+
+/// Changes the log filter until the next restart
+#[axum::debug_handler]
+pub async fn post_log_filter(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<LogFilter>,
+) -> Response<Body> {
+    let detail = serde_json::to_value(&body).unwrap_or_default();
+    if let Err(e) = authorize_mutation(
+        &app_state,
+        &headers,
+        Role::Engineer,
+        "logging/filter",
+        &detail,
+    )
+    .await
+    {
+        return e.into();
+    }
+
+    match set_log_filter(body.filter.as_deref()) {
+        Ok(filter) => ResponseUtil::ok(LogFilter {
+            filter: Some(filter),
+        }),
+        Err(e) => ResponseUtil::bad_request(&e.to_string()),
+    }
+}
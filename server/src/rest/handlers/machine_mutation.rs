@@ -1,60 +1,181 @@
+use super::auth::authorize_machine_mutation;
 use crate::{
     app_state::AppState,
+    auth::{bearer_token, mutation_name},
+    config::config,
+    machines::mutation_limits::{MutationRejectedEvent, MutationRejection, check_limits},
+    remote_session::RemoteSession,
     rest::util::{ResponseUtil, ResponseUtilError},
 };
-use axum::{Json, body::Body, extract::State, http::Response};
+use axum::{
+    Json,
+    body::Body,
+    extract::State,
+    http::{HeaderMap, Response},
+};
 use control_core::{
-    machines::connection::MachineConnection,
-    rest::mutation::{MachineMutationBody, MutationResponse},
+    machines::{
+        Machine, connection::MachineConnection, identification::MachineIdentificationUnique,
+    },
+    rest::mutation::{MachineMutationBody, MutationError, MutationErrorKind, MutationResponse},
+    socketio::{
+        event::{BuildEvent, GenericEvent},
+        namespace_id::NamespaceId,
+        recorder::record_mutation,
+    },
 };
 use serde_json::Value;
-use std::sync::Arc;
+use smol::lock::Mutex;
+use std::{sync::Arc, time::Instant};
 
 #[axum::debug_handler]
 pub async fn post_machine_mutate(
     State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Json(body): Json<MachineMutationBody<Value>>,
 ) -> Response<Body> {
-    let result = _post_machine_mutate(State(app_state), Json(body)).await;
+    if let Err(e) = authorize_machine_mutation(&app_state, bearer_token(&headers), &body).await {
+        return e.into();
+    }
+    let result = mutate_machine(&app_state, body).await;
     match result {
         Ok(_) => ResponseUtil::ok(MutationResponse::success()),
-        Err(e) => ResponseUtilError::Error(e).into(),
+        Err(e) => ResponseUtilError::from(e).into(),
     }
 }
 
-async fn _post_machine_mutate(
-    State(app_state): State<Arc<AppState>>,
-    Json(body): Json<MachineMutationBody<Value>>,
-) -> Result<(), anyhow::Error> {
-    // lock machines
-    let machines_guard = app_state.machines.read().await;
-
-    // find machine with given identification in hashmap
-    let slot = machines_guard
-        .get(&body.machine_identification_unique)
-        .ok_or(anyhow::anyhow!(
-            "[{}::_post_machine_mutate] Machine not found {:?}",
-            module_path!(),
-            body.machine_identification_unique,
-        ))?;
-
-    // check machine for valid connection
-    let connection = &slot.lock_blocking().machine_connection;
-    let machine = match connection {
-        MachineConnection::Connected(m) => m,
-        MachineConnection::Error(error) => {
-            return Err(anyhow::anyhow!(
-                "[{}::_post_machine_mutate] Machine has error: {}",
+/// Reason a mutation was not applied
+#[derive(Debug)]
+pub enum MutateMachineError {
+    NotFound(MachineIdentificationUnique),
+    /// Machine is disconnected or has an error
+    Unavailable(String),
+    /// Machine rejected the mutation, e.g. unknown mutation or invalid value
+    Rejected(anyhow::Error),
+    /// Value outside of the configured limits or sent too often
+    Limited {
+        mutation: String,
+        rejection: MutationRejection,
+    },
+}
+
+impl std::fmt::Display for MutateMachineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound(machine) => write!(
+                f,
+                "[{}::mutate_machine] Machine not found {:?}",
+                module_path!(),
+                machine
+            ),
+            Self::Unavailable(reason) => {
+                write!(f, "[{}::mutate_machine] {}", module_path!(), reason)
+            }
+            Self::Rejected(e) => write!(
+                f,
+                "[{}::mutate_machine] Machine api_mutate error: {}",
+                module_path!(),
+                e
+            ),
+            Self::Limited {
+                mutation,
+                rejection,
+            } => write!(
+                f,
+                "[{}::mutate_machine] Rejected {}: {}",
                 module_path!(),
-                error
-            ));
+                mutation,
+                rejection
+            ),
         }
-        MachineConnection::Disconnected => {
-            return Err(anyhow::anyhow!(
-                "[{}::_post_machine_mutate] Machine is disconnected",
-                module_path!()
-            ));
+    }
+}
+
+impl std::error::Error for MutateMachineError {}
+
+impl MutateMachineError {
+    pub fn kind(&self) -> MutationErrorKind {
+        match self {
+            Self::NotFound(_) => MutationErrorKind::NotFound,
+            Self::Unavailable(_) => MutationErrorKind::NotReady,
+            Self::Rejected(e) => MutationError::kind_of(e),
+            Self::Limited {
+                rejection: MutationRejection::OutOfRange { .. },
+                ..
+            } => MutationErrorKind::Validation,
+            Self::Limited {
+                rejection: MutationRejection::RateLimited { .. },
+                ..
+            } => MutationErrorKind::RateLimited,
+            Self::Limited {
+                rejection: MutationRejection::RemoteSession { .. },
+                ..
+            } => MutationErrorKind::Forbidden,
         }
+    }
+}
+
+impl From<MutateMachineError> for ResponseUtilError {
+    fn from(error: MutateMachineError) -> Self {
+        match error.kind() {
+            MutationErrorKind::Validation => Self::BadRequest(error.into()),
+            MutationErrorKind::Interlock => Self::Interlock(error.into()),
+            MutationErrorKind::NotReady => Self::Conflict(error.into()),
+            MutationErrorKind::NotFound => Self::NotFound(error.into()),
+            MutationErrorKind::Unauthorized => Self::Unauthorized(error.into()),
+            MutationErrorKind::Forbidden => Self::Forbidden(error.into()),
+            MutationErrorKind::RateLimited => Self::TooManyRequests(error.into()),
+            MutationErrorKind::Internal => Self::Error(error.into()),
+        }
+    }
+}
+
+/// Applies a mutation to a connected machine
+///
+/// Shared by the REST API and the other integrations that accept machine mutations.
+pub async fn mutate_machine(
+    app_state: &Arc<AppState>,
+    body: MachineMutationBody<Value>,
+) -> Result<(), MutateMachineError> {
+    record_mutation(
+        &NamespaceId::Machine(body.machine_identification_unique.clone()),
+        &body.data,
+    );
+
+    // the registry and the slot are released before the mutation, the act loop and the
+    // motion thread lock them in every cycle
+    let (machine, key_switch) = {
+        let machines_guard = app_state.machines.read().await;
+
+        // find machine with given identification in hashmap
+        let slot = machines_guard
+            .get(&body.machine_identification_unique)
+            .ok_or_else(|| {
+                MutateMachineError::NotFound(body.machine_identification_unique.clone())
+            })?;
+
+        // check machine for valid connection
+        let machine = match &slot.lock().await.machine_connection {
+            MachineConnection::Connected(m) => m.clone(),
+            MachineConnection::Error(error) => {
+                return Err(MutateMachineError::Unavailable(format!(
+                    "Machine has error: {}",
+                    error
+                )));
+            }
+            MachineConnection::Disconnected => {
+                return Err(MutateMachineError::Unavailable(
+                    "Machine is disconnected".to_string(),
+                ));
+            }
+            MachineConnection::Degraded(_) => {
+                return Err(MutateMachineError::Unavailable(
+                    "Machine is degraded, waiting for its hardware to reconnect".to_string(),
+                ));
+            }
+        };
+        let key_switch = RemoteSession::key_switch(&machines_guard.values, Instant::now());
+        (machine, key_switch)
     };
 
     // log
@@ -69,14 +190,110 @@ async fn _post_machine_mutate(
     // lock machine
     let mut machine_guard = machine.lock().await;
 
-    // write data to machine
-    machine_guard.api_mutate(body.data).map_err(|e| {
-        anyhow::anyhow!(
-            "[{}::_post_machine_mutate] Machine api_mutate error: {}",
-            module_path!(),
-            e
-        )
-    })?;
+    // check the configured limits before the machine sees the mutation
+    let config = config();
+    if let Some(mutation) = mutation_name(&body.data) {
+        let now = Instant::now();
+        let mut checked = check_limits(&body.data, &config.mutations.limits);
+        if checked.is_ok() {
+            let current = machine_guard.api_current_mutation(mutation);
+            checked = app_state.remote_session.read().await.check(
+                &body.machine_identification_unique,
+                &body.data,
+                mutation,
+                current.as_ref(),
+                key_switch,
+                &config.remote_session,
+            );
+        }
+        if checked.is_ok() {
+            checked = app_state.mutation_rate_limiter.write().await.check(
+                &body.machine_identification_unique,
+                mutation,
+                config.mutations.max_rate_per_second,
+                now,
+            );
+        }
+        if let Err(rejection) = checked {
+            tracing::warn!(
+                "Rejected mutation machine={} mutation={} reason={}",
+                body.machine_identification_unique,
+                mutation,
+                rejection
+            );
+            let event = MutationRejectedEvent {
+                mutation: mutation.to_string(),
+                rejection: rejection.clone(),
+            }
+            .build();
+            machine_guard
+                .api_event_namespace()
+                .lock_blocking()
+                .emit_transient(Arc::new(GenericEvent::from(event)));
+            return Err(MutateMachineError::Limited {
+                mutation: mutation.to_string(),
+                rejection,
+            });
+        }
+    }
 
+    // applied decrease-only values bound the next ones during a remote session
+    let record = mutation_name(&body.data)
+        .filter(|mutation| config.remote_session.decrease_only.contains_key(*mutation))
+        .map(|_| {
+            (
+                body.machine_identification_unique.clone(),
+                body.data.clone(),
+            )
+        });
+
+    if machine_guard.has_dedicated_motion() {
+        drop(machine_guard);
+        mutate_on_motion_thread(app_state, machine, body.data).await?;
+    } else {
+        // write data to machine
+        machine_guard
+            .api_mutate(body.data)
+            .map_err(MutateMachineError::Rejected)?;
+    }
+
+    if let Some((machine, data)) = record {
+        if let Some(mutation) = mutation_name(&data) {
+            app_state.remote_session.write().await.record(
+                &machine,
+                &data,
+                mutation,
+                &config.remote_session,
+            );
+        }
+    }
     Ok(())
 }
+
+/// Hands the mutation to the motion thread, which applies it between two speed updates
+async fn mutate_on_motion_thread(
+    app_state: &Arc<AppState>,
+    machine: Arc<Mutex<dyn Machine>>,
+    data: Value,
+) -> Result<(), MutateMachineError> {
+    let (reply_tx, reply_rx) = smol::channel::bounded(1);
+    app_state
+        .motion
+        .queue(
+            machine,
+            Box::new(move |machine| {
+                let _ = reply_tx.try_send(machine.api_mutate(data));
+            }),
+        )
+        .map_err(|e| MutateMachineError::Unavailable(e.to_string()))?;
+
+    reply_rx
+        .recv()
+        .await
+        .map_err(|_| {
+            MutateMachineError::Unavailable(
+                "Machine disconnected before the mutation was applied".to_string(),
+            )
+        })?
+        .map_err(MutateMachineError::Rejected)
+}
@@ -0,0 +1,331 @@
+use crate::{
+    app_state::AppState,
+    auth::{Role, bearer_token},
+    rest::{
+        handlers::{
+            auth::{authorize_machine_mutation, authorize_mutation},
+            machine_mutation::mutate_machine,
+        },
+        util::{ResponseUtil, ResponseUtilError},
+    },
+};
+use axum::{
+    Json,
+    body::Body,
+    extract::{Path, State},
+    http::{HeaderMap, Response},
+};
+use control_core::{
+    helpers::clock::unix_millis,
+    machines::{
+        Machine,
+        identification::{MachineIdentification, MachineIdentificationUnique},
+        self_test::SelfTestReport,
+    },
+    rest::mutation::{MachineMutationBody, MutationResponse},
+    socketio::{event::GenericEvent, snapshot::Snapshot},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use smol::lock::Mutex;
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Interval the report of a running self test is checked in
+const SELF_TEST_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Longest a self test may take, the winder needs about 3 s plus its loopbacks
+const SELF_TEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Latest emitted value of a machine event
+#[derive(Serialize, Debug)]
+pub struct LatestEvent {
+    /// unix timestamp in milliseconds
+    pub ts: u64,
+    pub data: Value,
+}
+
+/// Configuration of a machine as exported, imported on a machine of the same type
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MachineConfigExport {
+    /// machine the configuration was exported from
+    pub machine_identification_unique: MachineIdentificationUnique,
+    /// unix timestamp in milliseconds
+    pub exported_at: u64,
+    /// machine specific settings
+    pub config: Value,
+}
+
+#[axum::debug_handler]
+pub async fn get_machines(State(app_state): State<Arc<AppState>>) -> Response<Body> {
+    ResponseUtil::ok(app_state.get_machine_objs())
+}
+
+/// Latest value of every event the machine emitted, keyed by event name
+#[axum::debug_handler]
+pub async fn get_machine_events(
+    State(app_state): State<Arc<AppState>>,
+    Path((vendor, machine, serial)): Path<(u16, u16, u16)>,
+) -> Response<Body> {
+    let machine = machine_identification_unique(vendor, machine, serial);
+    let snapshot = match machine_snapshot(&app_state, &machine).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => return e.into(),
+    };
+
+    let events: BTreeMap<_, _> = snapshot
+        .events
+        .iter()
+        .filter_map(|(name, event)| Some((name.clone(), latest_event(event)?)))
+        .collect();
+    ResponseUtil::ok(events)
+}
+
+#[axum::debug_handler]
+pub async fn get_machine_event(
+    State(app_state): State<Arc<AppState>>,
+    Path((vendor, machine, serial, event)): Path<(u16, u16, u16, String)>,
+) -> Response<Body> {
+    let machine = machine_identification_unique(vendor, machine, serial);
+    let snapshot = match machine_snapshot(&app_state, &machine).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => return e.into(),
+    };
+
+    match snapshot.get(&event).and_then(|event| latest_event(event)) {
+        Some(latest) => ResponseUtil::ok(latest),
+        None => ResponseUtil::not_found(&format!("Machine {} has not emitted {}", machine, event)),
+    }
+}
+
+/// Same as [`super::machine_mutation::post_machine_mutate`] with the machine in the path
+/// and the mutation as body, e.g. `{"SetTargetDiameter": 1.75}`
+#[axum::debug_handler]
+pub async fn post_machine_path_mutate(
+    State(app_state): State<Arc<AppState>>,
+    Path((vendor, machine, serial)): Path<(u16, u16, u16)>,
+    headers: HeaderMap,
+    Json(data): Json<Value>,
+) -> Response<Body> {
+    let body = MachineMutationBody {
+        machine_identification_unique: machine_identification_unique(vendor, machine, serial),
+        data,
+    };
+    if let Err(e) = authorize_machine_mutation(&app_state, bearer_token(&headers), &body).await {
+        return e.into();
+    }
+    match mutate_machine(&app_state, body).await {
+        Ok(_) => ResponseUtil::ok(MutationResponse::success()),
+        Err(e) => ResponseUtilError::from(e).into(),
+    }
+}
+
+/// Full configuration of a machine as a single document, e.g. to clone a line
+#[axum::debug_handler]
+pub async fn get_machine_config(
+    State(app_state): State<Arc<AppState>>,
+    Path((vendor, machine, serial)): Path<(u16, u16, u16)>,
+) -> Response<Body> {
+    let machine_identification_unique = machine_identification_unique(vendor, machine, serial);
+    let machine = match connected_machine(&app_state, &machine_identification_unique).await {
+        Ok(machine) => machine,
+        Err(e) => return e.into(),
+    };
+    let config = machine.lock().await.api_export_config();
+    match config {
+        Ok(config) => ResponseUtil::ok(MachineConfigExport {
+            machine_identification_unique,
+            exported_at: unix_millis(),
+            config,
+        }),
+        Err(e) => ResponseUtilError::BadRequest(e).into(),
+    }
+}
+
+/// Applies a configuration exported from a machine of the same type
+#[axum::debug_handler]
+pub async fn post_machine_config(
+    State(app_state): State<Arc<AppState>>,
+    Path((vendor, machine, serial)): Path<(u16, u16, u16)>,
+    headers: HeaderMap,
+    Json(body): Json<MachineConfigExport>,
+) -> Response<Body> {
+    let machine_identification_unique = machine_identification_unique(vendor, machine, serial);
+    // replaces limits and gains at once, so it is an engineering task
+    let detail = serde_json::to_value(&body).unwrap_or_default();
+    if let Err(e) = authorize_mutation(
+        &app_state,
+        &headers,
+        Role::Engineer,
+        "machine/config",
+        &detail,
+    )
+    .await
+    {
+        return e.into();
+    }
+    match import_machine_config(&app_state, &machine_identification_unique, body).await {
+        Ok(_) => ResponseUtil::ok(MutationResponse::success()),
+        Err(e) => e.into(),
+    }
+}
+
+async fn import_machine_config(
+    app_state: &Arc<AppState>,
+    machine_identification_unique: &MachineIdentificationUnique,
+    body: MachineConfigExport,
+) -> Result<(), ResponseUtilError> {
+    if body.machine_identification_unique.machine_identification
+        != machine_identification_unique.machine_identification
+    {
+        return Err(ResponseUtilError::BadRequest(anyhow::anyhow!(
+            "Configuration of {} can't be imported on {}, the machine types differ",
+            body.machine_identification_unique,
+            machine_identification_unique
+        )));
+    }
+    let machine = connected_machine(app_state, machine_identification_unique).await?;
+
+    tracing::info!(
+        "Importing configuration of {} on {}",
+        body.machine_identification_unique,
+        machine_identification_unique
+    );
+    let result = machine.lock().await.api_import_config(body.config);
+    app_state
+        .remote_session
+        .write()
+        .await
+        .forget(machine_identification_unique);
+    result.map_err(ResponseUtilError::BadRequest)
+}
+
+/// Report of the last self test of a machine
+#[axum::debug_handler]
+pub async fn get_machine_self_test(
+    State(app_state): State<Arc<AppState>>,
+    Path((vendor, machine, serial)): Path<(u16, u16, u16)>,
+) -> Response<Body> {
+    let machine_identification_unique = machine_identification_unique(vendor, machine, serial);
+    let machine = match connected_machine(&app_state, &machine_identification_unique).await {
+        Ok(machine) => machine,
+        Err(e) => return e.into(),
+    };
+    let report = machine.lock().await.api_self_test_report();
+    match report {
+        Some(report) => ResponseUtil::ok(report),
+        None => ResponseUtilError::NotFound(anyhow::anyhow!(
+            "Machine {} has no self test report",
+            machine_identification_unique
+        ))
+        .into(),
+    }
+}
+
+/// Runs the self test of a machine and responds with its report once it finished
+#[axum::debug_handler]
+pub async fn post_machine_self_test(
+    State(app_state): State<Arc<AppState>>,
+    Path((vendor, machine, serial)): Path<(u16, u16, u16)>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    let machine_identification_unique = machine_identification_unique(vendor, machine, serial);
+    // moves the motors and switches outputs, so it is a commissioning task
+    let detail = serde_json::to_value(&machine_identification_unique).unwrap_or_default();
+    if let Err(e) = authorize_mutation(
+        &app_state,
+        &headers,
+        Role::Engineer,
+        "machine/self-test",
+        &detail,
+    )
+    .await
+    {
+        return e.into();
+    }
+    match run_self_test(&app_state, &machine_identification_unique).await {
+        Ok(report) => ResponseUtil::ok(report),
+        Err(e) => e.into(),
+    }
+}
+
+async fn run_self_test(
+    app_state: &Arc<AppState>,
+    machine_identification_unique: &MachineIdentificationUnique,
+) -> Result<SelfTestReport, ResponseUtilError> {
+    let machine = connected_machine(app_state, machine_identification_unique).await?;
+    machine
+        .lock()
+        .await
+        .api_run_self_test()
+        .map_err(ResponseUtilError::Conflict)?;
+    tracing::info!("Running self test of {}", machine_identification_unique);
+
+    // the lock is released between the checks, the machine needs it to act
+    let start = Instant::now();
+    loop {
+        let report = machine.lock().await.api_self_test_report();
+        match report {
+            Some(report) if report.finished => return Ok(report),
+            _ if start.elapsed() > SELF_TEST_TIMEOUT => {
+                return Err(ResponseUtilError::Conflict(anyhow::anyhow!(
+                    "Self test of {} didn't finish in {} s",
+                    machine_identification_unique,
+                    SELF_TEST_TIMEOUT.as_secs()
+                )));
+            }
+            _ => smol::Timer::after(SELF_TEST_POLL_INTERVAL).await,
+        };
+    }
+}
+
+const fn machine_identification_unique(
+    vendor: u16,
+    machine: u16,
+    serial: u16,
+) -> MachineIdentificationUnique {
+    MachineIdentificationUnique {
+        machine_identification: MachineIdentification { vendor, machine },
+        serial,
+    }
+}
+
+/// Latest events of a connected machine, read without locking its namespace
+pub async fn machine_snapshot(
+    app_state: &Arc<AppState>,
+    machine: &MachineIdentificationUnique,
+) -> Result<Arc<Snapshot>, ResponseUtilError> {
+    let machines = app_state.machines.read().await;
+    let slot = machines.get(machine).ok_or_else(|| {
+        ResponseUtilError::NotFound(anyhow::anyhow!("Machine {} not found", machine))
+    })?;
+    let slot = slot.lock_blocking();
+    if !slot.is_connected() {
+        return Err(ResponseUtilError::Conflict(anyhow::anyhow!(
+            "Machine {} is not connected",
+            machine
+        )));
+    }
+    Ok(slot.snapshot.load())
+}
+
+async fn connected_machine(
+    app_state: &Arc<AppState>,
+    machine: &MachineIdentificationUnique,
+) -> Result<Arc<Mutex<dyn Machine>>, ResponseUtilError> {
+    let machines = app_state.machines.read().await;
+    let slot = machines.get(machine).ok_or_else(|| {
+        ResponseUtilError::NotFound(anyhow::anyhow!("Machine {} not found", machine))
+    })?;
+    let slot = slot.lock_blocking();
+    slot.machine_connection.to_machine().ok_or_else(|| {
+        ResponseUtilError::Conflict(anyhow::anyhow!("Machine {} is not connected", machine))
+    })
+}
+
+pub fn latest_event(event: &GenericEvent) -> Option<LatestEvent> {
+    let data = serde_json::to_value(&event.data).ok()?;
+    Some(LatestEvent { ts: event.ts, data })
+}
@@ -0,0 +1,51 @@
+use super::auth::authorize_mutation;
+use crate::{
+    app_state::AppState,
+    auth::Role,
+    mes::{
+        MesOrder,
+        api::{OrdersEvent, emit_orders},
+    },
+    rest::util::{ResponseUtil, ResponseUtilError},
+};
+use axum::{
+    Json,
+    body::Body,
+    extract::State,
+    http::{HeaderMap, Response},
+};
+use control_core::rest::mutation::MutationResponse;
+use std::sync::Arc;
+
+/// Queues an order pushed by the MES, the operator starts it with `ApplyNextOrder`
+#[axum::debug_handler]
+pub async fn post_mes_order(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(order): Json<MesOrder>,
+) -> Response<Body> {
+    let detail = serde_json::json!({ "order_id": order.order_id });
+    if let Err(e) =
+        authorize_mutation(&app_state, &headers, Role::Operator, "mes/orders", &detail).await
+    {
+        return e.into();
+    }
+    let result = app_state.mes_orders.write().await.enqueue(order);
+    if let Err(e) = result {
+        return ResponseUtilError::BadRequest(e).into();
+    }
+    emit_orders(&app_state).await;
+    ResponseUtil::ok(MutationResponse::success())
+}
+
+#[axum::debug_handler]
+pub async fn get_mes_orders(State(app_state): State<Arc<AppState>>) -> Response<Body> {
+    let orders = OrdersEvent::from(&*app_state.mes_orders.read().await);
+    ResponseUtil::ok(orders)
+}
+
+/// JSON schema orders are validated against
+#[axum::debug_handler]
+pub async fn get_mes_order_schema() -> Response<Body> {
+    ResponseUtil::ok(schemars::schema_for!(MesOrder))
+}
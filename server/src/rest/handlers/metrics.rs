@@ -0,0 +1,17 @@
+use crate::{app_state::AppState, exporters::prometheus::render_metrics};
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Response, StatusCode},
+};
+use std::sync::Arc;
+
+#[axum::debug_handler]
+pub async fn get_metrics(State(app_state): State<Arc<AppState>>) -> Response<Body> {
+    let metrics = render_metrics(&app_state).await;
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "text/plain; version=0.0.4")
+        .body(Body::from(metrics))
+        .unwrap()
+}
@@ -1,2 +1,29 @@
+pub mod alarm_mutation;
+pub mod auth;
+pub mod batch_mutation;
+pub mod config;
+pub mod federation;
+pub mod firmware;
+pub mod history;
+pub mod instrumentation;
+pub mod io_mapping;
+pub mod laser_capture;
+pub mod logging;
 pub mod machine_mutation;
+pub mod machines;
+pub mod mes;
+pub mod metrics;
+pub mod recipe_mutation;
+pub mod recording;
+pub mod remote_session;
+pub mod scheduler;
+pub mod schema;
+pub mod scope;
+pub mod sequence_mutation;
+pub mod serial_inventory;
+pub mod simulation;
+pub mod sniffer_mutation;
+pub mod spool_types;
+pub mod time;
+pub mod watchdog;
 pub mod write_machine_device_identification;
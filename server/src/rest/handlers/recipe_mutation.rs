@@ -0,0 +1,89 @@
+use super::auth::authorize_mutation;
+use crate::{
+    app_state::AppState,
+    auth::Role,
+    recipes::{
+        api::{Mutation, RecipeAppliedEvent, RecipesNamespaceEvents, emit_recipes},
+        apply::apply_recipe,
+    },
+    rest::util::{ResponseUtil, ResponseUtilError},
+};
+use axum::{
+    Json,
+    body::Body,
+    extract::State,
+    http::{HeaderMap, Response},
+};
+use control_core::{
+    rest::mutation::{MutationError, MutationErrorKind, MutationResponse},
+    socketio::{event::BuildEvent, namespace::NamespaceCacheingLogic},
+};
+use std::sync::Arc;
+
+#[axum::debug_handler]
+pub async fn post_recipe_mutate(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<Mutation>,
+) -> Response<Body> {
+    // applying is part of running the line, editing recipes is not
+    let required = match body {
+        Mutation::ApplyRecipe(_) => Role::Operator,
+        Mutation::SaveRecipe(_) | Mutation::DeleteRecipe(_) => Role::Engineer,
+    };
+    let detail = serde_json::to_value(&body).unwrap_or_default();
+    if let Err(e) =
+        authorize_mutation(&app_state, &headers, required, "recipes/mutate", &detail).await
+    {
+        return e.into();
+    }
+    let result = _post_recipe_mutate(&app_state, body).await;
+    emit_recipes(&app_state).await;
+    match result {
+        Ok(_) => ResponseUtil::ok(MutationResponse::success()),
+        Err(e) if MutationError::kind_of(&e) == MutationErrorKind::Forbidden => {
+            ResponseUtilError::Forbidden(e).into()
+        }
+        Err(e) => ResponseUtilError::Error(e).into(),
+    }
+}
+
+async fn _post_recipe_mutate(
+    app_state: &Arc<AppState>,
+    mutation: Mutation,
+) -> Result<(), anyhow::Error> {
+    tracing::info!("Mutating recipes data={:?}", mutation);
+
+    match mutation {
+        Mutation::SaveRecipe(recipe) => app_state.recipes.write().await.save(recipe),
+        Mutation::DeleteRecipe(name) => app_state.recipes.write().await.delete(&name),
+        Mutation::ApplyRecipe(name) => {
+            let recipe = app_state
+                .recipes
+                .read()
+                .await
+                .get(&name)
+                .cloned()
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "[{}::_post_recipe_mutate] Recipe {} not found",
+                        module_path!(),
+                        name
+                    )
+                })?;
+
+            let results = apply_recipe(app_state, &recipe).await?;
+            app_state.recipes.write().await.set_last_applied(&name);
+
+            let event = RecipeAppliedEvent { name, results }.build();
+            app_state
+                .socketio_setup
+                .namespaces
+                .write()
+                .await
+                .recipes_namespace
+                .emit(RecipesNamespaceEvents::RecipeApplied(event));
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use super::auth::authorize_mutation;
+use crate::{
+    app_state::AppState,
+    auth::Role,
+    rest::util::{ResponseUtil, ResponseUtilError},
+    socketio::recording::{self, Mutation},
+};
+use axum::{
+    Json,
+    body::Body,
+    extract::State,
+    http::{HeaderMap, Response},
+};
+use std::sync::Arc;
+
+#[axum::debug_handler]
+pub async fn post_recording_mutate(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<Mutation>,
+) -> Response<Body> {
+    let detail = serde_json::to_value(&body).unwrap_or_default();
+    if let Err(e) = authorize_mutation(
+        &app_state,
+        &headers,
+        Role::Engineer,
+        "recording/mutate",
+        &detail,
+    )
+    .await
+    {
+        return e.into();
+    }
+
+    tracing::info!("Mutating session recording data={:?}", body);
+    match body {
+        Mutation::Start => match recording::start() {
+            Ok(state) => ResponseUtil::ok(state),
+            Err(e) => ResponseUtilError::Conflict(e).into(),
+        },
+        Mutation::Stop => match recording::stop() {
+            true => ResponseUtil::ok(recording::state()),
+            false => ResponseUtilError::Conflict(anyhow::anyhow!("No session is recorded")).into(),
+        },
+    }
+}
+
+#[axum::debug_handler]
+pub async fn get_recording() -> Response<Body> {
+    ResponseUtil::ok(recording::state())
+}
@@ -0,0 +1,60 @@
+use super::auth::authorize_mutation;
+use crate::{
+    app_state::AppState,
+    auth::Role,
+    remote_session::{Mutation, RemoteSession},
+    rest::util::{ResponseUtil, ResponseUtilError},
+};
+use axum::{
+    Json,
+    body::Body,
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, Response},
+};
+use control_core::rest::mutation::MutationResponse;
+use std::{net::SocketAddr, sync::Arc, time::Instant};
+
+/// Whether a remote session restricts the mutations
+#[axum::debug_handler]
+pub async fn get_remote_session(State(app_state): State<Arc<AppState>>) -> Response<Body> {
+    let key_switch =
+        RemoteSession::key_switch(&app_state.machines.read().await.values, Instant::now());
+    ResponseUtil::ok(app_state.remote_session.read().await.state(key_switch))
+}
+
+/// Starts or ends a remote session, only the local UI on the line computer may do so
+#[axum::debug_handler]
+pub async fn post_remote_session_mutate(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(body): Json<Mutation>,
+) -> Response<Body> {
+    let detail = serde_json::to_value(&body).unwrap_or_default();
+    if let Err(e) = authorize_mutation(
+        &app_state,
+        &headers,
+        Role::Operator,
+        "remote-session/mutate",
+        &detail,
+    )
+    .await
+    {
+        return e.into();
+    }
+    // a remote supporter must not lift the restrictions it is working under
+    if !address.ip().is_loopback() {
+        return ResponseUtilError::Forbidden(anyhow::anyhow!(
+            "[{}::post_remote_session_mutate] Remote sessions are confirmed at the local UI only",
+            module_path!()
+        ))
+        .into();
+    }
+
+    app_state
+        .remote_session
+        .write()
+        .await
+        .set_local(matches!(body, Mutation::StartRemoteSession));
+    ResponseUtil::ok(MutationResponse::success())
+}
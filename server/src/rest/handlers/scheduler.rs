@@ -0,0 +1,10 @@
+use crate::{app_state::AppState, rest::util::ResponseUtil};
+use axum::{body::Body, extract::State, http::Response};
+use std::sync::Arc;
+
+/// Schedules, missed periods and deferred act calls of all connected machines
+#[axum::debug_handler]
+pub async fn get_scheduler(State(app_state): State<Arc<AppState>>) -> Response<Body> {
+    let scheduler = app_state.scheduler.read().await;
+    ResponseUtil::ok(scheduler.report())
+}
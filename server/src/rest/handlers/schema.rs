@@ -0,0 +1,31 @@
+use crate::{machines::registry::MACHINE_REGISTRY, rest::util::ResponseUtil};
+use axum::{body::Body, http::Response};
+use control_core::machines::{identification::MachineIdentification, schema::MachineApiSchema};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct MachineTypeSchema {
+    pub machine_identification: MachineIdentification,
+    #[serde(flatten)]
+    pub schema: MachineApiSchema,
+}
+
+/// JSON schemas of the mutations and events of all machine types the server supports
+#[axum::debug_handler]
+pub async fn get_api_schema() -> Response<Body> {
+    let mut schemas: Vec<MachineTypeSchema> = MACHINE_REGISTRY
+        .api_schemas()
+        .into_iter()
+        .map(|(machine_identification, schema)| MachineTypeSchema {
+            machine_identification,
+            schema,
+        })
+        .collect();
+    schemas.sort_by_key(|schema| {
+        (
+            schema.machine_identification.vendor,
+            schema.machine_identification.machine,
+        )
+    });
+    ResponseUtil::ok(schemas)
+}
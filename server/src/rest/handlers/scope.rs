@@ -0,0 +1,54 @@
+use super::auth::authorize_mutation;
+use crate::{
+    app_state::AppState,
+    auth::Role,
+    instrumentation::scope::{self, Mutation},
+    rest::util::{ResponseUtil, ResponseUtilError},
+};
+use axum::{
+    Json,
+    body::Body,
+    extract::State,
+    http::{HeaderMap, Response},
+};
+use std::sync::Arc;
+
+#[axum::debug_handler]
+pub async fn post_scope_mutate(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<Mutation>,
+) -> Response<Body> {
+    let detail = serde_json::to_value(&body).unwrap_or_default();
+    if let Err(e) = authorize_mutation(
+        &app_state,
+        &headers,
+        Role::Engineer,
+        "scope/mutate",
+        &detail,
+    )
+    .await
+    {
+        return e.into();
+    }
+
+    tracing::info!("Mutating scope data={:?}", body);
+    match body {
+        Mutation::Arm(names) => match scope::arm(names, app_state.clone()) {
+            Ok(()) => ResponseUtil::ok(scope::state()),
+            Err(e) => ResponseUtilError::BadRequest(e).into(),
+        },
+        Mutation::Disarm => match control_core::scope::disarm() {
+            true => ResponseUtil::ok(scope::state()),
+            false => {
+                ResponseUtilError::Conflict(anyhow::anyhow!("No scope channel is armed")).into()
+            }
+        },
+    }
+}
+
+/// Registered scope channels and which of them are armed
+#[axum::debug_handler]
+pub async fn get_scope() -> Response<Body> {
+    ResponseUtil::ok(scope::state())
+}
@@ -0,0 +1,57 @@
+use super::auth::authorize_mutation;
+use crate::{
+    app_state::AppState,
+    auth::Role,
+    rest::util::{ResponseUtil, ResponseUtilError},
+    sequences::{
+        api::{Mutation, emit_sequences},
+        run::start_sequence,
+    },
+};
+use axum::{
+    Json,
+    body::Body,
+    extract::State,
+    http::{HeaderMap, Response},
+};
+use control_core::rest::mutation::MutationResponse;
+use std::sync::Arc;
+
+#[axum::debug_handler]
+pub async fn post_sequence_mutate(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<Mutation>,
+) -> Response<Body> {
+    // running is part of running the line, editing sequences is not
+    let required = match body {
+        Mutation::RunSequence(_) | Mutation::AbortSequence => Role::Operator,
+        Mutation::SaveSequence(_) | Mutation::DeleteSequence(_) => Role::Engineer,
+    };
+    let detail = serde_json::to_value(&body).unwrap_or_default();
+    if let Err(e) =
+        authorize_mutation(&app_state, &headers, required, "sequences/mutate", &detail).await
+    {
+        return e.into();
+    }
+    let result = _post_sequence_mutate(&app_state, body).await;
+    emit_sequences(&app_state).await;
+    match result {
+        Ok(_) => ResponseUtil::ok(MutationResponse::success()),
+        Err(e) => ResponseUtilError::Error(e).into(),
+    }
+}
+
+async fn _post_sequence_mutate(
+    app_state: &Arc<AppState>,
+    mutation: Mutation,
+) -> Result<(), anyhow::Error> {
+    tracing::info!("Mutating sequences data={:?}", mutation);
+
+    match mutation {
+        Mutation::SaveSequence(sequence) => app_state.sequences.write().await.save(sequence),
+        Mutation::DeleteSequence(name) => app_state.sequences.write().await.delete(&name),
+        Mutation::RunSequence(name) => start_sequence(app_state, &name).await,
+        Mutation::AbortSequence => app_state.sequences.read().await.abort(),
+    }
+}
@@ -0,0 +1,16 @@
+use crate::{app_state::AppState, rest::util::ResponseUtil};
+use axum::{body::Body, extract::State, http::Response};
+use std::sync::Arc;
+
+/// Connected serial devices with their USB adapter, model, firmware version and serial number
+#[axum::debug_handler]
+pub async fn get_serial_inventory(State(app_state): State<Arc<AppState>>) -> Response<Body> {
+    let inventory = app_state
+        .serial_setup
+        .read()
+        .await
+        .serial_detection
+        .inventory()
+        .await;
+    ResponseUtil::ok(inventory)
+}
@@ -0,0 +1,56 @@
+use super::auth::authorize_mutation;
+use crate::{
+    app_state::AppState,
+    auth::Role,
+    rest::util::ResponseUtil,
+    simulation::{api::Mutation, set_sensor_overrides, simulation_state},
+};
+use axum::{
+    Json,
+    body::Body,
+    extract::State,
+    http::{HeaderMap, Response},
+};
+use control_core::rest::mutation::MutationResponse;
+use std::sync::Arc;
+
+const NOT_SIMULATING: &str = "The server is not running with --simulate";
+
+/// Drives and sensors of the simulated line
+#[axum::debug_handler]
+pub async fn get_simulation() -> Response<Body> {
+    match simulation_state() {
+        Some(state) => ResponseUtil::ok(state),
+        None => ResponseUtil::not_found(NOT_SIMULATING),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn post_simulation_mutate(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<Mutation>,
+) -> Response<Body> {
+    let detail = serde_json::to_value(&body).unwrap_or_default();
+    if let Err(e) = authorize_mutation(
+        &app_state,
+        &headers,
+        Role::Engineer,
+        "simulation/mutate",
+        &detail,
+    )
+    .await
+    {
+        return e.into();
+    }
+
+    tracing::info!("Mutating simulation data={:?}", body);
+    let applied = match body {
+        Mutation::SetOverrides(overrides) => set_sensor_overrides(overrides),
+    };
+    if applied {
+        ResponseUtil::ok(MutationResponse::success())
+    } else {
+        ResponseUtil::not_found(NOT_SIMULATING)
+    }
+}
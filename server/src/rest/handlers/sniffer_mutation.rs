@@ -0,0 +1,74 @@
+use super::auth::authorize_mutation;
+use crate::{
+    app_state::AppState,
+    auth::Role,
+    rest::util::{ResponseUtil, ResponseUtilError},
+    serial::sniffer::{
+        self,
+        api::{Mutation, emit_sniffer, sniffer_event},
+    },
+};
+use axum::{
+    Json,
+    body::Body,
+    extract::State,
+    http::{HeaderMap, Response},
+};
+use control_core::rest::mutation::MutationResponse;
+use std::sync::Arc;
+
+#[axum::debug_handler]
+pub async fn post_sniffer_mutate(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<Mutation>,
+) -> Response<Body> {
+    let detail = serde_json::to_value(&body).unwrap_or_default();
+    if let Err(e) = authorize_mutation(
+        &app_state,
+        &headers,
+        Role::Engineer,
+        "serial/sniffer/mutate",
+        &detail,
+    )
+    .await
+    {
+        return e.into();
+    }
+
+    tracing::info!("Mutating serial sniffer data={:?}", body);
+    let result = match body {
+        Mutation::Start { path, session } => {
+            let known = sniffer_event(&app_state).await.ports.contains(&path);
+            if known {
+                sniffer::start(&path, session);
+                Ok(())
+            } else {
+                Err(ResponseUtilError::BadRequest(anyhow::anyhow!(
+                    "No serial device at {}",
+                    path
+                )))
+            }
+        }
+        Mutation::Stop { path } => {
+            if sniffer::stop(&path) {
+                Ok(())
+            } else {
+                Err(ResponseUtilError::NotFound(anyhow::anyhow!(
+                    "Serial device {} is not sniffed",
+                    path
+                )))
+            }
+        }
+    };
+    emit_sniffer(&app_state).await;
+    match result {
+        Ok(_) => ResponseUtil::ok(MutationResponse::success()),
+        Err(e) => e.into(),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn get_sniffer(State(app_state): State<Arc<AppState>>) -> Response<Body> {
+    ResponseUtil::ok(sniffer_event(&app_state).await)
+}
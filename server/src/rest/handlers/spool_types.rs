@@ -0,0 +1,60 @@
+use super::auth::authorize_mutation;
+use crate::{
+    app_state::AppState,
+    auth::Role,
+    machines::winder2::spool::{SPOOL_TYPES, SpoolTypeMutation},
+    rest::util::{ResponseUtil, ResponseUtilError},
+};
+use axum::{
+    Json,
+    body::Body,
+    extract::State,
+    http::{HeaderMap, Response},
+};
+use control_core::rest::mutation::MutationResponse;
+use std::sync::Arc;
+
+#[axum::debug_handler]
+pub async fn post_spool_types_mutate(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<SpoolTypeMutation>,
+) -> Response<Body> {
+    let detail = serde_json::to_value(&body).unwrap_or_default();
+    if let Err(e) = authorize_mutation(
+        &app_state,
+        &headers,
+        Role::Engineer,
+        "spool-types/mutate",
+        &detail,
+    )
+    .await
+    {
+        return e.into();
+    }
+    match _post_spool_types_mutate(body) {
+        Ok(_) => ResponseUtil::ok(MutationResponse::success()),
+        Err(e) => e.into(),
+    }
+}
+
+fn _post_spool_types_mutate(mutation: SpoolTypeMutation) -> Result<(), ResponseUtilError> {
+    tracing::info!("Mutating spool types data={:?}", mutation);
+
+    let mut spool_types = SPOOL_TYPES.write().unwrap_or_else(|e| e.into_inner());
+    match mutation {
+        SpoolTypeMutation::SaveSpoolType(spool_type) => spool_types
+            .save(spool_type)
+            .map_err(ResponseUtilError::BadRequest),
+        SpoolTypeMutation::DeleteSpoolType(name) => spool_types
+            .remove(&name)
+            .map_err(ResponseUtilError::NotFound),
+    }
+}
+
+/// Stored spool types winders can select
+#[axum::debug_handler]
+pub async fn get_spool_types() -> Response<Body> {
+    let spool_types = SPOOL_TYPES.read().unwrap_or_else(|e| e.into_inner()).list();
+    ResponseUtil::ok(spool_types)
+}
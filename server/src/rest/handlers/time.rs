@@ -0,0 +1,33 @@
+use crate::rest::util::ResponseUtil;
+use axum::{body::Body, extract::Query, http::Response};
+use control_core::helpers::clock::{monotonic_epoch_millis, monotonic_millis, unix_millis};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Debug)]
+pub struct TimeSyncQuery {
+    /// Time of the client when it sent the request, in milliseconds
+    pub client_ts: Option<u64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TimeSync {
+    /// Echo of the request, so the client doesn't have to keep track of it
+    pub client_ts: Option<u64>,
+    /// Wall clock of the server in milliseconds, what event `ts` are taken from
+    pub ts: u64,
+    /// Milliseconds since the server started, what event `mono` are taken from
+    pub mono: u64,
+    /// Wall clock of the server in milliseconds when `mono` was zero
+    pub mono_epoch: u64,
+}
+
+/// Clocks of the server, clients estimate their offset to it from the round trip
+#[axum::debug_handler]
+pub async fn get_time(Query(query): Query<TimeSyncQuery>) -> Response<Body> {
+    ResponseUtil::ok(TimeSync {
+        client_ts: query.client_ts,
+        ts: unix_millis(),
+        mono: monotonic_millis(),
+        mono_epoch: monotonic_epoch_millis(),
+    })
+}
@@ -0,0 +1,10 @@
+use crate::{app_state::AppState, rest::util::ResponseUtil};
+use axum::{body::Body, extract::State, http::Response};
+use std::{sync::Arc, time::Instant};
+
+/// Act cycle statistics of all connected machines
+#[axum::debug_handler]
+pub async fn get_watchdog(State(app_state): State<Arc<AppState>>) -> Response<Body> {
+    let watchdog = app_state.watchdog.read().await;
+    ResponseUtil::ok(watchdog.report(Instant::now()))
+}
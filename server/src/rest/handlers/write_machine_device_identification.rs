@@ -1,5 +1,10 @@
-use crate::{app_state::AppState, rest::util::ResponseUtil};
-use axum::{Json, extract::State, http::Response};
+use super::auth::authorize_mutation;
+use crate::{app_state::AppState, auth::Role, rest::util::ResponseUtil};
+use axum::{
+    Json,
+    extract::State,
+    http::{HeaderMap, Response},
+};
 use control_core::{
     ethercat::eeprom_identification::write_machine_device_identification,
     machines::identification::{DeviceHardwareIdentificationEthercat, DeviceMachineIdentification},
@@ -7,7 +12,7 @@ use control_core::{
 };
 use std::sync::Arc;
 
-#[derive(serde::Deserialize, Debug)]
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
 pub struct Body {
     pub device_machine_identification: DeviceMachineIdentification,
     pub hardware_identification_ethercat: DeviceHardwareIdentificationEthercat,
@@ -16,8 +21,22 @@ pub struct Body {
 #[axum::debug_handler]
 pub async fn post_write_machine_device_identification(
     State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Json(body): Json<Body>,
 ) -> Response<axum::body::Body> {
+    let detail = serde_json::to_value(&body).unwrap_or_default();
+    if let Err(e) = authorize_mutation(
+        &app_state,
+        &headers,
+        Role::Engineer,
+        "write_machine_device_identification",
+        &detail,
+    )
+    .await
+    {
+        return e.into();
+    }
+
     let ethercat_setup_guard = app_state.ethercat_setup.read().await;
     let ethercat_setup = match ethercat_setup_guard.as_ref() {
         Some(setup) => setup,
@@ -1,11 +1,44 @@
+use super::handlers::alarm_mutation::{get_alarm_history, get_alarms, post_alarm_mutate};
+use super::handlers::auth::{get_session, post_login, post_logout, require_viewer};
+use super::handlers::batch_mutation::{get_label, get_run, get_runs, post_batch_mutate};
+use super::handlers::config::post_config_reload;
+use super::handlers::federation::get_federation;
+use super::handlers::firmware::{get_firmware, post_firmware_upload};
+use super::handlers::history::{get_annotations, get_history};
+use super::handlers::instrumentation::get_instrumentation;
+use super::handlers::io_mapping::{get_io_mapping, post_io_mapping_mutate};
+use super::handlers::laser_capture::{get_laser_capture, post_laser_capture_mutate};
+use super::handlers::logging::{get_log_filter, post_log_filter};
 use super::handlers::machine_mutation::post_machine_mutate;
+use super::handlers::machines::{
+    get_machine_config, get_machine_event, get_machine_events, get_machine_self_test, get_machines,
+    post_machine_config, post_machine_path_mutate, post_machine_self_test,
+};
+use super::handlers::mes::{get_mes_order_schema, get_mes_orders, post_mes_order};
+use super::handlers::metrics::get_metrics;
+use super::handlers::recipe_mutation::post_recipe_mutate;
+use super::handlers::recording::{get_recording, post_recording_mutate};
+use super::handlers::remote_session::{get_remote_session, post_remote_session_mutate};
+use super::handlers::scheduler::get_scheduler;
+use super::handlers::schema::get_api_schema;
+use super::handlers::scope::{get_scope, post_scope_mutate};
+use super::handlers::sequence_mutation::post_sequence_mutate;
+use super::handlers::serial_inventory::get_serial_inventory;
+use super::handlers::simulation::{get_simulation, post_simulation_mutate};
+use super::handlers::sniffer_mutation::{get_sniffer, post_sniffer_mutate};
+use super::handlers::spool_types::{get_spool_types, post_spool_types_mutate};
+use super::handlers::time::get_time;
+use super::handlers::watchdog::get_watchdog;
 use super::handlers::write_machine_device_identification::post_write_machine_device_identification;
 use crate::app_state::AppState;
+use crate::config::config;
 use crate::panic::{PanicDetails, send_panic};
 use crate::socketio::init::init_socketio;
 use anyhow::anyhow;
-use axum::routing::post;
+use axum::middleware;
+use axum::routing::{get, post};
 use smol::channel::Sender;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use std::thread::JoinHandle;
 use tower_http::cors::CorsLayer;
@@ -55,17 +88,109 @@ pub fn init_api(
                         post(post_write_machine_device_identification),
                     )
                     .route("/api/v1/machine/mutate", post(post_machine_mutate))
+                    .route("/api/v1/machines", get(get_machines))
+                    .route(
+                        "/api/v1/machines/{vendor}/{machine}/{serial}/events",
+                        get(get_machine_events),
+                    )
+                    .route(
+                        "/api/v1/machines/{vendor}/{machine}/{serial}/events/{event}",
+                        get(get_machine_event),
+                    )
+                    .route(
+                        "/api/v1/machines/{vendor}/{machine}/{serial}/mutate",
+                        post(post_machine_path_mutate),
+                    )
+                    .route(
+                        "/api/v1/machines/{vendor}/{machine}/{serial}/config",
+                        get(get_machine_config).post(post_machine_config),
+                    )
+                    .route(
+                        "/api/v1/machines/{vendor}/{machine}/{serial}/self-test",
+                        get(get_machine_self_test).post(post_machine_self_test),
+                    )
+                    .route("/api/v1/schema", get(get_api_schema))
+                    .route("/api/v1/recipes/mutate", post(post_recipe_mutate))
+                    .route("/api/v1/sequences/mutate", post(post_sequence_mutate))
+                    .route("/api/v1/batches/mutate", post(post_batch_mutate))
+                    .route("/api/v1/batches/runs", get(get_runs))
+                    .route("/api/v1/batches/runs/{id}", get(get_run))
+                    .route("/api/v1/batches/label", get(get_label))
+                    .route(
+                        "/api/v1/mes/orders",
+                        get(get_mes_orders).post(post_mes_order),
+                    )
+                    .route("/api/v1/mes/orders/schema", get(get_mes_order_schema))
+                    .route("/api/v1/alarms", get(get_alarms))
+                    .route("/api/v1/alarms/history", get(get_alarm_history))
+                    .route("/api/v1/alarms/mutate", post(post_alarm_mutate))
+                    .route("/api/v1/config/reload", post(post_config_reload))
+                    .route(
+                        "/api/v1/logging/filter",
+                        get(get_log_filter).post(post_log_filter),
+                    )
+                    .route("/api/v1/simulation", get(get_simulation))
+                    .route("/api/v1/simulation/mutate", post(post_simulation_mutate))
+                    .route("/api/v1/recording", get(get_recording))
+                    .route("/api/v1/recording/mutate", post(post_recording_mutate))
+                    .route("/api/v1/federation", get(get_federation))
+                    .route("/api/v1/time", get(get_time))
+                    .route("/api/v1/watchdog", get(get_watchdog))
+                    .route("/api/v1/instrumentation", get(get_instrumentation))
+                    .route("/api/v1/scope", get(get_scope))
+                    .route("/api/v1/scope/mutate", post(post_scope_mutate))
+                    .route("/api/v1/scheduler", get(get_scheduler))
+                    .route("/api/v1/serial/sniffer", get(get_sniffer))
+                    .route("/api/v1/serial/sniffer/mutate", post(post_sniffer_mutate))
+                    .route("/api/v1/serial/inventory", get(get_serial_inventory))
+                    .route("/api/v1/serial/firmware", get(get_firmware))
+                    .route("/api/v1/serial/firmware/upload", post(post_firmware_upload))
+                    .route("/api/v1/serial/laser-capture", get(get_laser_capture))
+                    .route(
+                        "/api/v1/serial/laser-capture/mutate",
+                        post(post_laser_capture_mutate),
+                    )
+                    .route("/api/v1/remote-session", get(get_remote_session))
+                    .route(
+                        "/api/v1/remote-session/mutate",
+                        post(post_remote_session_mutate),
+                    )
+                    .route("/api/v1/io-mapping", get(get_io_mapping))
+                    .route("/api/v1/io-mapping/mutate", post(post_io_mapping_mutate))
+                    .route("/api/v1/spool-types", get(get_spool_types))
+                    .route("/api/v1/spool-types/mutate", post(post_spool_types_mutate))
+                    .route("/api/v1/history/annotations", get(get_annotations))
+                    .route(
+                        "/api/v1/history/{vendor}/{machine}/{serial}",
+                        get(get_history),
+                    )
+                    .route_layer(middleware::from_fn_with_state(
+                        app_state.clone(),
+                        require_viewer,
+                    ))
+                    // reachable without a session
+                    .route("/metrics", get(get_metrics))
+                    .route("/api/v1/auth/login", post(post_login))
+                    .route("/api/v1/auth/logout", post(post_logout))
+                    .route("/api/v1/auth/session", get(get_session))
                     .layer(socketio_layer)
                     .layer(cors)
                     .layer(trace_layer)
                     .with_state(app_state);
 
-                let listener = tokio::net::TcpListener::bind("0.0.0.0:3001")
+                let bind_address = config().api.bind_address;
+                let listener = tokio::net::TcpListener::bind(bind_address)
                     .await
-                    .expect("Failed to bind to port 3001");
+                    .unwrap_or_else(|e| panic!("Failed to bind to {}: {}", bind_address, e));
 
-                tracing::info!("Starting HTTP server on 0.0.0.0:3001");
-                axum::serve(listener, app).await.expect("Failed to serve");
+                tracing::info!("Starting HTTP server on {}", bind_address);
+                // the peer address tells the local UI from remote clients
+                axum::serve(
+                    listener,
+                    app.into_make_service_with_connect_info::<SocketAddr>(),
+                )
+                .await
+                .expect("Failed to serve");
             });
         })
         .map_err(|e| anyhow!("Failed to spawn API thread: {}", e))
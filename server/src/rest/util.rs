@@ -1,7 +1,9 @@
+use crate::auth::AuthError;
 use axum::{
     body::Body,
     http::{Response, StatusCode},
 };
+use control_core::rest::mutation::{MutationErrorKind, MutationResponse};
 use serde_json::json;
 
 pub struct ResponseUtil {}
@@ -42,15 +44,39 @@ impl ResponseUtil {
     }
 
     pub fn not_found(message: &str) -> Response<Body> {
+        Self::error_with_status(StatusCode::NOT_FOUND, message)
+    }
+
+    pub fn bad_request(message: &str) -> Response<Body> {
+        Self::error_with_status(StatusCode::BAD_REQUEST, message)
+    }
+
+    /// Failed mutation with the kind of its error next to the message
+    pub fn mutation_error(status: StatusCode, response: &MutationResponse) -> Response<Body> {
+        let json = match serde_json::to_string(response) {
+            Ok(json) => json,
+            Err(e) => {
+                tracing::error!("Failed to serialize mutation response: {}", e);
+                return Self::error("Failed to serialize mutation response");
+            }
+        };
+        Response::builder()
+            .status(status)
+            .header("Content-Type", "application/json")
+            .body(Body::from(json))
+            .unwrap()
+    }
+
+    fn error_with_status(status: StatusCode, message: &str) -> Response<Body> {
         let json = match serde_json::to_string(&json!({ "error": message })) {
             Ok(json) => json,
             Err(e) => {
-                tracing::error!("Failed to serialize not found message: {}", e);
-                return Self::error("Failed to serialize not found message");
+                tracing::error!("Failed to serialize error message: {}", e);
+                return Self::error("Failed to serialize error message");
             }
         };
         Response::builder()
-            .status(StatusCode::NOT_FOUND)
+            .status(status)
             .header("Content-Type", "application/json")
             .body(Body::from(json))
             .unwrap()
@@ -60,13 +86,76 @@ impl ResponseUtil {
 pub enum ResponseUtilError {
     Error(anyhow::Error),
     NotFound(anyhow::Error),
+    BadRequest(anyhow::Error),
+    /// Request is valid but conflicts with the current state, e.g. a disconnected machine
+    Conflict(anyhow::Error),
+    /// Request is valid but a safety condition blocks it, e.g. an open guard
+    Interlock(anyhow::Error),
+    Unauthorized(anyhow::Error),
+    Forbidden(anyhow::Error),
+    /// Client sends faster than allowed
+    TooManyRequests(anyhow::Error),
+}
+
+impl ResponseUtilError {
+    pub const fn kind(&self) -> MutationErrorKind {
+        match self {
+            Self::Error(_) => MutationErrorKind::Internal,
+            Self::NotFound(_) => MutationErrorKind::NotFound,
+            Self::BadRequest(_) => MutationErrorKind::Validation,
+            Self::Conflict(_) => MutationErrorKind::NotReady,
+            Self::Interlock(_) => MutationErrorKind::Interlock,
+            Self::Unauthorized(_) => MutationErrorKind::Unauthorized,
+            Self::Forbidden(_) => MutationErrorKind::Forbidden,
+            Self::TooManyRequests(_) => MutationErrorKind::RateLimited,
+        }
+    }
+
+    const fn status(&self) -> StatusCode {
+        match self {
+            Self::Error(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Self::Conflict(_) | Self::Interlock(_) => StatusCode::CONFLICT,
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
+            Self::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    const fn error(&self) -> &anyhow::Error {
+        match self {
+            Self::Error(e)
+            | Self::NotFound(e)
+            | Self::BadRequest(e)
+            | Self::Conflict(e)
+            | Self::Interlock(e)
+            | Self::Unauthorized(e)
+            | Self::Forbidden(e)
+            | Self::TooManyRequests(e) => e,
+        }
+    }
+}
+
+impl From<ResponseUtilError> for MutationResponse {
+    fn from(error: ResponseUtilError) -> Self {
+        Self::error(error.kind(), error.error().to_string())
+    }
 }
 
+/// Errors are answered like a failed mutation, `{"success": false, "error": ..., "kind": ...}`
 impl From<ResponseUtilError> for Response<Body> {
     fn from(error: ResponseUtilError) -> Self {
+        let status = error.status();
+        ResponseUtil::mutation_error(status, &MutationResponse::from(error))
+    }
+}
+
+impl From<AuthError> for ResponseUtilError {
+    fn from(error: AuthError) -> Self {
         match error {
-            ResponseUtilError::Error(e) => ResponseUtil::error(&e.to_string()),
-            ResponseUtilError::NotFound(e) => ResponseUtil::not_found(&e.to_string()),
+            AuthError::Unauthenticated => Self::Unauthorized(error.into()),
+            AuthError::Forbidden { .. } => Self::Forbidden(error.into()),
         }
     }
 }
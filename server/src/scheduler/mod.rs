@@ -0,0 +1,374 @@
+use crate::{machines::machine_slug, storage};
+use control_core::machines::identification::MachineIdentificationUnique;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+/// File inside [`crate::storage::data_dir`] configuring the act scheduling
+///
+/// The defaults are used if the file does not exist.
+pub const SCHEDULER_FILE: &str = "scheduler.json";
+
+/// Order machines act in a loop cycle, higher priorities act first and are deferred last
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+    /// Acts even when the cycle budget is spent, e.g. motion control
+    Critical,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MachineSchedule {
+    /// Target time between two act calls, 0 acts in every loop cycle
+    pub period_us: u64,
+    pub priority: Priority,
+}
+
+impl MachineSchedule {
+    pub const fn period(&self) -> Duration {
+        Duration::from_micros(self.period_us)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SchedulerConfig {
+    /// Act time per loop cycle, once spent only [`Priority::Critical`] machines act
+    #[serde(default = "default_cycle_budget_us")]
+    pub cycle_budget_us: u64,
+    /// Schedule of machine types without an entry in `machines`
+    #[serde(default = "default_schedule")]
+    pub default: MachineSchedule,
+    /// Schedules of single machine types by slug, e.g. `{"laser": {"period_us": 10000, ...}}`
+    #[serde(default = "default_machine_schedules")]
+    pub machines: BTreeMap<String, MachineSchedule>,
+    /// Target time between two flushes of the batched machine events to the clients
+    #[serde(default = "default_emit_period_us")]
+    pub emit_period_us: u64,
+}
+
+const fn default_cycle_budget_us() -> u64 {
+    500
+}
+
+const fn default_schedule() -> MachineSchedule {
+    MachineSchedule {
+        period_us: 0,
+        priority: Priority::Normal,
+    }
+}
+
+fn default_machine_schedules() -> BTreeMap<String, MachineSchedule> {
+    BTreeMap::from([
+        (
+            "winder".to_string(),
+            MachineSchedule {
+                period_us: 1_000,
+                priority: Priority::Critical,
+            },
+        ),
+        (
+            "laser".to_string(),
+            MachineSchedule {
+                period_us: 10_000,
+                priority: Priority::High,
+            },
+        ),
+    ])
+}
+
+const fn default_emit_period_us() -> u64 {
+    100_000
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            cycle_budget_us: default_cycle_budget_us(),
+            default: default_schedule(),
+            machines: default_machine_schedules(),
+            emit_period_us: default_emit_period_us(),
+        }
+    }
+}
+
+impl SchedulerConfig {
+    /// Reads [`SCHEDULER_FILE`], the defaults if it does not exist
+    pub fn read() -> Result<Self, anyhow::Error> {
+        let path = storage::data_dir().join(SCHEDULER_FILE);
+        Ok(storage::read_json::<Self>(&path)?.unwrap_or_default())
+    }
+
+    /// Reads [`SCHEDULER_FILE`], falls back to the defaults so the server still starts
+    pub fn load() -> Self {
+        Self::read().unwrap_or_else(|e| {
+            tracing::error!("Failed to read scheduler config, using defaults: {:?}", e);
+            Self::default()
+        })
+    }
+
+    pub fn schedule(&self, machine: &MachineIdentificationUnique) -> MachineSchedule {
+        machine_slug(machine.machine_identification.machine)
+            .and_then(|slug| self.machines.get(slug))
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct MachineScheduleState {
+    next_due: Option<Instant>,
+    acts: u64,
+    /// Periods that passed without an act call because the loop was late
+    missed: u64,
+    /// Times the machine was due but did not fit into the cycle budget
+    deferred: u64,
+}
+
+/// [`MachineScheduleState`] as returned by the API
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct MachineScheduleReport {
+    pub machine_identification_unique: MachineIdentificationUnique,
+    pub schedule: MachineSchedule,
+    pub acts: u64,
+    pub missed: u64,
+    pub deferred: u64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SchedulerReport {
+    pub cycle_budget_us: u64,
+    /// Loop cycles whose act time exceeded the budget
+    pub cycle_overruns: u64,
+    pub emit_period_us: u64,
+    pub deferred_emits: u64,
+    pub machines: Vec<MachineScheduleReport>,
+}
+
+/// Decides which machines act in a loop cycle
+///
+/// Machines act once their period passed, highest priority first. When the act time of a
+/// cycle exceeds the budget the remaining machines are deferred to the next cycle, so under
+/// load low priority work degrades first. Flushing the machine events counts as
+/// [`Priority::Low`] work.
+#[derive(Debug)]
+pub struct ActScheduler {
+    pub config: SchedulerConfig,
+    machines: HashMap<MachineIdentificationUnique, MachineScheduleState>,
+    next_emit: Option<Instant>,
+    cycle_overruns: u64,
+    deferred_emits: u64,
+}
+
+impl ActScheduler {
+    pub fn new(config: SchedulerConfig) -> Self {
+        Self {
+            config,
+            machines: HashMap::new(),
+            next_emit: None,
+            cycle_overruns: 0,
+            deferred_emits: 0,
+        }
+    }
+
+    const fn cycle_budget(&self) -> Duration {
+        Duration::from_micros(self.config.cycle_budget_us)
+    }
+
+    /// Machines due at `now` with their priority, highest priority first
+    ///
+    /// Machines that are not given are forgotten.
+    pub fn due(
+        &mut self,
+        connected: &[MachineIdentificationUnique],
+        now: Instant,
+    ) -> Vec<(MachineIdentificationUnique, Priority)> {
+        self.machines
+            .retain(|machine, _| connected.contains(machine));
+
+        let mut due: Vec<_> = connected
+            .iter()
+            .filter(|machine| {
+                self.machines
+                    .get(*machine)
+                    .and_then(|state| state.next_due)
+                    .is_none_or(|next_due| now >= next_due)
+            })
+            .map(|machine| (machine.clone(), self.config.schedule(machine).priority))
+            .collect();
+        // stable, machines of the same priority keep their order
+        due.sort_by_key(|(_, priority)| std::cmp::Reverse(*priority));
+        due
+    }
+
+    /// Whether work of `priority` still fits after `spent` of the cycle budget
+    pub fn fits(&self, priority: Priority, spent: Duration) -> bool {
+        priority == Priority::Critical || spent < self.cycle_budget()
+    }
+
+    /// Records that a due machine acted at `now`, it is due again one period later
+    pub fn acted(&mut self, machine: &MachineIdentificationUnique, now: Instant) {
+        let period = self.config.schedule(machine).period();
+        let state = self.machines.entry(machine.clone()).or_default();
+        state.acts += 1;
+        let next_due = match state.next_due {
+            Some(next_due) if !period.is_zero() => next_due + period,
+            _ => now + period,
+        };
+        if next_due > now || period.is_zero() {
+            state.next_due = Some(next_due);
+            return;
+        }
+        // keep the cadence, but don't catch up on periods the loop was too late for
+        let behind = now.saturating_duration_since(next_due);
+        state.missed += (behind.as_nanos() / period.as_nanos()) as u64 + 1;
+        state.next_due = Some(now + period);
+    }
+
+    /// Records that a due machine did not fit into the cycle budget, it stays due
+    pub fn defer(&mut self, machine: &MachineIdentificationUnique) {
+        self.machines.entry(machine.clone()).or_default().deferred += 1;
+    }
+
+    /// Whether the machine events are flushed in this cycle after `spent` act time
+    pub fn emit_due(&mut self, now: Instant, spent: Duration) -> bool {
+        if self.next_emit.is_some_and(|next_emit| now < next_emit) {
+            return false;
+        }
+        if !self.fits(Priority::Low, spent) {
+            self.deferred_emits += 1;
+            return false;
+        }
+        self.next_emit = Some(now + Duration::from_micros(self.config.emit_period_us));
+        true
+    }
+
+    /// Records the act time of a whole loop cycle
+    pub fn cycle(&mut self, spent: Duration) {
+        if spent > self.cycle_budget() {
+            self.cycle_overruns += 1;
+        }
+    }
+
+    pub fn report(&self) -> SchedulerReport {
+        let mut machines: Vec<_> = self
+            .machines
+            .iter()
+            .map(|(machine, state)| MachineScheduleReport {
+                machine_identification_unique: machine.clone(),
+                schedule: self.config.schedule(machine),
+                acts: state.acts,
+                missed: state.missed,
+                deferred: state.deferred,
+            })
+            .collect();
+        machines.sort_by_key(|report| report.machine_identification_unique.to_string());
+        SchedulerReport {
+            cycle_budget_us: self.config.cycle_budget_us,
+            cycle_overruns: self.cycle_overruns,
+            emit_period_us: self.config.emit_period_us,
+            deferred_emits: self.deferred_emits,
+            machines,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machines::{
+        MACHINE_EXTRUDER_V1, MACHINE_LASER_V1, MACHINE_WINDER_V1, VENDOR_QITECH,
+    };
+    use control_core::machines::identification::MachineIdentification;
+
+    fn machine(machine: u16) -> MachineIdentificationUnique {
+        MachineIdentificationUnique {
+            machine_identification: MachineIdentification {
+                vendor: VENDOR_QITECH,
+                machine,
+            },
+            serial: 1,
+        }
+    }
+
+    fn due(scheduler: &mut ActScheduler, now: Instant) -> Vec<u16> {
+        let connected = [
+            machine(MACHINE_EXTRUDER_V1),
+            machine(MACHINE_LASER_V1),
+            machine(MACHINE_WINDER_V1),
+        ];
+        scheduler
+            .due(&connected, now)
+            .into_iter()
+            .map(|(machine, _)| machine.machine_identification.machine)
+            .collect()
+    }
+
+    #[test]
+    fn test_periods_and_priorities() {
+        let mut scheduler = ActScheduler::new(SchedulerConfig::default());
+        let start = Instant::now();
+
+        // everything is due at first, the winder first
+        assert_eq!(
+            due(&mut scheduler, start),
+            vec![MACHINE_WINDER_V1, MACHINE_LASER_V1, MACHINE_EXTRUDER_V1]
+        );
+        for id in [MACHINE_WINDER_V1, MACHINE_LASER_V1, MACHINE_EXTRUDER_V1] {
+            scheduler.acted(&machine(id), start);
+        }
+
+        // the extruder acts every cycle
+        let now = start + Duration::from_micros(500);
+        assert_eq!(due(&mut scheduler, now), vec![MACHINE_EXTRUDER_V1]);
+
+        let now = start + Duration::from_micros(1_000);
+        assert_eq!(
+            due(&mut scheduler, now),
+            vec![MACHINE_WINDER_V1, MACHINE_EXTRUDER_V1]
+        );
+        scheduler.acted(&machine(MACHINE_WINDER_V1), now);
+
+        // a late loop misses periods without catching up on them
+        let now = start + Duration::from_micros(4_500);
+        scheduler.acted(&machine(MACHINE_WINDER_V1), now);
+        let report = scheduler.report();
+        let winder = report
+            .machines
+            .iter()
+            .find(|report| report.schedule.priority == Priority::Critical)
+            .unwrap();
+        // due at 2ms, acting at 4.5ms misses the periods due at 3ms and 4ms
+        assert_eq!(winder.missed, 2);
+        assert_eq!(
+            due(&mut scheduler, now + Duration::from_micros(999)),
+            vec![MACHINE_EXTRUDER_V1]
+        );
+    }
+
+    #[test]
+    fn test_budget_degrades_low_priority_first() {
+        let mut scheduler = ActScheduler::new(SchedulerConfig::default());
+        let start = Instant::now();
+        let spent = Duration::from_micros(600);
+
+        assert!(scheduler.fits(Priority::Critical, spent));
+        assert!(!scheduler.fits(Priority::Normal, spent));
+        assert!(scheduler.fits(Priority::Normal, Duration::from_micros(100)));
+
+        // deferred emits are flushed once there is time
+        assert!(!scheduler.emit_due(start, spent));
+        assert!(scheduler.emit_due(start, Duration::ZERO));
+        assert!(!scheduler.emit_due(start + Duration::from_millis(50), Duration::ZERO));
+        assert!(scheduler.emit_due(start + Duration::from_millis(100), Duration::ZERO));
+
+        scheduler.cycle(spent);
+        let report = scheduler.report();
+        assert_eq!(report.deferred_emits, 1);
+        assert_eq!(report.cycle_overruns, 1);
+    }
+}
@@ -0,0 +1,124 @@
+use super::Sequence;
+use crate::app_state::AppState;
+use control_core::socketio::{
+    event::{BuildEvent, Event, GenericEvent},
+    namespace::{CacheFn, CacheableEvents, Namespace, NamespaceCacheingLogic, cache_one_event},
+    namespace_id::NamespaceId,
+};
+use control_core_derive::BuildEvent;
+use serde::{Deserialize, Serialize};
+use smol::channel::Sender;
+use socketioxide::extract::SocketRef;
+use std::sync::Arc;
+use tracing::instrument;
+
+#[derive(Serialize, Debug, Clone, BuildEvent)]
+pub struct SequencesEvent {
+    /// all stored sequences
+    pub sequences: Vec<Sequence>,
+    /// name of the running sequence
+    pub running: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceRunState {
+    Running,
+    Completed,
+    Failed,
+    Aborted,
+}
+
+#[derive(Serialize, Debug, Clone, BuildEvent)]
+pub struct SequenceProgressEvent {
+    pub name: String,
+    pub state: SequenceRunState,
+    /// 1-based index of the current step, the failed or aborted one once finished
+    pub step: usize,
+    pub steps: usize,
+    /// description of the current step
+    pub description: String,
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub enum Mutation {
+    /// Insert or replace a sequence by its name
+    SaveSequence(Sequence),
+    DeleteSequence(String),
+    /// Run a sequence by name in the background
+    RunSequence(String),
+    AbortSequence,
+}
+
+pub enum SequencesNamespaceEvents {
+    Sequences(Event<SequencesEvent>),
+    SequenceProgress(Event<SequenceProgressEvent>),
+}
+
+impl CacheableEvents<Self> for SequencesNamespaceEvents {
+    fn event_value(&self) -> GenericEvent {
+        match self {
+            Self::Sequences(event) => event.into(),
+            Self::SequenceProgress(event) => event.into(),
+        }
+    }
+
+    fn event_cache_fn(&self) -> CacheFn {
+        match self {
+            Self::Sequences(_) => cache_one_event(),
+            Self::SequenceProgress(_) => cache_one_event(),
+        }
+    }
+}
+
+pub struct SequencesRoom {
+    pub namespace: Namespace,
+}
+
+impl SequencesRoom {
+    pub fn new(socket_queue_tx: Sender<(SocketRef, Arc<GenericEvent>)>) -> Self {
+        Self {
+            namespace: Namespace::new(socket_queue_tx).with_id(NamespaceId::Sequences),
+        }
+    }
+}
+
+impl NamespaceCacheingLogic<SequencesNamespaceEvents> for SequencesRoom {
+    #[instrument(skip_all)]
+    fn emit(&mut self, event: SequencesNamespaceEvents) {
+        let buffer_fn = event.event_cache_fn();
+        let generic_event = Arc::new(event.event_value());
+        self.namespace.emit(generic_event, &buffer_fn);
+    }
+}
+
+/// Emits the current list of sequences to the sequences namespace
+pub async fn emit_sequences(app_state: &Arc<AppState>) {
+    let event = {
+        let sequences = app_state.sequences.read().await;
+        SequencesEvent {
+            sequences: sequences.list(),
+            running: sequences.running(),
+        }
+        .build()
+    };
+
+    app_state
+        .socketio_setup
+        .namespaces
+        .write()
+        .await
+        .sequences_namespace
+        .emit(SequencesNamespaceEvents::Sequences(event));
+}
+
+/// Emits the progress of a run to the sequences namespace
+pub async fn emit_progress(app_state: &Arc<AppState>, progress: SequenceProgressEvent) {
+    app_state
+        .socketio_setup
+        .namespaces
+        .write()
+        .await
+        .sequences_namespace
+        .emit(SequencesNamespaceEvents::SequenceProgress(progress.build()));
+}
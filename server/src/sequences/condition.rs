@@ -0,0 +1,150 @@
+use control_core::{
+    machines::values::{DIAMETER, IN_TOLERANCE, LINE_SPEED, MachineValueBus},
+    uom_extensions::velocity::meter_per_minute,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use uom::si::length::millimeter;
+
+/// State of the line a sequence waits for, read from the values the machines publish
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub enum SequenceCondition {
+    /// Measured diameter stays within a band of `band` mm
+    DiameterStable { band: f64 },
+    /// Laser reports the diameter in tolerance
+    InTolerance,
+    /// Line runs with at least `speed` m/min
+    LineSpeedAtLeast { speed: f64 },
+}
+
+impl SequenceCondition {
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        match self {
+            Self::DiameterStable { band } if !(band.is_finite() && *band > 0.0) => {
+                Err(anyhow::anyhow!("Diameter band must be positive"))
+            }
+            Self::LineSpeedAtLeast { speed } if !speed.is_finite() => {
+                Err(anyhow::anyhow!("Line speed must be finite"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Self::DiameterStable { band } => format!("diameter stable within {} mm", band),
+            Self::InTolerance => "diameter in tolerance".to_string(),
+            Self::LineSpeedAtLeast { speed } => format!("line speed at least {} m/min", speed),
+        }
+    }
+}
+
+/// Tracks since when a [`SequenceCondition`] holds
+#[derive(Debug)]
+pub struct ConditionWatch {
+    condition: SequenceCondition,
+    held_since: Option<Instant>,
+    /// min and max diameter in mm since `held_since`
+    diameter_range: Option<(f64, f64)>,
+}
+
+impl ConditionWatch {
+    /// Values older are from a disconnected machine and never fulfill a condition
+    const MAX_AGE: Duration = Duration::from_secs(1);
+
+    pub const fn new(condition: SequenceCondition) -> Self {
+        Self {
+            condition,
+            held_since: None,
+            diameter_range: None,
+        }
+    }
+
+    /// Time the condition held until `now`, zero if it doesn't hold
+    pub fn update(&mut self, now: Instant, values: &MachineValueBus) -> Duration {
+        let holds = match &self.condition {
+            SequenceCondition::DiameterStable { band } => {
+                let diameter = values
+                    .latest(DIAMETER)
+                    .filter(|sample| !sample.is_stale(now, Self::MAX_AGE))
+                    .map(|sample| sample.value.get::<millimeter>());
+                match diameter {
+                    Some(diameter) => {
+                        let (min, max) = self
+                            .diameter_range
+                            .map_or((diameter, diameter), |(min, max)| {
+                                (min.min(diameter), max.max(diameter))
+                            });
+                        if max - min > *band {
+                            // the band starts again at the latest measurement
+                            self.held_since = None;
+                            self.diameter_range = Some((diameter, diameter));
+                        } else {
+                            self.diameter_range = Some((min, max));
+                        }
+                        true
+                    }
+                    None => {
+                        self.diameter_range = None;
+                        false
+                    }
+                }
+            }
+            SequenceCondition::InTolerance => values
+                .latest(IN_TOLERANCE)
+                .filter(|sample| !sample.is_stale(now, Self::MAX_AGE))
+                .is_some_and(|sample| sample.value),
+            SequenceCondition::LineSpeedAtLeast { speed } => values
+                .latest(LINE_SPEED)
+                .filter(|sample| !sample.is_stale(now, Self::MAX_AGE))
+                .is_some_and(|sample| sample.value.get::<meter_per_minute>().abs() >= *speed),
+        };
+        if !holds {
+            self.held_since = None;
+            return Duration::ZERO;
+        }
+        now.saturating_duration_since(*self.held_since.get_or_insert(now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use control_core::machines::identification::{
+        MachineIdentification, MachineIdentificationUnique,
+    };
+    use uom::si::f64::Length;
+
+    #[test]
+    fn test_diameter_stable() {
+        let laser = MachineIdentificationUnique {
+            machine_identification: MachineIdentification {
+                vendor: 1,
+                machine: 6,
+            },
+            serial: 1,
+        };
+        let values = MachineValueBus::new();
+        let mut watch = ConditionWatch::new(SequenceCondition::DiameterStable { band: 0.02 });
+        let start = Instant::now();
+        let mut measure = |seconds: u64, diameter: f64| {
+            let now = start + Duration::from_secs(seconds);
+            values.publish(&laser, DIAMETER, Length::new::<millimeter>(diameter), now);
+            watch.update(now, &values)
+        };
+
+        assert_eq!(measure(0, 1.80), Duration::ZERO);
+        assert_eq!(measure(10, 1.79), Duration::from_secs(10));
+        // leaves the band, it starts again
+        assert_eq!(measure(20, 1.76), Duration::ZERO);
+        assert_eq!(measure(30, 1.75), Duration::from_secs(10));
+        assert_eq!(measure(40, 1.765), Duration::from_secs(20));
+
+        // a lost laser doesn't fulfill the condition
+        assert_eq!(
+            watch.update(start + Duration::from_secs(45), &values),
+            Duration::ZERO
+        );
+    }
+}
@@ -0,0 +1,8 @@
+use super::api::emit_sequences;
+use crate::app_state::AppState;
+use std::sync::Arc;
+
+/// Publishes the stored sequences so clients get them on connect
+pub fn init_sequences(app_state: Arc<AppState>) {
+    smol::block_on(emit_sequences(&app_state));
+}
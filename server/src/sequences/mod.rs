@@ -0,0 +1,353 @@
+use crate::{auth::mutation_name, storage};
+use condition::SequenceCondition;
+use control_core::machines::identification::MachineIdentificationUnique;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+pub mod api;
+pub mod condition;
+pub mod init;
+pub mod run;
+
+/// File inside [`storage::data_dir`] holding all sequences
+pub const SEQUENCES_FILE: &str = "sequences.json";
+
+/// Named list of steps run one after another on the machines of the line, e.g. to start it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct Sequence {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub steps: Vec<SequenceStep>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub enum SequenceStep {
+    /// Applies a mutation to a machine, e.g. `{ "SetPullerRegulationMode": "Diameter" }`
+    Mutate {
+        machine_identification_unique: MachineIdentificationUnique,
+        mutation: Value,
+    },
+    /// Waits a fixed time
+    Wait { seconds: f64 },
+    /// Waits until the condition held for `hold_seconds`, fails after `timeout_seconds`
+    WaitFor {
+        condition: SequenceCondition,
+        hold_seconds: f64,
+        timeout_seconds: Option<f64>,
+    },
+    /// Applies a mutation with a bare value going from `from` to `to` in `seconds`, e.g. to
+    /// ramp up the puller speed
+    Ramp {
+        machine_identification_unique: MachineIdentificationUnique,
+        mutation: String,
+        from: f64,
+        to: f64,
+        seconds: f64,
+    },
+}
+
+impl SequenceStep {
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        let non_negative = |name: &str, value: f64| {
+            if value.is_finite() && value >= 0.0 {
+                Ok(())
+            } else {
+                Err(anyhow::anyhow!("{} must not be negative", name))
+            }
+        };
+        match self {
+            Self::Mutate { mutation, .. } => {
+                if mutation_name(mutation).is_none() {
+                    return Err(anyhow::anyhow!("Mutation {} has no name", mutation));
+                }
+                Ok(())
+            }
+            Self::Wait { seconds } => non_negative("Wait seconds", *seconds),
+            Self::WaitFor {
+                condition,
+                hold_seconds,
+                timeout_seconds,
+            } => {
+                condition.validate()?;
+                non_negative("Hold seconds", *hold_seconds)?;
+                timeout_seconds.map_or(Ok(()), |timeout| non_negative("Timeout seconds", timeout))
+            }
+            Self::Ramp {
+                mutation,
+                from,
+                to,
+                seconds,
+                ..
+            } => {
+                if mutation.trim().is_empty() {
+                    return Err(anyhow::anyhow!("Ramp mutation must not be empty"));
+                }
+                if !(from.is_finite() && to.is_finite()) {
+                    return Err(anyhow::anyhow!("Ramp values must be finite"));
+                }
+                non_negative("Ramp seconds", *seconds)
+            }
+        }
+    }
+
+    /// Short description for the progress of a run
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Mutate {
+                machine_identification_unique,
+                mutation,
+            } => format!(
+                "{} on {}",
+                mutation_name(mutation).unwrap_or_default(),
+                machine_identification_unique
+            ),
+            Self::Wait { seconds } => format!("Wait {} s", seconds),
+            Self::WaitFor {
+                condition,
+                hold_seconds,
+                ..
+            } => format!("Wait until {} for {} s", condition.describe(), hold_seconds),
+            Self::Ramp {
+                machine_identification_unique,
+                mutation,
+                from,
+                to,
+                seconds,
+            } => format!(
+                "Ramp {} on {} from {} to {} in {} s",
+                mutation, machine_identification_unique, from, to, seconds
+            ),
+        }
+    }
+}
+
+impl Sequence {
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.name.trim().is_empty() {
+            return Err(anyhow::anyhow!("Sequence name must not be empty"));
+        }
+        if self.steps.is_empty() {
+            return Err(anyhow::anyhow!("Sequence {} has no steps", self.name));
+        }
+        for (i, step) in self.steps.iter().enumerate() {
+            step.validate()
+                .map_err(|e| anyhow::anyhow!("Step {} of sequence {}: {}", i + 1, self.name, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Sequence currently running, see [`run::start_sequence`]
+#[derive(Debug)]
+struct SequenceRun {
+    name: String,
+    abort: Arc<AtomicBool>,
+}
+
+/// Sequences persisted as a JSON file
+///
+/// One sequence runs at a time, two sequences mutating the same machines would fight each
+/// other.
+#[derive(Debug)]
+pub struct SequenceStore {
+    path: PathBuf,
+    sequences: BTreeMap<String, Sequence>,
+    running: Option<SequenceRun>,
+}
+
+impl SequenceStore {
+    pub const fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            sequences: BTreeMap::new(),
+            running: None,
+        }
+    }
+
+    /// Loads the sequences from `path`
+    ///
+    /// A missing or broken file results in an empty store so the server still starts.
+    pub fn load(path: PathBuf) -> Self {
+        let mut store = Self::new(path);
+        match storage::read_json::<Vec<Sequence>>(&store.path) {
+            Ok(Some(sequences)) => {
+                for sequence in sequences {
+                    store.sequences.insert(sequence.name.clone(), sequence);
+                }
+                tracing::info!("Loaded {} sequences", store.sequences.len());
+            }
+            Ok(None) => {}
+            Err(e) => tracing::error!("Failed to load sequences: {:?}", e),
+        }
+        store
+    }
+
+    pub fn list(&self) -> Vec<Sequence> {
+        self.sequences.values().cloned().collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Sequence> {
+        self.sequences.get(name)
+    }
+
+    /// Inserts or replaces a sequence and writes the store to disk
+    pub fn save(&mut self, sequence: Sequence) -> Result<(), anyhow::Error> {
+        sequence.validate()?;
+        self.sequences.insert(sequence.name.clone(), sequence);
+        self.persist()
+    }
+
+    /// Removes a sequence and writes the store to disk
+    pub fn delete(&mut self, name: &str) -> Result<(), anyhow::Error> {
+        if self.sequences.remove(name).is_none() {
+            return Err(anyhow::anyhow!("Sequence {} not found", name));
+        }
+        self.persist()
+    }
+
+    /// Name of the running sequence
+    pub fn running(&self) -> Option<String> {
+        self.running.as_ref().map(|run| run.name.clone())
+    }
+
+    /// Marks a sequence as running, returns it with the flag aborting it
+    pub fn start(&mut self, name: &str) -> Result<(Sequence, Arc<AtomicBool>), anyhow::Error> {
+        if let Some(run) = &self.running {
+            return Err(anyhow::anyhow!(
+                "[{}::SequenceStore::start] Sequence {} is still running",
+                module_path!(),
+                run.name
+            ));
+        }
+        let sequence = self.get(name).cloned().ok_or_else(|| {
+            anyhow::anyhow!(
+                "[{}::SequenceStore::start] Sequence {} not found",
+                module_path!(),
+                name
+            )
+        })?;
+        let abort = Arc::new(AtomicBool::new(false));
+        self.running = Some(SequenceRun {
+            name: sequence.name.clone(),
+            abort: abort.clone(),
+        });
+        Ok((sequence, abort))
+    }
+
+    /// Requests the running sequence to stop, a wait or ramp stops right away
+    pub fn abort(&self) -> Result<(), anyhow::Error> {
+        let run = self.running.as_ref().ok_or_else(|| {
+            anyhow::anyhow!(
+                "[{}::SequenceStore::abort] No sequence is running",
+                module_path!()
+            )
+        })?;
+        run.abort.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn finish(&mut self) {
+        self.running = None;
+    }
+
+    fn persist(&self) -> Result<(), anyhow::Error> {
+        storage::write_json(&self.path, &self.list())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use control_core::machines::identification::MachineIdentification;
+    use serde_json::json;
+
+    fn start_line() -> Sequence {
+        let winder = MachineIdentificationUnique {
+            machine_identification: MachineIdentification {
+                vendor: 1,
+                machine: 2,
+            },
+            serial: 3,
+        };
+        Sequence {
+            name: "start".to_string(),
+            description: String::new(),
+            steps: vec![
+                SequenceStep::Mutate {
+                    machine_identification_unique: winder.clone(),
+                    mutation: json!({ "SetMode": "Pull" }),
+                },
+                SequenceStep::WaitFor {
+                    condition: SequenceCondition::DiameterStable { band: 0.02 },
+                    hold_seconds: 30.0,
+                    timeout_seconds: Some(600.0),
+                },
+                SequenceStep::Ramp {
+                    machine_identification_unique: winder,
+                    mutation: "SetPullerTargetSpeed".to_string(),
+                    from: 5.0,
+                    to: 20.0,
+                    seconds: 60.0,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_validate_sequence() {
+        let mut sequence = start_line();
+        assert!(sequence.validate().is_ok());
+
+        sequence.steps.push(SequenceStep::Wait { seconds: -1.0 });
+        assert!(sequence.validate().is_err());
+        sequence.steps.pop();
+        sequence.steps.push(SequenceStep::Mutate {
+            machine_identification_unique: MachineIdentificationUnique {
+                machine_identification: MachineIdentification {
+                    vendor: 1,
+                    machine: 2,
+                },
+                serial: 3,
+            },
+            mutation: json!(5.0),
+        });
+        assert!(sequence.validate().is_err());
+
+        sequence.steps.clear();
+        assert!(sequence.validate().is_err());
+    }
+
+    #[test]
+    fn test_store_roundtrip() {
+        let path = std::env::temp_dir()
+            .join(format!("qitech-sequences-{}", std::process::id()))
+            .join(SEQUENCES_FILE);
+        let mut store = SequenceStore::new(path.clone());
+        store.save(start_line()).unwrap();
+        assert!(store.delete("stop").is_err());
+
+        let mut loaded = SequenceStore::load(path.clone());
+        assert_eq!(loaded.get("start"), Some(&start_line()));
+
+        // one sequence runs at a time
+        assert!(loaded.abort().is_err());
+        let (_, abort) = loaded.start("start").unwrap();
+        assert!(loaded.start("start").is_err());
+        loaded.abort().unwrap();
+        assert!(abort.load(Ordering::Relaxed));
+        loaded.finish();
+        assert_eq!(loaded.running(), None);
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,187 @@
+use super::{
+    Sequence, SequenceStep,
+    api::{SequenceProgressEvent, SequenceRunState, emit_progress, emit_sequences},
+    condition::ConditionWatch,
+};
+use crate::{app_state::AppState, rest::handlers::machine_mutation::mutate_machine};
+use control_core::{
+    machines::identification::MachineIdentificationUnique, rest::mutation::MachineMutationBody,
+};
+use serde_json::{Map, Value};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+/// Interval waits check their condition and the abort flag in
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Interval a ramp applies its next value in, well below the mutation rate limit
+const RAMP_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Starts a stored sequence in the background
+pub async fn start_sequence(app_state: &Arc<AppState>, name: &str) -> Result<(), anyhow::Error> {
+    let (sequence, abort) = app_state.sequences.write().await.start(name)?;
+    tracing::info!("Running sequence {}", sequence.name);
+    let app_state = app_state.clone();
+    smol::spawn(async move {
+        run_sequence(&app_state, sequence, &abort).await;
+        app_state.sequences.write().await.finish();
+        emit_sequences(&app_state).await;
+    })
+    .detach();
+    Ok(())
+}
+
+/// Runs the steps one after another until one fails or the sequence is aborted
+///
+/// An aborted ramp keeps the value it applied last, the machines are left as they are.
+async fn run_sequence(app_state: &Arc<AppState>, sequence: Sequence, abort: &AtomicBool) {
+    let steps = sequence.steps.len();
+    let progress =
+        |step: usize, state: SequenceRunState, error: Option<String>| SequenceProgressEvent {
+            name: sequence.name.clone(),
+            state,
+            step: step + 1,
+            steps,
+            description: sequence.steps[step].describe(),
+            error,
+        };
+
+    for (i, step) in sequence.steps.iter().enumerate() {
+        emit_progress(app_state, progress(i, SequenceRunState::Running, None)).await;
+        if let Err(e) = run_step(app_state, step, abort).await {
+            let event = match abort.load(Ordering::Relaxed) {
+                true => {
+                    tracing::info!("Aborted sequence {} at step {}", sequence.name, i + 1);
+                    progress(i, SequenceRunState::Aborted, None)
+                }
+                false => {
+                    tracing::error!(
+                        "Sequence {} failed at step {}: {:?}",
+                        sequence.name,
+                        i + 1,
+                        e
+                    );
+                    progress(i, SequenceRunState::Failed, Some(e.to_string()))
+                }
+            };
+            emit_progress(app_state, event).await;
+            return;
+        }
+    }
+
+    tracing::info!("Completed sequence {}", sequence.name);
+    emit_progress(
+        app_state,
+        progress(steps - 1, SequenceRunState::Completed, None),
+    )
+    .await;
+}
+
+async fn run_step(
+    app_state: &Arc<AppState>,
+    step: &SequenceStep,
+    abort: &AtomicBool,
+) -> Result<(), anyhow::Error> {
+    match step {
+        SequenceStep::Mutate {
+            machine_identification_unique,
+            mutation,
+        } => {
+            check_abort(abort)?;
+            mutate(app_state, machine_identification_unique, mutation.clone()).await
+        }
+        SequenceStep::Wait { seconds } => {
+            let until = Instant::now() + Duration::from_secs_f64(*seconds);
+            while Instant::now() < until {
+                check_abort(abort)?;
+                smol::Timer::after(POLL_INTERVAL).await;
+            }
+            Ok(())
+        }
+        SequenceStep::WaitFor {
+            condition,
+            hold_seconds,
+            timeout_seconds,
+        } => {
+            let hold = Duration::from_secs_f64(*hold_seconds);
+            let timeout = timeout_seconds.map(Duration::from_secs_f64);
+            let values = app_state.machines.read().await.values.clone();
+            let mut watch = ConditionWatch::new(condition.clone());
+            let start = Instant::now();
+            loop {
+                check_abort(abort)?;
+                let now = Instant::now();
+                if watch.update(now, &values) >= hold {
+                    return Ok(());
+                }
+                if timeout.is_some_and(|timeout| now.saturating_duration_since(start) > timeout) {
+                    return Err(anyhow::anyhow!(
+                        "[{}::run_step] Timed out waiting until {}",
+                        module_path!(),
+                        condition.describe()
+                    ));
+                }
+                smol::Timer::after(POLL_INTERVAL).await;
+            }
+        }
+        SequenceStep::Ramp {
+            machine_identification_unique,
+            mutation,
+            from,
+            to,
+            seconds,
+        } => {
+            let start = Instant::now();
+            loop {
+                check_abort(abort)?;
+                let elapsed = start.elapsed().as_secs_f64();
+                let progress = match *seconds > 0.0 {
+                    true => (elapsed / seconds).min(1.0),
+                    false => 1.0,
+                };
+                let mut data = Map::new();
+                data.insert(
+                    mutation.clone(),
+                    (to - from).mul_add(progress, *from).into(),
+                );
+                mutate(
+                    app_state,
+                    machine_identification_unique,
+                    Value::Object(data),
+                )
+                .await?;
+                if progress >= 1.0 {
+                    return Ok(());
+                }
+                smol::Timer::after(RAMP_INTERVAL).await;
+            }
+        }
+    }
+}
+
+fn check_abort(abort: &AtomicBool) -> Result<(), anyhow::Error> {
+    match abort.load(Ordering::Relaxed) {
+        true => Err(anyhow::anyhow!("[{}::check_abort] Aborted", module_path!())),
+        false => Ok(()),
+    }
+}
+
+async fn mutate(
+    app_state: &Arc<AppState>,
+    machine_identification_unique: &MachineIdentificationUnique,
+    data: Value,
+) -> Result<(), anyhow::Error> {
+    mutate_machine(
+        app_state,
+        MachineMutationBody {
+            machine_identification_unique: machine_identification_unique.clone(),
+            data,
+        },
+    )
+    .await
+    .map_err(|e| anyhow::anyhow!("{}", e))
+}
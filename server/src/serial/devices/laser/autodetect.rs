@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use serialport::Parity;
+
+use super::{
+    driver::{LaserDriver, LaserPortSettings, open_port},
+    mitutoyo::MitutoyoLaserDriver,
+    modbus::ModbusLaserDriver,
+    sikora::SikoraLaserDriver,
+    zumbach::ZumbachLaserDriver,
+};
+
+/// Baud rates tried after the one a driver ships with
+const BAUD_RATES: [u32; 5] = [9_600, 19_200, 38_400, 57_600, 115_200];
+
+const PARITIES: [Parity; 2] = [Parity::None, Parity::Even];
+
+/// Time a gauge gets to answer a probe, shorter than while measuring to keep probing fast
+const PROBE_TIMEOUT: Duration = Duration::from_millis(150);
+
+/// Drivers in the order they are probed
+fn candidates() -> Vec<Box<dyn LaserDriver>> {
+    vec![
+        Box::new(ModbusLaserDriver),
+        Box::new(MitutoyoLaserDriver),
+        Box::new(SikoraLaserDriver),
+        Box::new(ZumbachLaserDriver),
+    ]
+}
+
+/// Line settings probed for a driver, the ones it ships with first
+fn probe_settings(default: LaserPortSettings) -> Vec<LaserPortSettings> {
+    let mut settings = vec![default];
+    for parity in PARITIES {
+        for baud_rate in BAUD_RATES {
+            let candidate = LaserPortSettings { baud_rate, parity };
+            if candidate != default {
+                settings.push(candidate);
+            }
+        }
+    }
+    settings
+}
+
+/// Identifies an unknown gauge by sending the request of every driver with common line
+/// settings until one answers with a valid measurement
+pub fn detect(path: &str) -> Result<(Box<dyn LaserDriver>, LaserPortSettings), anyhow::Error> {
+    // try the settings every driver ships with before cycling through the rest
+    let mut attempts: Vec<(usize, LaserPortSettings)> = vec![];
+    let mut drivers = candidates();
+    for (i, driver) in drivers.iter().enumerate() {
+        attempts.push((i, driver.port_settings()));
+    }
+    for (i, driver) in drivers.iter().enumerate() {
+        for settings in probe_settings(driver.port_settings()).into_iter().skip(1) {
+            attempts.push((i, settings));
+        }
+    }
+
+    for (i, settings) in attempts {
+        let driver = &mut drivers[i];
+        // if the port can't be opened other settings won't help either
+        let mut port = open_port(path, settings, PROBE_TIMEOUT)?;
+        match driver.measure(&mut *port) {
+            Ok(Some(_)) => {
+                tracing::info!(
+                    "Detected {} gauge on {} with {:?}",
+                    driver.name(),
+                    path,
+                    settings
+                );
+                drop(port);
+                return Ok((drivers.swap_remove(i), settings));
+            }
+            Ok(None) => {}
+            Err(e) => tracing::trace!(
+                "Probing {} with {} driver and {:?} failed: {:?}",
+                path,
+                driver.name(),
+                settings,
+                e
+            ),
+        }
+    }
+
+    Err(anyhow!("No known gauge answered on {}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_settings() {
+        let default = LaserPortSettings {
+            baud_rate: 38_400,
+            parity: Parity::None,
+        };
+        let settings = probe_settings(default);
+        assert_eq!(settings[0], default);
+        assert_eq!(settings.len(), BAUD_RATES.len() * PARITIES.len());
+        assert_eq!(settings.iter().filter(|s| **s == default).count(), 1);
+    }
+}
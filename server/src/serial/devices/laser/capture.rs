@@ -0,0 +1,235 @@
+use super::driver::LaserMeasurement;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    sync::{
+        RwLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use uom::si::length::millimeter;
+
+lazy_static! {
+    /// Latest capture of every gauge by port path
+    ///
+    /// Device threads only take the lock while at least one capture records.
+    static ref CAPTURES: RwLock<BTreeMap<String, LaserCapture>> = RwLock::new(BTreeMap::new());
+}
+
+static RECORDING_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Longest capture, a fast gauge fills the memory quickly
+pub const MAX_DURATION: Duration = Duration::from_secs(10);
+
+/// Scans kept per capture, more are dropped
+const MAX_SCANS: usize = 100_000;
+
+#[derive(Deserialize, Serialize, Debug)]
+pub enum Mutation {
+    /// Records every scan of the gauge at `path` for `duration_ms`, replacing its last capture
+    Start { path: String, duration_ms: u64 },
+    /// Drops the capture of the gauge at `path`
+    Clear { path: String },
+}
+
+/// One reading of the gauge as the driver parsed it, lengths in mm
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RawScan {
+    /// time since the start of the capture in µs
+    pub time_us: u64,
+    pub diameter: f64,
+    pub x_axis: Option<f64>,
+    pub y_axis: Option<f64>,
+    pub strands: Vec<f64>,
+}
+
+/// Scans of a gauge at the rate it answers, before the machine samples them in its cycle
+#[derive(Serialize, Debug, Clone)]
+pub struct LaserCapture {
+    pub path: String,
+    /// unix timestamp in milliseconds
+    pub started_at: u64,
+    pub duration_ms: u64,
+    /// whether the duration elapsed, scans are added until then
+    pub complete: bool,
+    /// scans per second
+    pub rate: f64,
+    /// scans dropped after [`MAX_SCANS`]
+    pub dropped: u64,
+    pub scans: Vec<RawScan>,
+    #[serde(skip)]
+    start: Instant,
+}
+
+impl LaserCapture {
+    fn elapsed(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.start)
+            .min(Duration::from_millis(self.duration_ms))
+    }
+
+    /// Completes the capture once its duration elapsed, returns whether it did now
+    fn finish(&mut self, now: Instant) -> bool {
+        if self.complete || self.elapsed(now) < Duration::from_millis(self.duration_ms) {
+            return false;
+        }
+        self.complete = true;
+        RECORDING_COUNT.fetch_sub(1, Ordering::Relaxed);
+        tracing::info!(
+            "Captured {} scans of laser {} at {:.1} scans/s",
+            self.scans.len(),
+            self.path,
+            self.rate
+        );
+        true
+    }
+}
+
+/// Starts a capture of the gauge at `path`, fails while one records
+pub fn start(path: &str, duration: Duration, now: Instant) -> Result<(), anyhow::Error> {
+    if duration.is_zero() || duration > MAX_DURATION {
+        return Err(anyhow::anyhow!(
+            "[{}::start] Capture duration must be between 0 and {:?}, got {:?}",
+            module_path!(),
+            MAX_DURATION,
+            duration
+        ));
+    }
+    let mut captures = CAPTURES.write().unwrap_or_else(|e| e.into_inner());
+    if let Some(capture) = captures.get_mut(path) {
+        capture.finish(now);
+        if !capture.complete {
+            return Err(anyhow::anyhow!(
+                "[{}::start] Laser {} is already capturing",
+                module_path!(),
+                path
+            ));
+        }
+    }
+    captures.insert(
+        path.to_string(),
+        LaserCapture {
+            path: path.to_string(),
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            duration_ms: duration.as_millis() as u64,
+            complete: false,
+            rate: 0.0,
+            dropped: 0,
+            scans: Vec::new(),
+            start: now,
+        },
+    );
+    RECORDING_COUNT.fetch_add(1, Ordering::Relaxed);
+    tracing::info!("Capturing laser {} for {:?}", path, duration);
+    Ok(())
+}
+
+/// Drops the capture of the gauge at `path`, returns if there was one
+pub fn clear(path: &str) -> bool {
+    let mut captures = CAPTURES.write().unwrap_or_else(|e| e.into_inner());
+    match captures.remove(path) {
+        Some(capture) => {
+            if !capture.complete {
+                RECORDING_COUNT.fetch_sub(1, Ordering::Relaxed);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Latest capture of the gauge at `path`, possibly still recording
+pub fn get(path: &str, now: Instant) -> Option<LaserCapture> {
+    let mut captures = CAPTURES.write().unwrap_or_else(|e| e.into_inner());
+    let capture = captures.get_mut(path)?;
+    // a gauge that stopped answering doesn't complete the capture itself
+    capture.finish(now);
+    Some(capture.clone())
+}
+
+/// Adds a scan to the capture of the gauge at `path` if one records
+///
+/// Cheap if no capture records, so the device thread calls it for every measurement.
+pub fn record(path: &str, measurement: &LaserMeasurement, now: Instant) {
+    if RECORDING_COUNT.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+    let mut captures = CAPTURES.write().unwrap_or_else(|e| e.into_inner());
+    let Some(capture) = captures.get_mut(path) else {
+        return;
+    };
+    if capture.complete || capture.finish(now) {
+        return;
+    }
+
+    let elapsed = capture.elapsed(now);
+    if capture.scans.len() < MAX_SCANS {
+        capture.scans.push(RawScan {
+            time_us: elapsed.as_micros() as u64,
+            diameter: measurement.diameter.get::<millimeter>(),
+            x_axis: measurement.x_axis.map(|x| x.get::<millimeter>()),
+            y_axis: measurement.y_axis.map(|y| y.get::<millimeter>()),
+            strands: measurement
+                .strands
+                .iter()
+                .map(|strand| strand.get::<millimeter>())
+                .collect(),
+        });
+    } else {
+        capture.dropped += 1;
+    }
+    if !elapsed.is_zero() {
+        capture.rate =
+            (capture.scans.len() as u64 + capture.dropped) as f64 / elapsed.as_secs_f64();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture() {
+        let path = "/dev/capture-test";
+        let now = Instant::now();
+        let measurement = LaserMeasurement::two_axis(1.74, 1.76);
+
+        // nothing is recorded without a capture
+        record(path, &measurement, now);
+        assert!(get(path, now).is_none());
+
+        assert!(start(path, Duration::from_secs(11), now).is_err());
+        start(path, Duration::from_secs(1), now).unwrap();
+        assert!(start(path, Duration::from_secs(1), now).is_err());
+        for ms in [0, 250, 500, 750] {
+            record(path, &measurement, now + Duration::from_millis(ms));
+        }
+        let capture = get(path, now + Duration::from_millis(800)).unwrap();
+        assert!(!capture.complete);
+        assert_eq!(capture.scans.len(), 4);
+        assert_eq!(capture.scans[3].time_us, 750_000);
+        assert_eq!(capture.scans[3].x_axis, Some(1.74));
+
+        // scans after the duration are not part of the capture
+        record(path, &measurement, now + Duration::from_millis(1000));
+        let capture = get(path, now + Duration::from_millis(1200)).unwrap();
+        assert!(capture.complete);
+        assert_eq!(capture.scans.len(), 4);
+        assert!((capture.rate - 5.33).abs() < 0.01);
+
+        // a complete capture is replaced by the next one
+        start(path, Duration::from_secs(1), now + Duration::from_secs(2)).unwrap();
+        assert!(
+            get(path, now + Duration::from_secs(2))
+                .unwrap()
+                .scans
+                .is_empty()
+        );
+        assert!(clear(path));
+        assert!(!clear(path));
+    }
+}
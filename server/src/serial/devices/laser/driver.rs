@@ -0,0 +1,175 @@
+use std::{fmt::Debug, io::ErrorKind, time::Duration};
+
+use anyhow::anyhow;
+use control_core::serial::SerialDeviceInfo;
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+
+use crate::serial::sniffer::{SerialDirection, sniff};
+use uom::si::{f64::Length, length::millimeter};
+
+/// One reading of a diameter gauge
+#[derive(Debug, Clone, PartialEq)]
+pub struct LaserMeasurement {
+    pub diameter: Length,
+    /// Axes of two axis gauges
+    pub x_axis: Option<Length>,
+    pub y_axis: Option<Length>,
+    /// Diameter of every strand, gauges measuring one strand have a single entry
+    pub strands: Vec<Length>,
+}
+
+impl LaserMeasurement {
+    pub fn single_axis(diameter_mm: f64) -> Self {
+        let diameter = Length::new::<millimeter>(diameter_mm);
+        Self {
+            diameter,
+            x_axis: None,
+            y_axis: None,
+            strands: vec![diameter],
+        }
+    }
+
+    /// The diameter of two axis gauges is the mean of both axes
+    pub fn two_axis(x_mm: f64, y_mm: f64) -> Self {
+        let diameter = Length::new::<millimeter>((x_mm + y_mm) / 2.0);
+        Self {
+            diameter,
+            x_axis: Some(Length::new::<millimeter>(x_mm)),
+            y_axis: Some(Length::new::<millimeter>(y_mm)),
+            strands: vec![diameter],
+        }
+    }
+}
+
+/// Line settings of a gauge port, 8 data bits and 1 stop bit are used by all gauges
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LaserPortSettings {
+    pub baud_rate: u32,
+    pub parity: Parity,
+}
+
+/// Protocol of a diameter gauge
+///
+/// The [`super::Laser`] device owns the port and calls [`LaserDriver::measure`] in a loop,
+/// the driver does the framing and parsing of its gauge.
+pub trait LaserDriver: Debug + Send {
+    /// Name of the gauge family for logs
+    fn name(&self) -> &'static str;
+
+    /// Line settings the gauge ships with
+    fn port_settings(&self) -> LaserPortSettings;
+
+    /// Number of axes the gauge measures
+    fn axes(&self) -> u8 {
+        1
+    }
+
+    /// Time to wait for a response before the request is retried
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(500)
+    }
+
+    /// Requests a measurement and reads the response
+    ///
+    /// Returns `None` if the gauge did not answer.
+    fn measure(
+        &mut self,
+        port: &mut dyn SerialPort,
+    ) -> Result<Option<LaserMeasurement>, anyhow::Error>;
+
+    /// Requests the identity of the gauge, called once after the port was opened
+    ///
+    /// Gauges without an identity request report nothing.
+    fn identify(&mut self, port: &mut dyn SerialPort) -> Result<SerialDeviceInfo, anyhow::Error> {
+        let _ = port;
+        Ok(SerialDeviceInfo::default())
+    }
+}
+
+/// Opens and configures a gauge port
+pub fn open_port(
+    path: &str,
+    settings: LaserPortSettings,
+    timeout: Duration,
+) -> Result<Box<dyn SerialPort>, anyhow::Error> {
+    let mut port = serialport::new(path, settings.baud_rate)
+        .data_bits(DataBits::Eight)
+        .parity(settings.parity)
+        .stop_bits(StopBits::One)
+        .flow_control(FlowControl::None)
+        .timeout(timeout)
+        .open()
+        .map_err(|e| anyhow!("Failed to open port {}: {}", path, e))?;
+
+    port.write_data_terminal_ready(true).ok();
+    port.write_request_to_send(true).ok();
+
+    port.clear(ClearBuffer::All).ok();
+    Ok(port)
+}
+
+/// Writes a request, failing the measurement if the port is gone
+pub fn write_request(port: &mut dyn SerialPort, request: &[u8]) -> Result<(), anyhow::Error> {
+    port.write_all(request)
+        .map_err(|e| anyhow!("Failed to write to port: {}", e))?;
+    sniff(port, SerialDirection::Tx, request, None);
+    Ok(())
+}
+
+/// Reads until `terminator` and returns the frame without it
+///
+/// Returns `None` if nothing arrived before the port timeout.
+pub fn read_frame(
+    port: &mut dyn SerialPort,
+    terminator: u8,
+    max_len: usize,
+) -> Result<Option<Vec<u8>>, anyhow::Error> {
+    let mut frame = Vec::new();
+    let mut byte = [0u8; 1];
+    loop {
+        match port.read(&mut byte) {
+            Ok(0) => return Ok(None),
+            Ok(_) if byte[0] == terminator => {
+                sniff(port, SerialDirection::Rx, &frame, None);
+                return Ok(Some(frame));
+            }
+            Ok(_) => frame.push(byte[0]),
+            Err(e) if e.kind() == ErrorKind::TimedOut && frame.is_empty() => return Ok(None),
+            Err(e) => return Err(anyhow!("Failed to read from port: {}", e)),
+        }
+        if frame.len() > max_len {
+            return Err(anyhow!("Frame longer than {} bytes", max_len));
+        }
+    }
+}
+
+/// Parses a decimal value with optional sign and unit suffix, e.g. `+001.75012mm`
+pub fn parse_decimal(text: &str) -> Result<f64, anyhow::Error> {
+    let text = text.trim();
+    let end = text
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, '+' | '-' | '.')))
+        .unwrap_or(text.len());
+    text[..end]
+        .parse::<f64>()
+        .map_err(|e| anyhow!("Invalid value {:?}: {}", text, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_decimal() {
+        assert_eq!(parse_decimal("+001.75012").unwrap(), 1.75012);
+        assert_eq!(parse_decimal(" 1.75mm ").unwrap(), 1.75);
+        assert_eq!(parse_decimal("-0.5").unwrap(), -0.5);
+        assert!(parse_decimal("ERR").is_err());
+    }
+
+    #[test]
+    fn test_two_axis_diameter() {
+        let measurement = LaserMeasurement::two_axis(1.74, 1.76);
+        assert!((measurement.diameter.get::<millimeter>() - 1.75).abs() < 1e-9);
+        assert_eq!(measurement.x_axis, Some(Length::new::<millimeter>(1.74)));
+    }
+}
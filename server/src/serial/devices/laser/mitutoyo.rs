@@ -0,0 +1,64 @@
+use anyhow::anyhow;
+use serialport::{Parity, SerialPort};
+
+use super::driver::{
+    LaserDriver, LaserMeasurement, LaserPortSettings, parse_decimal, read_frame, write_request,
+};
+
+/// Requests the current measurement
+const REQUEST: &[u8] = b"R\r";
+
+/// Mitutoyo laser scan micrometer
+///
+/// Answers a request with one ASCII line holding the diameter in mm, e.g. `+001.75012\r`.
+/// Errors are reported as a line starting with `E`.
+#[derive(Debug, Default)]
+pub struct MitutoyoLaserDriver;
+
+impl LaserDriver for MitutoyoLaserDriver {
+    fn name(&self) -> &'static str {
+        "Mitutoyo"
+    }
+
+    fn port_settings(&self) -> LaserPortSettings {
+        LaserPortSettings {
+            baud_rate: 9_600,
+            parity: Parity::None,
+        }
+    }
+
+    fn measure(
+        &mut self,
+        port: &mut dyn SerialPort,
+    ) -> Result<Option<LaserMeasurement>, anyhow::Error> {
+        write_request(port, REQUEST)?;
+        read_frame(port, b'\r', 32)?
+            .map(|frame| parse_response(&frame))
+            .transpose()
+    }
+}
+
+fn parse_response(frame: &[u8]) -> Result<LaserMeasurement, anyhow::Error> {
+    let text = String::from_utf8_lossy(frame);
+    let text = text.trim();
+    if text.starts_with('E') {
+        return Err(anyhow!("Gauge reported error {:?}", text));
+    }
+    Ok(LaserMeasurement::single_axis(parse_decimal(text)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uom::si::length::millimeter;
+
+    #[test]
+    fn test_parse_response() {
+        let measurement = parse_response(b"\n+001.75012").unwrap();
+        assert_eq!(measurement.diameter.get::<millimeter>(), 1.75012);
+        assert_eq!(measurement.x_axis, None);
+
+        assert!(parse_response(b"E1").is_err());
+        assert!(parse_response(b"").is_err());
+    }
+}
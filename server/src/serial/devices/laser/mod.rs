@@ -1,140 +1,128 @@
-use std::{
-    io::Write,
-    sync::Arc,
-    thread,
-    time::{Duration, Instant},
-};
+use std::{sync::Arc, thread, time::Instant};
 
+use crate::exporters::prometheus::record_serial_round_trip;
 use crate::machines::{MACHINE_LASER_V1, VENDOR_QITECH};
-use anyhow::anyhow;
+use crate::serial::{firmware, registry::SERIAL_DEVICE_REGISTRY};
 use control_core::{
-    helpers::{
-        hashing::{byte_folding_u16, hash_djb2},
-        retry::retry_n_times,
-    },
+    helpers::retry::retry_n_times,
     machines::identification::{
         DeviceHardwareIdentification, DeviceHardwareIdentificationSerial, DeviceIdentification,
         DeviceMachineIdentification, MachineIdentification, MachineIdentificationUnique,
     },
-    modbus::{self, ModbusRequest, ModbusResponse},
     serial::{
-        SerialDevice, SerialDeviceNew, SerialDeviceNewParams, panic::send_serial_device_panic,
+        SerialDevice, SerialDeviceInfo, SerialDeviceNew, SerialDeviceNewParams,
+        firmware::FirmwareProtocol, panic::send_serial_device_panic,
         serial_detection::SerialDeviceRemoval,
     },
 };
-use serialport::SerialPort;
-use serialport::{ClearBuffer, DataBits, FlowControl, Parity, StopBits};
+use serialport::{ClearBuffer, SerialPort};
 use smol::lock::RwLock;
+use tokio::sync::watch;
 use uom::si::f64::Length;
 
+use autodetect::detect;
+use driver::{LaserDriver, LaserPortSettings, open_port};
+use modbus::ModbusLaserDriver;
+
+pub mod autodetect;
+pub mod capture;
+pub mod driver;
+pub mod mitutoyo;
+pub mod modbus;
+pub mod sikora;
+pub mod zumbach;
+
 /// The struct of Laser Device
+///
+/// The protocol of the gauge is implemented by a [`LaserDriver`], selected by the USB
+/// identification the device is registered with. Measurements are published by the device
+/// thread on a watch channel, readers never wait for the serial port.
 #[derive(Debug)]
 pub struct Laser {
-    pub data: Option<LaserData>,
+    data: watch::Receiver<Option<LaserData>>,
+    /// Identity the gauge reported, queried again after a firmware update
+    info: watch::Receiver<SerialDeviceInfo>,
     pub path: String,
+    /// Name of the driver talking to the gauge
+    pub driver: &'static str,
+    /// Number of axes the gauge measures
+    pub axes: u8,
 }
 
-impl SerialDevice for Laser {}
-
-enum LaserModbusRequsts {
-    ReadDiameter,
+impl SerialDevice for Laser {
+    fn device_info(&self) -> SerialDeviceInfo {
+        self.info.borrow().clone()
+    }
 }
 
-struct LaserDiameterResponse {
-    pub diameter: Length,
-    pub x_axis: Option<Length>,
-    pub y_axis: Option<Length>,
+impl SerialDeviceNew for Laser {
+    fn new_serial(
+        params: &SerialDeviceNewParams,
+    ) -> Result<(DeviceIdentification, Arc<RwLock<Self>>), anyhow::Error> {
+        Self::new_with_driver(params, Box::new(ModbusLaserDriver))
+    }
 }
 
-impl TryFrom<ModbusResponse> for LaserDiameterResponse {
-    type Error = anyhow::Error;
-
-    fn try_from(value: ModbusResponse) -> Result<Self, Self::Error> {
-        if value.data.len() < 3 {
-            return Err(anyhow!(
-                "Invalid response data length: {}",
-                value.data.len()
-            ));
-        }
-        let diameter = u16::from_be_bytes([value.data[1], value.data[2]]) as f64 / 1000.0;
-        // Depending on if its a 2 axis Laser we get more values out of the data
-        let (x_axis, y_axis) = if value.data.len() >= 7 {
-            let x = u16::from_be_bytes([value.data[3], value.data[4]]) as f64 / 1000.0;
-            let y = u16::from_be_bytes([value.data[5], value.data[6]]) as f64 / 1000.0;
-            (
-                Some(Length::new::<uom::si::length::millimeter>(x)),
-                Some(Length::new::<uom::si::length::millimeter>(y)),
-            )
-        } else {
-            (None, None)
-        };
-
-        Ok(Self {
-            diameter: Length::new::<uom::si::length::millimeter>(diameter),
-            x_axis,
-            y_axis,
-        })
+impl Laser {
+    pub fn new_with_driver(
+        params: &SerialDeviceNewParams,
+        driver: Box<dyn LaserDriver>,
+    ) -> Result<(DeviceIdentification, Arc<RwLock<Self>>), anyhow::Error> {
+        let settings = driver.port_settings();
+        Self::new_with_settings(params, driver, settings)
     }
-}
 
-impl From<LaserModbusRequsts> for modbus::ModbusRequest {
-    fn from(request: LaserModbusRequsts) -> Self {
-        match request {
-            LaserModbusRequsts::ReadDiameter => Self {
-                slave_id: 1,
-                function_code: modbus::ModbusFunctionCode::ReadInputRegister,
-                data: vec![(0 >> 8) as u8, (0 & 0xFF) as u8],
-            },
-        }
+    /// Probes the port for a known gauge, for devices without a registered identification
+    pub fn new_autodetect(
+        params: &SerialDeviceNewParams,
+    ) -> Result<(DeviceIdentification, Arc<RwLock<Self>>), anyhow::Error> {
+        let (driver, settings) = detect(&params.path)?;
+        Self::new_with_settings(params, driver, settings)
     }
-}
 
-impl SerialDeviceNew for Laser {
-    fn new_serial(
+    pub fn new_with_settings(
         params: &SerialDeviceNewParams,
+        driver: Box<dyn LaserDriver>,
+        settings: LaserPortSettings,
     ) -> Result<(DeviceIdentification, Arc<RwLock<Self>>), anyhow::Error> {
-        let laser_data = Some(LaserData {
+        let (data_tx, data_rx) = watch::channel(Some(LaserData {
             diameter: Length::new::<uom::si::length::millimeter>(0.0),
             x_axis: None,
             y_axis: None,
+            strands: Vec::new(),
             last_timestamp: Instant::now(),
+        }));
+        let (info_tx, info_rx) = watch::channel(SerialDeviceInfo {
+            model: Some(driver.name().to_string()),
+            ..Default::default()
         });
-        let hash = hash_djb2(params.path.as_bytes());
-        let serial = byte_folding_u16(&hash.to_le_bytes());
-        let device_identification = DeviceIdentification {
-            device_machine_identification: Some(DeviceMachineIdentification {
-                machine_identification_unique: MachineIdentificationUnique {
-                    machine_identification: MachineIdentification {
-                        vendor: VENDOR_QITECH,
-                        machine: MACHINE_LASER_V1,
-                    },
-                    serial,
-                },
-                role: 0,
-            }),
-            device_hardware_identification: DeviceHardwareIdentification::Serial(
-                DeviceHardwareIdentificationSerial {
-                    path: params.path.clone(),
-                },
-            ),
-        };
+        let device_identification = Self::device_identification(params);
+
+        // gauges with a bootloader take firmware updates between measurements
+        let bootloader = params
+            .serial_device_identification
+            .as_ref()
+            .and_then(|id| SERIAL_DEVICE_REGISTRY.firmware_protocol(id));
 
         // Create a new Laser instance
         let _self = Arc::new(RwLock::new(Self {
-            data: laser_data,
+            data: data_rx,
+            info: info_rx,
             path: params.path.clone(),
+            driver: driver.name(),
+            axes: driver.axes(),
         }));
 
         // Spawn the device thread
         let device_thread_panic_tx = params.device_thread_panic_tx.clone();
-        let _self_clone = _self.clone();
         let path = params.path.clone();
         thread::Builder::new()
             .name("laser".to_owned())
             .spawn(move || {
                 send_serial_device_panic(path.clone(), device_thread_panic_tx.clone());
                 smol::block_on(async {
-                    let process_result = Self::process(_self_clone).await;
+                    let process_result =
+                        Self::process(&path, data_tx, info_tx, driver, settings, bootloader).await;
 
                     let removal = match process_result {
                         Ok(_) => SerialDeviceRemoval::Disconnect(path),
@@ -151,97 +139,142 @@ impl SerialDeviceNew for Laser {
 
         Ok((device_identification, _self))
     }
+
+    /// Laser without a gauge, its measurements are published by the simulation
+    pub fn new_simulated(
+        params: &SerialDeviceNewParams,
+    ) -> (
+        DeviceIdentification,
+        Arc<RwLock<Self>>,
+        watch::Sender<Option<LaserData>>,
+    ) {
+        let (data_tx, data_rx) = watch::channel(None);
+        let (_, info_rx) = watch::channel(SerialDeviceInfo {
+            model: Some("simulation".to_string()),
+            ..Default::default()
+        });
+        let laser = Arc::new(RwLock::new(Self {
+            data: data_rx,
+            info: info_rx,
+            path: params.path.clone(),
+            driver: "simulation",
+            axes: 1,
+        }));
+        (Self::device_identification(params), laser, data_tx)
+    }
+
+    fn device_identification(params: &SerialDeviceNewParams) -> DeviceIdentification {
+        // stays the same if the OS assigns another path to the gauge
+        let serial = params.machine_serial();
+        DeviceIdentification {
+            device_machine_identification: Some(DeviceMachineIdentification {
+                machine_identification_unique: MachineIdentificationUnique {
+                    machine_identification: MachineIdentification {
+                        vendor: VENDOR_QITECH,
+                        machine: MACHINE_LASER_V1,
+                    },
+                    serial,
+                },
+                role: 0,
+            }),
+            device_hardware_identification: DeviceHardwareIdentification::Serial(
+                DeviceHardwareIdentificationSerial {
+                    path: params.path.clone(),
+                    usb_serial_number: params.usb_serial_number.clone(),
+                },
+            ),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct LaserData {
+    /// Diameter of the first strand
     pub diameter: Length,
     pub x_axis: Option<Length>,
     pub y_axis: Option<Length>,
+    /// Diameter of every strand of multi-strand lines
+    pub strands: Vec<Length>,
     pub last_timestamp: Instant,
 }
 
 impl Laser {
-    pub async fn get_diameter(&self) -> Result<Length, String> {
-        match &self.data {
-            Some(data) => Ok(data.diameter),
-            None => Err("No data from Laser".to_string()),
-        }
+    /// Receiver of the latest measurement, `None` while the gauge has no data
+    ///
+    /// The receiver reports the channel as closed once the device thread stopped.
+    pub fn subscribe(&self) -> watch::Receiver<Option<LaserData>> {
+        self.data.clone()
     }
 
-    pub async fn get_x(&self) -> Result<Option<Length>, String> {
-        match &self.data {
-            Some(data) => Ok(data.x_axis),
-            None => Err("No data from Laser".to_string()),
-        }
+    pub fn get_data(&self) -> Option<LaserData> {
+        self.data.borrow().clone()
     }
 
-    pub async fn get_y(&self) -> Result<Option<Length>, String> {
-        match &self.data {
-            Some(data) => Ok(data.y_axis),
-            None => Err("No data from Laser".to_string()),
+    /// Caches the identity of the gauge, a gauge without one still measures
+    fn identify(
+        path: &str,
+        info_tx: &watch::Sender<SerialDeviceInfo>,
+        driver: &mut dyn LaserDriver,
+        port: &mut dyn SerialPort,
+    ) {
+        match driver.identify(port) {
+            Ok(info) => {
+                tracing::info!("Laser on {} identified as {:?}", path, info);
+                info_tx.send_modify(|cached| {
+                    // the driver name stays the model of gauges that don't report one
+                    cached.model = info.model.or_else(|| cached.model.take());
+                    cached.firmware_version = info.firmware_version;
+                    cached.serial_number = info.serial_number;
+                });
+            }
+            Err(e) => tracing::warn!("Failed to identify the laser on {}: {:?}", path, e),
         }
+        port.clear(ClearBuffer::All).ok();
     }
 
-    pub async fn get_data(&self) -> Option<LaserData> {
-        self.data.clone()
-    }
-
-    async fn process(_self: Arc<RwLock<Self>>) -> Result<(), anyhow::Error> {
-        let path = {
-            let read_guard = _self.read().await;
-            read_guard.path.clone()
-        };
-
-        let request: ModbusRequest = LaserModbusRequsts::ReadDiameter.into();
-        let request_buffer: Vec<u8> = request.into();
+    async fn process(
+        path: &str,
+        data_tx: watch::Sender<Option<LaserData>>,
+        info_tx: watch::Sender<SerialDeviceInfo>,
+        mut driver: Box<dyn LaserDriver>,
+        settings: LaserPortSettings,
+        mut bootloader: Option<Box<dyn FirmwareProtocol>>,
+    ) -> Result<(), anyhow::Error> {
+        tracing::info!(
+            "Using {} laser driver on {} with {:?}",
+            driver.name(),
+            path,
+            settings
+        );
 
         // port configuration
-        let mut port: Box<dyn SerialPort> = serialport::new(&path, 38_400)
-            .data_bits(DataBits::Eight)
-            .parity(Parity::None)
-            .stop_bits(StopBits::One)
-            .flow_control(FlowControl::None)
-            .timeout(Duration::from_millis(500)) // start with something forgiving
-            .open()
-            .map_err(|e| anyhow!("Failed to open port {}: {}", path, e))?;
-
-        port.write_data_terminal_ready(true).ok();
-        port.write_request_to_send(true).ok();
-
-        port.clear(ClearBuffer::All).ok();
+        let mut port = open_port(path, settings, driver.timeout())?;
+        Self::identify(path, &info_tx, driver.as_mut(), &mut *port);
 
         loop {
-            // send diameter request
-            let response = retry_n_times(10, || {
-                if let Err(e) = port.write_all(&request_buffer) {
-                    return Err(anyhow!("Failed to write to port: {}", e));
+            if let Some(protocol) = bootloader.as_mut() {
+                if firmware::run_pending(path, &mut port, protocol.as_mut()) {
+                    // the gauge restarted, drop what it sent while booting
+                    port.clear(ClearBuffer::All).ok();
+                    Self::identify(path, &info_tx, driver.as_mut(), &mut *port);
                 }
+            }
 
-                // wait for the response
-                std::thread::sleep(modbus::calculate_modbus_rtu_timeout(
-                    8,
-                    Duration::from_millis(10),
-                    38400,
-                    8,
-                ));
-
-                modbus::receive_data_modbus(&mut *port)?
-                    .map(ModbusResponse::try_from)
-                    .transpose()
-            })?;
+            // request a measurement
+            let request_start = Instant::now();
+            let measurement = retry_n_times(10, || driver.measure(&mut *port))?;
 
-            if let Some(diameter_response) = response {
-                // try to convert it to a LaserDiameterResponse
-                let diameter_response = LaserDiameterResponse::try_from(diameter_response)?;
-                // save the diameter
-                let mut self_guard = _self.write().await;
-                self_guard.data = Some(LaserData {
-                    diameter: diameter_response.diameter,
-                    x_axis: diameter_response.x_axis,
-                    y_axis: diameter_response.y_axis,
+            if let Some(measurement) = measurement {
+                record_serial_round_trip(path, request_start.elapsed());
+                capture::record(path, &measurement, Instant::now());
+                // publish the diameter
+                data_tx.send_replace(Some(LaserData {
+                    diameter: measurement.diameter,
+                    x_axis: measurement.x_axis,
+                    y_axis: measurement.y_axis,
+                    strands: measurement.strands,
                     last_timestamp: Instant::now(),
-                });
+                }));
             }
         }
     }
@@ -0,0 +1,187 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use control_core::{
+    modbus::{self, ModbusRequest, ModbusResponse},
+    serial::SerialDeviceInfo,
+};
+use serialport::{Parity, SerialPort};
+use uom::si::{f64::Length, length::millimeter};
+
+use super::driver::{LaserDriver, LaserMeasurement, LaserPortSettings, write_request};
+use crate::serial::sniffer::{SerialDirection, sniff};
+
+const BAUD_RATE: u32 = 38_400;
+/// Input registers of the identity: firmware major, minor and patch, serial number as u32
+const IDENTITY_REGISTER: u16 = 0x0010;
+const IDENTITY_REGISTERS: u16 = 5;
+
+/// QiTech laser, reads the diameter input registers over Modbus RTU
+#[derive(Debug, Default)]
+pub struct ModbusLaserDriver;
+
+enum LaserModbusRequsts {
+    ReadDiameter,
+    ReadIdentity,
+}
+
+impl From<LaserModbusRequsts> for ModbusRequest {
+    fn from(request: LaserModbusRequsts) -> Self {
+        match request {
+            LaserModbusRequsts::ReadDiameter => Self {
+                slave_id: 1,
+                function_code: modbus::ModbusFunctionCode::ReadInputRegister,
+                data: vec![(0 >> 8) as u8, (0 & 0xFF) as u8],
+            },
+            LaserModbusRequsts::ReadIdentity => {
+                let mut data = IDENTITY_REGISTER.to_be_bytes().to_vec();
+                data.extend_from_slice(&IDENTITY_REGISTERS.to_be_bytes());
+                Self {
+                    slave_id: 1,
+                    function_code: modbus::ModbusFunctionCode::ReadInputRegister,
+                    data,
+                }
+            }
+        }
+    }
+}
+
+impl TryFrom<ModbusResponse> for LaserMeasurement {
+    type Error = anyhow::Error;
+
+    fn try_from(value: ModbusResponse) -> Result<Self, Self::Error> {
+        if value.data.len() < 3 {
+            return Err(anyhow!(
+                "Invalid response data length: {}",
+                value.data.len()
+            ));
+        }
+        let diameter = u16::from_be_bytes([value.data[1], value.data[2]]) as f64 / 1000.0;
+        // Depending on if its a 2 axis Laser we get more values out of the data
+        let (x_axis, y_axis) = if value.data.len() >= 7 {
+            let x = u16::from_be_bytes([value.data[3], value.data[4]]) as f64 / 1000.0;
+            let y = u16::from_be_bytes([value.data[5], value.data[6]]) as f64 / 1000.0;
+            (
+                Some(Length::new::<millimeter>(x)),
+                Some(Length::new::<millimeter>(y)),
+            )
+        } else {
+            (None, None)
+        };
+
+        let diameter = Length::new::<millimeter>(diameter);
+        Ok(Self {
+            diameter,
+            x_axis,
+            y_axis,
+            strands: vec![diameter],
+        })
+    }
+}
+
+/// Firmware version and serial number from the identity registers
+fn parse_identity(response: &ModbusResponse) -> Result<SerialDeviceInfo, anyhow::Error> {
+    let len = 1 + 2 * IDENTITY_REGISTERS as usize;
+    if response.data.len() < len {
+        return Err(anyhow!(
+            "Invalid identity response length: {}",
+            response.data.len()
+        ));
+    }
+    let register =
+        |i: usize| u16::from_be_bytes([response.data[1 + 2 * i], response.data[2 + 2 * i]]);
+    let serial_number = (u32::from(register(3)) << 16) | u32::from(register(4));
+    Ok(SerialDeviceInfo {
+        model: None,
+        firmware_version: Some(format!("{}.{}.{}", register(0), register(1), register(2))),
+        serial_number: Some(serial_number.to_string()),
+    })
+}
+
+impl ModbusLaserDriver {
+    /// Sends a request and reads the response, `None` if the gauge did not answer
+    fn request(
+        port: &mut dyn SerialPort,
+        request: LaserModbusRequsts,
+    ) -> Result<Option<ModbusResponse>, anyhow::Error> {
+        let request: ModbusRequest = request.into();
+        let request_buffer: Vec<u8> = request.into();
+        write_request(port, &request_buffer)?;
+
+        // wait for the response
+        std::thread::sleep(modbus::calculate_modbus_rtu_timeout(
+            8,
+            Duration::from_millis(10),
+            port.baud_rate().unwrap_or(BAUD_RATE),
+            8,
+        ));
+
+        let mut buf = [0u8; 256];
+        let data_length = port.read(&mut buf)?;
+        if data_length == 0 {
+            return Ok(None);
+        }
+        let raw = &buf[..data_length];
+        sniff(
+            port,
+            SerialDirection::Rx,
+            raw,
+            Some(modbus::is_crc_valid(raw)),
+        );
+
+        ModbusResponse::try_from(modbus::validate_modbus_response(raw.to_vec())?).map(Some)
+    }
+}
+
+impl LaserDriver for ModbusLaserDriver {
+    fn name(&self) -> &'static str {
+        "QiTech Modbus"
+    }
+
+    fn port_settings(&self) -> LaserPortSettings {
+        LaserPortSettings {
+            baud_rate: BAUD_RATE,
+            parity: Parity::None,
+        }
+    }
+
+    fn measure(
+        &mut self,
+        port: &mut dyn SerialPort,
+    ) -> Result<Option<LaserMeasurement>, anyhow::Error> {
+        Self::request(port, LaserModbusRequsts::ReadDiameter)?
+            .map(LaserMeasurement::try_from)
+            .transpose()
+    }
+
+    fn identify(&mut self, port: &mut dyn SerialPort) -> Result<SerialDeviceInfo, anyhow::Error> {
+        match Self::request(port, LaserModbusRequsts::ReadIdentity)? {
+            Some(response) => parse_identity(&response),
+            None => Err(anyhow!("Laser did not answer the identity request")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_identity() {
+        let response = ModbusResponse {
+            slave_id: 1,
+            function_code: modbus::ModbusFunctionCode::ReadInputRegister,
+            data: vec![10, 0, 1, 0, 4, 0, 2, 0x00, 0x01, 0xE2, 0x40],
+            crc: 0,
+        };
+        let info = parse_identity(&response).unwrap();
+        assert_eq!(info.firmware_version.as_deref(), Some("1.4.2"));
+        assert_eq!(info.serial_number.as_deref(), Some("123456"));
+
+        let short = ModbusResponse {
+            data: vec![2, 0, 1],
+            ..response
+        };
+        assert!(parse_identity(&short).is_err());
+    }
+}
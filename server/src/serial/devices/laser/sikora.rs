@@ -0,0 +1,96 @@
+use anyhow::anyhow;
+use serialport::{Parity, SerialPort};
+use uom::si::{f64::Length, length::millimeter};
+
+use super::driver::{
+    LaserDriver, LaserMeasurement, LaserPortSettings, parse_decimal, read_frame, write_request,
+};
+
+/// Requests the diameter of both axes
+const REQUEST: &[u8] = b"?D\r";
+
+/// Sikora two axis diameter gauge
+///
+/// Answers a request with one ASCII line holding the axes in mm, e.g. `X=1.7501 Y=1.7498\r`.
+/// The line may carry the averaged diameter as `D=`, otherwise the mean of the axes is used.
+#[derive(Debug, Default)]
+pub struct SikoraLaserDriver;
+
+impl LaserDriver for SikoraLaserDriver {
+    fn name(&self) -> &'static str {
+        "Sikora"
+    }
+
+    fn axes(&self) -> u8 {
+        2
+    }
+
+    fn port_settings(&self) -> LaserPortSettings {
+        LaserPortSettings {
+            baud_rate: 9_600,
+            parity: Parity::None,
+        }
+    }
+
+    fn measure(
+        &mut self,
+        port: &mut dyn SerialPort,
+    ) -> Result<Option<LaserMeasurement>, anyhow::Error> {
+        write_request(port, REQUEST)?;
+        read_frame(port, b'\r', 64)?
+            .map(|frame| parse_response(&frame))
+            .transpose()
+    }
+}
+
+fn parse_response(frame: &[u8]) -> Result<LaserMeasurement, anyhow::Error> {
+    let text = String::from_utf8_lossy(frame);
+    let mut diameter = None;
+    let mut x = None;
+    let mut y = None;
+    for field in text.split_whitespace() {
+        let Some((key, value)) = field.split_once('=') else {
+            continue;
+        };
+        match key {
+            "D" => diameter = Some(parse_decimal(value)?),
+            "X" => x = Some(parse_decimal(value)?),
+            "Y" => y = Some(parse_decimal(value)?),
+            _ => {}
+        }
+    }
+
+    match (x, y) {
+        (Some(x), Some(y)) => {
+            let mut measurement = LaserMeasurement::two_axis(x, y);
+            if let Some(diameter) = diameter {
+                measurement.diameter = Length::new::<millimeter>(diameter);
+            }
+            Ok(measurement)
+        }
+        _ => diameter
+            .map(LaserMeasurement::single_axis)
+            .ok_or_else(|| anyhow!("No diameter in response {:?}", text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response() {
+        let measurement = parse_response(b"X=1.7400 Y=1.7600").unwrap();
+        assert!((measurement.diameter.get::<millimeter>() - 1.75).abs() < 1e-9);
+        assert_eq!(measurement.y_axis.unwrap().get::<millimeter>(), 1.76);
+
+        let measurement = parse_response(b"D=1.7520 X=1.7400 Y=1.7600").unwrap();
+        assert_eq!(measurement.diameter.get::<millimeter>(), 1.752);
+
+        let measurement = parse_response(b"D=1.7520").unwrap();
+        assert_eq!(measurement.x_axis, None);
+
+        assert!(parse_response(b"STATUS=0").is_err());
+        assert!(parse_response(b"X=abc Y=1.0").is_err());
+    }
+}
@@ -0,0 +1,86 @@
+use anyhow::anyhow;
+use serialport::{Parity, SerialPort};
+
+use super::driver::{
+    LaserDriver, LaserMeasurement, LaserPortSettings, parse_decimal, read_frame, write_request,
+};
+
+const STX: u8 = 0x02;
+const ETX: u8 = 0x03;
+
+/// Requests the current measurement
+const REQUEST: &[u8] = &[STX, b'M', ETX];
+
+/// Zumbach diameter gauge
+///
+/// Answers a request with a frame between `STX` and `ETX` holding the axes in mm separated
+/// by `;`, one value for single axis and two values for two axis heads.
+#[derive(Debug, Default)]
+pub struct ZumbachLaserDriver;
+
+impl LaserDriver for ZumbachLaserDriver {
+    fn name(&self) -> &'static str {
+        "Zumbach"
+    }
+
+    fn axes(&self) -> u8 {
+        2
+    }
+
+    fn port_settings(&self) -> LaserPortSettings {
+        LaserPortSettings {
+            baud_rate: 19_200,
+            parity: Parity::None,
+        }
+    }
+
+    fn measure(
+        &mut self,
+        port: &mut dyn SerialPort,
+    ) -> Result<Option<LaserMeasurement>, anyhow::Error> {
+        write_request(port, REQUEST)?;
+        read_frame(port, ETX, 64)?
+            .map(|frame| parse_response(&frame))
+            .transpose()
+    }
+}
+
+fn parse_response(frame: &[u8]) -> Result<LaserMeasurement, anyhow::Error> {
+    // skip anything before the frame start, e.g. the rest of a previous response
+    let start = frame
+        .iter()
+        .rposition(|byte| *byte == STX)
+        .ok_or_else(|| anyhow!("Response without frame start"))?;
+    let text = String::from_utf8_lossy(&frame[start + 1..]);
+    let values = text
+        .split(';')
+        .map(parse_decimal)
+        .collect::<Result<Vec<_>, _>>()?;
+    match values.as_slice() {
+        [diameter] => Ok(LaserMeasurement::single_axis(*diameter)),
+        [x, y] => Ok(LaserMeasurement::two_axis(*x, *y)),
+        _ => Err(anyhow!(
+            "Unexpected number of values in response {:?}",
+            text
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uom::si::length::millimeter;
+
+    #[test]
+    fn test_parse_response() {
+        let measurement = parse_response(b"\x021.7502").unwrap();
+        assert_eq!(measurement.diameter.get::<millimeter>(), 1.7502);
+
+        let measurement = parse_response(b"garbage\x021.7400;1.7600").unwrap();
+        assert!((measurement.diameter.get::<millimeter>() - 1.75).abs() < 1e-9);
+        assert_eq!(measurement.x_axis.unwrap().get::<millimeter>(), 1.74);
+
+        assert!(parse_response(b"1.75").is_err());
+        assert!(parse_response(b"\x021;2;3").is_err());
+    }
+}
@@ -1,7 +1,6 @@
 use std::sync::Arc;
 
 use control_core::{
-    helpers::hashing::{byte_folding_u16, hash_djb2},
     machines::identification::{
         DeviceHardwareIdentification, DeviceHardwareIdentificationSerial, DeviceIdentification,
         DeviceMachineIdentification, MachineIdentification, MachineIdentificationUnique,
@@ -28,9 +27,8 @@ impl SerialDeviceNew for MockSerialDevice {
     where
         Self: Sized,
     {
-        // Generate a unique serial number based on the path
-        let hash = hash_djb2(params.path.as_bytes());
-        let serial = byte_folding_u16(&hash.to_le_bytes());
+        // Generate a unique serial number based on the device
+        let serial = params.machine_serial();
 
         let device_identification = DeviceIdentification {
             device_machine_identification: Some(DeviceMachineIdentification {
@@ -46,6 +44,7 @@ impl SerialDeviceNew for MockSerialDevice {
             device_hardware_identification: DeviceHardwareIdentification::Serial(
                 DeviceHardwareIdentificationSerial {
                     path: params.path.clone(),
+                    usb_serial_number: params.usb_serial_number.clone(),
                 },
             ),
         };
@@ -0,0 +1,69 @@
+use super::jobs;
+use crate::{
+    app_state::AppState,
+    serial::{registry::SERIAL_DEVICE_REGISTRY, sniffer::api::DiagnosticsNamespaceEvents},
+};
+use control_core::{
+    serial::{SerialDeviceIdentification, firmware::FirmwareProgress},
+    socketio::{event::BuildEvent, namespace::NamespaceCacheingLogic},
+};
+use control_core_derive::BuildEvent;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::Arc};
+
+#[derive(Serialize, Debug, Clone, BuildEvent)]
+pub struct FirmwareEvent {
+    /// Updates by port path
+    pub jobs: BTreeMap<String, FirmwareProgress>,
+    /// Paths of the detected devices that can be updated
+    pub updatable: Vec<String>,
+}
+
+/// Parameters of an upload, the image is the request body
+#[derive(Deserialize, Serialize, Debug)]
+pub struct FirmwareUploadQuery {
+    pub path: String,
+    pub version: String,
+}
+
+/// Detected devices whose identification has a registered bootloader protocol
+pub async fn updatable_ports(app_state: &Arc<AppState>) -> Vec<String> {
+    let mut ports: Vec<_> = app_state
+        .serial_setup
+        .read()
+        .await
+        .serial_detection
+        .ports
+        .iter()
+        .filter(|(_, (usb_port_info, _, _))| {
+            SERIAL_DEVICE_REGISTRY
+                .firmware
+                .contains_key(&SerialDeviceIdentification {
+                    vendor_id: usb_port_info.vid,
+                    product_id: usb_port_info.pid,
+                })
+        })
+        .map(|(path, _)| path.clone())
+        .collect();
+    ports.sort();
+    ports
+}
+
+pub async fn firmware_event(app_state: &Arc<AppState>) -> FirmwareEvent {
+    FirmwareEvent {
+        jobs: jobs(),
+        updatable: updatable_ports(app_state).await,
+    }
+}
+
+/// Emits the firmware updates to the diagnostics namespace
+pub async fn emit_firmware(app_state: &Arc<AppState>) {
+    let event = firmware_event(app_state).await.build();
+    let diagnostics_namespace = &mut app_state
+        .socketio_setup
+        .namespaces
+        .write()
+        .await
+        .diagnostics_namespace;
+    diagnostics_namespace.emit(DiagnosticsNamespaceEvents::Firmware(event));
+}
@@ -0,0 +1,38 @@
+use super::{api::emit_firmware, queue};
+use crate::{
+    app_state::AppState,
+    panic::{PanicDetails, send_panic},
+};
+use smol::channel::Sender;
+use std::sync::Arc;
+
+/// Forwards the progress of firmware updates to the diagnostics namespace
+pub fn init_firmware(
+    thread_panic_tx: Sender<PanicDetails>,
+    app_state: Arc<AppState>,
+) -> Result<(), anyhow::Error> {
+    smol::block_on(emit_firmware(&app_state));
+
+    let queue = queue();
+    std::thread::Builder::new()
+        .name("serial-firmware".to_owned())
+        .spawn(move || {
+            send_panic(thread_panic_tx);
+            smol::block_on(async {
+                while queue.recv().await.is_ok() {
+                    // one event carries all updates, skip progress that piled up meanwhile
+                    while queue.try_recv().is_ok() {}
+                    emit_firmware(&app_state).await;
+                }
+            });
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_firmware] Failed to spawn serial firmware thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    Ok(())
+}
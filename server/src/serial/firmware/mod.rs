@@ -0,0 +1,181 @@
+use control_core::serial::firmware::{
+    FirmwareImage, FirmwarePort, FirmwareProgress, FirmwareProtocol, update_firmware,
+};
+use lazy_static::lazy_static;
+use smol::channel::{Receiver, Sender};
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+pub mod api;
+pub mod init;
+pub mod qitech;
+
+/// Progress updates waiting for the firmware thread, the final state is also kept in the jobs
+const PROGRESS_QUEUE_LEN: usize = 1024;
+
+lazy_static! {
+    /// Updates by port path, finished updates stay until the next update of the device
+    static ref JOBS: RwLock<BTreeMap<String, FirmwareJob>> = RwLock::new(BTreeMap::new());
+    static ref QUEUE: (Sender<String>, Receiver<String>) =
+        smol::channel::bounded(PROGRESS_QUEUE_LEN);
+}
+
+/// Updates not yet picked up by their device thread
+static PENDING_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug)]
+struct FirmwareJob {
+    /// Taken by the device thread when it starts the update
+    image: Option<Arc<FirmwareImage>>,
+    progress: FirmwareProgress,
+}
+
+/// Queues `image` for the device at `path`
+///
+/// The device thread installs it between two of its regular requests, so the device stays
+/// attached to its machine.
+pub fn schedule(path: &str, image: FirmwareImage) -> Result<(), anyhow::Error> {
+    let mut jobs = JOBS.write().unwrap_or_else(|e| e.into_inner());
+    if let Some(job) = jobs.get(path) {
+        if !job.progress.stage.is_finished() {
+            return Err(anyhow::anyhow!(
+                "[{}::schedule] Device {} is already being updated",
+                module_path!(),
+                path
+            ));
+        }
+    }
+    tracing::info!(
+        "Scheduled firmware {} ({} bytes) for {}",
+        image.version,
+        image.data.len(),
+        path
+    );
+    jobs.insert(
+        path.to_string(),
+        FirmwareJob {
+            progress: FirmwareProgress::new(&image),
+            image: Some(Arc::new(image)),
+        },
+    );
+    PENDING_COUNT.fetch_add(1, Ordering::Relaxed);
+    let _ = QUEUE.0.try_send(path.to_string());
+    Ok(())
+}
+
+/// Latest progress of all updates by port path
+pub fn jobs() -> BTreeMap<String, FirmwareProgress> {
+    JOBS.read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(path, job)| (path.clone(), job.progress.clone()))
+        .collect()
+}
+
+/// Forgets the update of a device that was unplugged before it picked the update up
+pub fn cancel(path: &str) {
+    let mut jobs = JOBS.write().unwrap_or_else(|e| e.into_inner());
+    if let Some(job) = jobs.get(path) {
+        if job.image.is_some() {
+            jobs.remove(path);
+            PENDING_COUNT.fetch_sub(1, Ordering::Relaxed);
+            let _ = QUEUE.0.try_send(path.to_string());
+        }
+    }
+}
+
+fn take(path: &str) -> Option<Arc<FirmwareImage>> {
+    let mut jobs = JOBS.write().unwrap_or_else(|e| e.into_inner());
+    let image = jobs.get_mut(path)?.image.take()?;
+    PENDING_COUNT.fetch_sub(1, Ordering::Relaxed);
+    Some(image)
+}
+
+fn report(path: &str, progress: &FirmwareProgress) {
+    if let Some(job) = JOBS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_mut(path)
+    {
+        job.progress = progress.clone();
+    }
+    // never block the device thread, the namespace catches up with the next update
+    let _ = QUEUE.0.try_send(path.to_string());
+}
+
+/// Installs a scheduled update of the device at `path`, returns if one was installed
+///
+/// Cheap if no update is scheduled, so device threads call it on every cycle.
+pub fn run_pending(
+    path: &str,
+    port: &mut dyn FirmwarePort,
+    protocol: &mut dyn FirmwareProtocol,
+) -> bool {
+    if PENDING_COUNT.load(Ordering::Relaxed) == 0 {
+        return false;
+    }
+    let Some(image) = take(path) else {
+        return false;
+    };
+
+    tracing::info!(
+        "Installing firmware {} on {} with the {} bootloader",
+        image.version,
+        path,
+        protocol.name()
+    );
+    let result = update_firmware(protocol, port, &image, &mut |progress| {
+        report(path, progress)
+    });
+    match result {
+        Ok(_) => tracing::info!("Installed firmware {} on {}", image.version, path),
+        Err(e) => tracing::error!(
+            "Failed to install firmware {} on {}: {:?}",
+            image.version,
+            path,
+            e
+        ),
+    }
+    true
+}
+
+/// Paths of devices whose progress changed
+pub(crate) fn queue() -> Receiver<String> {
+    QUEUE.1.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use control_core::serial::firmware::FirmwareUpdateStage;
+
+    #[test]
+    fn test_schedule() {
+        let path = "/dev/test-firmware";
+        let image = FirmwareImage {
+            version: "2.0.0".to_string(),
+            data: vec![1, 2, 3],
+        };
+
+        schedule(path, image.clone()).unwrap();
+        assert!(schedule(path, image.clone()).is_err());
+        assert_eq!(jobs()[path].stage, FirmwareUpdateStage::Pending);
+
+        assert_eq!(take(path), Some(Arc::new(image.clone())));
+        assert_eq!(take(path), None);
+
+        // a finished update can be followed by the next one
+        let mut progress = FirmwareProgress::new(&image);
+        progress.stage = FirmwareUpdateStage::RolledBack;
+        report(path, &progress);
+        assert_eq!(jobs()[path].stage, FirmwareUpdateStage::RolledBack);
+        schedule(path, image).unwrap();
+        cancel(path);
+        assert!(!jobs().contains_key(path));
+    }
+}
@@ -0,0 +1,254 @@
+use std::{io::Read, time::Duration};
+
+use anyhow::{Error, anyhow};
+use control_core::{
+    modbus::{self, ModbusRequest},
+    serial::firmware::{FirmwarePort, FirmwareProtocol},
+};
+
+/// Start of every bootloader frame
+const FRAME_START: u8 = 0x7E;
+const ACK: u8 = 0x06;
+const NAK: u8 = 0x15;
+
+const CMD_HELLO: u8 = 0x01;
+const CMD_WRITE: u8 = 0x03;
+const CMD_CRC: u8 = 0x04;
+const CMD_COMMIT: u8 = 0x05;
+const CMD_ROLLBACK: u8 = 0x06;
+
+/// Holding register the application firmware jumps into the bootloader on
+const BOOTLOADER_REGISTER: u16 = 0x0100;
+const BOOTLOADER_MAGIC: u16 = 0xB007;
+
+/// Time the device needs to restart into the bootloader
+const BOOTLOADER_START: Duration = Duration::from_millis(500);
+
+/// Bootloader of QiTech serial boards, e.g. the laser
+///
+/// Frames are `0x7E, command, length (u16 LE), payload, CRC-16/MODBUS (LE)` with the CRC
+/// over command, length and payload. The device answers every frame with ACK or NAK as
+/// command and an optional payload.
+#[derive(Debug, Default)]
+pub struct QiTechBootloader;
+
+impl QiTechBootloader {
+    fn encode(command: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![FRAME_START, command];
+        frame.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+        frame.extend_from_slice(payload);
+        let crc = modbus::modbus_crc16(&frame[1..]);
+        frame.extend_from_slice(&crc.to_le_bytes());
+        frame
+    }
+
+    /// Reads a frame, returns command and payload
+    fn decode<R: Read + ?Sized>(port: &mut R) -> Result<(u8, Vec<u8>), Error> {
+        let mut header = [0u8; 4];
+        port.read_exact(&mut header)
+            .map_err(|e| anyhow!("No response from bootloader: {}", e))?;
+        if header[0] != FRAME_START {
+            return Err(anyhow!("Invalid frame start {:#04x}", header[0]));
+        }
+        let len = u16::from_le_bytes([header[2], header[3]]) as usize;
+        let mut rest = vec![0u8; len + 2];
+        port.read_exact(&mut rest)
+            .map_err(|e| anyhow!("Incomplete frame from bootloader: {}", e))?;
+
+        let (payload, crc) = rest.split_at(len);
+        let mut checked = header[1..].to_vec();
+        checked.extend_from_slice(payload);
+        if modbus::modbus_crc16(&checked) != u16::from_le_bytes([crc[0], crc[1]]) {
+            return Err(anyhow!("Invalid CRC in bootloader frame"));
+        }
+        Ok((header[1], payload.to_vec()))
+    }
+
+    /// Sends a command and returns the payload of the ACK
+    fn request(port: &mut dyn FirmwarePort, command: u8, payload: &[u8]) -> Result<Vec<u8>, Error> {
+        port.write_all(&Self::encode(command, payload))
+            .map_err(|e| anyhow!("Failed to write to port: {}", e))?;
+        match Self::decode(port)? {
+            (ACK, payload) => Ok(payload),
+            (NAK, payload) => Err(anyhow!(
+                "Bootloader rejected command {:#04x}: {}",
+                command,
+                String::from_utf8_lossy(&payload)
+            )),
+            (other, _) => Err(anyhow!("Unexpected bootloader response {:#04x}", other)),
+        }
+    }
+}
+
+impl FirmwareProtocol for QiTechBootloader {
+    fn name(&self) -> &'static str {
+        "QiTech"
+    }
+
+    fn chunk_size(&self) -> usize {
+        256
+    }
+
+    fn enter_bootloader(&mut self, port: &mut dyn FirmwarePort) -> Result<String, Error> {
+        // the application only speaks Modbus, writing the magic value restarts into the bootloader
+        let mut data = BOOTLOADER_REGISTER.to_be_bytes().to_vec();
+        data.extend_from_slice(&BOOTLOADER_MAGIC.to_be_bytes());
+        let request: Vec<u8> = ModbusRequest {
+            slave_id: 1,
+            function_code: modbus::ModbusFunctionCode::PresetHoldingRegister,
+            data,
+        }
+        .into();
+        port.write_all(&request)
+            .map_err(|e| anyhow!("Failed to write to port: {}", e))?;
+        std::thread::sleep(BOOTLOADER_START);
+
+        // drop the Modbus echo and anything else sent while restarting
+        let mut discard = [0u8; 256];
+        let _ = port.read(&mut discard);
+
+        let version = Self::request(port, CMD_HELLO, &[])?;
+        Ok(String::from_utf8_lossy(&version).into_owned())
+    }
+
+    fn write_chunk(
+        &mut self,
+        port: &mut dyn FirmwarePort,
+        offset: u32,
+        chunk: &[u8],
+    ) -> Result<(), Error> {
+        let mut payload = offset.to_le_bytes().to_vec();
+        payload.extend_from_slice(chunk);
+        Self::request(port, CMD_WRITE, &payload).map(|_| ())
+    }
+
+    fn image_crc(&mut self, port: &mut dyn FirmwarePort, len: u32) -> Result<u32, Error> {
+        let payload = Self::request(port, CMD_CRC, &len.to_le_bytes())?;
+        let crc: [u8; 4] = payload
+            .as_slice()
+            .try_into()
+            .map_err(|_| anyhow!("Invalid CRC response length {}", payload.len()))?;
+        Ok(u32::from_le_bytes(crc))
+    }
+
+    fn commit(&mut self, port: &mut dyn FirmwarePort) -> Result<(), Error> {
+        Self::request(port, CMD_COMMIT, &[]).map(|_| ())
+    }
+
+    fn rollback(&mut self, port: &mut dyn FirmwarePort) -> Result<(), Error> {
+        Self::request(port, CMD_ROLLBACK, &[]).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use control_core::serial::firmware::{FirmwareImage, crc32, update_firmware};
+    use std::{collections::VecDeque, io::Write};
+
+    /// Emulates the bootloader of a board on the other end of the port
+    #[derive(Debug, Default)]
+    struct EmulatedBoard {
+        received: Vec<u8>,
+        responses: VecDeque<u8>,
+        slot: Vec<u8>,
+        committed: bool,
+        rolled_back: bool,
+    }
+
+    impl EmulatedBoard {
+        fn respond(&mut self, command: u8, payload: &[u8]) {
+            self.responses
+                .extend(QiTechBootloader::encode(command, payload));
+        }
+
+        fn handle(&mut self) {
+            // the Modbus request to enter the bootloader is not answered
+            if self.received.first() == Some(&1) {
+                self.received.clear();
+                return;
+            }
+            let Ok((command, payload)) = QiTechBootloader::decode(&mut self.received.as_slice())
+            else {
+                return;
+            };
+            self.received.clear();
+            match command {
+                CMD_HELLO => self.respond(ACK, b"1.0.0"),
+                CMD_WRITE => {
+                    let offset = u32::from_le_bytes(payload[..4].try_into().unwrap()) as usize;
+                    self.slot.truncate(offset);
+                    self.slot.extend_from_slice(&payload[4..]);
+                    self.respond(ACK, &[]);
+                }
+                CMD_CRC => {
+                    let len = u32::from_le_bytes(payload[..4].try_into().unwrap()) as usize;
+                    let crc = crc32(&self.slot[..len.min(self.slot.len())]);
+                    self.respond(ACK, &crc.to_le_bytes());
+                }
+                CMD_COMMIT => {
+                    self.committed = true;
+                    self.respond(ACK, &[]);
+                }
+                CMD_ROLLBACK => {
+                    self.rolled_back = true;
+                    self.respond(ACK, &[]);
+                }
+                _ => self.respond(NAK, b"unknown command"),
+            }
+        }
+    }
+
+    impl Write for EmulatedBoard {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.received.extend_from_slice(buf);
+            self.handle();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Read for EmulatedBoard {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = buf.len().min(self.responses.len());
+            for byte in buf.iter_mut().take(len) {
+                *byte = self.responses.pop_front().unwrap_or_default();
+            }
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn test_frame_roundtrip() {
+        let frame = QiTechBootloader::encode(CMD_WRITE, &[1, 2, 3]);
+        assert_eq!(&frame[..4], &[FRAME_START, CMD_WRITE, 3, 0]);
+        let decoded = QiTechBootloader::decode(&mut frame.as_slice()).unwrap();
+        assert_eq!(decoded, (CMD_WRITE, vec![1, 2, 3]));
+
+        let mut corrupted = frame;
+        corrupted[4] ^= 0xFF;
+        assert!(QiTechBootloader::decode(&mut corrupted.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_update() {
+        let image = FirmwareImage {
+            version: "1.1.0".to_string(),
+            data: (0..600).map(|i| i as u8).collect(),
+        };
+        let mut board = EmulatedBoard::default();
+        let mut previous_version = None;
+        update_firmware(&mut QiTechBootloader, &mut board, &image, &mut |progress| {
+            previous_version = progress.previous_version.clone()
+        })
+        .unwrap();
+
+        assert_eq!(board.slot, image.data);
+        assert!(board.committed);
+        assert!(!board.rolled_back);
+        assert_eq!(previous_version.as_deref(), Some("1.0.0"));
+    }
+}
@@ -1,7 +1,11 @@
 use crate::panic::{PanicDetails, send_panic};
+use crate::serial::firmware::{self, api::emit_firmware};
+use crate::serial::sniffer::api::emit_sniffer;
 use crate::socketio::main_namespace::MainNamespaceEvents;
 use crate::socketio::main_namespace::machines_event::MachinesEventBuilder;
+use crate::socketio::registry_namespace::sync_registry;
 use crate::{app_state::AppState, machines::registry::MACHINE_REGISTRY};
+use control_core::machines::identification::DeviceHardwareIdentification;
 use control_core::socketio::namespace::NamespaceCacheingLogic;
 use smol::channel::Sender;
 use std::{sync::Arc, thread, time::Duration};
@@ -55,6 +59,11 @@ pub fn init_serial(
                                     )
                                 }
                                 for device_identification in result.removed {
+                                    if let DeviceHardwareIdentification::Serial(serial) =
+                                        &device_identification.device_hardware_identification
+                                    {
+                                        firmware::cancel(&serial.path);
+                                    }
                                     machine_guard.remove_serial_device(&device_identification)
                                 }
                             }
@@ -71,6 +80,9 @@ pub fn init_serial(
                                 let event = MachinesEventBuilder().build(app_state_event.clone());
                                 main_namespace.emit(MainNamespaceEvents::MachinesEvent(event));
                             });
+                            smol::block_on(sync_registry(&app_state));
+                            smol::block_on(emit_sniffer(&app_state));
+                            smol::block_on(emit_firmware(&app_state));
                         }
                         smol::Timer::after(Duration::from_millis(300)).await;
                     }
@@ -1,3 +1,5 @@
 pub mod devices;
+pub mod firmware;
 pub mod init;
 pub mod registry;
+pub mod sniffer;
@@ -1,7 +1,10 @@
 use control_core::serial::{SerialDeviceIdentification, registry::SerialDeviceRegistry};
 use lazy_static::lazy_static;
 
-use crate::serial::devices::laser::Laser;
+use crate::serial::devices::laser::{
+    Laser, mitutoyo::MitutoyoLaserDriver, sikora::SikoraLaserDriver, zumbach::ZumbachLaserDriver,
+};
+use crate::serial::firmware::qitech::QiTechBootloader;
 
 #[cfg(feature = "mock-machine")]
 use crate::serial::devices::mock::MockSerialDevice;
@@ -9,10 +12,47 @@ use crate::serial::devices::mock::MockSerialDevice;
 lazy_static! {
     pub static ref SERIAL_DEVICE_REGISTRY: SerialDeviceRegistry = {
         let mut sdr = SerialDeviceRegistry::new();
+        // QiTech laser (FTDI FT232R)
         sdr.register::<Laser>(SerialDeviceIdentification {
             vendor_id: 0x0403,
             product_id: 0x6001,
         });
+        sdr.register_firmware(
+            SerialDeviceIdentification {
+                vendor_id: 0x0403,
+                product_id: 0x6001,
+            },
+            || Box::new(QiTechBootloader),
+        );
+
+        // commercial gauges, selected by the USB serial adapter they are connected with
+        // Prolific PL2303
+        sdr.register_with(
+            SerialDeviceIdentification {
+                vendor_id: 0x067b,
+                product_id: 0x2303,
+            },
+            |params| Laser::new_with_driver(params, Box::new(MitutoyoLaserDriver)),
+        );
+        // Silicon Labs CP210x
+        sdr.register_with(
+            SerialDeviceIdentification {
+                vendor_id: 0x10c4,
+                product_id: 0xea60,
+            },
+            |params| Laser::new_with_driver(params, Box::new(SikoraLaserDriver)),
+        );
+        // FTDI FT232H
+        sdr.register_with(
+            SerialDeviceIdentification {
+                vendor_id: 0x0403,
+                product_id: 0x6014,
+            },
+            |params| Laser::new_with_driver(params, Box::new(ZumbachLaserDriver)),
+        );
+
+        // probe ports of unknown USB serial adapters for a known gauge
+        sdr.register_fallback(Laser::new_autodetect);
 
         // Register MockSerialDevice when mock-machine feature is enabled
         #[cfg(feature = "mock-machine")]
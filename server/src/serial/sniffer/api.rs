@@ -0,0 +1,133 @@
+use super::{SerialFrame, SniffSession, sessions};
+use crate::{
+    app_state::AppState,
+    instrumentation::{api::PerformanceEvent, scope::ScopeEvent},
+    serial::firmware::api::FirmwareEvent,
+};
+use control_core::socketio::{
+    event::{BuildEvent, Event, GenericEvent},
+    namespace::{CacheFn, CacheableEvents, Namespace, NamespaceCacheingLogic, cache_one_event},
+    namespace_id::NamespaceId,
+};
+use control_core_derive::BuildEvent;
+use serde::{Deserialize, Serialize};
+use smol::channel::Sender;
+use socketioxide::extract::SocketRef;
+use std::{collections::BTreeMap, sync::Arc};
+use tracing::instrument;
+
+#[derive(Serialize, Debug, Clone, BuildEvent)]
+pub struct SnifferEvent {
+    /// Sniffed devices by port path
+    pub sessions: BTreeMap<String, SniffSession>,
+    /// Paths of all detected serial devices
+    pub ports: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone, BuildEvent)]
+pub struct SerialFrameEvent {
+    pub frame: SerialFrame,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub enum Mutation {
+    /// Start or reconfigure sniffing of the device at `path`
+    Start {
+        path: String,
+        #[serde(flatten)]
+        session: SniffSession,
+    },
+    Stop {
+        path: String,
+    },
+}
+
+pub enum DiagnosticsNamespaceEvents {
+    Sniffer(Event<SnifferEvent>),
+    SerialFrame(Event<SerialFrameEvent>),
+    Firmware(Event<FirmwareEvent>),
+    Performance(Event<PerformanceEvent>),
+    Scope(Event<ScopeEvent>),
+}
+
+impl CacheableEvents<Self> for DiagnosticsNamespaceEvents {
+    fn event_value(&self) -> GenericEvent {
+        match self {
+            Self::Sniffer(event) => event.into(),
+            Self::SerialFrame(event) => event.into(),
+            Self::Firmware(event) => event.into(),
+            Self::Performance(event) => event.into(),
+            Self::Scope(event) => event.into(),
+        }
+    }
+
+    fn event_cache_fn(&self) -> CacheFn {
+        match self {
+            Self::Sniffer(_) => cache_one_event(),
+            Self::SerialFrame(_) => cache_one_event(),
+            Self::Firmware(_) => cache_one_event(),
+            Self::Performance(_) => cache_one_event(),
+            Self::Scope(_) => cache_one_event(),
+        }
+    }
+}
+
+pub struct DiagnosticsRoom {
+    pub namespace: Namespace,
+}
+
+impl DiagnosticsRoom {
+    pub fn new(socket_queue_tx: Sender<(SocketRef, Arc<GenericEvent>)>) -> Self {
+        Self {
+            namespace: Namespace::new(socket_queue_tx).with_id(NamespaceId::Diagnostics),
+        }
+    }
+}
+
+impl NamespaceCacheingLogic<DiagnosticsNamespaceEvents> for DiagnosticsRoom {
+    #[instrument(skip_all)]
+    fn emit(&mut self, event: DiagnosticsNamespaceEvents) {
+        let generic_event = Arc::new(event.event_value());
+        match event {
+            // frames and samples are a live stream, replaying old ones would be misleading
+            DiagnosticsNamespaceEvents::SerialFrame(_) | DiagnosticsNamespaceEvents::Scope(_) => {
+                self.namespace.emit_transient(generic_event);
+            }
+            DiagnosticsNamespaceEvents::Sniffer(_)
+            | DiagnosticsNamespaceEvents::Firmware(_)
+            | DiagnosticsNamespaceEvents::Performance(_) => {
+                let buffer_fn = event.event_cache_fn();
+                self.namespace.emit(generic_event, &buffer_fn);
+            }
+        }
+    }
+}
+
+pub async fn sniffer_event(app_state: &Arc<AppState>) -> SnifferEvent {
+    let mut ports: Vec<_> = app_state
+        .serial_setup
+        .read()
+        .await
+        .serial_detection
+        .ports
+        .keys()
+        .cloned()
+        .collect();
+    ports.sort();
+    SnifferEvent {
+        sessions: sessions(),
+        ports,
+    }
+}
+
+/// Emits the sniffed devices to the diagnostics namespace
+pub async fn emit_sniffer(app_state: &Arc<AppState>) {
+    let event = sniffer_event(app_state).await.build();
+    let diagnostics_namespace = &mut app_state
+        .socketio_setup
+        .namespaces
+        .write()
+        .await
+        .diagnostics_namespace;
+    diagnostics_namespace.emit(DiagnosticsNamespaceEvents::Sniffer(event));
+}
@@ -0,0 +1,108 @@
+use super::{
+    CAPTURES_DIR, PcapWriter, SerialFrame, SnifferMessage,
+    api::{DiagnosticsNamespaceEvents, SerialFrameEvent, emit_sniffer},
+    queue, session,
+};
+use crate::{
+    app_state::AppState,
+    panic::{PanicDetails, send_panic},
+    storage,
+};
+use control_core::{
+    helpers::clock::unix_millis,
+    socketio::{event::BuildEvent, namespace::NamespaceCacheingLogic},
+};
+use smol::channel::Sender;
+use std::{collections::HashMap, fs::File, io::BufWriter, sync::Arc};
+
+/// Forwards sniffed serial frames to the diagnostics namespace and capture files
+pub fn init_sniffer(
+    thread_panic_tx: Sender<PanicDetails>,
+    app_state: Arc<AppState>,
+) -> Result<(), anyhow::Error> {
+    smol::block_on(emit_sniffer(&app_state));
+
+    let queue = queue();
+    std::thread::Builder::new()
+        .name("serial-sniffer".to_owned())
+        .spawn(move || {
+            send_panic(thread_panic_tx);
+            smol::block_on(async {
+                // open capture files by port path
+                let mut captures: HashMap<String, PcapWriter<BufWriter<File>>> = HashMap::new();
+                while let Ok(message) = queue.recv().await {
+                    match message {
+                        SnifferMessage::Frame(frame) => {
+                            forward_frame(&app_state, &mut captures, frame).await;
+                        }
+                        SnifferMessage::Stopped(path) => {
+                            if captures.remove(&path).is_some() {
+                                tracing::info!("Closed serial capture of {}", path);
+                            }
+                        }
+                    }
+                }
+            });
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_sniffer] Failed to spawn serial sniffer thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    Ok(())
+}
+
+async fn forward_frame(
+    app_state: &Arc<AppState>,
+    captures: &mut HashMap<String, PcapWriter<BufWriter<File>>>,
+    frame: SerialFrame,
+) {
+    // the session may have stopped while the frame was queued
+    let Some(session) = session(&frame.path) else {
+        return;
+    };
+
+    if session.file {
+        if !captures.contains_key(&frame.path) {
+            match open_capture(&frame.path) {
+                Ok(capture) => {
+                    captures.insert(frame.path.clone(), capture);
+                }
+                Err(e) => {
+                    tracing::error!("Failed to open serial capture of {}: {:?}", frame.path, e)
+                }
+            }
+        }
+        if let Some(capture) = captures.get_mut(&frame.path) {
+            if let Err(e) = capture.write_frame(&frame) {
+                tracing::error!("Failed to write serial capture of {}: {}", frame.path, e);
+                captures.remove(&frame.path);
+            }
+        }
+    }
+
+    if session.namespace {
+        let event = SerialFrameEvent { frame }.build();
+        app_state
+            .socketio_setup
+            .namespaces
+            .write()
+            .await
+            .diagnostics_namespace
+            .emit(DiagnosticsNamespaceEvents::SerialFrame(event));
+    }
+}
+
+fn open_capture(path: &str) -> Result<PcapWriter<BufWriter<File>>, anyhow::Error> {
+    let dir = storage::data_dir().join(CAPTURES_DIR);
+    std::fs::create_dir_all(&dir)?;
+    // e.g. /dev/ttyUSB0 -> dev_ttyUSB0-1700000000000.pcap
+    let name = path.trim_start_matches('/').replace(['/', '\\', ':'], "_");
+    let file_path = dir.join(format!("{}-{}.pcap", name, unix_millis()));
+    let file = File::create(&file_path)?;
+    tracing::info!("Writing serial capture of {} to {:?}", path, file_path);
+    Ok(PcapWriter::new(BufWriter::new(file))?)
+}
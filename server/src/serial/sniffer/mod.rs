@@ -0,0 +1,237 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serialport::SerialPort;
+use smol::channel::{Receiver, Sender};
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    sync::{
+        RwLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub mod api;
+pub mod init;
+
+/// Directory inside [`crate::storage::data_dir`] the capture files are written to
+pub const CAPTURES_DIR: &str = "serial_captures";
+
+/// Frames waiting for the sniffer thread, frames are dropped if it falls behind
+const FRAME_QUEUE_LEN: usize = 4096;
+
+lazy_static! {
+    /// Devices whose traffic is mirrored
+    ///
+    /// Device threads only take the lock while at least one device is sniffed.
+    static ref SESSIONS: RwLock<BTreeMap<String, SniffSession>> = RwLock::new(BTreeMap::new());
+    static ref QUEUE: (Sender<SnifferMessage>, Receiver<SnifferMessage>) =
+        smol::channel::bounded(FRAME_QUEUE_LEN);
+}
+
+static SESSION_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SerialDirection {
+    /// Sent to the device
+    Tx,
+    /// Received from the device
+    Rx,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SerialFrame {
+    /// Path of the port, e.g. `/dev/ttyUSB0`
+    pub path: String,
+    pub direction: SerialDirection,
+    /// unix timestamp in µs
+    pub timestamp_us: u64,
+    pub data: Vec<u8>,
+    /// `None` for protocols without a checksum
+    pub crc_valid: Option<bool>,
+}
+
+/// Where the traffic of a sniffed device goes
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SniffSession {
+    /// Mirror frames to the diagnostics namespace
+    pub namespace: bool,
+    /// Write frames to a pcap file in [`CAPTURES_DIR`]
+    pub file: bool,
+}
+
+pub(crate) enum SnifferMessage {
+    Frame(SerialFrame),
+    /// Sniffing of the device at the path stopped, its capture file is closed
+    Stopped(String),
+}
+
+/// Starts or reconfigures mirroring of the device at `path`
+pub fn start(path: &str, session: SniffSession) {
+    let mut sessions = SESSIONS.write().unwrap_or_else(|e| e.into_inner());
+    let previous = sessions.insert(path.to_string(), session.clone());
+    SESSION_COUNT.store(sessions.len(), Ordering::Relaxed);
+    // a reconfigured session without file closes the capture
+    if previous.is_some_and(|previous| previous.file && !session.file) {
+        let _ = QUEUE.0.try_send(SnifferMessage::Stopped(path.to_string()));
+    }
+    tracing::info!("Sniffing serial device {} {:?}", path, session);
+}
+
+/// Stops mirroring of the device at `path`, returns if it was sniffed
+pub fn stop(path: &str) -> bool {
+    let mut sessions = SESSIONS.write().unwrap_or_else(|e| e.into_inner());
+    let stopped = sessions.remove(path).is_some();
+    SESSION_COUNT.store(sessions.len(), Ordering::Relaxed);
+    if stopped {
+        let _ = QUEUE.0.try_send(SnifferMessage::Stopped(path.to_string()));
+        tracing::info!("Stopped sniffing serial device {}", path);
+    }
+    stopped
+}
+
+pub fn sessions() -> BTreeMap<String, SniffSession> {
+    SESSIONS.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+pub fn session(path: &str) -> Option<SniffSession> {
+    SESSIONS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(path)
+        .cloned()
+}
+
+/// Mirrors a frame sent to or received from `port` if the device is sniffed
+///
+/// Cheap if no device is sniffed, so device drivers call it for every frame.
+pub fn sniff(
+    port: &dyn SerialPort,
+    direction: SerialDirection,
+    data: &[u8],
+    crc_valid: Option<bool>,
+) {
+    if SESSION_COUNT.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+    let Some(path) = port.name() else {
+        return;
+    };
+    if session(&path).is_none() {
+        return;
+    }
+
+    let frame = SerialFrame {
+        path,
+        direction,
+        timestamp_us: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64,
+        data: data.to_vec(),
+        crc_valid,
+    };
+    // never block the device thread
+    let _ = QUEUE.0.try_send(SnifferMessage::Frame(frame));
+}
+
+pub(crate) fn queue() -> Receiver<SnifferMessage> {
+    QUEUE.1.clone()
+}
+
+/// Writes frames in the pcap format with link type `LINKTYPE_USER0`
+///
+/// Every packet starts with two bytes before the raw frame: the direction (0 = TX, 1 = RX)
+/// and the CRC status (0 = none, 1 = valid, 2 = invalid).
+pub struct PcapWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> PcapWriter<W> {
+    const MAGIC: u32 = 0xa1b2_c3d4;
+    const SNAPLEN: u32 = 65_535;
+    const LINKTYPE_USER0: u32 = 147;
+
+    pub fn new(mut writer: W) -> Result<Self, std::io::Error> {
+        writer.write_all(&Self::MAGIC.to_le_bytes())?;
+        writer.write_all(&2u16.to_le_bytes())?;
+        writer.write_all(&4u16.to_le_bytes())?;
+        // timezone offset and timestamp accuracy
+        writer.write_all(&0i32.to_le_bytes())?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(&Self::SNAPLEN.to_le_bytes())?;
+        writer.write_all(&Self::LINKTYPE_USER0.to_le_bytes())?;
+        Ok(Self { writer })
+    }
+
+    pub fn write_frame(&mut self, frame: &SerialFrame) -> Result<(), std::io::Error> {
+        let direction = match frame.direction {
+            SerialDirection::Tx => 0u8,
+            SerialDirection::Rx => 1u8,
+        };
+        let crc = match frame.crc_valid {
+            None => 0u8,
+            Some(true) => 1u8,
+            Some(false) => 2u8,
+        };
+        let len = (frame.data.len() + 2) as u32;
+
+        self.writer
+            .write_all(&((frame.timestamp_us / 1_000_000) as u32).to_le_bytes())?;
+        self.writer
+            .write_all(&((frame.timestamp_us % 1_000_000) as u32).to_le_bytes())?;
+        self.writer.write_all(&len.to_le_bytes())?;
+        self.writer.write_all(&len.to_le_bytes())?;
+        self.writer.write_all(&[direction, crc])?;
+        self.writer.write_all(&frame.data)?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pcap_writer() {
+        let frame = SerialFrame {
+            path: "/dev/ttyUSB0".to_string(),
+            direction: SerialDirection::Rx,
+            timestamp_us: 1_700_000_000_250_000,
+            data: vec![0x01, 0x04, 0x02],
+            crc_valid: Some(false),
+        };
+
+        let mut pcap = PcapWriter::new(Vec::new()).unwrap();
+        pcap.write_frame(&frame).unwrap();
+        let bytes = pcap.writer;
+
+        // global header
+        assert_eq!(bytes.len(), 24 + 16 + 5);
+        assert_eq!(&bytes[0..4], &0xa1b2_c3d4u32.to_le_bytes());
+        assert_eq!(&bytes[20..24], &147u32.to_le_bytes());
+
+        // packet header
+        assert_eq!(&bytes[24..28], &1_700_000_000u32.to_le_bytes());
+        assert_eq!(&bytes[28..32], &250_000u32.to_le_bytes());
+        assert_eq!(&bytes[32..36], &5u32.to_le_bytes());
+        assert_eq!(&bytes[40..], &[1, 2, 0x01, 0x04, 0x02]);
+    }
+
+    #[test]
+    fn test_sessions() {
+        let path = "/dev/test-sniffer";
+        start(
+            path,
+            SniffSession {
+                namespace: true,
+                file: false,
+            },
+        );
+        assert!(session(path).is_some_and(|session| session.namespace));
+        assert!(stop(path));
+        assert!(!stop(path));
+        assert!(session(path).is_none());
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{
+    app_state::AppState,
+    history::init::log_new_events,
+    panic::{PanicDetails, send_panic},
+};
+use control_core::machines::identification::MachineIdentificationUnique;
+use signal_hook::{
+    consts::{SIGINT, SIGTERM},
+    iterator::Signals,
+};
+use smol::channel::Sender;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Time the machines get to reach their safe state, the server exits anyway afterwards
+const SAFE_STOP_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Interval the machines are asked for their safe state
+const SAFE_STOP_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Shuts the server down gracefully on SIGTERM and SIGINT
+///
+/// Machines are brought into a safe state and pending history is written before the
+/// process exits. A second signal exits immediately.
+pub fn init_shutdown(
+    thread_panic_tx: Sender<PanicDetails>,
+    app_state: Arc<AppState>,
+) -> Result<(), anyhow::Error> {
+    let mut signals = Signals::new([SIGTERM, SIGINT]).map_err(|e| {
+        anyhow::anyhow!(
+            "[{}::init_shutdown] Failed to register signal handlers\n{:?}",
+            module_path!(),
+            e
+        )
+    })?;
+
+    std::thread::Builder::new()
+        .name("shutdown".to_owned())
+        .spawn(move || {
+            send_panic(thread_panic_tx.clone());
+            for (count, signal) in signals.forever().enumerate() {
+                if count > 0 {
+                    tracing::warn!("Received signal {} again, exiting immediately", signal);
+                    std::process::exit(1);
+                }
+                tracing::info!("Received signal {}, shutting down", signal);
+
+                // keep listening for a second signal while shutting down
+                let app_state = app_state.clone();
+                let thread_panic_tx = thread_panic_tx.clone();
+                let spawned = std::thread::Builder::new()
+                    .name("shutdown-sequence".to_owned())
+                    .spawn(move || {
+                        send_panic(thread_panic_tx);
+                        smol::block_on(shutdown(&app_state));
+                    });
+                if let Err(e) = spawned {
+                    tracing::error!("Failed to spawn shutdown thread, exiting: {:?}", e);
+                    std::process::exit(1);
+                }
+            }
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_shutdown] Failed to spawn shutdown thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    Ok(())
+}
+
+async fn shutdown(app_state: &Arc<AppState>) {
+    safe_stop_machines(app_state).await;
+    log_new_events(app_state).await;
+    tracing::info!("Shutdown complete");
+    std::process::exit(0);
+}
+
+/// Asks all connected machines for their safe state until all are safe or the timeout passed
+///
+/// The control loop keeps running meanwhile, so the machines ramp their actuators down.
+async fn safe_stop_machines(app_state: &Arc<AppState>) {
+    let start = Instant::now();
+    loop {
+        let machines: Vec<_> = app_state
+            .machines
+            .read()
+            .await
+            .iter()
+            .filter_map(|(machine_identification_unique, slot)| {
+                let machine = slot.lock_blocking().machine_connection.to_machine()?;
+                Some((machine_identification_unique.clone(), machine))
+            })
+            .collect();
+
+        let mut pending: Vec<MachineIdentificationUnique> = vec![];
+        for (machine_identification_unique, machine) in machines {
+            if !machine.lock().await.act_safe_stop() {
+                pending.push(machine_identification_unique);
+            }
+        }
+
+        if pending.is_empty() {
+            tracing::info!("All machines are in a safe state");
+            return;
+        }
+        if start.elapsed() >= SAFE_STOP_TIMEOUT {
+            tracing::warn!(
+                "Machines did not reach a safe state within {:?}: {:?}",
+                SAFE_STOP_TIMEOUT,
+                pending
+            );
+            return;
+        }
+        smol::Timer::after(SAFE_STOP_INTERVAL).await;
+    }
+}
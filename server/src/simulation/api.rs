@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+/// Sensor values replacing those of the model, e.g. to test how a machine reacts to a fault
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SensorOverrides {
+    /// mm
+    pub laser_diameter: Option<f64>,
+    /// degrees
+    pub tension_arm_angle: Option<f64>,
+    pub traverse_end_stop: Option<bool>,
+}
+
+/// Drives as commanded by the machines and sensors as seen by them
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SimulationState {
+    /// m/min
+    pub puller_speed: f64,
+    /// rpm
+    pub spool_speed: f64,
+    /// mm from the end stop
+    pub traverse_position: f64,
+    pub laser_pointer: bool,
+    /// mm, `None` until the filament reaches the laser
+    pub laser_diameter: Option<f64>,
+    /// degrees
+    pub tension_arm_angle: f64,
+    pub traverse_end_stop: bool,
+    pub overrides: SensorOverrides,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub enum Mutation {
+    /// Replaces all overrides, sensors without a value follow the model again
+    SetOverrides(SensorOverrides),
+}
@@ -0,0 +1,333 @@
+use super::{
+    SIMULATION,
+    api::SimulationState,
+    model::{LineDrives, LineModel},
+    winder::SimulatedWinder,
+};
+use crate::{
+    app_state::AppState,
+    config::config,
+    machines::registry::MACHINE_REGISTRY,
+    panic::{PanicDetails, send_panic},
+    serial::devices::laser::{Laser, LaserData},
+    socketio::{
+        main_namespace::{MainNamespaceEvents, machines_event::MachinesEventBuilder},
+        registry_namespace::sync_registry,
+    },
+};
+use control_core::{
+    machines::identification::DeviceIdentification,
+    serial::{SerialDevice, SerialDeviceNew, SerialDeviceNewParams},
+    socketio::namespace::NamespaceCacheingLogic,
+};
+use ethercat_hal::io::analog_input::AnalogInputInput;
+use smol::{channel::Sender, lock::RwLock};
+use std::{
+    f64::consts::PI,
+    sync::{Arc, RwLock as StdRwLock},
+    time::{Duration, Instant},
+};
+use tokio::sync::watch;
+use uom::si::{f64::Length, length::millimeter};
+
+/// Interval the line is advanced with
+const SIMULATION_STEP: Duration = Duration::from_millis(10);
+
+/// Mechanics of the winder as assumed by [`crate::machines::winder2::Winder2`]
+const STEPS_PER_REVOLUTION: f64 = 200.0;
+/// m
+const PULLER_WHEEL_DIAMETER: f64 = 0.08;
+/// mm the traverse moves per revolution
+const TRAVERSE_CIRCUMFERENCE: f64 = 35.0;
+/// mm the traverse starts away from its end stop, so homing has to find it
+const TRAVERSE_START: f64 = 20.0;
+
+/// Creates a laser and a winder on a virtual line instead of detecting hardware
+///
+/// The extruder only exists in the model, its screw speed is set in the config.
+pub fn init_simulation(
+    thread_panic_tx: Sender<PanicDetails>,
+    app_state: Arc<AppState>,
+) -> Result<(), anyhow::Error> {
+    SIMULATION
+        .set(StdRwLock::new(SimulationState::default()))
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "[{}::init_simulation] Simulation is already initialized",
+                module_path!()
+            )
+        })?;
+
+    // nothing sends removals, the simulated devices never disconnect
+    let (device_thread_panic_tx, _device_thread_panic_rx) = smol::channel::unbounded();
+    let params = |path: &str| SerialDeviceNewParams {
+        path: path.to_string(),
+        device_thread_panic_tx: device_thread_panic_tx.clone(),
+        serial_device_identification: None,
+        usb_serial_number: None,
+    };
+
+    let (laser_identification, laser, laser_tx) =
+        Laser::new_simulated(&params("/dev/simulated-laser"));
+    let (winder_identification, winder) =
+        SimulatedWinder::new_serial(&params("/dev/simulated-winder"))?;
+
+    smol::block_on(async {
+        add_device(&app_state, &laser_identification, laser).await;
+        add_device(&app_state, &winder_identification, winder.clone()).await;
+    });
+
+    std::thread::Builder::new()
+        .name("simulation".to_owned())
+        .spawn(move || {
+            send_panic(thread_panic_tx);
+            simulate(&winder, &laser_tx);
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_simulation] Failed to spawn simulation thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    tracing::info!("Simulating the line, no hardware is detected");
+    Ok(())
+}
+
+async fn add_device(
+    app_state: &Arc<AppState>,
+    device_identification: &DeviceIdentification,
+    device: Arc<RwLock<dyn SerialDevice>>,
+) {
+    app_state.machines.write().await.add_serial_device(
+        device_identification,
+        device,
+        &MACHINE_REGISTRY,
+        app_state.socketio_setup.socket_queue_tx.clone(),
+        Arc::downgrade(&app_state.machines),
+    );
+
+    let event = MachinesEventBuilder().build(app_state.clone());
+    app_state
+        .socketio_setup
+        .namespaces
+        .write()
+        .await
+        .main_namespace
+        .emit(MainNamespaceEvents::MachinesEvent(event));
+    sync_registry(app_state).await;
+}
+
+/// Speeds of the drives that turned `puller_steps` and `spool_steps` full steps in `dt`
+const fn line_drives(puller_steps: f64, spool_steps: f64, dt: Duration) -> LineDrives {
+    let seconds = dt.as_secs_f64();
+    LineDrives {
+        puller_speed: puller_steps / seconds / STEPS_PER_REVOLUTION * PI * PULLER_WHEEL_DIAMETER,
+        spool_speed: spool_steps / seconds / STEPS_PER_REVOLUTION,
+    }
+}
+
+/// Moves the drives of the winder and feeds the line back into its sensors and the laser
+fn simulate(winder: &RwLock<SimulatedWinder>, laser_tx: &watch::Sender<Option<LaserData>>) {
+    let Some(state) = SIMULATION.get() else {
+        return;
+    };
+    let mut model = LineModel::new();
+    // full steps, the end stop triggers at 0
+    let mut traverse_position = TRAVERSE_START / TRAVERSE_CIRCUMFERENCE * STEPS_PER_REVOLUTION;
+    let mut last_step = Instant::now();
+
+    loop {
+        std::thread::sleep(SIMULATION_STEP);
+        let now = Instant::now();
+        let dt = now.duration_since(last_step);
+        last_step = now;
+        let overrides = state
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .overrides
+            .clone();
+
+        let mut winder = winder.write_blocking();
+        let puller_steps = winder.puller.advance(dt);
+        let spool_steps = winder.spool.advance(dt);
+        traverse_position = (traverse_position + winder.traverse.advance(dt)).max(0.0);
+        let traverse_end_stop = overrides
+            .traverse_end_stop
+            .unwrap_or(traverse_position <= 0.0);
+        winder.traverse_end_stop.set_value(traverse_end_stop);
+
+        let drives = line_drives(puller_steps, spool_steps, dt);
+        let server_config = config();
+        let simulation_config = server_config.simulation.clone();
+        model.step(&simulation_config, drives, dt);
+
+        // 5 V per revolution of the arm on a 0 to 10 V input
+        let tension_arm_angle = overrides
+            .tension_arm_angle
+            .unwrap_or_else(|| model.tension_arm_angle());
+        winder.tension_arm.set_input(AnalogInputInput {
+            normalized: (tension_arm_angle / 720.0) as f32,
+            wiring_error: false,
+        });
+        let laser_pointer = winder.laser_pointer.get_value();
+        drop(winder);
+
+        let laser_diameter = overrides
+            .laser_diameter
+            .or_else(|| model.laser_diameter(&simulation_config));
+        if let Some(diameter) = laser_diameter {
+            let diameter = Length::new::<millimeter>(diameter);
+            laser_tx.send_replace(Some(LaserData {
+                diameter,
+                x_axis: None,
+                y_axis: None,
+                // the strands run through the same model
+                strands: vec![diameter; server_config.machines.strands],
+                last_timestamp: now,
+            }));
+        }
+
+        let mut state = state.write().unwrap_or_else(|e| e.into_inner());
+        state.puller_speed = drives.puller_speed * 60.0;
+        state.spool_speed = drives.spool_speed * 60.0;
+        state.traverse_position = traverse_position / STEPS_PER_REVOLUTION * TRAVERSE_CIRCUMFERENCE;
+        state.laser_pointer = laser_pointer;
+        state.laser_diameter = laser_diameter;
+        state.tension_arm_angle = tension_arm_angle;
+        state.traverse_end_stop = traverse_end_stop;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{LaserDefaults, SimulationConfig},
+        machines::winder2::puller_speed_controller::{PullerRegulationMode, PullerSpeedController},
+    };
+    use approx::assert_relative_eq;
+    use control_core::{
+        converters::linear_step_converter::LinearStepConverter,
+        uom_extensions::velocity::meter_per_minute,
+    };
+    use ethercat_hal::{
+        io::{
+            stepper_velocity_el70x1::StepperVelocityEL70x1,
+            stepper_velocity_el70x1_dummy::StepperVelocityEL70x1Dummy,
+        },
+        shared_config::el70x1::EL70x1SpeedRange,
+    };
+    use uom::si::{f64::Velocity, length::meter};
+
+    const TARGET_DIAMETER: f64 = 1.75;
+
+    /// Laser, diameter regulation and puller motor of the winder closed over the line model
+    struct ClosedLoop {
+        config: SimulationConfig,
+        model: LineModel,
+        controller: PullerSpeedController,
+        puller: StepperVelocityEL70x1,
+        motor: StepperVelocityEL70x1Dummy,
+        t: Instant,
+    }
+
+    impl ClosedLoop {
+        fn new(config: SimulationConfig) -> Self {
+            // configured like the simulated winder
+            let motor = StepperVelocityEL70x1Dummy::new(EL70x1SpeedRange::Steps1000, 1);
+            let mut puller = motor.stepper_velocity();
+            puller.set_enabled(true);
+            let mut controller = PullerSpeedController::new(
+                Velocity::new::<meter_per_minute>(20.0),
+                Length::new::<millimeter>(TARGET_DIAMETER),
+                LinearStepConverter::from_diameter(
+                    200,
+                    Length::new::<meter>(PULLER_WHEEL_DIAMETER),
+                ),
+                1,
+            );
+            controller.set_regulation_mode(PullerRegulationMode::Diameter);
+            controller.set_enabled(true);
+            Self {
+                config,
+                model: LineModel::new(),
+                controller,
+                puller,
+                motor,
+                t: Instant::now(),
+            }
+        }
+
+        /// Runs the line for `duration`, returns the diameters the laser measured in mm
+        fn run(&mut self, duration: Duration) -> Vec<f64> {
+            let mut measured = vec![];
+            for _ in 0..duration.as_millis() / SIMULATION_STEP.as_millis() {
+                self.t += SIMULATION_STEP;
+                let diameter = self.model.laser_diameter(&self.config);
+                measured.extend(diameter);
+
+                // act cycle of the winder
+                let angular_velocity = self
+                    .controller
+                    .calc_angular_velocity(self.t, diameter.map(Length::new::<millimeter>));
+                let steps_per_second = self
+                    .controller
+                    .converter
+                    .angular_velocity_to_steps(angular_velocity);
+                self.puller.set_speed(steps_per_second).unwrap();
+
+                let puller_steps = self.motor.advance(SIMULATION_STEP);
+                let drives = line_drives(puller_steps, 0.0, SIMULATION_STEP);
+                self.model.step(&self.config, drives, SIMULATION_STEP);
+            }
+            measured
+        }
+
+        /// m/min
+        fn line_speed(&self) -> f64 {
+            self.controller.last_speed.get::<meter_per_minute>()
+        }
+    }
+
+    /// Largest deviation from the target diameter in mm
+    fn max_deviation(measured: &[f64]) -> f64 {
+        measured
+            .iter()
+            .map(|diameter| (diameter - TARGET_DIAMETER).abs())
+            .fold(0.0, f64::max)
+    }
+
+    fn assert_settled(measured: &[f64]) {
+        let tolerance = LaserDefaults::default().lower_tolerance;
+        let mean = measured.iter().sum::<f64>() / measured.len() as f64;
+        assert_relative_eq!(mean, TARGET_DIAMETER, epsilon = 0.002);
+        // what is left is the ripple of the screw
+        assert!(max_deviation(measured) < tolerance / 2.0, "{:?}", measured);
+    }
+
+    #[test]
+    fn test_diameter_regulation_rejects_extruder_steps() {
+        // 1.75 mm at 20 m/min, fast enough for the default gains despite the transport delay
+        // to the laser
+        let mut line = ClosedLoop::new(SimulationConfig {
+            screw_displacement: 2.4,
+            ..Default::default()
+        });
+        line.run(Duration::from_secs(90));
+        assert_settled(&line.run(Duration::from_secs(30)));
+        let line_speed = line.line_speed();
+
+        // the extruder output steps by 10 %, the puller has to follow it
+        for (screw_speed, ratio) in [(22.0, 1.1), (18.0, 0.9)] {
+            line.config.screw_speed = screw_speed;
+            let disturbed = line.run(Duration::from_secs(90));
+            let peak = max_deviation(&disturbed);
+            assert!((0.03..0.15).contains(&peak), "peak deviation {}", peak);
+
+            assert_settled(&line.run(Duration::from_secs(30)));
+            assert_relative_eq!(line.line_speed(), line_speed * ratio, max_relative = 0.02);
+        }
+    }
+}
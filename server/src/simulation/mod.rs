@@ -0,0 +1,30 @@
+//! Virtual production line for the `--simulate` mode
+//!
+//! A laser and a winder are created on simulated devices, the [`model::LineModel`] connects
+//! them with a virtual extruder so controllers, recipes and clients can be run without hardware.
+
+use api::{SensorOverrides, SimulationState};
+use std::sync::{OnceLock, RwLock};
+
+pub mod api;
+pub mod init;
+pub mod model;
+pub mod winder;
+
+/// Set by [`init::init_simulation`], updated by the simulation thread every step
+static SIMULATION: OnceLock<RwLock<SimulationState>> = OnceLock::new();
+
+/// Current drives and sensors, `None` if the server is not simulating
+pub fn simulation_state() -> Option<SimulationState> {
+    let state = SIMULATION.get()?;
+    Some(state.read().unwrap_or_else(|e| e.into_inner()).clone())
+}
+
+/// Injects sensor values, `false` if the server is not simulating
+pub fn set_sensor_overrides(overrides: SensorOverrides) -> bool {
+    let Some(state) = SIMULATION.get() else {
+        return false;
+    };
+    state.write().unwrap_or_else(|e| e.into_inner()).overrides = overrides;
+    true
+}
@@ -0,0 +1,201 @@
+use crate::config::SimulationConfig;
+use std::{collections::VecDeque, f64::consts::PI, time::Duration};
+
+/// Seconds the screw takes to settle at a new speed
+const SCREW_TIME_CONSTANT: f64 = 2.0;
+
+/// Largest diameter leaving the nozzle, the melt piles up instead of getting thicker
+const MAX_DIAMETER: f64 = 10.0;
+
+/// Slack the dancer takes up between its tightest and its loosest position in m
+const DANCER_TRAVEL: f64 = 0.5;
+/// Tension arm angles in degrees, as seen by the adaptive spool speed controller
+const ARM_TIGHT: f64 = 20.0;
+const ARM_LOOSE: f64 = 90.0;
+
+/// Speeds of the winder drives in physical units
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LineDrives {
+    /// m/s
+    pub puller_speed: f64,
+    /// rev/s
+    pub spool_speed: f64,
+}
+
+/// Extruder, laser and winder connected by the filament
+///
+/// The extruder pushes a volume per screw revolution which the puller stretches to a cross
+/// section, so the diameter follows the puller speed. The laser sees it after the filament
+/// travelled [`SimulationConfig::laser_distance`]. Between puller and spool the dancer
+/// takes up the difference of the speeds, the spool radius grows with the wound volume.
+#[derive(Debug)]
+pub struct LineModel {
+    /// rpm
+    screw_speed: f64,
+    /// revolutions since the start, phase of the ripple
+    screw_revolutions: f64,
+    /// m pulled since the start
+    pulled: f64,
+    /// diameters in mm by the pulled position in m they left the nozzle at
+    transport: VecDeque<(f64, f64)>,
+    /// m of filament held by the dancer
+    slack: f64,
+    /// mm³ on the spool
+    wound_volume: f64,
+}
+
+impl Default for LineModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LineModel {
+    pub fn new() -> Self {
+        Self {
+            screw_speed: 0.0,
+            screw_revolutions: 0.0,
+            pulled: 0.0,
+            transport: VecDeque::new(),
+            slack: DANCER_TRAVEL / 2.0,
+            wound_volume: 0.0,
+        }
+    }
+
+    /// Advances the line by `dt`
+    pub fn step(&mut self, config: &SimulationConfig, drives: LineDrives, dt: Duration) {
+        let dt = dt.as_secs_f64();
+
+        // extruder
+        let settle = (dt / SCREW_TIME_CONSTANT).min(1.0);
+        self.screw_speed += (config.screw_speed - self.screw_speed) * settle;
+        self.screw_revolutions += self.screw_speed / 60.0 * dt;
+
+        // nozzle
+        let puller_speed = drives.puller_speed.max(0.0);
+        if puller_speed > 0.0 {
+            // mm³/s over mm/s
+            let flow = self.screw_speed / 60.0 * config.screw_displacement * 1000.0;
+            let area = flow / (puller_speed * 1000.0);
+            let ripple = config
+                .diameter_ripple
+                .mul_add((self.screw_revolutions * 2.0 * PI).sin(), 1.0);
+            let diameter = ((4.0 * area / PI).sqrt() * ripple).min(MAX_DIAMETER);
+
+            self.pulled += puller_speed * dt;
+            self.transport.push_back((self.pulled, diameter));
+        }
+
+        // only the newest diameter that passed the laser is kept
+        let at_laser = self.pulled - config.laser_distance;
+        while self
+            .transport
+            .get(1)
+            .is_some_and(|(position, _)| *position <= at_laser)
+        {
+            self.transport.pop_front();
+        }
+
+        // spool, a tight dancer can't give more than the puller delivers
+        let take_up = drives.spool_speed.max(0.0) * 2.0 * PI * self.spool_radius(config) / 1000.0;
+        let slack = (puller_speed - take_up).mul_add(dt, self.slack);
+        let wound = if slack < 0.0 {
+            puller_speed * dt + self.slack
+        } else {
+            take_up * dt
+        };
+        self.slack = slack.clamp(0.0, DANCER_TRAVEL);
+        self.wound_volume += wound * 1000.0 * self.cross_section();
+    }
+
+    /// mm, diameter measured by the laser, `None` until the filament reaches it
+    pub fn laser_diameter(&self, config: &SimulationConfig) -> Option<f64> {
+        let (position, diameter) = self.transport.front()?;
+        (*position <= self.pulled - config.laser_distance).then_some(*diameter)
+    }
+
+    /// Degrees, loose at [`ARM_LOOSE`] and tight at [`ARM_TIGHT`]
+    pub fn tension_arm_angle(&self) -> f64 {
+        ARM_TIGHT + (ARM_LOOSE - ARM_TIGHT) * self.slack / DANCER_TRAVEL
+    }
+
+    /// mm, grows with the wound filament
+    pub fn spool_radius(&self, config: &SimulationConfig) -> f64 {
+        let core = config.spool_core_diameter / 2.0;
+        (core * core + self.wound_volume / (PI * config.spool_width)).sqrt()
+    }
+
+    /// mm², of the filament leaving the puller
+    fn cross_section(&self) -> f64 {
+        self.transport
+            .back()
+            .map_or(0.0, |(_, diameter)| PI / 4.0 * diameter * diameter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn run(model: &mut LineModel, config: &SimulationConfig, drives: LineDrives, seconds: u64) {
+        for _ in 0..seconds * 100 {
+            model.step(config, drives, Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_diameter_follows_puller_speed() {
+        let config = SimulationConfig {
+            diameter_ripple: 0.0,
+            ..Default::default()
+        };
+        let mut model = LineModel::new();
+        let drives = LineDrives {
+            puller_speed: 1.0 / 60.0,
+            spool_speed: 0.0,
+        };
+
+        // the filament needs a minute to reach the laser at 1 m/min
+        run(&mut model, &config, drives, 30);
+        assert_eq!(model.laser_diameter(&config), None);
+
+        run(&mut model, &config, drives, 60);
+        let diameter = model.laser_diameter(&config).unwrap();
+        assert_relative_eq!(diameter, 1.75, epsilon = 0.01);
+
+        // pulling faster makes it thinner once the new filament arrived
+        let faster = LineDrives {
+            puller_speed: 2.0 / 60.0,
+            ..drives
+        };
+        run(&mut model, &config, faster, 60);
+        let thinner = model.laser_diameter(&config).unwrap();
+        assert_relative_eq!(thinner, diameter / 2f64.sqrt(), epsilon = 0.01);
+    }
+
+    #[test]
+    fn test_spool_takes_up_slack() {
+        let config = SimulationConfig::default();
+        let mut model = LineModel::new();
+        let loose = model.tension_arm_angle();
+
+        // the spool standing still lets the dancer fill up
+        let drives = LineDrives {
+            puller_speed: 1.0 / 60.0,
+            spool_speed: 0.0,
+        };
+        run(&mut model, &config, drives, 10);
+        assert!(model.tension_arm_angle() > loose);
+
+        // winding faster than the puller tightens it
+        let core = model.spool_radius(&config);
+        let drives = LineDrives {
+            puller_speed: 1.0 / 60.0,
+            spool_speed: 0.1,
+        };
+        run(&mut model, &config, drives, 60);
+        assert_relative_eq!(model.tension_arm_angle(), ARM_TIGHT);
+        assert!(model.spool_radius(&config) > core);
+    }
+}
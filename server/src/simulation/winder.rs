@@ -0,0 +1,89 @@
+use std::{fmt, sync::Arc};
+
+use control_core::{
+    machines::identification::{
+        DeviceHardwareIdentification, DeviceHardwareIdentificationSerial, DeviceIdentification,
+        DeviceMachineIdentification, MachineIdentification, MachineIdentificationUnique,
+    },
+    serial::{SerialDevice, SerialDeviceNew, SerialDeviceNewParams},
+};
+use ethercat_hal::{
+    io::{
+        analog_input::physical::AnalogInputRange, analog_input_dummy::AnalogInputDummy,
+        digital_input_dummy::DigitalInputDummy, digital_output_dummy::DigitalOutputDummy,
+        stepper_velocity_el70x1_dummy::StepperVelocityEL70x1Dummy,
+    },
+    shared_config::el70x1::EL70x1SpeedRange,
+};
+use smol::lock::RwLock;
+use uom::si::{electric_potential::volt, f64::ElectricPotential};
+
+use crate::machines::{MACHINE_WINDER_V1, VENDOR_QITECH};
+
+/// Terminals of a winder without hardware, configured like in [`crate::machines::winder2::Winder2`]
+///
+/// Registered as a serial device so the winder is created by the machine manager like any
+/// other machine, the simulation moves the drives and feeds back the sensors.
+pub struct SimulatedWinder {
+    pub path: String,
+    pub traverse: StepperVelocityEL70x1Dummy,
+    pub puller: StepperVelocityEL70x1Dummy,
+    pub spool: StepperVelocityEL70x1Dummy,
+    pub tension_arm: AnalogInputDummy,
+    pub traverse_end_stop: DigitalInputDummy,
+    pub laser_pointer: DigitalOutputDummy,
+}
+
+impl fmt::Debug for SimulatedWinder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SimulatedWinder({})", self.path)
+    }
+}
+
+impl SerialDevice for SimulatedWinder {}
+
+impl SerialDeviceNew for SimulatedWinder {
+    fn new_serial(
+        params: &SerialDeviceNewParams,
+    ) -> Result<(DeviceIdentification, Arc<RwLock<Self>>), anyhow::Error> {
+        let device_identification = DeviceIdentification {
+            device_machine_identification: Some(DeviceMachineIdentification {
+                machine_identification_unique: MachineIdentificationUnique {
+                    machine_identification: MachineIdentification {
+                        vendor: VENDOR_QITECH,
+                        machine: MACHINE_WINDER_V1,
+                    },
+                    serial: params.machine_serial(),
+                },
+                role: 0,
+            }),
+            device_hardware_identification: DeviceHardwareIdentification::Serial(
+                DeviceHardwareIdentificationSerial {
+                    path: params.path.clone(),
+                    usb_serial_number: params.usb_serial_number.clone(),
+                },
+            ),
+        };
+
+        let winder = Self {
+            path: params.path.clone(),
+            // EL7031, 64 microsteps
+            traverse: StepperVelocityEL70x1Dummy::new(EL70x1SpeedRange::Steps1000, 64),
+            // EL7031-0030
+            puller: StepperVelocityEL70x1Dummy::new(EL70x1SpeedRange::Steps1000, 1),
+            // EL7041-0052
+            spool: StepperVelocityEL70x1Dummy::new(EL70x1SpeedRange::Steps2000, 1),
+            // AI1 of the EL7031-0030
+            tension_arm: AnalogInputDummy::new(AnalogInputRange::Potential {
+                min: ElectricPotential::new::<volt>(0.0),
+                max: ElectricPotential::new::<volt>(10.0),
+                min_raw: 0,
+                max_raw: i16::MAX,
+            }),
+            traverse_end_stop: DigitalInputDummy::new(),
+            laser_pointer: DigitalOutputDummy::new(),
+        };
+
+        Ok((device_identification, Arc::new(RwLock::new(winder))))
+    }
+}
@@ -2,13 +2,27 @@ use std::str::FromStr;
 use std::sync::Arc;
 
 use crate::app_state::AppState;
-use control_core::socketio::namespace_id::NamespaceId;
+use crate::auth::{AuthError, Role, bearer_token};
+use crate::rest::handlers::{auth::authorize_machine_mutation, machine_mutation::mutate_machine};
+use crate::rest::util::ResponseUtilError;
+use crate::socketio::registry_namespace::{QUERY_MACHINES, answer_query, sync_registry};
+use control_core::rest::mutation::{MachineMutationBody, MutationResponse};
+use control_core::socketio::{
+    chart::{ChartSubscription, SUBSCRIBE_CHART, UNSUBSCRIBE_CHART},
+    namespace_id::NamespaceId,
+    units::UnitSystem,
+};
+use serde_json::Value;
 use socketioxide::ParserConfig;
-use socketioxide::extract::SocketRef;
+use socketioxide::extract::{AckSender, Data, SocketRef};
 use socketioxide::layer::SocketIoLayer;
 use tracing::info_span;
 use tracing_futures::Instrument;
 
+/// Client message with a mutation of the machine of the namespace, acknowledged with a
+/// [`MutationResponse`]
+pub const MUTATE: &str = "Mutate";
+
 pub async fn init_socketio(app_state: &Arc<AppState>) -> SocketIoLayer {
     // create
     let (socketio_layer, io) = socketioxide::SocketIoBuilder::new()
@@ -24,6 +38,47 @@ pub async fn init_socketio(app_state: &Arc<AppState>) -> SocketIoLayer {
         handle_socket_connection(socket, app_state_main.clone());
     });
 
+    // set the on connect handler for recipes namespace
+    let app_state_recipes = app_state.clone();
+    io.ns("/recipes", move |socket: SocketRef| {
+        handle_socket_connection(socket, app_state_recipes.clone());
+    });
+
+    // set the on connect handler for batches namespace
+    let app_state_batches = app_state.clone();
+    io.ns("/batches", move |socket: SocketRef| {
+        handle_socket_connection(socket, app_state_batches.clone());
+    });
+
+    // set the on connect handler for alarms namespace
+    let app_state_alarms = app_state.clone();
+    io.ns("/alarms", move |socket: SocketRef| {
+        handle_socket_connection(socket, app_state_alarms.clone());
+    });
+
+    // set the on connect handler for registry namespace
+    let app_state_registry = app_state.clone();
+    io.ns("/registry", move |socket: SocketRef| {
+        let app_state_query = app_state_registry.clone();
+        socket.on(QUERY_MACHINES, move |socket: SocketRef| {
+            answer_query(socket, &app_state_query);
+        });
+        handle_socket_connection(socket, app_state_registry.clone());
+    });
+    sync_registry(app_state).await;
+
+    // set the on connect handler for diagnostics namespace
+    let app_state_diagnostics = app_state.clone();
+    io.ns("/diagnostics", move |socket: SocketRef| {
+        handle_socket_connection(socket, app_state_diagnostics.clone());
+    });
+
+    // set the on connect handler for sequences namespace
+    let app_state_sequences = app_state.clone();
+    io.ns("/sequences", move |socket: SocketRef| {
+        handle_socket_connection(socket, app_state_sequences.clone());
+    });
+
     // Clone app_state for the second handler
     let app_state_machine = app_state.clone();
 
@@ -36,6 +91,17 @@ pub async fn init_socketio(app_state: &Arc<AppState>) -> SocketIoLayer {
         tracing::error!("Failed to detect machine namespace: {}", err);
     }
 
+    // machines of other control servers in federation mode
+    let app_state_remote = app_state.clone();
+    if let Err(err) = io.dyn_ns(
+        "/remote/{server}/machine/{vendor}/{machine}/{serial}",
+        move |socket: SocketRef| {
+            handle_socket_connection(socket, app_state_remote.clone());
+        },
+    ) {
+        tracing::error!("Failed to detect remote machine namespace: {}", err);
+    }
+
     // set the io to the app state
     let mut socketio_guard = app_state.socketio_setup.socketio.write().await;
     socketio_guard.replace(io);
@@ -44,6 +110,17 @@ pub async fn init_socketio(app_state: &Arc<AppState>) -> SocketIoLayer {
 }
 
 fn handle_socket_connection(socket: SocketRef, app_state: Arc<AppState>) {
+    if let Err(err) = authorize_socket(&socket, &app_state) {
+        tracing::warn!(
+            "Rejecting unauthorized socket socket={:?} namespace={} error={}",
+            socket.id,
+            socket.ns(),
+            err
+        );
+        let _ = socket.disconnect();
+        return;
+    }
+
     let namespace_id = match NamespaceId::from_str(socket.ns()) {
         Ok(namespace_id) => namespace_id,
         Err(err) => {
@@ -52,13 +129,60 @@ fn handle_socket_connection(socket: SocketRef, app_state: Arc<AppState>) {
         }
     };
 
+    // The socket queue addresses sockets by their id room to send an event to several of
+    // them as one encoded packet
+    socket.join(socket.id);
+
     // Setup disconnection handler
     setup_disconnection(socket.clone(), namespace_id.clone(), app_state.clone());
 
+    // Setup chart subscriptions
+    setup_charts(&socket, &namespace_id, &app_state);
+
+    // Setup machine mutations
+    setup_mutations(&socket, &namespace_id, &app_state);
+
     // Setup connection
     setup_connection(socket, namespace_id, app_state);
 }
 
+/// Sockets need at least a viewer session, passed as `token` query parameter
+/// or `Authorization: Bearer <token>` header
+fn authorize_socket(socket: &SocketRef, app_state: &Arc<AppState>) -> Result<(), AuthError> {
+    app_state
+        .auth
+        .read_blocking()
+        .authorize(socket_token(socket), Role::Viewer)
+        .map(|_| ())
+}
+
+fn socket_token(socket: &SocketRef) -> Option<&str> {
+    let parts = socket.req_parts();
+    let query_token = parts.uri.query().and_then(|query| {
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("token="))
+    });
+    bearer_token(&parts.headers).or(query_token)
+}
+
+/// Units the client wants values in, passed as `units` query parameter, e.g. `units=imperial`
+///
+/// Unknown values fall back to metric units.
+pub fn unit_system(socket: &SocketRef) -> UnitSystem {
+    socket
+        .req_parts()
+        .uri
+        .query()
+        .and_then(|query| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("units="))
+        })
+        .and_then(|units| units.parse().ok())
+        .unwrap_or_default()
+}
+
 fn setup_disconnection(socket: SocketRef, namespace_id: NamespaceId, app_state: Arc<AppState>) {
     socket.on_disconnect(move |socket: SocketRef| {
         let namespace_id = namespace_id.clone();
@@ -97,6 +221,101 @@ fn setup_disconnection(socket: SocketRef, namespace_id: NamespaceId, app_state:
     });
 }
 
+/// Charts of a single value the server decimates for the socket, see [`ChartSubscription`]
+fn setup_charts(socket: &SocketRef, namespace_id: &NamespaceId, app_state: &Arc<AppState>) {
+    let subscribe_namespace_id = namespace_id.clone();
+    let subscribe_app_state = app_state.clone();
+    socket.on(
+        SUBSCRIBE_CHART,
+        move |socket: SocketRef, Data(subscription): Data<ChartSubscription>| {
+            let namespace_id = subscribe_namespace_id.clone();
+            let app_state = subscribe_app_state.clone();
+            smol::spawn(async move {
+                let mut socketio_namespaces_guard =
+                    app_state.socketio_setup.namespaces.write().await;
+                socketio_namespaces_guard
+                    .apply_mut(namespace_id.clone(), &app_state, |namespace_interface| {
+                        let result = namespace_interface.and_then(|namespace_interface| {
+                            namespace_interface.subscribe_chart(socket.clone(), subscription)
+                        });
+                        if let Err(err) = result {
+                            tracing::warn!(
+                                "Failed to subscribe chart socket={:?} namespace={} error={:?}",
+                                socket.id,
+                                namespace_id,
+                                err
+                            );
+                        }
+                    })
+                    .await;
+            })
+            .detach();
+        },
+    );
+
+    let unsubscribe_namespace_id = namespace_id.clone();
+    let unsubscribe_app_state = app_state.clone();
+    socket.on(
+        UNSUBSCRIBE_CHART,
+        move |socket: SocketRef, Data(id): Data<String>| {
+            let namespace_id = unsubscribe_namespace_id.clone();
+            let app_state = unsubscribe_app_state.clone();
+            smol::spawn(async move {
+                let mut socketio_namespaces_guard =
+                    app_state.socketio_setup.namespaces.write().await;
+                socketio_namespaces_guard
+                    .apply_mut(namespace_id, &app_state, |namespace_interface| {
+                        if let Ok(namespace_interface) = namespace_interface {
+                            namespace_interface.unsubscribe_chart(&socket, &id);
+                        }
+                    })
+                    .await;
+            })
+            .detach();
+        },
+    );
+}
+
+/// Mutations sent on a machine namespace, authorized with the session of the socket
+fn setup_mutations(socket: &SocketRef, namespace_id: &NamespaceId, app_state: &Arc<AppState>) {
+    let NamespaceId::Machine(machine_identification_unique) = namespace_id else {
+        return;
+    };
+    let machine_identification_unique = machine_identification_unique.clone();
+    let app_state = app_state.clone();
+    socket.on(
+        MUTATE,
+        move |socket: SocketRef, Data(data): Data<Value>, ack: AckSender| {
+            let body = MachineMutationBody {
+                machine_identification_unique: machine_identification_unique.clone(),
+                data,
+            };
+            let app_state = app_state.clone();
+            smol::spawn(async move {
+                let result = async {
+                    authorize_machine_mutation(&app_state, socket_token(&socket), &body).await?;
+                    mutate_machine(&app_state, body)
+                        .await
+                        .map_err(ResponseUtilError::from)
+                }
+                .await;
+                let response = match result {
+                    Ok(_) => MutationResponse::success(),
+                    Err(e) => MutationResponse::from(e),
+                };
+                if let Err(err) = ack.send(&response) {
+                    tracing::warn!(
+                        "Failed to acknowledge mutation socket={:?} error={}",
+                        socket.id,
+                        err
+                    );
+                }
+            })
+            .detach();
+        },
+    );
+}
+
 fn setup_connection(socket: SocketRef, namespace_id: NamespaceId, app_state: Arc<AppState>) {
     // Spawn async task to avoid blocking and potential deadlocks
     let socket_clone = socket.clone();
@@ -1,6 +1,9 @@
 use std::sync::Arc;
 
-use control_core::{machines::identification::MachineIdentificationUnique, socketio::event::Event};
+use control_core::{
+    machines::identification::{MachineCapabilities, MachineIdentificationUnique},
+    socketio::event::Event,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::app_state::AppState;
@@ -14,6 +17,9 @@ pub struct MachinesEvent {
 pub struct MachineObj {
     pub machine_identification_unique: MachineIdentificationUnique,
     pub error: Option<String>,
+    /// Hardware variant of the machine, peers of older servers don't send it
+    #[serde(default)]
+    pub capabilities: MachineCapabilities,
 }
 pub struct MachinesEventBuilder();
 
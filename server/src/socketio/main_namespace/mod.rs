@@ -3,6 +3,7 @@ use std::sync::Arc;
 use control_core::socketio::{
     event::{Event, GenericEvent},
     namespace::{CacheFn, CacheableEvents, Namespace, NamespaceCacheingLogic, cache_one_event},
+    namespace_id::NamespaceId,
 };
 use ethercat_devices_event::EthercatDevicesEvent;
 use ethercat_interface_discovery_event::EthercatInterfaceDiscoveryEvent;
@@ -22,7 +23,7 @@ pub struct MainRoom {
 impl MainRoom {
     pub fn new(socket_queue_tx: Sender<(SocketRef, Arc<GenericEvent>)>) -> Self {
         Self {
-            namespace: Namespace::new(socket_queue_tx),
+            namespace: Namespace::new(socket_queue_tx).with_id(NamespaceId::Main),
         }
     }
 }
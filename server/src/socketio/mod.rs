@@ -2,3 +2,6 @@ pub mod init;
 pub mod main_namespace;
 pub mod namespaces;
 pub mod queue;
+pub mod rate_limits;
+pub mod recording;
+pub mod registry_namespace;
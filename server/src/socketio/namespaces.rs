@@ -1,24 +1,102 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use control_core::{
-    machines::connection::MachineConnection,
-    socketio::{event::GenericEvent, namespace::Namespace, namespace_id::NamespaceId},
+    machines::{connection::MachineConnection, identification::MachineIdentificationUnique},
+    socketio::{
+        event::GenericEvent,
+        namespace::{Namespace, cache_one_event},
+        namespace_id::NamespaceId,
+        rate_limit::EmitRateLimits,
+    },
 };
 use smol::channel::Sender;
 use socketioxide::extract::SocketRef;
 
-use crate::app_state;
+use crate::{
+    alarms::api::AlarmsRoom, app_state, batches::api::BatchesRoom, recipes::api::RecipesRoom,
+    sequences::api::SequencesRoom, serial::sniffer::api::DiagnosticsRoom,
+};
 
-use super::main_namespace::MainRoom;
+use super::{main_namespace::MainRoom, registry_namespace::RegistryRoom};
 
 pub struct Namespaces {
     pub main_namespace: MainRoom,
+    pub recipes_namespace: RecipesRoom,
+    pub batches_namespace: BatchesRoom,
+    pub alarms_namespace: AlarmsRoom,
+    pub registry_namespace: RegistryRoom,
+    pub diagnostics_namespace: DiagnosticsRoom,
+    pub sequences_namespace: SequencesRoom,
+    /// Machines of other control servers by server name, see [`crate::federation`]
+    pub remote_namespaces: HashMap<(String, MachineIdentificationUnique), Namespace>,
+    socket_queue_tx: Sender<(SocketRef, Arc<GenericEvent>)>,
 }
 
 impl Namespaces {
     pub fn new(socket_queue_tx: Sender<(SocketRef, Arc<GenericEvent>)>) -> Self {
         Self {
-            main_namespace: MainRoom::new(socket_queue_tx),
+            main_namespace: MainRoom::new(socket_queue_tx.clone()),
+            recipes_namespace: RecipesRoom::new(socket_queue_tx.clone()),
+            batches_namespace: BatchesRoom::new(socket_queue_tx.clone()),
+            alarms_namespace: AlarmsRoom::new(socket_queue_tx.clone()),
+            registry_namespace: RegistryRoom::new(socket_queue_tx.clone()),
+            diagnostics_namespace: DiagnosticsRoom::new(socket_queue_tx.clone()),
+            sequences_namespace: SequencesRoom::new(socket_queue_tx.clone()),
+            remote_namespaces: HashMap::new(),
+            socket_queue_tx,
+        }
+    }
+
+    /// Creates the namespaces of new machines of a server and disconnects the clients of
+    /// machines it no longer has
+    pub fn sync_remote(&mut self, server: &str, machines: &[MachineIdentificationUnique]) {
+        self.remote_namespaces
+            .retain(|(remote_server, machine), namespace| {
+                let keep = remote_server != server || machines.contains(machine);
+                if !keep {
+                    namespace.disconnect_all();
+                }
+                keep
+            });
+        for machine in machines {
+            self.remote_namespaces
+                .entry((server.to_string(), machine.clone()))
+                .or_insert_with(|| {
+                    Namespace::new(self.socket_queue_tx.clone()).with_id(NamespaceId::Remote {
+                        server: server.to_string(),
+                        machine: machine.clone(),
+                    })
+                });
+        }
+    }
+
+    /// Emits an event of a machine of another server, the latest value per event is cached
+    pub fn emit_remote(
+        &mut self,
+        server: &str,
+        machine: &MachineIdentificationUnique,
+        event: GenericEvent,
+    ) {
+        if let Some(namespace) = self
+            .remote_namespaces
+            .get_mut(&(server.to_string(), machine.clone()))
+        {
+            namespace.emit(Arc::new(event), &cache_one_event());
+        }
+    }
+
+    /// Replaces the emit rates of all namespaces that are not machine namespaces
+    pub fn apply_rate_limits(&mut self, limits: &EmitRateLimits) {
+        for namespace in [
+            &mut self.main_namespace.namespace,
+            &mut self.recipes_namespace.namespace,
+            &mut self.batches_namespace.namespace,
+            &mut self.alarms_namespace.namespace,
+            &mut self.registry_namespace.namespace,
+            &mut self.diagnostics_namespace.namespace,
+            &mut self.sequences_namespace.namespace,
+        ] {
+            namespace.apply_rate_limits(limits);
         }
     }
 
@@ -30,6 +108,24 @@ impl Namespaces {
     ) {
         match namespace_id {
             NamespaceId::Main => callback(Ok(&mut self.main_namespace.namespace)),
+            NamespaceId::Recipes => callback(Ok(&mut self.recipes_namespace.namespace)),
+            NamespaceId::Batches => callback(Ok(&mut self.batches_namespace.namespace)),
+            NamespaceId::Alarms => callback(Ok(&mut self.alarms_namespace.namespace)),
+            NamespaceId::Registry => callback(Ok(&mut self.registry_namespace.namespace)),
+            NamespaceId::Diagnostics => callback(Ok(&mut self.diagnostics_namespace.namespace)),
+            NamespaceId::Sequences => callback(Ok(&mut self.sequences_namespace.namespace)),
+            NamespaceId::Remote { server, machine } => {
+                let key = (server, machine);
+                match self.remote_namespaces.get_mut(&key) {
+                    Some(namespace) => callback(Ok(namespace)),
+                    None => callback(Err(anyhow::anyhow!(
+                        "[{}::Namespaces::appply_mut] Machine {:?} of {} not found",
+                        module_path!(),
+                        key.1,
+                        key.0
+                    ))),
+                }
+            }
             NamespaceId::Machine(machine_identification_unique) => {
                 // Lock machines and work directly with the reference to avoid cloning issues
                 let machines_guard = app_state.machines.read().await;
@@ -67,7 +163,10 @@ impl Namespaces {
                         )));
                         return;
                     }
-                    MachineConnection::Connected(mutex) => mutex,
+                    // degraded machines keep their namespace, clients stay subscribed
+                    MachineConnection::Connected(mutex) | MachineConnection::Degraded(mutex) => {
+                        mutex
+                    }
                 };
                 let mut machine_guard = machine.lock().await;
                 let namespace = machine_guard.api_event_namespace();
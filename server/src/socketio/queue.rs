@@ -1,67 +1,232 @@
 use crate::{
     app_state::AppState,
+    config::config,
     panic::{PanicDetails, send_panic},
+    socketio::init::unit_system,
+};
+use control_core::socketio::{
+    client_queue::ClientQueue,
+    delivery::{Delivery, coalesce},
+    event::GenericEvent,
+    units::{UnitSystem, convert_event},
 };
 use smol::channel::Sender;
-use std::{sync::Arc, time::Instant};
-use tracing::{debug, error, info, instrument, trace};
+use socketioxide::{SendError, SocketError, extract::SocketRef, socket::Sid};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::{debug, error, info, instrument, trace, warn};
+
+/// Maximum number of queued events taken at once to coalesce them into deliveries
+const MAX_QUEUE_DRAIN: usize = 1024;
+
+/// How long sockets with a full channel wait before they are tried again
+const RETRY_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Copy of the event in the units of the client, `None` for metric clients
+fn converted_event(event: &GenericEvent, system: UnitSystem) -> Option<serde_json::Value> {
+    match system {
+        UnitSystem::Metric => None,
+        system => serde_json::to_value(event).ok().map(|mut value| {
+            convert_event(&mut value, system);
+            value
+        }),
+    }
+}
+
+/// Events waiting for a socket, see [`ClientQueue`]
+struct Client {
+    socket: SocketRef,
+    units: UnitSystem,
+    queue: ClientQueue,
+}
+
+impl Client {
+    fn new(socket: SocketRef, capacity: usize) -> Self {
+        Self {
+            units: unit_system(&socket),
+            socket,
+            queue: ClientQueue::new(capacity),
+        }
+    }
+}
+
+enum Emitted {
+    Sent,
+    /// The socket channel is full, the event has to be sent again later
+    Full,
+    /// Sending failed for good, e.g. the socket closed
+    Dropped,
+}
 
-/// Send a single event with retry logic
+/// Send the event of a delivery to all of its sockets
+///
+/// Sockets wanting the same units get the event as one packet, encoded once and shared.
 #[instrument(skip_all)]
-async fn send_event_with_retry(
-    socket: &socketioxide::extract::SocketRef,
-    event: &Arc<control_core::socketio::event::GenericEvent>,
-) {
-    // retry loop for each event
-    loop {
-        // check if socket is still connected
-        if !socket.connected() {
+async fn send_delivery(delivery: Delivery<SocketRef>, clients: &HashMap<Sid, Client>) {
+    let Delivery { event, recipients } = delivery;
+    let mut groups: Vec<(UnitSystem, Vec<SocketRef>)> = vec![];
+    for socket in recipients {
+        let system = clients
+            .get(&socket.id)
+            .map_or_else(UnitSystem::default, |client| client.units);
+        match groups.iter_mut().find(|(s, _)| *s == system) {
+            Some((_, sockets)) => sockets.push(socket),
+            None => groups.push((system, vec![socket])),
+        }
+    }
+    for (system, sockets) in groups {
+        broadcast_event(&sockets, &event, system).await;
+    }
+}
+
+/// Send an event to several sockets of a namespace through their id rooms
+///
+/// Unlike [`try_emit`] a full socket channel is not retried, the event is dropped for that
+/// client. Only used for droppable events.
+async fn broadcast_event(sockets: &[SocketRef], event: &Arc<GenericEvent>, system: UnitSystem) {
+    let rooms: Vec<String> = sockets.iter().map(|socket| socket.id.to_string()).collect();
+    let operators = sockets[0].within(rooms);
+    let result = match converted_event(event, system) {
+        Some(value) => operators.emit("event", &value).await,
+        None => operators.emit("event", event.as_ref()).await,
+    };
+    match result {
+        Ok(()) => trace!(
+            sockets = sockets.len(),
+            event = %event.name,
+            timestamp = event.ts,
+            "Successfully broadcasted event"
+        ),
+        Err(e) => debug!(
+            sockets = sockets.len(),
+            event = %event.name,
+            error = %e,
+            "Failed to broadcast event"
+        ),
+    }
+}
+
+/// Send a single event without waiting for room in the socket channel
+fn try_emit(socket: &SocketRef, event: &GenericEvent, units: UnitSystem) -> Emitted {
+    // clients preferring other units get a converted copy of the event
+    let result = match converted_event(event, units) {
+        Some(value) => socket.emit("event", &value),
+        None => socket.emit("event", event),
+    };
+    match result {
+        Ok(()) => {
+            trace!(
+                socket_id = ?socket.id,
+                event = %event.name,
+                timestamp = event.ts,
+                "Successfully emitted event"
+            );
+            Emitted::Sent
+        }
+        Err(SendError::Socket(SocketError::InternalChannelFull)) => Emitted::Full,
+        Err(e) => {
+            // no reason in retrying serialization errors or closed sockets
             trace!(
                 socket_id = ?socket.id,
                 event = %event.name,
-                "Socket disconnected, skipping event"
+                error = %e,
+                "Failed to emit event, skipping event"
             );
-            break; // Exit the loop if the socket is not connected
+            Emitted::Dropped
         }
+    }
+}
 
-        match socket.emit("event", event.as_ref()) {
-            Ok(_) => {
-                trace!(
-                    socket_id = ?socket.id,
-                    event = %event.name,
-                    timestamp = event.ts,
-                    "Successfully emitted event"
-                );
-                break; // Successfully emitted, exit loop
+/// Send queued events until every queue is empty or its socket channel is full
+///
+/// A full socket is skipped until the next call instead of retried, so it doesn't hold back
+/// the other clients. Live values at the front of several queues go out as one packet, see
+/// [`send_delivery`]. Returns the number of events sent.
+async fn send_queued(clients: &mut HashMap<Sid, Client>) -> usize {
+    let mut full: HashSet<Sid> = HashSet::new();
+    let mut sent = 0;
+    loop {
+        let mut progress = false;
+
+        let mut fronts: Vec<(SocketRef, Arc<GenericEvent>)> = clients
+            .values()
+            .filter(|client| !full.contains(&client.socket.id))
+            .filter_map(|client| {
+                let event = client.queue.front().filter(|event| event.droppable)?;
+                Some((client.socket.clone(), event.clone()))
+            })
+            .collect();
+        // coalescing only merges neighbours
+        fronts.sort_by_key(|(_, event)| Arc::as_ptr(event) as usize);
+        for delivery in coalesce(fronts) {
+            if delivery.recipients.len() < 2 {
+                continue;
             }
-            Err(e) => match e {
-                socketioxide::SendError::Serialize(serialize_error) => {
-                    trace!(
-                        socket_id = ?socket.id,
-                        event = %event.name,
-                        error = %serialize_error,
-                        "Serialization error, skipping event"
-                    );
-                    break; // no reason in retrying serialization errors
+            for socket in &delivery.recipients {
+                if let Some(client) = clients.get_mut(&socket.id) {
+                    client.queue.pop_front();
                 }
-                socketioxide::SendError::Socket(socket_error) => match socket_error {
-                    socketioxide::SocketError::InternalChannelFull => {
-                        continue; // Retry sending the event
-                    }
-                    socketioxide::SocketError::Closed => {
-                        trace!(
-                            socket_id = ?socket.id,
-                            event = %event.name,
-                            "Socket closed, skipping event"
-                        );
-                        break; // Socket is closed, no point in retrying
-                    }
-                },
-            },
+            }
+            sent += delivery.recipients.len();
+            progress = true;
+            send_delivery(delivery, clients).await;
+        }
+
+        for client in clients.values_mut() {
+            if full.contains(&client.socket.id) {
+                continue;
+            }
+            let Some(event) = client.queue.front() else {
+                continue;
+            };
+            match try_emit(&client.socket, event, client.units) {
+                Emitted::Sent => {
+                    sent += 1;
+                    client.queue.pop_front();
+                    progress = true;
+                }
+                Emitted::Dropped => {
+                    client.queue.pop_front();
+                    progress = true;
+                }
+                Emitted::Full => {
+                    full.insert(client.socket.id);
+                }
+            }
+        }
+
+        if !progress {
+            return sent;
         }
     }
 }
 
+/// Forget disconnected sockets and disconnect the ones that can't keep up, they get the
+/// current state again when they reconnect
+fn drop_stalled(clients: &mut HashMap<Sid, Client>, stall_timeout: Duration) {
+    let now = Instant::now();
+    clients.retain(|_, client| {
+        if !client.socket.connected() {
+            return false;
+        }
+        if !client.queue.is_stalled(now, stall_timeout) {
+            return true;
+        }
+        warn!(
+            socket_id = ?client.socket.id,
+            namespace = client.socket.ns(),
+            queued = client.queue.len(),
+            dropped = client.queue.dropped,
+            "Disconnecting socket that can't keep up"
+        );
+        let _ = client.socket.clone().disconnect();
+        false
+    });
+}
+
 pub fn init_socketio_queue(thread_panic_tx: Sender<PanicDetails>, app_state: Arc<AppState>) {
     std::thread::Builder::new()
         .name("socketio-queue".to_string())
@@ -79,36 +244,70 @@ pub fn init_socketio_queue(thread_panic_tx: Sender<PanicDetails>, app_state: Arc
 
                 let mut event_count = 0;
                 let mut batch_start = Instant::now();
+                let mut clients: HashMap<Sid, Client> = HashMap::new();
 
                 loop {
-                    match app_state.socketio_setup.socket_queue_rx.recv().await {
-                        Ok((socket, event)) => {
-                            event_count += 1;
-
-                            // Handle the received message with retry logic
-                            send_event_with_retry(&socket, &event).await;
-
-                            // Log batch statistics every 5 seconds
-                            if batch_start.elapsed().as_secs() >= 5 {
-                                let elapsed = batch_start.elapsed();
-                                if event_count > 0 {
-                                    debug!(
-                                        "[{}::init_socketio_queue] Processed {} events in {:.2?} ({:.1} events/s)",
-                                        module_path!(),
-                                        event_count,
-                                        elapsed,
-                                        event_count as f64 / elapsed.as_secs_f64(),
-                                    );
+                    let queue_rx = &app_state.socketio_setup.socket_queue_rx;
+                    let socketio_config = config().socketio.clone();
+
+                    // sockets with a full channel are retried shortly, otherwise wait for events
+                    let received = if clients.values().any(|client| !client.queue.is_empty()) {
+                        tokio::time::timeout(RETRY_INTERVAL, queue_rx.recv())
+                            .await
+                            .ok()
+                    } else {
+                        Some(queue_rx.recv().await)
+                    };
+                    match received {
+                        Some(Ok(first)) => {
+                            // take what else is queued so events for many sockets are
+                            // encoded once
+                            let now = Instant::now();
+                            let mut item = Some(first);
+                            let mut drained = 0;
+                            while let Some((socket, event)) = item {
+                                clients
+                                    .entry(socket.id)
+                                    .or_insert_with(|| {
+                                        Client::new(socket, socketio_config.client_queue_capacity)
+                                    })
+                                    .queue
+                                    .push(event, now);
+                                drained += 1;
+                                if drained >= MAX_QUEUE_DRAIN {
+                                    break;
                                 }
-                                event_count = 0;
-                                batch_start = Instant::now();
+                                item = queue_rx.try_recv().ok();
                             }
                         }
-                        Err(e) => {
+                        Some(Err(e)) => {
                             error!(error = %e, "Error receiving from global socketio queue");
                             info!("SocketIO global queue listener stopping");
                             break;
                         }
+                        None => {}
+                    }
+
+                    event_count += send_queued(&mut clients).await;
+                    drop_stalled(
+                        &mut clients,
+                        Duration::from_millis(socketio_config.stall_timeout_ms),
+                    );
+
+                    // Log batch statistics every 5 seconds
+                    if batch_start.elapsed().as_secs() >= 5 {
+                        let elapsed = batch_start.elapsed();
+                        if event_count > 0 {
+                            debug!(
+                                "[{}::init_socketio_queue] Processed {} events in {:.2?} ({:.1} events/s)",
+                                module_path!(),
+                                event_count,
+                                elapsed,
+                                event_count as f64 / elapsed.as_secs_f64(),
+                            );
+                        }
+                        event_count = 0;
+                        batch_start = Instant::now();
                     }
                 }
             });
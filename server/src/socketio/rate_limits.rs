@@ -0,0 +1,25 @@
+use crate::storage;
+use control_core::socketio::rate_limit::{EmitRateLimits, set_emit_rate_limits};
+
+/// File inside [`crate::storage::data_dir`] with the maximum emit rates by event name
+///
+/// Replaces the default limits, e.g. `{"max_rate_hz": {"LiveValuesEvent": 10.0}}`. Events in
+/// `adaptive` slow down while their value is stable, e.g.
+/// `{"adaptive": {"LiveValuesEvent": {"heartbeat_hz": 1.0, "min_change": 0.002}}}`.
+pub const EMIT_RATES_FILE: &str = "emit_rates.json";
+
+/// Reads [`EMIT_RATES_FILE`], the defaults if it does not exist
+pub fn read_emit_rates() -> Result<EmitRateLimits, anyhow::Error> {
+    let path = storage::data_dir().join(EMIT_RATES_FILE);
+    Ok(storage::read_json::<EmitRateLimits>(&path)?.unwrap_or_default())
+}
+
+/// Applies [`EMIT_RATES_FILE`] to all namespaces, must run before the namespaces are created
+pub fn init_emit_rates() {
+    let limits = read_emit_rates().unwrap_or_else(|e| {
+        tracing::error!("Failed to read emit rates, using defaults: {:?}", e);
+        EmitRateLimits::default()
+    });
+    tracing::info!("Limiting emit rates to {:?}", limits.max_rate_hz);
+    set_emit_rate_limits(limits);
+}
@@ -0,0 +1,183 @@
+//! Session recordings of the socketio traffic, see [`control_core::socketio::recorder`]
+//!
+//! A recording is a gzip compressed file of JSON lines in [`RECORDINGS_DIR`], one
+//! [`RecordedEntry`] per line in the order they were emitted. The `replay` binary of the `hil`
+//! crate feeds it into a server running `--simulate`, so a bug seen in the field can be
+//! reproduced at the desk.
+
+use crate::storage;
+use control_core::{
+    helpers::clock::unix_millis,
+    socketio::recorder::{RecordedEntry, dropped_entries, set_recorder},
+};
+use flate2::{Compression, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+use smol::channel::Receiver;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    sync::{LazyLock, Mutex},
+};
+
+/// Directory inside [`storage::data_dir`] the recordings are written to
+pub const RECORDINGS_DIR: &str = "recordings";
+
+/// Entries waiting for the writer, entries are dropped if the disk falls behind
+const ENTRY_QUEUE_LEN: usize = 16_384;
+
+static STATE: LazyLock<Mutex<RecordingState>> =
+    LazyLock::new(|| Mutex::new(RecordingState::default()));
+
+#[derive(Deserialize, Serialize, Debug)]
+pub enum Mutation {
+    /// Starts a new recording file
+    Start,
+    /// Stops the recording, the file is complete once the queued entries are written
+    Stop,
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordingState {
+    pub active: bool,
+    /// Name of the current or last file in [`RECORDINGS_DIR`]
+    pub file: Option<String>,
+    /// unix timestamp in milliseconds
+    pub started_at: Option<u64>,
+    /// Entries dropped because the writer fell behind
+    pub dropped: u64,
+}
+
+pub fn state() -> RecordingState {
+    let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    if state.active {
+        state.dropped = dropped_entries();
+    }
+    state
+}
+
+/// Starts recording every emitted event and received machine mutation to a new file
+pub fn start() -> Result<RecordingState, anyhow::Error> {
+    let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    if state.active {
+        return Err(anyhow::anyhow!(
+            "[{}::start] Already recording to {}",
+            module_path!(),
+            state.file.as_deref().unwrap_or_default()
+        ));
+    }
+
+    let dir = storage::data_dir().join(RECORDINGS_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let started_at = unix_millis();
+    let name = format!("session-{}.jsonl.gz", started_at);
+    let file = File::create(dir.join(&name)).map_err(|e| {
+        anyhow::anyhow!(
+            "[{}::start] Failed to create recording {}: {}",
+            module_path!(),
+            name,
+            e
+        )
+    })?;
+    let encoder = GzEncoder::new(BufWriter::new(file), Compression::default());
+
+    let (tx, rx) = smol::channel::bounded(ENTRY_QUEUE_LEN);
+    let writer_name = name.clone();
+    std::thread::Builder::new()
+        .name("session-recorder".to_owned())
+        .spawn(move || write_recording(&writer_name, encoder, rx))
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::start] Failed to spawn recording thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+    set_recorder(Some(tx));
+
+    tracing::info!("Recording session to {}", name);
+    *state = RecordingState {
+        active: true,
+        file: Some(name),
+        started_at: Some(started_at),
+        dropped: 0,
+    };
+    Ok(state.clone())
+}
+
+/// Stops the recording, returns if one was running
+pub fn stop() -> bool {
+    let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    if !state.active {
+        return false;
+    }
+    state.dropped = dropped_entries();
+    state.active = false;
+    // closes the channel, the writer finishes the file once it is empty
+    set_recorder(None);
+    tracing::info!(
+        "Stopped recording session to {}",
+        state.file.as_deref().unwrap_or_default()
+    );
+    true
+}
+
+fn write_recording(
+    name: &str,
+    mut encoder: GzEncoder<BufWriter<File>>,
+    rx: Receiver<RecordedEntry>,
+) {
+    let mut entries = 0u64;
+    while let Ok(entry) = rx.recv_blocking() {
+        if let Err(e) = write_entry(&mut encoder, &entry) {
+            tracing::error!("Failed to write recording {}: {:?}", name, e);
+            // a new recording may have started in the meantime
+            if state().file.as_deref() == Some(name) {
+                stop();
+            }
+            return;
+        }
+        entries += 1;
+    }
+    match encoder.finish().and_then(|mut writer| writer.flush()) {
+        Ok(()) => tracing::info!("Recorded {} entries to {}", entries, name),
+        Err(e) => tracing::error!("Failed to finish recording {}: {}", name, e),
+    }
+}
+
+fn write_entry(writer: &mut impl Write, entry: &RecordedEntry) -> Result<(), anyhow::Error> {
+    serde_json::to_writer(&mut *writer, entry)?;
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use control_core::socketio::{namespace_id::NamespaceId, recorder::RecordedKind};
+    use flate2::read::GzDecoder;
+    use serde_json::json;
+    use std::io::{BufRead, BufReader};
+
+    #[test]
+    fn test_write_entry() {
+        let entry = RecordedEntry {
+            ts: 1_700_000_000_000,
+            mono: 20,
+            namespace: NamespaceId::Alarms,
+            kind: RecordedKind::Event {
+                name: "AlarmsEvent".to_string(),
+                data: json!({ "active": [] }),
+            },
+        };
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        write_entry(&mut encoder, &entry).unwrap();
+        write_entry(&mut encoder, &entry).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let lines: Vec<RecordedEntry> = BufReader::new(GzDecoder::new(compressed.as_slice()))
+            .lines()
+            .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+            .collect();
+        assert_eq!(lines, vec![entry.clone(), entry]);
+    }
+}
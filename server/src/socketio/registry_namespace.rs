@@ -0,0 +1,282 @@
+use std::{collections::HashSet, sync::Arc};
+
+use control_core::{
+    machines::{connection::MachineConnection, identification::MachineIdentificationUnique},
+    socketio::{
+        event::{BuildEvent, Event, GenericEvent},
+        namespace::{CacheFn, CacheableEvents, Namespace, NamespaceCacheingLogic, cache_one_event},
+        namespace_id::NamespaceId,
+    },
+};
+use control_core_derive::BuildEvent;
+use serde::Serialize;
+use smol::channel::Sender;
+use socketioxide::extract::SocketRef;
+use tracing::instrument;
+
+use crate::{
+    app_state::AppState,
+    machines::{MachineCapability, machine_capabilities, machine_slug},
+};
+
+/// Client message answered with a [`MachineListEvent`] to the asking socket
+pub const QUERY_MACHINES: &str = "QueryMachines";
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct RegistryMachine {
+    pub machine_identification_unique: MachineIdentificationUnique,
+    pub slug: Option<&'static str>,
+    /// Namespace the machine emits its events on
+    pub namespace: NamespaceId,
+    pub connected: bool,
+    /// Hardware is gone, the machine keeps its configuration until it reconnects
+    pub degraded: bool,
+    pub error: Option<String>,
+    pub capabilities: &'static [MachineCapability],
+}
+
+/// All known machines, including disconnected ones
+#[derive(Serialize, Debug, Clone, BuildEvent)]
+pub struct MachineListEvent {
+    pub machines: Vec<RegistryMachine>,
+}
+
+#[derive(Serialize, Debug, Clone, BuildEvent)]
+pub struct MachineConnectedEvent {
+    pub machine: RegistryMachine,
+}
+
+#[derive(Serialize, Debug, Clone, BuildEvent)]
+pub struct MachineDisconnectedEvent {
+    pub machine_identification_unique: MachineIdentificationUnique,
+    /// Error of the machine if it did not disconnect cleanly
+    pub error: Option<String>,
+}
+
+pub enum RegistryNamespaceEvents {
+    MachineList(Event<MachineListEvent>),
+    MachineConnected(Event<MachineConnectedEvent>),
+    MachineDisconnected(Event<MachineDisconnectedEvent>),
+}
+
+impl CacheableEvents<Self> for RegistryNamespaceEvents {
+    fn event_value(&self) -> GenericEvent {
+        match self {
+            Self::MachineList(event) => event.into(),
+            Self::MachineConnected(event) => event.into(),
+            Self::MachineDisconnected(event) => event.into(),
+        }
+    }
+
+    fn event_cache_fn(&self) -> CacheFn {
+        match self {
+            Self::MachineList(_) => cache_one_event(),
+            Self::MachineConnected(_) => cache_one_event(),
+            Self::MachineDisconnected(_) => cache_one_event(),
+        }
+    }
+}
+
+pub struct RegistryRoom {
+    pub namespace: Namespace,
+    /// Machines that were connected at the last sync
+    connected: HashSet<MachineIdentificationUnique>,
+}
+
+impl RegistryRoom {
+    pub fn new(socket_queue_tx: Sender<(SocketRef, Arc<GenericEvent>)>) -> Self {
+        Self {
+            namespace: Namespace::new(socket_queue_tx).with_id(NamespaceId::Registry),
+            connected: HashSet::new(),
+        }
+    }
+
+    /// Emits connect and disconnect events for changes since the last sync, then the list
+    pub fn sync(&mut self, machines: Vec<RegistryMachine>) {
+        let (connected, disconnected) = diff(&self.connected, &machines);
+        for machine in connected {
+            tracing::info!(
+                "Machine connected {}",
+                machine.machine_identification_unique
+            );
+            let event = MachineConnectedEvent { machine }.build();
+            self.emit(RegistryNamespaceEvents::MachineConnected(event));
+        }
+        for (machine_identification_unique, error) in disconnected {
+            tracing::info!("Machine disconnected {}", machine_identification_unique);
+            let event = MachineDisconnectedEvent {
+                machine_identification_unique,
+                error,
+            }
+            .build();
+            self.emit(RegistryNamespaceEvents::MachineDisconnected(event));
+        }
+
+        self.connected = machines
+            .iter()
+            .filter(|machine| machine.connected)
+            .map(|machine| machine.machine_identification_unique.clone())
+            .collect();
+        let event = MachineListEvent { machines }.build();
+        self.emit(RegistryNamespaceEvents::MachineList(event));
+    }
+}
+
+impl NamespaceCacheingLogic<RegistryNamespaceEvents> for RegistryRoom {
+    #[instrument(skip_all)]
+    fn emit(&mut self, event: RegistryNamespaceEvents) {
+        let generic_event = Arc::new(event.event_value());
+        match event {
+            // connect and disconnect events are not replayed, the list is the current state
+            RegistryNamespaceEvents::MachineConnected(_)
+            | RegistryNamespaceEvents::MachineDisconnected(_) => {
+                self.namespace.emit_transient(generic_event);
+            }
+            RegistryNamespaceEvents::MachineList(_) => {
+                let buffer_fn = event.event_cache_fn();
+                self.namespace.emit(generic_event, &buffer_fn);
+            }
+        }
+    }
+}
+
+/// Machines that connected and machines that disconnected (with their error)
+fn diff(
+    previous: &HashSet<MachineIdentificationUnique>,
+    machines: &[RegistryMachine],
+) -> (
+    Vec<RegistryMachine>,
+    Vec<(MachineIdentificationUnique, Option<String>)>,
+) {
+    let connected = machines
+        .iter()
+        .filter(|machine| {
+            machine.connected && !previous.contains(&machine.machine_identification_unique)
+        })
+        .cloned()
+        .collect();
+
+    let disconnected = previous
+        .iter()
+        .filter_map(|machine_identification_unique| {
+            let machine = machines.iter().find(|machine| {
+                &machine.machine_identification_unique == machine_identification_unique
+            });
+            match machine {
+                Some(machine) if machine.connected => None,
+                Some(machine) => {
+                    Some((machine_identification_unique.clone(), machine.error.clone()))
+                }
+                None => Some((machine_identification_unique.clone(), None)),
+            }
+        })
+        .collect();
+
+    (connected, disconnected)
+}
+
+/// Current state of all machines in the machine manager
+pub fn registry_machines(app_state: &AppState) -> Vec<RegistryMachine> {
+    let machines = app_state.machines.read_blocking();
+    let mut registry_machines: Vec<_> = machines
+        .iter()
+        .map(|(machine_identification_unique, slot)| {
+            let slot = slot.lock_blocking();
+            let machine = machine_identification_unique.machine_identification.machine;
+            RegistryMachine {
+                machine_identification_unique: machine_identification_unique.clone(),
+                slug: machine_slug(machine),
+                namespace: NamespaceId::Machine(machine_identification_unique.clone()),
+                connected: slot.is_connected(),
+                degraded: slot.is_degraded(),
+                error: match &slot.machine_connection {
+                    MachineConnection::Error(e) => Some(e.to_string()),
+                    _ => None,
+                },
+                capabilities: machine_capabilities(machine),
+            }
+        })
+        .collect();
+    registry_machines.sort_by_key(|machine| machine.namespace.to_string());
+    registry_machines
+}
+
+/// Syncs the registry namespace with the machine manager
+///
+/// Called whenever machines are added or removed.
+pub async fn sync_registry(app_state: &Arc<AppState>) {
+    let machines = registry_machines(app_state);
+    app_state
+        .socketio_setup
+        .namespaces
+        .write()
+        .await
+        .registry_namespace
+        .sync(machines);
+}
+
+/// Sends the current machine list to a single socket
+pub fn answer_query(socket: SocketRef, app_state: &AppState) {
+    let event = MachineListEvent {
+        machines: registry_machines(app_state),
+    }
+    .build();
+    let generic_event = Arc::new(GenericEvent::from(&event));
+    if let Err(e) = app_state
+        .socketio_setup
+        .socket_queue_tx
+        .try_send((socket, generic_event))
+    {
+        tracing::error!("Failed to answer machine list query: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use control_core::machines::identification::MachineIdentification;
+
+    fn machine(serial: u16, connected: bool) -> RegistryMachine {
+        let machine_identification_unique = MachineIdentificationUnique {
+            machine_identification: MachineIdentification {
+                vendor: 1,
+                machine: 2,
+            },
+            serial,
+        };
+        RegistryMachine {
+            machine_identification_unique: machine_identification_unique.clone(),
+            slug: machine_slug(2),
+            namespace: NamespaceId::Machine(machine_identification_unique),
+            connected,
+            degraded: false,
+            error: None,
+            capabilities: machine_capabilities(2),
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let previous = HashSet::from([
+            machine(1, true).machine_identification_unique,
+            machine(2, true).machine_identification_unique,
+            machine(3, true).machine_identification_unique,
+        ]);
+        // 1 stays, 2 disconnects, 3 is gone, 4 connects, 5 is known but not connected
+        let machines = [
+            machine(1, true),
+            machine(2, false),
+            machine(4, true),
+            machine(5, false),
+        ];
+
+        let (connected, mut disconnected) = diff(&previous, &machines);
+        assert_eq!(connected, vec![machine(4, true)]);
+        disconnected.sort_by_key(|(machine, _)| machine.serial);
+        let serials: Vec<_> = disconnected
+            .iter()
+            .map(|(machine, _)| machine.serial)
+            .collect();
+        assert_eq!(serials, vec![2, 3]);
+    }
+}
@@ -0,0 +1,287 @@
+use crate::{
+    app_state::AppState,
+    config::{LineStatus, StackLightColor, StackLightOutput, StackLightRule, config},
+    machines::{MACHINE_WINDER_V1, VENDOR_QITECH, winder2::Winder2},
+    panic::{PanicDetails, send_panic},
+};
+use control_core::{
+    alarms::{Alarm, AlarmSeverity},
+    machines::{
+        identification::{MachineIdentification, MachineIdentificationUnique},
+        values::IN_TOLERANCE,
+    },
+};
+use serialport::SerialPort;
+use smol::channel::Sender;
+use std::{
+    io::Write,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+const COLORS: [StackLightColor; 3] = [
+    StackLightColor::Red,
+    StackLightColor::Yellow,
+    StackLightColor::Green,
+];
+
+/// A tolerance older is from a laser that stopped monitoring
+const TOLERANCE_MAX_AGE: Duration = Duration::from_secs(2);
+const BLINK_PERIOD: Duration = Duration::from_secs(1);
+
+/// Status of the line from its alarms and the tolerance of its laser
+///
+/// Acknowledged alarms still count, the light shows the condition until it is gone.
+pub fn line_status(active: &[Alarm], in_tolerance: Option<bool>) -> LineStatus {
+    let severity = active.iter().map(|alarm| alarm.severity).max();
+    match (severity, in_tolerance) {
+        (Some(AlarmSeverity::Critical), _) => LineStatus::Alarm,
+        (Some(AlarmSeverity::Warning), _) => LineStatus::Warning,
+        (_, Some(false)) => LineStatus::OutOfTolerance,
+        (_, Some(true)) => LineStatus::InTolerance,
+        (_, None) => LineStatus::Idle,
+    }
+}
+
+/// Whether each color is lit, blinking colors are lit in the first half of the period
+pub fn lights(
+    rules: &[StackLightRule],
+    status: LineStatus,
+    since_start: Duration,
+) -> [(StackLightColor, bool); 3] {
+    let blink_on =
+        since_start.as_millis() % BLINK_PERIOD.as_millis() < BLINK_PERIOD.as_millis() / 2;
+    COLORS.map(|color| {
+        let lit = rules
+            .iter()
+            .find(|rule| rule.color == color)
+            .is_some_and(|rule| rule.statuses.contains(&status) && (blink_on || !rule.blink));
+        (color, lit)
+    })
+}
+
+const fn winder_signal(color: StackLightColor) -> &'static str {
+    match color {
+        StackLightColor::Red => Winder2::IO_STACK_LIGHT_RED,
+        StackLightColor::Yellow => Winder2::IO_STACK_LIGHT_YELLOW,
+        StackLightColor::Green => Winder2::IO_STACK_LIGHT_GREEN,
+    }
+}
+
+/// Line the serial LED controller receives, e.g. `R1 Y0 G0\n`
+fn serial_line(lights: &[(StackLightColor, bool)]) -> String {
+    let mut line = lights
+        .iter()
+        .map(|(color, lit)| {
+            let letter = match color {
+                StackLightColor::Red => 'R',
+                StackLightColor::Yellow => 'Y',
+                StackLightColor::Green => 'G',
+            };
+            format!("{}{}", letter, u8::from(*lit))
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    line.push('\n');
+    line
+}
+
+/// Drives the configured output, the serial port stays open between calls
+#[derive(Default)]
+struct StackLightDriver {
+    output: Option<StackLightOutput>,
+    port: Option<Box<dyn SerialPort>>,
+}
+
+impl StackLightDriver {
+    async fn drive(
+        &mut self,
+        app_state: &Arc<AppState>,
+        output: Option<&StackLightOutput>,
+        lights: &[(StackLightColor, bool)],
+    ) {
+        if self.output.as_ref() != output {
+            // don't leave the previous output lit
+            if let Some(previous) = self.output.take() {
+                let off = COLORS.map(|color| (color, false));
+                if let Err(e) = self.write(app_state, &previous, &off).await {
+                    tracing::warn!("Failed to switch off the stack light: {:?}", e);
+                }
+            }
+            self.port = None;
+            self.output = output.cloned();
+        }
+        let Some(output) = self.output.clone() else {
+            return;
+        };
+        if let Err(e) = self.write(app_state, &output, lights).await {
+            tracing::warn!("Failed to drive the stack light: {:?}", e);
+            // reopened on the next interval, e.g. after the controller was plugged in again
+            self.port = None;
+        }
+    }
+
+    async fn write(
+        &mut self,
+        app_state: &Arc<AppState>,
+        output: &StackLightOutput,
+        lights: &[(StackLightColor, bool)],
+    ) -> Result<(), anyhow::Error> {
+        match output {
+            StackLightOutput::Winder { serial } => {
+                let machine_identification_unique = MachineIdentificationUnique {
+                    machine_identification: MachineIdentification {
+                        vendor: VENDOR_QITECH,
+                        machine: MACHINE_WINDER_V1,
+                    },
+                    serial: *serial,
+                };
+                let machine = {
+                    let machines = app_state.machines.read().await;
+                    machines
+                        .get(&machine_identification_unique)
+                        .and_then(|slot| slot.lock_blocking().machine_connection.to_machine())
+                };
+                // a disconnected winder has its outputs off anyway
+                let Some(machine) = machine else {
+                    return Ok(());
+                };
+                let machine = machine.lock().await;
+                if let Some(winder) = machine.as_any().downcast_ref::<Winder2>() {
+                    for (color, lit) in lights {
+                        winder.io.set_output(winder_signal(*color), *lit);
+                    }
+                }
+                Ok(())
+            }
+            StackLightOutput::Serial { port, baud_rate } => {
+                if self.port.is_none() {
+                    let opened = serialport::new(port, *baud_rate)
+                        .timeout(Duration::from_millis(100))
+                        .open()
+                        .map_err(|e| {
+                            anyhow::anyhow!(
+                                "[{}::StackLightDriver::write] Failed to open port {}: {}",
+                                module_path!(),
+                                port,
+                                e
+                            )
+                        })?;
+                    self.port = Some(opened);
+                }
+                if let Some(port) = &mut self.port {
+                    port.write_all(serial_line(lights).as_bytes())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Starts driving the stack light, it stays idle while no output is configured
+pub fn init_stack_light(
+    thread_panic_tx: Sender<PanicDetails>,
+    app_state: Arc<AppState>,
+) -> Result<(), anyhow::Error> {
+    std::thread::Builder::new()
+        .name("stack_light".to_owned())
+        .spawn(move || {
+            send_panic(thread_panic_tx);
+            smol::block_on(async {
+                let start = Instant::now();
+                let mut driver = StackLightDriver::default();
+                loop {
+                    let config = config();
+                    let stack_light = &config.stack_light;
+                    smol::Timer::after(Duration::from_millis(stack_light.interval_ms)).await;
+
+                    let now = Instant::now();
+                    let active = app_state.alarms.read().await.active();
+                    let in_tolerance = app_state
+                        .machines
+                        .read()
+                        .await
+                        .values
+                        .latest(IN_TOLERANCE)
+                        .filter(|sample| !sample.is_stale(now, TOLERANCE_MAX_AGE))
+                        .map(|sample| sample.value);
+                    let status = line_status(&active, in_tolerance);
+                    let colors = lights(
+                        &stack_light.lights,
+                        status,
+                        now.saturating_duration_since(start),
+                    );
+                    driver
+                        .drive(&app_state, stack_light.output.as_ref(), &colors)
+                        .await;
+                }
+            });
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_stack_light] Failed to spawn stack light thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::StackLightConfig;
+    use control_core::alarms::AlarmState;
+
+    #[test]
+    fn test_stack_light() {
+        let alarm = |severity| Alarm {
+            id: 1,
+            machine: None,
+            code: "filament_break".to_string(),
+            message: String::new(),
+            severity,
+            state: AlarmState::Acknowledged,
+            raised_at: 0,
+            acknowledged_at: None,
+            acknowledged_by: None,
+            cleared_at: None,
+        };
+        assert_eq!(line_status(&[], None), LineStatus::Idle);
+        assert_eq!(line_status(&[], Some(false)), LineStatus::OutOfTolerance);
+        assert_eq!(
+            line_status(&[alarm(AlarmSeverity::Info)], Some(true)),
+            LineStatus::InTolerance
+        );
+        assert_eq!(
+            line_status(
+                &[
+                    alarm(AlarmSeverity::Warning),
+                    alarm(AlarmSeverity::Critical)
+                ],
+                Some(true)
+            ),
+            LineStatus::Alarm
+        );
+
+        let rules = StackLightConfig::default().lights;
+        let at = Duration::from_millis;
+        assert_eq!(
+            lights(&rules, LineStatus::InTolerance, at(0)),
+            [
+                (StackLightColor::Red, false),
+                (StackLightColor::Yellow, false),
+                (StackLightColor::Green, true)
+            ]
+        );
+        // red blinks by default
+        assert!(lights(&rules, LineStatus::Alarm, at(100))[0].1);
+        assert!(!lights(&rules, LineStatus::Alarm, at(600))[0].1);
+
+        assert_eq!(
+            serial_line(&lights(&rules, LineStatus::Warning, at(0))),
+            "R0 Y1 G0\n"
+        );
+    }
+}
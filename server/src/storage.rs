@@ -0,0 +1,110 @@
+use serde::{Serialize, de::DeserializeOwned};
+use std::path::{Path, PathBuf};
+
+/// Directory for data that has to survive server restarts
+///
+/// systemd exports `STATE_DIRECTORY` when the unit has `StateDirectory=` set.
+/// Outside of systemd (development) we fall back to `./data`.
+pub fn data_dir() -> PathBuf {
+    match std::env::var("STATE_DIRECTORY") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from("data"),
+    }
+}
+
+/// Reads a JSON file
+///
+/// Returns `Ok(None)` if the file does not exist yet.
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, anyhow::Error> {
+    let content = match std::fs::read(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(anyhow::anyhow!(
+                "[{}::read_json] Failed to read {:?}: {}",
+                module_path!(),
+                path,
+                e
+            ));
+        }
+    };
+
+    let value = serde_json::from_slice(&content).map_err(|e| {
+        anyhow::anyhow!(
+            "[{}::read_json] Failed to parse {:?}: {}",
+            module_path!(),
+            path,
+            e
+        )
+    })?;
+
+    Ok(Some(value))
+}
+
+/// Reads a TOML file
+///
+/// Returns `Ok(None)` if the file does not exist yet. Parse errors name the line and column.
+pub fn read_toml<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, anyhow::Error> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(anyhow::anyhow!(
+                "[{}::read_toml] Failed to read {:?}: {}",
+                module_path!(),
+                path,
+                e
+            ));
+        }
+    };
+
+    let value = toml::from_str(&content).map_err(|e| {
+        anyhow::anyhow!(
+            "[{}::read_toml] Failed to parse {:?}: {}",
+            module_path!(),
+            path,
+            e
+        )
+    })?;
+
+    Ok(Some(value))
+}
+
+/// Writes a JSON file
+///
+/// The file is written to a temporary sibling first and then renamed
+/// so a crash never leaves a half written file behind.
+pub fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), anyhow::Error> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::write_json] Failed to create {:?}: {}",
+                module_path!(),
+                parent,
+                e
+            )
+        })?;
+    }
+
+    let content = serde_json::to_vec_pretty(value)?;
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, content).map_err(|e| {
+        anyhow::anyhow!(
+            "[{}::write_json] Failed to write {:?}: {}",
+            module_path!(),
+            tmp_path,
+            e
+        )
+    })?;
+    std::fs::rename(&tmp_path, path).map_err(|e| {
+        anyhow::anyhow!(
+            "[{}::write_json] Failed to move {:?} to {:?}: {}",
+            module_path!(),
+            tmp_path,
+            path,
+            e
+        )
+    })?;
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use crate::{
+    app_state::AppState,
+    panic::{PanicDetails, send_panic},
+};
+use control_core::machines::identification::MachineIdentificationUnique;
+use smol::channel::Sender;
+use std::{
+    collections::HashSet,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Interval the act cycles are checked against the deadline
+const CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+pub fn init_watchdog(
+    thread_panic_tx: Sender<PanicDetails>,
+    app_state: Arc<AppState>,
+) -> Result<(), anyhow::Error> {
+    {
+        let watchdog = app_state.watchdog.read_blocking();
+        tracing::info!(
+            "Supervising act cycles with a deadline of {}ms, safe stop {}",
+            watchdog.config.deadline_ms,
+            if watchdog.config.safe_stop {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        );
+    }
+
+    std::thread::Builder::new()
+        .name("watchdog".to_owned())
+        .spawn(move || {
+            send_panic(thread_panic_tx);
+            smol::block_on(async {
+                // stalled machines that did not reach their safe state yet
+                let mut safe_stop_pending = HashSet::new();
+                loop {
+                    smol::Timer::after(CHECK_INTERVAL).await;
+                    check(&app_state, &mut safe_stop_pending).await;
+                }
+            });
+        })
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "[{}::init_watchdog] Failed to spawn watchdog thread\n{:?}",
+                module_path!(),
+                e
+            )
+        })?;
+
+    Ok(())
+}
+
+async fn check(
+    app_state: &Arc<AppState>,
+    safe_stop_pending: &mut HashSet<MachineIdentificationUnique>,
+) {
+    let machines: Vec<_> = app_state
+        .machines
+        .read()
+        .await
+        .iter()
+        .filter_map(|(machine_identification_unique, slot)| {
+            let machine = slot.lock_blocking().machine_connection.to_machine()?;
+            Some((machine_identification_unique.clone(), machine))
+        })
+        .collect();
+    let connected: Vec<_> = machines
+        .iter()
+        .map(|(machine_identification_unique, _)| machine_identification_unique.clone())
+        .collect();
+
+    let (stalled, safe_stop) = {
+        let mut watchdog = app_state.watchdog.write().await;
+        let stalled = watchdog.check(&connected, Instant::now());
+        (stalled, watchdog.config.safe_stop)
+    };
+    for machine_identification_unique in stalled {
+        tracing::warn!(
+            "Act cycle of machine {} stalled",
+            machine_identification_unique
+        );
+        if safe_stop {
+            safe_stop_pending.insert(machine_identification_unique);
+        }
+    }
+
+    safe_stop_pending
+        .retain(|machine_identification_unique| connected.contains(machine_identification_unique));
+    for (machine_identification_unique, machine) in machines {
+        if !safe_stop_pending.contains(&machine_identification_unique) {
+            continue;
+        }
+        // the machine stays locked while its act cycle hangs, try again next check
+        let Some(mut machine) = machine.try_lock() else {
+            continue;
+        };
+        if machine.act_safe_stop() {
+            tracing::info!(
+                "Stalled machine {} reached its safe state",
+                machine_identification_unique
+            );
+            safe_stop_pending.remove(&machine_identification_unique);
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use crate::storage;
+use control_core::machines::identification::MachineIdentificationUnique;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+pub mod init;
+
+/// File inside [`crate::storage::data_dir`] configuring the watchdog
+///
+/// The watchdog runs with the default configuration if the file does not exist.
+pub const WATCHDOG_FILE: &str = "watchdog.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WatchdogConfig {
+    /// Time without an act cycle after which a machine counts as stalled
+    #[serde(default = "default_deadline_ms")]
+    pub deadline_ms: u64,
+    /// Bring stalled machines into their safe state as soon as they can be locked again
+    #[serde(default)]
+    pub safe_stop: bool,
+}
+
+const fn default_deadline_ms() -> u64 {
+    500
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            deadline_ms: default_deadline_ms(),
+            safe_stop: false,
+        }
+    }
+}
+
+impl WatchdogConfig {
+    /// Reads [`WATCHDOG_FILE`], the defaults if it does not exist
+    pub fn read() -> Result<Self, anyhow::Error> {
+        let path = storage::data_dir().join(WATCHDOG_FILE);
+        Ok(storage::read_json::<Self>(&path)?.unwrap_or_default())
+    }
+
+    /// Reads [`WATCHDOG_FILE`], falls back to the defaults so the server still starts
+    pub fn load() -> Self {
+        Self::read().unwrap_or_else(|e| {
+            tracing::error!("Failed to read watchdog config, using defaults: {:?}", e);
+            Self::default()
+        })
+    }
+}
+
+/// Act cycle timing of one machine
+#[derive(Debug, Clone)]
+pub struct ActStatistics {
+    /// Last time the machine acted, or connected if it did not act yet
+    pub last_act: Instant,
+    pub cycles: u64,
+    pub last_duration: Duration,
+    pub max_duration: Duration,
+    pub total_duration: Duration,
+    pub stalled: bool,
+    /// Number of times the machine stalled
+    pub stalls: u64,
+}
+
+impl ActStatistics {
+    const fn new(now: Instant) -> Self {
+        Self {
+            last_act: now,
+            cycles: 0,
+            last_duration: Duration::ZERO,
+            max_duration: Duration::ZERO,
+            total_duration: Duration::ZERO,
+            stalled: false,
+            stalls: 0,
+        }
+    }
+}
+
+/// [`ActStatistics`] as returned by the API
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ActStatisticsReport {
+    pub machine_identification_unique: MachineIdentificationUnique,
+    /// time since the last act cycle in ms
+    pub since_last_act_ms: f64,
+    pub cycles: u64,
+    /// durations of the act cycles in µs
+    pub last_duration_us: f64,
+    pub mean_duration_us: f64,
+    pub max_duration_us: f64,
+    pub stalled: bool,
+    pub stalls: u64,
+}
+
+/// Supervises the act cycles of the connected machines
+///
+/// The control loop records every act cycle, the watchdog thread checks the deadline.
+#[derive(Debug)]
+pub struct Watchdog {
+    pub config: WatchdogConfig,
+    machines: HashMap<MachineIdentificationUnique, ActStatistics>,
+}
+
+impl Watchdog {
+    pub fn new(config: WatchdogConfig) -> Self {
+        Self {
+            config,
+            machines: HashMap::new(),
+        }
+    }
+
+    /// Records an act cycle that started at `now` and took `duration`
+    pub fn record(
+        &mut self,
+        machine: &MachineIdentificationUnique,
+        now: Instant,
+        duration: Duration,
+    ) {
+        let statistics = self
+            .machines
+            .entry(machine.clone())
+            .or_insert_with(|| ActStatistics::new(now));
+        statistics.last_act = now + duration;
+        statistics.cycles += 1;
+        statistics.last_duration = duration;
+        statistics.max_duration = statistics.max_duration.max(duration);
+        statistics.total_duration += duration;
+    }
+
+    /// Updates the stall state of the connected machines, returns the machines that stalled
+    /// since the last check
+    ///
+    /// Machines that are no longer connected are forgotten.
+    pub fn check(
+        &mut self,
+        connected: &[MachineIdentificationUnique],
+        now: Instant,
+    ) -> Vec<MachineIdentificationUnique> {
+        self.machines
+            .retain(|machine, _| connected.contains(machine));
+
+        let deadline = Duration::from_millis(self.config.deadline_ms);
+        let mut stalled = vec![];
+        for machine in connected {
+            let statistics = self
+                .machines
+                .entry(machine.clone())
+                .or_insert_with(|| ActStatistics::new(now));
+            let is_stalled = now.saturating_duration_since(statistics.last_act) > deadline;
+            if is_stalled && !statistics.stalled {
+                statistics.stalls += 1;
+                stalled.push(machine.clone());
+            }
+            statistics.stalled = is_stalled;
+        }
+        stalled
+    }
+
+    pub fn is_stalled(&self, machine: &MachineIdentificationUnique) -> bool {
+        self.machines
+            .get(machine)
+            .is_some_and(|statistics| statistics.stalled)
+    }
+
+    pub fn report(&self, now: Instant) -> Vec<ActStatisticsReport> {
+        let mut reports: Vec<_> = self
+            .machines
+            .iter()
+            .map(|(machine, statistics)| ActStatisticsReport {
+                machine_identification_unique: machine.clone(),
+                since_last_act_ms: now
+                    .saturating_duration_since(statistics.last_act)
+                    .as_secs_f64()
+                    * 1000.0,
+                cycles: statistics.cycles,
+                last_duration_us: statistics.last_duration.as_secs_f64() * 1e6,
+                mean_duration_us: if statistics.cycles > 0 {
+                    statistics.total_duration.as_secs_f64() * 1e6 / statistics.cycles as f64
+                } else {
+                    0.0
+                },
+                max_duration_us: statistics.max_duration.as_secs_f64() * 1e6,
+                stalled: statistics.stalled,
+                stalls: statistics.stalls,
+            })
+            .collect();
+        reports.sort_by_key(|report| report.machine_identification_unique.to_string());
+        reports
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use control_core::machines::identification::MachineIdentification;
+
+    fn machine(serial: u16) -> MachineIdentificationUnique {
+        MachineIdentificationUnique {
+            machine_identification: MachineIdentification {
+                vendor: 1,
+                machine: 2,
+            },
+            serial,
+        }
+    }
+
+    #[test]
+    fn test_stall_detection() {
+        let mut watchdog = Watchdog::new(WatchdogConfig::default());
+        let start = Instant::now();
+        let connected = [machine(1), machine(2)];
+
+        // connecting starts the deadline
+        assert!(watchdog.check(&connected, start).is_empty());
+
+        watchdog.record(
+            &machine(1),
+            start + Duration::from_millis(400),
+            Duration::ZERO,
+        );
+        let stalled = watchdog.check(&connected, start + Duration::from_millis(600));
+        assert_eq!(stalled, vec![machine(2)]);
+        assert!(watchdog.is_stalled(&machine(2)));
+        assert!(!watchdog.is_stalled(&machine(1)));
+
+        // a stall is reported once
+        assert!(
+            watchdog
+                .check(&connected, start + Duration::from_millis(700))
+                .is_empty()
+        );
+
+        // acting again recovers
+        watchdog.record(
+            &machine(2),
+            start + Duration::from_millis(800),
+            Duration::ZERO,
+        );
+        watchdog.check(&connected, start + Duration::from_millis(800));
+        assert!(!watchdog.is_stalled(&machine(2)));
+    }
+
+    #[test]
+    fn test_report() {
+        let mut watchdog = Watchdog::new(WatchdogConfig::default());
+        let start = Instant::now();
+        watchdog.record(&machine(1), start, Duration::from_micros(100));
+        watchdog.record(&machine(1), start, Duration::from_micros(300));
+
+        let report = watchdog.report(start + Duration::from_micros(300));
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].cycles, 2);
+        assert!((report[0].mean_duration_us - 200.0).abs() < 1e-6);
+        assert!((report[0].max_duration_us - 300.0).abs() < 1e-6);
+
+        // disconnected machines are forgotten
+        watchdog.check(&[], start);
+        assert!(watchdog.report(start).is_empty());
+    }
+}